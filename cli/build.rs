@@ -0,0 +1,52 @@
+//! Embeds the bundled daemon (`bun run build`, which writes ../dist/daemon.js)
+//! into the binary. AGENT_BROWSER_DAEMON_JS points at a bundle elsewhere, e.g.
+//! in a container that only mounts cli/. Without a bundle the binary is built
+//! with an empty one and looks for the daemon on disk.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=AGENT_BROWSER_DAEMON_JS");
+    let bundle = match env::var_os("AGENT_BROWSER_DAEMON_JS") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("../dist/daemon.js"),
+    };
+
+    let source = match fs::read(&bundle) {
+        Ok(source) => {
+            println!("cargo:rerun-if-changed={}", bundle.display());
+            source
+        }
+        Err(_) => {
+            // Release builds check again every time so a bundle built later is
+            // never missed; development builds don't pay for it
+            if env::var("PROFILE").as_deref() == Ok("release") {
+                println!("cargo:rerun-if-changed={}", bundle.display());
+                println!(
+                    "cargo:warning={} not found, building without an embedded daemon (run `bun run build` first)",
+                    bundle.display()
+                );
+            } else {
+                println!("cargo:rerun-if-changed=build.rs");
+            }
+            Vec::new()
+        }
+    };
+
+    // Names the extracted copy, so a different bundle never reuses a stale one
+    println!(
+        "cargo:rustc-env=AGENT_BROWSER_DAEMON_HASH={:016x}",
+        fnv1a(&source)
+    );
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("daemon.js");
+    fs::write(out, source).expect("failed to write the daemon bundle to OUT_DIR");
+}
+
+/// 64-bit FNV-1a, enough to tell bundles apart
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
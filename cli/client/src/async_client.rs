@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::connection::{auth_token, response_timeout, token_for};
+use crate::protocol::{
+    data_as, request_id, Command, EvaluateData, HelloData, NavigateData, ScreenshotData, TextData,
+    TitleData, UrlData, PROTOCOL_VERSION,
+};
+use crate::{into_data, request, snapshot_command, Error, Response, Snapshot, SnapshotOptions};
+
+type Reply = oneshot::Sender<Result<Response, Error>>;
+
+/// Requests waiting for their response, by id. `None` once the connection
+/// has closed, so later requests fail instead of waiting forever.
+type Pending = Arc<Mutex<Option<HashMap<String, Reply>>>>;
+
+/// An async connection to one session's daemon.
+///
+/// Methods take `&self`, so one client can be shared (e.g. in an `Arc`) and
+/// used from many tasks at once. Requests are written as they come and each
+/// response is matched to its request by id, so a slow command does not hold
+/// up the others.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), agent_browser_client::Error> {
+/// use agent_browser_client::AsyncClient;
+/// use std::sync::Arc;
+///
+/// let client = Arc::new(AsyncClient::connect("default").await?);
+/// client.navigate("https://example.com").await?;
+///
+/// let shared = Arc::clone(&client);
+/// let title = tokio::spawn(async move { shared.title().await });
+/// let url = client.url().await?;
+/// println!("{} {}", title.await.unwrap()?, url);
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncClient {
+    lines: mpsc::UnboundedSender<String>,
+    pending: Pending,
+    token: Option<String>,
+    tasks: [JoinHandle<()>; 2],
+}
+
+impl AsyncClient {
+    /// Connect to the daemon of a running session
+    pub async fn connect(session: &str) -> Result<Self, Error> {
+        let mut client = Self::connect_stream(session).await?;
+        client.set_token(token_for(session));
+        Ok(client)
+    }
+
+    async fn connect_stream(session: &str) -> Result<Self, Error> {
+        if let Some(address) = crate::connection::remote_address()? {
+            let stream = tokio::net::TcpStream::connect(address)
+                .await
+                .map_err(Error::Connect)?;
+            return Ok(Self::from_stream(stream));
+        }
+        #[cfg(unix)]
+        {
+            let socket_path = crate::connection::get_socket_path(session);
+            let stream = tokio::net::UnixStream::connect(&socket_path)
+                .await
+                .map_err(Error::Connect)?;
+            Ok(Self::from_stream(stream))
+        }
+        #[cfg(windows)]
+        {
+            let port = crate::connection::get_port_for_session(session)?;
+            let stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .map_err(Error::Connect)?;
+            Ok(Self::from_stream(stream))
+        }
+    }
+
+    /// Wrap an already connected stream. Must be called within a Tokio
+    /// runtime: the reads and writes run on tasks of their own.
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (read_half, mut write_half) = tokio::io::split(stream);
+        let pending: Pending = Arc::new(Mutex::new(Some(HashMap::new())));
+
+        // Writes go through a channel so that dropping a request's future
+        // can never leave half a line on the stream
+        let (lines, mut queue) = mpsc::unbounded_channel::<String>();
+        let writer_pending = Arc::clone(&pending);
+        let writer = tokio::spawn(async move {
+            while let Some(line) = queue.recv().await {
+                if let Err(e) = write_half.write_all(line.as_bytes()).await {
+                    fail_all(&writer_pending, |msg| {
+                        Error::Send(io::Error::new(e.kind(), msg))
+                    });
+                    return;
+                }
+            }
+        });
+
+        let reader_pending = Arc::clone(&pending);
+        let reader = tokio::spawn(async move {
+            let mut lines = BufReader::new(read_half).lines();
+            let closed = loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break io::Error::new(io::ErrorKind::UnexpectedEof, "EOF"),
+                    Err(e) => break e,
+                };
+                // Lines without an id (event pushes after subscribe) are not
+                // answers to anything
+                let Ok(value) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                let Some(id) = value.get("id").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let reply = match reader_pending.lock().unwrap().as_mut() {
+                    Some(waiting) => waiting.remove(id),
+                    None => None,
+                };
+                if let Some(reply) = reply {
+                    let _ =
+                        reply.send(serde_json::from_value(value).map_err(Error::InvalidResponse));
+                }
+            };
+            fail_all(&reader_pending, |msg| {
+                Error::Read(io::Error::new(closed.kind(), msg))
+            });
+        });
+
+        Self {
+            lines,
+            pending,
+            token: auth_token(),
+            tasks: [writer, reader],
+        }
+    }
+
+    /// The token sent with every request. [`connect`](Self::connect) reads
+    /// the session's token file; otherwise it comes from `AGENT_BROWSER_TOKEN`.
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    /// Send a protocol command (it must have an `id`) and wait for its
+    /// response
+    pub async fn send(&self, cmd: &Value) -> Result<Response, Error> {
+        let id = cmd
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or(Error::MissingField("id"))?
+            .to_string();
+        let mut request = cmd.clone();
+        if let (Some(token), Some(obj)) = (&self.token, request.as_object_mut()) {
+            obj.entry("token").or_insert(token.as_str().into());
+        }
+        let mut line = serde_json::to_string(&request).map_err(|e| Error::Send(e.into()))?;
+        line.push('\n');
+
+        let (reply, response) = oneshot::channel();
+        match self.pending.lock().unwrap().as_mut() {
+            Some(waiting) => waiting.insert(id.clone(), reply),
+            None => return Err(closed_error()),
+        };
+        if self.lines.send(line).is_err() {
+            self.forget(&id);
+            return Err(closed_error());
+        }
+
+        match tokio::time::timeout(response_timeout(cmd), response).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(closed_error()),
+            Err(_) => {
+                self.forget(&id);
+                Err(Error::Read(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for the daemon",
+                )))
+            }
+        }
+    }
+
+    fn forget(&self, id: &str) {
+        if let Some(waiting) = self.pending.lock().unwrap().as_mut() {
+            waiting.remove(id);
+        }
+    }
+
+    /// Send a typed command and return its `data`
+    pub async fn run(&self, command: Command) -> Result<Value, Error> {
+        let cmd = command.into_request(request_id("a"));
+        into_data(self.send(&cmd).await?)
+    }
+
+    /// Send any protocol command and return its `data`. `params` must be an
+    /// object (or null); it is merged into the request next to `action`.
+    pub async fn command(&self, action: &str, params: Value) -> Result<Value, Error> {
+        let cmd = request(request_id("a"), action, params);
+        into_data(self.send(&cmd).await?)
+    }
+
+    /// Exchange protocol versions with the daemon, see [`Client::hello`]
+    ///
+    /// [`Client::hello`]: crate::Client::hello
+    pub async fn hello(&self) -> Result<HelloData, Error> {
+        let protocol_version = PROTOCOL_VERSION;
+        data_as(self.run(Command::Hello { protocol_version }).await?)
+    }
+
+    pub async fn navigate(&self, url: &str) -> Result<NavigateData, Error> {
+        data_as(self.run(Command::navigate(url)).await?)
+    }
+
+    pub async fn back(&self) -> Result<(), Error> {
+        self.run(Command::Back).await.map(drop)
+    }
+
+    pub async fn forward(&self) -> Result<(), Error> {
+        self.run(Command::Forward).await.map(drop)
+    }
+
+    pub async fn reload(&self) -> Result<(), Error> {
+        self.run(Command::Reload).await.map(drop)
+    }
+
+    pub async fn click(&self, selector: &str) -> Result<(), Error> {
+        self.run(Command::click(selector)).await.map(drop)
+    }
+
+    /// Clear an input and fill it with `value`
+    pub async fn fill(&self, selector: &str, value: &str) -> Result<(), Error> {
+        self.run(Command::fill(selector, value)).await.map(drop)
+    }
+
+    /// Type `text` key by key into an element
+    pub async fn type_text(&self, selector: &str, text: &str) -> Result<(), Error> {
+        self.run(Command::type_text(selector, text)).await.map(drop)
+    }
+
+    /// Press a key or chord, e.g. `Enter` or `Control+a`
+    pub async fn press(&self, key: &str) -> Result<(), Error> {
+        self.run(Command::press(key)).await.map(drop)
+    }
+
+    pub async fn hover(&self, selector: &str) -> Result<(), Error> {
+        let selector = selector.to_string();
+        self.run(Command::Hover { selector }).await.map(drop)
+    }
+
+    pub async fn snapshot(&self, options: &SnapshotOptions) -> Result<Snapshot, Error> {
+        Snapshot::from_data(self.run(snapshot_command(options)).await?)
+    }
+
+    /// Evaluate JavaScript in the page and return its result
+    pub async fn evaluate(&self, script: &str) -> Result<Value, Error> {
+        let data: EvaluateData = data_as(self.run(Command::evaluate(script)).await?)?;
+        Ok(data.result)
+    }
+
+    pub async fn url(&self) -> Result<String, Error> {
+        data_as(self.run(Command::Url).await?).map(|d: UrlData| d.url)
+    }
+
+    pub async fn title(&self) -> Result<String, Error> {
+        data_as(self.run(Command::Title).await?).map(|d: TitleData| d.title)
+    }
+
+    /// Text content of an element
+    pub async fn text(&self, selector: &str) -> Result<String, Error> {
+        let selector = selector.to_string();
+        let data: TextData = data_as(self.run(Command::Gettext { selector }).await?)?;
+        Ok(data.text)
+    }
+
+    /// Save a screenshot to `path` and return the path the daemon wrote
+    pub async fn screenshot(&self, path: &str, full_page: bool) -> Result<String, Error> {
+        let data: ScreenshotData = data_as(self.run(Command::screenshot(path, full_page)).await?)?;
+        data.path.ok_or(Error::MissingField("path"))
+    }
+
+    /// Close the browser. The daemon exits afterwards.
+    pub async fn close(self) -> Result<(), Error> {
+        self.run(Command::Close).await.map(drop)
+    }
+}
+
+impl Drop for AsyncClient {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+fn closed_error() -> Error {
+    Error::Send(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "connection to the daemon is closed",
+    ))
+}
+
+/// Fail every waiting request and refuse new ones
+fn fail_all(pending: &Pending, error: impl Fn(String) -> Error) {
+    let waiting = pending.lock().unwrap().take().unwrap_or_default();
+    for reply in waiting.into_values() {
+        let _ = reply.send(Err(error("connection to the daemon closed".to_string())));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::net::UnixStream;
+
+    fn run<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_responses_matched_by_id() {
+        run(async {
+            let (ours, theirs) = UnixStream::pair().unwrap();
+            let client = Arc::new(AsyncClient::from_stream(ours));
+
+            // Answer the two requests in reverse order, with an event between
+            let daemon = tokio::spawn(async move {
+                let (read_half, mut write_half) = tokio::io::split(theirs);
+                let mut lines = BufReader::new(read_half).lines();
+                let mut requests = Vec::new();
+                for _ in 0..2 {
+                    let line = lines.next_line().await.unwrap().unwrap();
+                    requests.push(serde_json::from_str::<Value>(&line).unwrap());
+                }
+                let mut out = String::new();
+                for req in requests.iter().rev() {
+                    let data = match req["action"].as_str() {
+                        Some("url") => json!({ "url": "https://example.com/" }),
+                        _ => json!({ "title": "Example" }),
+                    };
+                    let resp = json!({ "id": req["id"], "success": true, "data": data });
+                    out.push_str(&format!("{}\n", resp));
+                    out.push_str("{\"event\":\"console\",\"data\":{}}\n");
+                }
+                write_half.write_all(out.as_bytes()).await.unwrap();
+                requests
+            });
+
+            let shared = Arc::clone(&client);
+            let url = tokio::spawn(async move { shared.url().await });
+            assert_eq!(client.title().await.unwrap(), "Example");
+            assert_eq!(url.await.unwrap().unwrap(), "https://example.com/");
+
+            let requests = daemon.await.unwrap();
+            assert_ne!(requests[0]["id"], requests[1]["id"]);
+        });
+    }
+
+    #[test]
+    fn test_pending_requests_fail_when_daemon_closes() {
+        run(async {
+            let (ours, theirs) = UnixStream::pair().unwrap();
+            let client = AsyncClient::from_stream(ours);
+
+            let daemon = tokio::spawn(async move {
+                let mut lines = BufReader::new(theirs).lines();
+                lines.next_line().await.unwrap();
+                // Dropping the stream closes the connection unanswered
+            });
+
+            let err = client.navigate("https://example.com").await.unwrap_err();
+            assert!(matches!(err, Error::Read(ref e) if e.kind() == io::ErrorKind::UnexpectedEof));
+            daemon.await.unwrap();
+
+            assert!(matches!(client.back().await, Err(Error::Send(_))));
+        });
+    }
+
+    #[test]
+    fn test_command_error() {
+        run(async {
+            let (ours, theirs) = UnixStream::pair().unwrap();
+            let client = AsyncClient::from_stream(ours);
+
+            tokio::spawn(async move {
+                let (read_half, mut write_half) = tokio::io::split(theirs);
+                let mut lines = BufReader::new(read_half).lines();
+                let line = lines.next_line().await.unwrap().unwrap();
+                let req: Value = serde_json::from_str(&line).unwrap();
+                let resp =
+                    json!({ "id": req["id"], "success": false, "error": "Element not found" });
+                write_half
+                    .write_all(format!("{}\n", resp).as_bytes())
+                    .await
+                    .unwrap();
+                lines.next_line().await.ok();
+            });
+
+            match client.click("#missing").await {
+                Err(Error::Command(msg)) => assert_eq!(msg, "Element not found"),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        });
+    }
+}
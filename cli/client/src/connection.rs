@@ -0,0 +1,1333 @@
+use flate2::read::GzDecoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use crate::pace;
+use crate::protocol::Compression;
+use crate::Error;
+
+/// The daemon's answer to one request
+#[derive(Deserialize, Serialize, Default, Debug, JsonSchema)]
+pub struct Response {
+    /// The request's id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub success: bool,
+    pub data: Option<Value>,
+    pub error: Option<String>,
+    /// What kind of failure `error` is, when the daemon could tell
+    #[serde(default, rename = "errorCode", skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    /// What the failed command was working on
+    #[serde(
+        default,
+        rename = "errorContext",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub error_context: Option<ErrorContext>,
+    /// Where the command's time went, when the request asked for `timings`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+}
+
+/// Failure classes, so callers can branch on a failure without matching its
+/// message. Daemons that predate them, and failures they can't classify,
+/// leave `errorCode` out.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request was malformed or named an unknown action
+    InvalidCommand,
+    /// The daemon requires a token and the request's was missing or wrong
+    Unauthorized,
+    /// No element matches the selector
+    NotFound,
+    /// The selector matches more than one element
+    Ambiguous,
+    /// The element exists but is hidden
+    NotVisible,
+    /// Another element, such as a modal or overlay, covers the element
+    Blocked,
+    /// The command's deadline or one of the browser's timeouts ran out
+    Timeout,
+    /// The page failed to load: DNS, connection, TLS or a crashed page
+    Navigation,
+    /// The command was cancelled before it finished
+    Cancelled,
+    /// No daemon answered. Set by clients, never sent by the daemon.
+    DaemonUnavailable,
+    /// A code this client doesn't know yet
+    #[serde(other)]
+    Unknown,
+}
+
+/// The selector or URL a failed command was working on, the daemon's stack
+/// at the failure and, for a timeout, where the page was
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug, JsonSchema)]
+pub struct ErrorContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Stack frames inside the daemon, one per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<String>,
+    /// Where the page was when the command timed out
+    #[serde(default, rename = "pageUrl", skip_serializing_if = "Option::is_none")]
+    pub page_url: Option<String>,
+    #[serde(default, rename = "pageTitle", skip_serializing_if = "Option::is_none")]
+    pub page_title: Option<String>,
+}
+
+/// Time spent on one command, in milliseconds. The daemon reports
+/// `daemon`; clients fill in the phases they measure themselves.
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq, Debug, JsonSchema)]
+pub struct Timings {
+    /// Waiting for a daemon to start, or checking that one is running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn: Option<f64>,
+    /// Connecting to the daemon's socket
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect: Option<f64>,
+    /// Running the command in the daemon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daemon: Option<f64>,
+    /// Sending the request and reading the response, less the daemon's share
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<f64>,
+    /// The whole command as the client saw it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+}
+
+impl Response {
+    /// Mask every occurrence of `secret` in the error and in string data.
+    pub fn redact(&mut self, secret: &str) {
+        fn redact_value(value: &mut Value, secret: &str) {
+            match value {
+                Value::String(s) if s.contains(secret) => *s = s.replace(secret, "********"),
+                Value::Array(items) => items.iter_mut().for_each(|v| redact_value(v, secret)),
+                Value::Object(map) => map.values_mut().for_each(|v| redact_value(v, secret)),
+                _ => {}
+            }
+        }
+        if let Some(error) = self.error.as_mut() {
+            *error = error.replace(secret, "********");
+        }
+        if let Some(data) = self.data.as_mut() {
+            redact_value(data, secret);
+        }
+        if let Some(context) = self.error_context.as_mut() {
+            for field in [&mut context.selector, &mut context.url, &mut context.stack] {
+                if let Some(value) = field.as_mut() {
+                    *value = value.replace(secret, "********");
+                }
+            }
+        }
+    }
+}
+
+/// A stream to the daemon: a Unix socket, or a localhost TCP port on Windows
+pub enum Connection {
+    #[cfg(unix)]
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(s) => s.read(buf),
+            Connection::Tcp(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(s) => s.write(buf),
+            Connection::Tcp(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(s) => s.flush(),
+            Connection::Tcp(s) => s.flush(),
+        }
+    }
+}
+
+impl Connection {
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(s) => s.set_read_timeout(dur),
+            Connection::Tcp(s) => s.set_read_timeout(dur),
+        }
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(s) => s.set_write_timeout(dur),
+            Connection::Tcp(s) => s.set_write_timeout(dur),
+        }
+    }
+
+    /// Another handle on the same stream
+    pub fn try_clone(&self) -> std::io::Result<Connection> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(s) => s.try_clone().map(Connection::Unix),
+            Connection::Tcp(s) => s.try_clone().map(Connection::Tcp),
+        }
+    }
+}
+
+/// Get the base directory for socket/pid files.
+/// Priority: AGENT_BROWSER_SOCKET_DIR > AGENT_BROWSER_RUNTIME_DIR > XDG_RUNTIME_DIR >
+/// ~/.agent-browser > tmpdir
+pub fn get_socket_dir() -> PathBuf {
+    // Explicit override for sockets only (ignore empty string)
+    match env::var("AGENT_BROWSER_SOCKET_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => get_app_dir(),
+    }
+}
+
+/// Get the runtime directory, which holds everything the daemon writes
+/// unless AGENT_BROWSER_SOCKET_DIR moves the sockets.
+/// Priority: AGENT_BROWSER_RUNTIME_DIR > XDG_RUNTIME_DIR > ~/.agent-browser > tmpdir
+pub fn get_app_dir() -> PathBuf {
+    // 1. Explicit runtime directory (ignore empty string)
+    if let Ok(dir) = env::var("AGENT_BROWSER_RUNTIME_DIR") {
+        if !dir.is_empty() {
+            return PathBuf::from(dir);
+        }
+    }
+
+    // 2. XDG_RUNTIME_DIR (Linux standard, ignore empty string)
+    if let Ok(runtime_dir) = env::var("XDG_RUNTIME_DIR") {
+        if !runtime_dir.is_empty() {
+            return PathBuf::from(runtime_dir).join("agent-browser");
+        }
+    }
+
+    // 3. Home directory fallback (like Docker Desktop's ~/.docker/run/)
+    if let Some(home) = dirs::home_dir() {
+        return home.join(".agent-browser");
+    }
+
+    // 4. Last resort: temp dir
+    env::temp_dir().join("agent-browser")
+}
+
+/// The session's socket: AGENT_BROWSER_SOCKET (`--socket`) when set,
+/// otherwise `<session>.sock` in the socket directory
+#[cfg(unix)]
+pub fn get_socket_path(session: &str) -> PathBuf {
+    match env::var("AGENT_BROWSER_SOCKET") {
+        Ok(path) if !path.is_empty() => PathBuf::from(path),
+        _ => get_socket_dir().join(format!("{}.sock", session)),
+    }
+}
+
+#[cfg(windows)]
+pub fn get_port_path(session: &str) -> PathBuf {
+    get_socket_dir().join(format!("{}.port", session))
+}
+
+#[cfg(windows)]
+pub fn get_port_for_session(session: &str) -> Result<u16, Error> {
+    let port_file = get_port_path(session);
+    let port_str = fs::read_to_string(&port_file)
+        .map_err(|e| Error::PortFile(format!("Failed to read port file: {}", e)))?;
+
+    port_str
+        .trim()
+        .parse()
+        .map_err(|e| Error::PortFile(format!("Invalid port in file: {}", e)))
+}
+
+/// Parse a `--remote` address. `tcp://host:port` is the only transport; for
+/// a daemon behind SSH, forward its port and point at the local end.
+pub fn parse_remote(url: &str) -> Result<String, Error> {
+    let (scheme, address) = url.split_once("://").unwrap_or(("", url));
+    match scheme {
+        "tcp" => {}
+        "ssh" => {
+            return Err(Error::Remote(format!(
+                "ssh:// is not supported directly. Forward the daemon's port instead \
+                 (ssh -L 9400:localhost:9400 {}) and use --remote tcp://localhost:9400",
+                address.trim_end_matches('/')
+            )))
+        }
+        _ => {
+            return Err(Error::Remote(format!(
+                "Invalid remote '{}': expected tcp://host:port",
+                url
+            )))
+        }
+    }
+    let address = address.trim_end_matches('/');
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+            Ok(address.to_string())
+        }
+        _ => Err(Error::Remote(format!(
+            "Invalid remote '{}': expected tcp://host:port",
+            url
+        ))),
+    }
+}
+
+/// The `host:port` of a remote daemon from `AGENT_BROWSER_REMOTE`, if set.
+/// A remote daemon is reached over TCP in place of the session's socket.
+pub fn remote_address() -> Result<Option<String>, Error> {
+    match env::var("AGENT_BROWSER_REMOTE") {
+        Ok(url) if !url.is_empty() => parse_remote(&url).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// The token a daemon started with `AGENT_BROWSER_TOKEN` expects on every
+/// request
+pub(crate) fn auth_token() -> Option<String> {
+    env::var("AGENT_BROWSER_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+/// The file a local daemon writes its secret to, next to its pid file.
+/// Only the user running the daemon can read it.
+pub fn get_token_path(session: &str) -> PathBuf {
+    get_socket_dir().join(format!("{}.token", session))
+}
+
+/// The token to send to `session`'s daemon: the one in its token file for a
+/// local daemon, or `AGENT_BROWSER_TOKEN`
+pub(crate) fn token_for(session: &str) -> Option<String> {
+    if !matches!(remote_address(), Ok(Some(_))) {
+        if let Ok(token) = fs::read_to_string(get_token_path(session)) {
+            let token = token.trim();
+            if !token.is_empty() {
+                return Some(token.to_string());
+            }
+        }
+    }
+    auth_token()
+}
+
+fn connect(session: &str) -> Result<Connection, Error> {
+    if let Some(address) = remote_address()? {
+        return TcpStream::connect(address)
+            .map(Connection::Tcp)
+            .map_err(Error::Connect);
+    }
+    #[cfg(unix)]
+    {
+        let socket_path = get_socket_path(session);
+        UnixStream::connect(&socket_path)
+            .map(Connection::Unix)
+            .map_err(Error::Connect)
+    }
+    #[cfg(windows)]
+    {
+        let port = get_port_for_session(session)?;
+        TcpStream::connect(format!("127.0.0.1:{}", port))
+            .map(Connection::Tcp)
+            .map_err(Error::Connect)
+    }
+}
+
+/// Send one command on a fresh connection, retrying transient failures
+/// (daemon busy, restarting or still starting).
+pub fn send_command(cmd: Value, session: &str) -> Result<Response, Error> {
+    send_command_with_progress(cmd, session, &mut |_, _| {})
+}
+
+/// [`send_command`], reporting how much of a large response has arrived as
+/// `progress(received, total)` in bytes
+pub fn send_command_with_progress(
+    cmd: Value,
+    session: &str,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<Response, Error> {
+    send_command_timed(cmd, session, progress).map(|(response, _)| response)
+}
+
+/// [`send_command_with_progress`], also returning how long connecting to the
+/// daemon took on the attempt that succeeded
+pub fn send_command_timed(
+    cmd: Value,
+    session: &str,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<(Response, Duration), Error> {
+    // Retry logic for transient errors (EAGAIN/EWOULDBLOCK/connection issues)
+    const MAX_RETRIES: u32 = 5;
+    const RETRY_DELAY_MS: u64 = 200;
+
+    let mut last_error = None;
+
+    for attempt in 0..MAX_RETRIES {
+        if attempt > 0 {
+            thread::sleep(Duration::from_millis(RETRY_DELAY_MS * (attempt as u64)));
+        }
+
+        match send_command_once(&cmd, session, progress) {
+            Ok(response) => return Ok(response),
+            Err(e) => {
+                if is_transient_error(&e.to_string()) {
+                    last_error = Some(e);
+                    continue;
+                }
+                // Non-transient error, fail immediately
+                return Err(e);
+            }
+        }
+    }
+
+    Err(Error::Unresponsive {
+        last: Box::new(last_error.expect("every attempt failed")),
+        retries: MAX_RETRIES,
+    })
+}
+
+/// Check if an error is transient and worth retrying.
+/// Transient errors include:
+/// - EAGAIN/EWOULDBLOCK (os error 35 on macOS, 11 on Linux)
+/// - EOF errors (daemon closed connection before responding)
+/// - Connection reset/broken pipe (daemon crashed or restarting)
+/// - Connection refused/socket not found (daemon still starting)
+fn is_transient_error(error: &str) -> bool {
+    error.contains("os error 35") // EAGAIN on macOS
+        || error.contains("os error 11") // EAGAIN on Linux
+        || error.contains("WouldBlock")
+        || error.contains("Resource temporarily unavailable")
+        || error.contains("EOF")
+        || error.contains("line 1 column 0") // Empty JSON response
+        || error.contains("Connection reset")
+        || error.contains("Broken pipe")
+        || error.contains("os error 54") // Connection reset by peer (macOS)
+        || error.contains("os error 104") // Connection reset by peer (Linux)
+        || error.contains("os error 2") // No such file or directory (socket gone)
+        || error.contains("os error 61") // Connection refused (macOS)
+        || error.contains("os error 111") // Connection refused (Linux)
+}
+
+fn send_command_once(
+    cmd: &Value,
+    session: &str,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<(Response, Duration), Error> {
+    let started = Instant::now();
+    let mut conn = DaemonConnection::open(session)?;
+    let connect = started.elapsed();
+    Ok((conn.send_with_progress(cmd, progress)?, connect))
+}
+
+/// How long to wait for the daemon to answer a command
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Read timeout for `cmd`. A request with a `deadline` (Unix time in ms, from
+/// `--timeout`) waits until shortly after it, however far off. Commands with
+/// their own `timeout` (wait, expect, download wait) get a little longer than
+/// that. Either way the daemon's timeout error arrives before the client
+/// gives up.
+pub(crate) fn response_timeout(cmd: &Value) -> Duration {
+    if let Some(deadline) = cmd.get("deadline").and_then(|v| v.as_u64()) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let remaining = Duration::from_millis(deadline).saturating_sub(now);
+        return remaining + Duration::from_secs(5);
+    }
+    cmd.get("timeout")
+        .and_then(|v| v.as_u64())
+        .map(|ms| Duration::from_millis(ms) + Duration::from_secs(5))
+        .map_or(RESPONSE_TIMEOUT, |d| d.max(RESPONSE_TIMEOUT))
+}
+
+/// How much of a framed response to read at a time
+const FRAME_CHUNK: usize = 64 * 1024;
+
+fn compression_from_env() -> Option<Compression> {
+    match env::var("AGENT_BROWSER_COMPRESSION").ok()?.as_str() {
+        "gzip" => Some(Compression::Gzip),
+        _ => None,
+    }
+}
+
+/// A connection to a session's daemon that stays open across commands.
+/// The daemon reads newline-delimited requests in a loop and runs each as it
+/// arrives, so several commands can be sent over the same stream, and be in
+/// flight at once: [`submit`](Self::submit) sends one without waiting, and
+/// [`receive`](Self::receive) waits for the response with a given id.
+pub struct DaemonConnection {
+    reader: BufReader<Connection>,
+    compression: Option<Compression>,
+    token: Option<String>,
+    in_flight: Arc<Mutex<InFlight>>,
+    /// The session connected to by [`open`](Self::open), whose rate limit
+    /// commands keep to
+    session: Option<String>,
+}
+
+/// The requests on one stream still waiting for their response, shared by
+/// every handle on it
+#[derive(Default)]
+struct InFlight {
+    /// How long to wait for each request's response, by id
+    waiting: HashMap<String, Duration>,
+    /// Responses read while waiting for another request's
+    answered: HashMap<String, Response>,
+}
+
+impl InFlight {
+    /// The id of the request `resp` answers, or None if no request waiting
+    /// here has its id: one given up on, or another client's. Failures the
+    /// daemon couldn't tie to a request (id `unknown`), and daemons that leave
+    /// the id out, answer the only request waiting.
+    fn answering(&self, resp: &Response) -> Option<String> {
+        match resp.id.as_deref() {
+            Some(id) if self.waiting.contains_key(id) => Some(id.to_string()),
+            None | Some("unknown") if self.waiting.len() == 1 => {
+                self.waiting.keys().next().cloned()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl DaemonConnection {
+    pub fn open(session: &str) -> Result<Self, Error> {
+        let mut conn = Self::new(connect(session)?);
+        conn.set_token(token_for(session));
+        conn.session = Some(session.to_string());
+        Ok(conn)
+    }
+
+    /// Wrap an already connected stream
+    pub fn new(stream: Connection) -> Self {
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT)).ok();
+        stream.set_write_timeout(Some(Duration::from_secs(5))).ok();
+
+        Self {
+            reader: BufReader::new(stream),
+            compression: compression_from_env(),
+            token: auth_token(),
+            in_flight: Arc::default(),
+            session: None,
+        }
+    }
+
+    /// Another handle on the same connection, e.g. to submit requests from
+    /// one thread while another reads the responses with
+    /// [`next_response`](Self::next_response). Only one handle should read.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let stream = self.reader.get_ref().try_clone().map_err(Error::Connect)?;
+        Ok(Self {
+            reader: BufReader::new(stream),
+            compression: self.compression,
+            token: self.token.clone(),
+            in_flight: Arc::clone(&self.in_flight),
+            session: self.session.clone(),
+        })
+    }
+
+    /// Send without keeping to the session's rate limit, for a client's own
+    /// polling rather than the commands a user asked for
+    pub fn set_unpaced(&mut self) {
+        self.session = None;
+    }
+
+    /// The token sent with every request. [`open`](Self::open) reads the
+    /// session's token file; otherwise it comes from `AGENT_BROWSER_TOKEN`.
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    /// Ask the daemon to compress large responses. Off by default, or set by
+    /// `AGENT_BROWSER_COMPRESSION=gzip`; daemons without support ignore it.
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
+
+    pub fn send(&mut self, cmd: &Value) -> Result<Response, Error> {
+        self.send_with_progress(cmd, &mut |_, _| {})
+    }
+
+    /// [`send`](Self::send), calling `progress(received, total)` in bytes as a
+    /// length-framed response arrives
+    pub fn send_with_progress(
+        &mut self,
+        cmd: &Value,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<Response, Error> {
+        self.submit(cmd)?;
+        let id = cmd.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+        self.receive_with_progress(id, progress)
+    }
+
+    /// Send `cmd` without waiting for its response; get that with
+    /// [`receive`](Self::receive) and the request's `id`. Ids must be unique
+    /// among the requests in flight. With a rate limit set for the session,
+    /// this first waits for `cmd`'s turn.
+    pub fn submit(&mut self, cmd: &Value) -> Result<(), Error> {
+        if let Some(session) = &self.session {
+            pace::wait_turn(session, cmd);
+        }
+        // Ask for a length-prefixed response. Daemons that predate framing
+        // ignore the field and answer with a line, which read_frame accepts.
+        let mut request = cmd.clone();
+        if let Some(obj) = request.as_object_mut() {
+            obj.entry("framing").or_insert(json!("length"));
+            if let Some(compression) = self.compression {
+                obj.entry("compression").or_insert(json!(compression));
+            }
+            if let Some(token) = &self.token {
+                obj.entry("token").or_insert(json!(token));
+            }
+        }
+        let mut json_str = serde_json::to_string(&request).map_err(|e| Error::Send(e.into()))?;
+        json_str.push('\n');
+
+        // Registered first: another handle may read the response before the write returns
+        let id = cmd.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+        self.in_flight
+            .lock()
+            .unwrap()
+            .waiting
+            .insert(id.to_string(), response_timeout(cmd));
+        let written = self.reader.get_mut().write_all(json_str.as_bytes());
+        if let Err(e) = written {
+            self.in_flight.lock().unwrap().waiting.remove(id);
+            return Err(Error::Send(e));
+        }
+        Ok(())
+    }
+
+    /// Wait for the response to the request submitted with `id`. Responses
+    /// to other requests that arrive first are kept for their own `receive`.
+    pub fn receive(&mut self, id: &str) -> Result<Response, Error> {
+        self.receive_with_progress(id, &mut |_, _| {})
+    }
+
+    fn receive_with_progress(
+        &mut self,
+        id: &str,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<Response, Error> {
+        loop {
+            let timeout = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                if let Some(resp) = in_flight.answered.remove(id) {
+                    return Ok(resp);
+                }
+                in_flight
+                    .waiting
+                    .get(id)
+                    .copied()
+                    .unwrap_or(RESPONSE_TIMEOUT)
+            };
+            self.reader.get_ref().set_read_timeout(Some(timeout)).ok();
+
+            let Some((answers, resp)) = self.read_answer(progress)? else {
+                // Closed before answering, reported like any unreadable response
+                return serde_json::from_slice(b"").map_err(Error::InvalidResponse);
+            };
+            if answers == id {
+                return Ok(resp);
+            }
+            self.in_flight
+                .lock()
+                .unwrap()
+                .answered
+                .insert(answers, resp);
+        }
+    }
+
+    /// Wait for whichever submitted request is answered next, however long
+    /// that takes. Returns `Ok(None)` once the daemon closes the connection.
+    pub fn next_response(&mut self) -> Result<Option<Response>, Error> {
+        self.reader.get_ref().set_read_timeout(None).ok();
+        Ok(self.read_answer(&mut |_, _| {})?.map(|(_, resp)| resp))
+    }
+
+    /// Read the next response and the id of the request it answers, skipping
+    /// events pushed after `subscribe` and responses to no request waiting.
+    /// `None` once the daemon closes the connection.
+    fn read_answer(
+        &mut self,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<Option<(String, Response)>, Error> {
+        loop {
+            let body = self.read_frame(progress)?;
+            if body.is_empty() {
+                return Ok(None);
+            }
+            let value: Value = serde_json::from_slice(&body).map_err(Error::InvalidResponse)?;
+            if value.get("event").is_some() && value.get("id").is_none() {
+                continue;
+            }
+            let resp: Response = serde_json::from_value(value).map_err(Error::InvalidResponse)?;
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let Some(answers) = in_flight.answering(&resp) else {
+                continue;
+            };
+            in_flight.waiting.remove(&answers);
+            return Ok(Some((answers, resp)));
+        }
+    }
+
+    /// Read one response: a `#<length>[ <encoding>]` header line and that
+    /// many bytes of JSON, or a plain JSON line
+    fn read_frame(&mut self, progress: &mut dyn FnMut(u64, u64)) -> Result<Vec<u8>, Error> {
+        let mut header = String::new();
+        self.reader.read_line(&mut header).map_err(Error::Read)?;
+        let Some(fields) = header.strip_prefix('#') else {
+            return Ok(header.into_bytes());
+        };
+        let invalid = || {
+            Error::Read(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid frame header: {}", header.trim_end()),
+            ))
+        };
+        let mut fields = fields.split_whitespace();
+        let total: u64 = fields
+            .next()
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(invalid)?;
+        let gzip = match fields.next() {
+            None => false,
+            Some("gzip") => true,
+            Some(_) => return Err(invalid()),
+        };
+
+        let mut body = Vec::with_capacity(total as usize);
+        let mut chunk = vec![0; FRAME_CHUNK];
+        while (body.len() as u64) < total {
+            let want = (total - body.len() as u64).min(FRAME_CHUNK as u64) as usize;
+            let n = self.reader.read(&mut chunk[..want]).map_err(Error::Read)?;
+            if n == 0 {
+                return Err(Error::Read(io::ErrorKind::UnexpectedEof.into()));
+            }
+            body.extend_from_slice(&chunk[..n]);
+            progress(body.len() as u64, total);
+        }
+
+        if !gzip {
+            return Ok(body);
+        }
+        let mut json = Vec::new();
+        GzDecoder::new(body.as_slice())
+            .read_to_end(&mut json)
+            .map_err(Error::Read)?;
+        Ok(json)
+    }
+
+    /// Block until the daemon writes another line (used after `subscribe`,
+    /// when the daemon pushes events without a request). Returns `Ok(None)`
+    /// once the daemon closes the connection.
+    pub fn next_event(&mut self) -> Result<Option<Value>, Error> {
+        self.reader.get_ref().set_read_timeout(None).ok();
+
+        let mut line = String::new();
+        let n = self.reader.read_line(&mut line).map_err(Error::Read)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        serde_json::from_str(&line)
+            .map(Some)
+            .map_err(Error::InvalidResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::{Mutex, MutexGuard};
+
+    // Mutex to prevent parallel tests from interfering with env vars
+    static ENV_MUTEX: Mutex<()> = Mutex::new(());
+
+    /// RAII guard that locks env mutex and restores env vars on drop
+    struct EnvGuard<'a> {
+        _lock: MutexGuard<'a, ()>,
+        vars: Vec<(String, Option<String>)>,
+    }
+
+    impl<'a> EnvGuard<'a> {
+        fn new(var_names: &[&str]) -> Self {
+            let lock = ENV_MUTEX.lock().unwrap();
+            let vars = var_names
+                .iter()
+                .map(|&name| (name.to_string(), env::var(name).ok()))
+                .collect();
+            Self { _lock: lock, vars }
+        }
+    }
+
+    impl Drop for EnvGuard<'_> {
+        fn drop(&mut self) {
+            for (name, value) in &self.vars {
+                match value {
+                    Some(v) => env::set_var(name, v),
+                    None => env::remove_var(name),
+                }
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_reads_length_frames() {
+        use std::os::unix::net::UnixStream;
+
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let daemon = thread::spawn(move || {
+            let mut writer = theirs.try_clone().unwrap();
+            let mut lines = BufReader::new(theirs).lines();
+            let mut requests = Vec::new();
+            for reply in [
+                // Large enough to arrive in several chunks
+                json!({ "success": true, "data": { "html": "x".repeat(200_000) } }).to_string(),
+                // From a daemon that predates framing
+                json!({ "success": true, "data": null }).to_string() + "\n",
+            ] {
+                let request: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+                if reply.ends_with('\n') {
+                    write!(writer, "{}", reply).unwrap();
+                } else {
+                    write!(writer, "#{}\n{}", reply.len(), reply).unwrap();
+                }
+                requests.push(request);
+            }
+            requests
+        });
+
+        let mut conn = DaemonConnection::new(Connection::Unix(ours));
+        let mut calls = Vec::new();
+        let resp = conn
+            .send_with_progress(
+                &json!({ "id": "1", "action": "content" }),
+                &mut |n, total| calls.push((n, total)),
+            )
+            .unwrap();
+        assert_eq!(resp.data.unwrap()["html"].as_str().unwrap().len(), 200_000);
+        assert!(calls.len() > 1);
+        let (received, total) = *calls.last().unwrap();
+        assert_eq!(received, total);
+
+        let resp = conn.send(&json!({ "id": "2", "action": "back" })).unwrap();
+        assert!(resp.success);
+
+        let requests = daemon.join().unwrap();
+        assert_eq!(requests[0]["framing"], "length");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpaced_connection() {
+        use std::os::unix::net::UnixStream;
+
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_MIN_DELAY", "AGENT_BROWSER_SOCKET_DIR"]);
+        let dir = env::temp_dir().join(format!("agent-browser-unpaced-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", &dir);
+        env::set_var("AGENT_BROWSER_MIN_DELAY", "400");
+
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let daemon = thread::spawn(move || {
+            let mut writer = theirs.try_clone().unwrap();
+            for line in BufReader::new(theirs).lines().take(4) {
+                let request: Value = serde_json::from_str(&line.unwrap()).unwrap();
+                let reply = json!({ "id": request["id"], "success": true, "data": null });
+                writeln!(writer, "{}", reply).unwrap();
+            }
+        });
+
+        let mut conn = DaemonConnection::new(Connection::Unix(ours));
+        conn.session = Some("paced".to_string());
+        let started = Instant::now();
+        conn.send(&json!({ "id": "1", "action": "url" })).unwrap();
+        conn.send(&json!({ "id": "2", "action": "url" })).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(350));
+
+        conn.set_unpaced();
+        let started = Instant::now();
+        conn.send(&json!({ "id": "3", "action": "url" })).unwrap();
+        conn.send(&json!({ "id": "4", "action": "url" })).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(350));
+
+        daemon.join().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_reads_gzip_frames() {
+        use flate2::write::GzEncoder;
+        use std::os::unix::net::UnixStream;
+
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let daemon = thread::spawn(move || {
+            let mut writer = theirs.try_clone().unwrap();
+            let mut lines = BufReader::new(theirs).lines();
+            let request: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+            let reply = json!({ "success": true, "data": { "html": "<p>hi</p>".repeat(10_000) } });
+            let mut gz = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            gz.write_all(reply.to_string().as_bytes()).unwrap();
+            let body = gz.finish().unwrap();
+            writeln!(writer, "#{} gzip", body.len()).unwrap();
+            writer.write_all(&body).unwrap();
+            request
+        });
+
+        let mut conn = DaemonConnection::new(Connection::Unix(ours));
+        conn.set_compression(Some(Compression::Gzip));
+        let resp = conn
+            .send(&json!({ "id": "1", "action": "content" }))
+            .unwrap();
+        assert_eq!(resp.data.unwrap()["html"].as_str().unwrap().len(), 90_000);
+        assert_eq!(daemon.join().unwrap()["compression"], "gzip");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_responses_matched_by_id() {
+        use std::os::unix::net::UnixStream;
+
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let daemon = thread::spawn(move || {
+            let mut writer = theirs.try_clone().unwrap();
+            let mut lines = BufReader::new(theirs).lines();
+            let mut ids = Vec::new();
+            for _ in 0..3 {
+                let request: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+                ids.push(request["id"].clone());
+            }
+            // Answered last first, with an event in between
+            for id in ids.iter().rev() {
+                writeln!(
+                    writer,
+                    "{}",
+                    json!({ "id": id, "success": true, "data": id })
+                )
+                .unwrap();
+                writeln!(writer, "{}", json!({ "event": "console", "data": {} })).unwrap();
+            }
+        });
+
+        let mut conn = DaemonConnection::new(Connection::Unix(ours));
+        for id in ["wait", "console", "url"] {
+            conn.submit(&json!({ "id": id, "action": id })).unwrap();
+        }
+        assert_eq!(conn.receive("console").unwrap().data.unwrap(), "console");
+        assert_eq!(conn.receive("wait").unwrap().data.unwrap(), "wait");
+        // Read while waiting for "console"
+        assert_eq!(conn.receive("url").unwrap().data.unwrap(), "url");
+        daemon.join().unwrap();
+        assert!(conn.in_flight.lock().unwrap().waiting.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_responses_to_other_requests_skipped() {
+        use std::os::unix::net::UnixStream;
+
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let daemon = thread::spawn(move || {
+            let mut writer = theirs.try_clone().unwrap();
+            let mut lines = BufReader::new(theirs).lines();
+            let request: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+            // The late answer to a request given up on, then the real one
+            for id in [json!("r1-1"), request["id"].clone()] {
+                writeln!(
+                    writer,
+                    "{}",
+                    json!({ "id": id, "success": true, "data": id })
+                )
+                .unwrap();
+            }
+        });
+
+        let mut conn = DaemonConnection::new(Connection::Unix(ours));
+        let resp = conn
+            .send(&json!({ "id": "r1-2", "action": "url" }))
+            .unwrap();
+        assert_eq!(resp.data.unwrap(), "r1-2");
+        daemon.join().unwrap();
+        assert!(conn.in_flight.lock().unwrap().answered.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_next_response_from_another_handle() {
+        use std::os::unix::net::UnixStream;
+
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let daemon = thread::spawn(move || {
+            let mut writer = theirs.try_clone().unwrap();
+            for line in BufReader::new(theirs).lines() {
+                let request: Value = serde_json::from_str(&line.unwrap()).unwrap();
+                // A failure the daemon couldn't tie to its request
+                let reply = match request["action"].as_str() {
+                    Some("bogus") => json!({ "id": "unknown", "success": false, "error": "x" }),
+                    _ => json!({ "id": request["id"], "success": true, "data": null }),
+                };
+                writeln!(writer, "{}", reply).unwrap();
+            }
+        });
+
+        let mut reader = DaemonConnection::new(Connection::Unix(ours));
+        let mut writer = reader.try_clone().unwrap();
+        writer
+            .submit(&json!({ "id": "1", "action": "bogus" }))
+            .unwrap();
+        let resp = reader.next_response().unwrap().unwrap();
+        assert!(!resp.success);
+        writer
+            .submit(&json!({ "id": "2", "action": "url" }))
+            .unwrap();
+        assert_eq!(reader.next_response().unwrap().unwrap().id.unwrap(), "2");
+        assert!(reader.in_flight.lock().unwrap().waiting.is_empty());
+
+        drop(writer);
+        drop(reader);
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_parse_remote() {
+        assert_eq!(
+            parse_remote("tcp://10.0.0.5:9400").unwrap(),
+            "10.0.0.5:9400"
+        );
+        assert_eq!(parse_remote("tcp://browser:9400/").unwrap(), "browser:9400");
+        assert_eq!(parse_remote("tcp://[::1]:9400").unwrap(), "[::1]:9400");
+        for invalid in ["tcp://host", "tcp://:9400", "tcp://host:http", "host:9400"] {
+            assert!(matches!(parse_remote(invalid), Err(Error::Remote(_))));
+        }
+        let err = parse_remote("ssh://vm").unwrap_err().to_string();
+        assert!(err.contains("ssh -L 9400:localhost:9400 vm"));
+    }
+
+    #[test]
+    fn test_remote_address_from_env() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_REMOTE"]);
+        env::remove_var("AGENT_BROWSER_REMOTE");
+        assert_eq!(remote_address().unwrap(), None);
+        env::set_var("AGENT_BROWSER_REMOTE", "tcp://localhost:9400");
+        assert_eq!(remote_address().unwrap().as_deref(), Some("localhost:9400"));
+    }
+
+    #[test]
+    fn test_token_for_reads_session_file() {
+        let _guard = EnvGuard::new(&[
+            "AGENT_BROWSER_SOCKET_DIR",
+            "AGENT_BROWSER_TOKEN",
+            "AGENT_BROWSER_REMOTE",
+        ]);
+        let dir = env::temp_dir().join(format!("agent-browser-token-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", &dir);
+        env::set_var("AGENT_BROWSER_TOKEN", "from-env");
+        env::remove_var("AGENT_BROWSER_REMOTE");
+        fs::write(get_token_path("work"), "abc123\n").unwrap();
+
+        assert_eq!(token_for("work").as_deref(), Some("abc123"));
+        assert_eq!(token_for("other").as_deref(), Some("from-env"));
+        // Token files belong to local daemons
+        env::set_var("AGENT_BROWSER_REMOTE", "tcp://10.0.0.5:9400");
+        assert_eq!(token_for("work").as_deref(), Some("from-env"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_response_timeout() {
+        assert_eq!(
+            response_timeout(&json!({ "action": "click" })),
+            RESPONSE_TIMEOUT
+        );
+        assert_eq!(
+            response_timeout(&json!({ "action": "wait", "timeout": 1000 })),
+            RESPONSE_TIMEOUT
+        );
+        assert_eq!(
+            response_timeout(&json!({ "action": "expect", "timeout": 60000 })),
+            Duration::from_secs(65)
+        );
+    }
+
+    #[test]
+    fn test_response_timeout_deadline() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let deadline = (now + Duration::from_secs(90)).as_millis() as u64;
+        let timeout = response_timeout(&json!({ "action": "click", "deadline": deadline }));
+        assert!(timeout > Duration::from_secs(90) && timeout <= Duration::from_secs(95));
+
+        // A short deadline also shortens the wait, and one already past still
+        // leaves time for the daemon's error
+        let deadline = (now + Duration::from_secs(1)).as_millis() as u64;
+        let timeout =
+            response_timeout(&json!({ "action": "wait", "timeout": 1000, "deadline": deadline }));
+        assert!(timeout < RESPONSE_TIMEOUT);
+        let timeout = response_timeout(&json!({ "action": "click", "deadline": 1 }));
+        assert_eq!(timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_get_socket_dir_explicit_override() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
+
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", "/custom/socket/path");
+        env::remove_var("XDG_RUNTIME_DIR");
+
+        assert_eq!(get_socket_dir(), PathBuf::from("/custom/socket/path"));
+    }
+
+    #[test]
+    fn test_get_socket_dir_ignores_empty_socket_dir() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
+
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", "");
+        env::remove_var("XDG_RUNTIME_DIR");
+
+        assert!(get_socket_dir()
+            .to_string_lossy()
+            .ends_with(".agent-browser"));
+    }
+
+    #[test]
+    fn test_get_socket_dir_xdg_runtime() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
+
+        env::remove_var("AGENT_BROWSER_SOCKET_DIR");
+        env::set_var("XDG_RUNTIME_DIR", "/run/user/1000");
+
+        assert_eq!(
+            get_socket_dir(),
+            PathBuf::from("/run/user/1000/agent-browser")
+        );
+    }
+
+    #[test]
+    fn test_get_socket_dir_ignores_empty_xdg_runtime() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
+
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", "");
+        env::set_var("XDG_RUNTIME_DIR", "");
+
+        assert!(get_socket_dir()
+            .to_string_lossy()
+            .ends_with(".agent-browser"));
+    }
+
+    #[test]
+    fn test_get_socket_dir_home_fallback() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR", "HOME"]);
+
+        env::remove_var("AGENT_BROWSER_SOCKET_DIR");
+        env::remove_var("XDG_RUNTIME_DIR");
+        // A home outside /home, like root's, would not be recognised
+        #[cfg(unix)]
+        env::set_var("HOME", "/home/tester");
+
+        let result = get_socket_dir();
+        assert!(result.to_string_lossy().ends_with(".agent-browser"));
+        assert!(
+            result.to_string_lossy().contains("home") || result.to_string_lossy().contains("Users")
+        );
+    }
+
+    #[test]
+    fn test_get_socket_dir_runtime_dir() {
+        let _guard = EnvGuard::new(&[
+            "AGENT_BROWSER_SOCKET_DIR",
+            "AGENT_BROWSER_RUNTIME_DIR",
+            "XDG_RUNTIME_DIR",
+        ]);
+
+        env::remove_var("AGENT_BROWSER_SOCKET_DIR");
+        env::set_var("AGENT_BROWSER_RUNTIME_DIR", "/srv/agent-browser");
+        env::set_var("XDG_RUNTIME_DIR", "/run/user/1000");
+        assert_eq!(get_socket_dir(), PathBuf::from("/srv/agent-browser"));
+
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", "/custom/socket/path");
+        assert_eq!(get_socket_dir(), PathBuf::from("/custom/socket/path"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_socket_path_override() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET", "AGENT_BROWSER_SOCKET_DIR"]);
+
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", "/custom/socket/path");
+        env::remove_var("AGENT_BROWSER_SOCKET");
+        assert_eq!(
+            get_socket_path("work"),
+            PathBuf::from("/custom/socket/path/work.sock")
+        );
+
+        env::set_var("AGENT_BROWSER_SOCKET", "/tmp/ci/browser.sock");
+        assert_eq!(
+            get_socket_path("work"),
+            PathBuf::from("/tmp/ci/browser.sock")
+        );
+    }
+
+    // === Transient Error Detection Tests ===
+
+    #[test]
+    fn test_is_transient_error_eagain_macos() {
+        assert!(is_transient_error(
+            "Failed to read: Resource temporarily unavailable (os error 35)"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_eagain_linux() {
+        assert!(is_transient_error(
+            "Failed to read: Resource temporarily unavailable (os error 11)"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_would_block() {
+        assert!(is_transient_error("operation WouldBlock"));
+    }
+
+    #[test]
+    fn test_is_transient_error_resource_unavailable() {
+        assert!(is_transient_error("Resource temporarily unavailable"));
+    }
+
+    #[test]
+    fn test_is_transient_error_eof() {
+        assert!(is_transient_error(
+            "Invalid response: EOF while parsing a value at line 1 column 0"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_empty_json() {
+        assert!(is_transient_error(
+            "Invalid response: expected value at line 1 column 0"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_connection_reset() {
+        assert!(is_transient_error("Connection reset by peer"));
+    }
+
+    #[test]
+    fn test_is_transient_error_broken_pipe() {
+        assert!(is_transient_error("Broken pipe"));
+    }
+
+    #[test]
+    fn test_is_transient_error_connection_reset_macos() {
+        assert!(is_transient_error(
+            "Failed to send: Connection reset by peer (os error 54)"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_connection_reset_linux() {
+        assert!(is_transient_error(
+            "Failed to send: Connection reset by peer (os error 104)"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_socket_not_found() {
+        assert!(is_transient_error(
+            "Failed to connect: No such file or directory (os error 2)"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_connection_refused_macos() {
+        assert!(is_transient_error(
+            "Failed to connect: Connection refused (os error 61)"
+        ));
+    }
+
+    #[test]
+    fn test_is_transient_error_connection_refused_linux() {
+        assert!(is_transient_error(
+            "Failed to connect: Connection refused (os error 111)"
+        ));
+    }
+
+    #[test]
+    fn test_response_redact() {
+        let mut resp = Response {
+            success: false,
+            data: Some(json!({ "value": "hunter2", "nested": ["x hunter2 y", 3] })),
+            error: Some("fill \"hunter2\" timed out".to_string()),
+            ..Default::default()
+        };
+        resp.redact("hunter2");
+        assert_eq!(resp.error.as_deref(), Some("fill \"********\" timed out"));
+        assert_eq!(
+            resp.data,
+            Some(json!({ "value": "********", "nested": ["x ******** y", 3] }))
+        );
+    }
+
+    #[test]
+    fn test_response_error_code() {
+        let line = r##"{"id":"r1","success":false,"error":"Element \"#go\" not found","errorCode":"not_found","errorContext":{"selector":"#go","stack":"at click"}}"##;
+        let mut resp: Response = serde_json::from_str(line).unwrap();
+        assert_eq!(resp.error_code, Some(ErrorCode::NotFound));
+        assert_eq!(
+            resp.error_context.as_ref().unwrap().selector.as_deref(),
+            Some("#go")
+        );
+        resp.redact("#go");
+        assert_eq!(
+            resp.error_context.unwrap().selector.as_deref(),
+            Some("********")
+        );
+
+        // Codes from newer daemons still parse, and older daemons send none
+        let resp: Response =
+            serde_json::from_str(r#"{"success":false,"error":"x","errorCode":"quota"}"#).unwrap();
+        assert_eq!(resp.error_code, Some(ErrorCode::Unknown));
+        let resp: Response = serde_json::from_str(r#"{"success":false,"error":"x"}"#).unwrap();
+        assert_eq!(resp.error_code, None);
+        assert!(!serde_json::to_string(&resp).unwrap().contains("errorCode"));
+    }
+
+    #[test]
+    fn test_is_transient_error_non_transient() {
+        // These should NOT be considered transient
+        assert!(!is_transient_error("Unknown command: foo"));
+        assert!(!is_transient_error("Invalid JSON syntax"));
+        assert!(!is_transient_error("Permission denied"));
+        assert!(!is_transient_error("Daemon not found"));
+    }
+}
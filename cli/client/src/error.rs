@@ -0,0 +1,54 @@
+use std::fmt;
+use std::io;
+
+/// Errors from talking to a session's daemon
+#[derive(Debug)]
+pub enum Error {
+    /// The daemon's socket could not be opened (not running, or still starting)
+    Connect(io::Error),
+    /// The session's port file is missing or unreadable (Windows)
+    PortFile(String),
+    /// The `--remote` address is malformed or uses an unsupported transport
+    Remote(String),
+    /// Writing the request failed
+    Send(io::Error),
+    /// Reading the response failed or timed out
+    Read(io::Error),
+    /// The daemon did not answer with valid JSON
+    InvalidResponse(serde_json::Error),
+    /// Every attempt failed with a transient error
+    Unresponsive { last: Box<Error>, retries: u32 },
+    /// The daemon ran the command and reported a failure
+    Command(String),
+    /// A successful response did not include an expected field
+    MissingField(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Connect(e) => write!(f, "Failed to connect: {}", e),
+            Error::PortFile(msg) | Error::Remote(msg) | Error::Command(msg) => f.write_str(msg),
+            Error::Send(e) => write!(f, "Failed to send: {}", e),
+            Error::Read(e) => write!(f, "Failed to read: {}", e),
+            Error::InvalidResponse(e) => write!(f, "Invalid response: {}", e),
+            Error::Unresponsive { last, retries } => write!(
+                f,
+                "{} (after {} retries - daemon may be busy or unresponsive)",
+                last, retries
+            ),
+            Error::MissingField(field) => write!(f, "Response did not include {}", field),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Connect(e) | Error::Send(e) | Error::Read(e) => Some(e),
+            Error::InvalidResponse(e) => Some(e),
+            Error::Unresponsive { last, .. } => Some(last.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Client for the agent-browser daemon.
+//!
+//! The agent-browser CLI sends JSON commands to a per-session daemon over a
+//! Unix socket (a localhost TCP port on Windows). This crate is that
+//! connection layer, plus a typed [`Client`] for driving the browser from
+//! Rust without spawning the CLI.
+//!
+//! The session's daemon must already be running. Any CLI command starts it,
+//! e.g. `agent-browser open about:blank`.
+//!
+//! ```no_run
+//! use agent_browser_client::{Client, SnapshotOptions};
+//!
+//! let mut client = Client::connect("default")?;
+//! client.navigate("https://example.com")?;
+//! let snapshot = client.snapshot(&SnapshotOptions {
+//!     interactive: true,
+//!     ..Default::default()
+//! })?;
+//! println!("{}", snapshot.tree);
+//! client.click("@e1")?;
+//! # Ok::<(), agent_browser_client::Error>(())
+//! ```
+//!
+//! Other actions can be sent as a typed [`Command`] with [`Client::run`], or
+//! by name through [`Client::command`], which takes the protocol's action
+//! name and parameters.
+//!
+//! To reach a daemon on another machine or in a container, set
+//! `AGENT_BROWSER_REMOTE=tcp://host:port` (and `AGENT_BROWSER_TOKEN` if the
+//! daemon requires one); connections then go there in place of the local
+//! socket.
+//!
+//! `AGENT_BROWSER_MIN_DELAY=<ms>` and `AGENT_BROWSER_MAX_RPS=<n>` space out
+//! the commands sent to a session, across processes: connections opened
+//! for a session wait their turn before sending.
+//!
+//! With the `tokio` feature, [`AsyncClient`] offers the same methods as
+//! futures and can run many commands at once over one connection.
+
+#[cfg(feature = "tokio")]
+mod async_client;
+pub mod connection;
+mod error;
+mod pace;
+pub mod protocol;
+
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncClient;
+pub use connection::{
+    get_socket_dir, parse_remote, remote_address, send_command, send_command_timed,
+    send_command_with_progress, Connection, DaemonConnection, ErrorCode, ErrorContext, Response,
+    Timings,
+};
+pub use error::Error;
+pub use protocol::Command;
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use protocol::{
+    data_as, request_id, HelloData, NavigateData, RefInfo, SnapshotData, PROTOCOL_VERSION,
+};
+
+/// Options for [`Client::snapshot`]
+pub type SnapshotOptions = protocol::SnapshotFilter;
+
+/// An accessibility snapshot of the page
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The tree as text, with refs (`[ref=e1]`) usable as `@e1` selectors
+    pub tree: String,
+    /// Role and name for each ref
+    pub refs: BTreeMap<String, RefInfo>,
+}
+
+impl Snapshot {
+    fn from_data(data: Value) -> Result<Self, Error> {
+        let data: SnapshotData = data_as(data)?;
+        Ok(Snapshot {
+            tree: data.snapshot.ok_or(Error::MissingField("snapshot"))?,
+            refs: data.refs,
+        })
+    }
+}
+
+fn snapshot_command(options: &SnapshotOptions) -> Command {
+    Command::Snapshot {
+        filter: options.clone(),
+        format: None,
+        max_chars: None,
+        page: None,
+        coords: false,
+        if_changed: None,
+    }
+}
+
+/// Build a request for `action`, merging in `params` (an object, or null)
+fn request(id: String, action: &str, params: Value) -> Value {
+    let mut cmd = json!({ "id": id, "action": action });
+    if let (Some(obj), Value::Object(extra)) = (cmd.as_object_mut(), params) {
+        obj.extend(extra);
+    }
+    cmd
+}
+
+/// The response's `data`, or its error as [`Error::Command`]
+fn into_data(resp: Response) -> Result<Value, Error> {
+    if resp.success {
+        Ok(resp.data.unwrap_or(Value::Null))
+    } else {
+        Err(Error::Command(
+            resp.error.unwrap_or_else(|| "Command failed".to_string()),
+        ))
+    }
+}
+
+/// A connection to one session's daemon with typed methods for common actions
+pub struct Client {
+    conn: DaemonConnection,
+}
+
+impl Client {
+    /// Connect to the daemon of a running session
+    pub fn connect(session: &str) -> Result<Self, Error> {
+        Ok(Self::from_connection(DaemonConnection::open(session)?))
+    }
+
+    pub fn from_connection(conn: DaemonConnection) -> Self {
+        Self { conn }
+    }
+
+    /// Send a typed command and return its `data`
+    pub fn run(&mut self, command: Command) -> Result<Value, Error> {
+        let cmd = command.into_request(request_id("c"));
+        into_data(self.conn.send(&cmd)?)
+    }
+
+    /// Send any protocol command and return its `data`. `params` must be an
+    /// object (or null); it is merged into the request next to `action`.
+    pub fn command(&mut self, action: &str, params: Value) -> Result<Value, Error> {
+        let cmd = request(request_id("c"), action, params);
+        into_data(self.conn.send(&cmd)?)
+    }
+
+    /// Exchange protocol versions with the daemon. Check the reply's
+    /// `protocol_version` against [`PROTOCOL_VERSION`] before relying on the
+    /// typed commands; a daemon older than the handshake answers with
+    /// [`Error::Command`].
+    pub fn hello(&mut self) -> Result<HelloData, Error> {
+        let protocol_version = PROTOCOL_VERSION;
+        data_as(self.run(Command::Hello { protocol_version })?)
+    }
+
+    pub fn navigate(&mut self, url: &str) -> Result<NavigateData, Error> {
+        data_as(self.run(Command::navigate(url))?)
+    }
+
+    pub fn back(&mut self) -> Result<(), Error> {
+        self.run(Command::Back).map(drop)
+    }
+
+    pub fn forward(&mut self) -> Result<(), Error> {
+        self.run(Command::Forward).map(drop)
+    }
+
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.run(Command::Reload).map(drop)
+    }
+
+    pub fn click(&mut self, selector: &str) -> Result<(), Error> {
+        self.run(Command::click(selector)).map(drop)
+    }
+
+    /// Clear an input and fill it with `value`
+    pub fn fill(&mut self, selector: &str, value: &str) -> Result<(), Error> {
+        self.run(Command::fill(selector, value)).map(drop)
+    }
+
+    /// Type `text` key by key into an element
+    pub fn type_text(&mut self, selector: &str, text: &str) -> Result<(), Error> {
+        self.run(Command::type_text(selector, text)).map(drop)
+    }
+
+    /// Press a key or chord, e.g. `Enter` or `Control+a`
+    pub fn press(&mut self, key: &str) -> Result<(), Error> {
+        self.run(Command::press(key)).map(drop)
+    }
+
+    pub fn hover(&mut self, selector: &str) -> Result<(), Error> {
+        let selector = selector.to_string();
+        self.run(Command::Hover { selector }).map(drop)
+    }
+
+    pub fn snapshot(&mut self, options: &SnapshotOptions) -> Result<Snapshot, Error> {
+        Snapshot::from_data(self.run(snapshot_command(options))?)
+    }
+
+    /// Evaluate JavaScript in the page and return its result
+    pub fn evaluate(&mut self, script: &str) -> Result<Value, Error> {
+        let data: protocol::EvaluateData = data_as(self.run(Command::evaluate(script))?)?;
+        Ok(data.result)
+    }
+
+    pub fn url(&mut self) -> Result<String, Error> {
+        data_as(self.run(Command::Url)?).map(|d: protocol::UrlData| d.url)
+    }
+
+    pub fn title(&mut self) -> Result<String, Error> {
+        data_as(self.run(Command::Title)?).map(|d: protocol::TitleData| d.title)
+    }
+
+    /// Text content of an element
+    pub fn text(&mut self, selector: &str) -> Result<String, Error> {
+        let selector = selector.to_string();
+        data_as(self.run(Command::Gettext { selector })?).map(|d: protocol::TextData| d.text)
+    }
+
+    /// Save a screenshot to `path` and return the path the daemon wrote
+    pub fn screenshot(&mut self, path: &str, full_page: bool) -> Result<String, Error> {
+        let data: protocol::ScreenshotData =
+            data_as(self.run(Command::screenshot(path, full_page))?)?;
+        data.path.ok_or(Error::MissingField("path"))
+    }
+
+    /// Close the browser. The daemon exits afterwards.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.run(Command::Close).map(drop)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    /// A client wired to a fake daemon that answers each request with `reply`
+    fn client_with(reply: fn(&Value) -> Value) -> (Client, thread::JoinHandle<Vec<Value>>) {
+        let (ours, theirs) = UnixStream::pair().unwrap();
+        let daemon = thread::spawn(move || {
+            let mut requests = Vec::new();
+            let mut writer = theirs.try_clone().unwrap();
+            for line in BufReader::new(theirs).lines() {
+                let request: Value = serde_json::from_str(&line.unwrap()).unwrap();
+                writeln!(writer, "{}", reply(&request)).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        let client = Client::from_connection(DaemonConnection::new(Connection::Unix(ours)));
+        (client, daemon)
+    }
+
+    #[test]
+    fn test_typed_methods() {
+        let (mut client, daemon) = client_with(|req| match req["action"].as_str() {
+            Some("navigate") => json!({
+                "success": true,
+                "data": { "url": "https://example.com/", "title": "Example" }
+            }),
+            Some("url") => json!({ "success": true, "data": { "url": "https://example.com/" } }),
+            Some("snapshot") => json!({
+                "success": true,
+                "data": {
+                    "snapshot": "- button \"Go\" [ref=e1]",
+                    "refs": { "e1": { "role": "button", "name": "Go" } }
+                }
+            }),
+            _ => json!({ "success": true, "data": null }),
+        });
+
+        let page = client.navigate("https://example.com").unwrap();
+        assert_eq!(page.title, "Example");
+        assert_eq!(client.url().unwrap(), "https://example.com/");
+        let snapshot = client
+            .snapshot(&SnapshotOptions {
+                interactive: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(snapshot.tree.contains("[ref=e1]"));
+        assert_eq!(snapshot.refs["e1"].role, "button");
+        drop(client);
+
+        let requests = daemon.join().unwrap();
+        assert_eq!(requests[0]["action"], "navigate");
+        assert_eq!(requests[0]["url"], "https://example.com");
+        assert_eq!(requests[2]["interactive"], true);
+        assert_ne!(requests[0]["id"], requests[1]["id"]);
+    }
+
+    #[test]
+    fn test_command_errors() {
+        let (mut client, _daemon) = client_with(|req| match req["action"].as_str() {
+            Some("title") => json!({ "success": true, "data": {} }),
+            _ => json!({ "success": false, "error": "Element not found: #missing" }),
+        });
+
+        match client.click("#missing") {
+            Err(Error::Command(msg)) => assert_eq!(msg, "Element not found: #missing"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(client.title(), Err(Error::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        let err = Error::Unresponsive {
+            last: Box::new(Error::Connect(refused)),
+            retries: 5,
+        };
+        assert!(err.to_string().starts_with("Failed to connect: "));
+        assert!(err
+            .to_string()
+            .ends_with("(after 5 retries - daemon may be busy or unresponsive)"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Politeness limits: a least time between the commands sent to a session, so
+//! crawling-style runs don't hammer the sites they visit or look like a bot.
+//!
+//! `AGENT_BROWSER_MIN_DELAY` (milliseconds) and `AGENT_BROWSER_MAX_RPS`
+//! (commands per second) each give an interval, and the longer one applies.
+//! When the last command went out is kept in `<session>.pace` in the socket
+//! directory, so separate invocations of the CLI keep to the limit together,
+//! as do the commands of one chain, script or pipe.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::connection::get_socket_dir;
+
+/// What clients ask for themselves rather than for the page, never held back
+const UNPACED: &[&str] = &[
+    "hello",
+    "status",
+    "cancel",
+    "launch",
+    "close",
+    "state_save",
+    "state_load",
+    "daemon_status",
+    "daemon_config",
+];
+
+/// When this process last sent a paced command (Unix ms), in case the file
+/// can't be written. Held while waiting, so threads take turns.
+static LAST_SENT: Mutex<Option<u64>> = Mutex::new(None);
+
+/// The least time between two commands: `min_delay` ms, or a second over
+/// `max_rps`, whichever is longer. `None` when neither limits anything.
+pub fn interval(min_delay: Option<u64>, max_rps: Option<f64>) -> Option<Duration> {
+    let delay = min_delay.filter(|&ms| ms > 0).map(Duration::from_millis);
+    let rate = max_rps
+        .filter(|&rps| rps > 0.0 && rps.is_finite())
+        .map(|rps| Duration::from_secs_f64(1.0 / rps));
+    delay.max(rate)
+}
+
+fn from_env() -> Option<Duration> {
+    let min_delay = env::var("AGENT_BROWSER_MIN_DELAY")
+        .ok()
+        .and_then(|v| v.parse().ok());
+    let max_rps = env::var("AGENT_BROWSER_MAX_RPS")
+        .ok()
+        .and_then(|v| v.parse().ok());
+    interval(min_delay, max_rps)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// How long to hold a command back at `now`, the last one having gone out at
+/// `last` (both Unix ms)
+fn delay(last: Option<u64>, now: u64, interval: Duration) -> Duration {
+    match last {
+        Some(last) => {
+            let next = last.saturating_add(interval.as_millis() as u64);
+            Duration::from_millis(next.saturating_sub(now))
+        }
+        None => Duration::ZERO,
+    }
+}
+
+/// Hold `cmd` back until the interval has passed since `session`'s last
+/// command, if a limit is set
+pub(crate) fn wait_turn(session: &str, cmd: &Value) {
+    let Some(interval) = from_env() else {
+        return;
+    };
+    let action = cmd.get("action").and_then(|v| v.as_str()).unwrap_or("");
+    if UNPACED.contains(&action) {
+        return;
+    }
+    let path = get_socket_dir().join(format!("{}.pace", session));
+    keep_to(&path, interval);
+}
+
+/// Wait until `interval` has passed since the last command recorded in the
+/// pace file at `path`, by any process, or sent by this one, and record the
+/// one about to go out
+fn keep_to(path: &Path, interval: Duration) {
+    let mut last_sent = LAST_SENT.lock().unwrap_or_else(|e| e.into_inner());
+    let recorded = fs::read_to_string(path)
+        .ok()
+        .and_then(|text| text.trim().parse::<u64>().ok());
+    thread::sleep(delay(recorded.max(*last_sent), now_ms(), interval));
+    let sent = now_ms();
+    *last_sent = Some(sent);
+    let _ = fs::write(path, sent.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval() {
+        assert_eq!(interval(None, None), None);
+        assert_eq!(interval(Some(0), None), None);
+        assert_eq!(interval(Some(500), None), Some(Duration::from_millis(500)));
+        assert_eq!(interval(None, Some(4.0)), Some(Duration::from_millis(250)));
+        // The stricter limit wins
+        assert_eq!(
+            interval(Some(100), Some(2.0)),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            interval(Some(800), Some(2.0)),
+            Some(Duration::from_millis(800))
+        );
+        assert_eq!(interval(None, Some(0.0)), None);
+    }
+
+    #[test]
+    fn test_delay() {
+        let second = Duration::from_secs(1);
+        assert_eq!(delay(None, 10_000, second), Duration::ZERO);
+        assert_eq!(
+            delay(Some(9_700), 10_000, second),
+            Duration::from_millis(700)
+        );
+        assert_eq!(delay(Some(8_000), 10_000, second), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_keep_to_pace_file() {
+        // Another invocation just sent a command; this process hasn't sent any
+        let path = env::temp_dir().join(format!("agent-browser-{}.pace", std::process::id()));
+        *LAST_SENT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        fs::write(&path, now_ms().to_string()).unwrap();
+
+        let started = now_ms();
+        keep_to(&path, Duration::from_millis(300));
+        assert!(now_ms() - started >= 250);
+        // And the next one waits for this one
+        let recorded: u64 = fs::read_to_string(&path).unwrap().parse().unwrap();
+        assert!(recorded >= started + 250);
+        fs::remove_file(&path).unwrap();
+    }
+}
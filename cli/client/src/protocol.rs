@@ -0,0 +1,673 @@
+//! Typed commands and response payloads for the daemon's wire protocol.
+//!
+//! Each request is one JSON object per line: an `id`, the `action`, and the
+//! action's parameters. [`Command`] types a core set of actions, the ones
+//! other clients use most; every other action the daemon accepts is sent as
+//! a raw [`serde_json::Value`], which is also how the CLI sends all of its
+//! commands. [`schema`] describes the typed part as JSON Schema
+//! (`agent-browser --dump-schema`). It is not the whole protocol: the
+//! daemon's zod schemas in `src/protocol.ts` are the reference for that.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{Error, Response};
+
+/// Wire protocol version this crate speaks, sent with [`Command::Hello`].
+/// Must match `PROTOCOL_VERSION` in `src/protocol.ts`.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// A request as sent on the wire
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Request {
+    pub id: String,
+    /// Unix time in milliseconds by which the daemon must answer; past it,
+    /// the command fails with a timeout error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub framing: Option<Framing>,
+    /// Compress large length-framed responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    /// Required by daemons started with `AGENT_BROWSER_TOKEN`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Report how long the daemon spent on the command in the response's
+    /// `timings`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub timings: bool,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// How the daemon frames its response to a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Framing {
+    /// The JSON followed by a newline
+    Line,
+    /// A `#<byte length>` header line, then exactly that many bytes of JSON
+    Length,
+}
+
+/// Response compression. The frame header names it: `#<length> gzip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+}
+
+/// A daemon action and its parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(
+    tag = "action",
+    rename_all = "lowercase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Command {
+    #[serde(rename_all = "camelCase")]
+    Navigate {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wait_until: Option<WaitUntil>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        referer: Option<String>,
+        /// Milliseconds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<u64>,
+        /// Extra HTTP headers, scoped to the URL's origin
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        headers: Option<BTreeMap<String, String>>,
+        /// iOS device to launch if the browser is not running yet
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ios_device: Option<String>,
+        /// Open the page in a new tab or window instead of the current one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target: Option<NavigateTarget>,
+    },
+    Back,
+    Forward,
+    Reload,
+    #[serde(rename_all = "camelCase")]
+    Click {
+        selector: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        button: Option<MouseButton>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        click_count: Option<u32>,
+        /// Milliseconds between mousedown and mouseup
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delay: Option<u64>,
+    },
+    Dblclick {
+        selector: String,
+    },
+    /// Clear an input and set its value
+    Fill {
+        selector: String,
+        value: String,
+        /// The value came from a secret and is masked in output
+        #[serde(default, skip_serializing_if = "is_false")]
+        secret: bool,
+    },
+    /// Type key by key
+    Type {
+        selector: String,
+        text: String,
+        /// Milliseconds between key presses
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delay: Option<u64>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        clear: bool,
+    },
+    Hover {
+        selector: String,
+    },
+    Focus {
+        selector: String,
+    },
+    Blur {
+        selector: String,
+    },
+    Clear {
+        selector: String,
+    },
+    Check {
+        selector: String,
+    },
+    Uncheck {
+        selector: String,
+    },
+    Scrollintoview {
+        selector: String,
+    },
+    /// Press a key or chord, e.g. `Enter` or `Control+a`
+    Press {
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<String>,
+        /// Number of presses (1-1000)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        count: Option<u32>,
+    },
+    Keydown {
+        key: String,
+    },
+    Keyup {
+        key: String,
+    },
+    /// The accessibility tree, with refs for its elements
+    #[serde(rename_all = "camelCase")]
+    Snapshot {
+        #[serde(flatten)]
+        filter: SnapshotFilter,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<SnapshotFormat>,
+        /// Split the output into pages of this many characters
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_chars: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        page: Option<u32>,
+        /// Add each ref's bounding box, and whether it's in the viewport
+        #[serde(default, skip_serializing_if = "is_false")]
+        coords: bool,
+        /// Answer with just `unchanged` when the tree still has this hash
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        if_changed: Option<String>,
+    },
+    /// Changes since the previous snapshot
+    #[serde(rename = "snapshot_diff")]
+    SnapshotDiff {
+        #[serde(flatten)]
+        filter: SnapshotFilter,
+    },
+    #[serde(rename_all = "camelCase")]
+    Screenshot {
+        /// Where to save the image; the daemon picks a temp file if omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        full_page: bool,
+        /// Capture only this element
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<ImageFormat>,
+        /// 0-100, jpeg and webp only
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        quality: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clip: Option<Clip>,
+        /// Return the image as base64 instead of saving it
+        #[serde(default, skip_serializing_if = "is_false")]
+        stdout: bool,
+        /// Draw the refs of the last snapshot onto the image
+        #[serde(default, skip_serializing_if = "is_false")]
+        annotate_refs: bool,
+    },
+    /// Run JavaScript in the page (or on an element)
+    Evaluate {
+        script: String,
+        /// Arguments passed to the script when it is a function
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<String>,
+    },
+    Url,
+    Title,
+    Gettext {
+        selector: String,
+    },
+    /// Push events on this connection until it closes
+    Subscribe {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        types: Option<Vec<EventType>>,
+    },
+    /// Exchange protocol versions; the reply is [`HelloData`]
+    #[serde(rename_all = "camelCase")]
+    Hello {
+        protocol_version: u32,
+    },
+    /// Abort the command with this id, running on another connection. Its
+    /// request then fails with "Cancelled".
+    #[serde(rename_all = "camelCase")]
+    Cancel {
+        command_id: String,
+    },
+    /// Close the browser and stop the daemon
+    Close,
+}
+
+impl Command {
+    /// Navigate and wait for the load event
+    pub fn navigate(url: impl Into<String>) -> Self {
+        Command::Navigate {
+            url: url.into(),
+            wait_until: None,
+            referer: None,
+            timeout: None,
+            headers: None,
+            ios_device: None,
+            target: None,
+        }
+    }
+
+    /// Left click, once
+    pub fn click(selector: impl Into<String>) -> Self {
+        Command::Click {
+            selector: selector.into(),
+            button: None,
+            click_count: None,
+            delay: None,
+        }
+    }
+
+    pub fn fill(selector: impl Into<String>, value: impl Into<String>) -> Self {
+        Command::Fill {
+            selector: selector.into(),
+            value: value.into(),
+            secret: false,
+        }
+    }
+
+    pub fn type_text(selector: impl Into<String>, text: impl Into<String>) -> Self {
+        Command::Type {
+            selector: selector.into(),
+            text: text.into(),
+            delay: None,
+            clear: false,
+        }
+    }
+
+    /// Press `key` once, on the focused element
+    pub fn press(key: impl Into<String>) -> Self {
+        Command::Press {
+            key: key.into(),
+            selector: None,
+            count: None,
+        }
+    }
+
+    pub fn screenshot(path: impl Into<String>, full_page: bool) -> Self {
+        Command::Screenshot {
+            path: Some(path.into()),
+            full_page,
+            selector: None,
+            format: None,
+            quality: None,
+            clip: None,
+            stdout: false,
+            annotate_refs: false,
+        }
+    }
+
+    pub fn evaluate(script: impl Into<String>) -> Self {
+        Command::Evaluate {
+            script: script.into(),
+            args: Vec::new(),
+            selector: None,
+        }
+    }
+
+    /// The request as a JSON object, ready to send
+    pub fn into_request(self, id: impl Into<String>) -> Value {
+        let request = Request {
+            id: id.into(),
+            deadline: None,
+            framing: None,
+            compression: None,
+            token: None,
+            timings: false,
+            command: self,
+        };
+        serde_json::to_value(request).expect("commands always serialize")
+    }
+}
+
+/// Ids handed out by [`request_id`] so far in this process
+static REQUEST_IDS: AtomicU64 = AtomicU64::new(0);
+
+/// A new request id: `prefix`, the process id and a counter. The daemon
+/// tracks requests in flight by id across all its connections (a cancel can
+/// come from another one), so ids must differ between processes and between
+/// connections in one process, not only within a connection.
+pub fn request_id(prefix: &str) -> String {
+    let n = REQUEST_IDS.fetch_add(1, Ordering::Relaxed) + 1;
+    format!("{}{}-{}", prefix, std::process::id(), n)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WaitUntil {
+    Load,
+    Domcontentloaded,
+    Networkidle,
+    Commit,
+}
+
+/// Where `navigate` opens its page: a new tab in the current window, or a new
+/// window with its own cookies and storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NavigateTarget {
+    Tab,
+    Window,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Which part of the accessibility tree a snapshot includes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFilter {
+    /// Only interactive elements (buttons, links, inputs)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub interactive: bool,
+    /// Also elements that only look clickable (cursor: pointer, onclick)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cursor: bool,
+    /// Remove empty structural elements
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub compact: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<u32>,
+    /// Only the subtree under this CSS selector
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotFormat {
+    Text,
+    Json,
+    Yaml,
+    Md,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+/// A page region in CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Clip {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    Console,
+    Network,
+    Dialog,
+}
+
+/// `data` of a navigate response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NavigateData {
+    pub url: String,
+    pub title: String,
+    /// Index of the tab it opened, for a `target`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab: Option<usize>,
+}
+
+/// `data` of a screenshot response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScreenshotData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The image, for `stdout` screenshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+    /// How many refs were drawn onto the image, for `annotate_refs` screenshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotated: Option<usize>,
+}
+
+/// Role and name of an element in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RefInfo {
+    pub role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// `data` of a snapshot response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotData {
+    /// The tree as text, with refs (`[ref=e1]`) usable as `@e1` selectors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    /// The parsed tree, for `format: "json"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<Value>,
+    #[serde(default)]
+    pub refs: BTreeMap<String, RefInfo>,
+    /// Present when the output was split with `maxChars` or `page`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Value>,
+    /// Content hash of the tree, to pass as `ifChanged` when polling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Whether the tree still has the `ifChanged` hash, in which case the
+    /// snapshot itself is left out. Absent when `ifChanged` wasn't sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unchanged: Option<bool>,
+}
+
+/// `data` of an evaluate response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EvaluateData {
+    pub result: Value,
+}
+
+/// `data` of a url response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UrlData {
+    pub url: String,
+}
+
+/// `data` of a title response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TitleData {
+    pub title: String,
+}
+
+/// `data` of a gettext response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TextData {
+    pub text: String,
+}
+
+/// `data` of a hello response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HelloData {
+    pub protocol_version: u32,
+    /// agent-browser version of the daemon
+    pub version: String,
+    /// Every action the daemon accepts
+    pub actions: Vec<String>,
+    /// Response compression the daemon supports, e.g. `gzip` (empty before
+    /// compression was added)
+    #[serde(default)]
+    pub compression: Vec<String>,
+}
+
+impl HelloData {
+    pub fn supports(&self, action: &str) -> bool {
+        self.actions.iter().any(|a| a == action)
+    }
+}
+
+/// Decode a response's `data` as one of the payload types
+pub fn data_as<T: DeserializeOwned>(data: Value) -> Result<T, Error> {
+    serde_json::from_value(data).map_err(Error::InvalidResponse)
+}
+
+/// JSON Schema (draft 7) for requests, responses and the typed payloads,
+/// all under `definitions`. Requests only cover the actions in [`Command`].
+pub fn schema() -> Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+    gen.subschema_for::<Request>();
+    gen.subschema_for::<Command>();
+    gen.subschema_for::<Response>();
+    gen.subschema_for::<NavigateData>();
+    gen.subschema_for::<ScreenshotData>();
+    gen.subschema_for::<SnapshotData>();
+    gen.subschema_for::<EvaluateData>();
+    gen.subschema_for::<UrlData>();
+    gen.subschema_for::<TitleData>();
+    gen.subschema_for::<TextData>();
+    gen.subschema_for::<HelloData>();
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "agent-browser protocol",
+        "description": "Newline-delimited JSON between agent-browser clients and the daemon. \
+            Each line is a Request or a Response; payloads are the data of successful responses. \
+            Only the core actions are typed here; the daemon accepts more, see src/protocol.ts.",
+        "definitions": gen.take_definitions(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_request() {
+        let mut cmd = Command::navigate("https://example.com");
+        if let Command::Navigate { wait_until, .. } = &mut cmd {
+            *wait_until = Some(WaitUntil::Networkidle);
+        }
+        assert_eq!(
+            cmd.into_request("r1"),
+            json!({
+                "id": "r1",
+                "action": "navigate",
+                "url": "https://example.com",
+                "waitUntil": "networkidle"
+            })
+        );
+        assert_eq!(
+            Command::Back.into_request("r2"),
+            json!({ "id": "r2", "action": "back" })
+        );
+        let command_id = "r1".to_string();
+        assert_eq!(
+            Command::Cancel { command_id }.into_request("r3"),
+            json!({ "id": "r3", "action": "cancel", "commandId": "r1" })
+        );
+    }
+
+    #[test]
+    fn test_request_ids_unique() {
+        let first = request_id("c");
+        let second = request_id("c");
+        assert_ne!(first, second);
+        assert!(first.starts_with(&format!("c{}-", std::process::id())));
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let line =
+            r#"{"id":"1","action":"snapshot","interactive":true,"maxDepth":3,"format":"md"}"#;
+        let request: Request = serde_json::from_str(line).unwrap();
+        assert_eq!(request.id, "1");
+        assert_eq!(
+            request.command,
+            Command::Snapshot {
+                filter: SnapshotFilter {
+                    interactive: true,
+                    max_depth: Some(3),
+                    ..Default::default()
+                },
+                format: Some(SnapshotFormat::Md),
+                max_chars: None,
+                page: None,
+                coords: false,
+                if_changed: None,
+            }
+        );
+        let value = request.command.clone().into_request("1");
+        assert_eq!(value, serde_json::from_str::<Value>(line).unwrap());
+
+        let diff: Request =
+            serde_json::from_str(r#"{"id":"2","action":"snapshot_diff","compact":true}"#).unwrap();
+        assert!(matches!(diff.command, Command::SnapshotDiff { filter } if filter.compact));
+    }
+
+    #[test]
+    fn test_schema_definitions() {
+        let schema = schema();
+        let definitions = schema["definitions"].as_object().unwrap();
+        for name in [
+            "Request",
+            "Command",
+            "Response",
+            "NavigateData",
+            "SnapshotData",
+        ] {
+            assert!(definitions.contains_key(name), "missing {}", name);
+        }
+        let actions: Vec<&str> = definitions["Command"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v["properties"]["action"]["enum"][0].as_str())
+            .collect();
+        assert!(actions.contains(&"navigate"));
+        assert!(actions.contains(&"snapshot_diff"));
+        assert!(actions.contains(&"close"));
+    }
+
+    #[test]
+    fn test_actions_match_daemon() {
+        let daemon = include_str!("../../../src/protocol.ts");
+        let definitions = schema()["definitions"].clone();
+        let variants = definitions["Command"]["oneOf"].as_array().unwrap().clone();
+        for variant in variants {
+            let action = variant["properties"]["action"]["enum"][0].as_str().unwrap();
+            assert!(
+                daemon.contains(&format!("z.literal('{}')", action)),
+                "daemon has no schema for {}",
+                action
+            );
+        }
+    }
+
+    #[test]
+    fn test_protocol_version_matches_daemon() {
+        let daemon = include_str!("../../../src/protocol.ts");
+        let expected = format!("export const PROTOCOL_VERSION = {};", PROTOCOL_VERSION);
+        assert!(daemon.contains(&expected));
+    }
+}
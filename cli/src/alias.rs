@@ -0,0 +1,215 @@
+//! `[aliases]` in the config file: command names of your own that stand for
+//! built-in commands, with `$1`, `$2`, ... for their arguments and `$@` for
+//! all of them. Several commands are chained with `++`:
+//!
+//! ```toml
+//! [aliases]
+//! login = "open $1 ++ fill #user $2 ++ fill #pass $3 ++ click #submit"
+//! ```
+
+use std::collections::HashMap;
+
+use crate::flags::command_positions;
+use crate::script::{tokenize, CHAIN_SEPARATOR};
+use crate::spec;
+
+/// `args` with each command that is an alias, on its own or in a `++` chain,
+/// replaced by what it stands for; `None` when there is none. Global flags
+/// are kept, moved ahead of the commands. Built-in commands can't be
+/// redefined.
+pub fn expand(
+    args: &[String],
+    aliases: &HashMap<String, String>,
+) -> Result<Option<Vec<String>>, String> {
+    let positions = command_positions(args);
+    let commands: Vec<String> = positions.iter().map(|&i| args[i].clone()).collect();
+
+    let mut expanded = Vec::new();
+    let mut found = false;
+    for (n, command) in commands.split(|a| a == CHAIN_SEPARATOR).enumerate() {
+        if n > 0 {
+            expanded.push(CHAIN_SEPARATOR.to_string());
+        }
+        let alias = command
+            .first()
+            .filter(|name| spec::flags(name).is_none())
+            .and_then(|name| aliases.get(name));
+        match alias {
+            Some(definition) => {
+                found = true;
+                expanded.extend(substitute(&command[0], definition, &command[1..])?);
+            }
+            None => expanded.extend(command.iter().cloned()),
+        }
+    }
+    if !found {
+        return Ok(None);
+    }
+
+    let mut with_flags: Vec<String> = (0..args.len())
+        .filter(|i| !positions.contains(i))
+        .map(|i| args[i].clone())
+        .collect();
+    with_flags.extend(expanded);
+    Ok(Some(with_flags))
+}
+
+/// The alias's arguments with `$N` and `$@` replaced by `params`. Each
+/// parameter stays one argument, spaces and all.
+fn substitute(name: &str, definition: &str, params: &[String]) -> Result<Vec<String>, String> {
+    let tokens = tokenize(definition).map_err(|e| format!("Invalid alias {}: {}", name, e))?;
+    if tokens.is_empty() {
+        return Err(format!("Invalid alias {}: it has no command", name));
+    }
+
+    let mut expanded = Vec::new();
+    // The highest $N, and whether $@ takes whatever is left
+    let mut expects = 0;
+    let mut takes_all = false;
+    for token in tokens {
+        if token == "$@" {
+            takes_all = true;
+            expanded.extend(params.iter().cloned());
+            continue;
+        }
+        let mut text = String::new();
+        let mut rest = token.as_str();
+        while let Some(dollar) = rest.find('$') {
+            text.push_str(&rest[..dollar]);
+            let after = &rest[dollar + 1..];
+            let digits = after
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(after.len());
+            match after[..digits].parse::<usize>() {
+                Ok(n) if n > 0 => {
+                    expects = expects.max(n);
+                    text.push_str(params.get(n - 1).map_or("", String::as_str));
+                    rest = &after[digits..];
+                }
+                _ => {
+                    text.push('$');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        expanded.push(text);
+    }
+
+    if params.len() < expects {
+        return Err(format!(
+            "Missing arguments for alias: {} (expects {}, got {})\nAlias: {} = {}",
+            name,
+            expects,
+            params.len(),
+            name,
+            definition
+        ));
+    }
+    if params.len() > expects && !takes_all {
+        return Err(format!(
+            "Too many arguments for alias: {} (expects {}, got {})\nAlias: {} = {}",
+            name,
+            expects,
+            params.len(),
+            name,
+            definition
+        ));
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    fn aliases() -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "login".to_string(),
+                "open $1 ++ fill #user $2 ++ fill #pass $3 ++ click #submit".to_string(),
+            ),
+            ("home".to_string(), "open https://$1/".to_string()),
+            ("shot".to_string(), "screenshot --full $@".to_string()),
+            ("open".to_string(), "open example.com".to_string()),
+        ])
+    }
+
+    fn expand_args(s: &str) -> Result<Option<Vec<String>>, String> {
+        expand(&args(s), &aliases())
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand_args("login example.com/login me hunter2").unwrap(),
+            Some(args(
+                "open example.com/login ++ fill #user me ++ fill #pass hunter2 ++ click #submit"
+            ))
+        );
+        assert_eq!(
+            expand_args("home example.com").unwrap(),
+            Some(args("open https://example.com/"))
+        );
+        assert_eq!(
+            expand_args("shot page.png").unwrap(),
+            Some(args("screenshot --full page.png"))
+        );
+        assert_eq!(
+            expand_args("shot").unwrap(),
+            Some(args("screenshot --full"))
+        );
+    }
+
+    #[test]
+    fn test_expand_keeps_global_flags() {
+        assert_eq!(
+            expand_args("--session work home example.com --json").unwrap(),
+            Some(args("--session work --json open https://example.com/"))
+        );
+    }
+
+    #[test]
+    fn test_expand_in_chain() {
+        assert_eq!(
+            expand_args("home a.test ++ snapshot -i ++ shot").unwrap(),
+            Some(args(
+                "open https://a.test/ ++ snapshot -i ++ screenshot --full"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_expand_keeps_params_whole() {
+        let args = vec![
+            "login".to_string(),
+            "a.test".to_string(),
+            "me".to_string(),
+            "pass word".to_string(),
+        ];
+        let expanded = expand(&args, &aliases()).unwrap().unwrap();
+        assert_eq!(expanded[9], "pass word");
+    }
+
+    #[test]
+    fn test_expand_not_an_alias() {
+        assert_eq!(expand_args("snapshot -i").unwrap(), None);
+        assert_eq!(expand_args("--json").unwrap(), None);
+        // Built-in commands win
+        assert_eq!(expand_args("open other.test").unwrap(), None);
+    }
+
+    #[test]
+    fn test_expand_argument_count() {
+        assert!(expand_args("login example.com")
+            .unwrap_err()
+            .starts_with("Missing arguments for alias: login (expects 3, got 1)"));
+        assert!(expand_args("home a.test b.test")
+            .unwrap_err()
+            .starts_with("Too many arguments for alias: home (expects 1, got 2)"));
+    }
+}
@@ -0,0 +1,328 @@
+//! `auth login <url>`: log in by hand once, then reuse the login headless. A
+//! headed browser opens the login page in a session of its own
+//! (`<session>-login`), so the session's own browser keeps its mode. Once the
+//! user has logged in, which is when the page leaves the login page or
+//! `--success <selector>` appears, its storage state is saved as a named
+//! profile (see `profiles`) and the login browser is closed. `profile use
+//! <name>` then loads the login into any session.
+
+use std::env;
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::gen_id;
+use crate::connection::{self, ensure_daemon, send_command, DaemonConnection, Error, Response};
+use crate::flags::Flags;
+use crate::format;
+use crate::profiles;
+use crate::waiting::Spinner;
+
+const USAGE: &str =
+    "Usage: agent-browser auth login <url> [--save <profile>] [--success <selector>]";
+
+/// How long the user has to log in, unless --timeout says otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often the page is checked
+const POLL: Duration = Duration::from_millis(500);
+
+struct Login {
+    url: String,
+    profile: String,
+    success: Option<String>,
+}
+
+fn parse(args: &[String]) -> Result<Login, String> {
+    match args.first().map(String::as_str) {
+        Some("login") => {}
+        Some(other) => return Err(format!("Unknown subcommand: {}\n{}", other, USAGE)),
+        None => return Err(USAGE.to_string()),
+    }
+    let mut url = None;
+    let mut profile = None;
+    let mut success = None;
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--save" | "--success" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value\n{}", arg, USAGE))?;
+                if arg == "--save" {
+                    profile = Some(value.clone());
+                } else {
+                    success = Some(value.clone());
+                }
+            }
+            _ if url.is_none() => url = Some(arg.clone()),
+            other => return Err(format!("Unexpected argument: {}\n{}", other, USAGE)),
+        }
+    }
+    let url = url.ok_or_else(|| format!("auth login requires a URL\n{}", USAGE))?;
+    // Like open, a bare host is an https URL
+    let url = if url.contains("://") {
+        url
+    } else {
+        format!("https://{}", url)
+    };
+    let profile = match profile {
+        Some(name) => name,
+        None => default_profile(&url)
+            .ok_or_else(|| format!("Name the profile with --save <name>\n{}", USAGE))?,
+    };
+    profiles::validate_name(&profile)?;
+    Ok(Login {
+        url,
+        profile,
+        success,
+    })
+}
+
+/// The profile a login is saved as without --save: the URL's host, e.g.
+/// `app-example-com` for https://app.example.com/login
+fn default_profile(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    let name: String = host
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let name = name.trim_matches('-');
+    (!name.is_empty()).then(|| name.to_ascii_lowercase())
+}
+
+/// A URL without its query and fragment, which logging in needn't change
+fn page(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+fn request(session: &str, mut cmd: Value) -> Result<Value, String> {
+    cmd["id"] = json!(gen_id());
+    let action = cmd["action"].as_str().unwrap_or("").to_string();
+    data(&action, send_command(cmd, session))
+}
+
+/// [`request`] on an open connection
+fn poll(conn: &mut DaemonConnection, mut cmd: Value) -> Result<Value, String> {
+    cmd["id"] = json!(gen_id());
+    let action = cmd["action"].as_str().unwrap_or("").to_string();
+    data(&action, conn.send(&cmd))
+}
+
+fn data(action: &str, resp: Result<Response, Error>) -> Result<Value, String> {
+    let resp = resp.map_err(|e| e.to_string())?;
+    if !resp.success {
+        return Err(resp.error.unwrap_or_else(|| format!("{} failed", action)));
+    }
+    Ok(resp.data.unwrap_or(Value::Null))
+}
+
+/// Start the login session's daemon and a headed browser in it
+fn open(session: &str, flags: &Flags) -> Result<(), String> {
+    ensure_daemon(
+        session,
+        true,
+        flags.browser.as_deref(),
+        flags.executable_path.as_deref(),
+        &flags.extensions,
+        flags.args.as_deref(),
+        flags.user_agent.as_deref(),
+        flags.proxy.as_deref(),
+        flags.proxy_bypass.as_deref(),
+        flags.ignore_https_errors,
+        flags.allow_file_access,
+        None,
+        None,
+        None,
+        None,
+    )?;
+    // A login browser left running headless by something else is relaunched
+    let mut launch = json!({ "action": "launch", "headless": false });
+    if let Some(browser) = &flags.browser {
+        launch["browser"] = json!(browser);
+    }
+    request(session, launch).map(|_| ())
+}
+
+/// Wait until the user has logged in: `success` is visible or, without one,
+/// the page is no longer `start`
+fn wait_for_login(
+    session: &str,
+    start: &str,
+    success: Option<&str>,
+    timeout: Duration,
+) -> Result<String, String> {
+    let deadline = Instant::now() + timeout;
+    let lost = |e: String| format!("Lost the login browser (was its window closed?): {}", e);
+    // Checking on the user isn't a command of theirs, so it isn't rate limited
+    let mut conn = DaemonConnection::open(session).map_err(|e| lost(e.to_string()))?;
+    conn.set_unpaced();
+    loop {
+        let url = poll(&mut conn, json!({ "action": "url" })).map_err(lost)?["url"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        let done = match success {
+            Some(selector) => {
+                poll(
+                    &mut conn,
+                    json!({ "action": "isvisible", "selector": selector }),
+                )
+                .map_err(lost)?["visible"]
+                    == true
+            }
+            None => page(&url) != page(start),
+        };
+        if done {
+            return Ok(url);
+        }
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "Not logged in after {}s (--timeout <ms> to wait longer)",
+                timeout.as_secs()
+            ));
+        }
+        thread::sleep(POLL);
+    }
+}
+
+fn login(args: &[String], flags: &Flags) -> Result<Value, String> {
+    if env::var_os("AGENT_BROWSER_REMOTE").is_some() {
+        return Err("Profiles are files the CLI keeps, so they need a local daemon".to_string());
+    }
+    let login = parse(args)?;
+    let timeout = match &flags.timeout {
+        Some(ms) => ms
+            .parse::<u64>()
+            .map(Duration::from_millis)
+            .map_err(|_| format!("Invalid --timeout: {}", ms))?,
+        None => DEFAULT_TIMEOUT,
+    };
+    let session = format!("{}-login", flags.session);
+    open(&session, flags)?;
+
+    let result = request(&session, json!({ "action": "navigate", "url": login.url }))
+        .and_then(|page| {
+            // Where a login redirect landed, not what was asked for
+            let start = page["url"].as_str().unwrap_or(&login.url).to_string();
+            if !flags.json {
+                eprintln!(
+                    "{} Log in in the browser window; the login is saved when {}",
+                    color::cyan("→"),
+                    match &login.success {
+                        Some(selector) => format!("{} appears", selector),
+                        None => "the page leaves the login page".to_string(),
+                    }
+                );
+            }
+            let _spinner = Spinner::start("you to log in");
+            wait_for_login(&session, &start, login.success.as_deref(), timeout)
+        })
+        .and_then(|url| {
+            // Cookies set while the landing page loads are part of the login
+            let _ = request(
+                &session,
+                json!({ "action": "waitforloadstate", "state": "load" }),
+            );
+            let path = profiles::save_as(&session, &login.profile)?;
+            Ok(json!({
+                "profile": login.profile,
+                "path": path.to_string_lossy(),
+                "url": url,
+            }))
+        });
+
+    // The login browser has done its job either way
+    let pid = connection::daemon_pid(&session);
+    if request(&session, json!({ "action": "close" })).is_ok() {
+        if let Some(pid) = pid {
+            connection::wait_for_shutdown(&session, pid, crate::SHUTDOWN_TIMEOUT);
+        }
+    }
+    result
+}
+
+pub fn run_auth(args: &[String], flags: &Flags) {
+    let data = match login(args, flags) {
+        Ok(data) => data,
+        Err(e) => {
+            if flags.json {
+                format::print(&json!({ "success": false, "error": e }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), e);
+            }
+            exit(1);
+        }
+    };
+    if flags.json {
+        format::print(&json!({ "success": true, "data": data }));
+        return;
+    }
+    let profile = data["profile"].as_str().unwrap_or("");
+    println!(
+        "{} Logged in; saved as profile {}",
+        color::success_indicator(),
+        color::bold(profile)
+    );
+    println!(
+        "{}",
+        color::dim(&format!(
+            "Use it with: agent-browser profile use {}",
+            profile
+        ))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let login = parse(&args("login https://app.example.com/login")).unwrap();
+        assert_eq!(login.url, "https://app.example.com/login");
+        assert_eq!(login.profile, "app-example-com");
+        assert!(login.success.is_none());
+
+        let login = parse(&args("login example.com --save work --success #avatar")).unwrap();
+        assert_eq!(login.url, "https://example.com");
+        assert_eq!(login.profile, "work");
+        assert_eq!(login.success.as_deref(), Some("#avatar"));
+
+        assert!(parse(&args("login")).is_err());
+        assert!(parse(&args("logout example.com")).is_err());
+        assert!(parse(&args("login example.com --save")).is_err());
+        assert!(parse(&args("login example.com --save ../x")).is_err());
+    }
+
+    #[test]
+    fn test_default_profile() {
+        assert_eq!(
+            default_profile("https://user@App.Example.com:8443/login?next=/").unwrap(),
+            "app-example-com"
+        );
+        assert_eq!(
+            default_profile("http://localhost:3000").unwrap(),
+            "localhost"
+        );
+        assert!(default_profile("https:///login").is_none());
+    }
+
+    #[test]
+    fn test_page() {
+        assert_eq!(
+            page("https://example.com/login?next=%2F#top"),
+            "https://example.com/login"
+        );
+        assert_eq!(page("https://example.com/"), "https://example.com/");
+    }
+}
@@ -0,0 +1,248 @@
+//! `agent-browser bench`: how much time the tool itself adds to each step of
+//! an agent loop. Round trips connect to the session's daemon and exchange
+//! `hello`, which every command pays for; snapshots time the current page's
+//! accessibility tree; cold starts spawn a throwaway daemon and wait for its
+//! first answer, which is what the first command of a session pays.
+
+use std::process::{exit, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use agent_browser_client::{Client, SnapshotOptions};
+use serde_json::{json, Map, Value};
+
+use crate::color;
+use crate::connection::{
+    self, cleanup_stale_files, create_private_dir, get_socket_dir, is_daemon_running,
+    remote_address,
+};
+use crate::flags::Flags;
+use crate::format;
+
+/// Round trips and snapshots when --n isn't given
+const DEFAULT_RUNS: usize = 100;
+/// Each cold start is a new Bun process, so a few are enough
+const SPAWN_RUNS: usize = 3;
+/// Longer than this and the daemon counts as failed to start
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a throwaway daemon gets to shut down after `close`
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Nearest-rank percentiles of one measurement
+#[derive(Debug, PartialEq)]
+struct Summary {
+    runs: usize,
+    p50: Duration,
+    p90: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+impl Summary {
+    fn of(mut samples: Vec<Duration>) -> Summary {
+        samples.sort();
+        let rank = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+        Summary {
+            runs: samples.len(),
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: samples[samples.len() - 1],
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let ms = |d: Duration| (d.as_secs_f64() * 10_000.0).round() / 10.0;
+        json!({
+            "runs": self.runs,
+            "p50": ms(self.p50),
+            "p90": ms(self.p90),
+            "p99": ms(self.p99),
+            "max": ms(self.max),
+        })
+    }
+
+    fn print(&self, name: &str) {
+        let ms = |d: Duration| format!("{:.1} ms", d.as_secs_f64() * 1000.0);
+        println!(
+            "{:<18} p50 {:>9}  p90 {:>9}  p99 {:>9}  max {:>9}",
+            format!("{} ({})", name, self.runs),
+            ms(self.p50),
+            ms(self.p90),
+            ms(self.p99),
+            ms(self.max)
+        );
+    }
+}
+
+/// The number of runs from `bench [--n <count>]`
+fn parse_runs(args: &[String]) -> Result<usize, String> {
+    match args {
+        [] => Ok(DEFAULT_RUNS),
+        [flag, value] if flag == "--n" => value
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("--n must be a positive number, got {}", value)),
+        [flag] if flag == "--n" => Err("--n requires a number of runs".to_string()),
+        [other, ..] => Err(format!(
+            "Unexpected argument: {}\nUsage: agent-browser bench [--n <count>]",
+            other
+        )),
+    }
+}
+
+fn time<T>(run: impl FnOnce() -> Result<T, String>) -> Result<Duration, String> {
+    let started = Instant::now();
+    run()?;
+    Ok(started.elapsed())
+}
+
+/// Start a daemon for `session` and time it until it answers `hello`, then
+/// shut it down again
+fn cold_start(session: &str) -> Result<Duration, String> {
+    create_private_dir(&get_socket_dir())
+        .map_err(|e| format!("Failed to create socket directory: {}", e))?;
+    let mut cmd = connection::daemon_command()?;
+    cmd.env("AGENT_BROWSER_DAEMON", "1")
+        .env("AGENT_BROWSER_SESSION", session)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start daemon: {}", e))?;
+    let ready = loop {
+        if Client::connect(session).and_then(|mut c| c.hello()).is_ok() {
+            break Some(started.elapsed());
+        }
+        if started.elapsed() > SPAWN_TIMEOUT || matches!(child.try_wait(), Ok(Some(_))) {
+            break None;
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+
+    // `close` shuts the daemon down; one that doesn't go is killed
+    let _ = Client::connect(session).and_then(|mut c| c.command("close", Value::Null));
+    let closing = Instant::now();
+    while matches!(child.try_wait(), Ok(None)) && closing.elapsed() < SHUTDOWN_TIMEOUT {
+        thread::sleep(Duration::from_millis(20));
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    cleanup_stale_files(session);
+
+    ready.ok_or_else(|| format!("The daemon didn't start within {:?}", SPAWN_TIMEOUT))
+}
+
+/// Time each measurement, handing it to `report` as it completes
+fn bench(
+    runs: usize,
+    session: &str,
+    report: &mut dyn FnMut(&'static str, Summary),
+) -> Result<(), String> {
+    let remote = remote_address().map_err(|e| e.to_string())?.is_some();
+    if !remote && !is_daemon_running(session) {
+        return Err(format!(
+            "No daemon is running for session '{}'. Open the page to measure first: agent-browser open <url>",
+            session
+        ));
+    }
+
+    let round_trips = (0..runs)
+        .map(|_| {
+            time(|| {
+                Client::connect(session)
+                    .and_then(|mut c| c.hello())
+                    .map_err(|e| e.to_string())
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    report("roundTrip", Summary::of(round_trips));
+
+    let mut client = Client::connect(session).map_err(|e| e.to_string())?;
+    let snapshots = (0..runs)
+        .map(|_| {
+            time(|| {
+                client
+                    .snapshot(&SnapshotOptions::default())
+                    .map_err(|e| format!("Snapshot failed: {}", e))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    report("snapshot", Summary::of(snapshots));
+
+    // A remote daemon is started on its own machine
+    if !remote {
+        let session = format!("bench-{}", std::process::id());
+        let starts = (0..SPAWN_RUNS)
+            .map(|_| cold_start(&session))
+            .collect::<Result<Vec<_>, _>>()?;
+        report("coldStart", Summary::of(starts));
+    }
+    Ok(())
+}
+
+pub fn run_bench(args: &[String], flags: &Flags) {
+    let mut results = Map::new();
+    let result = parse_runs(args).and_then(|runs| {
+        bench(runs, &flags.session, &mut |name, summary| {
+            if flags.json {
+                results.insert(name.to_string(), summary.to_json());
+            } else {
+                let label = match name {
+                    "roundTrip" => "Round trip",
+                    "snapshot" => "Snapshot",
+                    _ => "Cold start",
+                };
+                summary.print(label);
+            }
+        })
+    });
+    match result {
+        Ok(()) if flags.json => format::print(&json!({ "success": true, "data": results })),
+        Ok(()) => {}
+        Err(e) => {
+            if flags.json {
+                format::print(&json!({ "success": false, "error": e }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), e);
+            }
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&v| Duration::from_millis(v)).collect()
+    }
+
+    #[test]
+    fn test_summary_percentiles() {
+        let summary = Summary::of(ms(&(1..=100).rev().collect::<Vec<_>>()));
+        assert_eq!(summary.runs, 100);
+        assert_eq!(summary.p50, Duration::from_millis(50));
+        assert_eq!(summary.p90, Duration::from_millis(90));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+        assert_eq!(summary.max, Duration::from_millis(100));
+
+        let single = Summary::of(ms(&[7]));
+        assert_eq!(ms(&[7, 7, 7]), vec![single.p50, single.p99, single.max]);
+    }
+
+    #[test]
+    fn test_parse_runs() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(parse_runs(&[]), Ok(DEFAULT_RUNS));
+        assert_eq!(parse_runs(&args("--n 20")), Ok(20));
+        assert!(parse_runs(&args("--n 0")).is_err());
+        assert!(parse_runs(&args("--n")).is_err());
+        assert!(parse_runs(&args("20")).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use serde_json::json;
+
+use crate::color;
+use crate::commands::{parse_command, secret_value};
+use crate::connection::{live_sessions, send_command, Error, Response};
+use crate::exit_code;
+use crate::flags::Flags;
+use crate::format;
+use crate::lock;
+use crate::output::print_response;
+
+/// A session's answer, with connection errors turned into failed responses
+/// so every session gets an entry
+fn into_response(result: Result<Response, Error>, secret: Option<&str>) -> Response {
+    match result {
+        Ok(mut resp) => {
+            if let Some(secret) = secret {
+                resp.redact(secret);
+            }
+            resp
+        }
+        Err(e) => Response {
+            success: false,
+            error: Some(e.to_string()),
+            error_code: exit_code::classify_error(None, &e),
+            ..Default::default()
+        },
+    }
+}
+
+/// Run one command against every live session (`--all-sessions` or
+/// `session each -- <command>`). Sessions are not started. With `--json` the
+/// results are printed as one array of `{session, success, data, error,
+/// errorCode, errorContext}`.
+/// Returns whether the command succeeded in every session.
+pub fn run(args: &[String], flags: &Flags) -> bool {
+    let cmd = match parse_command(args, flags) {
+        Ok(cmd) => cmd,
+        Err(e) => return fail(&e.format(), flags.json),
+    };
+    let streams = cmd.get("follow").and_then(|v| v.as_bool()) == Some(true);
+    let to_stdout = cmd.get("stdout").and_then(|v| v.as_bool()) == Some(true);
+    if streams || to_stdout || cmd.get("diff").is_some() {
+        return fail(
+            "--follow, --diff and --stdout are not available across sessions",
+            flags.json,
+        );
+    }
+    let lock_timeout = match lock::parse_timeout(flags.lock_timeout.as_deref()) {
+        Ok(timeout) => timeout,
+        Err(e) => return fail(&e, flags.json),
+    };
+    let action = cmd.get("action").and_then(|v| v.as_str());
+    let secret = secret_value(&cmd);
+
+    let sessions = live_sessions();
+    if sessions.is_empty() && !flags.json {
+        println!("No active sessions");
+        return true;
+    }
+
+    let mut results = Vec::new();
+    let mut all_ok = true;
+    for session in &sessions {
+        let mut resp = if flags.lock == Some(true) {
+            match lock::acquire(session, lock_timeout) {
+                Ok(_lock) => into_response(send_command(cmd.clone(), session), secret),
+                Err(e) => into_response(Err(Error::Command(e)), None),
+            }
+        } else {
+            into_response(send_command(cmd.clone(), session), secret)
+        };
+        if !resp.success {
+            resp.error_code = exit_code::classify(action, &resp);
+        }
+        all_ok &= resp.success && !crate::predicate_failed(action, &resp);
+        if flags.json {
+            let entry = json!({
+                "session": session,
+                "success": resp.success,
+                "data": resp.data,
+                "error": resp.error,
+                "errorCode": resp.error_code,
+                "errorContext": resp.error_context,
+            });
+            // jsonl prints each session's result as soon as it answers
+            if format::is_streaming() {
+                format::print(&entry);
+            } else {
+                results.push(entry);
+            }
+        } else {
+            println!("{}", color::bold(&format!("[{}]", session)));
+            print_response(&resp, false, action);
+        }
+    }
+
+    if flags.json && !format::is_streaming() {
+        format::print(&results);
+    }
+    all_ok
+}
+
+fn fail(message: &str, json_mode: bool) -> bool {
+    if json_mode {
+        format::print(&json!({ "success": false, "error": message }));
+    } else {
+        eprintln!("{} {}", color::error_indicator(), message);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_response_redacts() {
+        let resp = Response {
+            success: true,
+            data: Some(json!({ "url": "https://example.com/?key=hunter2" })),
+            ..Default::default()
+        };
+        let resp = into_response(Ok(resp), Some("hunter2"));
+        assert!(resp.success);
+        assert_eq!(
+            resp.data.unwrap()["url"],
+            "https://example.com/?key=********"
+        );
+    }
+
+    #[test]
+    fn test_into_response_connection_error() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        let resp = into_response(Err(Error::Connect(refused)), None);
+        assert!(!resp.success);
+        assert!(resp.error.unwrap().starts_with("Failed to connect"));
+    }
+}
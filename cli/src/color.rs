@@ -1,15 +1,25 @@
 //! Color output utilities respecting NO_COLOR environment variable.
 //!
 //! When the NO_COLOR environment variable is present (regardless of value),
-//! all color formatting is disabled per https://no-color.org/
+//! all color formatting is disabled per https://no-color.org/. So it is with
+//! `--no-color`, and when stdout is not a terminal, so piped output and logs
+//! carry no escape codes.
 
 use std::env;
+use std::io::{self, IsTerminal};
 use std::sync::OnceLock;
 
-/// Returns true if color output is enabled (NO_COLOR is NOT set)
+static COLORS_ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Returns true if color output is enabled (NO_COLOR is NOT set and stdout is a terminal)
 pub fn is_enabled() -> bool {
-    static COLORS_ENABLED: OnceLock<bool> = OnceLock::new();
-    *COLORS_ENABLED.get_or_init(|| env::var("NO_COLOR").is_err())
+    *COLORS_ENABLED.get_or_init(|| env::var("NO_COLOR").is_err() && io::stdout().is_terminal())
+}
+
+/// Turn color off for the rest of the process (`--no-color`). Must be called
+/// before anything is colored.
+pub fn disable() {
+    let _ = COLORS_ENABLED.set(false);
 }
 
 /// Format text in red (errors)
@@ -1,8 +1,15 @@
+use agent_browser_client::protocol::{
+    Clip, Command, ImageFormat, NavigateTarget, SnapshotFilter, SnapshotFormat,
+};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use serde_json::{json, Value};
 use std::io::{self, BufRead};
 
+use crate::diff;
 use crate::flags::Flags;
+use crate::form;
+use crate::plugins;
+use crate::spec;
 
 /// Error type for command parsing with contextual information
 #[derive(Debug)]
@@ -24,6 +31,12 @@ pub enum ParseError {
         message: String,
         usage: &'static str,
     },
+    /// Command exists but doesn't take this flag
+    UnknownFlag {
+        flag: String,
+        command: String,
+        valid_options: &'static [&'static str],
+    },
 }
 
 impl ParseError {
@@ -51,22 +64,153 @@ impl ParseError {
             ParseError::InvalidValue { message, usage } => {
                 format!("{}\nUsage: agent-browser {}", message, usage)
             }
+            ParseError::UnknownFlag {
+                flag,
+                command,
+                valid_options,
+            } => {
+                let valid = if valid_options.is_empty() {
+                    "none besides the global flags".to_string()
+                } else {
+                    valid_options.join(", ")
+                };
+                format!(
+                    "Unknown flag for {}: {}\nValid flags: {}\nPut text that starts with - after a -- separator",
+                    command, flag, valid
+                )
+            }
         }
     }
 }
 
+/// An id for a request, unique among every CLI process talking to the daemon
 pub fn gen_id() -> String {
-    format!(
-        "r{}",
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_micros()
-            % 1000000
-    )
+    agent_browser_client::protocol::request_id("r")
 }
 
+/// Resolve a path against the CLI's working directory. The daemon keeps the
+/// working directory it was started from, so relative paths must be made
+/// absolute before they are sent.
+fn absolute_path(path: &str) -> String {
+    let p = std::path::Path::new(path);
+    if p.is_absolute() {
+        return path.to_string();
+    }
+    std::env::current_dir()
+        .map(|cwd| cwd.join(p).to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Actions with their own `timeout` for the operation. `--timeout` fills it
+/// in, so Playwright gives up at the same time as the daemon.
+const TIMEOUT_ACTIONS: &[&str] = &[
+    "navigate",
+    "wait",
+    "waitforurl",
+    "waitforloadstate",
+    "waitforfunction",
+    "waitfordownload",
+    "waitforrequest",
+    "waitforresponse",
+    "waitforspaidle",
+    "responsebody",
+    "expect",
+    "watch",
+];
+
+/// Interactions `--retry` applies to: they act on an element, which a
+/// re-render can swap out between finding it and acting on it.
+const RETRY_ACTIONS: &[&str] = &[
+    "click",
+    "dblclick",
+    "fill",
+    "type",
+    "hover",
+    "focus",
+    "clear",
+    "check",
+    "uncheck",
+    "select",
+    "drag",
+    "upload",
+    "press",
+    "tap",
+    "scrollintoview",
+    "fillform",
+    "getbyname",
+    "getbyrole",
+    "getbytext",
+    "getbylabel",
+    "getbyplaceholder",
+    "getbyalttext",
+    "getbytitle",
+    "getbytestid",
+    "nth",
+];
+
+/// Most `--retry` allows, matching the daemon's limit
+const MAX_RETRY: u32 = 10;
+
 pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError> {
+    if let Some(command) = args.first() {
+        if let Some(flag) = spec::unknown_flag(command, args) {
+            return Err(ParseError::UnknownFlag {
+                flag: flag.to_string(),
+                command: command.clone(),
+                valid_options: spec::flags(command).unwrap_or_default(),
+            });
+        }
+    }
+    // `--` only ends the flags; the command never sees it
+    let args: Vec<String> = match args.iter().position(|arg| arg == "--") {
+        Some(i) => [&args[..i], &args[i + 1..]].concat(),
+        None => args.to_vec(),
+    };
+    let mut cmd = parse_action(&args, flags)?;
+    if let Some(ref value) = flags.timeout {
+        const USAGE: &str = "<command> [args...] --timeout <ms>";
+        let (_, timeout) = take_timeout(&["--timeout", value], USAGE)?;
+        if let (Some(ms), Some(obj)) = (timeout, cmd.as_object_mut()) {
+            // Absolute, so time spent reaching the daemon counts against it too
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+            obj.insert("deadline".to_string(), json!(now + ms));
+            let action = obj.get("action").and_then(|v| v.as_str()).unwrap_or("");
+            if TIMEOUT_ACTIONS.contains(&action) && !obj.contains_key("timeout") {
+                obj.insert("timeout".to_string(), json!(ms));
+            }
+        }
+    }
+    if let Some(ref value) = flags.retry {
+        let retry = value
+            .parse::<u32>()
+            .ok()
+            .filter(|&n| n <= MAX_RETRY)
+            .ok_or_else(|| ParseError::InvalidValue {
+                message: format!(
+                    "--retry must be a number from 0 to {}, got '{}'",
+                    MAX_RETRY, value
+                ),
+                usage: "<command> [args...] --retry <n>",
+            })?;
+        if let Some(obj) = cmd.as_object_mut() {
+            let action = obj.get("action").and_then(|v| v.as_str()).unwrap_or("");
+            if retry > 0 && RETRY_ACTIONS.contains(&action) {
+                obj.insert("retry".to_string(), json!(retry));
+            }
+        }
+    }
+    if flags.timings {
+        if let Some(obj) = cmd.as_object_mut() {
+            obj.insert("timings".to_string(), json!(true));
+        }
+    }
+    Ok(cmd)
+}
+
+fn parse_action(args: &[String], flags: &Flags) -> Result<Value, ParseError> {
     if args.is_empty() {
         return Err(ParseError::MissingArguments {
             context: "".to_string(),
@@ -80,131 +224,148 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
     match cmd {
         // === Navigation ===
-        "open" | "goto" | "navigate" => {
-            let url = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: cmd.to_string(),
-                usage: "open <url>",
-            })?;
-            let url_lower = url.to_lowercase();
-            let url = if url_lower.starts_with("http://")
-                || url_lower.starts_with("https://")
-                || url_lower.starts_with("about:")
-                || url_lower.starts_with("data:")
-                || url_lower.starts_with("file:")
-            {
-                url.to_string()
-            } else {
-                format!("https://{}", url)
-            };
-            let mut nav_cmd = json!({ "id": id, "action": "navigate", "url": url });
-            // If --headers flag is set, include headers (scoped to this origin)
-            if let Some(ref headers_json) = flags.headers {
-                if let Ok(headers) = serde_json::from_str::<serde_json::Value>(headers_json) {
-                    nav_cmd["headers"] = headers;
-                }
-            }
-            // Include iOS device info if specified (needed for auto-launch with existing daemon)
-            if flags.provider.as_deref() == Some("ios") {
-                if let Some(ref device) = flags.device {
-                    nav_cmd["iosDevice"] = json!(device);
-                }
-            }
-            Ok(nav_cmd)
-        }
-        "back" => Ok(json!({ "id": id, "action": "back" })),
-        "forward" => Ok(json!({ "id": id, "action": "forward" })),
-        "reload" => Ok(json!({ "id": id, "action": "reload" })),
+        "open" | "goto" | "navigate" => parse_open(&rest, &id, flags, cmd),
+        "back" => Ok(Command::Back.into_request(id)),
+        "forward" => Ok(Command::Forward.into_request(id)),
+        "reload" => Ok(Command::Reload.into_request(id)),
 
         // === Core Actions ===
+        "click" | "hover" if rest.contains(&"--text") => parse_by_name(&rest, &id, cmd),
         "click" => {
+            const USAGE: &str = "click <selector> [--nth <n> | --all]";
+            let (rest, pick) = take_pick(&rest, USAGE)?;
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "click".to_string(),
-                usage: "click <selector>",
+                usage: USAGE,
             })?;
-            Ok(json!({ "id": id, "action": "click", "selector": sel }))
+            Ok(pick.apply(Command::click(*sel).into_request(id)))
         }
         "dblclick" => {
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "dblclick".to_string(),
                 usage: "dblclick <selector>",
             })?;
-            Ok(json!({ "id": id, "action": "dblclick", "selector": sel }))
-        }
-        "fill" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "fill".to_string(),
-                usage: "fill <selector> <text>",
-            })?;
-            Ok(json!({ "id": id, "action": "fill", "selector": sel, "value": rest[1..].join(" ") }))
+            let selector = sel.to_string();
+            Ok(Command::Dblclick { selector }.into_request(id))
         }
+        "fill" => parse_fill(&rest, &id),
         "type" => {
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "type".to_string(),
                 usage: "type <selector> <text>",
             })?;
-            Ok(json!({ "id": id, "action": "type", "selector": sel, "text": rest[1..].join(" ") }))
+            Ok(Command::type_text(*sel, rest[1..].join(" ")).into_request(id))
         }
         "hover" => {
+            const USAGE: &str = "hover <selector> [--nth <n> | --all]";
+            let (rest, pick) = take_pick(&rest, USAGE)?;
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "hover".to_string(),
-                usage: "hover <selector>",
+                usage: USAGE,
             })?;
-            Ok(json!({ "id": id, "action": "hover", "selector": sel }))
+            let selector = sel.to_string();
+            Ok(pick.apply(Command::Hover { selector }.into_request(id)))
         }
         "focus" => {
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "focus".to_string(),
                 usage: "focus <selector>",
             })?;
-            Ok(json!({ "id": id, "action": "focus", "selector": sel }))
+            let selector = sel.to_string();
+            Ok(Command::Focus { selector }.into_request(id))
+        }
+        "blur" => {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
+                context: "blur".to_string(),
+                usage: "blur <selector>",
+            })?;
+            let selector = sel.to_string();
+            Ok(Command::Blur { selector }.into_request(id))
+        }
+        "clear" => {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
+                context: "clear".to_string(),
+                usage: "clear <selector>",
+            })?;
+            let selector = sel.to_string();
+            Ok(Command::Clear { selector }.into_request(id))
         }
         "check" => {
+            const USAGE: &str = "check <selector> [--nth <n> | --all]";
+            let (rest, pick) = take_pick(&rest, USAGE)?;
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "check".to_string(),
-                usage: "check <selector>",
+                usage: USAGE,
             })?;
-            Ok(json!({ "id": id, "action": "check", "selector": sel }))
+            let selector = sel.to_string();
+            Ok(pick.apply(Command::Check { selector }.into_request(id)))
         }
         "uncheck" => {
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "uncheck".to_string(),
                 usage: "uncheck <selector>",
             })?;
-            Ok(json!({ "id": id, "action": "uncheck", "selector": sel }))
+            let selector = sel.to_string();
+            Ok(Command::Uncheck { selector }.into_request(id))
         }
-        "select" => {
+        "select" => parse_select(&rest, &id),
+        "drag" => parse_drag(&rest, &id),
+        "upload" => {
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "select".to_string(),
-                usage: "select <selector> <value...>",
-            })?;
-            let _val = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "select".to_string(),
-                usage: "select <selector> <value...>",
+                context: "upload".to_string(),
+                usage: "upload <selector> <files...>",
             })?;
-            let values = &rest[1..];
-            if values.len() == 1 {
-                Ok(json!({ "id": id, "action": "select", "selector": sel, "values": values[0] }))
-            } else {
-                Ok(json!({ "id": id, "action": "select", "selector": sel, "values": values }))
+            if rest.len() < 2 {
+                return Err(ParseError::MissingArguments {
+                    context: "upload".to_string(),
+                    usage: "upload <selector> <files...>",
+                });
+            }
+            // Fail before contacting the daemon if any file is missing
+            let missing: Vec<&str> = rest[1..]
+                .iter()
+                .copied()
+                .filter(|f| !std::path::Path::new(f).is_file())
+                .collect();
+            if !missing.is_empty() {
+                return Err(ParseError::InvalidValue {
+                    message: format!("File not found: {}", missing.join(", ")),
+                    usage: "upload <selector> <files...>",
+                });
             }
+            let files: Vec<String> = rest[1..].iter().map(|f| absolute_path(f)).collect();
+            Ok(json!({ "id": id, "action": "upload", "selector": sel, "files": files }))
         }
-        "drag" => {
-            let src = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "drag".to_string(),
-                usage: "drag <source> <target>",
-            })?;
-            let tgt = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "drag".to_string(),
-                usage: "drag <source> <target>",
-            })?;
-            Ok(json!({ "id": id, "action": "drag", "source": src, "target": tgt }))
+        "download" if rest.first() == Some(&"wait") => {
+            let mut cmd = json!({ "id": id, "action": "waitfordownload" });
+            let mut i = 1;
+            while i < rest.len() {
+                match rest[i] {
+                    "--timeout" => {
+                        let ms = rest
+                            .get(i + 1)
+                            .and_then(|s| s.parse::<u64>().ok())
+                            .ok_or_else(|| ParseError::InvalidValue {
+                                message: "--timeout requires a number of milliseconds".to_string(),
+                                usage: "download wait [path] [--timeout <ms>]",
+                            })?;
+                        cmd["timeout"] = json!(ms);
+                        i += 2;
+                    }
+                    path => {
+                        cmd["path"] = json!(absolute_path(path));
+                        i += 1;
+                    }
+                }
+            }
+            Ok(cmd)
         }
-        "upload" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "upload".to_string(),
-                usage: "upload <selector> <files...>",
+        "download" if rest.first() == Some(&"dir") => {
+            let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "download dir".to_string(),
+                usage: "download dir <path>",
             })?;
-            Ok(json!({ "id": id, "action": "upload", "selector": sel, "files": &rest[1..] }))
+            Ok(json!({ "id": id, "action": "download_dir", "path": absolute_path(path) }))
         }
         "download" => {
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
@@ -219,253 +380,200 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         }
 
         // === Keyboard ===
-        "press" | "key" => {
-            let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "press".to_string(),
-                usage: "press <key>",
-            })?;
-            Ok(json!({ "id": id, "action": "press", "key": key }))
-        }
+        "press" | "key" => parse_press(&rest, &id),
         "keydown" => {
             let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "keydown".to_string(),
                 usage: "keydown <key>",
             })?;
-            Ok(json!({ "id": id, "action": "keydown", "key": key }))
+            let key = normalize_key(key);
+            Ok(Command::Keydown { key }.into_request(id))
         }
         "keyup" => {
             let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "keyup".to_string(),
                 usage: "keyup <key>",
             })?;
-            Ok(json!({ "id": id, "action": "keyup", "key": key }))
+            let key = normalize_key(key);
+            Ok(Command::Keyup { key }.into_request(id))
+        }
+        "keys" => {
+            const VALID: &[&str] = &["down", "up"];
+            match rest.first().copied() {
+                Some(dir @ ("down" | "up")) => {
+                    let key = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                        context: format!("keys {}", dir),
+                        usage: "keys <down|up> <key>",
+                    })?;
+                    let key = normalize_key(key);
+                    let cmd = if dir == "down" {
+                        Command::Keydown { key }
+                    } else {
+                        Command::Keyup { key }
+                    };
+                    Ok(cmd.into_request(id))
+                }
+                Some(sub) => Err(ParseError::UnknownSubcommand {
+                    subcommand: sub.to_string(),
+                    valid_options: VALID,
+                }),
+                None => Err(ParseError::MissingArguments {
+                    context: "keys".to_string(),
+                    usage: "keys <down|up> <key>",
+                }),
+            }
         }
 
         // === Scroll ===
-        "scroll" => {
-            let dir = rest.get(0).unwrap_or(&"down");
-            let amount = rest
-                .get(1)
-                .and_then(|s| s.parse::<i32>().ok())
-                .unwrap_or(300);
-            Ok(json!({ "id": id, "action": "scroll", "direction": dir, "amount": amount }))
-        }
+        "scroll" => parse_scroll(&rest, &id),
         "scrollintoview" | "scrollinto" => {
             let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
                 context: "scrollintoview".to_string(),
                 usage: "scrollintoview <selector>",
             })?;
-            Ok(json!({ "id": id, "action": "scrollintoview", "selector": sel }))
+            let selector = sel.to_string();
+            Ok(Command::Scrollintoview { selector }.into_request(id))
         }
 
         // === Wait ===
-        "wait" => {
-            // Check for --url flag: wait --url "**/dashboard"
-            if let Some(idx) = rest.iter().position(|&s| s == "--url" || s == "-u") {
-                let url = rest
-                    .get(idx + 1)
-                    .ok_or_else(|| ParseError::MissingArguments {
-                        context: "wait --url".to_string(),
-                        usage: "wait --url <pattern>",
-                    })?;
-                return Ok(json!({ "id": id, "action": "waitforurl", "url": url }));
-            }
-
-            // Check for --load flag: wait --load networkidle
-            if let Some(idx) = rest.iter().position(|&s| s == "--load" || s == "-l") {
-                let state = rest
-                    .get(idx + 1)
-                    .ok_or_else(|| ParseError::MissingArguments {
-                        context: "wait --load".to_string(),
-                        usage: "wait --load <state>",
-                    })?;
-                return Ok(json!({ "id": id, "action": "waitforloadstate", "state": state }));
-            }
-
-            // Check for --fn flag: wait --fn "window.ready === true"
-            if let Some(idx) = rest.iter().position(|&s| s == "--fn" || s == "-f") {
-                let expr = rest
-                    .get(idx + 1)
-                    .ok_or_else(|| ParseError::MissingArguments {
-                        context: "wait --fn".to_string(),
-                        usage: "wait --fn <expression>",
-                    })?;
-                return Ok(json!({ "id": id, "action": "waitforfunction", "expression": expr }));
-            }
-
-            // Check for --text flag: wait --text "Welcome"
-            if let Some(idx) = rest.iter().position(|&s| s == "--text" || s == "-t") {
-                let text = rest
-                    .get(idx + 1)
-                    .ok_or_else(|| ParseError::MissingArguments {
-                        context: "wait --text".to_string(),
-                        usage: "wait --text <text>",
-                    })?;
-                // Use getByText locator to wait for text to appear
-                return Ok(
-                    json!({ "id": id, "action": "wait", "selector": format!("text={}", text) }),
-                );
-            }
-
-            // Check for --download flag: wait --download [path] [--timeout ms]
-            if rest.iter().any(|&s| s == "--download" || s == "-d") {
-                let mut cmd = json!({ "id": id, "action": "waitfordownload" });
-                // Check for optional path (first non-flag argument after --download)
-                let download_idx = rest
-                    .iter()
-                    .position(|&s| s == "--download" || s == "-d")
-                    .unwrap();
-                if let Some(path) = rest.get(download_idx + 1) {
-                    if !path.starts_with("--") {
-                        cmd["path"] = json!(path);
-                    }
-                }
-                // Check for optional timeout
-                if let Some(idx) = rest.iter().position(|&s| s == "--timeout") {
-                    if let Some(timeout_str) = rest.get(idx + 1) {
-                        if let Ok(timeout) = timeout_str.parse::<u64>() {
-                            cmd["timeout"] = json!(timeout);
-                        }
-                    }
-                }
-                return Ok(cmd);
-            }
-
-            // Default: selector or timeout
-            if let Some(arg) = rest.get(0) {
-                if arg.parse::<u64>().is_ok() {
-                    Ok(
-                        json!({ "id": id, "action": "wait", "timeout": arg.parse::<u64>().unwrap() }),
-                    )
-                } else {
-                    Ok(json!({ "id": id, "action": "wait", "selector": arg }))
-                }
-            } else {
-                Err(ParseError::MissingArguments {
-                    context: "wait".to_string(),
-                    usage: "wait <selector|ms|--url|--load|--fn|--text>",
-                })
-            }
-        }
+        "wait" => parse_wait(&rest, &id),
+        "expect" => parse_expect(&rest, &id),
 
         // === Screenshot/PDF ===
-        "screenshot" => {
-            // screenshot [selector] [path]
-            // selector: @ref or CSS selector
-            // path: file path (contains / or . or ends with known extension)
-            let (selector, path) = match (rest.get(0), rest.get(1)) {
-                (Some(first), Some(second)) => {
-                    // Two args: first is selector, second is path
-                    (Some(*first), Some(*second))
-                }
-                (Some(first), None) => {
-                    // One arg: determine if it's a selector or a path
-                    let is_relative_path = first.starts_with("./") || first.starts_with("../");
-                    let is_selector = !is_relative_path
-                        && (first.starts_with('.')
-                            || first.starts_with('#')
-                            || first.starts_with('@'));
-                    let has_path_extension = first.ends_with(".png")
-                        || first.ends_with(".jpg")
-                        || first.ends_with(".jpeg")
-                        || first.ends_with(".webp");
-                    let is_path = is_relative_path || first.contains('/') || has_path_extension;
-                    if is_selector || !is_path {
-                        (Some(*first), None)
-                    } else {
-                        (None, Some(*first))
-                    }
-                }
-                _ => (None, None),
-            };
-            Ok(
-                json!({ "id": id, "action": "screenshot", "path": path, "selector": selector, "fullPage": flags.full }),
-            )
-        }
-        "pdf" => {
-            let path = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "pdf".to_string(),
-                usage: "pdf <path>",
-            })?;
-            Ok(json!({ "id": id, "action": "pdf", "path": path }))
+        "screenshot" if rest.first() == Some(&"diff") => {
+            parse_screenshot_diff(&rest[1..], &id, flags)
         }
+        "screenshot" => parse_screenshot(&rest, &id, flags),
+        "pdf" => parse_pdf(&rest, &id),
+        "save" => parse_save(&rest, &id),
+        "read" => parse_read(&rest, &id),
 
         // === Snapshot ===
         "snapshot" => {
-            let mut cmd = json!({ "id": id, "action": "snapshot" });
-            let obj = cmd.as_object_mut().unwrap();
+            // `snapshot diff` takes the same filters and compares against the last snapshot
+            let (is_diff, rest) = match rest.split_first() {
+                Some((&"diff", tail)) => (true, tail),
+                _ => (false, &rest[..]),
+            };
+            let mut filter = SnapshotFilter::default();
+            let mut format = None;
+            let mut max_chars = None;
+            let mut page = None;
+            let mut coords = false;
+            let mut if_changed = None;
             let mut i = 0;
             while i < rest.len() {
                 match rest[i] {
-                    "-i" | "--interactive" => {
-                        obj.insert("interactive".to_string(), json!(true));
-                    }
-                    "-c" | "--compact" => {
-                        obj.insert("compact".to_string(), json!(true));
-                    }
-                    "-C" | "--cursor" => {
-                        obj.insert("cursor".to_string(), json!(true));
-                    }
+                    "-i" | "--interactive" => filter.interactive = true,
+                    "-c" | "--compact" => filter.compact = true,
+                    "-C" | "--cursor" => filter.cursor = true,
                     "-d" | "--depth" => {
                         if let Some(d) = rest.get(i + 1) {
-                            if let Ok(n) = d.parse::<i32>() {
-                                obj.insert("maxDepth".to_string(), json!(n));
+                            if let Ok(n) = d.parse::<u32>() {
+                                filter.max_depth = Some(n);
                                 i += 1;
                             }
                         }
                     }
                     "-s" | "--selector" => {
                         if let Some(s) = rest.get(i + 1) {
-                            obj.insert("selector".to_string(), json!(s));
+                            filter.selector = Some(s.to_string());
                             i += 1;
                         }
                     }
+                    "--format" | "--max-chars" | "--page" | "--coords" | "--if-changed"
+                        if is_diff =>
+                    {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("snapshot diff does not support {}", rest[i]),
+                            usage: "snapshot diff [-i] [-c] [-C] [-d <n>] [-s <sel>]",
+                        });
+                    }
+                    "--coords" => coords = true,
+                    "--if-changed" => {
+                        let hash = rest
+                            .get(i + 1)
+                            .ok_or_else(|| ParseError::MissingArguments {
+                                context: "snapshot --if-changed".to_string(),
+                                usage: "snapshot [options] --if-changed <hash>",
+                            })?;
+                        if_changed = Some(hash.to_string());
+                        i += 1;
+                    }
+                    "--format" => {
+                        let value = json!(rest.get(i + 1).copied().unwrap_or(""));
+                        let parsed = serde_json::from_value(value).map_err(|_| {
+                            ParseError::InvalidValue {
+                                message: "--format must be text, json, yaml or md".to_string(),
+                                usage: "snapshot --format <text|json|yaml|md>",
+                            }
+                        })?;
+                        format = Some(parsed);
+                        i += 1;
+                    }
+                    flag @ ("--max-chars" | "--page") => {
+                        let n = rest
+                            .get(i + 1)
+                            .and_then(|s| s.parse::<u32>().ok())
+                            .filter(|&n| n > 0)
+                            .ok_or_else(|| ParseError::InvalidValue {
+                                message: format!("{} requires a positive number", flag),
+                                usage: "snapshot [--max-chars <n>] [--page <k>]",
+                            })?;
+                        if flag == "--page" {
+                            page = Some(n);
+                        } else {
+                            max_chars = Some(n);
+                        }
+                        i += 1;
+                    }
                     _ => {}
                 }
                 i += 1;
             }
-            Ok(cmd)
-        }
-
-        // === Eval ===
-        "eval" => {
-            // Check for flags: -b/--base64 or --stdin
-            let (is_base64, is_stdin, script_parts): (bool, bool, &[&str]) =
-                if rest.first() == Some(&"-b") || rest.first() == Some(&"--base64") {
-                    (true, false, &rest[1..])
-                } else if rest.first() == Some(&"--stdin") {
-                    (false, true, &rest[1..])
-                } else {
-                    (false, false, rest.as_slice())
-                };
-
-            let script = if is_stdin {
-                // Read script from stdin
-                let stdin = io::stdin();
-                let lines: Vec<String> = stdin.lock().lines()
-                    .map(|l| l.unwrap_or_default())
-                    .collect();
-                lines.join("\n")
+            let paged = max_chars.is_some() || page.is_some();
+            if paged && format == Some(SnapshotFormat::Json) {
+                return Err(ParseError::InvalidValue {
+                    message: "--max-chars and --page apply to text, yaml and md output".to_string(),
+                    usage: "snapshot [--format text|yaml|md] [--max-chars <n>] [--page <k>]",
+                });
+            }
+            let cmd = if is_diff {
+                Command::SnapshotDiff { filter }
             } else {
-                let raw_script = script_parts.join(" ");
-                if is_base64 {
-                    let decoded = STANDARD.decode(&raw_script).map_err(|_| ParseError::InvalidValue {
-                        message: "Invalid base64 encoding".to_string(),
-                        usage: "eval -b <base64-encoded-script>",
-                    })?;
-                    String::from_utf8(decoded).map_err(|_| ParseError::InvalidValue {
-                        message: "Base64 decoded to invalid UTF-8".to_string(),
-                        usage: "eval -b <base64-encoded-script>",
-                    })?
-                } else {
-                    raw_script
+                Command::Snapshot {
+                    filter,
+                    format,
+                    max_chars,
+                    page,
+                    coords,
+                    if_changed,
                 }
             };
-            Ok(json!({ "id": id, "action": "evaluate", "script": script }))
+            Ok(cmd.into_request(id))
         }
+        "refs" => match rest.as_slice() {
+            [] => Ok(json!({ "id": id, "action": "refs" })),
+            ["--filter", filter @ ..] if !filter.is_empty() => {
+                Ok(json!({ "id": id, "action": "refs", "filter": filter.join(" ") }))
+            }
+            ["--filter"] => Err(ParseError::MissingArguments {
+                context: "refs --filter".to_string(),
+                usage: "refs [--filter <text>]",
+            }),
+            _ => Err(ParseError::InvalidValue {
+                message: format!("Unknown refs option: {}", rest[0]),
+                usage: "refs [--filter <text>]",
+            }),
+        },
+        "visible-elements" => parse_visible_elements(&rest, &id),
+
+        // === Eval ===
+        "eval" => parse_eval(&rest, &id),
+        "raw" => parse_raw(&rest, &id),
 
         // === Close ===
-        "close" | "quit" | "exit" => Ok(json!({ "id": id, "action": "close" })),
+        "close" | "quit" | "exit" => parse_close(&rest, &id),
 
         // === Connect (CDP) ===
         "connect" => {
@@ -515,6 +623,16 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Get ===
         "get" => parse_get(&rest, &id),
+        "links" => parse_links(&rest, &id),
+        "forms" => match rest.first() {
+            Some(arg) => Err(ParseError::InvalidValue {
+                message: format!("Unexpected argument: {}", arg),
+                usage: "forms",
+            }),
+            None => Ok(json!({ "id": id, "action": "forms" })),
+        },
+        "table" => parse_table(&rest, &id),
+        "audit" => parse_audit(&rest, &id),
 
         // === Is (state checks) ===
         "is" => parse_is(&rest, &id),
@@ -530,6 +648,56 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Network ===
         "network" => parse_network(&rest, &id),
+        "mock" => parse_mock(&rest, &id),
+        "fetch" => parse_fetch(&rest, &id),
+        "headers" => {
+            const VALID: &[&str] = &["set", "clear"];
+            match rest.first().copied() {
+                Some("set") => {
+                    let (name, value) = match (rest.get(1), rest.get(2..)) {
+                        (Some(name), Some(value)) if !value.is_empty() => (name, value.join(" ")),
+                        _ => {
+                            return Err(ParseError::MissingArguments {
+                                context: "headers set".to_string(),
+                                usage: "headers set <name> <value>",
+                            })
+                        }
+                    };
+                    Ok(json!({
+                        "id": id,
+                        "action": "headers",
+                        "headers": { *name: value },
+                        "merge": true,
+                    }))
+                }
+                Some("clear") => Ok(json!({ "id": id, "action": "headers", "headers": {} })),
+                Some(sub) => Err(ParseError::UnknownSubcommand {
+                    subcommand: sub.to_string(),
+                    valid_options: VALID,
+                }),
+                None => Err(ParseError::MissingArguments {
+                    context: "headers".to_string(),
+                    usage: "headers <set|clear> [name] [value]",
+                }),
+            }
+        }
+        "block" => {
+            if rest.is_empty() {
+                return Err(ParseError::MissingArguments {
+                    context: "block".to_string(),
+                    usage: "block <pattern>...",
+                });
+            }
+            Ok(json!({ "id": id, "action": "block", "patterns": rest }))
+        }
+        "unblock" => match rest.as_slice() {
+            [] => Err(ParseError::MissingArguments {
+                context: "unblock".to_string(),
+                usage: "unblock <pattern>... | unblock --all",
+            }),
+            ["--all"] => Ok(json!({ "id": id, "action": "unblock" })),
+            patterns => Ok(json!({ "id": id, "action": "unblock", "patterns": patterns })),
+        },
 
         // === Storage ===
         "storage" => parse_storage(&rest, &id),
@@ -645,7 +813,29 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     Ok(json!({ "id": id, "action": "cookies_set", "cookies": [cookie] }))
                 }
                 "clear" => Ok(json!({ "id": id, "action": "cookies_clear" })),
-                _ => Ok(json!({ "id": id, "action": "cookies_get" })),
+                "get" => match rest.get(1) {
+                    Some(name) => Ok(json!({ "id": id, "action": "cookies_get", "name": name })),
+                    None => Ok(json!({ "id": id, "action": "cookies_get" })),
+                },
+                "list" => Ok(json!({ "id": id, "action": "cookies_get" })),
+                "export" | "import" => {
+                    let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                        context: format!("cookies {}", op),
+                        usage: "cookies <export|import> <file>",
+                    })?;
+                    if *op == "import" && !std::path::Path::new(path).is_file() {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("File not found: {}", path),
+                            usage: "cookies import <file>",
+                        });
+                    }
+                    let action = format!("cookies_{}", op);
+                    Ok(json!({ "id": id, "action": action, "path": absolute_path(path) }))
+                }
+                other => Err(ParseError::UnknownSubcommand {
+                    subcommand: other.to_string(),
+                    valid_options: &["list", "get", "set", "clear", "export", "import"],
+                }),
             }
         }
 
@@ -688,76 +878,66 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             }
         }
 
+        // === Context ===
+        "context" => parse_context(&rest, &id),
+
         // === Frame ===
-        "frame" => {
-            if rest.get(0).map(|s| *s) == Some("main") {
-                Ok(json!({ "id": id, "action": "mainframe" }))
-            } else {
-                let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                    context: "frame".to_string(),
-                    usage: "frame <selector|main>",
-                })?;
-                Ok(json!({ "id": id, "action": "frame", "selector": sel }))
-            }
-        }
+        "frame" => parse_frame(&rest, &id),
 
         // === Dialog ===
-        "dialog" => {
-            const VALID: &[&str] = &["accept", "dismiss"];
-            match rest.get(0).map(|s| *s) {
-                Some("accept") => {
-                    let mut cmd = json!({ "id": id, "action": "dialog", "response": "accept" });
-                    if let Some(prompt_text) = rest.get(1) {
-                        cmd["promptText"] = json!(prompt_text);
-                    }
-                    Ok(cmd)
-                }
-                Some(sub) => Err(ParseError::UnknownSubcommand {
-                    subcommand: sub.to_string(),
-                    valid_options: VALID,
-                }),
-                None => Err(ParseError::MissingArguments {
-                    context: "dialog".to_string(),
-                    usage: "dialog <accept|dismiss> [text]",
-                }),
-            }
-        }
+        "dialog" => parse_dialog(&rest, &id),
 
         // === Debug ===
-        "trace" => {
+        "trace" => parse_trace(&rest, &id),
+        "coverage" => parse_coverage(&rest, &id),
+
+        // === HAR ===
+        "har" => {
             const VALID: &[&str] = &["start", "stop"];
-            match rest.get(0).map(|s| *s) {
-                Some("start") => Ok(json!({ "id": id, "action": "trace_start" })),
-                Some("stop") => {
+            match rest.first().copied() {
+                Some("start") => {
                     let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                        context: "trace stop".to_string(),
-                        usage: "trace stop <path>",
+                        context: "har start".to_string(),
+                        usage: "har start <file.har>",
                     })?;
-                    Ok(json!({ "id": id, "action": "trace_stop", "path": path }))
+                    Ok(json!({ "id": id, "action": "har_start", "path": absolute_path(path) }))
+                }
+                Some("stop") => {
+                    let mut cmd = json!({ "id": id, "action": "har_stop" });
+                    if let Some(path) = rest.get(1) {
+                        cmd["path"] = json!(absolute_path(path));
+                    }
+                    Ok(cmd)
                 }
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
                     valid_options: VALID,
                 }),
                 None => Err(ParseError::MissingArguments {
-                    context: "trace".to_string(),
-                    usage: "trace <start|stop> [path]",
+                    context: "har".to_string(),
+                    usage: "har <start|stop> [file.har]",
                 }),
             }
         }
 
-        // === Recording (Playwright native video recording) ===
+        // === Recording (Playwright native video recording, or a script of the user's actions) ===
         "record" => {
             const VALID: &[&str] = &["start", "stop", "restart"];
             match rest.get(0).map(|s| *s) {
                 Some("start") => {
                     let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                         context: "record start".to_string(),
-                        usage: "record start <output.webm> [url]",
+                        usage: "record start <output.webm|dir|script.ab|actions.json> [url]",
                     })?;
                     // Optional URL parameter
                     let url = rest.get(2);
-                    let mut cmd = json!({ "id": id, "action": "recording_start", "path": path });
+                    let action = if is_script_path(path) {
+                        "codegen_start"
+                    } else {
+                        "recording_start"
+                    };
+                    let mut cmd =
+                        json!({ "id": id, "action": action, "path": absolute_path(path) });
                     if let Some(u) = url {
                         // Add https:// prefix if needed
                         let url_str = if u.starts_with("http") {
@@ -773,10 +953,11 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                 Some("restart") => {
                     let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                         context: "record restart".to_string(),
-                        usage: "record restart <output.webm> [url]",
+                        usage: "record restart <output.webm|dir> [url]",
                     })?;
                     // Optional URL parameter
                     let url = rest.get(2);
+                    let path = absolute_path(path);
                     let mut cmd = json!({ "id": id, "action": "recording_restart", "path": path });
                     if let Some(u) = url {
                         // Add https:// prefix if needed
@@ -799,20 +980,78 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                 }),
             }
         }
-        "console" => {
-            let clear = rest.iter().any(|&s| s == "--clear");
-            Ok(json!({ "id": id, "action": "console", "clear": clear }))
-        }
+        "console" => parse_console(&rest, &id),
+        "events" => parse_events(&rest, &id),
+        "watch" => parse_watch(&rest, &id),
         "errors" => {
             let clear = rest.iter().any(|&s| s == "--clear");
             Ok(json!({ "id": id, "action": "errors", "clear": clear }))
         }
-        "highlight" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-                context: "highlight".to_string(),
-                usage: "highlight <selector>",
-            })?;
-            Ok(json!({ "id": id, "action": "highlight", "selector": sel }))
+        "history" => {
+            const USAGE: &str = "history [-n <count>] | history go <n>";
+            if rest.first() == Some(&"go") {
+                let index = rest
+                    .get(1)
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: "history go requires an entry number from history".to_string(),
+                        usage: USAGE,
+                    })?;
+                return Ok(json!({ "id": id, "action": "history_go", "index": index }));
+            }
+            let mut limit = 20;
+            let mut i = 0;
+            while i < rest.len() {
+                match rest[i] {
+                    "-n" => {
+                        limit = rest
+                            .get(i + 1)
+                            .and_then(|s| s.parse::<u64>().ok())
+                            .filter(|&n| n > 0)
+                            .ok_or_else(|| ParseError::InvalidValue {
+                                message: "-n requires a positive number".to_string(),
+                                usage: USAGE,
+                            })?;
+                        i += 1;
+                    }
+                    other => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Unexpected argument: {}", other),
+                            usage: USAGE,
+                        })
+                    }
+                }
+                i += 1;
+            }
+            Ok(json!({ "id": id, "action": "history", "limit": limit }))
+        }
+        "highlight" => parse_highlight(&rest, &id),
+        "clipboard" => {
+            const VALID: &[&str] = &["read", "write", "copy", "paste"];
+            match rest.first().copied() {
+                Some("write") if rest.len() > 1 => Ok(json!({
+                    "id": id,
+                    "action": "clipboard",
+                    "operation": "write",
+                    "text": rest[1..].join(" "),
+                })),
+                Some("write") => Err(ParseError::MissingArguments {
+                    context: "clipboard write".to_string(),
+                    usage: "clipboard write <text>",
+                }),
+                Some(op @ ("read" | "copy" | "paste")) => {
+                    Ok(json!({ "id": id, "action": "clipboard", "operation": op }))
+                }
+                Some(sub) => Err(ParseError::UnknownSubcommand {
+                    subcommand: sub.to_string(),
+                    valid_options: VALID,
+                }),
+                None => Err(ParseError::MissingArguments {
+                    context: "clipboard".to_string(),
+                    usage: "clipboard <read|write|copy|paste> [text]",
+                }),
+            }
         }
 
         // === State ===
@@ -824,14 +1063,20 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                         context: "state save".to_string(),
                         usage: "state save <path>",
                     })?;
-                    Ok(json!({ "id": id, "action": "state_save", "path": path }))
+                    Ok(json!({ "id": id, "action": "state_save", "path": absolute_path(path) }))
                 }
                 Some("load") => {
                     let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                         context: "state load".to_string(),
                         usage: "state load <path>",
                     })?;
-                    Ok(json!({ "id": id, "action": "state_load", "path": path }))
+                    if !std::path::Path::new(path).is_file() {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("File not found: {}", path),
+                            usage: "state load <path>",
+                        });
+                    }
+                    Ok(json!({ "id": id, "action": "state_load", "path": absolute_path(path) }))
                 }
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
@@ -879,39 +1124,575 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     // List available iOS simulators
                     Ok(json!({ "id": id, "action": "device_list" }))
                 }
-                Some(sub) => Err(ParseError::UnknownSubcommand {
-                    subcommand: sub.to_string(),
-                    valid_options: &["list"],
-                }),
+                // Anything else names a Playwright device descriptor to emulate
+                Some(_) => Ok(json!({ "id": id, "action": "device", "device": rest.join(" ") })),
             }
         }
-
-        _ => Err(ParseError::UnknownCommand {
-            command: cmd.to_string(),
-        }),
+        "daemon" => parse_daemon(&rest, &id, flags),
+        "status" => Ok(json!({ "id": id, "action": "status" })),
+        "emulate" => parse_emulate(&rest, &id),
+        "permissions" => parse_permissions(&rest, &id),
+        "viewport" => parse_viewport(
+            &rest,
+            &id,
+            "viewport",
+            "viewport <width> <height> [--scale <n>]",
+        ),
+
+        _ => match plugins::find(cmd) {
+            Some(path) => plugins::command(&path, &rest, &id),
+            None => Err(ParseError::UnknownCommand {
+                command: cmd.to_string(),
+            }),
+        },
     }
 }
 
-fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &[
-        "text", "html", "value", "attr", "url", "title", "count", "box", "styles",
-    ];
-
-    match rest.get(0).map(|s| *s) {
-        Some("text") => {
-            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "get text".to_string(),
-                usage: "get text <selector>",
-            })?;
-            Ok(json!({ "id": id, "action": "gettext", "selector": sel }))
-        }
-        Some("html") => {
-            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "get html".to_string(),
-                usage: "get html <selector>",
-            })?;
-            Ok(json!({ "id": id, "action": "innerhtml", "selector": sel }))
+fn parse_context(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["new", "list", "switch", "close"];
+    const USAGE: &str = "context <new [--name <name>] | list | switch <name> | close [name]>";
+    match rest.first().copied() {
+        Some("new") => {
+            let mut cmd = json!({ "id": id, "action": "context_new" });
+            match rest.get(1..).unwrap_or_default() {
+                [] => {}
+                ["--name", name] => cmd["name"] = json!(name),
+                ["--name"] => {
+                    return Err(ParseError::MissingArguments {
+                        context: "context new --name".to_string(),
+                        usage: USAGE,
+                    })
+                }
+                [other, ..] => {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Unexpected argument: {}", other),
+                        usage: USAGE,
+                    })
+                }
+            }
+            Ok(cmd)
+        }
+        Some("list") | None => Ok(json!({ "id": id, "action": "context_list" })),
+        Some("switch") => {
+            let name = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "context switch".to_string(),
+                usage: USAGE,
+            })?;
+            Ok(json!({ "id": id, "action": "context_switch", "name": name }))
+        }
+        Some("close") => {
+            let mut cmd = json!({ "id": id, "action": "context_close" });
+            if let Some(name) = rest.get(1) {
+                cmd["name"] = json!(name);
+            }
+            Ok(cmd)
+        }
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+    }
+}
+
+fn parse_open(rest: &[&str], id: &str, flags: &Flags, name: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "open <url> [--new-tab | --window] [--wait-until <state>] [--referer <url>] [--timeout <ms>]";
+    const WAIT_UNTIL: &[&str] = &["load", "domcontentloaded", "networkidle", "commit"];
+
+    let (args, timeout) = take_timeout(rest, USAGE)?;
+    let mut url = None;
+    let mut wait_until = None;
+    let mut referer = None;
+    let mut target = None;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg {
+            "--new-tab" | "--window" => {
+                if target.is_some() {
+                    return Err(ParseError::InvalidValue {
+                        message: "--new-tab and --window can't be used together".to_string(),
+                        usage: USAGE,
+                    });
+                }
+                target = Some(if arg == "--window" {
+                    NavigateTarget::Window
+                } else {
+                    NavigateTarget::Tab
+                });
+            }
+            "--wait-until" => {
+                let state = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{} --wait-until", name),
+                    usage: USAGE,
+                })?;
+                let state =
+                    serde_json::from_value(json!(state)).map_err(|_| ParseError::InvalidValue {
+                        message: format!(
+                            "Invalid --wait-until: {} (expected {})",
+                            state,
+                            WAIT_UNTIL.join(", ")
+                        ),
+                        usage: USAGE,
+                    })?;
+                wait_until = Some(state);
+            }
+            "--referer" => {
+                referer = Some(iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{} --referer", name),
+                    usage: USAGE,
+                })?);
+            }
+            _ if url.is_none() => url = Some(arg),
+            _ => {}
+        }
+    }
+
+    let url = url.ok_or_else(|| ParseError::MissingArguments {
+        context: name.to_string(),
+        usage: USAGE,
+    })?;
+    let url_lower = url.to_lowercase();
+    let url = if url_lower.starts_with("http://")
+        || url_lower.starts_with("https://")
+        || url_lower.starts_with("about:")
+        || url_lower.starts_with("data:")
+        || url_lower.starts_with("file:")
+    {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+    let nav_cmd = Command::Navigate {
+        url,
+        wait_until,
+        referer: referer.map(str::to_string),
+        timeout,
+        // If --headers flag is set, include headers (scoped to this origin)
+        headers: flags
+            .headers
+            .as_deref()
+            .and_then(|h| serde_json::from_str(h).ok()),
+        // Include iOS device info if specified (needed for auto-launch with existing daemon)
+        ios_device: flags
+            .device
+            .clone()
+            .filter(|_| flags.provider.as_deref() == Some("ios")),
+        target,
+    };
+    Ok(nav_cmd.into_request(id))
+}
+
+fn parse_screenshot(rest: &[&str], id: &str, flags: &Flags) -> Result<Value, ParseError> {
+    const USAGE: &str = "screenshot [selector] [path] [--full-page] [--selector <sel>] [--format png|jpeg|webp] [--quality <n>] [--clip x,y,w,h] [--stdout [--base64]] [--annotate-refs]";
+
+    let mut full_page = flags.full;
+    let mut selector: Option<&str> = None;
+    let mut format: Option<ImageFormat> = None;
+    let mut quality: Option<u8> = None;
+    let mut clip: Option<Clip> = None;
+    let mut stdout = false;
+    let mut base64 = false;
+    let mut annotate_refs = false;
+    let mut positional: Vec<&str> = Vec::new();
+
+    let mut i = 0;
+    while i < rest.len() {
+        let value = || {
+            rest.get(i + 1)
+                .copied()
+                .ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("screenshot {}", rest[i]),
+                    usage: USAGE,
+                })
+        };
+        match rest[i] {
+            "--full-page" => full_page = true,
+            "--stdout" => stdout = true,
+            "--base64" => base64 = true,
+            "--annotate-refs" => annotate_refs = true,
+            "--selector" => {
+                selector = Some(value()?);
+                i += 1;
+            }
+            "--format" => {
+                let f = value()?;
+                format = Some(match f.to_ascii_lowercase().as_str() {
+                    "png" => ImageFormat::Png,
+                    "jpeg" | "jpg" => ImageFormat::Jpeg,
+                    "webp" => ImageFormat::Webp,
+                    _ => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Invalid format: {}. Expected png, jpeg or webp", f),
+                            usage: USAGE,
+                        })
+                    }
+                });
+                i += 1;
+            }
+            "--quality" => {
+                let q = value()?;
+                quality = Some(q.parse::<u8>().ok().filter(|q| *q <= 100).ok_or_else(|| {
+                    ParseError::InvalidValue {
+                        message: format!("Invalid quality: {}. Expected 0-100", q),
+                        usage: USAGE,
+                    }
+                })?);
+                i += 1;
+            }
+            "--clip" => {
+                let c = value()?;
+                let parts: Vec<f64> = c.split(',').filter_map(|p| p.trim().parse().ok()).collect();
+                match parts.as_slice() {
+                    &[x, y, width, height] if width > 0.0 && height > 0.0 => {
+                        clip = Some(Clip {
+                            x,
+                            y,
+                            width,
+                            height,
+                        });
+                    }
+                    _ => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!(
+                                "Invalid clip: {}. Expected x,y,width,height (e.g. 0,0,800,600)",
+                                c
+                            ),
+                            usage: USAGE,
+                        })
+                    }
+                }
+                i += 1;
+            }
+            arg => positional.push(arg),
+        }
+        i += 1;
+    }
+
+    // screenshot [selector] [path]
+    // selector: @ref or CSS selector
+    // path: file path (contains / or . or ends with known extension)
+    let path = match (positional.first(), positional.get(1)) {
+        (Some(first), Some(second)) if selector.is_none() => {
+            // Two args: first is selector, second is path
+            selector = Some(*first);
+            Some(*second)
+        }
+        (Some(first), None) if selector.is_none() => {
+            // One arg: determine if it's a selector or a path
+            let is_relative_path = first.starts_with("./") || first.starts_with("../");
+            let is_selector = !is_relative_path
+                && (first.starts_with('.') || first.starts_with('#') || first.starts_with('@'));
+            let is_path = is_relative_path || first.contains('/') || image_format(first).is_some();
+            if is_selector || !is_path {
+                selector = Some(*first);
+                None
+            } else {
+                Some(*first)
+            }
+        }
+        // With --selector, the only positional is the path
+        (Some(first), None) => Some(*first),
+        (None, _) => None,
+        _ => {
+            return Err(ParseError::InvalidValue {
+                message: "Too many arguments".to_string(),
+                usage: USAGE,
+            })
+        }
+    };
+
+    if selector.is_some() && clip.is_some() {
+        return Err(ParseError::InvalidValue {
+            message: "--clip cannot be combined with an element selector".to_string(),
+            usage: USAGE,
+        });
+    }
+
+    if base64 && !stdout {
+        return Err(ParseError::InvalidValue {
+            message: "--base64 requires --stdout".to_string(),
+            usage: USAGE,
+        });
+    }
+    if stdout && path.is_some() {
+        return Err(ParseError::InvalidValue {
+            message: "--stdout cannot be combined with a path".to_string(),
+            usage: USAGE,
+        });
+    }
+
+    // Infer the format from the file extension unless it was given explicitly
+    let format = format.or_else(|| path.and_then(image_format));
+    if quality.is_some() && !matches!(format, Some(ImageFormat::Jpeg | ImageFormat::Webp)) {
+        return Err(ParseError::InvalidValue {
+            message: "--quality requires --format jpeg or webp".to_string(),
+            usage: USAGE,
+        });
+    }
+
+    let mut cmd = Command::Screenshot {
+        path: path.map(str::to_string),
+        full_page,
+        selector: selector.map(str::to_string),
+        format,
+        quality,
+        clip,
+        stdout,
+        annotate_refs,
+    }
+    .into_request(id);
+    if stdout {
+        // The daemon always returns base64; `base64` only tells the CLI how to print it
+        cmd["base64"] = json!(base64);
+    }
+    Ok(cmd)
+}
+
+/// `screenshot diff <baseline.png>` captures a PNG over stdout and attaches the
+/// comparison settings under `diff`; the CLI does the comparison itself.
+fn parse_screenshot_diff(rest: &[&str], id: &str, flags: &Flags) -> Result<Value, ParseError> {
+    const USAGE: &str =
+        "screenshot diff <baseline.png> [--threshold <0-1>] [--out <diff.png>] [--full-page] [--selector <sel>]";
+
+    let mut baseline: Option<&str> = None;
+    let mut threshold = diff::DEFAULT_THRESHOLD;
+    let mut out: Option<String> = None;
+    let mut capture: Vec<&str> = Vec::new();
+
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--threshold" => {
+                let value = rest.get(i + 1).copied().unwrap_or_default();
+                threshold = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|t| (0.0..=1.0).contains(t))
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: format!(
+                            "Invalid threshold: {}. Expected a fraction between 0 and 1",
+                            value
+                        ),
+                        usage: USAGE,
+                    })?;
+                i += 1;
+            }
+            "--out" => {
+                let value = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "screenshot diff --out".to_string(),
+                        usage: USAGE,
+                    })?;
+                out = Some(absolute_path(value));
+                i += 1;
+            }
+            // Capture options are passed through to the screenshot itself
+            "--selector" | "--format" | "--quality" | "--clip" => {
+                capture.extend(rest.get(i..i + 2).unwrap_or(&rest[i..]));
+                i += 1;
+            }
+            arg if arg.starts_with("--") => capture.push(arg),
+            arg if baseline.is_none() => baseline = Some(arg),
+            arg => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", arg),
+                    usage: USAGE,
+                })
+            }
+        }
+        i += 1;
+    }
+
+    let baseline = baseline.ok_or_else(|| ParseError::MissingArguments {
+        context: "screenshot diff".to_string(),
+        usage: USAGE,
+    })?;
+    if !std::path::Path::new(baseline).is_file() {
+        return Err(ParseError::InvalidValue {
+            message: format!("File not found: {}", baseline),
+            usage: USAGE,
+        });
+    }
+
+    capture.push("--stdout");
+    let mut cmd = parse_screenshot(&capture, id, flags)?;
+    if cmd.get("format").is_some_and(|f| f != "png") {
+        return Err(ParseError::InvalidValue {
+            message: "screenshot diff only compares PNG images".to_string(),
+            usage: USAGE,
+        });
+    }
+    cmd["format"] = json!("png");
+    cmd["diff"] = json!({
+        "baseline": absolute_path(baseline),
+        "threshold": threshold,
+        "out": out,
+    });
+    Ok(cmd)
+}
+
+/// Image format implied by a file extension, if it is one screenshot supports.
+fn image_format(path: &str) -> Option<ImageFormat> {
+    let lower = path.to_ascii_lowercase();
+    if lower.ends_with(".png") {
+        Some(ImageFormat::Png)
+    } else if lower.ends_with(".jpg") || lower.ends_with(".jpeg") {
+        Some(ImageFormat::Jpeg)
+    } else if lower.ends_with(".webp") {
+        Some(ImageFormat::Webp)
+    } else {
+        None
+    }
+}
+
+fn parse_read(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "read [selector | next | prev] [--max-chars <n>]";
+    let mut cmd = json!({ "id": id, "action": "read" });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--max-chars" => {
+                let n = rest
+                    .get(i + 1)
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: "--max-chars requires a positive number".to_string(),
+                        usage: USAGE,
+                    })?;
+                cmd["maxChars"] = json!(n);
+                i += 1;
+            }
+            _ if cmd.get("selector").is_some() || cmd.get("direction").is_some() => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", rest[i]),
+                    usage: USAGE,
+                })
+            }
+            direction @ ("next" | "prev") => cmd["direction"] = json!(direction),
+            selector => cmd["selector"] = json!(selector),
+        }
+        i += 1;
+    }
+    Ok(cmd)
+}
+
+fn parse_save(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "save <path.mhtml> | save --html-dir <dir>";
+    match rest {
+        ["--html-dir", dir] => {
+            Ok(json!({ "id": id, "action": "save", "htmlDir": absolute_path(dir) }))
+        }
+        ["--html-dir"] | [] => Err(ParseError::MissingArguments {
+            context: "save".to_string(),
+            usage: USAGE,
+        }),
+        [path] if !path.starts_with('-') => {
+            Ok(json!({ "id": id, "action": "save", "path": absolute_path(path) }))
+        }
+        _ => Err(ParseError::InvalidValue {
+            message: "save takes either a path or --html-dir <dir>".to_string(),
+            usage: USAGE,
+        }),
+    }
+}
+
+fn parse_pdf(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "pdf [path] [--format <size>] [--landscape] [--margin <margin>]";
+    const FORMATS: &[&str] = &[
+        "Letter", "Legal", "Tabloid", "Ledger", "A0", "A1", "A2", "A3", "A4", "A5", "A6",
+    ];
+
+    let mut cmd = json!({ "id": id, "action": "pdf" });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--format" => {
+                let value = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "pdf --format".to_string(),
+                        usage: USAGE,
+                    })?;
+                let format = FORMATS
+                    .iter()
+                    .find(|f| f.eq_ignore_ascii_case(value))
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: format!(
+                            "Invalid format: {}. Expected one of: {}",
+                            value,
+                            FORMATS.join(", ")
+                        ),
+                        usage: USAGE,
+                    })?;
+                cmd["format"] = json!(format);
+                i += 2;
+            }
+            "--landscape" => {
+                cmd["landscape"] = json!(true);
+                i += 1;
+            }
+            "--margin" => {
+                let value = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "pdf --margin".to_string(),
+                        usage: USAGE,
+                    })?;
+                // One value for all sides, or top,right,bottom,left
+                let sides: Vec<&str> = value.split(',').map(str::trim).collect();
+                let (top, right, bottom, left) = match sides.as_slice() {
+                    [all] if !all.is_empty() => (*all, *all, *all, *all),
+                    [t, r, b, l] if sides.iter().all(|s| !s.is_empty()) => (*t, *r, *b, *l),
+                    _ => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!(
+                                "Invalid margin: {}. Use one value (1cm) or top,right,bottom,left",
+                                value
+                            ),
+                            usage: USAGE,
+                        })
+                    }
+                };
+                cmd["margin"] =
+                    json!({ "top": top, "right": right, "bottom": bottom, "left": left });
+                i += 2;
+            }
+            // A misspelt flag is not a file name
+            flag if flag.starts_with("--") => {
+                return Err(ParseError::UnknownFlag {
+                    flag: flag.to_string(),
+                    command: "pdf".to_string(),
+                    valid_options: spec::flags("pdf").unwrap_or_default(),
+                })
+            }
+            path => {
+                cmd["path"] = json!(absolute_path(path));
+                i += 1;
+            }
+        }
+    }
+    Ok(cmd)
+}
+
+fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &[
+        "text", "html", "value", "attr", "url", "title", "count", "box", "bbox", "styles",
+        "style", "article", "metrics",
+    ];
+
+    match rest.get(0).map(|s| *s) {
+        Some("text") => {
+            const USAGE: &str = "get text <selector> [--nth <n> | --all]";
+            let (args, pick) = take_pick(&rest[1..], USAGE)?;
+            let sel = args.first().ok_or_else(|| ParseError::MissingArguments {
+                context: "get text".to_string(),
+                usage: USAGE,
+            })?;
+            let selector = sel.to_string();
+            Ok(pick.apply(Command::Gettext { selector }.into_request(id)))
         }
+        Some("html") => parse_get_html(&rest[1..], id),
         Some("value") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "get value".to_string(),
@@ -930,8 +1711,11 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             })?;
             Ok(json!({ "id": id, "action": "getattribute", "selector": sel, "attribute": attr }))
         }
-        Some("url") => Ok(json!({ "id": id, "action": "url" })),
-        Some("title") => Ok(json!({ "id": id, "action": "title" })),
+        Some("url") if rest[1..].contains(&"--parsed") => {
+            Ok(json!({ "id": id, "action": "url", "parsed": true }))
+        }
+        Some("url") => Ok(Command::Url.into_request(id)),
+        Some("title") => Ok(Command::Title.into_request(id)),
         Some("count") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "get count".to_string(),
@@ -939,7 +1723,7 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             })?;
             Ok(json!({ "id": id, "action": "count", "selector": sel }))
         }
-        Some("box") => {
+        Some("box") | Some("bbox") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "get box".to_string(),
                 usage: "get box <selector>",
@@ -953,75 +1737,834 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             })?;
             Ok(json!({ "id": id, "action": "styles", "selector": sel }))
         }
+        Some("style") => match &rest[1..] {
+            [sel, properties @ ..] if !properties.is_empty() => Ok(json!({
+                "id": id,
+                "action": "computedstyle",
+                "selector": sel,
+                "properties": properties,
+            })),
+            _ => Err(ParseError::MissingArguments {
+                context: "get style".to_string(),
+                usage: "get style <selector> <property...>",
+            }),
+        },
+        Some("metrics") => Ok(json!({ "id": id, "action": "metrics" })),
+        Some("article") => match &rest[1..] {
+            [] => Ok(json!({ "id": id, "action": "article" })),
+            ["--selector" | "-s", sel] => {
+                Ok(json!({ "id": id, "action": "article", "selector": sel }))
+            }
+            _ => Err(ParseError::InvalidValue {
+                message: "get article takes only --selector <sel>".to_string(),
+                usage: "get article [--selector <sel>]",
+            }),
+        },
         Some(sub) => Err(ParseError::UnknownSubcommand {
             subcommand: sub.to_string(),
             valid_options: VALID,
         }),
         None => Err(ParseError::MissingArguments {
             context: "get".to_string(),
-            usage: "get <text|html|value|attr|url|title|count|box|styles> [args...]",
+            usage:
+                "get <text|html|value|attr|url|title|count|box|styles|style|article|metrics> [args...]",
         }),
     }
 }
 
-fn parse_is(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["visible", "enabled", "checked"];
+/// `get html [selector] [--selector <sel>] [--outer] [-o <file>]`. Without a
+/// selector this is the whole document.
+fn parse_get_html(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "get html [--selector <sel>] [--outer] [-o <file>]";
+
+    let mut cmd = json!({ "id": id, "action": "content" });
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--selector" | "-s" => {
+                let sel = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "get html --selector".to_string(),
+                    usage: USAGE,
+                })?;
+                cmd["selector"] = json!(sel);
+            }
+            "--outer" => cmd["outer"] = json!(true),
+            "-o" | "--output" => {
+                let path = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "get html -o".to_string(),
+                    usage: USAGE,
+                })?;
+                cmd["path"] = json!(absolute_path(path));
+            }
+            sel if !sel.starts_with('-') && cmd.get("selector").is_none() => {
+                cmd["selector"] = json!(sel);
+            }
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", arg),
+                    usage: USAGE,
+                })
+            }
+        }
+    }
+    if cmd.get("outer").is_some() && cmd.get("selector").is_none() {
+        return Err(ParseError::InvalidValue {
+            message: "--outer requires a selector".to_string(),
+            usage: USAGE,
+        });
+    }
+    Ok(cmd)
+}
 
-    match rest.get(0).map(|s| *s) {
-        Some("visible") => {
-            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "is visible".to_string(),
-                usage: "is visible <selector>",
+fn parse_links(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "links [--selector <sel>] [--absolute]";
+
+    let mut cmd = json!({ "id": id, "action": "links" });
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--selector" | "-s" => {
+                let sel = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "links --selector".to_string(),
+                    usage: USAGE,
+                })?;
+                cmd["selector"] = json!(sel);
+            }
+            "--absolute" => cmd["absolute"] = json!(true),
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", arg),
+                    usage: USAGE,
+                })
+            }
+        }
+    }
+    Ok(cmd)
+}
+
+fn parse_fill(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str =
+        "fill <selector> <text> | --stdin | --secret <ENV_NAME> | fill --form <file>";
+
+    match rest {
+        ["--form", path] => {
+            let fields = form::read_fields(&absolute_path(path)).map_err(|message| {
+                ParseError::InvalidValue {
+                    message,
+                    usage: USAGE,
+                }
             })?;
-            Ok(json!({ "id": id, "action": "isvisible", "selector": sel }))
+            let fields: Vec<Value> = fields
+                .into_iter()
+                .map(|(field, value)| json!({ "field": field, "value": value }))
+                .collect();
+            return Ok(json!({ "id": id, "action": "fillform", "fields": fields }));
         }
-        Some("enabled") => {
-            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "is enabled".to_string(),
-                usage: "is enabled <selector>",
+        ["--form"] => {
+            return Err(ParseError::MissingArguments {
+                context: "fill --form".to_string(),
+                usage: USAGE,
+            })
+        }
+        _ => {}
+    }
+
+    let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
+        context: "fill".to_string(),
+        usage: USAGE,
+    })?;
+    let (value, secret) = match &rest[1..] {
+        ["--stdin"] => {
+            let input = io::read_to_string(io::stdin()).map_err(|e| ParseError::InvalidValue {
+                message: format!("Failed to read stdin: {}", e),
+                usage: USAGE,
             })?;
-            Ok(json!({ "id": id, "action": "isenabled", "selector": sel }))
+            // Drop the newline `echo` and heredocs add
+            let value = input.strip_suffix('\n').unwrap_or(&input);
+            (value.strip_suffix('\r').unwrap_or(value).to_string(), false)
         }
-        Some("checked") => {
-            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "is checked".to_string(),
-                usage: "is checked <selector>",
+        ["--secret", name] => {
+            let value = std::env::var(name).map_err(|_| ParseError::InvalidValue {
+                message: format!("Environment variable {} is not set", name),
+                usage: USAGE,
             })?;
-            Ok(json!({ "id": id, "action": "ischecked", "selector": sel }))
+            (value, true)
         }
-        Some(sub) => Err(ParseError::UnknownSubcommand {
-            subcommand: sub.to_string(),
-            valid_options: VALID,
-        }),
-        None => Err(ParseError::MissingArguments {
-            context: "is".to_string(),
-            usage: "is <visible|enabled|checked> <selector>",
-        }),
+        ["--secret"] => {
+            return Err(ParseError::MissingArguments {
+                context: "fill --secret".to_string(),
+                usage: USAGE,
+            })
+        }
+        text => (text.join(" "), false),
+    };
+    Ok(Command::Fill {
+        selector: sel.to_string(),
+        value,
+        secret,
     }
+    .into_request(id))
 }
 
-fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &[
-        "role",
-        "text",
-        "label",
-        "placeholder",
-        "alt",
-        "title",
-        "testid",
-        "first",
-        "last",
-        "nth",
-    ];
+/// Whether `record start` was given a script (`.ab`) or command list
+/// (`.json`) to record the user's actions to, rather than a video
+fn is_script_path(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ab") || ext.eq_ignore_ascii_case("json"))
+}
 
-    let locator = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
-        context: "find".to_string(),
-        usage: "find <locator> <value> [action] [text]",
+/// The value of a `fill --secret` command, which must never be echoed back.
+pub fn secret_value(cmd: &Value) -> Option<&str> {
+    if cmd.get("secret").and_then(|v| v.as_bool()) != Some(true) {
+        return None;
+    }
+    cmd.get("value")
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+}
+
+fn parse_close(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "close [--keep-daemon] [--save-state <file>]";
+
+    let mut cmd = Command::Close.into_request(id);
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--keep-daemon" => cmd["keepDaemon"] = json!(true),
+            "--save-state" => {
+                let path = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "close --save-state".to_string(),
+                        usage: USAGE,
+                    })?;
+                cmd["saveState"] = json!(absolute_path(path));
+                i += 1;
+            }
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage: USAGE,
+                })
+            }
+        }
+        i += 1;
+    }
+    Ok(cmd)
+}
+
+/// `raw`: a protocol request written out by hand, sent as it is. Only the
+/// `id` is filled in when the payload has none.
+fn parse_raw(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "raw <json> | raw --file <path>";
+
+    let text = match rest {
+        ["--file", path] => {
+            std::fs::read_to_string(path).map_err(|e| ParseError::InvalidValue {
+                message: format!("Failed to read {}: {}", path, e),
+                usage: USAGE,
+            })?
+        }
+        ["--file"] | [] => {
+            return Err(ParseError::MissingArguments {
+                context: "raw".to_string(),
+                usage: USAGE,
+            })
+        }
+        [json] => json.to_string(),
+        _ => {
+            return Err(ParseError::InvalidValue {
+                message: "Expected one JSON payload (quote it) or --file <path>".to_string(),
+                usage: USAGE,
+            })
+        }
+    };
+
+    let mut cmd: Value = serde_json::from_str(&text).map_err(|e| ParseError::InvalidValue {
+        message: format!("Invalid JSON: {}", e),
+        usage: USAGE,
     })?;
+    if cmd.get("action").and_then(|v| v.as_str()).is_none() {
+        return Err(ParseError::InvalidValue {
+            message: "The payload needs an \"action\" string".to_string(),
+            usage: USAGE,
+        });
+    }
+    if cmd.get("id").is_none() {
+        cmd["id"] = json!(id);
+    }
+    Ok(cmd)
+}
 
-    let name_idx = rest.iter().position(|&s| s == "--name");
-    let name = name_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
-    let exact = rest.iter().any(|&s| s == "--exact");
+fn parse_eval(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str =
+        "eval [--file <path> | --stdin | -b] [--selector <sel>] [--arg <json>]... [script]";
+
+    let mut is_base64 = false;
+    let mut is_stdin = false;
+    let mut file: Option<&str> = None;
+    let mut selector: Option<&str> = None;
+    let mut eval_args: Vec<Value> = Vec::new();
+    let mut script_parts: Vec<&str> = Vec::new();
+
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        let mut value = |flag: &str| {
+            iter.next()
+                .copied()
+                .ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("eval {}", flag),
+                    usage: USAGE,
+                })
+        };
+        match arg {
+            "-b" | "--base64" => is_base64 = true,
+            "--stdin" => is_stdin = true,
+            "--file" => file = Some(value(arg)?),
+            "--selector" | "-s" => selector = Some(value(arg)?),
+            // JSON values; anything that isn't valid JSON is passed as a string
+            "--arg" => {
+                let raw = value(arg)?;
+                eval_args.push(serde_json::from_str(raw).unwrap_or_else(|_| json!(raw)));
+            }
+            _ => script_parts.push(arg),
+        }
+    }
+
+    let script = if let Some(path) = file {
+        if is_stdin || !script_parts.is_empty() {
+            return Err(ParseError::InvalidValue {
+                message: "Use either --file, --stdin or an inline script".to_string(),
+                usage: USAGE,
+            });
+        }
+        std::fs::read_to_string(path).map_err(|e| ParseError::InvalidValue {
+            message: format!("Failed to read {}: {}", path, e),
+            usage: USAGE,
+        })?
+    } else if is_stdin {
+        // Read script from stdin
+        let stdin = io::stdin();
+        let lines: Vec<String> = stdin
+            .lock()
+            .lines()
+            .map(|l| l.unwrap_or_default())
+            .collect();
+        lines.join("\n")
+    } else {
+        let raw_script = script_parts.join(" ");
+        if is_base64 {
+            let decoded = STANDARD
+                .decode(&raw_script)
+                .map_err(|_| ParseError::InvalidValue {
+                    message: "Invalid base64 encoding".to_string(),
+                    usage: "eval -b <base64-encoded-script>",
+                })?;
+            String::from_utf8(decoded).map_err(|_| ParseError::InvalidValue {
+                message: "Base64 decoded to invalid UTF-8".to_string(),
+                usage: "eval -b <base64-encoded-script>",
+            })?
+        } else {
+            raw_script
+        }
+    };
+
+    if script.trim().is_empty() && !is_stdin {
+        return Err(ParseError::MissingArguments {
+            context: "eval".to_string(),
+            usage: USAGE,
+        });
+    }
+
+    Ok(Command::Evaluate {
+        script,
+        args: eval_args,
+        selector: selector.map(str::to_string),
+    }
+    .into_request(id))
+}
+
+fn parse_table(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "table <selector> [--format csv|json]";
+
+    let mut cmd = json!({ "id": id, "action": "table" });
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--format" => {
+                let format = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "table --format".to_string(),
+                    usage: USAGE,
+                })?;
+                if !matches!(*format, "csv" | "json") {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Invalid table format: {}", format),
+                        usage: USAGE,
+                    });
+                }
+                cmd["format"] = json!(format);
+            }
+            _ if cmd.get("selector").is_none() => cmd["selector"] = json!(arg),
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", arg),
+                    usage: USAGE,
+                })
+            }
+        }
+    }
+    if cmd.get("selector").is_none() {
+        return Err(ParseError::MissingArguments {
+            context: "table".to_string(),
+            usage: USAGE,
+        });
+    }
+    Ok(cmd)
+}
+
+fn parse_audit(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["a11y"];
+    const USAGE: &str = "audit a11y [--selector <sel>] [--fail-on minor|moderate|serious|critical]";
+
+    match rest.first().copied() {
+        Some("a11y") => {}
+        Some(sub) => {
+            return Err(ParseError::UnknownSubcommand {
+                subcommand: sub.to_string(),
+                valid_options: VALID,
+            })
+        }
+        None => {
+            return Err(ParseError::MissingArguments {
+                context: "audit".to_string(),
+                usage: USAGE,
+            })
+        }
+    }
+
+    let mut cmd = json!({ "id": id, "action": "audit" });
+    let mut iter = rest[1..].iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--selector" | "-s" => {
+                let sel = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "audit a11y --selector".to_string(),
+                    usage: USAGE,
+                })?;
+                cmd["selector"] = json!(sel);
+            }
+            "--fail-on" => {
+                let level = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "audit a11y --fail-on".to_string(),
+                    usage: USAGE,
+                })?;
+                if !matches!(*level, "minor" | "moderate" | "serious" | "critical") {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Invalid impact level: {}", level),
+                        usage: USAGE,
+                    });
+                }
+                cmd["failOn"] = json!(level);
+            }
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", arg),
+                    usage: USAGE,
+                })
+            }
+        }
+    }
+    Ok(cmd)
+}
+
+/// Split `--timeout <ms>` out of `rest`, returning the remaining arguments and
+/// the timeout if one was given.
+fn take_timeout<'a>(
+    rest: &[&'a str],
+    usage: &'static str,
+) -> Result<(Vec<&'a str>, Option<u64>), ParseError> {
+    let mut timeout = None;
+    let mut args = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        if arg != "--timeout" {
+            args.push(arg);
+            continue;
+        }
+        let value = iter.next().ok_or_else(|| ParseError::MissingArguments {
+            context: "--timeout".to_string(),
+            usage,
+        })?;
+        let ms = value
+            .parse::<u64>()
+            .ok()
+            .filter(|&ms| ms > 0)
+            .ok_or_else(|| ParseError::InvalidValue {
+                message: format!("Invalid timeout: {} (expected milliseconds)", value),
+                usage,
+            })?;
+        timeout = Some(ms);
+    }
+    Ok((args, timeout))
+}
+
+fn parse_wait(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "wait <selector|ms|--url|--load|--idle|--spa-idle|--fn|--text|--hidden|--download|--request|--response> [--timeout <ms>]";
+
+    let (args, timeout) = take_timeout(rest, USAGE)?;
+    // Value following the first of `flags`, if present
+    let flag_value =
+        |flags: &[&str], usage: &'static str| -> Result<Option<&str>, ParseError> {
+            match args.iter().position(|s| flags.contains(s)) {
+                Some(idx) => args.get(idx + 1).copied().map(Some).ok_or_else(|| {
+                    ParseError::MissingArguments {
+                        context: format!("wait {}", flags[0]),
+                        usage,
+                    }
+                }),
+                None => Ok(None),
+            }
+        };
+    let url = flag_value(&["--url", "-u"], "wait --url <pattern>")?;
+    let load = flag_value(&["--load", "-l"], "wait --load <state>")?;
+    let expr = flag_value(&["--fn", "-f"], "wait --fn <expression>")?;
+    let text = flag_value(&["--text", "-t"], "wait --text <text>")?;
+    let hidden = flag_value(&["--hidden"], "wait --hidden <selector>")?;
+    let request = flag_value(&["--request"], "wait --request <url-glob>")?;
+    let response = flag_value(
+        &["--response"],
+        "wait --response <url-glob> [--status <code>]",
+    )?;
+    let status = flag_value(&["--status"], "wait --response <url-glob> --status <code>")?
+        .map(|value| {
+            value
+                .parse::<u16>()
+                .ok()
+                .filter(|code| (100..=599).contains(code))
+                .ok_or_else(|| ParseError::InvalidValue {
+                    message: format!("Invalid status: {} (expected an HTTP status code)", value),
+                    usage: "wait --response <url-glob> --status <code>",
+                })
+        })
+        .transpose()?;
+    let quiet = flag_value(&["--quiet"], "wait --spa-idle --quiet <ms>")?
+        .map(|value| {
+            parse_duration(value)
+                .filter(|&ms| ms > 0)
+                .ok_or_else(|| ParseError::InvalidValue {
+                    message: format!("Invalid quiet period: {} (expected e.g. 500 or 1s)", value),
+                    usage: "wait --spa-idle --quiet <ms>",
+                })
+        })
+        .transpose()?;
+    if quiet.is_some() && !args.contains(&"--spa-idle") {
+        return Err(ParseError::InvalidValue {
+            message: "--quiet only applies to wait --spa-idle".to_string(),
+            usage: "wait --spa-idle --quiet <ms>",
+        });
+    }
+    if status.is_some() && response.is_none() {
+        return Err(ParseError::InvalidValue {
+            message: "--status only applies to wait --response".to_string(),
+            usage: "wait --response <url-glob> --status <code>",
+        });
+    }
+
+    let mut cmd = if let Some(url) = url {
+        json!({ "id": id, "action": "waitforurl", "url": url })
+    } else if let Some(url) = request {
+        json!({ "id": id, "action": "waitforrequest", "url": url })
+    } else if let Some(url) = response {
+        let mut cmd = json!({ "id": id, "action": "waitforresponse", "url": url });
+        if let Some(status) = status {
+            cmd["status"] = json!(status);
+        }
+        cmd
+    } else if let Some(state) = load {
+        json!({ "id": id, "action": "waitforloadstate", "state": state })
+    } else if args.contains(&"--spa-idle") {
+        let mut cmd = json!({ "id": id, "action": "waitforspaidle" });
+        if let Some(ms) = quiet {
+            cmd["quiet"] = json!(ms);
+        }
+        cmd
+    } else if args.contains(&"--idle") {
+        json!({ "id": id, "action": "waitforloadstate", "state": "networkidle" })
+    } else if let Some(expr) = expr {
+        json!({ "id": id, "action": "waitforfunction", "expression": expr })
+    } else if let Some(text) = text {
+        // Use getByText locator to wait for text to appear
+        json!({ "id": id, "action": "wait", "selector": format!("text={}", text) })
+    } else if let Some(sel) = hidden {
+        json!({ "id": id, "action": "wait", "selector": sel, "state": "hidden" })
+    } else if let Some(idx) = args.iter().position(|&s| s == "--download" || s == "-d") {
+        // Optional path: first non-flag argument after --download
+        let mut cmd = json!({ "id": id, "action": "waitfordownload" });
+        if let Some(path) = args.get(idx + 1).filter(|p| !p.starts_with("--")) {
+            cmd["path"] = json!(absolute_path(path));
+        }
+        cmd
+    } else {
+        // Default: selector or milliseconds
+        match args.first() {
+            Some(arg) => match arg.parse::<u64>() {
+                Ok(_) if timeout.is_some() => {
+                    return Err(ParseError::InvalidValue {
+                        message: "--timeout cannot be combined with wait <ms>".to_string(),
+                        usage: USAGE,
+                    })
+                }
+                Ok(ms) => json!({ "id": id, "action": "wait", "timeout": ms }),
+                Err(_) => json!({ "id": id, "action": "wait", "selector": arg }),
+            },
+            None => {
+                return Err(ParseError::MissingArguments {
+                    context: "wait".to_string(),
+                    usage: USAGE,
+                })
+            }
+        }
+    };
+
+    if let Some(ms) = timeout {
+        cmd["timeout"] = json!(ms);
+    }
+    Ok(cmd)
+}
+
+fn parse_expect(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["text", "visible", "url", "title"];
+    const USAGE: &str = "expect <text|visible|url|title> [args...] [--timeout <ms>]";
+
+    let (args, timeout) = take_timeout(rest, USAGE)?;
+    let mut cmd = match args.first().copied() {
+        Some("text") => match &args[1..] {
+            [sel, expected @ ..] if !expected.is_empty() => json!({
+                "id": id,
+                "action": "expect",
+                "kind": "text",
+                "selector": sel,
+                "expected": expected.join(" "),
+            }),
+            _ => {
+                return Err(ParseError::MissingArguments {
+                    context: "expect text".to_string(),
+                    usage: "expect text <selector> <expected> [--timeout <ms>]",
+                })
+            }
+        },
+        Some("visible") => {
+            let sel = args.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "expect visible".to_string(),
+                usage: "expect visible <selector> [--timeout <ms>]",
+            })?;
+            json!({ "id": id, "action": "expect", "kind": "visible", "selector": sel })
+        }
+        Some(kind @ ("url" | "title")) => {
+            if args.len() < 2 {
+                return Err(ParseError::MissingArguments {
+                    context: format!("expect {}", kind),
+                    usage: "expect <url|title> <pattern> [--timeout <ms>]",
+                });
+            }
+            json!({ "id": id, "action": "expect", "kind": kind, "expected": args[1..].join(" ") })
+        }
+        Some(sub) => {
+            return Err(ParseError::UnknownSubcommand {
+                subcommand: sub.to_string(),
+                valid_options: VALID,
+            })
+        }
+        None => {
+            return Err(ParseError::MissingArguments {
+                context: "expect".to_string(),
+                usage: USAGE,
+            })
+        }
+    };
+    if let Some(ms) = timeout {
+        cmd["timeout"] = json!(ms);
+    }
+    Ok(cmd)
+}
+
+fn parse_is(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["visible", "enabled", "checked"];
+
+    match rest.get(0).map(|s| *s) {
+        Some("visible") => {
+            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "is visible".to_string(),
+                usage: "is visible <selector>",
+            })?;
+            Ok(json!({ "id": id, "action": "isvisible", "selector": sel }))
+        }
+        Some("enabled") => {
+            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "is enabled".to_string(),
+                usage: "is enabled <selector>",
+            })?;
+            Ok(json!({ "id": id, "action": "isenabled", "selector": sel }))
+        }
+        Some("checked") => {
+            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "is checked".to_string(),
+                usage: "is checked <selector>",
+            })?;
+            Ok(json!({ "id": id, "action": "ischecked", "selector": sel }))
+        }
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "is".to_string(),
+            usage: "is <visible|enabled|checked> <selector>",
+        }),
+    }
+}
+
+/// Which matches of a selector `click`, `hover`, `check` and `get text` act
+/// on; without `--nth` or `--all` the selector must match one element
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pick {
+    Only,
+    /// 0 for the first match, -1 for the last
+    Nth(i32),
+    All,
+}
+
+impl Pick {
+    fn apply(self, mut cmd: Value) -> Value {
+        match self {
+            Pick::Only => {}
+            Pick::Nth(index) => cmd["index"] = json!(index),
+            Pick::All => cmd["all"] = json!(true),
+        }
+        cmd
+    }
+}
+
+fn parse_nth(value: &str, usage: &'static str) -> Result<i32, ParseError> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|&n| n >= -1)
+        .ok_or_else(|| ParseError::InvalidValue {
+            message: format!(
+                "Invalid --nth: {} (expected 0 for the first match, -1 for the last)",
+                value
+            ),
+            usage,
+        })
+}
+
+/// Take `--nth <n>` and `--all` out of `rest`
+fn take_pick<'a>(
+    rest: &[&'a str],
+    usage: &'static str,
+) -> Result<(Vec<&'a str>, Pick), ParseError> {
+    let mut pick = Pick::Only;
+    let mut args = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        let next = match arg {
+            "--all" => Pick::All,
+            "--nth" => {
+                let value = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "--nth".to_string(),
+                    usage,
+                })?;
+                Pick::Nth(parse_nth(value, usage)?)
+            }
+            _ => {
+                args.push(arg);
+                continue;
+            }
+        };
+        if pick != Pick::Only && pick != next {
+            return Err(ParseError::InvalidValue {
+                message: "Use either --nth or --all, not both".to_string(),
+                usage,
+            });
+        }
+        pick = next;
+    }
+    Ok((args, pick))
+}
+
+/// `click --text <text> [--exact] [--nth <n>]`, and the same for `hover`: the
+/// daemon finds the element by its accessible name or visible text
+fn parse_by_name(rest: &[&str], id: &str, subaction: &str) -> Result<Value, ParseError> {
+    let usage = match subaction {
+        "hover" => "hover --text <text> [--exact] [--nth <n>]",
+        _ => "click --text <text> [--exact] [--nth <n>]",
+    };
+
+    let mut cmd = json!({ "id": id, "action": "getbyname", "subaction": subaction });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--exact" => cmd["exact"] = json!(true),
+            flag @ ("--text" | "--nth") => {
+                let value = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: format!("{} {}", subaction, flag),
+                        usage,
+                    })?;
+                if flag == "--text" {
+                    cmd["text"] = json!(value);
+                } else {
+                    cmd["index"] = json!(parse_nth(value, usage)?);
+                }
+                i += 1;
+            }
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage,
+                })
+            }
+        }
+        i += 1;
+    }
+    if cmd["text"].as_str().is_none_or(str::is_empty) {
+        return Err(ParseError::MissingArguments {
+            context: format!("{} --text", subaction),
+            usage,
+        });
+    }
+    Ok(cmd)
+}
+
+fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const LOCATORS: &[&str] = &[
+        "role",
+        "text",
+        "label",
+        "placeholder",
+        "alt",
+        "title",
+        "testid",
+        "first",
+        "last",
+        "nth",
+    ];
+    const ACTIONS: &[&str] = &[
+        "click", "fill", "type", "hover", "focus", "check", "uncheck",
+    ];
+
+    let locator = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+        context: "find".to_string(),
+        usage: "find <locator> <value> [action] [text] | find <query> [--role <role>]",
+    })?;
+
+    let name_idx = rest.iter().position(|&s| s == "--name");
+    let name = name_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
+    let exact = rest.iter().any(|&s| s == "--exact");
 
     match *locator {
         "role" | "text" | "label" | "placeholder" | "alt" | "title" | "testid" | "first"
@@ -1130,15 +2673,96 @@ fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             }
             Ok(cmd)
         }
-        _ => Err(ParseError::UnknownSubcommand {
-            subcommand: locator.to_string(),
-            valid_options: VALID,
-        }),
+        // `find Role button` or `find rol button click` is a mistyped locator,
+        // not a query for the whole line
+        other
+            if !other.starts_with('-')
+                && (LOCATORS.iter().any(|l| l.eq_ignore_ascii_case(other))
+                    || rest.get(2).is_some_and(|action| ACTIONS.contains(action))) =>
+        {
+            Err(ParseError::UnknownSubcommand {
+                subcommand: other.to_string(),
+                valid_options: LOCATORS,
+            })
+        }
+        _ => parse_find_query(rest, id),
+    }
+}
+
+/// `find <query> [--role <role>] [--limit <n>]`: rank elements by how well
+/// their accessible name matches a free-text query.
+fn parse_find_query(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "find <query> [--role <role>] [--limit <n>]";
+
+    let mut cmd = json!({ "id": id, "action": "find" });
+    let mut words = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--role" => {
+                let role = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "find --role".to_string(),
+                    usage: USAGE,
+                })?;
+                cmd["role"] = json!(role);
+            }
+            "--limit" => {
+                let limit = iter
+                    .next()
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: "--limit requires a positive number".to_string(),
+                        usage: USAGE,
+                    })?;
+                cmd["limit"] = json!(limit);
+            }
+            _ => words.push(arg),
+        }
+    }
+    if words.is_empty() {
+        return Err(ParseError::MissingArguments {
+            context: "find".to_string(),
+            usage: USAGE,
+        });
+    }
+    cmd["query"] = json!(words.join(" "));
+    Ok(cmd)
+}
+
+/// `visible-elements [--role <role>[,<role>...]]`: interactive elements
+/// inside the viewport. `--role` can be repeated.
+fn parse_visible_elements(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "visible-elements [--role <role>[,<role>...]]";
+
+    let mut roles: Vec<&str> = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--role" => {
+                let value = iter.next().ok_or_else(|| ParseError::MissingArguments {
+                    context: "visible-elements --role".to_string(),
+                    usage: USAGE,
+                })?;
+                roles.extend(value.split(',').map(str::trim).filter(|r| !r.is_empty()));
+            }
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unknown visible-elements option: {}", arg),
+                    usage: USAGE,
+                })
+            }
+        }
     }
+    let mut cmd = json!({ "id": id, "action": "visible_elements" });
+    if !roles.is_empty() {
+        cmd["roles"] = json!(roles);
+    }
+    Ok(cmd)
 }
 
 fn parse_mouse(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["move", "down", "up", "wheel"];
+    const VALID: &[&str] = &["move", "click", "down", "up", "wheel"];
 
     match rest.get(0).map(|s| *s) {
         Some("move") => {
@@ -1164,11 +2788,39 @@ fn parse_mouse(rest: &[&str], id: &str) -> Result<Value, ParseError> {
                 })?;
             Ok(json!({ "id": id, "action": "mousemove", "x": x, "y": y }))
         }
+        Some("click") => {
+            const USAGE: &str = "mouse click <x> <y> [--button left|right|middle] [--double]";
+            let coord = |i: usize| {
+                rest.get(i)
+                    .and_then(|s| s.parse::<i32>().ok())
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "mouse click".to_string(),
+                        usage: USAGE,
+                    })
+            };
+            let (x, y) = (coord(1)?, coord(2)?);
+            let button = mouse_button(&rest[3..], USAGE)?;
+            let click_count = if rest[3..].contains(&"--double") {
+                2
+            } else {
+                1
+            };
+            Ok(json!({
+                "id": id,
+                "action": "mouseclick",
+                "x": x,
+                "y": y,
+                "button": button,
+                "clickCount": click_count,
+            }))
+        }
         Some("down") => {
-            Ok(json!({ "id": id, "action": "mousedown", "button": rest.get(1).unwrap_or(&"left") }))
+            let button = mouse_button(&rest[1..], "mouse down [--button left|right|middle]")?;
+            Ok(json!({ "id": id, "action": "mousedown", "button": button }))
         }
         Some("up") => {
-            Ok(json!({ "id": id, "action": "mouseup", "button": rest.get(1).unwrap_or(&"left") }))
+            let button = mouse_button(&rest[1..], "mouse up [--button left|right|middle]")?;
+            Ok(json!({ "id": id, "action": "mouseup", "button": button }))
         }
         Some("wheel") => {
             let dy = rest
@@ -1184,97 +2836,338 @@ fn parse_mouse(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         }),
         None => Err(ParseError::MissingArguments {
             context: "mouse".to_string(),
-            usage: "mouse <move|down|up|wheel> [args...]",
+            usage: "mouse <move|click|down|up|wheel> [args...]",
         }),
     }
 }
 
-fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &[
-        "viewport",
-        "device",
-        "geo",
-        "geolocation",
-        "offline",
-        "headers",
-        "credentials",
-        "auth",
-        "media",
-    ];
+/// Mouse button from `--button <name>` or a bare `<name>` argument (the
+/// original `mouse down right` form). Defaults to left.
+fn mouse_button<'a>(args: &[&'a str], usage: &'static str) -> Result<&'a str, ParseError> {
+    let button = match args.iter().position(|&a| a == "--button") {
+        Some(i) => args.get(i + 1).copied(),
+        None => args.iter().copied().find(|a| !a.starts_with("--")),
+    };
+    match button {
+        None => Ok("left"),
+        Some(b @ ("left" | "right" | "middle")) => Ok(b),
+        Some(other) => Err(ParseError::InvalidValue {
+            message: format!(
+                "Invalid mouse button: {} (expected left, right or middle)",
+                other
+            ),
+            usage,
+        }),
+    }
+}
 
-    match rest.get(0).map(|s| *s) {
-        Some("viewport") => {
-            let w_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "set viewport".to_string(),
-                usage: "set viewport <width> <height>",
-            })?;
-            let h_str = rest.get(2).ok_or_else(|| ParseError::MissingArguments {
-                context: "set viewport".to_string(),
-                usage: "set viewport <width> <height>",
-            })?;
-            let w = w_str
-                .parse::<i32>()
-                .map_err(|_| ParseError::MissingArguments {
-                    context: "set viewport".to_string(),
-                    usage: "set viewport <width> <height>",
-                })?;
-            let h = h_str
-                .parse::<i32>()
-                .map_err(|_| ParseError::MissingArguments {
-                    context: "set viewport".to_string(),
-                    usage: "set viewport <width> <height>",
-                })?;
-            Ok(json!({ "id": id, "action": "viewport", "width": w, "height": h }))
-        }
-        Some("device") => {
-            let dev = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "set device".to_string(),
-                usage: "set device <name>",
-            })?;
-            Ok(json!({ "id": id, "action": "device", "device": dev }))
+/// `<width> <height> [--scale <n>]`, shared by `viewport` and `set viewport`.
+fn parse_viewport(
+    rest: &[&str],
+    id: &str,
+    context: &str,
+    usage: &'static str,
+) -> Result<Value, ParseError> {
+    let (w_str, h_str) = match rest {
+        [w, h, ..] => (w, h),
+        _ => {
+            return Err(ParseError::MissingArguments {
+                context: context.to_string(),
+                usage,
+            })
         }
-        Some("geo") | Some("geolocation") => {
-            let lat_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "set geo".to_string(),
-                usage: "set geo <latitude> <longitude>",
-            })?;
-            let lng_str = rest.get(2).ok_or_else(|| ParseError::MissingArguments {
-                context: "set geo".to_string(),
-                usage: "set geo <latitude> <longitude>",
-            })?;
-            let lat = lat_str
-                .parse::<f64>()
-                .map_err(|_| ParseError::MissingArguments {
-                    context: "set geo".to_string(),
-                    usage: "set geo <latitude> <longitude>",
-                })?;
-            let lng = lng_str
+    };
+    let dimension = |value: &str| {
+        value
+            .parse::<u32>()
+            .ok()
+            .filter(|v| *v > 0)
+            .ok_or_else(|| ParseError::InvalidValue {
+                message: format!("Invalid viewport size: {}", value),
+                usage,
+            })
+    };
+    let mut cmd = json!({
+        "id": id,
+        "action": "viewport",
+        "width": dimension(w_str)?,
+        "height": dimension(h_str)?,
+    });
+    match &rest[2..] {
+        [] => {}
+        ["--scale", scale] => {
+            let scale = scale
                 .parse::<f64>()
-                .map_err(|_| ParseError::MissingArguments {
-                    context: "set geo".to_string(),
-                    usage: "set geo <latitude> <longitude>",
+                .ok()
+                .filter(|s| *s > 0.0 && s.is_finite())
+                .ok_or_else(|| ParseError::InvalidValue {
+                    message: format!("Invalid scale: {}", scale),
+                    usage,
                 })?;
-            Ok(json!({ "id": id, "action": "geolocation", "latitude": lat, "longitude": lng }))
+            cmd["deviceScaleFactor"] = json!(scale);
         }
-        Some("offline") => {
-            let off = rest
-                .get(1)
-                .map(|s| *s != "off" && *s != "false")
-                .unwrap_or(true);
-            Ok(json!({ "id": id, "action": "offline", "offline": off }))
+        ["--scale"] => {
+            return Err(ParseError::MissingArguments {
+                context: format!("{} --scale", context),
+                usage,
+            })
         }
-        Some("headers") => {
-            let headers_json = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "set headers".to_string(),
-                usage: "set headers <json>",
-            })?;
-            // Parse the JSON string into an object
-            let headers: serde_json::Value =
-                serde_json::from_str(headers_json).map_err(|_| ParseError::MissingArguments {
-                    context: "set headers".to_string(),
-                    usage: "set headers <json> (must be valid JSON object)",
-                })?;
-            Ok(json!({ "id": id, "action": "headers", "headers": headers }))
+        [other, ..] => {
+            return Err(ParseError::InvalidValue {
+                message: format!("Unexpected argument: {}", other),
+                usage,
+            })
+        }
+    }
+    Ok(cmd)
+}
+
+fn parse_emulate(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["geo", "locale", "timezone", "reset"];
+    const GEO_USAGE: &str = "emulate geo <latitude> <longitude> [--accuracy <meters>]";
+
+    match rest.first().copied() {
+        Some("geo") => {
+            let (lat_str, lng_str) = match rest.get(1..3) {
+                Some([lat, lng]) => (*lat, *lng),
+                _ => {
+                    return Err(ParseError::MissingArguments {
+                        context: "emulate geo".to_string(),
+                        usage: GEO_USAGE,
+                    })
+                }
+            };
+            let coordinate = |value: &str, limit: f64| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.abs() <= limit)
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: format!("Invalid coordinate: {}", value),
+                        usage: GEO_USAGE,
+                    })
+            };
+            let mut cmd = json!({
+                "id": id,
+                "action": "geolocation",
+                "latitude": coordinate(lat_str, 90.0)?,
+                "longitude": coordinate(lng_str, 180.0)?,
+            });
+            match &rest[3..] {
+                [] => {}
+                ["--accuracy", meters] => {
+                    let accuracy = meters
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|m| *m >= 0.0)
+                        .ok_or_else(|| ParseError::InvalidValue {
+                            message: format!("Invalid accuracy: {}", meters),
+                            usage: GEO_USAGE,
+                        })?;
+                    cmd["accuracy"] = json!(accuracy);
+                }
+                [other, ..] => {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Unexpected argument: {}", other),
+                        usage: GEO_USAGE,
+                    })
+                }
+            }
+            Ok(cmd)
+        }
+        Some("locale") => match rest.get(1) {
+            Some(locale) => Ok(json!({ "id": id, "action": "locale", "locale": locale })),
+            None => Err(ParseError::MissingArguments {
+                context: "emulate locale".to_string(),
+                usage: "emulate locale <tag>",
+            }),
+        },
+        Some("timezone") => match rest.get(1) {
+            Some(tz) => Ok(json!({ "id": id, "action": "timezone", "timezone": tz })),
+            None => Err(ParseError::MissingArguments {
+                context: "emulate timezone".to_string(),
+                usage: "emulate timezone <tz>",
+            }),
+        },
+        Some("reset") => Ok(json!({ "id": id, "action": "emulate_reset" })),
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "emulate".to_string(),
+            usage: "emulate <geo|locale|timezone|reset> [args...]",
+        }),
+    }
+}
+
+/// Permissions `permissions grant` knows, as Playwright names them
+const PERMISSIONS: &[&str] = &[
+    "accelerometer",
+    "ambient-light-sensor",
+    "background-sync",
+    "camera",
+    "clipboard-read",
+    "clipboard-write",
+    "geolocation",
+    "gyroscope",
+    "local-fonts",
+    "magnetometer",
+    "microphone",
+    "midi",
+    "midi-sysex",
+    "notifications",
+    "payment-handler",
+    "storage-access",
+];
+
+fn parse_permissions(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["grant", "clear"];
+    const USAGE: &str = "permissions grant <origin|*> <permission...>";
+
+    match rest.first().copied() {
+        Some("grant") => {
+            let (origin, names) = match rest.get(1..) {
+                Some([origin, names @ ..]) if !names.is_empty() => (*origin, names),
+                _ => {
+                    return Err(ParseError::MissingArguments {
+                        context: "permissions grant".to_string(),
+                        usage: USAGE,
+                    })
+                }
+            };
+            let mut permissions = Vec::new();
+            for name in names.iter().flat_map(|n| n.split(',')) {
+                if !PERMISSIONS.contains(&name) {
+                    return Err(ParseError::InvalidValue {
+                        message: format!(
+                            "Unknown permission: {}\nKnown permissions: {}",
+                            name,
+                            PERMISSIONS.join(", ")
+                        ),
+                        usage: USAGE,
+                    });
+                }
+                permissions.push(name);
+            }
+            let mut cmd = json!({
+                "id": id,
+                "action": "permissions",
+                "permissions": permissions,
+                "grant": true,
+            });
+            // `*` grants them to every origin; a bare host means https, as for open
+            if origin != "*" {
+                cmd["origin"] = json!(if origin.contains("://") {
+                    origin.trim_end_matches('/').to_string()
+                } else {
+                    format!("https://{}", origin.trim_end_matches('/'))
+                });
+            }
+            Ok(cmd)
+        }
+        Some("clear") => Ok(json!({
+            "id": id,
+            "action": "permissions",
+            "permissions": [],
+            "grant": false,
+        })),
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "permissions".to_string(),
+            usage: "permissions <grant|clear> [origin] [permission...]",
+        }),
+    }
+}
+
+/// What `set` changes; in scripts `set` with any other name defines a variable
+pub const SET_OPTIONS: &[&str] = &[
+    "viewport",
+    "device",
+    "geo",
+    "geolocation",
+    "offline",
+    "headers",
+    "user-agent",
+    "useragent",
+    "credentials",
+    "auth",
+    "media",
+];
+
+fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = SET_OPTIONS;
+
+    match rest.get(0).map(|s| *s) {
+        Some("viewport") => parse_viewport(
+            &rest[1..],
+            id,
+            "set viewport",
+            "set viewport <width> <height> [--scale <n>]",
+        ),
+        Some("device") => {
+            if rest.len() < 2 {
+                return Err(ParseError::MissingArguments {
+                    context: "set device".to_string(),
+                    usage: "set device <name>",
+                });
+            }
+            Ok(json!({ "id": id, "action": "device", "device": rest[1..].join(" ") }))
+        }
+        Some("geo") | Some("geolocation") => {
+            let lat_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "set geo".to_string(),
+                usage: "set geo <latitude> <longitude>",
+            })?;
+            let lng_str = rest.get(2).ok_or_else(|| ParseError::MissingArguments {
+                context: "set geo".to_string(),
+                usage: "set geo <latitude> <longitude>",
+            })?;
+            let lat = lat_str
+                .parse::<f64>()
+                .map_err(|_| ParseError::MissingArguments {
+                    context: "set geo".to_string(),
+                    usage: "set geo <latitude> <longitude>",
+                })?;
+            let lng = lng_str
+                .parse::<f64>()
+                .map_err(|_| ParseError::MissingArguments {
+                    context: "set geo".to_string(),
+                    usage: "set geo <latitude> <longitude>",
+                })?;
+            Ok(json!({ "id": id, "action": "geolocation", "latitude": lat, "longitude": lng }))
+        }
+        Some("offline") => {
+            let off = rest
+                .get(1)
+                .map(|s| *s != "off" && *s != "false")
+                .unwrap_or(true);
+            Ok(json!({ "id": id, "action": "offline", "offline": off }))
+        }
+        Some("headers") => {
+            let headers_json = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "set headers".to_string(),
+                usage: "set headers <json>",
+            })?;
+            // Parse the JSON string into an object
+            let headers: serde_json::Value =
+                serde_json::from_str(headers_json).map_err(|_| ParseError::MissingArguments {
+                    context: "set headers".to_string(),
+                    usage: "set headers <json> (must be valid JSON object)",
+                })?;
+            Ok(json!({ "id": id, "action": "headers", "headers": headers }))
+        }
+        Some("user-agent") | Some("useragent") => {
+            if rest.len() < 2 {
+                return Err(ParseError::MissingArguments {
+                    context: "set user-agent".to_string(),
+                    usage: "set user-agent <ua>",
+                });
+            }
+            Ok(json!({ "id": id, "action": "useragent", "userAgent": rest[1..].join(" ") }))
         }
         Some("credentials") | Some("auth") => {
             let user = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
@@ -1310,979 +3203,4473 @@ fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         }),
         None => Err(ParseError::MissingArguments {
             context: "set".to_string(),
-            usage: "set <viewport|device|geo|offline|headers|credentials|media> [args...]",
+            usage:
+                "set <viewport|device|geo|offline|headers|user-agent|credentials|media> [args...]",
         }),
     }
 }
 
-fn parse_network(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["route", "unroute", "requests"];
+fn parse_press(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "press <key|chord> [x<count>]";
 
-    match rest.get(0).map(|s| *s) {
-        Some("route") => {
-            let url = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "network route".to_string(),
-                usage: "network route <url> [--abort|--body <json>]",
+    let key = rest.first().ok_or_else(|| ParseError::MissingArguments {
+        context: "press".to_string(),
+        usage: USAGE,
+    })?;
+    let count = match rest.get(1) {
+        None => None,
+        Some(repeat) => {
+            // `press Tab x3` presses Tab three times
+            let count = repeat
+                .strip_prefix(['x', 'X'])
+                .and_then(|n| n.parse::<u32>().ok())
+                .filter(|n| (1..=1000).contains(n))
+                .ok_or_else(|| ParseError::InvalidValue {
+                    message: format!("Invalid repeat count: {} (expected e.g. x3)", repeat),
+                    usage: USAGE,
+                })?;
+            Some(count).filter(|&n| n > 1)
+        }
+    };
+    Ok(Command::Press {
+        key: normalize_key(key),
+        selector: None,
+        count,
+    }
+    .into_request(id))
+}
+
+/// Canonicalize modifier aliases in a key chord so `ctrl+shift+p` and
+/// `Cmd+K` reach Playwright as `Control+Shift+p` and `Meta+K`. Key names other
+/// than modifiers are passed through unchanged.
+fn normalize_key(chord: &str) -> String {
+    // A lone "+" (or a chord ending in "++") names the plus key itself
+    if chord == "+" {
+        return chord.to_string();
+    }
+    let (keys, plus) = match chord.strip_suffix("++") {
+        Some(head) => (head, true),
+        None => (chord, false),
+    };
+    let mut parts: Vec<&str> = keys
+        .split('+')
+        .map(|part| match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => "Control",
+            "shift" => "Shift",
+            "alt" | "option" | "opt" => "Alt",
+            "cmd" | "command" | "meta" | "super" | "win" => "Meta",
+            "mod" | "controlormeta" => "ControlOrMeta",
+            _ => part,
+        })
+        .collect();
+    if plus {
+        parts.push("+");
+    }
+    parts.join("+")
+}
+
+fn parse_drag(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "drag <source> <target> [--steps <n>]";
+    let steps_idx = rest.iter().position(|&s| s == "--steps");
+    let positional: Vec<&str> = rest
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| steps_idx.is_none_or(|s| *i != s && *i != s + 1))
+        .map(|(_, a)| *a)
+        .collect();
+    let src = positional
+        .first()
+        .ok_or_else(|| ParseError::MissingArguments {
+            context: "drag".to_string(),
+            usage: USAGE,
+        })?;
+    let tgt = positional
+        .get(1)
+        .ok_or_else(|| ParseError::MissingArguments {
+            context: "drag".to_string(),
+            usage: USAGE,
+        })?;
+    let mut cmd = json!({ "id": id, "action": "drag", "source": src, "target": tgt });
+    if let Some(i) = steps_idx {
+        let steps = rest
+            .get(i + 1)
+            .and_then(|s| s.parse::<u32>().ok())
+            .filter(|&n| n > 0)
+            .ok_or_else(|| ParseError::InvalidValue {
+                message: "--steps requires a positive number".to_string(),
+                usage: USAGE,
             })?;
-            let abort = rest.iter().any(|&s| s == "--abort");
-            let body_idx = rest.iter().position(|&s| s == "--body");
-            let body = body_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
-            Ok(json!({ "id": id, "action": "route", "url": url, "abort": abort, "body": body }))
+        cmd["steps"] = json!(steps);
+    }
+    Ok(cmd)
+}
+
+fn parse_scroll(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    match rest.first().copied() {
+        Some("to") => {
+            let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "scroll to".to_string(),
+                usage: "scroll to <selector>",
+            })?;
+            let selector = sel.to_string();
+            Ok(Command::Scrollintoview { selector }.into_request(id))
         }
-        Some("unroute") => {
-            let mut cmd = json!({ "id": id, "action": "unroute" });
-            if let Some(url) = rest.get(1) {
-                cmd["url"] = json!(url);
+        Some("by") => {
+            let delta = |i: usize| {
+                rest.get(i)
+                    .and_then(|s| s.parse::<i32>().ok())
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: "scroll by needs two pixel offsets".to_string(),
+                        usage: "scroll by <dx> <dy>",
+                    })
+            };
+            let (x, y) = (delta(1)?, delta(2)?);
+            Ok(json!({ "id": id, "action": "scroll", "x": x, "y": y }))
+        }
+        Some(position @ ("top" | "bottom")) => {
+            Ok(json!({ "id": id, "action": "scroll", "position": position }))
+        }
+        dir => {
+            let amount = rest
+                .get(1)
+                .and_then(|s| s.parse::<i32>().ok())
+                .unwrap_or(300);
+            let dir = dir.unwrap_or("down");
+            Ok(json!({ "id": id, "action": "scroll", "direction": dir, "amount": amount }))
+        }
+    }
+}
+
+fn parse_select(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "select <selector> <value...|--label <text>...|--index <n>>";
+
+    let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
+        context: "select".to_string(),
+        usage: USAGE,
+    })?;
+
+    let mut values: Vec<&str> = Vec::new();
+    let mut labels: Vec<&str> = Vec::new();
+    let mut index: Option<u32> = None;
+    let mut i = 1;
+    while i < rest.len() {
+        match rest[i] {
+            "--label" => {
+                let label = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "select --label".to_string(),
+                        usage: USAGE,
+                    })?;
+                labels.push(label);
+                i += 1;
+            }
+            "--index" => {
+                let n = rest
+                    .get(i + 1)
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: "--index requires a non-negative number".to_string(),
+                        usage: USAGE,
+                    })?;
+                index = Some(n);
+                i += 1;
+            }
+            value => values.push(value),
+        }
+        i += 1;
+    }
+
+    let kinds = [!values.is_empty(), !labels.is_empty(), index.is_some()];
+    let mut cmd = json!({ "id": id, "action": "select", "selector": sel });
+    match kinds.iter().filter(|&&k| k).count() {
+        0 => {
+            return Err(ParseError::MissingArguments {
+                context: "select".to_string(),
+                usage: USAGE,
+            })
+        }
+        1 => {}
+        _ => {
+            return Err(ParseError::InvalidValue {
+                message: "Use only one of: values, --label, --index".to_string(),
+                usage: USAGE,
+            })
+        }
+    }
+
+    if let Some(n) = index {
+        cmd["index"] = json!(n);
+    } else if !labels.is_empty() {
+        cmd["label"] = one_or_many(&labels);
+    } else {
+        cmd["values"] = one_or_many(&values);
+    }
+    Ok(cmd)
+}
+
+/// A single string stays a string; several become an array
+fn one_or_many(items: &[&str]) -> Value {
+    if items.len() == 1 {
+        json!(items[0])
+    } else {
+        json!(items)
+    }
+}
+
+fn parse_frame(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    match rest.first().copied() {
+        Some("main") => Ok(json!({ "id": id, "action": "mainframe" })),
+        Some("list") => Ok(json!({ "id": id, "action": "frame_list" })),
+        Some("use") => {
+            let target = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "frame use".to_string(),
+                usage: "frame use <selector|index>",
+            })?;
+            // Numbers are indexes from `frame list`; anything else is a selector
+            match target.parse::<u32>() {
+                Ok(index) => Ok(json!({ "id": id, "action": "frame", "index": index })),
+                Err(_) => Ok(json!({ "id": id, "action": "frame", "selector": target })),
+            }
+        }
+        Some(sel) => Ok(json!({ "id": id, "action": "frame", "selector": sel })),
+        None => Err(ParseError::MissingArguments {
+            context: "frame".to_string(),
+            usage: "frame <list|use <selector|index>|main>",
+        }),
+    }
+}
+
+fn parse_dialog(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["accept", "dismiss", "auto"];
+    const AUTO_VALID: &[&str] = &["accept", "dismiss", "off"];
+
+    match rest.first().copied() {
+        Some(response @ ("accept" | "dismiss")) => {
+            let mut cmd = json!({ "id": id, "action": "dialog", "response": response });
+            if response == "accept" && rest.len() > 1 {
+                cmd["promptText"] = json!(rest[1..].join(" "));
             }
             Ok(cmd)
         }
-        Some("requests") => {
-            let clear = rest.iter().any(|&s| s == "--clear");
-            let filter_idx = rest.iter().position(|&s| s == "--filter");
-            let filter = filter_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
-            let mut cmd = json!({ "id": id, "action": "requests", "clear": clear });
-            if let Some(f) = filter {
-                cmd["filter"] = json!(f);
+        Some("auto") => match rest.get(1).copied() {
+            Some(response @ ("accept" | "dismiss" | "off")) => {
+                let mut cmd = json!({ "id": id, "action": "dialog_auto", "response": response });
+                if response == "accept" && rest.len() > 2 {
+                    cmd["promptText"] = json!(rest[2..].join(" "));
+                }
+                Ok(cmd)
+            }
+            Some(sub) => Err(ParseError::UnknownSubcommand {
+                subcommand: sub.to_string(),
+                valid_options: AUTO_VALID,
+            }),
+            None => Err(ParseError::MissingArguments {
+                context: "dialog auto".to_string(),
+                usage: "dialog auto <accept|dismiss|off> [text]",
+            }),
+        },
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "dialog".to_string(),
+            usage: "dialog <accept|dismiss|auto> [args...]",
+        }),
+    }
+}
+
+fn parse_trace(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["start", "stop"];
+    const START_USAGE: &str = "trace start [--screenshots] [--snapshots]";
+
+    match rest.first().copied() {
+        Some("start") => {
+            let mut cmd = json!({ "id": id, "action": "trace_start" });
+            // Naming either part records only what was named; neither records both
+            let screenshots = rest.contains(&"--screenshots");
+            let snapshots = rest.contains(&"--snapshots");
+            if screenshots || snapshots {
+                cmd["screenshots"] = json!(screenshots);
+                cmd["snapshots"] = json!(snapshots);
+            }
+            if let Some(other) = rest[1..]
+                .iter()
+                .find(|a| **a != "--screenshots" && **a != "--snapshots")
+            {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unknown trace start option: {}", other),
+                    usage: START_USAGE,
+                });
             }
             Ok(cmd)
         }
+        Some("stop") => {
+            let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "trace stop".to_string(),
+                usage: "trace stop <file.zip>",
+            })?;
+            Ok(json!({ "id": id, "action": "trace_stop", "path": absolute_path(path) }))
+        }
         Some(sub) => Err(ParseError::UnknownSubcommand {
             subcommand: sub.to_string(),
             valid_options: VALID,
         }),
         None => Err(ParseError::MissingArguments {
-            context: "network".to_string(),
-            usage: "network <route|unroute|requests> [args...]",
+            context: "trace".to_string(),
+            usage: "trace <start|stop> [args...]",
         }),
     }
 }
 
-fn parse_storage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["local", "session"];
+fn parse_coverage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["start", "stop"];
+    const STOP_USAGE: &str = "coverage stop [--lcov <file.info>]";
 
-    match rest.get(0).map(|s| *s) {
-        Some("local") | Some("session") => {
-            let storage_type = rest.get(0).unwrap();
-            let op = rest.get(1).unwrap_or(&"get");
-            let key = rest.get(2);
-            let value = rest.get(3);
-            match *op {
-                "set" => {
-                    let k = key.ok_or_else(|| ParseError::MissingArguments {
-                        context: format!("storage {} set", storage_type),
-                        usage: "storage <local|session> set <key> <value>",
-                    })?;
-                    let v = value.ok_or_else(|| ParseError::MissingArguments {
-                        context: format!("storage {} set", storage_type),
-                        usage: "storage <local|session> set <key> <value>",
-                    })?;
-                    Ok(
-                        json!({ "id": id, "action": "storage_set", "type": storage_type, "key": k, "value": v }),
-                    )
-                }
-                "clear" => Ok(json!({ "id": id, "action": "storage_clear", "type": storage_type })),
-                _ => {
-                    let mut cmd =
-                        json!({ "id": id, "action": "storage_get", "type": storage_type });
-                    if let Some(k) = key {
-                        cmd.as_object_mut()
-                            .unwrap()
-                            .insert("key".to_string(), json!(k));
+    match rest.first().copied() {
+        Some("start") => Ok(json!({ "id": id, "action": "coverage_start" })),
+        Some("stop") => {
+            let mut cmd = json!({ "id": id, "action": "coverage_stop" });
+            let mut i = 1;
+            while i < rest.len() {
+                match rest[i] {
+                    "--lcov" => {
+                        let path = rest
+                            .get(i + 1)
+                            .ok_or_else(|| ParseError::MissingArguments {
+                                context: "coverage stop --lcov".to_string(),
+                                usage: STOP_USAGE,
+                            })?;
+                        cmd["lcov"] = json!(absolute_path(path));
+                        i += 1;
+                    }
+                    other => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Unknown coverage stop option: {}", other),
+                            usage: STOP_USAGE,
+                        })
                     }
-                    Ok(cmd)
                 }
+                i += 1;
             }
+            Ok(cmd)
         }
         Some(sub) => Err(ParseError::UnknownSubcommand {
             subcommand: sub.to_string(),
             valid_options: VALID,
         }),
         None => Err(ParseError::MissingArguments {
-            context: "storage".to_string(),
-            usage: "storage <local|session> [get|set|clear] [key] [value]",
+            context: "coverage".to_string(),
+            usage: "coverage <start|stop> [--lcov <file.info>]",
         }),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn default_flags() -> Flags {
-        Flags {
-            session: "test".to_string(),
-            json: false,
-            full: false,
-            headed: false,
-            debug: false,
-            headers: None,
-            executable_path: None,
-            extensions: Vec::new(),
-            cdp: None,
-            profile: None,
-            state: None,
-            proxy: None,
-            proxy_bypass: None,
-            args: None,
-            user_agent: None,
-            provider: None,
-            ignore_https_errors: false,
-            allow_file_access: false,
-            device: None,
-            cli_executable_path: false,
-            cli_extensions: false,
-            cli_profile: false,
-            cli_state: false,
-            cli_args: false,
-            cli_user_agent: false,
-            cli_proxy: false,
-            cli_proxy_bypass: false,
-            cli_allow_file_access: false,
+fn parse_console(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "console [--follow] [--level error|warn|all] [--clear]";
+
+    let mut cmd = json!({ "id": id, "action": "console", "clear": false });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--clear" => cmd["clear"] = json!(true),
+            "--follow" => cmd["follow"] = json!(true),
+            "--level" => {
+                let level = rest
+                    .get(i + 1)
+                    .copied()
+                    .filter(|l| matches!(*l, "error" | "warn" | "all"))
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: "--level must be one of: error, warn, all".to_string(),
+                        usage: USAGE,
+                    })?;
+                cmd["level"] = json!(level);
+                i += 1;
+            }
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage: USAGE,
+                })
+            }
         }
+        i += 1;
+    }
+
+    if cmd.get("follow").is_some() && cmd["clear"] == true {
+        return Err(ParseError::InvalidValue {
+            message: "--follow cannot be combined with --clear".to_string(),
+            usage: USAGE,
+        });
+    }
+    Ok(cmd)
+}
+
+fn parse_events(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "events --follow [--types console,network,dialog]";
+    const TYPES: &[&str] = &["console", "network", "dialog"];
+
+    // Events are always streamed; --follow is accepted for symmetry with console
+    let mut cmd = json!({ "id": id, "action": "subscribe", "follow": true });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--follow" => {}
+            "--types" => {
+                let list = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "events --types".to_string(),
+                        usage: USAGE,
+                    })?;
+                let types: Vec<&str> = list.split(',').map(str::trim).collect();
+                if let Some(bad) = types.iter().find(|t| !TYPES.contains(t)) {
+                    return Err(ParseError::InvalidValue {
+                        message: format!(
+                            "Unknown event type: {} (expected console, network or dialog)",
+                            bad
+                        ),
+                        usage: USAGE,
+                    });
+                }
+                cmd["types"] = json!(types);
+                i += 1;
+            }
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage: USAGE,
+                })
+            }
+        }
+        i += 1;
+    }
+    Ok(cmd)
+}
+
+fn parse_watch(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "watch <selector> [--event added|removed|text] [--once] [--timeout <ms>]";
+    const EVENTS: &[&str] = &["added", "removed", "text"];
+
+    // Changes are streamed like events; `follow` keeps it out of pipe and serve
+    let mut cmd = json!({ "id": id, "action": "watch", "follow": true });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--once" => cmd["once"] = json!(true),
+            "--event" => {
+                let list = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "watch --event".to_string(),
+                        usage: USAGE,
+                    })?;
+                let events: Vec<&str> = list.split(',').map(str::trim).collect();
+                if let Some(bad) = events.iter().find(|e| !EVENTS.contains(e)) {
+                    return Err(ParseError::InvalidValue {
+                        message: format!(
+                            "Unknown change: {} (expected added, removed or text)",
+                            bad
+                        ),
+                        usage: USAGE,
+                    });
+                }
+                cmd["events"] = json!(events);
+                i += 1;
+            }
+            other if cmd.get("selector").is_none() => cmd["selector"] = json!(other),
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage: USAGE,
+                })
+            }
+        }
+        i += 1;
+    }
+    if cmd.get("selector").is_none() {
+        return Err(ParseError::MissingArguments {
+            context: "watch".to_string(),
+            usage: USAGE,
+        });
+    }
+    Ok(cmd)
+}
+
+fn parse_highlight(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "highlight <selector> [--duration <ms>] [--label <text>]";
+
+    let mut cmd = json!({ "id": id, "action": "highlight" });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            flag @ ("--duration" | "--label") => {
+                let value = rest
+                    .get(i + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: format!("highlight {}", flag),
+                        usage: USAGE,
+                    })?;
+                if flag == "--label" {
+                    cmd["label"] = json!(value);
+                } else {
+                    let ms = parse_duration(value).ok_or_else(|| ParseError::InvalidValue {
+                        message: format!(
+                            "Invalid duration: {} (expected e.g. 1500, 5s or 0)",
+                            value
+                        ),
+                        usage: USAGE,
+                    })?;
+                    cmd["duration"] = json!(ms);
+                }
+                i += 1;
+            }
+            other if cmd.get("selector").is_none() => cmd["selector"] = json!(other),
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage: USAGE,
+                })
+            }
+        }
+        i += 1;
+    }
+    if cmd.get("selector").is_none() {
+        return Err(ParseError::MissingArguments {
+            context: "highlight".to_string(),
+            usage: USAGE,
+        });
+    }
+    Ok(cmd)
+}
+
+fn parse_network(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &[
+        "route", "unroute", "requests", "log", "capture", "show", "offline", "throttle",
+    ];
+
+    match rest.get(0).map(|s| *s) {
+        Some("route") => {
+            let url = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
+                context: "network route".to_string(),
+                usage: "network route <url> [--abort|--body <json>]",
+            })?;
+            let abort = rest.iter().any(|&s| s == "--abort");
+            let body_idx = rest.iter().position(|&s| s == "--body");
+            let body = body_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
+            Ok(json!({ "id": id, "action": "route", "url": url, "abort": abort, "body": body }))
+        }
+        Some("unroute") => {
+            let mut cmd = json!({ "id": id, "action": "unroute" });
+            if let Some(url) = rest.get(1) {
+                cmd["url"] = json!(url);
+            }
+            Ok(cmd)
+        }
+        Some("requests") => {
+            let clear = rest.iter().any(|&s| s == "--clear");
+            let filter_idx = rest.iter().position(|&s| s == "--filter");
+            let filter = filter_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
+            let mut cmd = json!({ "id": id, "action": "requests", "clear": clear });
+            if let Some(f) = filter {
+                cmd["filter"] = json!(f);
+            }
+            Ok(cmd)
+        }
+        Some("log") => {
+            let mut cmd = json!({ "id": id, "action": "network_log" });
+            let mut i = 1;
+            while i < rest.len() {
+                match rest[i] {
+                    "--filter" => {
+                        let pattern =
+                            rest.get(i + 1)
+                                .ok_or_else(|| ParseError::MissingArguments {
+                                    context: "network log --filter".to_string(),
+                                    usage: "network log [--filter <url-glob>] [--since <id>]",
+                                })?;
+                        cmd["filter"] = json!(pattern);
+                        i += 1;
+                    }
+                    "--since" => {
+                        let since = rest
+                            .get(i + 1)
+                            .and_then(|s| s.parse::<u64>().ok())
+                            .ok_or_else(|| ParseError::InvalidValue {
+                                message: "--since requires a request id".to_string(),
+                                usage: "network log [--filter <url-glob>] [--since <id>]",
+                            })?;
+                        cmd["since"] = json!(since);
+                        i += 1;
+                    }
+                    other => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Unexpected argument: {}", other),
+                            usage: "network log [--filter <url-glob>] [--since <id>]",
+                        })
+                    }
+                }
+                i += 1;
+            }
+            Ok(cmd)
+        }
+        Some("capture") => parse_network_capture(&rest[1..], id),
+        Some("show") => {
+            const USAGE: &str = "network show <id>";
+            let request_id = match rest.get(1) {
+                Some(s) => s.parse::<u64>().ok().filter(|&n| n > 0).ok_or_else(|| {
+                    ParseError::InvalidValue {
+                        message: format!("Expected a request id from network log, got: {}", s),
+                        usage: USAGE,
+                    }
+                })?,
+                None => {
+                    return Err(ParseError::MissingArguments {
+                        context: "network show".to_string(),
+                        usage: USAGE,
+                    })
+                }
+            };
+            Ok(json!({ "id": id, "action": "network_show", "requestId": request_id }))
+        }
+        Some("offline") => {
+            let offline = match rest.get(1).copied() {
+                Some("on") | Some("true") => true,
+                Some("off") | Some("false") => false,
+                Some(other) => {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Expected on or off, got: {}", other),
+                        usage: "network offline <on|off>",
+                    })
+                }
+                None => {
+                    return Err(ParseError::MissingArguments {
+                        context: "network offline".to_string(),
+                        usage: "network offline <on|off>",
+                    })
+                }
+            };
+            Ok(json!({ "id": id, "action": "offline", "offline": offline }))
+        }
+        Some("throttle") => parse_throttle(&rest[1..], id),
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "network".to_string(),
+            usage: "network <route|unroute|requests|log|capture|show|offline|throttle> [args...]",
+        }),
+    }
+}
+
+/// `network capture <url-glob> [--limit <n>]` or `network capture off`
+fn parse_network_capture(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "network capture <url-glob> [--limit <n>] | network capture off";
+
+    let mut cmd = json!({ "id": id, "action": "network_capture" });
+    let mut url = None;
+    let mut iter = rest.iter();
+    while let Some(&arg) = iter.next() {
+        match arg {
+            "--limit" => {
+                let limit = iter
+                    .next()
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: "--limit requires a positive number".to_string(),
+                        usage: USAGE,
+                    })?;
+                cmd["limit"] = json!(limit);
+            }
+            _ if url.is_none() => url = Some(arg),
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage: USAGE,
+                })
+            }
+        }
+    }
+    match url {
+        Some("off") if cmd.get("limit").is_none() => {
+            Ok(json!({ "id": id, "action": "network_capture_stop" }))
+        }
+        Some(url) => {
+            cmd["url"] = json!(url);
+            Ok(cmd)
+        }
+        None => Err(ParseError::MissingArguments {
+            context: "network capture".to_string(),
+            usage: USAGE,
+        }),
+    }
+}
+
+fn parse_throttle(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str =
+        "network throttle <slow3g|fast3g|off|custom> [--down <kbps>] [--up <kbps>] [--latency <ms>]";
+
+    let profile = match rest.first().copied() {
+        Some(p @ ("slow3g" | "fast3g" | "off" | "custom")) => p,
+        Some(other) => {
+            return Err(ParseError::InvalidValue {
+                message: format!("Unknown throttle profile: {}", other),
+                usage: USAGE,
+            })
+        }
+        None => {
+            return Err(ParseError::MissingArguments {
+                context: "network throttle".to_string(),
+                usage: USAGE,
+            })
+        }
+    };
+    let mut cmd = json!({ "id": id, "action": "throttle", "profile": profile });
+
+    let mut iter = rest[1..].iter();
+    while let Some(&flag) = iter.next() {
+        let key = match flag {
+            "--down" => "downloadKbps",
+            "--up" => "uploadKbps",
+            "--latency" => "latencyMs",
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument: {}", other),
+                    usage: USAGE,
+                })
+            }
+        };
+        if profile != "custom" {
+            return Err(ParseError::InvalidValue {
+                message: format!("{} only applies to the custom profile", flag),
+                usage: USAGE,
+            });
+        }
+        let value = iter.next().ok_or_else(|| ParseError::MissingArguments {
+            context: format!("network throttle {}", flag),
+            usage: USAGE,
+        })?;
+        let number = value
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && (*n > 0.0 || (key == "latencyMs" && *n == 0.0)))
+            .ok_or_else(|| ParseError::InvalidValue {
+                message: format!("Invalid {} value: {}", flag, value),
+                usage: USAGE,
+            })?;
+        cmd[key] = json!(number);
+    }
+
+    let limits = ["downloadKbps", "uploadKbps", "latencyMs"];
+    if profile == "custom" && !limits.iter().any(|key| cmd.get(*key).is_some()) {
+        return Err(ParseError::MissingArguments {
+            context: "network throttle custom".to_string(),
+            usage: USAGE,
+        });
+    }
+    Ok(cmd)
+}
+
+fn parse_mock(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["add", "list", "remove"];
+    const ADD_USAGE: &str =
+        "mock add <url-pattern> [--status <code>] [--body-file <file> | --body <text>] [--header <name:value>]...";
+
+    match rest.first().copied() {
+        Some("add") => {
+            let url = rest
+                .get(1)
+                .filter(|u| !u.starts_with("--"))
+                .ok_or_else(|| ParseError::MissingArguments {
+                    context: "mock add".to_string(),
+                    usage: ADD_USAGE,
+                })?;
+
+            let mut status: u16 = 200;
+            let mut body: Vec<u8> = Vec::new();
+            let mut content_type: Option<String> = None;
+            let mut headers = serde_json::Map::new();
+
+            let mut i = 2;
+            while i < rest.len() {
+                let value =
+                    rest.get(i + 1)
+                        .copied()
+                        .ok_or_else(|| ParseError::MissingArguments {
+                            context: format!("mock add {}", rest[i]),
+                            usage: ADD_USAGE,
+                        })?;
+                match rest[i] {
+                    "--status" => {
+                        status = value
+                            .parse()
+                            .ok()
+                            .filter(|s| (100..=599).contains(s))
+                            .ok_or_else(|| ParseError::InvalidValue {
+                                message: format!("Invalid status: {}", value),
+                                usage: ADD_USAGE,
+                            })?;
+                    }
+                    "--body" => body = value.as_bytes().to_vec(),
+                    "--body-file" => {
+                        body = std::fs::read(value).map_err(|e| ParseError::InvalidValue {
+                            message: format!("Failed to read {}: {}", value, e),
+                            usage: ADD_USAGE,
+                        })?;
+                        content_type = content_type.or(mime_type(value).map(String::from));
+                    }
+                    "--header" => {
+                        let (name, header_value) =
+                            value
+                                .split_once(':')
+                                .ok_or_else(|| ParseError::InvalidValue {
+                                    message: format!(
+                                        "Invalid header: {}. Expected name:value",
+                                        value
+                                    ),
+                                    usage: ADD_USAGE,
+                                })?;
+                        let name = name.trim().to_string();
+                        if name.eq_ignore_ascii_case("content-type") {
+                            content_type = Some(header_value.trim().to_string());
+                        } else {
+                            headers.insert(name, json!(header_value.trim()));
+                        }
+                    }
+                    other => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Unknown option: {}", other),
+                            usage: ADD_USAGE,
+                        })
+                    }
+                }
+                i += 2;
+            }
+
+            // The body travels base64-encoded so binary fixtures survive the JSON protocol
+            let mut cmd = json!({
+                "id": id,
+                "action": "mock_add",
+                "url": url,
+                "status": status,
+                "body": STANDARD.encode(&body),
+            });
+            if let Some(ct) = content_type {
+                cmd["contentType"] = json!(ct);
+            }
+            if !headers.is_empty() {
+                cmd["headers"] = Value::Object(headers);
+            }
+            Ok(cmd)
+        }
+        Some("list") => Ok(json!({ "id": id, "action": "mock_list" })),
+        Some("remove") => {
+            let mock_id = rest
+                .get(1)
+                .ok_or_else(|| ParseError::MissingArguments {
+                    context: "mock remove".to_string(),
+                    usage: "mock remove <id>",
+                })?
+                .trim_start_matches('#');
+            let mock_id = mock_id
+                .parse::<u64>()
+                .map_err(|_| ParseError::InvalidValue {
+                    message: format!("Invalid mock id: {}", mock_id),
+                    usage: "mock remove <id>",
+                })?;
+            Ok(json!({ "id": id, "action": "mock_remove", "mockId": mock_id }))
+        }
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "mock".to_string(),
+            usage: "mock <add|list|remove> [args...]",
+        }),
+    }
+}
+
+fn parse_fetch(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str =
+        "fetch <url> [--method <method>] [--body <text|@file>] [--header <name:value>]... [--output <file>]";
+
+    let url = rest
+        .first()
+        .filter(|u| !u.starts_with('-'))
+        .ok_or_else(|| ParseError::MissingArguments {
+            context: "fetch".to_string(),
+            usage: USAGE,
+        })?;
+
+    let mut cmd = json!({ "id": id, "action": "fetch", "url": url });
+    let mut headers = serde_json::Map::new();
+    let mut file_type: Option<&str> = None;
+
+    let mut iter = rest[1..].iter();
+    while let Some(&flag) = iter.next() {
+        let value = iter
+            .next()
+            .copied()
+            .ok_or_else(|| ParseError::MissingArguments {
+                context: format!("fetch {}", flag),
+                usage: USAGE,
+            })?;
+        match flag {
+            "--method" | "-X" => cmd["method"] = json!(value.to_ascii_uppercase()),
+            "--body" | "-d" => {
+                // `@file` sends the file's bytes, like curl
+                let body = match value.strip_prefix('@') {
+                    Some(file) => {
+                        file_type = mime_type(file);
+                        std::fs::read(file).map_err(|e| ParseError::InvalidValue {
+                            message: format!("Failed to read {}: {}", file, e),
+                            usage: USAGE,
+                        })?
+                    }
+                    None => value.as_bytes().to_vec(),
+                };
+                cmd["body"] = json!(STANDARD.encode(&body));
+            }
+            "--header" | "-H" => {
+                let (name, header_value) =
+                    value
+                        .split_once(':')
+                        .ok_or_else(|| ParseError::InvalidValue {
+                            message: format!("Invalid header: {}. Expected name:value", value),
+                            usage: USAGE,
+                        })?;
+                headers.insert(name.trim().to_string(), json!(header_value.trim()));
+            }
+            "--output" | "-o" => cmd["path"] = json!(absolute_path(value)),
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unknown option: {}", other),
+                    usage: USAGE,
+                })
+            }
+        }
+    }
+
+    let has_content_type = headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("content-type"));
+    if let (Some(content_type), false) = (file_type, has_content_type) {
+        headers.insert("Content-Type".to_string(), json!(content_type));
+    }
+    if !headers.is_empty() {
+        cmd["headers"] = Value::Object(headers);
+    }
+    Ok(cmd)
+}
+
+/// Content type for common fixture file extensions.
+fn mime_type(path: &str) -> Option<&'static str> {
+    let ext = std::path::Path::new(path).extension()?.to_str()?;
+    Some(match ext.to_ascii_lowercase().as_str() {
+        "json" => "application/json",
+        "html" | "htm" => "text/html",
+        "txt" => "text/plain",
+        "js" | "mjs" => "application/javascript",
+        "css" => "text/css",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        _ => return None,
+    })
+}
+
+fn parse_storage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["get", "set", "remove", "clear", "local", "session"];
+
+    // `storage local|session <op>` picks the area positionally; otherwise
+    // localStorage is used unless --session-storage is passed.
+    let session_flag = rest.contains(&"--session-storage");
+    let rest: Vec<&str> = rest
+        .iter()
+        .copied()
+        .filter(|a| *a != "--session-storage")
+        .collect();
+    let positional = matches!(rest.first().copied(), Some("local" | "session"));
+    let (storage_type, args) = match rest.first().copied() {
+        Some(t) if positional => (t, &rest[1..]),
+        _ if session_flag => ("session", &rest[..]),
+        _ => ("local", &rest[..]),
+    };
+
+    let op = args.first().copied().unwrap_or("get");
+    let key = args.get(1);
+    match op {
+        "get" => {
+            let mut cmd = json!({ "id": id, "action": "storage_get", "type": storage_type });
+            if let Some(k) = key {
+                cmd["key"] = json!(k);
+            }
+            Ok(cmd)
+        }
+        "set" => {
+            let (k, v) = key
+                .zip(args.get(2))
+                .ok_or_else(|| ParseError::MissingArguments {
+                    context: "storage set".to_string(),
+                    usage: "storage set <key> <value> [--session-storage]",
+                })?;
+            Ok(
+                json!({ "id": id, "action": "storage_set", "type": storage_type, "key": k, "value": v }),
+            )
+        }
+        "remove" => {
+            let k = key.ok_or_else(|| ParseError::MissingArguments {
+                context: "storage remove".to_string(),
+                usage: "storage remove <key> [--session-storage]",
+            })?;
+            Ok(json!({ "id": id, "action": "storage_remove", "type": storage_type, "key": k }))
+        }
+        "clear" => Ok(json!({ "id": id, "action": "storage_clear", "type": storage_type })),
+        // Shorthand: `storage local <key>`
+        k if positional && args.len() == 1 => {
+            Ok(json!({ "id": id, "action": "storage_get", "type": storage_type, "key": k }))
+        }
+        sub => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+    }
+}
+
+/// Parse a time limit like `90s`, `10m` or `2h` into milliseconds. A bare
+/// number is milliseconds; `0` and `off` mean no limit.
+pub fn parse_duration(value: &str) -> Option<u64> {
+    if value == "off" {
+        return Some(0);
+    }
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit {
+        "" | "ms" => 1,
+        "s" => 1000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(scale)
+}
+
+fn parse_daemon(rest: &[&str], id: &str, flags: &Flags) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["status", "config"];
+    const USAGE: &str = "daemon config [--idle-timeout <duration>] [--max-lifetime <duration>]";
+
+    match rest.first().copied() {
+        Some("status") | None => Ok(json!({ "id": id, "action": "daemon_status" })),
+        Some("config") => {
+            // The limits arrive as global flags, which also apply to a daemon started now
+            let mut cmd = json!({ "id": id, "action": "daemon_config" });
+            let idle = flags
+                .idle_timeout
+                .as_deref()
+                .filter(|_| flags.cli_idle_timeout);
+            let lifetime = flags
+                .max_lifetime
+                .as_deref()
+                .filter(|_| flags.cli_max_lifetime);
+            let limits = [
+                ("--idle-timeout", "idleTimeout", idle),
+                ("--max-lifetime", "maxLifetime", lifetime),
+            ];
+            for (flag, field, value) in limits {
+                let Some(value) = value else { continue };
+                let ms = parse_duration(value).ok_or_else(|| ParseError::InvalidValue {
+                    message: format!(
+                        "Invalid {}: {} (expected e.g. 90s, 10m, 2h or off)",
+                        flag, value
+                    ),
+                    usage: USAGE,
+                })?;
+                cmd[field] = json!(ms);
+            }
+            if cmd.get("idleTimeout").is_none() && cmd.get("maxLifetime").is_none() {
+                return Err(ParseError::MissingArguments {
+                    context: "daemon config".to_string(),
+                    usage: USAGE,
+                });
+            }
+            Ok(cmd)
+        }
+        // Run by main, as it replaces the daemon a script or chain would be talking to
+        Some("upgrade") => Err(ParseError::InvalidValue {
+            message: "daemon upgrade must be run on its own".to_string(),
+            usage: "daemon upgrade",
+        }),
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_flags() -> Flags {
+        Flags {
+            session: "test".to_string(),
+            json: false,
+            format: None,
+            filter: None,
+            output: None,
+            full: false,
+            headed: false,
+            headless: false,
+            debug: false,
+            headers: None,
+            executable_path: None,
+            extensions: Vec::new(),
+            cdp: None,
+            profile: None,
+            state: None,
+            proxy: None,
+            proxy_bypass: None,
+            args: None,
+            user_agent: None,
+            provider: None,
+            ignore_https_errors: false,
+            allow_file_access: false,
+            device: None,
+            browser: None,
+            timeout: None,
+            remote: None,
+            socket: None,
+            runtime: None,
+            no_color: false,
+            quiet: false,
+            verbose: false,
+            timings: false,
+            show_daemon_log: false,
+            dry_run: false,
+            all_sessions: false,
+            lock: None,
+            lock_timeout: None,
+            retry: None,
+            idle_timeout: None,
+            max_lifetime: None,
+            min_delay: None,
+            max_rps: None,
+            cli_headed: false,
+            cli_browser: false,
+            cli_executable_path: false,
+            cli_extensions: false,
+            cli_profile: false,
+            cli_state: false,
+            cli_args: false,
+            cli_user_agent: false,
+            cli_proxy: false,
+            cli_proxy_bypass: false,
+            cli_allow_file_access: false,
+            cli_session: false,
+            cli_idle_timeout: false,
+            cli_max_lifetime: false,
+        }
+    }
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    /// Parse a whole command line the way main does, global flags and all
+    fn parse_line(s: &str) -> Result<Value, ParseError> {
+        let argv = args(s);
+        let mut flags = crate::flags::parse_flags(&argv);
+        flags.session = "test".to_string();
+        parse_command(&crate::flags::clean_args(&argv), &flags)
+    }
+
+    // === Cookies Tests ===
+
+    #[test]
+    fn test_cookies_get() {
+        let cmd = parse_command(&args("cookies"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_get");
+    }
+
+    #[test]
+    fn test_cookies_get_explicit() {
+        let cmd = parse_command(&args("cookies get"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_get");
+    }
+
+    #[test]
+    fn test_cookies_list() {
+        let cmd = parse_command(&args("cookies list"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_get");
+        assert!(cmd.get("name").is_none());
+    }
+
+    #[test]
+    fn test_cookies_get_by_name() {
+        let cmd = parse_command(&args("cookies get session_id"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_get");
+        assert_eq!(cmd["name"], "session_id");
+    }
+
+    #[test]
+    fn test_cookies_export_absolute_path() {
+        let cmd = parse_command(&args("cookies export cookies.json"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_export");
+        let path = cmd["path"].as_str().unwrap();
+        assert!(std::path::Path::new(path).is_absolute());
+        assert!(path.ends_with("cookies.json"));
+    }
+
+    #[test]
+    fn test_cookies_import_missing_file() {
+        let result = parse_command(
+            &args("cookies import /nonexistent/cookies.json"),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_cookies_unknown_subcommand() {
+        let result = parse_command(&args("cookies bogus"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+    }
+
+    #[test]
+    fn test_cookies_set() {
+        let cmd = parse_command(&args("cookies set mycookie myvalue"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+    }
+
+    #[test]
+    fn test_cookies_set_missing_value() {
+        let result = parse_command(&args("cookies set mycookie"), &default_flags());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cookies_clear() {
+        let cmd = parse_command(&args("cookies clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_clear");
+    }
+
+    #[test]
+    fn test_cookies_set_with_url() {
+        let cmd = parse_command(
+            &args("cookies set mycookie myvalue --url https://example.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_cookies_set_with_domain() {
+        let cmd = parse_command(
+            &args("cookies set mycookie myvalue --domain example.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["domain"], "example.com");
+    }
+
+    #[test]
+    fn test_cookies_set_with_path() {
+        let cmd = parse_command(
+            &args("cookies set mycookie myvalue --path /api"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["path"], "/api");
+    }
+
+    #[test]
+    fn test_cookies_set_with_httponly() {
+        let cmd = parse_command(
+            &args("cookies set mycookie myvalue --httpOnly"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["httpOnly"], true);
+    }
+
+    #[test]
+    fn test_cookies_set_with_secure() {
+        let cmd = parse_command(
+            &args("cookies set mycookie myvalue --secure"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["secure"], true);
+    }
+
+    #[test]
+    fn test_cookies_set_with_samesite() {
+        let cmd = parse_command(
+            &args("cookies set mycookie myvalue --sameSite Strict"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["sameSite"], "Strict");
+    }
+
+    #[test]
+    fn test_cookies_set_with_expires() {
+        let cmd = parse_command(
+            &args("cookies set mycookie myvalue --expires 1234567890"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["expires"], 1234567890);
+    }
+
+    #[test]
+    fn test_cookies_set_with_multiple_flags() {
+        let cmd = parse_command(&args("cookies set mycookie myvalue --url https://example.com --httpOnly --secure --sameSite Lax"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["url"], "https://example.com");
+        assert_eq!(cmd["cookies"][0]["httpOnly"], true);
+        assert_eq!(cmd["cookies"][0]["secure"], true);
+        assert_eq!(cmd["cookies"][0]["sameSite"], "Lax");
+    }
+
+    #[test]
+    fn test_cookies_set_with_all_flags() {
+        let cmd = parse_command(&args("cookies set mycookie myvalue --url https://example.com --domain example.com --path /api --httpOnly --secure --sameSite None --expires 9999999999"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "cookies_set");
+        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
+        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+        assert_eq!(cmd["cookies"][0]["url"], "https://example.com");
+        assert_eq!(cmd["cookies"][0]["domain"], "example.com");
+        assert_eq!(cmd["cookies"][0]["path"], "/api");
+        assert_eq!(cmd["cookies"][0]["httpOnly"], true);
+        assert_eq!(cmd["cookies"][0]["secure"], true);
+        assert_eq!(cmd["cookies"][0]["sameSite"], "None");
+        assert_eq!(cmd["cookies"][0]["expires"], 9999999999i64);
+    }
+
+    #[test]
+    fn test_cookies_set_invalid_samesite() {
+        let result = parse_command(
+            &args("cookies set mycookie myvalue --sameSite Invalid"),
+            &default_flags(),
+        );
+        assert!(result.is_err());
+    }
+
+    // === Upload Tests ===
+
+    #[test]
+    fn test_upload_existing_file() {
+        let path = std::env::temp_dir().join(format!("ab-upload-{}.txt", std::process::id()));
+        std::fs::write(&path, "hello").unwrap();
+        let input = vec![
+            "upload".to_string(),
+            "#file".to_string(),
+            path.to_string_lossy().to_string(),
+        ];
+        let cmd = parse_command(&input, &default_flags()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cmd["action"], "upload");
+        assert_eq!(cmd["selector"], "#file");
+        assert_eq!(cmd["files"][0], path.to_string_lossy().as_ref());
+    }
+
+    #[test]
+    fn test_upload_missing_file() {
+        let result = parse_command(&args("upload #file /nonexistent/a.txt"), &default_flags());
+        match result {
+            Err(ParseError::InvalidValue { message, .. }) => {
+                assert!(message.contains("/nonexistent/a.txt"))
+            }
+            _ => panic!("expected InvalidValue"),
+        }
+    }
+
+    #[test]
+    fn test_upload_requires_files() {
+        let result = parse_command(&args("upload #file"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    // === Close Tests ===
+
+    #[test]
+    fn test_close_options() {
+        let cmd = parse_command(&args("close"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "close");
+        assert!(cmd.get("keepDaemon").is_none());
+
+        let cmd = parse_command(
+            &args("quit --keep-daemon --save-state auth.json"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["keepDaemon"], true);
+        assert!(std::path::Path::new(cmd["saveState"].as_str().unwrap()).is_absolute());
+
+        assert!(matches!(
+            parse_command(&args("close --save-state"), &default_flags()),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(parse_command(&args("close now"), &default_flags()).is_err());
+    }
+
+    // === State Tests ===
+
+    #[test]
+    fn test_state_save_absolute_path() {
+        let cmd = parse_command(&args("state save auth.json"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "state_save");
+        assert!(std::path::Path::new(cmd["path"].as_str().unwrap()).is_absolute());
+    }
+
+    #[test]
+    fn test_state_load() {
+        let path = std::env::temp_dir().join(format!("ab-state-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"cookies":[],"origins":[]}"#).unwrap();
+        let input = vec![
+            "state".to_string(),
+            "load".to_string(),
+            path.to_string_lossy().to_string(),
+        ];
+        let cmd = parse_command(&input, &default_flags()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cmd["action"], "state_load");
+        assert_eq!(cmd["path"], path.to_string_lossy().as_ref());
+    }
+
+    #[test]
+    fn test_state_load_missing_file() {
+        let result = parse_command(&args("state load /nonexistent/auth.json"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    // === Storage Tests ===
+
+    #[test]
+    fn test_storage_local_get() {
+        let cmd = parse_command(&args("storage local"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_get");
+        assert_eq!(cmd["type"], "local");
+        assert!(cmd.get("key").is_none());
+    }
+
+    #[test]
+    fn test_storage_local_get_key() {
+        let cmd = parse_command(&args("storage local get mykey"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_get");
+        assert_eq!(cmd["type"], "local");
+        assert_eq!(cmd["key"], "mykey");
+    }
+
+    #[test]
+    fn test_storage_session_get() {
+        let cmd = parse_command(&args("storage session"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_get");
+        assert_eq!(cmd["type"], "session");
+    }
+
+    #[test]
+    fn test_storage_local_set() {
+        let cmd =
+            parse_command(&args("storage local set mykey myvalue"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_set");
+        assert_eq!(cmd["type"], "local");
+        assert_eq!(cmd["key"], "mykey");
+        assert_eq!(cmd["value"], "myvalue");
+    }
+
+    #[test]
+    fn test_storage_session_set() {
+        let cmd =
+            parse_command(&args("storage session set skey svalue"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_set");
+        assert_eq!(cmd["type"], "session");
+        assert_eq!(cmd["key"], "skey");
+        assert_eq!(cmd["value"], "svalue");
+    }
+
+    #[test]
+    fn test_storage_set_missing_value() {
+        let result = parse_command(&args("storage local set mykey"), &default_flags());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_storage_local_clear() {
+        let cmd = parse_command(&args("storage local clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_clear");
+        assert_eq!(cmd["type"], "local");
+    }
+
+    #[test]
+    fn test_storage_session_clear() {
+        let cmd = parse_command(&args("storage session clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_clear");
+        assert_eq!(cmd["type"], "session");
+    }
+
+    #[test]
+    fn test_storage_invalid_type() {
+        let result = parse_command(&args("storage invalid"), &default_flags());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_storage_local_key_shorthand() {
+        let cmd = parse_command(&args("storage local mykey"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_get");
+        assert_eq!(cmd["key"], "mykey");
+    }
+
+    #[test]
+    fn test_storage_get_defaults_to_local() {
+        let cmd = parse_command(&args("storage get token"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_get");
+        assert_eq!(cmd["type"], "local");
+        assert_eq!(cmd["key"], "token");
+    }
+
+    #[test]
+    fn test_storage_set_session_storage_flag() {
+        let cmd = parse_command(
+            &args("storage set token abc --session-storage"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "storage_set");
+        assert_eq!(cmd["type"], "session");
+        assert_eq!(cmd["key"], "token");
+        assert_eq!(cmd["value"], "abc");
+    }
+
+    #[test]
+    fn test_storage_remove() {
+        let cmd = parse_command(&args("storage remove token"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_remove");
+        assert_eq!(cmd["type"], "local");
+        assert_eq!(cmd["key"], "token");
+    }
+
+    #[test]
+    fn test_storage_remove_missing_key() {
+        let result = parse_command(&args("storage remove"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_storage_clear_session_storage_flag() {
+        let cmd =
+            parse_command(&args("storage --session-storage clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_clear");
+        assert_eq!(cmd["type"], "session");
+    }
+
+    // === Navigation Tests ===
+
+    #[test]
+    fn test_navigate_with_https() {
+        let cmd = parse_command(&args("open https://example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "navigate");
+        assert_eq!(cmd["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_navigate_without_protocol() {
+        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "navigate");
+        assert_eq!(cmd["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_navigate_with_headers() {
+        let mut flags = default_flags();
+        flags.headers = Some(r#"{"Authorization": "Bearer token"}"#.to_string());
+        let cmd = parse_command(&args("open api.example.com"), &flags).unwrap();
+        assert_eq!(cmd["action"], "navigate");
+        assert_eq!(cmd["url"], "https://api.example.com");
+        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
+    }
+
+    #[test]
+    fn test_navigate_with_multiple_headers() {
+        let mut flags = default_flags();
+        flags.headers =
+            Some(r#"{"Authorization": "Bearer token", "X-Custom": "value"}"#.to_string());
+        let cmd = parse_command(&args("open api.example.com"), &flags).unwrap();
+        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
+        assert_eq!(cmd["headers"]["X-Custom"], "value");
+    }
+
+    #[test]
+    fn test_navigate_without_headers_flag() {
+        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "navigate");
+        // headers should not be present when flag is not set
+        assert!(cmd.get("headers").is_none());
+    }
+
+    #[test]
+    fn test_navigate_with_invalid_headers_json() {
+        let mut flags = default_flags();
+        flags.headers = Some("not valid json".to_string());
+        let cmd = parse_command(&args("open api.example.com"), &flags).unwrap();
+        // Invalid JSON should result in no headers field (graceful handling)
+        assert!(cmd.get("headers").is_none());
+    }
+
+    #[test]
+    fn test_navigate_options() {
+        let cmd = parse_line(
+            "open a.com --wait-until networkidle --referer https://b.com --timeout 9000",
+        )
+        .unwrap();
+        assert_eq!(cmd["url"], "https://a.com");
+        assert_eq!(cmd["waitUntil"], "networkidle");
+        assert_eq!(cmd["referer"], "https://b.com");
+        assert_eq!(cmd["timeout"], 9000);
+    }
+
+    #[test]
+    fn test_navigate_options_before_url() {
+        let cmd = parse_command(
+            &args("goto --wait-until commit example.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["url"], "https://example.com");
+        assert_eq!(cmd["waitUntil"], "commit");
+        assert!(cmd.get("referer").is_none());
+        assert!(cmd.get("timeout").is_none());
+    }
+
+    #[test]
+    fn test_navigate_invalid_wait_until() {
+        let result = parse_command(
+            &args("open example.com --wait-until idle"),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_navigate_new_tab_or_window() {
+        let cmd = parse_command(&args("open example.com --new-tab"), &default_flags()).unwrap();
+        assert_eq!(cmd["target"], "tab");
+        let cmd = parse_command(&args("open --window example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["target"], "window");
+        assert_eq!(cmd["url"], "https://example.com");
+        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
+        assert!(cmd.get("target").is_none());
+        let result = parse_command(&args("open a.com --new-tab --window"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_navigate_missing_url() {
+        let result = parse_command(&args("open --referer https://a.com"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    // === Set Headers Tests ===
+
+    #[test]
+    fn test_set_headers_parses_json() {
+        let input: Vec<String> = vec![
+            "set".to_string(),
+            "headers".to_string(),
+            r#"{"Authorization":"Bearer token"}"#.to_string(),
+        ];
+        let cmd = parse_command(&input, &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "headers");
+        // Headers should be an object, not a string
+        assert!(cmd["headers"].is_object());
+        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
+    }
+
+    #[test]
+    fn test_set_headers_with_multiple_values() {
+        let input: Vec<String> = vec![
+            "set".to_string(),
+            "headers".to_string(),
+            r#"{"Authorization": "Bearer token", "X-Custom": "value"}"#.to_string(),
+        ];
+        let cmd = parse_command(&input, &default_flags()).unwrap();
+        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
+        assert_eq!(cmd["headers"]["X-Custom"], "value");
+    }
+
+    #[test]
+    fn test_set_headers_invalid_json_error() {
+        let input: Vec<String> = vec![
+            "set".to_string(),
+            "headers".to_string(),
+            "not-valid-json".to_string(),
+        ];
+        let result = parse_command(&input, &default_flags());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_back() {
+        let cmd = parse_command(&args("back"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "back");
+    }
+
+    #[test]
+    fn test_forward() {
+        let cmd = parse_command(&args("forward"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "forward");
+    }
+
+    #[test]
+    fn test_reload() {
+        let cmd = parse_command(&args("reload"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "reload");
+    }
+
+    // === Core Actions ===
+
+    #[test]
+    fn test_click() {
+        let cmd = parse_command(&args("click #button"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "click");
+        assert_eq!(cmd["selector"], "#button");
+    }
+
+    #[test]
+    fn test_fill() {
+        let cmd = parse_command(&args("fill #input hello world"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "fill");
+        assert_eq!(cmd["selector"], "#input");
+        assert_eq!(cmd["value"], "hello world");
+        assert!(secret_value(&cmd).is_none());
+    }
+
+    #[test]
+    fn test_fill_secret() {
+        std::env::set_var("AB_TEST_FILL_SECRET", "hunter2");
+        let cmd = parse_command(
+            &args("fill #password --secret AB_TEST_FILL_SECRET"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["value"], "hunter2");
+        assert_eq!(cmd["secret"], true);
+        assert_eq!(secret_value(&cmd), Some("hunter2"));
+    }
+
+    #[test]
+    fn test_fill_secret_invalid() {
+        let result = parse_command(&args("fill #p --secret"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(
+            &args("fill #p --secret AB_TEST_FILL_SECRET_UNSET"),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_fill_form() {
+        let file = std::env::temp_dir().join(format!("ab-form-{}.yaml", std::process::id()));
+        std::fs::write(&file, "Email: ada@example.com\nSubscribe: true\n").unwrap();
+        let cmd = parse_command(
+            &args(&format!("fill --form {}", file.display())),
+            &default_flags(),
+        )
+        .unwrap();
+        std::fs::remove_file(&file).ok();
+        assert_eq!(cmd["action"], "fillform");
+        assert_eq!(
+            cmd["fields"],
+            json!([
+                { "field": "Email", "value": "ada@example.com" },
+                { "field": "Subscribe", "value": true },
+            ])
+        );
+
+        let result = parse_command(&args("fill --form"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(
+            &args("fill --form /nonexistent/form.json"),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_type_command() {
+        let cmd = parse_command(&args("type #input some text"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "type");
+        assert_eq!(cmd["selector"], "#input");
+        assert_eq!(cmd["text"], "some text");
+    }
+
+    #[test]
+    fn test_select() {
+        let cmd = parse_command(&args("select #menu option1"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "select");
+        assert_eq!(cmd["selector"], "#menu");
+        assert_eq!(cmd["values"], "option1");
+    }
+
+    #[test]
+    fn test_select_multiple_values() {
+        let cmd = parse_command(&args("select #menu opt1 opt2 opt3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "select");
+        assert_eq!(cmd["selector"], "#menu");
+        assert_eq!(cmd["values"], json!(["opt1", "opt2", "opt3"]));
+    }
+
+    #[test]
+    fn test_scroll_default() {
+        let cmd = parse_command(&args("scroll"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "scroll");
+        assert_eq!(cmd["direction"], "down");
+        assert_eq!(cmd["amount"], 300);
+    }
+
+    #[test]
+    fn test_scroll_to() {
+        let cmd = parse_command(&args("scroll to #footer"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "scrollintoview");
+        assert_eq!(cmd["selector"], "#footer");
+    }
+
+    #[test]
+    fn test_scroll_by() {
+        let cmd = parse_command(&args("scroll by 0 -250"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "scroll");
+        assert_eq!(cmd["x"], 0);
+        assert_eq!(cmd["y"], -250);
+        assert!(cmd.get("direction").is_none());
+    }
+
+    #[test]
+    fn test_scroll_by_missing_offset() {
+        let result = parse_command(&args("scroll by 100"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_scroll_bottom() {
+        let cmd = parse_command(&args("scroll bottom"), &default_flags()).unwrap();
+        assert_eq!(cmd["position"], "bottom");
+    }
+
+    #[test]
+    fn test_press() {
+        let cmd = parse_command(&args("press Enter"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "press");
+        assert_eq!(cmd["key"], "Enter");
+        assert!(cmd.get("count").is_none());
+    }
+
+    #[test]
+    fn test_press_chord_aliases() {
+        let cmd = parse_command(&args("press ctrl+shift+P"), &default_flags()).unwrap();
+        assert_eq!(cmd["key"], "Control+Shift+P");
+        let cmd = parse_command(&args("press cmd+k"), &default_flags()).unwrap();
+        assert_eq!(cmd["key"], "Meta+k");
+    }
+
+    #[test]
+    fn test_press_repeat() {
+        let cmd = parse_command(&args("press Tab x3"), &default_flags()).unwrap();
+        assert_eq!(cmd["key"], "Tab");
+        assert_eq!(cmd["count"], 3);
+    }
+
+    #[test]
+    fn test_press_invalid_repeat() {
+        let result = parse_command(&args("press Tab three"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("press Tab x0"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_normalize_key_plus() {
+        assert_eq!(normalize_key("+"), "+");
+        assert_eq!(normalize_key("ctrl++"), "Control++");
+        assert_eq!(normalize_key("Control+="), "Control+=");
+    }
+
+    #[test]
+    fn test_keys_down_up() {
+        let cmd = parse_command(&args("keys down shift"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "keydown");
+        assert_eq!(cmd["key"], "Shift");
+        let cmd = parse_command(&args("keys up Shift"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "keyup");
+        assert_eq!(cmd["key"], "Shift");
+    }
+
+    #[test]
+    fn test_keys_unknown_subcommand() {
+        let result = parse_command(&args("keys hold Shift"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+    }
+
+    #[test]
+    fn test_drag() {
+        let cmd = parse_command(&args("drag #card #done"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "drag");
+        assert_eq!(cmd["source"], "#card");
+        assert_eq!(cmd["target"], "#done");
+        assert!(cmd.get("steps").is_none());
+    }
+
+    #[test]
+    fn test_drag_steps() {
+        let cmd = parse_command(&args("drag --steps 20 @e1 @e2"), &default_flags()).unwrap();
+        assert_eq!(cmd["source"], "@e1");
+        assert_eq!(cmd["target"], "@e2");
+        assert_eq!(cmd["steps"], 20);
+    }
+
+    #[test]
+    fn test_drag_invalid_steps() {
+        let result = parse_command(&args("drag #a #b --steps 0"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_check() {
+        let cmd = parse_command(&args("check #terms"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "check");
+        assert_eq!(cmd["selector"], "#terms");
+    }
+
+    #[test]
+    fn test_uncheck_missing_selector() {
+        let result = parse_command(&args("uncheck"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_selector_engines_pass_through() {
+        for sel in [
+            "text=Sign in",
+            "role=button[name=\"Save\"]",
+            "xpath=//div[@id='main']",
+        ] {
+            let cmd =
+                parse_command(&["click".to_string(), sel.to_string()], &default_flags()).unwrap();
+            assert_eq!(cmd["selector"], sel);
+        }
+    }
+
+    #[test]
+    fn test_blur() {
+        let cmd = parse_command(&args("blur @e3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "blur");
+        assert_eq!(cmd["selector"], "@e3");
+    }
+
+    #[test]
+    fn test_clear() {
+        let cmd = parse_command(&args("clear #search"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "clear");
+        assert_eq!(cmd["selector"], "#search");
+    }
+
+    #[test]
+    fn test_clear_missing_selector() {
+        let result = parse_command(&args("clear"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_select_label() {
+        let cmd = parse_command(&args("select #country --label Canada"), &default_flags()).unwrap();
+        assert_eq!(cmd["label"], "Canada");
+        assert!(cmd.get("values").is_none());
+    }
+
+    #[test]
+    fn test_select_index() {
+        let cmd = parse_command(&args("select #country --index 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["index"], 3);
+        assert!(cmd.get("values").is_none());
+    }
+
+    #[test]
+    fn test_select_mixed_modes() {
+        let result = parse_command(&args("select #country US --index 3"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_select_missing_value() {
+        let result = parse_command(&args("select #country"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_frame_main() {
+        let cmd = parse_command(&args("frame main"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "mainframe");
+    }
+
+    #[test]
+    fn test_frame_list() {
+        let cmd = parse_command(&args("frame list"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "frame_list");
+    }
+
+    #[test]
+    fn test_frame_use_index() {
+        let cmd = parse_command(&args("frame use 2"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "frame");
+        assert_eq!(cmd["index"], 2);
+        assert!(cmd.get("selector").is_none());
+    }
+
+    #[test]
+    fn test_frame_use_selector() {
+        let cmd = parse_command(&args("frame use #checkout"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#checkout");
+        assert!(cmd.get("index").is_none());
+    }
+
+    #[test]
+    fn test_frame_selector_shorthand() {
+        let cmd = parse_command(&args("frame #checkout"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "frame");
+        assert_eq!(cmd["selector"], "#checkout");
+    }
+
+    #[test]
+    fn test_frame_use_missing_target() {
+        let result = parse_command(&args("frame use"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    // === Tabs ===
+
+    #[test]
+    fn test_tab_new() {
+        let cmd = parse_command(&args("tab new"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "tab_new");
+        assert!(
+            cmd.get("url").is_none(),
+            "url should not be present when not provided"
+        );
+    }
+
+    #[test]
+    fn test_tab_new_with_url() {
+        let cmd = parse_command(&args("tab new https://example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "tab_new");
+        assert_eq!(cmd["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_tab_list() {
+        let cmd = parse_command(&args("tab list"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "tab_list");
+    }
+
+    #[test]
+    fn test_tab_switch() {
+        let cmd = parse_command(&args("tab 2"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "tab_switch");
+        assert_eq!(cmd["index"], 2);
+    }
+
+    #[test]
+    fn test_tab_close() {
+        let cmd = parse_command(&args("tab close"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "tab_close");
+    }
+
+    // === Screenshot ===
+
+    #[test]
+    fn test_screenshot() {
+        let cmd = parse_command(&args("screenshot"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["path"], serde_json::Value::Null);
+        assert_eq!(cmd["selector"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_screenshot_path() {
+        let cmd = parse_command(&args("screenshot out.png"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["path"], "out.png");
+    }
+
+    #[test]
+    fn test_screenshot_full_page() {
+        let mut flags = default_flags();
+        flags.full = true;
+        let cmd = parse_command(&args("screenshot"), &flags).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["fullPage"], true);
+    }
+
+    #[test]
+    fn test_screenshot_with_ref() {
+        let cmd = parse_command(&args("screenshot @e1"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["selector"], "@e1");
+        assert_eq!(cmd["path"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_screenshot_with_css_class() {
+        let cmd = parse_command(&args("screenshot .my-button"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["selector"], ".my-button");
+        assert_eq!(cmd["path"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_screenshot_with_css_id() {
+        let cmd = parse_command(&args("screenshot #header"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["selector"], "#header");
+        assert_eq!(cmd["path"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_screenshot_with_path() {
+        let cmd = parse_command(&args("screenshot ./output.png"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["selector"], serde_json::Value::Null);
+        assert_eq!(cmd["path"], "./output.png");
+    }
+
+    #[test]
+    fn test_screenshot_with_selector_and_path() {
+        let cmd = parse_command(&args("screenshot .btn ./button.png"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["selector"], ".btn");
+        assert_eq!(cmd["path"], "./button.png");
+    }
+
+    #[test]
+    fn test_screenshot_full_page_flag() {
+        let cmd = parse_command(&args("screenshot --full-page"), &default_flags()).unwrap();
+        assert_eq!(cmd["fullPage"], true);
+    }
+
+    #[test]
+    fn test_screenshot_selector_flag() {
+        let cmd = parse_command(
+            &args("screenshot --selector main out.png"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["selector"], "main");
+        assert_eq!(cmd["path"], "out.png");
+    }
+
+    #[test]
+    fn test_screenshot_annotate_refs() {
+        let cmd = parse_command(
+            &args("screenshot --annotate-refs labelled.png"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["annotateRefs"], true);
+        assert_eq!(cmd["path"], "labelled.png");
+        let cmd = parse_command(&args("screenshot"), &default_flags()).unwrap();
+        assert!(cmd.get("annotateRefs").is_none());
+    }
+
+    #[test]
+    fn test_screenshot_format_and_quality() {
+        let cmd = parse_command(
+            &args("screenshot --format jpg --quality 80"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["format"], "jpeg");
+        assert_eq!(cmd["quality"], 80);
+    }
+
+    #[test]
+    fn test_screenshot_format_from_extension() {
+        let cmd = parse_command(&args("screenshot ./shot.webp"), &default_flags()).unwrap();
+        assert_eq!(cmd["path"], "./shot.webp");
+        assert_eq!(cmd["format"], "webp");
+        assert_eq!(cmd["selector"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_screenshot_quality_requires_lossy_format() {
+        let result = parse_command(&args("screenshot --quality 80"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_screenshot_invalid_format() {
+        let result = parse_command(&args("screenshot --format gif"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_screenshot_clip() {
+        let cmd =
+            parse_command(&args("screenshot --clip 10,20,300,200"), &default_flags()).unwrap();
+        assert_eq!(cmd["clip"]["x"], 10.0);
+        assert_eq!(cmd["clip"]["y"], 20.0);
+        assert_eq!(cmd["clip"]["width"], 300.0);
+        assert_eq!(cmd["clip"]["height"], 200.0);
+    }
+
+    #[test]
+    fn test_screenshot_invalid_clip() {
+        let result = parse_command(&args("screenshot --clip 10,20,0,200"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("screenshot --clip 10,20"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_screenshot_clip_with_selector_rejected() {
+        let result = parse_command(&args("screenshot @e1 --clip 0,0,10,10"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_screenshot_stdout() {
+        let cmd = parse_command(&args("screenshot --stdout"), &default_flags()).unwrap();
+        assert_eq!(cmd["stdout"], true);
+        assert_eq!(cmd["base64"], false);
+        assert_eq!(cmd["path"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_screenshot_stdout_base64_with_selector() {
+        let cmd =
+            parse_command(&args("screenshot @e2 --stdout --base64"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "@e2");
+        assert_eq!(cmd["stdout"], true);
+        assert_eq!(cmd["base64"], true);
+    }
+
+    #[test]
+    fn test_screenshot_stdout_with_path_rejected() {
+        let result = parse_command(&args("screenshot ./out.png --stdout"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_screenshot_base64_requires_stdout() {
+        let result = parse_command(&args("screenshot --base64"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    // === Screenshot Diff ===
+
+    fn write_baseline(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}.png", name, std::process::id()));
+        std::fs::write(&path, b"png").unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_screenshot_diff_defaults() {
+        let baseline = write_baseline("ab-diff-defaults");
+        let cmd = parse_command(
+            &args(&format!("screenshot diff {}", baseline)),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["stdout"], true);
+        assert_eq!(cmd["format"], "png");
+        assert_eq!(cmd["diff"]["baseline"], baseline);
+        assert_eq!(cmd["diff"]["threshold"], 0.01);
+        assert_eq!(cmd["diff"]["out"], serde_json::Value::Null);
+        std::fs::remove_file(baseline).unwrap();
+    }
+
+    #[test]
+    fn test_screenshot_diff_options() {
+        let baseline = write_baseline("ab-diff-options");
+        let cmd = parse_command(
+            &args(&format!(
+                "screenshot diff {} --threshold 0.05 --out /tmp/d.png --full-page --selector main",
+                baseline
+            )),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["diff"]["threshold"], 0.05);
+        assert_eq!(cmd["diff"]["out"], "/tmp/d.png");
+        assert_eq!(cmd["fullPage"], true);
+        assert_eq!(cmd["selector"], "main");
+        std::fs::remove_file(baseline).unwrap();
+    }
+
+    #[test]
+    fn test_screenshot_diff_missing_baseline() {
+        let result = parse_command(&args("screenshot diff"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(
+            &args("screenshot diff /nonexistent/baseline.png"),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_screenshot_diff_invalid_threshold() {
+        let baseline = write_baseline("ab-diff-threshold");
+        let result = parse_command(
+            &args(&format!("screenshot diff {} --threshold 2", baseline)),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        std::fs::remove_file(baseline).unwrap();
+    }
+
+    #[test]
+    fn test_screenshot_diff_rejects_jpeg() {
+        let baseline = write_baseline("ab-diff-jpeg");
+        let result = parse_command(
+            &args(&format!("screenshot diff {} --format jpeg", baseline)),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        std::fs::remove_file(baseline).unwrap();
+    }
+
+    // === PDF ===
+
+    #[test]
+    fn test_pdf_no_path() {
+        let cmd = parse_command(&args("pdf"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "pdf");
+        assert!(cmd.get("path").is_none());
+    }
+
+    #[test]
+    fn test_pdf_path_is_absolute() {
+        let cmd = parse_command(&args("pdf page.pdf"), &default_flags()).unwrap();
+        let path = cmd["path"].as_str().unwrap();
+        assert!(std::path::Path::new(path).is_absolute());
+        assert!(path.ends_with("page.pdf"));
+    }
+
+    #[test]
+    fn test_pdf_options() {
+        let cmd = parse_command(
+            &args("pdf /tmp/out.pdf --format a4 --landscape --margin 1cm"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["path"], "/tmp/out.pdf");
+        assert_eq!(cmd["format"], "A4");
+        assert_eq!(cmd["landscape"], true);
+        assert_eq!(cmd["margin"]["top"], "1cm");
+        assert_eq!(cmd["margin"]["left"], "1cm");
+    }
+
+    #[test]
+    fn test_pdf_unknown_flag() {
+        let result = parse_pdf(&["--landscpe"], "1");
+        assert!(
+            matches!(result, Err(ParseError::UnknownFlag { ref flag, .. }) if flag == "--landscpe")
+        );
+        let result = parse_command(&args("pdf out.pdf --landscpe"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownFlag { .. })));
+    }
+
+    #[test]
+    fn test_pdf_margin_four_sides() {
+        let cmd =
+            parse_command(&args("pdf --margin 10px,20px,30px,40px"), &default_flags()).unwrap();
+        assert_eq!(cmd["margin"]["top"], "10px");
+        assert_eq!(cmd["margin"]["right"], "20px");
+        assert_eq!(cmd["margin"]["bottom"], "30px");
+        assert_eq!(cmd["margin"]["left"], "40px");
+    }
+
+    #[test]
+    fn test_pdf_invalid_margin() {
+        let result = parse_command(&args("pdf --margin 1cm,2cm"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_pdf_invalid_format() {
+        let result = parse_command(&args("pdf --format B5"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_pdf_format_missing_value() {
+        let result = parse_command(&args("pdf --format"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    // === Snapshot ===
+
+    #[test]
+    fn test_snapshot() {
+        let cmd = parse_command(&args("snapshot"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot");
+    }
+
+    #[test]
+    fn test_snapshot_interactive() {
+        let cmd = parse_command(&args("snapshot -i"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot");
+        assert_eq!(cmd["interactive"], true);
+    }
+
+    #[test]
+    fn test_snapshot_cursor() {
+        let cmd = parse_command(&args("snapshot -C"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot");
+        assert_eq!(cmd["cursor"], true);
+    }
+
+    #[test]
+    fn test_snapshot_interactive_cursor() {
+        let cmd = parse_command(&args("snapshot -i -C"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot");
+        assert_eq!(cmd["interactive"], true);
+        assert_eq!(cmd["cursor"], true);
+    }
+
+    #[test]
+    fn test_snapshot_compact() {
+        let cmd = parse_command(&args("snapshot --compact"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot");
+        assert_eq!(cmd["compact"], true);
+    }
+
+    #[test]
+    fn test_snapshot_depth() {
+        let cmd = parse_command(&args("snapshot -d 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot");
+        assert_eq!(cmd["maxDepth"], 3);
+    }
+
+    #[test]
+    fn test_snapshot_format() {
+        let cmd = parse_command(&args("snapshot -i --format yaml"), &default_flags()).unwrap();
+        assert_eq!(cmd["format"], "yaml");
+        assert_eq!(cmd["interactive"], true);
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let cmd = parse_command(&args("snapshot diff -i -s #main"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "snapshot_diff");
+        assert_eq!(cmd["interactive"], true);
+        assert_eq!(cmd["selector"], "#main");
+    }
+
+    #[test]
+    fn test_snapshot_diff_rejects_format() {
+        let result = parse_command(&args("snapshot diff --format json"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_snapshot_pagination() {
+        let cmd = parse_command(
+            &args("snapshot --max-chars 8000 --page 2"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["maxChars"], 8000);
+        assert_eq!(cmd["page"], 2);
+    }
+
+    #[test]
+    fn test_snapshot_pagination_invalid() {
+        let result = parse_command(&args("snapshot --page 0"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("snapshot --max-chars"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(
+            &args("snapshot --format json --max-chars 100"),
+            &default_flags(),
+        );
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_snapshot_coords() {
+        let cmd = parse_command(&args("snapshot -i --coords"), &default_flags()).unwrap();
+        assert_eq!(cmd["coords"], true);
+        let cmd = parse_command(&args("snapshot"), &default_flags()).unwrap();
+        assert!(cmd.get("coords").is_none());
+        let result = parse_command(&args("snapshot diff --coords"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_snapshot_if_changed() {
+        let flags = default_flags();
+        let cmd = parse_command(&args("snapshot -i --if-changed 3f2a9c0d1b7e4a56"), &flags);
+        let cmd = cmd.unwrap();
+        assert_eq!(cmd["ifChanged"], "3f2a9c0d1b7e4a56");
+        assert_eq!(cmd["interactive"], true);
+        let cmd = parse_command(&args("snapshot"), &flags).unwrap();
+        assert!(cmd.get("ifChanged").is_none());
+        assert!(matches!(
+            parse_command(&args("snapshot --if-changed"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("snapshot diff --if-changed abc"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_invalid_format() {
+        let result = parse_command(&args("snapshot --format xml"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("snapshot --format"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    // === Wait ===
+
+    #[test]
+    fn test_wait_selector() {
+        let cmd = parse_command(&args("wait #element"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "wait");
+        assert_eq!(cmd["selector"], "#element");
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let cmd = parse_command(&args("wait 5000"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "wait");
+        assert_eq!(cmd["timeout"], 5000);
+    }
+
+    #[test]
+    fn test_wait_url() {
+        let cmd = parse_command(&args("wait --url **/dashboard"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforurl");
+        assert_eq!(cmd["url"], "**/dashboard");
+    }
+
+    #[test]
+    fn test_wait_load() {
+        let cmd = parse_command(&args("wait --load networkidle"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforloadstate");
+        assert_eq!(cmd["state"], "networkidle");
+    }
+
+    #[test]
+    fn test_wait_load_missing_state() {
+        let result = parse_command(&args("wait --load"), &default_flags());
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ParseError::MissingArguments { .. }
+        ));
+    }
+
+    #[test]
+    fn test_wait_fn() {
+        let cmd = parse_command(&args("wait --fn window.ready"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforfunction");
+        assert_eq!(cmd["expression"], "window.ready");
+    }
+
+    #[test]
+    fn test_wait_text() {
+        let cmd = parse_command(&args("wait --text Welcome"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "wait");
+        assert_eq!(cmd["selector"], "text=Welcome");
+    }
+
+    #[test]
+    fn test_wait_idle() {
+        let cmd = parse_command(&args("wait --idle --timeout 8000"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforloadstate");
+        assert_eq!(cmd["state"], "networkidle");
+        assert_eq!(cmd["timeout"], 8000);
+    }
+
+    #[test]
+    fn test_wait_hidden() {
+        let cmd = parse_command(&args("wait --hidden #spinner"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "wait");
+        assert_eq!(cmd["selector"], "#spinner");
+        assert_eq!(cmd["state"], "hidden");
+    }
+
+    #[test]
+    fn test_wait_request() {
+        let cmd = parse_command(&args("wait --request **/api/cart"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforrequest");
+        assert_eq!(cmd["url"], "**/api/cart");
+    }
+
+    #[test]
+    fn test_wait_response() {
+        let cmd = parse_command(
+            &args("wait --response /api/cart --status 201 --timeout 5000"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "waitforresponse");
+        assert_eq!(cmd["url"], "/api/cart");
+        assert_eq!(cmd["status"], 201);
+        assert_eq!(cmd["timeout"], 5000);
+        let cmd = parse_command(&args("wait --response /api/cart"), &default_flags()).unwrap();
+        assert!(cmd.get("status").is_none());
+    }
+
+    #[test]
+    fn test_wait_spa_idle() {
+        let cmd = parse_command(&args("wait --spa-idle"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforspaidle");
+        assert!(cmd.get("quiet").is_none());
+        let cmd = parse_command(
+            &args("wait --spa-idle --quiet 1s --timeout 8000"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["quiet"], 1000);
+        assert_eq!(cmd["timeout"], 8000);
+        for input in [
+            "wait --spa-idle --quiet soon",
+            "wait --spa-idle --quiet 0",
+            "wait --idle --quiet 500",
+        ] {
+            let result = parse_command(&args(input), &default_flags());
+            assert!(
+                matches!(result, Err(ParseError::InvalidValue { .. })),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_wait_response_invalid_status() {
+        for input in [
+            "wait --response /api --status ok",
+            "wait --response /api --status 42",
+            "wait --request /api --status 200",
+        ] {
+            let result = parse_command(&args(input), &default_flags());
+            assert!(
+                matches!(result, Err(ParseError::InvalidValue { .. })),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_wait_timeout_applies_to_each_mode() {
+        for input in [
+            "wait --url **/done --timeout 3000",
+            "wait --timeout 3000 --text Welcome",
+            "wait #modal --timeout 3000",
+            "wait --fn window.ready --timeout 3000",
+        ] {
+            let cmd = parse_line(input).unwrap();
+            assert_eq!(cmd["timeout"], 3000, "{}", input);
+            // The command's own timeout, not a deadline for the whole command
+            assert!(cmd.get("deadline").is_none(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_wait_ms_rejects_timeout() {
+        let result = parse_line("wait 500 --timeout 3000");
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        // Before the command it is the global flag, a deadline for the wait
+        let cmd = parse_line("--timeout 3000 wait 500").unwrap();
+        assert_eq!(cmd["timeout"], 500);
+        assert!(cmd.get("deadline").is_some());
+    }
+
+    #[test]
+    fn test_wait_invalid_timeout() {
+        let result = parse_line("wait --idle --timeout soon");
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    // === Unknown command ===
+
+    // === Record Tests ===
+
+    #[test]
+    fn test_record_start() {
+        let cmd = parse_command(&args("record start output.webm"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "recording_start");
+        assert_eq!(cmd["path"], absolute_path("output.webm"));
+        assert!(cmd.get("url").is_none());
+    }
+
+    #[test]
+    fn test_record_start_with_url() {
+        let cmd = parse_command(
+            &args("record start demo.webm https://example.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "recording_start");
+        assert_eq!(cmd["path"], absolute_path("demo.webm"));
+        assert_eq!(cmd["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_record_start_with_url_no_protocol() {
+        let cmd = parse_command(
+            &args("record start demo.webm example.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "recording_start");
+        assert_eq!(cmd["path"], absolute_path("demo.webm"));
+        assert_eq!(cmd["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_record_start_dir() {
+        // The daemon runs elsewhere, so a directory is resolved here too
+        let cmd = parse_command(&args("record start videos/"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "recording_start");
+        assert_eq!(cmd["path"], absolute_path("videos/"));
+        assert!(std::path::Path::new(cmd["path"].as_str().unwrap()).is_absolute());
+    }
+
+    #[test]
+    fn test_record_start_script() {
+        let cmd = parse_command(
+            &args("record start login.ab example.com/login"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "codegen_start");
+        assert!(cmd["path"].as_str().unwrap().ends_with("login.ab"));
+        assert_eq!(cmd["url"], "https://example.com/login");
+        let cmd = parse_command(&args("record start steps.JSON"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "codegen_start");
+    }
+
+    #[test]
+    fn test_record_start_missing_path() {
+        let result = parse_command(&args("record start"), &default_flags());
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ParseError::MissingArguments { .. }
+        ));
+    }
+
+    #[test]
+    fn test_record_stop() {
+        let cmd = parse_command(&args("record stop"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "recording_stop");
+    }
+
+    #[test]
+    fn test_record_restart() {
+        let cmd = parse_command(&args("record restart output.webm"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "recording_restart");
+        assert_eq!(cmd["path"], absolute_path("output.webm"));
+        assert!(cmd.get("url").is_none());
+    }
+
+    #[test]
+    fn test_record_restart_with_url() {
+        let cmd = parse_command(
+            &args("record restart demo.webm https://example.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "recording_restart");
+        assert_eq!(cmd["path"], absolute_path("demo.webm"));
+        assert_eq!(cmd["url"], "https://example.com");
+    }
+
+    #[test]
+    fn test_record_restart_missing_path() {
+        let result = parse_command(&args("record restart"), &default_flags());
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ParseError::MissingArguments { .. }
+        ));
+    }
+
+    #[test]
+    fn test_record_invalid_subcommand() {
+        let result = parse_command(&args("record foo"), &default_flags());
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ParseError::UnknownSubcommand { .. }
+        ));
+    }
+
+    #[test]
+    fn test_record_missing_subcommand() {
+        let result = parse_command(&args("record"), &default_flags());
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ParseError::MissingArguments { .. }
+        ));
+    }
+
+    // === HAR Tests ===
+
+    #[test]
+    fn test_har_start() {
+        let cmd = parse_command(&args("har start session.har"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "har_start");
+        let path = cmd["path"].as_str().unwrap();
+        assert!(std::path::Path::new(path).is_absolute());
+        assert!(path.ends_with("session.har"));
+    }
+
+    #[test]
+    fn test_har_start_missing_path() {
+        let result = parse_command(&args("har start"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_har_stop() {
+        let cmd = parse_command(&args("har stop"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "har_stop");
+        assert!(cmd.get("path").is_none());
+
+        let cmd = parse_command(&args("har stop /tmp/other.har"), &default_flags()).unwrap();
+        assert_eq!(cmd["path"], "/tmp/other.har");
+    }
+
+    #[test]
+    fn test_har_unknown_subcommand() {
+        let result = parse_command(&args("har pause"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+    }
+
+    // === Eval Tests ===
+
+    #[test]
+    fn test_eval_basic() {
+        let cmd = parse_command(&args("eval document.title"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "evaluate");
+        assert_eq!(cmd["script"], "document.title");
+    }
+
+    #[test]
+    fn test_eval_base64_short_flag() {
+        // "document.title" in base64
+        let cmd = parse_command(&args("eval -b ZG9jdW1lbnQudGl0bGU="), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "evaluate");
+        assert_eq!(cmd["script"], "document.title");
+    }
+
+    #[test]
+    fn test_eval_base64_long_flag() {
+        // "document.title" in base64
+        let cmd =
+            parse_command(&args("eval --base64 ZG9jdW1lbnQudGl0bGU="), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "evaluate");
+        assert_eq!(cmd["script"], "document.title");
+    }
+
+    #[test]
+    fn test_eval_base64_with_special_chars() {
+        // "document.querySelector('[src*=\"_next\"]')" in base64
+        let cmd = parse_command(
+            &args("eval -b ZG9jdW1lbnQucXVlcnlTZWxlY3RvcignW3NyYyo9Il9uZXh0Il0nKQ=="),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "evaluate");
+        assert_eq!(cmd["script"], "document.querySelector('[src*=\"_next\"]')");
+    }
+
+    #[test]
+    fn test_eval_file_with_args_and_selector() {
+        let file = std::env::temp_dir().join(format!("ab-eval-{}.js", std::process::id()));
+        std::fs::write(&file, "(el, n) => el.children.length > n").unwrap();
+        let cmd = parse_command(
+            &args(&format!(
+                "eval --file {} --selector #list --arg 3 --arg {{\"a\":1}} --arg hi",
+                file.display()
+            )),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["script"], "(el, n) => el.children.length > n");
+        assert_eq!(cmd["selector"], "#list");
+        assert_eq!(cmd["args"], json!([3, {"a": 1}, "hi"]));
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_eval_inline_with_args() {
+        let cmd = parse_command(&args("eval (a,b)=>a+b --arg 1 --arg 2"), &default_flags());
+        let cmd = cmd.unwrap();
+        assert_eq!(cmd["script"], "(a,b)=>a+b");
+        assert_eq!(cmd["args"], json!([1, 2]));
+        assert!(cmd.get("selector").is_none());
+    }
+
+    #[test]
+    fn test_eval_invalid() {
+        let flags = default_flags();
+        assert!(matches!(
+            parse_command(&args("eval"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("eval --file /nonexistent.js"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("eval --file a.js document.title"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("eval document.title --arg"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+    }
+
+    #[test]
+    fn test_eval_base64_invalid() {
+        let result = parse_command(&args("eval -b !!!invalid!!!"), &default_flags());
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(matches!(err, ParseError::InvalidValue { .. }));
+        assert!(err.format().contains("Invalid base64"));
+    }
+
+    #[test]
+    fn test_raw() {
+        let flags = default_flags();
+        let cmd = parse_command(&args(r#"raw {"action":"title"}"#), &flags).unwrap();
+        assert_eq!(cmd["action"], "title");
+        assert!(cmd["id"].is_string());
+
+        let payload = r#"{"id":"mine","action":"scroll","direction":"down","amount":300}"#;
+        let file = std::env::temp_dir().join(format!("ab-raw-{}.json", std::process::id()));
+        std::fs::write(&file, payload).unwrap();
+        let cmd = parse_command(&args(&format!("raw --file {}", file.display())), &flags);
+        assert_eq!(
+            cmd.unwrap(),
+            serde_json::from_str::<Value>(payload).unwrap()
+        );
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_raw_invalid() {
+        let flags = default_flags();
+        assert!(matches!(
+            parse_command(&args("raw"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("raw {action"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args(r#"raw {"url":"https://example.com"}"#), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("raw --file /nonexistent.json"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let result = parse_command(&args("unknowncommand"), &default_flags());
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ParseError::UnknownCommand { .. }
+        ));
+    }
+
+    #[test]
+    fn test_unknown_flag() {
+        let err = parse_command(&args("snapshot --interactiv"), &default_flags()).unwrap_err();
+        assert!(matches!(err, ParseError::UnknownFlag { ref flag, .. } if flag == "--interactiv"));
+        assert!(err.format().starts_with(
+            "Unknown flag for snapshot: --interactiv\nValid flags: --interactive, -i"
+        ));
+        let err = parse_command(&args("back --force"), &default_flags()).unwrap_err();
+        assert!(err
+            .format()
+            .contains("Valid flags: none besides the global flags"));
+
+        // After `--`, text that looks like a flag is an argument
+        let cmd = parse_command(&args("fill @e1 -- --not-a-flag"), &default_flags()).unwrap();
+        assert_eq!(cmd["value"], "--not-a-flag");
+    }
+
+    #[test]
+    fn test_empty_args() {
+        let result = parse_command(&[], &default_flags());
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ParseError::MissingArguments { .. }
+        ));
+    }
+
+    // === Get Tests ===
+
+    #[test]
+    fn test_get_attr() {
+        let cmd = parse_command(&args("get attr @e1 href"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "getattribute");
+        assert_eq!(cmd["selector"], "@e1");
+        assert_eq!(cmd["attribute"], "href");
+    }
+
+    #[test]
+    fn test_get_attr_missing_name() {
+        let result = parse_command(&args("get attr @e1"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_get_style() {
+        let cmd = parse_command(&args("get style #error color font-size"), &default_flags());
+        let cmd = cmd.unwrap();
+        assert_eq!(cmd["action"], "computedstyle");
+        assert_eq!(cmd["selector"], "#error");
+        assert_eq!(cmd["properties"], json!(["color", "font-size"]));
+        // Custom properties come after `--`, so they aren't taken for flags
+        let cmd = parse_command(&args("get style @e2 -- --accent"), &default_flags());
+        assert_eq!(cmd.unwrap()["properties"], json!(["--accent"]));
+        let result = parse_command(&args("get style #error"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_get_count() {
+        let cmd = parse_command(&args("get count li.item"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "count");
+        assert_eq!(cmd["selector"], "li.item");
+    }
+
+    #[test]
+    fn test_get_url_parsed() {
+        let cmd = parse_command(&args("get url"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "url");
+        assert!(cmd.get("parsed").is_none());
+        let cmd = parse_command(&args("get url --parsed"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "url");
+        assert_eq!(cmd["parsed"], true);
+    }
+
+    #[test]
+    fn test_get_bbox_alias() {
+        let cmd = parse_command(&args("get bbox #header"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "boundingbox");
+        assert_eq!(cmd["selector"], "#header");
+    }
+
+    #[test]
+    fn test_get_html_page() {
+        let cmd = parse_command(&args("get html"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "content");
+        assert!(cmd.get("selector").is_none());
+    }
+
+    #[test]
+    fn test_get_html_selector_outer_output() {
+        let cmd = parse_command(
+            &args("get html #main --outer -o /tmp/main.html"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["selector"], "#main");
+        assert_eq!(cmd["outer"], true);
+        assert_eq!(cmd["path"], "/tmp/main.html");
+
+        let cmd = parse_command(&args("get html --selector @e2"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "@e2");
+    }
+
+    #[test]
+    fn test_get_html_invalid() {
+        let result = parse_command(&args("get html --outer"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("get html -o"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("get html #a #b"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_links() {
+        let cmd = parse_command(&args("links"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "links");
+        assert!(cmd.get("selector").is_none());
+
+        let cmd = parse_command(&args("links -s nav --absolute"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "nav");
+        assert_eq!(cmd["absolute"], true);
+    }
+
+    #[test]
+    fn test_forms() {
+        let cmd = parse_command(&args("forms"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "forms");
+    }
+
+    #[test]
+    fn test_forms_rejects_arguments() {
+        let result = parse_command(&args("forms #signup"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_table() {
+        let cmd = parse_command(&args("table #prices"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "table");
+        assert_eq!(cmd["selector"], "#prices");
+        assert!(cmd.get("format").is_none());
+
+        let cmd = parse_command(&args("table --format json @e4"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "@e4");
+        assert_eq!(cmd["format"], "json");
+    }
+
+    #[test]
+    fn test_table_invalid() {
+        let result = parse_command(&args("table"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("table #a --format xml"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("table #a #b"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_links_invalid() {
+        let result = parse_command(&args("links --selector"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("links nav"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_get_article() {
+        let cmd = parse_command(&args("get article"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "article");
+        assert!(cmd.get("selector").is_none());
+
+        let cmd = parse_command(&args("get article --selector #post"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#post");
+    }
+
+    #[test]
+    fn test_audit_a11y() {
+        let cmd = parse_command(&args("audit a11y --fail-on serious"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "audit");
+        assert_eq!(cmd["failOn"], "serious");
+        assert!(cmd.get("selector").is_none());
+
+        let cmd = parse_command(&args("audit a11y -s #main"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#main");
+    }
+
+    #[test]
+    fn test_audit_invalid() {
+        let result = parse_command(&args("audit a11y --fail-on bad"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("audit perf"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+    }
+
+    #[test]
+    fn test_get_metrics() {
+        let cmd = parse_command(&args("get metrics"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "metrics");
+    }
+
+    #[test]
+    fn test_get_article_invalid_args() {
+        let result = parse_command(&args("get article #post"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("get article --selector"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    // === Expect Tests ===
+
+    #[test]
+    fn test_expect_text() {
+        let cmd = parse_command(&args("expect text #status Saved"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "expect");
+        assert_eq!(cmd["kind"], "text");
+        assert_eq!(cmd["selector"], "#status");
+        assert_eq!(cmd["expected"], "Saved");
+        assert!(cmd.get("timeout").is_none());
+    }
+
+    #[test]
+    fn test_expect_text_multiword_with_timeout() {
+        let cmd = parse_command(
+            &args("expect text @e1 All changes saved --timeout 2000"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["expected"], "All changes saved");
+        assert_eq!(cmd["timeout"], 2000);
+    }
+
+    #[test]
+    fn test_expect_text_missing_expected() {
+        let result = parse_command(&args("expect text #status"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_expect_visible() {
+        let cmd = parse_command(
+            &args("expect visible --timeout 500 #modal"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["kind"], "visible");
+        assert_eq!(cmd["selector"], "#modal");
+        assert_eq!(cmd["timeout"], 500);
+    }
+
+    #[test]
+    fn test_expect_url_and_title() {
+        let cmd = parse_command(&args("expect url **/dashboard"), &default_flags()).unwrap();
+        assert_eq!(cmd["kind"], "url");
+        assert_eq!(cmd["expected"], "**/dashboard");
+
+        let cmd = parse_command(&args("expect title Example Domain"), &default_flags()).unwrap();
+        assert_eq!(cmd["kind"], "title");
+        assert_eq!(cmd["expected"], "Example Domain");
+    }
+
+    #[test]
+    fn test_expect_invalid_timeout() {
+        let result = parse_line("expect url /done --timeout 0");
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_expect_unknown_subcommand() {
+        let result = parse_command(&args("expect hidden #modal"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+    }
+
+    // === Error message tests ===
+
+    #[test]
+    fn test_get_missing_subcommand() {
+        let result = parse_command(&args("get"), &default_flags());
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(matches!(err, ParseError::MissingArguments { .. }));
+        assert!(err.format().contains("get"));
+    }
+
+    #[test]
+    fn test_get_unknown_subcommand() {
+        let result = parse_command(&args("get foo"), &default_flags());
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(matches!(err, ParseError::UnknownSubcommand { .. }));
+        assert!(err.format().contains("foo"));
+        assert!(err.format().contains("text"));
+    }
+
+    #[test]
+    fn test_get_text_missing_selector() {
+        let result = parse_command(&args("get text"), &default_flags());
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(matches!(err, ParseError::MissingArguments { .. }));
+        assert!(err.format().contains("get text"));
+    }
+
+    // === Protocol alignment tests ===
+
+    #[test]
+    fn test_mouse_wheel() {
+        let cmd = parse_command(&args("mouse wheel 100 50"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "wheel");
+        assert_eq!(cmd["deltaY"], 100);
+        assert_eq!(cmd["deltaX"], 50);
     }
 
-    fn args(s: &str) -> Vec<String> {
-        s.split_whitespace().map(String::from).collect()
+    #[test]
+    fn test_mouse_click() {
+        let cmd = parse_command(&args("mouse click 120 340"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "mouseclick");
+        assert_eq!(cmd["x"], 120);
+        assert_eq!(cmd["y"], 340);
+        assert_eq!(cmd["button"], "left");
+        assert_eq!(cmd["clickCount"], 1);
     }
 
-    // === Cookies Tests ===
+    #[test]
+    fn test_mouse_click_right_double() {
+        let cmd = parse_command(
+            &args("mouse click 10 20 --button right --double"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["button"], "right");
+        assert_eq!(cmd["clickCount"], 2);
+    }
 
     #[test]
-    fn test_cookies_get() {
-        let cmd = parse_command(&args("cookies"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "cookies_get");
+    fn test_mouse_click_missing_coordinates() {
+        let result = parse_command(&args("mouse click 10"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
     #[test]
-    fn test_cookies_get_explicit() {
-        let cmd = parse_command(&args("cookies get"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "cookies_get");
+    fn test_mouse_down_button() {
+        let cmd = parse_command(&args("mouse down"), &default_flags()).unwrap();
+        assert_eq!(cmd["button"], "left");
+        let cmd = parse_command(&args("mouse down --button right"), &default_flags()).unwrap();
+        assert_eq!(cmd["button"], "right");
+        let cmd = parse_command(&args("mouse up middle"), &default_flags()).unwrap();
+        assert_eq!(cmd["button"], "middle");
     }
 
     #[test]
-    fn test_cookies_set() {
-        let cmd = parse_command(&args("cookies set mycookie myvalue"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
+    fn test_mouse_invalid_button() {
+        let result = parse_command(&args("mouse down --button side"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
     #[test]
-    fn test_cookies_set_missing_value() {
-        let result = parse_command(&args("cookies set mycookie"), &default_flags());
-        assert!(result.is_err());
+    fn test_set_media() {
+        let cmd = parse_command(&args("set media dark"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "emulatemedia");
+        assert_eq!(cmd["colorScheme"], "dark");
+        assert_eq!(cmd["reducedMotion"], "no-preference");
     }
 
     #[test]
-    fn test_cookies_clear() {
-        let cmd = parse_command(&args("cookies clear"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "cookies_clear");
+    fn test_set_media_reduced_motion() {
+        let cmd = parse_command(&args("set media light reduced-motion"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "emulatemedia");
+        assert_eq!(cmd["colorScheme"], "light");
+        assert_eq!(cmd["reducedMotion"], "reduce");
     }
 
     #[test]
-    fn test_cookies_set_with_url() {
-        let cmd = parse_command(
-            &args("cookies set mycookie myvalue --url https://example.com"),
-            &default_flags(),
-        )
-        .unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["url"], "https://example.com");
+    fn test_set_user_agent() {
+        let cmd =
+            parse_command(&args("set user-agent MyBot/1.0 (test)"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "useragent");
+        assert_eq!(cmd["userAgent"], "MyBot/1.0 (test)");
+        assert!(parse_command(&args("set user-agent"), &default_flags()).is_err());
     }
 
     #[test]
-    fn test_cookies_set_with_domain() {
-        let cmd = parse_command(
-            &args("cookies set mycookie myvalue --domain example.com"),
-            &default_flags(),
-        )
-        .unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["domain"], "example.com");
+    fn test_set_credentials() {
+        let cmd = parse_command(&args("set credentials admin hunter2"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "credentials");
+        assert_eq!(cmd["username"], "admin");
+        assert_eq!(cmd["password"], "hunter2");
     }
 
     #[test]
-    fn test_cookies_set_with_path() {
-        let cmd = parse_command(
-            &args("cookies set mycookie myvalue --path /api"),
-            &default_flags(),
-        )
-        .unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["path"], "/api");
+    fn test_find_first_no_value() {
+        let cmd = parse_command(&args("find first a click"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "nth");
+        assert_eq!(cmd["index"], 0);
+        assert!(cmd.get("value").is_none());
     }
 
     #[test]
-    fn test_cookies_set_with_httponly() {
-        let cmd = parse_command(
-            &args("cookies set mycookie myvalue --httpOnly"),
-            &default_flags(),
-        )
-        .unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["httpOnly"], true);
+    fn test_find_first_with_value() {
+        let cmd = parse_command(&args("find first input fill hello"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "nth");
+        assert_eq!(cmd["index"], 0);
+        assert_eq!(cmd["value"], "hello");
     }
 
     #[test]
-    fn test_cookies_set_with_secure() {
-        let cmd = parse_command(
-            &args("cookies set mycookie myvalue --secure"),
-            &default_flags(),
-        )
-        .unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["secure"], true);
+    fn test_find_nth_no_value() {
+        let cmd = parse_command(&args("find nth 2 a click"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "nth");
+        assert_eq!(cmd["index"], 2);
+        assert!(cmd.get("value").is_none());
     }
 
     #[test]
-    fn test_cookies_set_with_samesite() {
-        let cmd = parse_command(
-            &args("cookies set mycookie myvalue --sameSite Strict"),
-            &default_flags(),
-        )
-        .unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["sameSite"], "Strict");
+    fn test_find_query() {
+        let cmd = parse_command(&args("find sign in"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "find");
+        assert_eq!(cmd["query"], "sign in");
+        assert!(cmd.get("role").is_none());
+        assert!(cmd.get("limit").is_none());
     }
 
     #[test]
-    fn test_cookies_set_with_expires() {
+    fn test_find_query_with_role_and_limit() {
         let cmd = parse_command(
-            &args("cookies set mycookie myvalue --expires 1234567890"),
+            &args("find --role button save draft --limit 3"),
             &default_flags(),
         )
         .unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["expires"], 1234567890);
+        assert_eq!(cmd["query"], "save draft");
+        assert_eq!(cmd["role"], "button");
+        assert_eq!(cmd["limit"], 3);
     }
 
     #[test]
-    fn test_cookies_set_with_multiple_flags() {
-        let cmd = parse_command(&args("cookies set mycookie myvalue --url https://example.com --httpOnly --secure --sameSite Lax"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["url"], "https://example.com");
-        assert_eq!(cmd["cookies"][0]["httpOnly"], true);
-        assert_eq!(cmd["cookies"][0]["secure"], true);
-        assert_eq!(cmd["cookies"][0]["sameSite"], "Lax");
+    fn test_find_query_invalid_limit() {
+        let result = parse_command(&args("find save --limit 0"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
     #[test]
-    fn test_cookies_set_with_all_flags() {
-        let cmd = parse_command(&args("cookies set mycookie myvalue --url https://example.com --domain example.com --path /api --httpOnly --secure --sameSite None --expires 9999999999"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "cookies_set");
-        assert_eq!(cmd["cookies"][0]["name"], "mycookie");
-        assert_eq!(cmd["cookies"][0]["value"], "myvalue");
-        assert_eq!(cmd["cookies"][0]["url"], "https://example.com");
-        assert_eq!(cmd["cookies"][0]["domain"], "example.com");
-        assert_eq!(cmd["cookies"][0]["path"], "/api");
-        assert_eq!(cmd["cookies"][0]["httpOnly"], true);
-        assert_eq!(cmd["cookies"][0]["secure"], true);
-        assert_eq!(cmd["cookies"][0]["sameSite"], "None");
-        assert_eq!(cmd["cookies"][0]["expires"], 9999999999i64);
+    fn test_find_unknown_locator() {
+        let result = parse_command(&args("find rol button click"), &default_flags());
+        assert!(
+            matches!(result, Err(ParseError::UnknownSubcommand { ref subcommand, .. }) if subcommand == "rol")
+        );
+        let result = parse_command(&args("find Role button"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+        // An action word elsewhere still makes a query
+        let cmd = parse_command(&args("find click here"), &default_flags()).unwrap();
+        assert_eq!(cmd["query"], "click here");
+        let cmd = parse_command(&args("find --role button click"), &default_flags()).unwrap();
+        assert_eq!(cmd["query"], "click");
     }
 
     #[test]
-    fn test_cookies_set_invalid_samesite() {
-        let result = parse_command(
-            &args("cookies set mycookie myvalue --sameSite Invalid"),
-            &default_flags(),
-        );
-        assert!(result.is_err());
+    fn test_find_query_missing() {
+        let result = parse_command(&args("find --role button"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
-    // === Storage Tests ===
+    // === Refs Tests ===
 
     #[test]
-    fn test_storage_local_get() {
-        let cmd = parse_command(&args("storage local"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "storage_get");
-        assert_eq!(cmd["type"], "local");
-        assert!(cmd.get("key").is_none());
+    fn test_refs() {
+        let cmd = parse_command(&args("refs"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "refs");
+        assert!(cmd.get("filter").is_none());
     }
 
     #[test]
-    fn test_storage_local_get_key() {
-        let cmd = parse_command(&args("storage local get mykey"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "storage_get");
-        assert_eq!(cmd["type"], "local");
-        assert_eq!(cmd["key"], "mykey");
+    fn test_refs_filter() {
+        let cmd = parse_command(&args("refs --filter sign in"), &default_flags()).unwrap();
+        assert_eq!(cmd["filter"], "sign in");
     }
 
     #[test]
-    fn test_storage_session_get() {
-        let cmd = parse_command(&args("storage session"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "storage_get");
-        assert_eq!(cmd["type"], "session");
+    fn test_refs_filter_missing() {
+        let result = parse_command(&args("refs --filter"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
     #[test]
-    fn test_storage_local_set() {
-        let cmd =
-            parse_command(&args("storage local set mykey myvalue"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "storage_set");
-        assert_eq!(cmd["type"], "local");
-        assert_eq!(cmd["key"], "mykey");
-        assert_eq!(cmd["value"], "myvalue");
+    fn test_refs_unknown_option() {
+        let result = parse_command(&args("refs button"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
     #[test]
-    fn test_storage_session_set() {
-        let cmd =
-            parse_command(&args("storage session set skey svalue"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "storage_set");
-        assert_eq!(cmd["type"], "session");
-        assert_eq!(cmd["key"], "skey");
-        assert_eq!(cmd["value"], "svalue");
+    fn test_visible_elements() {
+        let cmd = parse_command(&args("visible-elements"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "visible_elements");
+        assert!(cmd.get("roles").is_none());
+        let cmd = parse_command(
+            &args("visible-elements --role button,link --role textbox"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["roles"], json!(["button", "link", "textbox"]));
+        let result = parse_command(&args("visible-elements --role"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("visible-elements button"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
+    // === Trace Tests ===
+
     #[test]
-    fn test_storage_set_missing_value() {
-        let result = parse_command(&args("storage local set mykey"), &default_flags());
-        assert!(result.is_err());
+    fn test_trace_start() {
+        let cmd = parse_command(&args("trace start"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "trace_start");
+        assert!(cmd.get("screenshots").is_none());
+
+        let cmd = parse_command(&args("trace start --snapshots"), &default_flags()).unwrap();
+        assert_eq!(cmd["screenshots"], false);
+        assert_eq!(cmd["snapshots"], true);
+
+        let result = parse_command(&args("trace start --video"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownFlag { .. })));
     }
 
     #[test]
-    fn test_storage_local_clear() {
-        let cmd = parse_command(&args("storage local clear"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "storage_clear");
-        assert_eq!(cmd["type"], "local");
+    fn test_trace_stop_absolute_path() {
+        let cmd = parse_command(&args("trace stop run.zip"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "trace_stop");
+        assert!(std::path::Path::new(cmd["path"].as_str().unwrap()).is_absolute());
+        assert!(parse_command(&args("trace stop"), &default_flags()).is_err());
     }
 
+    // === Coverage Tests ===
+
     #[test]
-    fn test_storage_session_clear() {
-        let cmd = parse_command(&args("storage session clear"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "storage_clear");
-        assert_eq!(cmd["type"], "session");
+    fn test_coverage_start() {
+        let cmd = parse_command(&args("coverage start"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "coverage_start");
     }
 
     #[test]
-    fn test_storage_invalid_type() {
-        let result = parse_command(&args("storage invalid"), &default_flags());
-        assert!(result.is_err());
+    fn test_coverage_stop_lcov() {
+        let cmd = parse_command(&args("coverage stop"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "coverage_stop");
+        assert!(cmd.get("lcov").is_none());
+
+        let cmd = parse_command(&args("coverage stop --lcov out.info"), &default_flags()).unwrap();
+        assert!(std::path::Path::new(cmd["lcov"].as_str().unwrap()).is_absolute());
+
+        let result = parse_command(&args("coverage stop --lcov"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("coverage stop out.info"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
-    // === Navigation Tests ===
+    // === Dialog Tests ===
 
     #[test]
-    fn test_navigate_with_https() {
-        let cmd = parse_command(&args("open https://example.com"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "navigate");
-        assert_eq!(cmd["url"], "https://example.com");
+    fn test_dialog_accept_with_text() {
+        let cmd = parse_command(&args("dialog accept my answer"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "dialog");
+        assert_eq!(cmd["response"], "accept");
+        assert_eq!(cmd["promptText"], "my answer");
     }
 
     #[test]
-    fn test_navigate_without_protocol() {
-        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "navigate");
-        assert_eq!(cmd["url"], "https://example.com");
+    fn test_dialog_dismiss() {
+        let cmd = parse_command(&args("dialog dismiss"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "dialog");
+        assert_eq!(cmd["response"], "dismiss");
+        assert!(cmd.get("promptText").is_none());
     }
 
     #[test]
-    fn test_navigate_with_headers() {
-        let mut flags = default_flags();
-        flags.headers = Some(r#"{"Authorization": "Bearer token"}"#.to_string());
-        let cmd = parse_command(&args("open api.example.com"), &flags).unwrap();
-        assert_eq!(cmd["action"], "navigate");
-        assert_eq!(cmd["url"], "https://api.example.com");
-        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
-    }
+    fn test_dialog_auto() {
+        let cmd = parse_command(&args("dialog auto accept"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "dialog_auto");
+        assert_eq!(cmd["response"], "accept");
 
-    #[test]
-    fn test_navigate_with_multiple_headers() {
-        let mut flags = default_flags();
-        flags.headers =
-            Some(r#"{"Authorization": "Bearer token", "X-Custom": "value"}"#.to_string());
-        let cmd = parse_command(&args("open api.example.com"), &flags).unwrap();
-        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
-        assert_eq!(cmd["headers"]["X-Custom"], "value");
+        let cmd = parse_command(&args("dialog auto off"), &default_flags()).unwrap();
+        assert_eq!(cmd["response"], "off");
     }
 
     #[test]
-    fn test_navigate_without_headers_flag() {
-        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "navigate");
-        // headers should not be present when flag is not set
-        assert!(cmd.get("headers").is_none());
+    fn test_dialog_auto_invalid() {
+        let result = parse_command(&args("dialog auto maybe"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+        let result = parse_command(&args("dialog auto"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
+    // === Console Tests ===
+
     #[test]
-    fn test_navigate_with_invalid_headers_json() {
-        let mut flags = default_flags();
-        flags.headers = Some("not valid json".to_string());
-        let cmd = parse_command(&args("open api.example.com"), &flags).unwrap();
-        // Invalid JSON should result in no headers field (graceful handling)
-        assert!(cmd.get("headers").is_none());
+    fn test_console() {
+        let cmd = parse_command(&args("console"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "console");
+        assert_eq!(cmd["clear"], false);
+        assert!(cmd.get("level").is_none());
+        assert!(cmd.get("follow").is_none());
     }
 
-    // === Set Headers Tests ===
-
     #[test]
-    fn test_set_headers_parses_json() {
-        let input: Vec<String> = vec![
-            "set".to_string(),
-            "headers".to_string(),
-            r#"{"Authorization":"Bearer token"}"#.to_string(),
-        ];
-        let cmd = parse_command(&input, &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "headers");
-        // Headers should be an object, not a string
-        assert!(cmd["headers"].is_object());
-        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
+    fn test_console_follow_level() {
+        let cmd = parse_command(&args("console --follow --level error"), &default_flags()).unwrap();
+        assert_eq!(cmd["follow"], true);
+        assert_eq!(cmd["level"], "error");
+    }
+
+    #[test]
+    fn test_console_clear() {
+        let cmd = parse_command(&args("console --clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["clear"], true);
     }
 
     #[test]
-    fn test_set_headers_with_multiple_values() {
-        let input: Vec<String> = vec![
-            "set".to_string(),
-            "headers".to_string(),
-            r#"{"Authorization": "Bearer token", "X-Custom": "value"}"#.to_string(),
-        ];
-        let cmd = parse_command(&input, &default_flags()).unwrap();
-        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
-        assert_eq!(cmd["headers"]["X-Custom"], "value");
+    fn test_console_invalid_level() {
+        let result = parse_command(&args("console --level debug"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
     #[test]
-    fn test_set_headers_invalid_json_error() {
-        let input: Vec<String> = vec![
-            "set".to_string(),
-            "headers".to_string(),
-            "not-valid-json".to_string(),
-        ];
-        let result = parse_command(&input, &default_flags());
-        assert!(result.is_err());
+    fn test_console_follow_with_clear() {
+        let result = parse_command(&args("console --follow --clear"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
+    // === Events Tests ===
+
     #[test]
-    fn test_back() {
-        let cmd = parse_command(&args("back"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "back");
+    fn test_events_follow() {
+        let cmd = parse_command(&args("events --follow"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "subscribe");
+        assert_eq!(cmd["follow"], true);
+        assert!(cmd.get("types").is_none());
     }
 
     #[test]
-    fn test_forward() {
-        let cmd = parse_command(&args("forward"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "forward");
+    fn test_events_types() {
+        let cmd = parse_command(
+            &args("events --follow --types console,dialog"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["types"], json!(["console", "dialog"]));
     }
 
     #[test]
-    fn test_reload() {
-        let cmd = parse_command(&args("reload"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "reload");
+    fn test_events_unknown_type() {
+        let result = parse_command(&args("events --types console,cookies"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
-    // === Core Actions ===
+    // === Click By Text Tests ===
 
     #[test]
-    fn test_click() {
-        let cmd = parse_command(&args("click #button"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "click");
-        assert_eq!(cmd["selector"], "#button");
+    fn test_click_by_text() {
+        let cmd = parse_command(&args("click --text Sign-in"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "getbyname");
+        assert_eq!(cmd["subaction"], "click");
+        assert_eq!(cmd["text"], "Sign-in");
+        assert!(cmd.get("exact").is_none());
+        assert!(cmd.get("index").is_none());
+        let cmd =
+            parse_command(&args("hover --text Menu --exact --nth 2"), &default_flags()).unwrap();
+        assert_eq!(cmd["subaction"], "hover");
+        assert_eq!(cmd["exact"], true);
+        assert_eq!(cmd["index"], 2);
+        let cmd = parse_command(&args("click --text Next --nth -1"), &default_flags()).unwrap();
+        assert_eq!(cmd["index"], -1);
     }
 
     #[test]
-    fn test_fill() {
-        let cmd = parse_command(&args("fill #input hello world"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "fill");
-        assert_eq!(cmd["selector"], "#input");
-        assert_eq!(cmd["value"], "hello world");
+    fn test_click_by_text_errors() {
+        let result = parse_command(&args("click --text"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("click --text Next --nth first"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("click #btn --text Next"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
+    // === Multiple Match Tests ===
+
     #[test]
-    fn test_type_command() {
-        let cmd = parse_command(&args("type #input some text"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "type");
-        assert_eq!(cmd["selector"], "#input");
-        assert_eq!(cmd["text"], "some text");
+    fn test_nth_and_all() {
+        let flags = default_flags();
+        let cmd = parse_command(&args("click .item --nth 2"), &flags).unwrap();
+        assert_eq!(cmd["action"], "click");
+        assert_eq!(cmd["selector"], ".item");
+        assert_eq!(cmd["index"], 2);
+        assert!(cmd.get("all").is_none());
+
+        let cmd = parse_command(&args("get text .price --all"), &flags).unwrap();
+        assert_eq!(cmd["action"], "gettext");
+        assert_eq!(cmd["selector"], ".price");
+        assert_eq!(cmd["all"], true);
+
+        let cmd = parse_command(&args("check --all input.row"), &flags).unwrap();
+        assert_eq!(cmd["selector"], "input.row");
+        assert_eq!(cmd["all"], true);
+
+        let cmd = parse_command(&args("hover li --nth -1"), &flags).unwrap();
+        assert_eq!(cmd["index"], -1);
+
+        let cmd = parse_command(&args("click .item"), &flags).unwrap();
+        assert!(cmd.get("index").is_none() && cmd.get("all").is_none());
     }
 
     #[test]
-    fn test_select() {
-        let cmd = parse_command(&args("select #menu option1"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "select");
-        assert_eq!(cmd["selector"], "#menu");
-        assert_eq!(cmd["values"], "option1");
+    fn test_nth_and_all_errors() {
+        let flags = default_flags();
+        let result = parse_command(&args("click .item --nth 1 --all"), &flags);
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("get text .item --nth -2"), &flags);
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("check .item --nth"), &flags);
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("click --all"), &flags);
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
+    // === Highlight Tests ===
+
     #[test]
-    fn test_select_multiple_values() {
-        let cmd = parse_command(&args("select #menu opt1 opt2 opt3"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "select");
-        assert_eq!(cmd["selector"], "#menu");
-        assert_eq!(cmd["values"], json!(["opt1", "opt2", "opt3"]));
+    fn test_highlight() {
+        let cmd = parse_command(&args("highlight .card"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "highlight");
+        assert_eq!(cmd["selector"], ".card");
+        assert!(cmd.get("duration").is_none());
+        let cmd = parse_command(
+            &args("highlight .card --duration 5s --label cards"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["duration"], 5000);
+        assert_eq!(cmd["label"], "cards");
     }
 
     #[test]
-    fn test_frame_main() {
-        let cmd = parse_command(&args("frame main"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "mainframe");
+    fn test_highlight_errors() {
+        let result = parse_command(&args("highlight"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("highlight .card --duration soon"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("highlight .card --label"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
-    // === Tabs ===
+    // === Watch Tests ===
 
     #[test]
-    fn test_tab_new() {
-        let cmd = parse_command(&args("tab new"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "tab_new");
-        assert!(
-            cmd.get("url").is_none(),
-            "url should not be present when not provided"
-        );
+    fn test_watch() {
+        let cmd = parse_command(&args("watch #results"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "watch");
+        assert_eq!(cmd["selector"], "#results");
+        assert_eq!(cmd["follow"], true);
+        assert!(cmd.get("events").is_none());
+        let cmd = parse_command(
+            &args("watch @e4 --event added,removed --once"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["selector"], "@e4");
+        assert_eq!(cmd["events"], json!(["added", "removed"]));
+        assert_eq!(cmd["once"], true);
     }
 
     #[test]
-    fn test_tab_new_with_url() {
-        let cmd = parse_command(&args("tab new https://example.com"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "tab_new");
-        assert_eq!(cmd["url"], "https://example.com");
+    fn test_watch_timeout() {
+        let mut flags = default_flags();
+        flags.timeout = Some("5000".to_string());
+        let cmd = parse_command(&args("watch #results --once"), &flags).unwrap();
+        assert_eq!(cmd["timeout"], 5000);
     }
 
     #[test]
-    fn test_tab_list() {
-        let cmd = parse_command(&args("tab list"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "tab_list");
+    fn test_watch_errors() {
+        let result = parse_command(&args("watch"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("watch #list --event moved"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("watch #list #other"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
+    // === Read Tests ===
+
     #[test]
-    fn test_tab_switch() {
-        let cmd = parse_command(&args("tab 2"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "tab_switch");
-        assert_eq!(cmd["index"], 2);
+    fn test_read() {
+        let cmd = parse_command(&args("read"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "read");
+        assert!(cmd.get("selector").is_none());
+        let cmd = parse_command(&args("read #pricing --max-chars 500"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#pricing");
+        assert_eq!(cmd["maxChars"], 500);
+        let cmd = parse_command(&args("read next"), &default_flags()).unwrap();
+        assert_eq!(cmd["direction"], "next");
+        assert!(cmd.get("selector").is_none());
     }
 
     #[test]
-    fn test_tab_close() {
-        let cmd = parse_command(&args("tab close"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "tab_close");
+    fn test_read_invalid() {
+        let result = parse_command(&args("read next #pricing"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("read --max-chars 0"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
-    // === Screenshot ===
+    // === Save Tests ===
 
     #[test]
-    fn test_screenshot() {
-        let cmd = parse_command(&args("screenshot"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["path"], serde_json::Value::Null);
-        assert_eq!(cmd["selector"], serde_json::Value::Null);
+    fn test_save() {
+        let cmd = parse_command(&args("save /tmp/page.mhtml"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "save");
+        assert_eq!(cmd["path"], "/tmp/page.mhtml");
+        let cmd = parse_command(&args("save --html-dir /tmp/page"), &default_flags()).unwrap();
+        assert_eq!(cmd["htmlDir"], "/tmp/page");
+        assert!(cmd.get("path").is_none());
     }
 
     #[test]
-    fn test_screenshot_path() {
-        let cmd = parse_command(&args("screenshot out.png"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["path"], "out.png");
+    fn test_save_invalid() {
+        let result = parse_command(&args("save"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("save a.mhtml --html-dir /tmp/page"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
+    // === Context Tests ===
+
     #[test]
-    fn test_screenshot_full_page() {
-        let mut flags = default_flags();
-        flags.full = true;
-        let cmd = parse_command(&args("screenshot"), &flags).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["fullPage"], true);
+    fn test_context() {
+        let cmd = parse_command(&args("context new --name work"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "context_new");
+        assert_eq!(cmd["name"], "work");
+        let cmd = parse_command(&args("context new"), &default_flags()).unwrap();
+        assert!(cmd.get("name").is_none());
+        let cmd = parse_command(&args("context"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "context_list");
+        let cmd = parse_command(&args("context switch work"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "context_switch");
+        assert_eq!(cmd["name"], "work");
+        let cmd = parse_command(&args("context close"), &default_flags()).unwrap();
+        assert!(cmd.get("name").is_none());
     }
 
     #[test]
-    fn test_screenshot_with_ref() {
-        let cmd = parse_command(&args("screenshot @e1"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["selector"], "@e1");
-        assert_eq!(cmd["path"], serde_json::Value::Null);
+    fn test_context_invalid() {
+        let result = parse_command(&args("context switch"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("context new work"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("context open"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
     }
 
+    // === History Tests ===
+
     #[test]
-    fn test_screenshot_with_css_class() {
-        let cmd = parse_command(&args("screenshot .my-button"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["selector"], ".my-button");
-        assert_eq!(cmd["path"], serde_json::Value::Null);
+    fn test_history() {
+        let cmd = parse_command(&args("history"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "history");
+        assert_eq!(cmd["limit"], 20);
+        let cmd = parse_command(&args("history -n 5"), &default_flags()).unwrap();
+        assert_eq!(cmd["limit"], 5);
     }
 
     #[test]
-    fn test_screenshot_with_css_id() {
-        let cmd = parse_command(&args("screenshot #header"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["selector"], "#header");
-        assert_eq!(cmd["path"], serde_json::Value::Null);
+    fn test_history_go() {
+        let cmd = parse_command(&args("history go 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "history_go");
+        assert_eq!(cmd["index"], 3);
+        let result = parse_command(&args("history go back"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("history -n 0"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
+    // === Network Log Tests ===
+
     #[test]
-    fn test_screenshot_with_path() {
-        let cmd = parse_command(&args("screenshot ./output.png"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["selector"], serde_json::Value::Null);
-        assert_eq!(cmd["path"], "./output.png");
+    fn test_network_log() {
+        let cmd = parse_command(&args("network log"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "network_log");
+        assert!(cmd.get("filter").is_none());
+        assert!(cmd.get("since").is_none());
     }
 
     #[test]
-    fn test_screenshot_with_selector_and_path() {
-        let cmd = parse_command(&args("screenshot .btn ./button.png"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "screenshot");
-        assert_eq!(cmd["selector"], ".btn");
-        assert_eq!(cmd["path"], "./button.png");
+    fn test_network_log_filter_and_since() {
+        let cmd = parse_command(
+            &args("network log --filter **/api/** --since 42"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["filter"], "**/api/**");
+        assert_eq!(cmd["since"], 42);
     }
 
-    // === Snapshot ===
-
     #[test]
-    fn test_snapshot() {
-        let cmd = parse_command(&args("snapshot"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "snapshot");
+    fn test_network_capture() {
+        let cmd = parse_command(
+            &args("network capture **/api/cart* --limit 5"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "network_capture");
+        assert_eq!(cmd["url"], "**/api/cart*");
+        assert_eq!(cmd["limit"], 5);
+        let cmd = parse_command(&args("network capture off"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "network_capture_stop");
+        let result = parse_command(&args("network capture"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("network capture /api --limit 0"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
     #[test]
-    fn test_snapshot_interactive() {
-        let cmd = parse_command(&args("snapshot -i"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "snapshot");
-        assert_eq!(cmd["interactive"], true);
+    fn test_network_show() {
+        let cmd = parse_command(&args("network show 42"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "network_show");
+        assert_eq!(cmd["requestId"], 42);
+        let result = parse_command(&args("network show latest"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("network show"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
     #[test]
-    fn test_snapshot_cursor() {
-        let cmd = parse_command(&args("snapshot -C"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "snapshot");
-        assert_eq!(cmd["cursor"], true);
+    fn test_network_log_invalid_since() {
+        let result = parse_command(&args("network log --since latest"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
     #[test]
-    fn test_snapshot_interactive_cursor() {
-        let cmd = parse_command(&args("snapshot -i -C"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "snapshot");
-        assert_eq!(cmd["interactive"], true);
-        assert_eq!(cmd["cursor"], true);
+    fn test_network_unknown_subcommand_lists_log() {
+        match parse_command(&args("network foo"), &default_flags()) {
+            Err(ParseError::UnknownSubcommand { valid_options, .. }) => {
+                assert!(valid_options.contains(&"log"));
+            }
+            other => panic!("expected UnknownSubcommand, got {:?}", other),
+        }
     }
 
+    // === Viewport & Device Tests ===
+
     #[test]
-    fn test_snapshot_compact() {
-        let cmd = parse_command(&args("snapshot --compact"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "snapshot");
-        assert_eq!(cmd["compact"], true);
+    fn test_viewport() {
+        let cmd = parse_command(&args("viewport 390 844 --scale 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "viewport");
+        assert_eq!(cmd["width"], 390);
+        assert_eq!(cmd["height"], 844);
+        assert_eq!(cmd["deviceScaleFactor"], 3.0);
+
+        let cmd = parse_command(&args("set viewport 1920 1080"), &default_flags()).unwrap();
+        assert_eq!(cmd["width"], 1920);
+        assert!(cmd.get("deviceScaleFactor").is_none());
     }
 
     #[test]
-    fn test_snapshot_depth() {
-        let cmd = parse_command(&args("snapshot -d 3"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "snapshot");
-        assert_eq!(cmd["maxDepth"], 3);
+    fn test_viewport_invalid() {
+        let flags = default_flags();
+        assert!(matches!(
+            parse_command(&args("viewport 390"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("viewport wide 844"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("viewport 390 844 --scale 0"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("viewport 390 844 --scale"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
     }
 
-    // === Wait ===
+    #[test]
+    fn test_device() {
+        let cmd = parse_command(&args("device iPhone 14"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "device");
+        assert_eq!(cmd["device"], "iPhone 14");
+
+        let cmd = parse_command(&args("set device Pixel 7"), &default_flags()).unwrap();
+        assert_eq!(cmd["device"], "Pixel 7");
+
+        let cmd = parse_command(&args("device list"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "device_list");
+    }
 
     #[test]
-    fn test_wait_selector() {
-        let cmd = parse_command(&args("wait #element"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "wait");
-        assert_eq!(cmd["selector"], "#element");
+    fn test_status() {
+        let cmd = parse_command(&args("status"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "status");
     }
 
     #[test]
-    fn test_wait_timeout() {
-        let cmd = parse_command(&args("wait 5000"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "wait");
-        assert_eq!(cmd["timeout"], 5000);
+    fn test_daemon() {
+        let cmd = parse_command(&args("daemon status"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "daemon_status");
+
+        let mut flags = default_flags();
+        flags.idle_timeout = Some("10m".to_string());
+        flags.cli_idle_timeout = true;
+        flags.max_lifetime = Some("off".to_string());
+        flags.cli_max_lifetime = true;
+        let cmd = parse_command(&args("daemon config"), &flags).unwrap();
+        assert_eq!(cmd["action"], "daemon_config");
+        assert_eq!(cmd["idleTimeout"], 600_000);
+        assert_eq!(cmd["maxLifetime"], 0);
+
+        // Limits only from the environment don't count as asking for a change
+        flags.cli_idle_timeout = false;
+        flags.cli_max_lifetime = false;
+        assert!(matches!(
+            parse_command(&args("daemon config"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+
+        flags.idle_timeout = Some("soon".to_string());
+        flags.cli_idle_timeout = true;
+        assert!(matches!(
+            parse_command(&args("daemon config"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+
+        // Main runs it; a script or chain can't
+        assert!(matches!(
+            parse_command(&args("daemon upgrade"), &default_flags()),
+            Err(ParseError::InvalidValue { .. })
+        ));
     }
 
     #[test]
-    fn test_wait_url() {
-        let cmd = parse_command(&args("wait --url **/dashboard"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "waitforurl");
-        assert_eq!(cmd["url"], "**/dashboard");
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("250"), Some(250));
+        assert_eq!(parse_duration("90s"), Some(90_000));
+        assert_eq!(parse_duration("10m"), Some(600_000));
+        assert_eq!(parse_duration("2h"), Some(7_200_000));
+        assert_eq!(parse_duration("off"), Some(0));
+        assert_eq!(parse_duration("1d"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("-5s"), None);
     }
 
     #[test]
-    fn test_wait_load() {
-        let cmd = parse_command(&args("wait --load networkidle"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "waitforloadstate");
-        assert_eq!(cmd["state"], "networkidle");
+    fn test_emulate() {
+        let cmd = parse_command(&args("emulate geo 48.8566 2.3522"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "geolocation");
+        assert_eq!(cmd["latitude"], 48.8566);
+        assert_eq!(cmd["longitude"], 2.3522);
+        assert!(cmd.get("accuracy").is_none());
+
+        let flags = default_flags();
+        let cmd = parse_command(&args("emulate geo -33.9 151.2 --accuracy 50"), &flags).unwrap();
+        assert_eq!(cmd["accuracy"], 50.0);
+
+        let cmd = parse_command(&args("emulate locale de-DE"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "locale");
+        assert_eq!(cmd["locale"], "de-DE");
+
+        let cmd = parse_command(&args("emulate timezone Asia/Tokyo"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "timezone");
+        assert_eq!(cmd["timezone"], "Asia/Tokyo");
+
+        let cmd = parse_command(&args("emulate reset"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "emulate_reset");
     }
 
     #[test]
-    fn test_wait_load_missing_state() {
-        let result = parse_command(&args("wait --load"), &default_flags());
-        assert!(result.is_err());
+    fn test_emulate_invalid() {
+        let flags = default_flags();
         assert!(matches!(
-            result.unwrap_err(),
-            ParseError::MissingArguments { .. }
+            parse_command(&args("emulate geo 48.8"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("emulate geo 91 0"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("emulate locale"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("emulate weather"), &flags),
+            Err(ParseError::UnknownSubcommand { .. })
         ));
     }
 
     #[test]
-    fn test_wait_fn() {
-        let cmd = parse_command(&args("wait --fn window.ready"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "waitforfunction");
-        assert_eq!(cmd["expression"], "window.ready");
+    fn test_permissions() {
+        let flags = default_flags();
+        let cmd = parse_command(
+            &args("permissions grant example.com camera clipboard-read,notifications"),
+            &flags,
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "permissions");
+        assert_eq!(cmd["grant"], true);
+        assert_eq!(cmd["origin"], "https://example.com");
+        assert_eq!(
+            cmd["permissions"],
+            json!(["camera", "clipboard-read", "notifications"])
+        );
+
+        let cmd = parse_command(&args("permissions grant * geolocation"), &flags).unwrap();
+        assert!(cmd.get("origin").is_none());
+        let cmd = parse_command(
+            &args("permissions grant http://localhost:3000/ geolocation"),
+            &flags,
+        )
+        .unwrap();
+        assert_eq!(cmd["origin"], "http://localhost:3000");
+
+        let cmd = parse_command(&args("permissions clear"), &flags).unwrap();
+        assert_eq!(cmd["grant"], false);
+        assert_eq!(cmd["permissions"], json!([]));
+
+        assert!(matches!(
+            parse_command(&args("permissions grant example.com"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("permissions grant example.com webcam"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("permissions revoke"), &flags),
+            Err(ParseError::UnknownSubcommand { .. })
+        ));
     }
 
+    // === Clipboard Tests ===
+
     #[test]
-    fn test_wait_text() {
-        let cmd = parse_command(&args("wait --text Welcome"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "wait");
-        assert_eq!(cmd["selector"], "text=Welcome");
+    fn test_clipboard() {
+        let cmd = parse_command(&args("clipboard read"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "clipboard");
+        assert_eq!(cmd["operation"], "read");
+
+        let cmd = parse_command(&args("clipboard write hello world"), &default_flags()).unwrap();
+        assert_eq!(cmd["operation"], "write");
+        assert_eq!(cmd["text"], "hello world");
+
+        let cmd = parse_command(&args("clipboard paste"), &default_flags()).unwrap();
+        assert_eq!(cmd["operation"], "paste");
     }
 
-    // === Unknown command ===
+    #[test]
+    fn test_clipboard_invalid() {
+        let result = parse_command(&args("clipboard write"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("clipboard"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+        let result = parse_command(&args("clipboard clear"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
+    }
 
-    // === Record Tests ===
+    // === Fetch Tests ===
 
     #[test]
-    fn test_record_start() {
-        let cmd = parse_command(&args("record start output.webm"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "recording_start");
-        assert_eq!(cmd["path"], "output.webm");
-        assert!(cmd.get("url").is_none());
+    fn test_fetch_get() {
+        let cmd = parse_command(&args("fetch /api/me"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "fetch");
+        assert_eq!(cmd["url"], "/api/me");
+        assert!(cmd.get("method").is_none());
+        assert!(cmd.get("body").is_none());
+        assert!(cmd.get("headers").is_none());
     }
 
     #[test]
-    fn test_record_start_with_url() {
+    fn test_fetch_post_body_file() {
+        let file = std::env::temp_dir().join(format!("ab-fetch-{}.json", std::process::id()));
+        std::fs::write(&file, r#"{"q":1}"#).unwrap();
         let cmd = parse_command(
-            &args("record start demo.webm https://example.com"),
+            &args(&format!(
+                "fetch /api/search --method post --body @{} -H x-csrf:abc",
+                file.display()
+            )),
             &default_flags(),
         )
         .unwrap();
-        assert_eq!(cmd["action"], "recording_start");
-        assert_eq!(cmd["path"], "demo.webm");
-        assert_eq!(cmd["url"], "https://example.com");
+        assert_eq!(cmd["method"], "POST");
+        assert_eq!(cmd["headers"]["x-csrf"], "abc");
+        assert_eq!(cmd["headers"]["Content-Type"], "application/json");
+        let body = STANDARD.decode(cmd["body"].as_str().unwrap()).unwrap();
+        assert_eq!(body, br#"{"q":1}"#);
+        std::fs::remove_file(file).unwrap();
     }
 
     #[test]
-    fn test_record_start_with_url_no_protocol() {
+    fn test_fetch_inline_body_and_output() {
         let cmd = parse_command(
-            &args("record start demo.webm example.com"),
+            &args("fetch /x -d a=1 -H content-type:text/plain -o out.bin"),
             &default_flags(),
         )
         .unwrap();
-        assert_eq!(cmd["action"], "recording_start");
-        assert_eq!(cmd["path"], "demo.webm");
-        assert_eq!(cmd["url"], "https://example.com");
+        let body = STANDARD.decode(cmd["body"].as_str().unwrap()).unwrap();
+        assert_eq!(body, b"a=1");
+        assert_eq!(cmd["headers"]["content-type"], "text/plain");
+        assert!(cmd["headers"].get("Content-Type").is_none());
+        assert!(cmd["path"].as_str().unwrap().ends_with("out.bin"));
+        assert!(std::path::Path::new(cmd["path"].as_str().unwrap()).is_absolute());
     }
 
     #[test]
-    fn test_record_start_missing_path() {
-        let result = parse_command(&args("record start"), &default_flags());
-        assert!(result.is_err());
+    fn test_fetch_errors() {
+        let flags = default_flags();
         assert!(matches!(
-            result.unwrap_err(),
-            ParseError::MissingArguments { .. }
+            parse_command(&args("fetch"), &flags),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("fetch /x --header nocolon"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("fetch /x --body @/nonexistent.json"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("fetch /x --method"), &flags),
+            Err(ParseError::MissingArguments { .. })
         ));
     }
 
-    #[test]
-    fn test_record_stop() {
-        let cmd = parse_command(&args("record stop"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "recording_stop");
-    }
+    // === Network Conditions Tests ===
 
     #[test]
-    fn test_record_restart() {
-        let cmd = parse_command(&args("record restart output.webm"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "recording_restart");
-        assert_eq!(cmd["path"], "output.webm");
-        assert!(cmd.get("url").is_none());
+    fn test_network_offline() {
+        let cmd = parse_command(&args("network offline on"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "offline");
+        assert_eq!(cmd["offline"], true);
+        let cmd = parse_command(&args("network offline off"), &default_flags()).unwrap();
+        assert_eq!(cmd["offline"], false);
+
+        let result = parse_command(&args("network offline maybe"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("network offline"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
     #[test]
-    fn test_record_restart_with_url() {
+    fn test_network_throttle() {
+        let cmd = parse_command(&args("network throttle slow3g"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "throttle");
+        assert_eq!(cmd["profile"], "slow3g");
+
         let cmd = parse_command(
-            &args("record restart demo.webm https://example.com"),
+            &args("network throttle custom --down 1000 --up 250 --latency 0"),
             &default_flags(),
         )
         .unwrap();
-        assert_eq!(cmd["action"], "recording_restart");
-        assert_eq!(cmd["path"], "demo.webm");
-        assert_eq!(cmd["url"], "https://example.com");
+        assert_eq!(cmd["profile"], "custom");
+        assert_eq!(cmd["downloadKbps"], 1000.0);
+        assert_eq!(cmd["uploadKbps"], 250.0);
+        assert_eq!(cmd["latencyMs"], 0.0);
     }
 
     #[test]
-    fn test_record_restart_missing_path() {
-        let result = parse_command(&args("record restart"), &default_flags());
-        assert!(result.is_err());
+    fn test_network_throttle_invalid() {
+        let flags = default_flags();
         assert!(matches!(
-            result.unwrap_err(),
-            ParseError::MissingArguments { .. }
+            parse_command(&args("network throttle 2g"), &flags),
+            Err(ParseError::InvalidValue { .. })
         ));
-    }
-
-    #[test]
-    fn test_record_invalid_subcommand() {
-        let result = parse_command(&args("record foo"), &default_flags());
-        assert!(result.is_err());
         assert!(matches!(
-            result.unwrap_err(),
-            ParseError::UnknownSubcommand { .. }
+            parse_command(&args("network throttle custom"), &flags),
+            Err(ParseError::MissingArguments { .. })
         ));
-    }
-
-    #[test]
-    fn test_record_missing_subcommand() {
-        let result = parse_command(&args("record"), &default_flags());
-        assert!(result.is_err());
         assert!(matches!(
-            result.unwrap_err(),
-            ParseError::MissingArguments { .. }
+            parse_command(&args("network throttle custom --down 0"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("network throttle slow3g --latency 10"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("network throttle custom --up"), &flags),
+            Err(ParseError::MissingArguments { .. })
         ));
     }
 
-    // === Eval Tests ===
-
-    #[test]
-    fn test_eval_basic() {
-        let cmd = parse_command(&args("eval document.title"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "evaluate");
-        assert_eq!(cmd["script"], "document.title");
-    }
+    // === Mock Tests ===
 
     #[test]
-    fn test_eval_base64_short_flag() {
-        // "document.title" in base64
-        let cmd = parse_command(&args("eval -b ZG9jdW1lbnQudGl0bGU="), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "evaluate");
-        assert_eq!(cmd["script"], "document.title");
+    fn test_mock_add_body_file() {
+        let file = std::env::temp_dir().join(format!("ab-mock-{}.json", std::process::id()));
+        std::fs::write(&file, r#"{"ok":true}"#).unwrap();
+        let cmd = parse_command(
+            &args(&format!(
+                "mock add **/api/users --status 201 --body-file {} --header x-test:1",
+                file.display()
+            )),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "mock_add");
+        assert_eq!(cmd["url"], "**/api/users");
+        assert_eq!(cmd["status"], 201);
+        assert_eq!(cmd["contentType"], "application/json");
+        assert_eq!(cmd["headers"]["x-test"], "1");
+        let body = STANDARD.decode(cmd["body"].as_str().unwrap()).unwrap();
+        assert_eq!(body, br#"{"ok":true}"#);
+        std::fs::remove_file(file).unwrap();
     }
 
     #[test]
-    fn test_eval_base64_long_flag() {
-        // "document.title" in base64
-        let cmd =
-            parse_command(&args("eval --base64 ZG9jdW1lbnQudGl0bGU="), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "evaluate");
-        assert_eq!(cmd["script"], "document.title");
+    fn test_mock_add_defaults() {
+        let cmd = parse_command(&args("mock add **/health"), &default_flags()).unwrap();
+        assert_eq!(cmd["status"], 200);
+        assert_eq!(cmd["body"], "");
+        assert!(cmd.get("contentType").is_none());
+        assert!(cmd.get("headers").is_none());
     }
 
     #[test]
-    fn test_eval_base64_with_special_chars() {
-        // "document.querySelector('[src*=\"_next\"]')" in base64
+    fn test_mock_add_content_type_header() {
         let cmd = parse_command(
-            &args("eval -b ZG9jdW1lbnQucXVlcnlTZWxlY3RvcignW3NyYyo9Il9uZXh0Il0nKQ=="),
+            &args("mock add **/x --body hi --header Content-Type:text/csv"),
             &default_flags(),
         )
         .unwrap();
-        assert_eq!(cmd["action"], "evaluate");
-        assert_eq!(cmd["script"], "document.querySelector('[src*=\"_next\"]')");
-    }
-
-    #[test]
-    fn test_eval_base64_invalid() {
-        let result = parse_command(&args("eval -b !!!invalid!!!"), &default_flags());
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(matches!(err, ParseError::InvalidValue { .. }));
-        assert!(err.format().contains("Invalid base64"));
+        assert_eq!(cmd["contentType"], "text/csv");
+        assert!(cmd.get("headers").is_none());
     }
 
     #[test]
-    fn test_unknown_command() {
-        let result = parse_command(&args("unknowncommand"), &default_flags());
-        assert!(result.is_err());
+    fn test_mock_add_errors() {
+        let flags = default_flags();
         assert!(matches!(
-            result.unwrap_err(),
-            ParseError::UnknownCommand { .. }
+            parse_command(&args("mock add"), &flags),
+            Err(ParseError::MissingArguments { .. })
         ));
-    }
-
-    #[test]
-    fn test_empty_args() {
-        let result = parse_command(&[], &default_flags());
-        assert!(result.is_err());
         assert!(matches!(
-            result.unwrap_err(),
-            ParseError::MissingArguments { .. }
+            parse_command(&args("mock add **/x --status 42"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("mock add **/x --header nocolon"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("mock add **/x --body-file /nonexistent.json"), &flags),
+            Err(ParseError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_command(&args("mock add **/x --status"), &flags),
+            Err(ParseError::MissingArguments { .. })
         ));
     }
 
-    // === Error message tests ===
-
     #[test]
-    fn test_get_missing_subcommand() {
-        let result = parse_command(&args("get"), &default_flags());
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(matches!(err, ParseError::MissingArguments { .. }));
-        assert!(err.format().contains("get"));
-    }
+    fn test_mock_list_and_remove() {
+        let cmd = parse_command(&args("mock list"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "mock_list");
 
-    #[test]
-    fn test_get_unknown_subcommand() {
-        let result = parse_command(&args("get foo"), &default_flags());
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(matches!(err, ParseError::UnknownSubcommand { .. }));
-        assert!(err.format().contains("foo"));
-        assert!(err.format().contains("text"));
+        let cmd = parse_command(&args("mock remove 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "mock_remove");
+        assert_eq!(cmd["mockId"], 3);
+
+        let cmd = parse_command(&args("mock remove #4"), &default_flags()).unwrap();
+        assert_eq!(cmd["mockId"], 4);
+
+        let result = parse_command(&args("mock remove abc"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
     }
 
+    // === Headers Tests ===
+
     #[test]
-    fn test_get_text_missing_selector() {
-        let result = parse_command(&args("get text"), &default_flags());
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(matches!(err, ParseError::MissingArguments { .. }));
-        assert!(err.format().contains("get text"));
+    fn test_headers_set() {
+        let cmd = parse_command(
+            &args("headers set Authorization Bearer abc123"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["action"], "headers");
+        assert_eq!(cmd["headers"]["Authorization"], "Bearer abc123");
+        assert_eq!(cmd["merge"], true);
     }
 
-    // === Protocol alignment tests ===
-
     #[test]
-    fn test_mouse_wheel() {
-        let cmd = parse_command(&args("mouse wheel 100 50"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "wheel");
-        assert_eq!(cmd["deltaY"], 100);
-        assert_eq!(cmd["deltaX"], 50);
+    fn test_headers_set_missing_value() {
+        let result = parse_command(&args("headers set X-Test"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
     #[test]
-    fn test_set_media() {
-        let cmd = parse_command(&args("set media dark"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "emulatemedia");
-        assert_eq!(cmd["colorScheme"], "dark");
-        assert_eq!(cmd["reducedMotion"], "no-preference");
+    fn test_headers_clear() {
+        let cmd = parse_command(&args("headers clear"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "headers");
+        assert_eq!(cmd["headers"], json!({}));
+        assert!(cmd.get("merge").is_none());
     }
 
     #[test]
-    fn test_set_media_reduced_motion() {
-        let cmd = parse_command(&args("set media light reduced-motion"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "emulatemedia");
-        assert_eq!(cmd["colorScheme"], "light");
-        assert_eq!(cmd["reducedMotion"], "reduce");
+    fn test_headers_unknown_subcommand() {
+        let result = parse_command(&args("headers list"), &default_flags());
+        assert!(matches!(result, Err(ParseError::UnknownSubcommand { .. })));
     }
 
+    // === Block Tests ===
+
     #[test]
-    fn test_find_first_no_value() {
-        let cmd = parse_command(&args("find first a click"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "nth");
-        assert_eq!(cmd["index"], 0);
-        assert!(cmd.get("value").is_none());
+    fn test_block_patterns() {
+        let cmd = parse_command(&args("block *.png *analytics*"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "block");
+        assert_eq!(cmd["patterns"], json!(["*.png", "*analytics*"]));
     }
 
     #[test]
-    fn test_find_first_with_value() {
-        let cmd = parse_command(&args("find first input fill hello"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "nth");
-        assert_eq!(cmd["index"], 0);
-        assert_eq!(cmd["value"], "hello");
+    fn test_block_missing_pattern() {
+        let result = parse_command(&args("block"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
     #[test]
-    fn test_find_nth_no_value() {
-        let cmd = parse_command(&args("find nth 2 a click"), &default_flags()).unwrap();
-        assert_eq!(cmd["action"], "nth");
-        assert_eq!(cmd["index"], 2);
-        assert!(cmd.get("value").is_none());
+    fn test_unblock() {
+        let cmd = parse_command(&args("unblock *.png"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "unblock");
+        assert_eq!(cmd["patterns"], json!(["*.png"]));
+
+        let cmd = parse_command(&args("unblock --all"), &default_flags()).unwrap();
+        assert!(cmd.get("patterns").is_none());
+
+        let result = parse_command(&args("unblock"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
     }
 
     // === Download Tests ===
@@ -2323,6 +7710,49 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_download_wait() {
+        let cmd = parse_command(&args("download wait"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitfordownload");
+        assert!(cmd.get("path").is_none());
+        assert!(cmd.get("timeout").is_none());
+    }
+
+    #[test]
+    fn test_download_wait_with_timeout_and_path() {
+        let cmd = parse_line("download wait --timeout 5000 ./out.zip").unwrap();
+        assert_eq!(cmd["action"], "waitfordownload");
+        assert_eq!(cmd["timeout"], 5000);
+        assert_eq!(cmd["path"], absolute_path("./out.zip"));
+    }
+
+    #[test]
+    fn test_download_wait_unknown_flag() {
+        let result = parse_line("download wait --tiemout 5000 ./out.zip");
+        assert!(matches!(result, Err(ParseError::UnknownFlag { .. })));
+    }
+
+    #[test]
+    fn test_download_wait_invalid_timeout() {
+        let result = parse_line("download wait --timeout soon");
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_download_dir() {
+        let cmd = parse_command(&args("download dir downloads"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "download_dir");
+        let path = cmd["path"].as_str().unwrap();
+        assert!(std::path::Path::new(path).is_absolute());
+        assert!(path.ends_with("downloads"));
+    }
+
+    #[test]
+    fn test_download_dir_missing_path() {
+        let result = parse_command(&args("download dir"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
     // === Wait for Download Tests ===
 
     #[test]
@@ -2336,7 +7766,7 @@ mod tests {
     fn test_wait_download_with_path() {
         let cmd = parse_command(&args("wait --download ./file.pdf"), &default_flags()).unwrap();
         assert_eq!(cmd["action"], "waitfordownload");
-        assert_eq!(cmd["path"], "./file.pdf");
+        assert_eq!(cmd["path"], absolute_path("./file.pdf"));
     }
 
     #[test]
@@ -2355,7 +7785,7 @@ mod tests {
         )
         .unwrap();
         assert_eq!(cmd["action"], "waitfordownload");
-        assert_eq!(cmd["path"], "./file.pdf");
+        assert_eq!(cmd["path"], absolute_path("./file.pdf"));
         assert_eq!(cmd["timeout"], 30000);
     }
 
@@ -2363,7 +7793,7 @@ mod tests {
     fn test_wait_download_short_flag() {
         let cmd = parse_command(&args("wait -d ./file.pdf"), &default_flags()).unwrap();
         assert_eq!(cmd["action"], "waitfordownload");
-        assert_eq!(cmd["path"], "./file.pdf");
+        assert_eq!(cmd["path"], absolute_path("./file.pdf"));
     }
 
     // === Connect (CDP) tests ===
@@ -2463,4 +7893,113 @@ mod tests {
         assert_eq!(cmd["action"], "launch");
         assert_eq!(cmd["cdpPort"], 1);
     }
+
+    // === Timeout Flag Tests ===
+
+    fn timeout_flags(ms: &str) -> Flags {
+        let mut flags = default_flags();
+        flags.timeout = Some(ms.to_string());
+        flags
+    }
+
+    #[test]
+    fn test_timeout_flag_sets_deadline() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let cmd = parse_command(&args("click @e1"), &timeout_flags("45000")).unwrap();
+        let deadline = cmd["deadline"].as_u64().unwrap();
+        assert!(deadline >= now + 45000 && deadline < now + 46000);
+        // click has no timeout of its own
+        assert!(cmd.get("timeout").is_none());
+    }
+
+    #[test]
+    fn test_timeout_flag_fills_command_timeout() {
+        let cmd = parse_command(&args("wait --idle"), &timeout_flags("8000")).unwrap();
+        assert_eq!(cmd["timeout"], 8000);
+        let cmd = parse_command(&args("open example.com"), &timeout_flags("8000")).unwrap();
+        assert_eq!(cmd["timeout"], 8000);
+        // `wait <ms>` keeps its duration
+        let cmd = parse_command(&args("wait 500"), &timeout_flags("8000")).unwrap();
+        assert_eq!(cmd["timeout"], 500);
+        assert!(cmd.get("deadline").is_some());
+    }
+
+    #[test]
+    fn test_retry_flag() {
+        let mut flags = default_flags();
+        flags.retry = Some("3".to_string());
+        let cmd = parse_command(&args("click @e1"), &flags).unwrap();
+        assert_eq!(cmd["retry"], 3);
+        let cmd = parse_command(&args("fill @e2 hello"), &flags).unwrap();
+        assert_eq!(cmd["retry"], 3);
+        // Reads and navigation have nothing to re-query
+        let cmd = parse_command(&args("snapshot"), &flags).unwrap();
+        assert!(cmd.get("retry").is_none());
+        let cmd = parse_command(&args("open example.com"), &flags).unwrap();
+        assert!(cmd.get("retry").is_none());
+
+        flags.retry = Some("0".to_string());
+        let cmd = parse_command(&args("click @e1"), &flags).unwrap();
+        assert!(cmd.get("retry").is_none());
+        for bad in ["-1", "11", "twice"] {
+            flags.retry = Some(bad.to_string());
+            assert!(matches!(
+                parse_command(&args("click @e1"), &flags),
+                Err(ParseError::InvalidValue { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_timings_flag() {
+        let mut flags = default_flags();
+        assert!(parse_command(&args("click @e1"), &flags)
+            .unwrap()
+            .get("timings")
+            .is_none());
+        flags.timings = true;
+        let cmd = parse_command(&args("click @e1"), &flags).unwrap();
+        assert_eq!(cmd["timings"], true);
+    }
+
+    #[test]
+    fn test_timeout_flag_invalid() {
+        let result = parse_command(&args("click @e1"), &timeout_flags("soon"));
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("click @e1"), &timeout_flags("0"));
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+    }
+
+    // === Protocol Tests ===
+
+    #[test]
+    fn test_typed_commands_round_trip() {
+        use agent_browser_client::protocol::Request;
+
+        for line in [
+            "open example.com --wait-until networkidle",
+            "click @e1",
+            "fill #email user@example.com",
+            "press Control+a x2",
+            "snapshot -i -d 3 --format md",
+            "snapshot diff -c",
+            "screenshot --stdout --base64",
+            "eval --arg 1 (a)=>a",
+            "get text @e2",
+            "close",
+        ] {
+            let cmd = parse_command(&args(line), &default_flags()).unwrap();
+            let request: Request =
+                serde_json::from_value(cmd.clone()).unwrap_or_else(|e| panic!("{}: {}", line, e));
+            let mut typed = request.command.into_request(request.id);
+            // `base64` is read by the CLI, not the daemon
+            if let Some(base64) = cmd.get("base64") {
+                typed["base64"] = base64.clone();
+            }
+            assert_eq!(typed, cmd, "{}", line);
+        }
+    }
 }
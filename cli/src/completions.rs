@@ -0,0 +1,261 @@
+//! Shell completion scripts (`agent-browser completions bash|zsh|fish`),
+//! generated from the command table in spec.rs.
+
+use crate::flags::{GLOBAL_FLAGS, GLOBAL_FLAGS_WITH_VALUE, SHARED_FLAGS_WITH_VALUE};
+use crate::spec::COMMANDS;
+
+pub use crate::spec::SHELLS;
+
+/// Flags completed anywhere, including the ones main handles itself
+fn global_flags() -> Vec<&'static str> {
+    let mut flags = GLOBAL_FLAGS.to_vec();
+    flags.extend(["--help", "--version", "--dump-schema"]);
+    flags
+}
+
+/// Global flags followed by a value
+fn value_flags() -> Vec<&'static str> {
+    let mut flags = GLOBAL_FLAGS_WITH_VALUE.to_vec();
+    flags.extend(SHARED_FLAGS_WITH_VALUE);
+    flags
+}
+
+/// The completion script for `shell`
+pub fn script(shell: &str) -> Result<String, String> {
+    match shell {
+        "bash" => Ok(bash()),
+        "zsh" => Ok(zsh()),
+        "fish" => Ok(fish()),
+        _ => Err(format!(
+            "Unknown shell: {} (expected {})",
+            shell,
+            SHELLS.join(", ")
+        )),
+    }
+}
+
+fn command_names() -> String {
+    COMMANDS
+        .iter()
+        .map(|(name, _, _)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What completes after `name`: its subcommands, or every command for `help`
+fn subcommands(name: &str, subcommands: &[&str]) -> String {
+    if name == "help" {
+        command_names()
+    } else {
+        subcommands.join(" ")
+    }
+}
+
+/// Subcommands and flags of one command, space-separated
+fn command_words(name: &str, subcommands: &[&str], flags: &[&str]) -> String {
+    let subcommands = self::subcommands(name, subcommands);
+    let words = std::iter::once(subcommands.as_str()).chain(flags.iter().copied());
+    words
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bash() -> String {
+    let mut cases = String::new();
+    for (name, subcommands, flags) in COMMANDS {
+        let words = command_words(name, subcommands, flags);
+        if !words.is_empty() {
+            cases.push_str(&format!("        {}) words=\"{}\" ;;\n", name, words));
+        }
+    }
+    format!(
+        r#"# agent-browser completion for bash. Load it with:
+#   source <(agent-browser completions bash)
+_agent_browser() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    local global_flags="{global}"
+    local value_flags="{value}"
+    local cmd="" words="" i
+
+    # A flag's value: fall back to file names
+    [[ " $value_flags " == *" $prev "* ]] && return
+
+    # The command is the first word that is neither a flag nor a flag's value
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        if [[ " $value_flags " == *" ${{COMP_WORDS[i]}} "* ]]; then
+            ((i++))
+        elif [[ ${{COMP_WORDS[i]}} != -* ]]; then
+            cmd="${{COMP_WORDS[i]}}"
+            break
+        fi
+    done
+
+    case "$cmd" in
+        "") words="{commands}" ;;
+{cases}    esac
+    if [[ -z $cmd || $cur == -* ]]; then
+        words="$words $global_flags $value_flags"
+    fi
+    COMPREPLY=($(compgen -W "$words" -- "$cur"))
+}}
+complete -o default -F _agent_browser agent-browser
+"#,
+        global = global_flags().join(" "),
+        value = value_flags().join(" "),
+        commands = command_names(),
+        cases = cases,
+    )
+}
+
+fn zsh() -> String {
+    let mut cases = String::new();
+    for (name, subcommands, flags) in COMMANDS {
+        let words = command_words(name, subcommands, flags);
+        if !words.is_empty() {
+            cases.push_str(&format!("        {}) candidates=({}) ;;\n", name, words));
+        }
+    }
+    format!(
+        r#"#compdef agent-browser
+# agent-browser completion for zsh. Load it with:
+#   source <(agent-browser completions zsh)
+# or save it as _agent_browser in a directory on $fpath.
+_agent_browser() {{
+    local -a global_flags value_flags candidates
+    global_flags=({global})
+    value_flags=({value})
+    local cmd="" i
+
+    # A flag's value: complete file names
+    if (( ${{value_flags[(Ie)${{words[CURRENT-1]}}]}} )); then
+        _files
+        return
+    fi
+
+    # The command is the first word that is neither a flag nor a flag's value
+    for ((i = 2; i < CURRENT; i++)); do
+        if (( ${{value_flags[(Ie)${{words[i]}}]}} )); then
+            ((i++))
+        elif [[ ${{words[i]}} != -* ]]; then
+            cmd=${{words[i]}}
+            break
+        fi
+    done
+
+    case $cmd in
+        "") candidates=({commands}) ;;
+{cases}    esac
+    if [[ -z $cmd || $PREFIX == -* ]]; then
+        candidates+=($global_flags $value_flags)
+    fi
+    compadd -a candidates || _files
+}}
+
+if [[ $funcstack[1] == _agent_browser ]]; then
+    _agent_browser "$@"
+else
+    compdef _agent_browser agent-browser
+fi
+"#,
+        global = global_flags().join(" "),
+        value = value_flags().join(" "),
+        commands = command_names(),
+        cases = cases,
+    )
+}
+
+/// `-l name` or `-s n` for one flag
+fn fish_flag(flag: &str) -> String {
+    match flag.strip_prefix("--") {
+        Some(long) => format!("-l {}", long),
+        None => format!("-s {}", flag.trim_start_matches('-')),
+    }
+}
+
+fn fish() -> String {
+    let mut out = format!(
+        r#"# agent-browser completion for fish. Load it with:
+#   agent-browser completions fish | source
+# or save it as ~/.config/fish/completions/agent-browser.fish
+
+# The command is the first word that is neither a flag nor a flag's value
+function __agent_browser_command
+    set -l tokens (commandline -opc)
+    set -e tokens[1]
+    set -l skip 0
+    for token in $tokens
+        if test $skip = 1
+            set skip 0
+        else if contains -- $token {value}
+            set skip 1
+        else if not string match -q -- '-*' $token
+            echo $token
+            return 0
+        end
+    end
+    return 1
+end
+
+function __agent_browser_using
+    set -l cmd (__agent_browser_command); and test "$cmd" = $argv[1]
+end
+
+complete -c agent-browser -f
+complete -c agent-browser -n 'not __agent_browser_command' -a '{commands}'
+"#,
+        value = value_flags().join(" "),
+        commands = command_names(),
+    );
+    for flag in global_flags() {
+        out.push_str(&format!("complete -c agent-browser {}\n", fish_flag(flag)));
+    }
+    for flag in value_flags() {
+        out.push_str(&format!(
+            "complete -c agent-browser {} -r -F\n",
+            fish_flag(flag)
+        ));
+    }
+    for (name, subcommands, flags) in COMMANDS {
+        let using = format!("-n '__agent_browser_using {}'", name);
+        let subcommands = self::subcommands(name, subcommands);
+        if !subcommands.is_empty() {
+            out.push_str(&format!(
+                "complete -c agent-browser {} -a '{}'\n",
+                using, subcommands
+            ));
+        }
+        for flag in *flags {
+            out.push_str(&format!(
+                "complete -c agent-browser {} {}\n",
+                using,
+                fish_flag(flag)
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts() {
+        for shell in SHELLS {
+            let script = script(shell).unwrap();
+            assert!(script.contains("snapshot"));
+            assert!(script.contains("agent-browser"));
+        }
+        assert!(bash().contains("        snapshot) words=\"diff --interactive -i"));
+        assert!(
+            fish().contains("complete -c agent-browser -n '__agent_browser_using snapshot' -s i\n")
+        );
+        assert!(fish().contains("complete -c agent-browser -s S -r -F\n"));
+        assert!(bash().contains("        help) words=\"open back forward"));
+        assert_eq!(
+            script("powershell").unwrap_err(),
+            "Unknown shell: powershell (expected bash, zsh, fish)"
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::flags::Flags;
+
+/// Name of the project-local config file, looked up from the current
+/// directory upwards.
+const PROJECT_CONFIG_NAME: &str = ".agent-browser.toml";
+
+/// Settings that can be set at the top level of a config file or per session
+/// under `[sessions.<name>]`.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct Settings {
+    /// Directory for socket/pid files (AGENT_BROWSER_SOCKET_DIR)
+    pub socket_dir: Option<String>,
+    /// Default action timeout in milliseconds
+    pub timeout: Option<u64>,
+    /// Output as JSON by default
+    pub json: Option<bool>,
+    /// Output format by default: plain, json, jsonl or yaml (same as --format)
+    pub format: Option<String>,
+    /// Run the browser headless (false is the same as --headed)
+    pub headless: Option<bool>,
+    pub viewport: Option<Viewport>,
+    /// Proxy server URL, with optional credentials (same as --proxy)
+    pub proxy: Option<String>,
+    /// Comma-separated hosts that skip the proxy (same as --proxy-bypass)
+    pub proxy_bypass: Option<String>,
+    /// Daemon to use instead of a local one, e.g. `tcp://10.0.0.5:9400` (same as --remote)
+    pub remote: Option<String>,
+    /// Bun executable to start daemons with (same as --runtime)
+    pub runtime: Option<String>,
+    /// Hold the session's lock for every command (same as --lock)
+    pub lock: Option<bool>,
+    /// Retries of an interaction whose element detaches or won't settle (same as --retry)
+    pub retry: Option<u32>,
+    /// Shut the daemon down after this long without commands, e.g. `10m` (same as --idle-timeout)
+    pub idle_timeout: Option<String>,
+    /// Shut the daemon down this long after it started, e.g. `2h` (same as --max-lifetime)
+    pub max_lifetime: Option<String>,
+    /// Least time between commands to the session, in ms (same as --min-delay)
+    pub min_delay: Option<u64>,
+    /// Most commands per second to the session (same as --max-rps)
+    pub max_rps: Option<f64>,
+    /// Log every command the daemon runs (AGENT_BROWSER_AUDIT_LOG)
+    pub audit_log: Option<AuditLog>,
+}
+
+/// `audit_log = true` for `~/.agent-browser/audit/<session>.jsonl`, or the
+/// path of the file to append to
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum AuditLog {
+    Enabled(bool),
+    Path(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct Config {
+    /// Default session name
+    pub session: Option<String>,
+    #[serde(flatten)]
+    pub defaults: Settings,
+    #[serde(default)]
+    pub sessions: HashMap<String, Settings>,
+    /// Command names of your own and what they stand for, e.g.
+    /// `login = "open $1 ++ fill #user $2 ++ click #submit"`
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    /// Settings a project config set but may not, to warn about
+    #[serde(skip)]
+    pub ignored: Vec<String>,
+}
+
+impl Settings {
+    /// Clear what only the user config may set, returning the names cleared.
+    /// A project config comes with whatever repository it is in, so it can't
+    /// choose a program for the CLI to run, a host to send commands to or a
+    /// file for the command log to write.
+    fn clear_user_only(&mut self) -> Vec<&'static str> {
+        let mut cleared = Vec::new();
+        if self.runtime.take().is_some() {
+            cleared.push("runtime");
+        }
+        if self.remote.take().is_some() {
+            cleared.push("remote");
+        }
+        if self.audit_log.take().is_some() {
+            cleared.push("audit_log");
+        }
+        cleared
+    }
+
+    /// Fill unset values from `other`; values already set here win.
+    fn or(self, other: Settings) -> Settings {
+        Settings {
+            socket_dir: self.socket_dir.or(other.socket_dir),
+            timeout: self.timeout.or(other.timeout),
+            json: self.json.or(other.json),
+            format: self.format.or(other.format),
+            headless: self.headless.or(other.headless),
+            viewport: self.viewport.or(other.viewport),
+            proxy: self.proxy.or(other.proxy),
+            proxy_bypass: self.proxy_bypass.or(other.proxy_bypass),
+            remote: self.remote.or(other.remote),
+            runtime: self.runtime.or(other.runtime),
+            lock: self.lock.or(other.lock),
+            retry: self.retry.or(other.retry),
+            idle_timeout: self.idle_timeout.or(other.idle_timeout),
+            max_lifetime: self.max_lifetime.or(other.max_lifetime),
+            min_delay: self.min_delay.or(other.min_delay),
+            max_rps: self.max_rps.or(other.max_rps),
+            audit_log: self.audit_log.or(other.audit_log),
+        }
+    }
+}
+
+impl Config {
+    /// Layer `overrides` (e.g. the project config) on top of this config.
+    fn merge(mut self, overrides: Config) -> Config {
+        for (name, settings) in overrides.sessions {
+            let base = self.sessions.remove(&name).unwrap_or_default();
+            self.sessions.insert(name, settings.or(base));
+        }
+        self.aliases.extend(overrides.aliases);
+        self.ignored.extend(overrides.ignored);
+        Config {
+            session: overrides.session.or(self.session),
+            defaults: overrides.defaults.or(self.defaults),
+            sessions: self.sessions,
+            aliases: self.aliases,
+            ignored: self.ignored,
+        }
+    }
+
+    /// Drop the settings only the user config may set from this project
+    /// config at `path`, noting them in `ignored`
+    fn restrict_to_project(&mut self, path: &Path) {
+        let mut cleared = self.defaults.clear_user_only();
+        for settings in self.sessions.values_mut() {
+            cleared.extend(settings.clear_user_only());
+        }
+        cleared.sort_unstable();
+        cleared.dedup();
+        if !cleared.is_empty() {
+            self.ignored.push(format!(
+                "Ignoring {} in {}: set it in the user config, the environment or a flag",
+                cleared.join(", "),
+                path.display()
+            ));
+        }
+    }
+
+    /// Effective settings for a session: `[sessions.<name>]` over top-level values.
+    pub fn settings_for(&self, session: &str) -> Settings {
+        self.sessions
+            .get(session)
+            .cloned()
+            .unwrap_or_default()
+            .or(self.defaults.clone())
+    }
+
+    /// Apply config defaults to flags that were not set on the command line or
+    /// through the environment. Values the daemon needs are exported as
+    /// environment variables so a newly spawned daemon inherits them.
+    pub fn apply(&self, flags: &mut Flags) {
+        if !flags.cli_session && env::var("AGENT_BROWSER_SESSION").is_err() {
+            if let Some(ref session) = self.session {
+                flags.session = session.clone();
+            }
+        }
+
+        let settings = self.settings_for(&flags.session);
+
+        // --json on the command line wins over a configured format
+        if flags.format.is_none() && !flags.json {
+            flags.format = settings.format;
+        }
+        if settings.json == Some(true) {
+            flags.json = true;
+        }
+        if settings.headless == Some(false) && !flags.headless {
+            flags.headed = true;
+        }
+        if flags.proxy.is_none() {
+            flags.proxy = settings.proxy;
+        }
+        if flags.proxy_bypass.is_none() {
+            flags.proxy_bypass = settings.proxy_bypass;
+        }
+        if flags.lock.is_none() {
+            flags.lock = settings.lock;
+        }
+        if flags.retry.is_none() {
+            flags.retry = settings.retry.map(|n| n.to_string());
+        }
+        if flags.idle_timeout.is_none() {
+            flags.idle_timeout = settings.idle_timeout;
+        }
+        if flags.max_lifetime.is_none() {
+            flags.max_lifetime = settings.max_lifetime;
+        }
+        if flags.min_delay.is_none() {
+            flags.min_delay = settings.min_delay.map(|ms| ms.to_string());
+        }
+        if flags.max_rps.is_none() {
+            flags.max_rps = settings.max_rps.map(|n| n.to_string());
+        }
+        if let Some(dir) = settings.socket_dir {
+            set_env_default("AGENT_BROWSER_SOCKET_DIR", &expand_home(&dir));
+        }
+        if let Some(remote) = settings.remote {
+            set_env_default("AGENT_BROWSER_REMOTE", &remote);
+        }
+        if let Some(runtime) = settings.runtime {
+            set_env_default("AGENT_BROWSER_RUNTIME", &expand_home(&runtime));
+        }
+        match settings.audit_log {
+            Some(AuditLog::Enabled(true)) => set_env_default("AGENT_BROWSER_AUDIT_LOG", "1"),
+            Some(AuditLog::Path(path)) => {
+                set_env_default("AGENT_BROWSER_AUDIT_LOG", &expand_home(&path))
+            }
+            Some(AuditLog::Enabled(false)) | None => {}
+        }
+        if let Some(timeout) = settings.timeout {
+            set_env_default("AGENT_BROWSER_DEFAULT_TIMEOUT", &timeout.to_string());
+        }
+        if let Some(vp) = settings.viewport {
+            set_env_default(
+                "AGENT_BROWSER_VIEWPORT",
+                &format!("{}x{}", vp.width, vp.height),
+            );
+        }
+    }
+}
+
+fn set_env_default(key: &str, value: &str) {
+    if env::var(key).map(|v| v.is_empty()).unwrap_or(true) {
+        env::set_var(key, value);
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// Path of the user config file: $XDG_CONFIG_HOME/agent-browser/config.toml,
+/// falling back to ~/.config/agent-browser/config.toml.
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        if !dir.is_empty() {
+            return Some(PathBuf::from(dir).join("agent-browser").join("config.toml"));
+        }
+    }
+    dirs::home_dir().map(|h| h.join(".config").join("agent-browser").join("config.toml"))
+}
+
+/// Find the nearest `.agent-browser.toml` in `start` or one of its parents.
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_NAME))
+        .find(|p| p.is_file())
+}
+
+pub fn parse_config(source: &str) -> Result<Config, String> {
+    toml::from_str(source).map_err(|e| e.to_string())
+}
+
+fn read_config(path: &Path) -> Result<Config, String> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+    parse_config(&source).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+}
+
+/// Load the user config and the project config (which takes precedence).
+/// Missing files are not an error; malformed ones are.
+pub fn load() -> Result<Config, String> {
+    let mut config = Config::default();
+
+    if let Some(path) = user_config_path().filter(|p| p.is_file()) {
+        config = config.merge(read_config(&path)?);
+    }
+
+    if let Some(path) = env::current_dir()
+        .ok()
+        .and_then(|cwd| find_project_config(&cwd))
+    {
+        let mut project = read_config(&path)?;
+        project.restrict_to_project(&path);
+        config = config.merge(project);
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_full() {
+        let config = parse_config(
+            r#"
+session = "work"
+json = true
+timeout = 15000
+viewport = { width = 1440, height = 900 }
+idle_timeout = "30m"
+max_rps = 2.5
+
+[sessions.debug]
+headless = false
+timeout = 60000
+idle_timeout = "off"
+format = "yaml"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.session.as_deref(), Some("work"));
+        assert_eq!(config.defaults.json, Some(true));
+        assert_eq!(
+            config.defaults.viewport,
+            Some(Viewport {
+                width: 1440,
+                height: 900
+            })
+        );
+
+        let debug = config.settings_for("debug");
+        assert_eq!(debug.headless, Some(false));
+        assert_eq!(debug.timeout, Some(60000));
+        assert_eq!(debug.json, Some(true));
+        assert_eq!(debug.idle_timeout.as_deref(), Some("off"));
+        assert_eq!(debug.format.as_deref(), Some("yaml"));
+
+        let other = config.settings_for("other");
+        assert_eq!(other.timeout, Some(15000));
+        assert_eq!(other.headless, None);
+        assert_eq!(other.idle_timeout.as_deref(), Some("30m"));
+        assert_eq!(other.max_rps, Some(2.5));
+    }
+
+    #[test]
+    fn test_apply_proxy_keeps_flag_value() {
+        let config = parse_config(
+            "proxy = \"http://config:8080\"\nproxy_bypass = \"localhost\"\n[sessions.scrape]\nproxy = \"socks5://127.0.0.1:1080\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.settings_for("scrape").proxy.as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+        assert_eq!(
+            config.settings_for("scrape").proxy_bypass.as_deref(),
+            Some("localhost")
+        );
+
+        let args = ["--proxy".to_string(), "http://cli:3128".to_string()];
+        let mut flags = crate::flags::parse_flags(&args);
+        config.apply(&mut flags);
+        assert_eq!(flags.proxy.as_deref(), Some("http://cli:3128"));
+        assert_eq!(flags.proxy_bypass.as_deref(), Some("localhost"));
+    }
+
+    #[test]
+    fn test_apply_retry() {
+        let config = parse_config("retry = 2\n[sessions.flaky]\nretry = 5\n").unwrap();
+        assert_eq!(config.settings_for("flaky").retry, Some(5));
+
+        let mut flags = crate::flags::parse_flags(&[]);
+        flags.retry = None;
+        config.apply(&mut flags);
+        assert_eq!(flags.retry.as_deref(), Some("2"));
+
+        let args = ["--retry".to_string(), "0".to_string()];
+        let mut flags = crate::flags::parse_flags(&args);
+        config.apply(&mut flags);
+        assert_eq!(flags.retry.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn test_parse_config_remote_per_session() {
+        let config = parse_config("[sessions.vm]\nremote = \"tcp://10.0.0.5:9400\"\n").unwrap();
+        assert_eq!(
+            config.settings_for("vm").remote.as_deref(),
+            Some("tcp://10.0.0.5:9400")
+        );
+        assert_eq!(config.settings_for("default").remote, None);
+    }
+
+    #[test]
+    fn test_parse_config_audit_log() {
+        let config =
+            parse_config("audit_log = true\n[sessions.ci]\naudit_log = \"~/logs/ci.jsonl\"\n")
+                .unwrap();
+        assert_eq!(
+            config.settings_for("default").audit_log,
+            Some(AuditLog::Enabled(true))
+        );
+        assert_eq!(
+            config.settings_for("ci").audit_log,
+            Some(AuditLog::Path("~/logs/ci.jsonl".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_config_aliases() {
+        let user = parse_config(
+            "[aliases]\nlogin = \"open $1 ++ fill #user $2 ++ click #submit\"\nhome = \"open a.test\"\n",
+        )
+        .unwrap();
+        let project = parse_config("[aliases]\nhome = \"open b.test\"\n").unwrap();
+        let config = user.merge(project);
+        assert_eq!(
+            config.aliases.get("login").map(String::as_str),
+            Some("open $1 ++ fill #user $2 ++ click #submit")
+        );
+        assert_eq!(
+            config.aliases.get("home").map(String::as_str),
+            Some("open b.test")
+        );
+    }
+
+    #[test]
+    fn test_parse_config_empty() {
+        assert_eq!(parse_config("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_parse_config_invalid() {
+        assert!(parse_config("timeout = \"soon\"").is_err());
+    }
+
+    #[test]
+    fn test_merge_project_overrides_user() {
+        let user = parse_config(
+            "session = \"a\"\njson = true\ntimeout = 1000\n[sessions.x]\nheadless = false\n",
+        )
+        .unwrap();
+        let project = parse_config("timeout = 2000\n[sessions.x]\ntimeout = 3000\n").unwrap();
+        let config = user.merge(project);
+
+        assert_eq!(config.session.as_deref(), Some("a"));
+        assert_eq!(config.defaults.json, Some(true));
+        assert_eq!(config.defaults.timeout, Some(2000));
+        let x = config.settings_for("x");
+        assert_eq!(x.timeout, Some(3000));
+        assert_eq!(x.headless, Some(false));
+    }
+
+    #[test]
+    fn test_project_config_cannot_set_runtime() {
+        let user = parse_config("runtime = \"~/.bun/bin/bun\"\n").unwrap();
+        let mut project = parse_config(
+            "runtime = \"./evil.sh\"\ntimeout = 2000\n[sessions.x]\nruntime = \"./x.sh\"\n",
+        )
+        .unwrap();
+        project.restrict_to_project(Path::new("/repo/.agent-browser.toml"));
+        let config = user.merge(project);
+
+        assert_eq!(config.defaults.runtime.as_deref(), Some("~/.bun/bin/bun"));
+        assert_eq!(
+            config.settings_for("x").runtime.as_deref(),
+            Some("~/.bun/bin/bun")
+        );
+        assert_eq!(config.defaults.timeout, Some(2000));
+        assert_eq!(
+            config.ignored,
+            vec![
+                "Ignoring runtime in /repo/.agent-browser.toml: set it in the user config, \
+                 the environment or a flag"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_config_cannot_set_remote() {
+        let mut project = parse_config(
+            "[sessions.vm]\nremote = \"tcp://attacker.test:9400\"\nruntime = \"./x.sh\"\n",
+        )
+        .unwrap();
+        project.restrict_to_project(Path::new("/repo/.agent-browser.toml"));
+        let config = parse_config("[sessions.vm]\nremote = \"tcp://10.0.0.5:9400\"\n")
+            .unwrap()
+            .merge(project);
+
+        assert_eq!(
+            config.settings_for("vm").remote.as_deref(),
+            Some("tcp://10.0.0.5:9400")
+        );
+        assert!(config.ignored[0].starts_with("Ignoring remote, runtime in "));
+    }
+
+    #[test]
+    fn test_project_config_cannot_set_audit_log() {
+        let mut project = parse_config("audit_log = \"~/.bashrc\"\n").unwrap();
+        project.restrict_to_project(Path::new("/repo/.agent-browser.toml"));
+        let config = Config::default().merge(project);
+
+        assert_eq!(config.settings_for("default").audit_log, None);
+        assert!(config.ignored[0].starts_with("Ignoring audit_log in "));
+    }
+
+    #[test]
+    fn test_find_project_config_walks_up() {
+        let root = env::temp_dir().join(format!("ab-config-test-{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(PROJECT_CONFIG_NAME), "json = true\n").unwrap();
+
+        assert_eq!(
+            find_project_config(&nested),
+            Some(root.join(PROJECT_CONFIG_NAME))
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
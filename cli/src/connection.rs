@@ -1,125 +1,116 @@
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use std::env;
 use std::fs;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::net::TcpStream;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+#[cfg(windows)]
+use std::net::TcpStream;
 #[cfg(unix)]
 use std::os::unix::net::UnixStream;
+#[cfg(windows)]
+use windows_sys::Win32::Foundation::CloseHandle;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
 
-#[derive(Serialize)]
-#[allow(dead_code)]
-pub struct Request {
-    pub id: String,
-    pub action: String,
-    #[serde(flatten)]
-    pub extra: Value,
-}
+#[cfg(unix)]
+use agent_browser_client::connection::get_socket_path;
+use agent_browser_client::connection::get_token_path;
+#[cfg(windows)]
+use agent_browser_client::connection::{get_port_for_session, get_port_path};
+use agent_browser_client::protocol::{HelloData, PROTOCOL_VERSION};
+use agent_browser_client::Client;
+pub use agent_browser_client::{
+    get_socket_dir, remote_address, DaemonConnection, Error, ErrorCode, Response, Timings,
+};
+use serde_json::Value;
 
-#[derive(Deserialize, Serialize, Default)]
-pub struct Response {
-    pub success: bool,
-    pub data: Option<Value>,
-    pub error: Option<String>,
-}
+use crate::color;
+use crate::commands::secret_value;
+use crate::embedded;
+use crate::output;
+use crate::runtime;
 
-#[allow(dead_code)]
-pub enum Connection {
-    #[cfg(unix)]
-    Unix(UnixStream),
-    Tcp(TcpStream),
+/// Send a command to the session's daemon and wait for its response
+pub fn send_command(cmd: Value, session: &str) -> Result<Response, Error> {
+    send_command_timed(cmd, session, &mut |_, _| {}).map(|(resp, _)| resp)
 }
 
-impl Read for Connection {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        match self {
-            #[cfg(unix)]
-            Connection::Unix(s) => s.read(buf),
-            Connection::Tcp(s) => s.read(buf),
-        }
-    }
+/// [`send_command`], reporting how much of a large response has arrived as
+/// `progress(received, total)` and returning how long connecting to the
+/// daemon took (for `--timings`). With `-v` the request, the response and
+/// the round trip are logged to stderr, secrets masked.
+pub fn send_command_timed(
+    cmd: Value,
+    session: &str,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<(Response, Duration), Error> {
+    if !output::is_verbose() {
+        return agent_browser_client::send_command_timed(cmd, session, progress);
+    }
+    let secret = secret_value(&cmd).map(str::to_string);
+    let mask = |text: String| match &secret {
+        Some(secret) => text.replace(secret.as_str(), "********"),
+        None => text,
+    };
+    eprintln!("{} {}", color::dim("→"), mask(cmd.to_string()));
+    let sent = Instant::now();
+    let result = agent_browser_client::send_command_timed(cmd, session, progress);
+    let elapsed = color::dim(&format!("({} ms)", sent.elapsed().as_millis()));
+    match &result {
+        Ok((resp, _)) => {
+            let resp = serde_json::to_string(resp).unwrap_or_default();
+            eprintln!("{} {} {}", color::dim("←"), mask(resp), elapsed);
+        }
+        Err(e) => eprintln!("{} {} {}", color::dim("←"), mask(e.to_string()), elapsed),
+    }
+    result
 }
 
-impl Write for Connection {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        match self {
-            #[cfg(unix)]
-            Connection::Unix(s) => s.write(buf),
-            Connection::Tcp(s) => s.write(buf),
-        }
-    }
-
-    fn flush(&mut self) -> std::io::Result<()> {
-        match self {
-            #[cfg(unix)]
-            Connection::Unix(s) => s.flush(),
-            Connection::Tcp(s) => s.flush(),
-        }
-    }
+fn get_pid_path(session: &str) -> PathBuf {
+    get_socket_dir().join(format!("{}.pid", session))
 }
 
-impl Connection {
-    pub fn set_read_timeout(&self, dur: Option<Duration>) -> std::io::Result<()> {
-        match self {
-            #[cfg(unix)]
-            Connection::Unix(s) => s.set_read_timeout(dur),
-            Connection::Tcp(s) => s.set_read_timeout(dur),
-        }
-    }
-
-    pub fn set_write_timeout(&self, dur: Option<Duration>) -> std::io::Result<()> {
-        match self {
-            #[cfg(unix)]
-            Connection::Unix(s) => s.set_write_timeout(dur),
-            Connection::Tcp(s) => s.set_write_timeout(dur),
-        }
-    }
+/// Where a daemon this CLI starts writes its output. It's left behind when
+/// the daemon dies, for `--show-daemon-log`, and started afresh by the next.
+pub fn get_log_path(session: &str) -> PathBuf {
+    get_socket_dir().join(format!("{}.log", session))
 }
 
-/// Get the base directory for socket/pid files.
-/// Priority: AGENT_BROWSER_SOCKET_DIR > XDG_RUNTIME_DIR > ~/.agent-browser > tmpdir
-pub fn get_socket_dir() -> PathBuf {
-    // 1. Explicit override (ignore empty string)
-    if let Ok(dir) = env::var("AGENT_BROWSER_SOCKET_DIR") {
-        if !dir.is_empty() {
-            return PathBuf::from(dir);
-        }
-    }
-
-    // 2. XDG_RUNTIME_DIR (Linux standard, ignore empty string)
-    if let Ok(runtime_dir) = env::var("XDG_RUNTIME_DIR") {
-        if !runtime_dir.is_empty() {
-            return PathBuf::from(runtime_dir).join("agent-browser");
-        }
-    }
-
-    // 3. Home directory fallback (like Docker Desktop's ~/.docker/run/)
-    if let Some(home) = dirs::home_dir() {
-        return home.join(".agent-browser");
+/// Stdout and stderr for a daemon starting now: its log, or nowhere if the
+/// log can't be opened
+fn daemon_output(session: &str) -> (Stdio, Stdio) {
+    let log = fs::File::create(get_log_path(session)).and_then(|f| Ok((f.try_clone()?, f)));
+    match log {
+        Ok((stdout, stderr)) => (stdout.into(), stderr.into()),
+        Err(_) => (Stdio::null(), Stdio::null()),
     }
-
-    // 4. Last resort: temp dir
-    env::temp_dir().join("agent-browser")
 }
 
-#[cfg(unix)]
-fn get_socket_path(session: &str) -> PathBuf {
-    get_socket_dir().join(format!("{}.sock", session))
+/// The last `n` lines of the session's daemon log
+pub fn daemon_log_tail(session: &str, n: usize) -> Vec<String> {
+    let log = fs::read_to_string(get_log_path(session)).unwrap_or_default();
+    last_lines(&log, n)
 }
 
-fn get_pid_path(session: &str) -> PathBuf {
-    get_socket_dir().join(format!("{}.pid", session))
+fn last_lines(text: &str, n: usize) -> Vec<String> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(n)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
 }
 
-/// Clean up stale socket and PID files for a session
-fn cleanup_stale_files(session: &str) {
+/// Clean up stale socket, PID and token files for a session
+pub fn cleanup_stale_files(session: &str) {
     let pid_path = get_pid_path(session);
     let _ = fs::remove_file(&pid_path);
+    let _ = fs::remove_file(get_token_path(session));
 
     #[cfg(unix)]
     {
@@ -134,25 +125,93 @@ fn cleanup_stale_files(session: &str) {
     }
 }
 
-#[cfg(windows)]
-fn get_port_path(session: &str) -> PathBuf {
-    get_socket_dir().join(format!("{}.port", session))
+/// The pid of the session's daemon, if it's running
+pub fn daemon_pid(session: &str) -> Option<u32> {
+    fs::read_to_string(get_pid_path(session))
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .filter(|&pid| process_alive(pid))
 }
 
-#[cfg(windows)]
-fn get_port_for_session(session: &str) -> Result<u16, String> {
-    let port_file = get_port_path(session);
-    let port_str =
-        fs::read_to_string(&port_file).map_err(|e| format!("Failed to read port file: {}", e))?;
-
-    Ok(port_str
-        .trim()
-        .parse()
-        .map_err(|e| format!("Invalid port in file: {}", e))?)
+/// After `close`: wait up to `timeout` for the daemon `pid` to exit, then
+/// remove any socket, PID or token file it didn't. Returns whether it exited;
+/// one that's still running keeps its files.
+pub fn wait_for_shutdown(session: &str, pid: u32, timeout: Duration) -> bool {
+    let started = Instant::now();
+    while process_alive(pid) {
+        if started.elapsed() >= timeout {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    cleanup_stale_files(session);
+    true
+}
+
+/// Sessions with a running daemon, found from the pid files in the socket directory
+pub fn live_sessions() -> Vec<String> {
+    let mut sessions: Vec<String> = pid_files()
+        .into_iter()
+        .filter(|(_, pid)| pid.is_some_and(process_alive))
+        .map(|(session, _)| session)
+        .collect();
+    sessions.sort();
+    sessions
+}
+
+/// Sessions whose pid file names a process that is gone (or no process at
+/// all), left behind by a daemon that crashed or was killed
+pub fn stale_sessions() -> Vec<String> {
+    let mut sessions: Vec<String> = pid_files()
+        .into_iter()
+        .filter(|(_, pid)| !pid.is_some_and(process_alive))
+        .map(|(session, _)| session)
+        .collect();
+    sessions.sort();
+    sessions
+}
+
+/// Every `<session>.pid` in the socket directory with the pid it holds
+fn pid_files() -> Vec<(String, Option<u32>)> {
+    let socket_dir = get_socket_dir();
+    let mut sessions = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(&socket_dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(session_name) = name.strip_suffix(".pid") {
+                if !session_name.is_empty() {
+                    let pid = fs::read_to_string(entry.path())
+                        .ok()
+                        .and_then(|pid| pid.trim().parse::<u32>().ok());
+                    sessions.push((session_name.to_string(), pid));
+                }
+            }
+        }
+    }
+    sessions
 }
 
+fn process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(pid as i32, 0) == 0
+    }
+    #[cfg(windows)]
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle != 0 {
+            CloseHandle(handle);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Whether a local daemon process for `session` exists (it may not be answering yet)
 #[cfg(unix)]
-fn is_daemon_running(session: &str) -> bool {
+pub fn is_daemon_running(session: &str) -> bool {
     let pid_path = get_pid_path(session);
     if !pid_path.exists() {
         return false;
@@ -168,7 +227,7 @@ fn is_daemon_running(session: &str) -> bool {
 }
 
 #[cfg(windows)]
-fn is_daemon_running(session: &str) -> bool {
+pub fn is_daemon_running(session: &str) -> bool {
     let pid_path = get_pid_path(session);
     if !pid_path.exists() {
         return false;
@@ -198,6 +257,115 @@ fn daemon_ready(session: &str) -> bool {
     }
 }
 
+/// How many times a command is retried on a restarted daemon when the one it
+/// was sent to is gone (`AGENT_BROWSER_MAX_RESTARTS`, 0 to never restart)
+pub fn max_restarts() -> u32 {
+    env::var("AGENT_BROWSER_MAX_RESTARTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_RESTARTS)
+}
+
+const DEFAULT_MAX_RESTARTS: u32 = 2;
+
+/// Wait before restart `attempt` (from 0): 250ms, doubling each time, at most 8s
+pub fn restart_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(250 << attempt.min(5))
+}
+
+/// Whether the command never reached the daemon: it could not be connected
+/// to or written to. Only then is it safe to start another daemon and send
+/// the command again. Remote daemons are never restarted from here.
+pub fn never_delivered(e: &Error) -> bool {
+    if matches!(remote_address(), Ok(Some(_))) {
+        return false;
+    }
+    match e {
+        Error::Connect(_) | Error::Send(_) => true,
+        Error::Unresponsive { last, .. } => never_delivered(last),
+        _ => false,
+    }
+}
+
+/// Where commands for `session` are sent: the remote daemon, or the
+/// session's socket (the file holding its port on Windows)
+pub fn target(session: &str) -> Result<String, Error> {
+    if let Some(address) = remote_address()? {
+        return Ok(format!("tcp://{}", address));
+    }
+    #[cfg(unix)]
+    let path = get_socket_path(session);
+    #[cfg(windows)]
+    let path = get_port_path(session);
+    Ok(path.display().to_string())
+}
+
+/// Create `dir` (and its parents) if missing, readable only by this user
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    if dir.exists() {
+        return Ok(());
+    }
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
+}
+
+/// The daemon sources of an AGENT_BROWSER_HOME checkout, an npm install, or a
+/// development build run from the repository
+fn find_daemon_on_disk() -> Result<PathBuf, String> {
+    let exe_path = env::current_exe().map_err(|e| e.to_string())?;
+    // Canonicalize to resolve symlinks (e.g., npm global bin symlink -> actual binary)
+    let exe_path = exe_path.canonicalize().unwrap_or(exe_path);
+    let exe_dir = exe_path.parent().unwrap();
+
+    let mut daemon_paths = vec![
+        exe_dir.join("daemon.ts"),
+        exe_dir.join("../src/daemon.ts"),
+        PathBuf::from("src/daemon.ts"),
+    ];
+
+    // Check AGENT_BROWSER_HOME environment variable
+    if let Ok(home) = env::var("AGENT_BROWSER_HOME") {
+        let home_path = PathBuf::from(&home);
+        daemon_paths.insert(0, home_path.join("src/daemon.ts"));
+        daemon_paths.insert(1, home_path.join("daemon.ts"));
+    }
+
+    daemon_paths.into_iter().find(|p| p.exists()).ok_or_else(|| {
+        "Daemon not found. Set AGENT_BROWSER_HOME environment variable or run from project directory."
+            .to_string()
+    })
+}
+
+/// The daemon's entry point, and whether it is the copy compiled into this
+/// binary. That copy is used unless AGENT_BROWSER_HOME points at another one.
+pub fn daemon_script() -> Result<(PathBuf, bool), String> {
+    let embedded = match env::var_os("AGENT_BROWSER_HOME") {
+        Some(_) => None,
+        None => embedded::daemon_path()?,
+    };
+    match embedded {
+        Some(path) => Ok((path, true)),
+        None => Ok((find_daemon_on_disk()?, false)),
+    }
+}
+
+/// Bun running the daemon's entry point, see [`daemon_script`]
+pub fn daemon_command() -> Result<Command, String> {
+    let (daemon_path, embedded) = daemon_script()?;
+    let mut cmd = Command::new(runtime::resolve()?);
+    cmd.arg(daemon_path);
+    if embedded {
+        cmd.env("AGENT_BROWSER_DAEMON_VERSION", embedded::DAEMON_VERSION);
+    }
+    Ok(cmd)
+}
+
 /// Result of ensure_daemon indicating whether a new daemon was started
 pub struct DaemonResult {
     /// True if we connected to an existing daemon, false if we started a new one
@@ -207,6 +375,7 @@ pub struct DaemonResult {
 pub fn ensure_daemon(
     session: &str,
     headed: bool,
+    browser: Option<&str>,
     executable_path: Option<&str>,
     extensions: &[String],
     args: Option<&str>,
@@ -220,6 +389,13 @@ pub fn ensure_daemon(
     provider: Option<&str>,
     device: Option<&str>,
 ) -> Result<DaemonResult, String> {
+    // A remote daemon is started on its own machine, so there is nothing to spawn
+    if remote_address().map_err(|e| e.to_string())?.is_some() {
+        return Ok(DaemonResult {
+            already_running: true,
+        });
+    }
+
     // Check if daemon is running AND responsive
     if is_daemon_running(session) && daemon_ready(session) {
         // Double-check it's actually responsive by waiting and checking again
@@ -236,12 +412,10 @@ pub fn ensure_daemon(
     // Clean up any stale socket/pid files before starting fresh
     cleanup_stale_files(session);
 
-    // Ensure socket directory exists
+    // Ensure socket directory exists, readable only by this user
     let socket_dir = get_socket_dir();
-    if !socket_dir.exists() {
-        fs::create_dir_all(&socket_dir)
-            .map_err(|e| format!("Failed to create socket directory: {}", e))?;
-    }
+    create_private_dir(&socket_dir)
+        .map_err(|e| format!("Failed to create socket directory: {}", e))?;
 
     // Pre-flight check: Validate socket path length (Unix limit is 104 bytes including null terminator)
     #[cfg(unix)]
@@ -251,7 +425,8 @@ pub fn ensure_daemon(
         if path_len > 103 {
             return Err(format!(
                 "Session name '{}' is too long. Socket path would be {} bytes (max 103).\n\
-                 Use a shorter session name or set AGENT_BROWSER_SOCKET_DIR to a shorter path.",
+                 Use a shorter session name, set AGENT_BROWSER_SOCKET_DIR to a shorter path \
+                 or pass --socket.",
                 session, path_len
             ));
         }
@@ -274,43 +449,24 @@ pub fn ensure_daemon(
         }
     }
 
-    let exe_path = env::current_exe().map_err(|e| e.to_string())?;
-    // Canonicalize to resolve symlinks (e.g., npm global bin symlink -> actual binary)
-    let exe_path = exe_path.canonicalize().unwrap_or(exe_path);
-    let exe_dir = exe_path.parent().unwrap();
-
-    let mut daemon_paths = vec![
-        exe_dir.join("daemon.ts"),
-        exe_dir.join("../src/daemon.ts"),
-        PathBuf::from("src/daemon.ts"),
-    ];
-
-    // Check AGENT_BROWSER_HOME environment variable
-    if let Ok(home) = env::var("AGENT_BROWSER_HOME") {
-        let home_path = PathBuf::from(&home);
-        daemon_paths.insert(0, home_path.join("src/daemon.ts"));
-        daemon_paths.insert(1, home_path.join("daemon.ts"));
-    }
-
-    let daemon_path = daemon_paths
-        .iter()
-        .find(|p| p.exists())
-        .ok_or("Daemon not found. Set AGENT_BROWSER_HOME environment variable or run from project directory.")?;
+    let mut cmd = daemon_command()?;
 
     // Spawn daemon as a fully detached background process
     #[cfg(unix)]
     {
         use std::os::unix::process::CommandExt;
 
-        let mut cmd = Command::new("bun");
-        cmd.arg(daemon_path)
-            .env("AGENT_BROWSER_DAEMON", "1")
+        cmd.env("AGENT_BROWSER_DAEMON", "1")
             .env("AGENT_BROWSER_SESSION", session);
 
         if headed {
             cmd.env("AGENT_BROWSER_HEADED", "1");
         }
 
+        if let Some(b) = browser {
+            cmd.env("AGENT_BROWSER_BROWSER", b);
+        }
+
         if let Some(path) = executable_path {
             cmd.env("AGENT_BROWSER_EXECUTABLE_PATH", path);
         }
@@ -368,9 +524,10 @@ pub fn ensure_daemon(
             });
         }
 
+        let (stdout, stderr) = daemon_output(session);
         cmd.stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr)
             .spawn()
             .map_err(|e| format!("Failed to start daemon: {}", e))?;
     }
@@ -379,15 +536,17 @@ pub fn ensure_daemon(
     {
         use std::os::windows::process::CommandExt;
 
-        let mut cmd = Command::new("bun");
-        cmd.arg(daemon_path)
-            .env("AGENT_BROWSER_DAEMON", "1")
+        cmd.env("AGENT_BROWSER_DAEMON", "1")
             .env("AGENT_BROWSER_SESSION", session);
 
         if headed {
             cmd.env("AGENT_BROWSER_HEADED", "1");
         }
 
+        if let Some(b) = browser {
+            cmd.env("AGENT_BROWSER_BROWSER", b);
+        }
+
         if let Some(path) = executable_path {
             cmd.env("AGENT_BROWSER_EXECUTABLE_PATH", path);
         }
@@ -440,10 +599,11 @@ pub fn ensure_daemon(
         const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
         const DETACHED_PROCESS: u32 = 0x00000008;
 
+        let (stdout, stderr) = daemon_output(session);
         cmd.creation_flags(CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS)
             .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr)
             .spawn()
             .map_err(|e| format!("Failed to start daemon: {}", e))?;
     }
@@ -463,286 +623,116 @@ pub fn ensure_daemon(
     ))
 }
 
-fn connect(session: &str) -> Result<Connection, String> {
-    #[cfg(unix)]
-    {
-        let socket_path = get_socket_path(session);
-        UnixStream::connect(&socket_path)
-            .map(Connection::Unix)
-            .map_err(|e| format!("Failed to connect: {}", e))
-    }
-    #[cfg(windows)]
-    {
-        let port = get_port_for_session(session)?;
-        TcpStream::connect(format!("127.0.0.1:{}", port))
-            .map(Connection::Tcp)
-            .map_err(|e| format!("Failed to connect: {}", e))
-    }
+/// How a running daemon answered `hello`
+pub enum Handshake {
+    /// Same protocol version as this CLI
+    Compatible(HelloData),
+    /// The daemon predates the handshake and rejected it
+    Legacy,
+    /// The daemon speaks another protocol version
+    Mismatch(HelloData),
 }
 
-pub fn send_command(cmd: Value, session: &str) -> Result<Response, String> {
-    // Retry logic for transient errors (EAGAIN/EWOULDBLOCK/connection issues)
-    const MAX_RETRIES: u32 = 5;
-    const RETRY_DELAY_MS: u64 = 200;
-
-    let mut last_error = String::new();
+/// Ask the session's daemon for its protocol version and actions
+pub fn handshake(session: &str) -> Result<Handshake, Error> {
+    let mut client = Client::connect(session)?;
+    match client.hello() {
+        Ok(hello) if hello.protocol_version == PROTOCOL_VERSION => Ok(Handshake::Compatible(hello)),
+        Ok(hello) => Ok(Handshake::Mismatch(hello)),
+        Err(Error::Command(_)) => Ok(Handshake::Legacy),
+        Err(e) => Err(e),
+    }
+}
 
-    for attempt in 0..MAX_RETRIES {
-        if attempt > 0 {
-            thread::sleep(Duration::from_millis(RETRY_DELAY_MS * (attempt as u64)));
+impl Handshake {
+    /// Whether `action` can be sent to this daemon. Returns a warning to show
+    /// the user, or an error when the daemon would not understand it.
+    /// `close` always goes through, so a stale daemon can be replaced.
+    pub fn check(&self, action: Option<&str>) -> Result<Option<String>, String> {
+        const RESTART: &str = "Run 'agent-browser close' to restart it.";
+        if action == Some("close") {
+            return Ok(None);
         }
-
-        match send_command_once(&cmd, session) {
-            Ok(response) => return Ok(response),
-            Err(e) => {
-                if is_transient_error(&e) {
-                    last_error = e;
-                    continue;
-                }
-                // Non-transient error, fail immediately
-                return Err(e);
-            }
+        match self {
+            Handshake::Legacy => Ok(Some(format!(
+                "Daemon is from an older agent-browser without protocol versioning; some commands may fail. {}",
+                RESTART
+            ))),
+            Handshake::Mismatch(hello) => Err(format!(
+                "Daemon speaks protocol version {} (agent-browser {}), this CLI speaks version {}. {}",
+                hello.protocol_version, hello.version, PROTOCOL_VERSION, RESTART
+            )),
+            Handshake::Compatible(hello) => match action {
+                Some(action) if !hello.supports(action) => Err(format!(
+                    "Daemon (agent-browser {}) does not support '{}'. {}",
+                    hello.version, action, RESTART
+                )),
+                _ => Ok(None),
+            },
         }
     }
-
-    Err(format!(
-        "{} (after {} retries - daemon may be busy or unresponsive)",
-        last_error, MAX_RETRIES
-    ))
-}
-
-/// Check if an error is transient and worth retrying.
-/// Transient errors include:
-/// - EAGAIN/EWOULDBLOCK (os error 35 on macOS, 11 on Linux)
-/// - EOF errors (daemon closed connection before responding)
-/// - Connection reset/broken pipe (daemon crashed or restarting)
-/// - Connection refused/socket not found (daemon still starting)
-fn is_transient_error(error: &str) -> bool {
-    error.contains("os error 35") // EAGAIN on macOS
-        || error.contains("os error 11") // EAGAIN on Linux
-        || error.contains("WouldBlock")
-        || error.contains("Resource temporarily unavailable")
-        || error.contains("EOF")
-        || error.contains("line 1 column 0") // Empty JSON response
-        || error.contains("Connection reset")
-        || error.contains("Broken pipe")
-        || error.contains("os error 54") // Connection reset by peer (macOS)
-        || error.contains("os error 104") // Connection reset by peer (Linux)
-        || error.contains("os error 2") // No such file or directory (socket gone)
-        || error.contains("os error 61") // Connection refused (macOS)
-        || error.contains("os error 111") // Connection refused (Linux)
-}
-
-fn send_command_once(cmd: &Value, session: &str) -> Result<Response, String> {
-    let mut stream = connect(session)?;
-
-    stream.set_read_timeout(Some(Duration::from_secs(30))).ok();
-    stream.set_write_timeout(Some(Duration::from_secs(5))).ok();
-
-    let mut json_str = serde_json::to_string(cmd).map_err(|e| e.to_string())?;
-    json_str.push('\n');
-
-    stream
-        .write_all(json_str.as_bytes())
-        .map_err(|e| format!("Failed to send: {}", e))?;
-
-    let mut reader = BufReader::new(stream);
-    let mut response_line = String::new();
-    reader
-        .read_line(&mut response_line)
-        .map_err(|e| format!("Failed to read: {}", e))?;
-
-    serde_json::from_str(&response_line).map_err(|e| format!("Invalid response: {}", e))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::{Mutex, MutexGuard};
-
-    // Mutex to prevent parallel tests from interfering with env vars
-    static ENV_MUTEX: Mutex<()> = Mutex::new(());
-
-    /// RAII guard that locks env mutex and restores env vars on drop
-    struct EnvGuard<'a> {
-        _lock: MutexGuard<'a, ()>,
-        vars: Vec<(String, Option<String>)>,
-    }
-
-    impl<'a> EnvGuard<'a> {
-        fn new(var_names: &[&str]) -> Self {
-            let lock = ENV_MUTEX.lock().unwrap();
-            let vars = var_names
-                .iter()
-                .map(|&name| (name.to_string(), env::var(name).ok()))
-                .collect();
-            Self { _lock: lock, vars }
-        }
-    }
-
-    impl Drop for EnvGuard<'_> {
-        fn drop(&mut self) {
-            for (name, value) in &self.vars {
-                match value {
-                    Some(v) => env::set_var(name, v),
-                    None => env::remove_var(name),
-                }
-            }
-        }
-    }
+    use std::io;
 
     #[test]
-    fn test_get_socket_dir_explicit_override() {
-        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
-
-        env::set_var("AGENT_BROWSER_SOCKET_DIR", "/custom/socket/path");
-        env::remove_var("XDG_RUNTIME_DIR");
-
-        assert_eq!(get_socket_dir(), PathBuf::from("/custom/socket/path"));
+    fn test_never_delivered() {
+        let refused = || io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert!(never_delivered(&Error::Connect(refused())));
+        assert!(never_delivered(&Error::Send(refused())));
+        assert!(never_delivered(&Error::Unresponsive {
+            last: Box::new(Error::Connect(refused())),
+            retries: 5,
+        }));
+        // The daemon may have run it
+        assert!(!never_delivered(&Error::Read(refused())));
+        assert!(!never_delivered(&Error::Command("Timeout".to_string())));
     }
 
     #[test]
-    fn test_get_socket_dir_ignores_empty_socket_dir() {
-        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
-
-        env::set_var("AGENT_BROWSER_SOCKET_DIR", "");
-        env::remove_var("XDG_RUNTIME_DIR");
-
-        assert!(get_socket_dir()
-            .to_string_lossy()
-            .ends_with(".agent-browser"));
-    }
-
-    #[test]
-    fn test_get_socket_dir_xdg_runtime() {
-        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
-
-        env::remove_var("AGENT_BROWSER_SOCKET_DIR");
-        env::set_var("XDG_RUNTIME_DIR", "/run/user/1000");
-
+    fn test_last_lines() {
+        let log = "Daemon listening\n\nError: Executable doesn't exist\n  at launch\n";
         assert_eq!(
-            get_socket_dir(),
-            PathBuf::from("/run/user/1000/agent-browser")
+            last_lines(log, 2),
+            vec!["Error: Executable doesn't exist", "  at launch"]
         );
+        assert_eq!(last_lines(log, 10).len(), 3);
+        assert!(last_lines("", 5).is_empty());
     }
 
     #[test]
-    fn test_get_socket_dir_ignores_empty_xdg_runtime() {
-        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
-
-        env::set_var("AGENT_BROWSER_SOCKET_DIR", "");
-        env::set_var("XDG_RUNTIME_DIR", "");
-
-        assert!(get_socket_dir()
-            .to_string_lossy()
-            .ends_with(".agent-browser"));
-    }
-
-    #[test]
-    fn test_get_socket_dir_home_fallback() {
-        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
-
-        env::remove_var("AGENT_BROWSER_SOCKET_DIR");
-        env::remove_var("XDG_RUNTIME_DIR");
-
-        let result = get_socket_dir();
-        assert!(result.to_string_lossy().ends_with(".agent-browser"));
-        assert!(
-            result.to_string_lossy().contains("home") || result.to_string_lossy().contains("Users")
-        );
-    }
-
-    // === Transient Error Detection Tests ===
-
-    #[test]
-    fn test_is_transient_error_eagain_macos() {
-        assert!(is_transient_error(
-            "Failed to read: Resource temporarily unavailable (os error 35)"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_eagain_linux() {
-        assert!(is_transient_error(
-            "Failed to read: Resource temporarily unavailable (os error 11)"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_would_block() {
-        assert!(is_transient_error("operation WouldBlock"));
+    fn test_restart_backoff() {
+        assert_eq!(restart_backoff(0), Duration::from_millis(250));
+        assert_eq!(restart_backoff(2), Duration::from_millis(1000));
+        assert_eq!(restart_backoff(30), Duration::from_secs(8));
     }
 
-    #[test]
-    fn test_is_transient_error_resource_unavailable() {
-        assert!(is_transient_error("Resource temporarily unavailable"));
-    }
-
-    #[test]
-    fn test_is_transient_error_eof() {
-        assert!(is_transient_error(
-            "Invalid response: EOF while parsing a value at line 1 column 0"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_empty_json() {
-        assert!(is_transient_error(
-            "Invalid response: expected value at line 1 column 0"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_connection_reset() {
-        assert!(is_transient_error("Connection reset by peer"));
-    }
-
-    #[test]
-    fn test_is_transient_error_broken_pipe() {
-        assert!(is_transient_error("Broken pipe"));
-    }
-
-    #[test]
-    fn test_is_transient_error_connection_reset_macos() {
-        assert!(is_transient_error(
-            "Failed to send: Connection reset by peer (os error 54)"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_connection_reset_linux() {
-        assert!(is_transient_error(
-            "Failed to send: Connection reset by peer (os error 104)"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_socket_not_found() {
-        assert!(is_transient_error(
-            "Failed to connect: No such file or directory (os error 2)"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_connection_refused_macos() {
-        assert!(is_transient_error(
-            "Failed to connect: Connection refused (os error 61)"
-        ));
-    }
-
-    #[test]
-    fn test_is_transient_error_connection_refused_linux() {
-        assert!(is_transient_error(
-            "Failed to connect: Connection refused (os error 111)"
-        ));
+    fn hello(protocol_version: u32, actions: &[&str]) -> HelloData {
+        HelloData {
+            protocol_version,
+            version: "0.9.0".to_string(),
+            actions: actions.iter().map(|a| a.to_string()).collect(),
+            compression: vec!["gzip".to_string()],
+        }
     }
 
     #[test]
-    fn test_is_transient_error_non_transient() {
-        // These should NOT be considered transient
-        assert!(!is_transient_error("Unknown command: foo"));
-        assert!(!is_transient_error("Invalid JSON syntax"));
-        assert!(!is_transient_error("Permission denied"));
-        assert!(!is_transient_error("Daemon not found"));
+    fn test_handshake_check() {
+        let current = Handshake::Compatible(hello(PROTOCOL_VERSION, &["navigate", "close"]));
+        assert_eq!(current.check(Some("navigate")), Ok(None));
+        assert_eq!(current.check(None), Ok(None));
+        let err = current.check(Some("swipe")).unwrap_err();
+        assert!(err.contains("does not support 'swipe'"));
+        assert!(err.contains("agent-browser close"));
+
+        let other = Handshake::Mismatch(hello(PROTOCOL_VERSION + 1, &["navigate"]));
+        let err = other.check(Some("navigate")).unwrap_err();
+        assert!(err.contains("protocol version"));
+        assert_eq!(other.check(Some("close")), Ok(None));
+
+        assert!(Handshake::Legacy.check(Some("navigate")).unwrap().is_some());
     }
 }
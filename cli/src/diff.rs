@@ -0,0 +1,307 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::color;
+use crate::connection::Response;
+use crate::format;
+
+/// Default fraction of pixels allowed to differ before `screenshot diff` fails
+pub const DEFAULT_THRESHOLD: f64 = 0.01;
+
+/// A decoded image as 8-bit RGBA pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Result of comparing two images of the same size
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub different_pixels: u64,
+    pub total_pixels: u64,
+    /// Highlighted diff: changed pixels in red over a faded copy of the baseline
+    pub diff: Image,
+}
+
+impl Comparison {
+    pub fn ratio(&self) -> f64 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.different_pixels as f64 / self.total_pixels as f64
+    }
+}
+
+pub fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    // Expand palettes and low bit depths, strip 16-bit to 8-bit
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+    buf.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err("Unsupported indexed PNG".to_string()),
+    };
+
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        rgba,
+    })
+}
+
+pub fn encode_png(image: &Image) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(&image.rgba)
+        .map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+/// Compare two images pixel by pixel. Images must have the same dimensions.
+pub fn compare(baseline: &Image, current: &Image) -> Result<Comparison, String> {
+    if (baseline.width, baseline.height) != (current.width, current.height) {
+        return Err(format!(
+            "Size mismatch: baseline is {}x{}, current page is {}x{}",
+            baseline.width, baseline.height, current.width, current.height
+        ));
+    }
+
+    let mut different_pixels = 0;
+    let mut rgba = Vec::with_capacity(baseline.rgba.len());
+    for (a, b) in baseline
+        .rgba
+        .chunks_exact(4)
+        .zip(current.rgba.chunks_exact(4))
+    {
+        if a == b {
+            // Fade unchanged pixels towards white so changes stand out
+            rgba.extend(a[..3].iter().map(|&c| 255 - (255 - c) / 4));
+            rgba.push(255);
+        } else {
+            different_pixels += 1;
+            rgba.extend([255, 0, 0, 255]);
+        }
+    }
+
+    Ok(Comparison {
+        different_pixels,
+        total_pixels: baseline.width as u64 * baseline.height as u64,
+        diff: Image {
+            width: baseline.width,
+            height: baseline.height,
+            rgba,
+        },
+    })
+}
+
+/// Default diff image path: `<baseline>.diff.png` next to the baseline.
+pub fn default_diff_path(baseline: &str) -> String {
+    let path = Path::new(baseline);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "screenshot".to_string());
+    path.with_file_name(format!("{}.diff.png", stem))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Compare the screenshot in `resp` against the baseline described by
+/// `options` (`baseline`, `threshold`, `out`) and report the result. Returns
+/// true if the difference is within the threshold.
+pub fn run(resp: &Response, options: &Value, json_mode: bool) -> bool {
+    let threshold = options
+        .get("threshold")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(DEFAULT_THRESHOLD);
+
+    match diff_screenshot(resp, options) {
+        Ok((comparison, diff_path)) => {
+            let ratio = comparison.ratio();
+            let matched = ratio <= threshold;
+            if json_mode {
+                let mut out = json!({
+                    "success": matched,
+                    "data": {
+                        "match": matched,
+                        "differentPixels": comparison.different_pixels,
+                        "totalPixels": comparison.total_pixels,
+                        "ratio": ratio,
+                        "threshold": threshold,
+                        "diffPath": diff_path,
+                    }
+                });
+                if !matched {
+                    out["error"] = json!("Screenshot differs from baseline");
+                }
+                format::print(&out);
+            } else if matched {
+                println!(
+                    "{} Screenshot matches baseline ({:.2}% of pixels differ, threshold {:.2}%)",
+                    color::success_indicator(),
+                    ratio * 100.0,
+                    threshold * 100.0
+                );
+            } else {
+                eprintln!(
+                    "{} Screenshot differs from baseline: {:.2}% of pixels differ (threshold {:.2}%)",
+                    color::error_indicator(),
+                    ratio * 100.0,
+                    threshold * 100.0
+                );
+            }
+            if !json_mode {
+                if let Some(path) = diff_path {
+                    println!("  Diff saved to {}", color::green(&path));
+                }
+            }
+            matched
+        }
+        Err(e) => {
+            if json_mode {
+                format::print(&json!({ "success": false, "error": e }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), e);
+            }
+            false
+        }
+    }
+}
+
+/// Decode both images, compare them and write the diff image when any pixel
+/// changed. Returns the comparison and the diff path, if one was written.
+fn diff_screenshot(
+    resp: &Response,
+    options: &Value,
+) -> Result<(Comparison, Option<String>), String> {
+    if !resp.success {
+        return Err(resp
+            .error
+            .clone()
+            .unwrap_or_else(|| "Unknown error".to_string()));
+    }
+
+    let baseline_path = options
+        .get("baseline")
+        .and_then(|v| v.as_str())
+        .ok_or("Missing baseline path")?;
+    let baseline_bytes =
+        fs::read(baseline_path).map_err(|e| format!("Failed to read {}: {}", baseline_path, e))?;
+    let baseline = decode_png(&baseline_bytes)
+        .map_err(|e| format!("Invalid baseline PNG {}: {}", baseline_path, e))?;
+
+    let data = resp
+        .data
+        .as_ref()
+        .and_then(|d| d.get("base64"))
+        .and_then(|v| v.as_str())
+        .ok_or("Response did not include image data")?;
+    let current_bytes = STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid image data: {}", e))?;
+    let current = decode_png(&current_bytes)?;
+
+    let comparison = compare(&baseline, &current)?;
+    if comparison.different_pixels == 0 {
+        return Ok((comparison, None));
+    }
+
+    let diff_path = options
+        .get("out")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| default_diff_path(baseline_path));
+    fs::write(&diff_path, encode_png(&comparison.diff)?)
+        .map_err(|e| format!("Failed to write {}: {}", diff_path, e))?;
+
+    Ok((comparison, Some(diff_path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, pixel: [u8; 4]) -> Image {
+        Image {
+            width,
+            height,
+            rgba: pixel.repeat((width * height) as usize),
+        }
+    }
+
+    #[test]
+    fn test_compare_identical() {
+        let a = solid(4, 4, [10, 20, 30, 255]);
+        let result = compare(&a, &a.clone()).unwrap();
+        assert_eq!(result.different_pixels, 0);
+        assert_eq!(result.total_pixels, 16);
+        assert_eq!(result.ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_compare_highlights_changed_pixels() {
+        let a = solid(2, 2, [0, 0, 0, 255]);
+        let mut b = a.clone();
+        b.rgba[4..8].copy_from_slice(&[1, 0, 0, 255]);
+
+        let result = compare(&a, &b).unwrap();
+        assert_eq!(result.different_pixels, 1);
+        assert_eq!(result.ratio(), 0.25);
+        assert_eq!(&result.diff.rgba[4..8], &[255, 0, 0, 255]);
+        assert_ne!(&result.diff.rgba[0..4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_compare_size_mismatch() {
+        let err = compare(&solid(2, 2, [0; 4]), &solid(3, 2, [0; 4])).unwrap_err();
+        assert!(err.contains("2x2"));
+        assert!(err.contains("3x2"));
+    }
+
+    #[test]
+    fn test_png_roundtrip() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            rgba: vec![255, 0, 0, 255, 0, 0, 255, 128],
+        };
+        let decoded = decode_png(&encode_png(&image).unwrap()).unwrap();
+        assert_eq!(decoded, image);
+    }
+
+    #[test]
+    fn test_decode_invalid_png() {
+        assert!(decode_png(b"not a png").is_err());
+    }
+
+    #[test]
+    fn test_default_diff_path() {
+        assert_eq!(
+            default_diff_path("/tmp/baseline.png"),
+            "/tmp/baseline.diff.png"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! `agent-browser doctor`: checks everything a daemon needs to start and talk
+//! to this CLI, and says how to fix each problem it finds.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use serde_json::json;
+
+use crate::color;
+use crate::connection::{
+    self, get_socket_dir, handshake, live_sessions, remote_address, stale_sessions, Handshake,
+};
+use crate::flags::Flags;
+use crate::format;
+use crate::runtime;
+use agent_browser_client::protocol::PROTOCOL_VERSION;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: Option<String>) -> Self {
+        Check {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix,
+        }
+    }
+}
+
+/// Run every check, print the results and exit non-zero if any failed. With
+/// `fix`, stale daemon files are removed instead of reported.
+pub fn run_doctor(flags: &Flags, fix: bool) {
+    let mut checks = Vec::new();
+    match remote_address() {
+        Ok(Some(address)) => checks.push(Check::pass(
+            "Daemon",
+            format!("remote at {}, nothing to start locally", address),
+        )),
+        Ok(None) => {
+            checks.push(check_runtime());
+            checks.push(check_daemon_script());
+            checks.push(check_browser(flags));
+            checks.push(check_socket_dir(&get_socket_dir(), &flags.session));
+            checks.push(check_stale_files(fix));
+        }
+        Err(e) => checks.push(Check::fail(
+            "Daemon",
+            e.to_string(),
+            Some("Pass --remote tcp://host:port".to_string()),
+        )),
+    }
+    checks.extend(check_protocol(flags));
+
+    let failed = checks.iter().any(|c| c.status == Status::Fail);
+    if flags.json {
+        let checks: Vec<_> = checks
+            .iter()
+            .map(|c| {
+                let status = match c.status {
+                    Status::Pass => "pass",
+                    Status::Warn => "warn",
+                    Status::Fail => "fail",
+                };
+                json!({ "name": c.name, "status": status, "detail": c.detail, "fix": c.fix })
+            })
+            .collect();
+        format::print(&json!({ "success": !failed, "data": { "checks": checks } }));
+    } else {
+        for check in &checks {
+            let indicator = match check.status {
+                Status::Pass => color::success_indicator(),
+                Status::Warn => color::warning_indicator(),
+                Status::Fail => color::error_indicator(),
+            };
+            println!("{} {:<10} {}", indicator, check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                println!("  {} {}", color::dim("Fix:"), fix);
+            }
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+fn check_runtime() -> Check {
+    match runtime::resolve_version() {
+        Ok((bun, version)) => {
+            Check::pass("Runtime", format!("Bun {} ({})", version, bun.display()))
+        }
+        // The runtime's errors already say what to install or upgrade
+        Err(e) => Check::fail("Runtime", e, None),
+    }
+}
+
+fn check_daemon_script() -> Check {
+    match connection::daemon_script() {
+        Ok((path, true)) => Check::pass(
+            "Daemon",
+            format!("embedded, extracted to {}", path.display()),
+        ),
+        Ok((path, false)) => Check::pass("Daemon", path.display().to_string()),
+        Err(e) => Check::fail(
+            "Daemon",
+            e,
+            Some("Reinstall agent-browser, or point AGENT_BROWSER_HOME at a checkout".to_string()),
+        ),
+    }
+}
+
+fn check_browser(flags: &Flags) -> Check {
+    if flags.cdp.is_some() || flags.provider.is_some() {
+        return Check::pass("Browser", "provided by --cdp or --provider");
+    }
+    if let Some(path) = &flags.executable_path {
+        return if Path::new(path).is_file() {
+            Check::pass("Browser", path.clone())
+        } else {
+            Check::fail(
+                "Browser",
+                format!("--executable-path {} does not exist", path),
+                Some("Point --executable-path at a browser executable".to_string()),
+            )
+        };
+    }
+
+    let browser = flags.browser.as_deref().unwrap_or("chromium");
+    let install = format!("agent-browser install {}", browser);
+    let Some(cache) = browsers_dir() else {
+        return Check::warn(
+            "Browser",
+            "Playwright's browser directory is unknown (PLAYWRIGHT_BROWSERS_PATH=0)",
+            format!("Run '{}' if the browser fails to launch", install),
+        );
+    };
+    match find_browser(&cache, browser) {
+        Some(dir) => Check::pass("Browser", format!("{} in {}", browser, dir.display())),
+        None => Check::fail(
+            "Browser",
+            format!("{} is not installed in {}", browser, cache.display()),
+            Some(format!("Run '{}'", install)),
+        ),
+    }
+}
+
+/// Where Playwright downloads browsers: PLAYWRIGHT_BROWSERS_PATH, or
+/// `ms-playwright` in the user's cache directory. `None` when they are kept
+/// inside the playwright-core package (PLAYWRIGHT_BROWSERS_PATH=0).
+fn browsers_dir() -> Option<PathBuf> {
+    match env::var_os("PLAYWRIGHT_BROWSERS_PATH") {
+        Some(path) if path == "0" => None,
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => dirs::cache_dir().map(|cache| cache.join("ms-playwright")),
+    }
+}
+
+/// The newest `<browser>-<revision>` download in `cache`
+fn find_browser(cache: &Path, browser: &str) -> Option<PathBuf> {
+    let prefix = format!("{}-", browser);
+    fs::read_dir(cache)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let revision: u32 = name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            Some((revision, entry.path()))
+        })
+        .max_by_key(|(revision, _)| *revision)
+        .map(|(_, path)| path)
+}
+
+fn check_socket_dir(dir: &Path, session: &str) -> Check {
+    let fix_permissions = format!(
+        "Run 'chmod 700 {}', or set AGENT_BROWSER_SOCKET_DIR to a directory you own",
+        dir.display()
+    );
+
+    #[cfg(unix)]
+    {
+        let socket_path = agent_browser_client::connection::get_socket_path(session);
+        if socket_path.as_os_str().len() > 103 {
+            return Check::fail(
+                "Sockets",
+                format!(
+                    "{} is longer than the 103 bytes a Unix socket path allows",
+                    socket_path.display()
+                ),
+                Some("Set AGENT_BROWSER_SOCKET_DIR to a shorter path".to_string()),
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = session;
+
+    let Ok(metadata) = fs::metadata(dir) else {
+        return Check::pass(
+            "Sockets",
+            format!("{} (created when a daemon starts)", dir.display()),
+        );
+    };
+    let probe = dir.join(".write_test");
+    if !metadata.is_dir() || fs::write(&probe, b"").is_err() {
+        return Check::fail(
+            "Sockets",
+            format!("{} is not a writable directory", dir.display()),
+            Some(fix_permissions),
+        );
+    }
+    let _ = fs::remove_file(&probe);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o077 != 0 {
+            return Check::warn(
+                "Sockets",
+                format!("{} is accessible to other users", dir.display()),
+                fix_permissions,
+            );
+        }
+    }
+    Check::pass("Sockets", dir.display().to_string())
+}
+
+fn check_stale_files(fix: bool) -> Check {
+    let stale = stale_sessions();
+    if stale.is_empty() {
+        return Check::pass("Sessions", "no stale pid or socket files");
+    }
+    let sessions = stale.join(", ");
+    if fix {
+        for session in &stale {
+            connection::cleanup_stale_files(session);
+        }
+        return Check::pass("Sessions", format!("removed stale files of {}", sessions));
+    }
+    Check::warn(
+        "Sessions",
+        format!(
+            "stale pid or socket files of {} (daemon not running)",
+            sessions
+        ),
+        "Run 'agent-browser doctor --fix' to remove them",
+    )
+}
+
+/// One check per running daemon (or the remote one): whether it speaks this
+/// CLI's protocol version
+fn check_protocol(flags: &Flags) -> Vec<Check> {
+    let sessions = match remote_address() {
+        Ok(Some(_)) => vec![flags.session.clone()],
+        _ => live_sessions(),
+    };
+    if sessions.is_empty() {
+        return vec![Check::pass("Protocol", "no daemons running")];
+    }
+
+    sessions
+        .iter()
+        .map(|session| {
+            let restart = format!(
+                "Run 'agent-browser --session {} close' to restart it",
+                session
+            );
+            match handshake(session) {
+                Ok(Handshake::Compatible(hello)) => Check::pass(
+                    "Protocol",
+                    format!(
+                        "{}: agent-browser {}, protocol {}",
+                        session, hello.version, hello.protocol_version
+                    ),
+                ),
+                Ok(Handshake::Legacy) => Check::warn(
+                    "Protocol",
+                    format!("{}: daemon predates protocol versioning", session),
+                    restart,
+                ),
+                Ok(Handshake::Mismatch(hello)) => Check::fail(
+                    "Protocol",
+                    format!(
+                        "{}: daemon speaks protocol {} (agent-browser {}), this CLI speaks {}",
+                        session, hello.protocol_version, hello.version, PROTOCOL_VERSION
+                    ),
+                    Some(restart),
+                ),
+                Err(e) => Check::fail(
+                    "Protocol",
+                    format!("{}: daemon is not answering ({})", session, e),
+                    Some(restart),
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "agent-browser-doctor-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_find_browser() {
+        let cache = temp_dir("cache");
+        for name in [
+            "chromium-999",
+            "chromium-1155",
+            "chromium_headless_shell-1155",
+            "ffmpeg-1010",
+        ] {
+            fs::create_dir_all(cache.join(name)).unwrap();
+        }
+        assert_eq!(
+            find_browser(&cache, "chromium"),
+            Some(cache.join("chromium-1155"))
+        );
+        assert_eq!(find_browser(&cache, "firefox"), None);
+        assert_eq!(find_browser(&cache.join("missing"), "chromium"), None);
+        fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn test_check_socket_dir() {
+        let dir = temp_dir("sockets");
+        assert_eq!(
+            check_socket_dir(&dir.join("new"), "default").status,
+            Status::Pass
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+            let check = check_socket_dir(&dir, "default");
+            assert_eq!(check.status, Status::Warn);
+            assert!(check.fix.unwrap().contains("chmod 700"));
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).unwrap();
+            assert_eq!(check_socket_dir(&dir, "default").status, Status::Pass);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
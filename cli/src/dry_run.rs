@@ -0,0 +1,111 @@
+//! `--dry-run`: print the request a command would send, and where it would go,
+//! without contacting (or starting) a daemon.
+//!
+//! Scripts and `++` chains print each line's request. Lines the CLI runs
+//! itself send nothing, and a line using a captured variable can only be
+//! parsed once the script runs, so neither has a request to show.
+
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::secret_value;
+use crate::connection;
+use crate::flags::Flags;
+use crate::format;
+use crate::script::{Script, Step};
+
+/// Print `cmd`, or every line of `script`, for `flags.session`. Returns
+/// false if the session's daemon can't be located.
+pub fn run(cmd: &Value, script: Option<&Script>, flags: &Flags) -> bool {
+    let target = match connection::target(&flags.session) {
+        Ok(target) => target,
+        Err(e) => {
+            if flags.json {
+                format::print(&json!({ "success": false, "error": e.to_string() }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), e);
+            }
+            return false;
+        }
+    };
+
+    if flags.json {
+        let mut data = json!({ "session": flags.session, "target": target });
+        match script {
+            Some(script) => {
+                let lines: Vec<Value> = script
+                    .lines
+                    .iter()
+                    .map(|line| {
+                        json!({
+                            "line": line.line,
+                            "command": line.text,
+                            "request": line.step.request().map(masked),
+                        })
+                    })
+                    .collect();
+                data["requests"] = json!(lines);
+            }
+            None => data["request"] = masked(cmd),
+        }
+        format::print(&json!({ "success": true, "data": data }));
+        return true;
+    }
+
+    println!(
+        "{}",
+        color::dim(&format!("Session {} at {}", flags.session, target))
+    );
+    match script {
+        Some(script) => {
+            for line in &script.lines {
+                println!("{}", color::dim(&format!("[{}] {}", line.line, line.text)));
+                match line.step.request() {
+                    Some(request) => println!("{}", pretty(&masked(request))),
+                    None if is_deferred(&line.step) => {
+                        println!("{}", color::dim("Parsed when the script runs"))
+                    }
+                    None => println!("{}", color::dim("Run by the CLI, nothing is sent")),
+                }
+            }
+        }
+        None => println!("{}", pretty(&masked(cmd))),
+    }
+    true
+}
+
+/// `cmd` with a `fill --secret` value hidden, as `-v` logs it
+fn masked(cmd: &Value) -> Value {
+    let mut cmd = cmd.clone();
+    if secret_value(&cmd).is_some() {
+        cmd["value"] = json!("********");
+    }
+    cmd
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn is_deferred(step: &Step) -> bool {
+    match step {
+        Step::Deferred(_) => true,
+        Step::Retry { step, .. } | Step::IfVisible { step, .. } | Step::Capture { step, .. } => {
+            is_deferred(step)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masked() {
+        let fill = json!({ "id": "1", "action": "fill", "selector": "#pw", "value": "hunter2", "secret": true });
+        assert_eq!(masked(&fill)["value"], "********");
+        let plain = json!({ "id": "1", "action": "fill", "selector": "#q", "value": "shoes" });
+        assert_eq!(masked(&plain), plain);
+    }
+}
@@ -0,0 +1,112 @@
+//! The daemon bundle compiled into the binary (see build.rs), extracted to the
+//! runtime directory the first time this build of the CLI starts a daemon.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use agent_browser_client::connection::get_app_dir;
+
+/// Empty when the binary was built without `dist/daemon.js`
+const DAEMON_JS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/daemon.js"));
+
+/// Version of the embedded daemon, passed to it since no package.json sits
+/// next to the extracted copy
+pub const DAEMON_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// `daemon-<version>-<hash>.js`: another version or build never reuses it
+fn file_name() -> String {
+    let hash = env!("AGENT_BROWSER_DAEMON_HASH");
+    format!("daemon-{}-{}.js", DAEMON_VERSION, &hash[..8])
+}
+
+/// Path of the embedded daemon, extracted if this is its first use. `None`
+/// when the binary has no embedded daemon.
+pub fn daemon_path() -> Result<Option<PathBuf>, String> {
+    if DAEMON_JS.is_empty() {
+        return Ok(None);
+    }
+    let dir = get_app_dir();
+    // A running daemon may still read its copy, e.g. after a CLI upgrade
+    let clean_up = crate::connection::live_sessions().is_empty();
+    extract(&dir, &file_name(), DAEMON_JS, clean_up)
+        .map(Some)
+        .map_err(|e| format!("Failed to extract the daemon to {}: {}", dir.display(), e))
+}
+
+/// Write `bytes` to `dir/name` unless a complete copy is already there, and
+/// with `clean_up`, remove copies left by other versions
+fn extract(dir: &Path, name: &str, bytes: &[u8], clean_up: bool) -> io::Result<PathBuf> {
+    let path = dir.join(name);
+    if fs::metadata(&path).is_ok_and(|m| m.len() == bytes.len() as u64) {
+        return Ok(path);
+    }
+
+    crate::connection::create_private_dir(dir)?;
+    // Written under a temporary name and renamed, so a CLI starting a daemon at
+    // the same time never runs half a file
+    let partial = dir.join(format!("{}.{}.tmp", name, process::id()));
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, &path)?;
+
+    if !clean_up {
+        return Ok(path);
+    }
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let other = entry.file_name();
+            let other = other.to_string_lossy();
+            if other != name && other.starts_with("daemon-") && other.ends_with(".js") {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_file_name_carries_version() {
+        let name = file_name();
+        assert!(name.starts_with(&format!("daemon-{}-", DAEMON_VERSION)));
+        assert!(name.ends_with(".js"));
+    }
+
+    #[test]
+    fn test_extract_reuses_and_replaces() {
+        let dir = env::temp_dir().join(format!("agent-browser-embedded-{}", process::id()));
+
+        let old = extract(&dir, "daemon-0.1.0-aaaaaaaa.js", b"old", true).unwrap();
+        assert_eq!(fs::read(&old).unwrap(), b"old");
+        fs::write(dir.join("default.pid"), b"1").unwrap();
+
+        // A truncated copy is rewritten
+        let path = dir.join("daemon-0.2.0-bbbbbbbb.js");
+        fs::write(&path, b"ne").unwrap();
+        assert_eq!(
+            extract(&dir, "daemon-0.2.0-bbbbbbbb.js", b"new", true).unwrap(),
+            path
+        );
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        // Only other daemon copies are cleaned up
+        assert!(!old.exists());
+        assert!(dir.join("default.pid").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_extract_keeps_copies_in_use() {
+        let dir = env::temp_dir().join(format!("agent-browser-in-use-{}", process::id()));
+
+        let old = extract(&dir, "daemon-0.1.0-aaaaaaaa.js", b"old", true).unwrap();
+        let new = extract(&dir, "daemon-0.2.0-bbbbbbbb.js", b"new", false).unwrap();
+        assert!(old.exists());
+        assert_eq!(fs::read(&new).unwrap(), b"new");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
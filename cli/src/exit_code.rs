@@ -0,0 +1,172 @@
+//! Exit codes by failure class, so scripts can branch on `$?` instead of
+//! matching the error text. Anything not listed exits with [`FAILURE`].
+
+use std::io;
+
+use crate::connection::{Error, ErrorCode, Response};
+
+/// Any other failure, including a failed `is`, `expect` or `audit` check
+pub const FAILURE: i32 = 1;
+/// No element matches the selector, or none is visible
+pub const NOT_FOUND: i32 = 2;
+/// The command or the action on an element timed out
+pub const TIMEOUT: i32 = 3;
+/// The daemon could not be started, reached or understood
+pub const DAEMON_UNAVAILABLE: i32 = 4;
+/// A page failed to load: DNS, connection, TLS or a crashed page
+pub const NAVIGATION: i32 = 5;
+
+const NAVIGATION_ACTIONS: &[&str] = &["navigate", "back", "forward", "reload"];
+
+/// Exit code for a failure of class `code`
+pub fn for_code(code: Option<ErrorCode>) -> i32 {
+    match code {
+        Some(ErrorCode::NotFound | ErrorCode::NotVisible) => NOT_FOUND,
+        Some(ErrorCode::Timeout) => TIMEOUT,
+        Some(ErrorCode::DaemonUnavailable) => DAEMON_UNAVAILABLE,
+        Some(ErrorCode::Navigation) => NAVIGATION,
+        _ => FAILURE,
+    }
+}
+
+/// The class of a failed response to `action`: its `errorCode`, or a guess
+/// from the message for daemons that send none
+pub fn classify(action: Option<&str>, resp: &Response) -> Option<ErrorCode> {
+    resp.error_code
+        .or_else(|| classify_message(action, resp.error.as_deref().unwrap_or("")))
+}
+
+/// The class of an error sending `action` or reading its response
+pub fn classify_error(action: Option<&str>, error: &Error) -> Option<ErrorCode> {
+    match error {
+        Error::Command(message) => classify_message(action, message),
+        // A read timeout shows up as WouldBlock on Unix
+        Error::Read(e) => match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Some(ErrorCode::Timeout),
+            _ => Some(ErrorCode::DaemonUnavailable),
+        },
+        Error::Connect(_)
+        | Error::PortFile(_)
+        | Error::Send(_)
+        | Error::InvalidResponse(_)
+        | Error::Unresponsive { .. } => Some(ErrorCode::DaemonUnavailable),
+        Error::Remote(_) | Error::MissingField(_) => None,
+    }
+}
+
+fn classify_message(action: Option<&str>, error: &str) -> Option<ErrorCode> {
+    // An assertion that never held reports a timeout, but it is a failed check
+    if action == Some("expect") {
+        return None;
+    }
+    if error.contains("not found or not visible") {
+        Some(ErrorCode::NotFound)
+    } else if error.contains("timed out")
+        || (error.contains("Timeout") && error.contains("exceeded"))
+    {
+        Some(ErrorCode::Timeout)
+    } else if action.is_some_and(|a| NAVIGATION_ACTIONS.contains(&a))
+        || error.contains("net::ERR_")
+        || error.contains("NS_ERROR_")
+    {
+        Some(ErrorCode::Navigation)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(message: &str) -> Response {
+        Response {
+            success: false,
+            error: Some(message.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let not_found = "Element \"#nope\" not found or not visible. Run 'snapshot' to see current page elements.";
+        assert_eq!(
+            classify(Some("click"), &failure(not_found)),
+            Some(ErrorCode::NotFound)
+        );
+        assert_eq!(
+            classify(Some("click"), &failure("Action on \"@e1\" timed out.")),
+            Some(ErrorCode::Timeout)
+        );
+        assert_eq!(
+            classify(
+                Some("navigate"),
+                &failure("page.goto: Timeout 30000ms exceeded.")
+            ),
+            Some(ErrorCode::Timeout)
+        );
+        assert_eq!(
+            classify(
+                Some("navigate"),
+                &failure("page.goto: net::ERR_NAME_NOT_RESOLVED")
+            ),
+            Some(ErrorCode::Navigation)
+        );
+        assert_eq!(
+            classify(
+                Some("expect"),
+                &failure("Expected #ok to be visible (timed out after 5000ms)")
+            ),
+            None
+        );
+        assert_eq!(
+            classify(Some("tab_switch"), &failure("No tab at index 4")),
+            None
+        );
+
+        // The daemon's code wins over the message
+        let resp = Response {
+            error_code: Some(ErrorCode::Ambiguous),
+            ..failure("Action on \"a\" timed out.")
+        };
+        assert_eq!(classify(Some("click"), &resp), Some(ErrorCode::Ambiguous));
+    }
+
+    #[test]
+    fn test_for_code() {
+        assert_eq!(for_code(Some(ErrorCode::NotVisible)), NOT_FOUND);
+        assert_eq!(for_code(Some(ErrorCode::Timeout)), TIMEOUT);
+        assert_eq!(for_code(Some(ErrorCode::Navigation)), NAVIGATION);
+        assert_eq!(for_code(Some(ErrorCode::Blocked)), FAILURE);
+        assert_eq!(for_code(None), FAILURE);
+    }
+
+    #[test]
+    fn test_classify_error() {
+        let refused = || io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert_eq!(
+            classify_error(None, &Error::Connect(refused())),
+            Some(ErrorCode::DaemonUnavailable)
+        );
+        let unresponsive = Error::Unresponsive {
+            last: Box::new(Error::Read(refused())),
+            retries: 3,
+        };
+        assert_eq!(
+            classify_error(None, &unresponsive),
+            Some(ErrorCode::DaemonUnavailable)
+        );
+        let timed_out = Error::Read(io::Error::from(io::ErrorKind::TimedOut));
+        assert_eq!(
+            classify_error(Some("wait"), &timed_out),
+            Some(ErrorCode::Timeout)
+        );
+        assert_eq!(
+            classify_error(
+                Some("reload"),
+                &Error::Command("net::ERR_ABORTED".to_string())
+            ),
+            Some(ErrorCode::Navigation)
+        );
+    }
+}
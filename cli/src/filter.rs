@@ -0,0 +1,166 @@
+//! `--filter <path>`: a jq-style path such as `.data.snapshot` or
+//! `.data.tabs[0].url` that picks one value out of the JSON result.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    /// Negative indexes count from the end, as in jq
+    Index(i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    steps: Vec<Step>,
+}
+
+impl Filter {
+    /// Parse `.`, `.key`, `."any key"`, `["any key"]`, `[n]` and chains of them
+    pub fn parse(path: &str) -> Result<Filter, String> {
+        let invalid = |why: String| {
+            format!(
+                "Invalid filter {}: {} (expected e.g. .data.snapshot or .data.tabs[0].url)",
+                path, why
+            )
+        };
+        let Some(mut rest) = path.strip_prefix('.') else {
+            return Err(invalid("it must start with '.'".to_string()));
+        };
+
+        let mut steps = Vec::new();
+        // Right after a '.', which the path starts with
+        let mut after_dot = true;
+        while !rest.is_empty() {
+            if let Some(bracket) = rest.strip_prefix('[') {
+                let end = bracket
+                    .find(']')
+                    .ok_or_else(|| invalid("'[' is never closed".to_string()))?;
+                let inner = &bracket[..end];
+                let step = match inner.strip_prefix('"').and_then(|k| k.strip_suffix('"')) {
+                    Some(key) => Step::Key(key.to_string()),
+                    None => Step::Index(
+                        inner
+                            .parse()
+                            .map_err(|_| invalid(format!("[{}] is not an index", inner)))?,
+                    ),
+                };
+                steps.push(step);
+                rest = &bracket[end + 1..];
+                after_dot = false;
+            } else if after_dot {
+                let (key, remaining) = match rest.strip_prefix('"') {
+                    Some(quoted) => {
+                        let end = quoted
+                            .find('"')
+                            .ok_or_else(|| invalid("a quoted key is never closed".to_string()))?;
+                        (&quoted[..end], &quoted[end + 1..])
+                    }
+                    None => {
+                        let end = rest
+                            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                            .unwrap_or(rest.len());
+                        if end == 0 {
+                            return Err(invalid(format!("unexpected '{}'", first(rest))));
+                        }
+                        rest.split_at(end)
+                    }
+                };
+                steps.push(Step::Key(key.to_string()));
+                rest = remaining;
+                after_dot = false;
+            } else if let Some(remaining) = rest.strip_prefix('.') {
+                rest = remaining;
+                after_dot = true;
+            } else {
+                return Err(invalid(format!("unexpected '{}'", first(rest))));
+            }
+        }
+        if after_dot && path != "." {
+            return Err(invalid("it ends with '.'".to_string()));
+        }
+        Ok(Filter { steps })
+    }
+
+    /// The value at the path, `None` where a key or index is missing
+    pub fn select<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.steps.iter().try_fold(value, |value, step| match step {
+            Step::Key(key) => value.get(key),
+            Step::Index(index) => {
+                let items = value.as_array()?;
+                let index = if *index < 0 {
+                    items.len().checked_sub(index.unsigned_abs() as usize)?
+                } else {
+                    *index as usize
+                };
+                items.get(index)
+            }
+        })
+    }
+}
+
+fn first(s: &str) -> char {
+    s.chars().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(path: &str, value: &Value) -> Option<Value> {
+        Filter::parse(path).unwrap().select(value).cloned()
+    }
+
+    #[test]
+    fn test_select() {
+        let resp = json!({
+            "success": true,
+            "data": {
+                "snapshot": "- heading \"Example\" [ref=e1]",
+                "tabs": [{ "url": "https://a.test" }, { "url": "https://b.test" }],
+                "content-type": "text/html",
+                "odd key": 1,
+            },
+        });
+        assert_eq!(
+            select(".data.snapshot", &resp),
+            Some(json!("- heading \"Example\" [ref=e1]"))
+        );
+        assert_eq!(
+            select(".data.tabs[1].url", &resp),
+            Some(json!("https://b.test"))
+        );
+        assert_eq!(
+            select(".data.tabs[-2].url", &resp),
+            Some(json!("https://a.test"))
+        );
+        assert_eq!(
+            select(".data.content-type", &resp),
+            Some(json!("text/html"))
+        );
+        assert_eq!(select(".data.\"odd key\"", &resp), Some(json!(1)));
+        assert_eq!(select(".data[\"odd key\"]", &resp), Some(json!(1)));
+        assert_eq!(select(".", &resp), Some(resp.clone()));
+        assert_eq!(select(".data.missing", &resp), None);
+        assert_eq!(select(".data.tabs[5]", &resp), None);
+        assert_eq!(select(".data.tabs[-3]", &resp), None);
+        assert_eq!(select(".success.x", &resp), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Filter::parse("data.snapshot")
+            .unwrap_err()
+            .contains("it must start with '.'"));
+        assert!(Filter::parse(".data.")
+            .unwrap_err()
+            .contains("it ends with '.'"));
+        assert!(Filter::parse(".data..x").is_err());
+        assert!(Filter::parse(".tabs[x]")
+            .unwrap_err()
+            .contains("[x] is not an index"));
+        assert!(Filter::parse(".tabs[0").is_err());
+        assert!(Filter::parse(".data | keys").is_err());
+    }
+}
@@ -1,9 +1,19 @@
 use std::env;
 
+use crate::spec;
+
 pub struct Flags {
+    /// Structured output: `--json`, or a `--format` other than plain once main has read it
     pub json: bool,
+    /// `--format <name>` for the output, validated by main
+    pub format: Option<String>,
+    /// `--filter <path>` picking the part of the result to print, validated by main
+    pub filter: Option<String>,
+    /// `-o/--output <path>`: write the result there instead of stdout (`-` for stdout)
+    pub output: Option<String>,
     pub full: bool,
     pub headed: bool,
+    pub headless: bool,
     pub debug: bool,
     pub session: String,
     pub headers: Option<String>,
@@ -20,9 +30,50 @@ pub struct Flags {
     pub ignore_https_errors: bool,
     pub allow_file_access: bool,
     pub device: Option<String>,
+    pub browser: Option<String>,
+    /// `--timeout <ms>` for the whole command, validated by `parse_command`.
+    /// After a command with its own `--timeout`, the flag is the command's.
+    pub timeout: Option<String>,
+    /// `--remote tcp://host:port`, a daemon reached over TCP instead of the local socket
+    pub remote: Option<String>,
+    /// `--socket <path>`, the daemon's Unix socket in place of `<session>.sock`
+    pub socket: Option<String>,
+    /// `--runtime <bun|path>`, the Bun executable daemons are started with
+    pub runtime: Option<String>,
+    /// `--no-color`: never color the output, as with NO_COLOR
+    pub no_color: bool,
+    /// `-q/--quiet`: print results and errors but no ✓ confirmations
+    pub quiet: bool,
+    /// `-v/--verbose`: log requests, responses and their timing to stderr
+    pub verbose: bool,
+    /// `--timings`: report where the command's time went
+    pub timings: bool,
+    /// `--show-daemon-log` (or AGENT_BROWSER_SHOW_DAEMON_LOG): print the end of the daemon's
+    /// log when a command fails
+    pub show_daemon_log: bool,
+    /// `--dry-run`: print the request instead of sending it
+    pub dry_run: bool,
+    /// `--all-sessions`: run the command against every live session
+    pub all_sessions: bool,
+    /// `--lock`/`--no-lock` (or AGENT_BROWSER_LOCK): hold the session's lock while running
+    pub lock: Option<bool>,
+    /// `--lock-timeout <ms>` (or AGENT_BROWSER_LOCK_TIMEOUT) to wait for the lock
+    pub lock_timeout: Option<String>,
+    /// `--retry <n>` (or AGENT_BROWSER_RETRY): retries of an interaction whose element detaches
+    pub retry: Option<String>,
+    /// `--idle-timeout <duration>` (or AGENT_BROWSER_IDLE_TIMEOUT) for a daemon started now
+    pub idle_timeout: Option<String>,
+    /// `--max-lifetime <duration>` (or AGENT_BROWSER_MAX_LIFETIME) for a daemon started now
+    pub max_lifetime: Option<String>,
+    /// `--min-delay <ms>` (or AGENT_BROWSER_MIN_DELAY) between commands to the session
+    pub min_delay: Option<String>,
+    /// `--max-rps <n>` (or AGENT_BROWSER_MAX_RPS): commands per second to the session
+    pub max_rps: Option<String>,
 
     // Track which launch-time options were explicitly passed via CLI
     // (as opposed to being set only via environment variables)
+    pub cli_headed: bool,
+    pub cli_browser: bool,
     pub cli_executable_path: bool,
     pub cli_extensions: bool,
     pub cli_profile: bool,
@@ -32,6 +83,9 @@ pub struct Flags {
     pub cli_proxy: bool,
     pub cli_proxy_bypass: bool,
     pub cli_allow_file_access: bool,
+    pub cli_session: bool,
+    pub cli_idle_timeout: bool,
+    pub cli_max_lifetime: bool,
 }
 
 pub fn parse_flags(args: &[String]) -> Flags {
@@ -47,8 +101,12 @@ pub fn parse_flags(args: &[String]) -> Flags {
 
     let mut flags = Flags {
         json: false,
+        format: None,
+        filter: None,
+        output: None,
         full: false,
         headed: false,
+        headless: false,
         debug: false,
         session: env::var("AGENT_BROWSER_SESSION").unwrap_or_else(|_| "default".to_string()),
         headers: None,
@@ -65,7 +123,31 @@ pub fn parse_flags(args: &[String]) -> Flags {
         ignore_https_errors: false,
         allow_file_access: env::var("AGENT_BROWSER_ALLOW_FILE_ACCESS").is_ok(),
         device: env::var("AGENT_BROWSER_IOS_DEVICE").ok(),
+        browser: env::var("AGENT_BROWSER_BROWSER").ok(),
+        timeout: None,
+        remote: None,
+        socket: None,
+        runtime: None,
+        no_color: false,
+        quiet: false,
+        verbose: false,
+        timings: false,
+        show_daemon_log: env::var("AGENT_BROWSER_SHOW_DAEMON_LOG")
+            .is_ok_and(|v| !matches!(v.as_str(), "" | "0" | "false")),
+        dry_run: false,
+        all_sessions: false,
+        lock: env::var("AGENT_BROWSER_LOCK")
+            .ok()
+            .map(|v| !matches!(v.as_str(), "" | "0" | "false")),
+        lock_timeout: env::var("AGENT_BROWSER_LOCK_TIMEOUT").ok(),
+        retry: env::var("AGENT_BROWSER_RETRY").ok(),
+        idle_timeout: env::var("AGENT_BROWSER_IDLE_TIMEOUT").ok(),
+        max_lifetime: env::var("AGENT_BROWSER_MAX_LIFETIME").ok(),
+        min_delay: env::var("AGENT_BROWSER_MIN_DELAY").ok(),
+        max_rps: env::var("AGENT_BROWSER_MAX_RPS").ok(),
         // Track CLI-passed flags (default false, set to true when flag is passed)
+        cli_headed: false,
+        cli_browser: false,
         cli_executable_path: false,
         cli_extensions: false,
         cli_profile: false,
@@ -75,18 +157,64 @@ pub fn parse_flags(args: &[String]) -> Flags {
         cli_proxy: false,
         cli_proxy_bypass: false,
         cli_allow_file_access: false,
+        cli_session: false,
+        cli_idle_timeout: false,
+        cli_max_lifetime: false,
     };
 
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
             "--json" => flags.json = true,
+            "--format" if is_global(args, i) => {
+                if let Some(f) = args.get(i + 1) {
+                    flags.format = Some(f.clone());
+                    i += 1;
+                }
+            }
+            "--filter" if is_global(args, i) => {
+                if let Some(f) = args.get(i + 1) {
+                    flags.filter = Some(f.clone());
+                    i += 1;
+                }
+            }
+            "-o" | "--output" if is_global(args, i) => {
+                if let Some(o) = args.get(i + 1) {
+                    flags.output = Some(o.clone());
+                    i += 1;
+                }
+            }
             "--full" | "-f" => flags.full = true,
-            "--headed" => flags.headed = true,
+            "--headed" => {
+                flags.headed = true;
+                flags.headless = false;
+                flags.cli_headed = true;
+            }
+            "--headless" => {
+                flags.headless = true;
+                flags.headed = false;
+            }
+            "--browser" => {
+                if let Some(b) = args.get(i + 1) {
+                    flags.browser = Some(b.clone());
+                    flags.cli_browser = true;
+                    i += 1;
+                }
+            }
             "--debug" => flags.debug = true,
-            "--session" => {
+            "--all-sessions" => flags.all_sessions = true,
+            "--no-color" => flags.no_color = true,
+            "-q" | "--quiet" => flags.quiet = true,
+            "-v" | "--verbose" => flags.verbose = true,
+            "--timings" => flags.timings = true,
+            "--show-daemon-log" => flags.show_daemon_log = true,
+            "--dry-run" => flags.dry_run = true,
+            "--lock" => flags.lock = Some(true),
+            "--no-lock" => flags.lock = Some(false),
+            "--session" | "-S" => {
                 if let Some(s) = args.get(i + 1) {
                     flags.session = s.clone();
+                    flags.cli_session = true;
                     i += 1;
                 }
             }
@@ -175,6 +303,68 @@ pub fn parse_flags(args: &[String]) -> Flags {
                     i += 1;
                 }
             }
+            "--timeout" if is_global(args, i) => {
+                if let Some(t) = args.get(i + 1) {
+                    flags.timeout = Some(t.clone());
+                    i += 1;
+                }
+            }
+            "--remote" => {
+                if let Some(r) = args.get(i + 1) {
+                    flags.remote = Some(r.clone());
+                    i += 1;
+                }
+            }
+            "--lock-timeout" => {
+                if let Some(t) = args.get(i + 1) {
+                    flags.lock_timeout = Some(t.clone());
+                    i += 1;
+                }
+            }
+            "--retry" => {
+                if let Some(n) = args.get(i + 1) {
+                    flags.retry = Some(n.clone());
+                    i += 1;
+                }
+            }
+            "--idle-timeout" => {
+                if let Some(t) = args.get(i + 1) {
+                    flags.idle_timeout = Some(t.clone());
+                    flags.cli_idle_timeout = true;
+                    i += 1;
+                }
+            }
+            "--max-lifetime" => {
+                if let Some(t) = args.get(i + 1) {
+                    flags.max_lifetime = Some(t.clone());
+                    flags.cli_max_lifetime = true;
+                    i += 1;
+                }
+            }
+            "--min-delay" => {
+                if let Some(ms) = args.get(i + 1) {
+                    flags.min_delay = Some(ms.clone());
+                    i += 1;
+                }
+            }
+            "--max-rps" => {
+                if let Some(n) = args.get(i + 1) {
+                    flags.max_rps = Some(n.clone());
+                    i += 1;
+                }
+            }
+            "--socket" => {
+                if let Some(s) = args.get(i + 1) {
+                    flags.socket = Some(s.clone());
+                    i += 1;
+                }
+            }
+            "--runtime" => {
+                if let Some(r) = args.get(i + 1) {
+                    flags.runtime = Some(r.clone());
+                    i += 1;
+                }
+            }
             _ => {}
         }
         i += 1;
@@ -182,51 +372,132 @@ pub fn parse_flags(args: &[String]) -> Flags {
     flags
 }
 
+/// Session names become socket, pid and token file names, so they can't
+/// contain path separators or be `.`/`..`
+pub fn validate_session(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(format!(
+            "Invalid session name '{}': use letters, digits, '-', '_' or '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Global flags that should be stripped from command args
+pub const GLOBAL_FLAGS: &[&str] = &[
+    "--json",
+    "--full",
+    "-f",
+    "--headed",
+    "--headless",
+    "--debug",
+    "--ignore-https-errors",
+    "--allow-file-access",
+    "--all-sessions",
+    "--lock",
+    "--no-lock",
+    "--no-color",
+    "-q",
+    "--quiet",
+    "-v",
+    "--verbose",
+    "--timings",
+    "--show-daemon-log",
+    "--dry-run",
+];
+
+/// Global flags that take a value (need to skip the next arg too)
+pub const GLOBAL_FLAGS_WITH_VALUE: &[&str] = &[
+    "--session",
+    "-S",
+    "--headers",
+    "--executable-path",
+    "--cdp",
+    "--extension",
+    "--profile",
+    "--state",
+    "--proxy",
+    "--proxy-bypass",
+    "--args",
+    "--user-agent",
+    "-p",
+    "--provider",
+    "--device",
+    "--browser",
+    "--remote",
+    "--socket",
+    "--lock-timeout",
+    "--retry",
+    "--idle-timeout",
+    "--max-lifetime",
+    "--runtime",
+    "--min-delay",
+    "--max-rps",
+];
+
+/// Global flags with a value that some commands also have for themselves,
+/// like `snapshot --format`, `refs --filter`, `fetch -o` or `wait --timeout`.
+/// They are global before the command, and after it unless the command has
+/// the flag too.
+pub const SHARED_FLAGS_WITH_VALUE: &[&str] =
+    &["--format", "--filter", "-o", "--output", "--timeout"];
+
+/// Index of the command: the first argument that is neither a flag nor the
+/// value of a global one
+fn command_index(args: &[String]) -> Option<usize> {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg) || SHARED_FLAGS_WITH_VALUE.contains(&arg) {
+            i += 2;
+        } else if arg.starts_with('-') {
+            i += 1;
+        } else {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Whether `args[i]` is a shared flag used as the global one
+fn is_global(args: &[String], i: usize) -> bool {
+    let flag = args[i].as_str();
+    if !SHARED_FLAGS_WITH_VALUE.contains(&flag) {
+        return false;
+    }
+    match command_index(args) {
+        Some(c) if c < i => !spec::flags(&args[c]).is_some_and(|flags| flags.contains(&flag)),
+        _ => true,
+    }
+}
+
 pub fn clean_args(args: &[String]) -> Vec<String> {
+    command_positions(args)
+        .into_iter()
+        .map(|i| args[i].clone())
+        .collect()
+}
+
+/// Where in `args` the arguments [`clean_args`] keeps are
+pub fn command_positions(args: &[String]) -> Vec<usize> {
     let mut result = Vec::new();
     let mut skip_next = false;
 
-    // Global flags that should be stripped from command args
-    const GLOBAL_FLAGS: &[&str] = &[
-        "--json",
-        "--full",
-        "--headed",
-        "--debug",
-        "--ignore-https-errors",
-        "--allow-file-access",
-    ];
-    // Global flags that take a value (need to skip the next arg too)
-    const GLOBAL_FLAGS_WITH_VALUE: &[&str] = &[
-        "--session",
-        "--headers",
-        "--executable-path",
-        "--cdp",
-        "--extension",
-        "--profile",
-        "--state",
-        "--proxy",
-        "--proxy-bypass",
-        "--args",
-        "--user-agent",
-        "-p",
-        "--provider",
-        "--device",
-    ];
-
-    for arg in args.iter() {
+    for (i, arg) in args.iter().enumerate() {
         if skip_next {
             skip_next = false;
             continue;
         }
-        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str()) {
+        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str()) || is_global(args, i) {
             skip_next = true;
             continue;
         }
         // Only strip known global flags, not command-specific flags
-        if GLOBAL_FLAGS.contains(&arg.as_str()) || arg == "-f" {
+        if GLOBAL_FLAGS.contains(&arg.as_str()) {
             continue;
         }
-        result.push(arg.clone());
+        result.push(i);
     }
     result
 }
@@ -261,6 +532,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_headless_and_browser() {
+        let flags = parse_flags(&args("--headed --headless --browser firefox open x.com"));
+        assert!(flags.headless);
+        assert!(!flags.headed);
+        assert!(flags.cli_headed);
+        assert_eq!(flags.browser.as_deref(), Some("firefox"));
+        assert!(flags.cli_browser);
+        let clean = clean_args(&args("--headless --browser webkit open x.com"));
+        assert_eq!(clean, vec!["open", "x.com"]);
+    }
+
     #[test]
     fn test_parse_no_headers_flag() {
         let flags = parse_flags(&args("open example.com"));
@@ -349,6 +632,77 @@ mod tests {
         assert_eq!(flags.executable_path, Some("/custom/chrome".to_string()));
     }
 
+    #[test]
+    fn test_session_short_flag() {
+        let flags = parse_flags(&args("-S work snapshot -i"));
+        assert_eq!(flags.session, "work");
+        assert!(flags.cli_session);
+        let clean = clean_args(&args("-S work snapshot -i"));
+        assert_eq!(clean, args("snapshot -i"));
+    }
+
+    #[test]
+    fn test_lock_flags() {
+        let flags = parse_flags(&args("--lock --lock-timeout 5000 click @e1"));
+        assert_eq!(flags.lock, Some(true));
+        assert_eq!(flags.lock_timeout.as_deref(), Some("5000"));
+        let clean = clean_args(&args("--lock --lock-timeout 5000 click @e1"));
+        assert_eq!(clean, args("click @e1"));
+        assert_eq!(parse_flags(&args("--no-lock snapshot")).lock, Some(false));
+    }
+
+    #[test]
+    fn test_retry_flag() {
+        let flags = parse_flags(&args("--retry 3 click @e1"));
+        assert_eq!(flags.retry.as_deref(), Some("3"));
+        assert_eq!(clean_args(&args("click @e1 --retry 3")), args("click @e1"));
+    }
+
+    #[test]
+    fn test_show_daemon_log_flag() {
+        let flags = parse_flags(&args("click @e1 --show-daemon-log"));
+        assert!(flags.show_daemon_log);
+        assert_eq!(
+            clean_args(&args("--show-daemon-log click @e1")),
+            args("click @e1")
+        );
+    }
+
+    #[test]
+    fn test_runtime_flag() {
+        let flags = parse_flags(&args("--runtime /opt/bun/bin/bun open example.com"));
+        assert_eq!(flags.runtime.as_deref(), Some("/opt/bun/bin/bun"));
+        let clean = clean_args(&args("--runtime bun open example.com"));
+        assert_eq!(clean, args("open example.com"));
+    }
+
+    #[test]
+    fn test_daemon_limit_flags() {
+        let flags = parse_flags(&args("--idle-timeout 10m --max-lifetime 2h open"));
+        assert_eq!(flags.idle_timeout.as_deref(), Some("10m"));
+        assert_eq!(flags.max_lifetime.as_deref(), Some("2h"));
+        assert!(flags.cli_idle_timeout && flags.cli_max_lifetime);
+        let clean = clean_args(&args("daemon config --idle-timeout 10m"));
+        assert_eq!(clean, args("daemon config"));
+    }
+
+    #[test]
+    fn test_pace_flags() {
+        let flags = parse_flags(&args("--min-delay 800 --max-rps 0.5 open example.com"));
+        assert_eq!(flags.min_delay.as_deref(), Some("800"));
+        assert_eq!(flags.max_rps.as_deref(), Some("0.5"));
+        let clean = clean_args(&args("batch --max-rps 2 --min-delay 300"));
+        assert_eq!(clean, args("batch"));
+    }
+
+    #[test]
+    fn test_validate_session() {
+        assert!(validate_session("agent-1.work_2").is_ok());
+        for name in ["", ".", "..", "../other", "a/b", "a\\b"] {
+            assert!(validate_session(name).is_err(), "{:?}", name);
+        }
+    }
+
     #[test]
     fn test_cli_executable_path_tracking() {
         // When --executable-path is passed via CLI, cli_executable_path should be true
@@ -377,6 +731,100 @@ mod tests {
         assert!(flags.cli_profile);
     }
 
+    #[test]
+    fn test_timeout_flag() {
+        let flags = parse_flags(&args("click @e1 --timeout 45000"));
+        assert_eq!(flags.timeout.as_deref(), Some("45000"));
+        let clean = clean_args(&args("click @e1 --timeout 45000"));
+        assert_eq!(clean, args("click @e1"));
+        // Commands with a --timeout of their own keep it
+        let flags = parse_flags(&args("wait --idle --timeout 45000"));
+        assert_eq!(flags.timeout, None);
+        let clean = clean_args(&args("wait --idle --timeout 45000"));
+        assert_eq!(clean, args("wait --idle --timeout 45000"));
+        let flags = parse_flags(&args("--timeout 45000 wait --idle"));
+        assert_eq!(flags.timeout.as_deref(), Some("45000"));
+    }
+
+    #[test]
+    fn test_remote_flag() {
+        let flags = parse_flags(&args("--remote tcp://10.0.0.5:9400 snapshot"));
+        assert_eq!(flags.remote.as_deref(), Some("tcp://10.0.0.5:9400"));
+        let clean = clean_args(&args("--remote tcp://10.0.0.5:9400 snapshot"));
+        assert_eq!(clean, args("snapshot"));
+    }
+
+    #[test]
+    fn test_socket_flag() {
+        let flags = parse_flags(&args("--socket /tmp/ci/browser.sock open example.com"));
+        assert_eq!(flags.socket.as_deref(), Some("/tmp/ci/browser.sock"));
+        let clean = clean_args(&args("--socket /tmp/ci/browser.sock open example.com"));
+        assert_eq!(clean, args("open example.com"));
+    }
+
+    #[test]
+    fn test_format_flag() {
+        let flags = parse_flags(&args("--format yaml get url"));
+        assert_eq!(flags.format.as_deref(), Some("yaml"));
+        assert_eq!(clean_args(&args("--format yaml get url")), args("get url"));
+        let flags = parse_flags(&args("tab list --format jsonl"));
+        assert_eq!(flags.format.as_deref(), Some("jsonl"));
+        assert_eq!(
+            clean_args(&args("tab list --format jsonl")),
+            args("tab list")
+        );
+
+        // After a command with its own --format, the flag is the command's
+        let flags = parse_flags(&args("snapshot --format md"));
+        assert!(flags.format.is_none());
+        assert_eq!(
+            clean_args(&args("snapshot --format md")),
+            args("snapshot --format md")
+        );
+        let flags = parse_flags(&args("--format yaml -S a table #t --format csv"));
+        assert_eq!(flags.format.as_deref(), Some("yaml"));
+        assert_eq!(
+            clean_args(&args("--format yaml -S a table #t --format csv")),
+            args("table #t --format csv")
+        );
+
+        let flags = parse_flags(&args("get url --filter .data.url"));
+        assert_eq!(flags.filter.as_deref(), Some(".data.url"));
+        let flags = parse_flags(&args("refs --filter button"));
+        assert!(flags.filter.is_none());
+    }
+
+    #[test]
+    fn test_color_and_verbosity_flags() {
+        let flags = parse_flags(&args("-q --no-color click @e1 --verbose"));
+        assert!(flags.quiet && flags.no_color && flags.verbose);
+        assert_eq!(
+            clean_args(&args("-q --no-color click @e1 --verbose")),
+            args("click @e1")
+        );
+        assert!(parse_flags(&args("-v get url")).verbose);
+    }
+
+    #[test]
+    fn test_output_flag() {
+        let flags = parse_flags(&args("snapshot -i -o tree.txt"));
+        assert_eq!(flags.output.as_deref(), Some("tree.txt"));
+        assert_eq!(
+            clean_args(&args("snapshot -i -o tree.txt")),
+            args("snapshot -i")
+        );
+        let flags = parse_flags(&args("-o - screenshot"));
+        assert_eq!(flags.output.as_deref(), Some("-"));
+
+        // fetch and get html save to their own --output
+        let flags = parse_flags(&args("fetch /api -o body.json"));
+        assert!(flags.output.is_none());
+        assert_eq!(
+            clean_args(&args("fetch /api -o body.json")),
+            args("fetch /api -o body.json")
+        );
+    }
+
     #[test]
     fn test_cli_multiple_flags_tracking() {
         let flags = parse_flags(&args(
@@ -0,0 +1,179 @@
+use serde_json::{json, Value};
+use std::thread;
+use std::time::Duration;
+
+use crate::color;
+use crate::connection::DaemonConnection;
+use crate::format;
+use crate::output::{format_console_message, format_mutation};
+
+/// How often `console --follow` asks the daemon for new messages
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Print console messages as they arrive until interrupted. `cmd` is a
+/// `console` command; it is re-sent with `since` set to the last sequence
+/// number seen. Returns false if the daemon could not be reached.
+pub fn console(cmd: &Value, session: &str, json_mode: bool) -> bool {
+    let mut conn = match DaemonConnection::open(session) {
+        Ok(c) => c,
+        Err(e) => {
+            report_error(&e.to_string(), json_mode);
+            return false;
+        }
+    };
+
+    let mut request = cmd.clone();
+    if let Some(obj) = request.as_object_mut() {
+        obj.remove("follow");
+    }
+
+    loop {
+        let resp = match conn.send(&request) {
+            Ok(resp) => resp,
+            Err(e) => {
+                report_error(&e.to_string(), json_mode);
+                return false;
+            }
+        };
+        if !resp.success {
+            report_error(resp.error.as_deref().unwrap_or("Unknown error"), json_mode);
+            return false;
+        }
+
+        let messages = resp
+            .data
+            .as_ref()
+            .and_then(|d| d.get("messages"))
+            .and_then(|v| v.as_array());
+        for message in messages.into_iter().flatten() {
+            if json_mode {
+                // One message per line so the stream can be piped into jq
+                format::print_item(message);
+            } else {
+                println!("{}", format_console_message(message));
+            }
+            if let Some(seq) = message.get("seq").and_then(|v| v.as_u64()) {
+                request["since"] = json!(seq);
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Subscribe to daemon events and print each one as a line of JSON until
+/// interrupted or the daemon goes away.
+pub fn events(cmd: &Value, session: &str, json_mode: bool) -> bool {
+    let mut conn = match DaemonConnection::open(session) {
+        Ok(c) => c,
+        Err(e) => {
+            report_error(&e.to_string(), json_mode);
+            return false;
+        }
+    };
+
+    let mut request = cmd.clone();
+    if let Some(obj) = request.as_object_mut() {
+        obj.remove("follow");
+    }
+
+    match conn.send(&request) {
+        Ok(resp) if resp.success => {}
+        Ok(resp) => {
+            report_error(resp.error.as_deref().unwrap_or("Unknown error"), json_mode);
+            return false;
+        }
+        Err(e) => {
+            report_error(&e.to_string(), json_mode);
+            return false;
+        }
+    }
+
+    loop {
+        match conn.next_event() {
+            Ok(Some(event)) => format::print_item(&event),
+            Ok(None) => return true,
+            Err(e) => {
+                report_error(&e.to_string(), json_mode);
+                return false;
+            }
+        }
+    }
+}
+
+/// Print each change `watch` reports until it stops or is interrupted. With
+/// `once`, stopping for any reason but a change is a failure, so scripts can
+/// wait on it. Returns false on failure.
+pub fn watch(cmd: &Value, session: &str, json_mode: bool) -> bool {
+    let mut conn = match DaemonConnection::open(session) {
+        Ok(c) => c,
+        Err(e) => {
+            report_error(&e.to_string(), json_mode);
+            return false;
+        }
+    };
+
+    let mut request = cmd.clone();
+    if let Some(obj) = request.as_object_mut() {
+        obj.remove("follow");
+    }
+    let selector = cmd.get("selector").and_then(|v| v.as_str()).unwrap_or("");
+    let once = cmd.get("once").and_then(|v| v.as_bool()) == Some(true);
+
+    match conn.send(&request) {
+        Ok(resp) if resp.success => {}
+        Ok(resp) => {
+            report_error(resp.error.as_deref().unwrap_or("Unknown error"), json_mode);
+            return false;
+        }
+        Err(e) => {
+            report_error(&e.to_string(), json_mode);
+            return false;
+        }
+    }
+
+    loop {
+        let event = match conn.next_event() {
+            Ok(Some(event)) => event,
+            Ok(None) => return true,
+            Err(e) => {
+                report_error(&e.to_string(), json_mode);
+                return false;
+            }
+        };
+        let data = event.get("data").cloned().unwrap_or(Value::Null);
+        if event.get("event").and_then(|v| v.as_str()) != Some("watch_end") {
+            if json_mode {
+                format::print_item(&event);
+            } else {
+                println!("{}", format_mutation(&data));
+            }
+            continue;
+        }
+
+        let reason = data.get("reason").and_then(|v| v.as_str()).unwrap_or("");
+        let message = match reason {
+            "change" => return true,
+            "timeout" => format!("No change to {} before the timeout", selector),
+            _ => "The page navigated away, which ends the watch".to_string(),
+        };
+        if once {
+            report_error(&message, json_mode);
+            return false;
+        }
+        if json_mode {
+            format::print_item(&event);
+        } else {
+            println!("{}", color::dim(&message));
+        }
+        return true;
+    }
+}
+
+fn report_error(msg: &str, json_mode: bool) {
+    if json_mode {
+        format::print(&json!({ "success": false, "error": msg }));
+    } else {
+        eprintln!("{} {}", color::error_indicator(), msg);
+    }
+}
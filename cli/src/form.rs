@@ -0,0 +1,195 @@
+//! `fill --form <file>`: the fields to fill, from a JSON object or a flat YAML
+//! mapping (`.yaml`/`.yml`) of field to value, in the order the file lists
+//! them. Fields are selectors, refs, labels or name attributes; the daemon
+//! works out which. Values are text, or true/false for checkboxes.
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+
+/// The (field, value) pairs in `path`
+pub fn read_fields(path: &str) -> Result<Vec<(String, Value)>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let is_yaml = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+    let fields = if is_yaml {
+        parse_yaml(&text)
+    } else {
+        parse_json(&text)
+    }
+    .map_err(|e| format!("{}: {}", path, e))?;
+    if fields.is_empty() {
+        return Err(format!("{}: no fields to fill", path));
+    }
+    Ok(fields)
+}
+
+/// A JSON object's entries in file order; serde_json's own map sorts them
+struct Fields(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for Fields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = Fields;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object of field to value")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Fields, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    fields.push(entry);
+                }
+                Ok(Fields(fields))
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+fn parse_json(text: &str) -> Result<Vec<(String, Value)>, String> {
+    let Fields(fields) = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    for (field, value) in &fields {
+        if !(value.is_string() || value.is_number() || value.is_boolean()) {
+            return Err(format!(
+                "the value of \"{}\" must be a string, number or boolean",
+                field
+            ));
+        }
+    }
+    Ok(fields)
+}
+
+/// `key: value` lines. Keys and values may be quoted, which a key starting
+/// with `#` (a comment otherwise) or `@` has to be. Unquoted true and false
+/// are booleans; every other value is text, so `zip: 02134` keeps its zero.
+fn parse_yaml(text: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut fields = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" {
+            continue;
+        }
+        let error = |message: &str| format!("line {}: {}", i + 1, message);
+        if line.starts_with(char::is_whitespace) || trimmed.starts_with('-') {
+            return Err(error("only a flat mapping of field: value is supported"));
+        }
+
+        let (field, rest) = match unquote(trimmed) {
+            Some((field, rest)) => (field, rest.trim_start()),
+            None => match trimmed
+                .find(": ")
+                .or_else(|| trimmed.strip_suffix(':').map(|k| k.len()))
+            {
+                Some(colon) => (trimmed[..colon].trim_end().to_string(), &trimmed[colon..]),
+                None => return Err(error("expected field: value")),
+            },
+        };
+        let value = rest
+            .strip_prefix(':')
+            .ok_or_else(|| error("expected : after the field"))?
+            .trim();
+
+        let value = match unquote(value) {
+            Some((value, rest)) if rest.trim().is_empty() || rest.trim().starts_with('#') => {
+                Value::String(value)
+            }
+            Some(_) => return Err(error("unexpected text after the quoted value")),
+            None => {
+                // A comment needs a space before it, as in `url: https://x.test/#top`
+                let value = value.split(" #").next().unwrap_or("").trim_end();
+                match value {
+                    "true" | "True" | "TRUE" => Value::Bool(true),
+                    "false" | "False" | "FALSE" => Value::Bool(false),
+                    _ => Value::String(value.to_string()),
+                }
+            }
+        };
+        fields.push((field, value));
+    }
+    Ok(fields)
+}
+
+/// The quoted string `text` starts with and what follows it, or None if it
+/// doesn't start with a quote. Double quotes take `\"` and `\\`; single
+/// quotes take `''`, as in YAML.
+fn unquote(text: &str) -> Option<(String, &str)> {
+    let quote = text.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let mut out = String::new();
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                if let Some((_, next)) = chars.next() {
+                    out.push(next);
+                }
+            }
+            '\'' if quote == '\'' && chars.peek().map(|&(_, c)| c) == Some('\'') => {
+                chars.next();
+                out.push('\'');
+            }
+            c if c == quote => return Some((out, &text[i + 1..])),
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_keeps_file_order() {
+        let fields = parse_json(
+            r##"{"#name": "Ada", "Email": "ada@example.com", "Age": 36, "Subscribe": true}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                ("#name".to_string(), json!("Ada")),
+                ("Email".to_string(), json!("ada@example.com")),
+                ("Age".to_string(), json!(36)),
+                ("Subscribe".to_string(), json!(true)),
+            ]
+        );
+        assert!(parse_json(r#"{"Tags": ["a", "b"]}"#).is_err());
+        assert!(parse_json(r#"["Email"]"#).is_err());
+    }
+
+    #[test]
+    fn test_yaml() {
+        let yaml = "---\n# Sign-up form\n\"#name\": Ada Lovelace\nEmail: ada@example.com  # work\n\
+                    zip: 02134\nSubscribe: true\n'@e3': 'it''s me'\nNotes:\n";
+        assert_eq!(
+            parse_yaml(yaml).unwrap(),
+            vec![
+                ("#name".to_string(), json!("Ada Lovelace")),
+                ("Email".to_string(), json!("ada@example.com")),
+                ("zip".to_string(), json!("02134")),
+                ("Subscribe".to_string(), json!(true)),
+                ("@e3".to_string(), json!("it's me")),
+                ("Notes".to_string(), json!("")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_yaml_errors() {
+        assert!(parse_yaml("address:\n  street: Main St\n")
+            .unwrap_err()
+            .starts_with("line 2:"));
+        assert!(parse_yaml("- Email\n").is_err());
+        assert!(parse_yaml("Email ada@example.com\n").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! Output formats for structured results (`--format json|jsonl|yaml|plain`).
+//!
+//! `plain` is the human-readable output. The structured formats print the
+//! same documents `--json` always has: `json` as one compact line, `jsonl`
+//! the same but with commands that produce several results (`run`,
+//! `session each`, `--follow`) printing each one on its own line as soon as
+//! it is ready, and `yaml` for reading them without jq.
+//!
+//! A `--filter` path prints only the part of each document it selects.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::filter::Filter;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Plain,
+    Json,
+    Jsonl,
+    Yaml,
+}
+
+pub const FORMATS: &[&str] = &["plain", "json", "jsonl", "yaml"];
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format, String> {
+        match name {
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "jsonl" | "ndjson" => Ok(Format::Jsonl),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            _ => Err(format!(
+                "Unknown format: {} (expected {})",
+                name,
+                FORMATS.join(", ")
+            )),
+        }
+    }
+}
+
+static FORMAT: OnceLock<Format> = OnceLock::new();
+static FILTER: OnceLock<Filter> = OnceLock::new();
+
+/// Choose the format for the rest of the process; main calls this once
+pub fn set(format: Format) {
+    let _ = FORMAT.set(format);
+}
+
+/// The format chosen with [`set`], plain if none was
+pub fn current() -> Format {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Print only what `filter` selects from now on; main calls this once
+pub fn set_filter(filter: Filter) {
+    let _ = FILTER.set(filter);
+}
+
+/// Whether multi-result commands print each result as it arrives
+pub fn is_streaming() -> bool {
+    current() == Format::Jsonl
+}
+
+/// Print one structured document: YAML with `--format yaml`, otherwise a
+/// line of JSON. With a filter only the selected value is printed, `null`
+/// when there is none, and in the plain format a string as raw text (like
+/// `jq -r`). Failures are printed whole so their error isn't filtered away.
+pub fn print(value: &impl Serialize) {
+    print!("{}", render(value));
+}
+
+/// The text [`print`] prints for `value`, ending in a newline
+pub fn render(value: &impl Serialize) -> String {
+    let filter = FILTER.get();
+    // Serialized directly, structs keep their field order
+    if filter.is_none() && current() != Format::Yaml {
+        return format!("{}\n", serde_json::to_string(value).unwrap_or_default());
+    }
+
+    let mut value = serde_json::to_value(value).unwrap_or_default();
+    if let Some(filter) = filter {
+        if value.get("success") != Some(&Value::Bool(false)) {
+            value = filter.select(&value).cloned().unwrap_or_default();
+        }
+    }
+    match (current(), &value) {
+        (Format::Yaml, _) => to_yaml(&value),
+        (Format::Plain, Value::String(text)) => format!("{}\n", text),
+        _ => format!("{}\n", value),
+    }
+}
+
+/// Print one document of a stream (`--follow`); YAML documents are
+/// separated by `---`
+pub fn print_item(value: &impl Serialize) {
+    if current() == Format::Yaml {
+        println!("---");
+    }
+    print(value);
+}
+
+/// `value` as a block-style YAML document
+pub fn to_yaml(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(map) if !map.is_empty() => write_object(&mut out, map, 0, false),
+        Value::Array(items) if !items.is_empty() => write_array(&mut out, items, 0, false),
+        _ => {
+            write_scalar(&mut out, value, 2);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// `map` at `indent`. In a list item (`inline`) the first key goes on the
+/// line with the `- `.
+fn write_object(
+    out: &mut String,
+    map: &serde_json::Map<String, Value>,
+    indent: usize,
+    inline: bool,
+) {
+    for (i, (key, value)) in map.iter().enumerate() {
+        if i > 0 || !inline {
+            push_indent(out, indent);
+        }
+        out.push_str(&scalar_string(key));
+        out.push(':');
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                out.push('\n');
+                write_object(out, map, indent + 2, false);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                out.push('\n');
+                write_array(out, items, indent + 2, false);
+            }
+            _ => {
+                out.push(' ');
+                write_scalar(out, value, indent + 2);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn write_array(out: &mut String, items: &[Value], indent: usize, inline: bool) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 || !inline {
+            push_indent(out, indent);
+        }
+        out.push_str("- ");
+        match item {
+            Value::Object(map) if !map.is_empty() => write_object(out, map, indent + 2, true),
+            Value::Array(items) if !items.is_empty() => write_array(out, items, indent + 2, true),
+            _ => {
+                write_scalar(out, item, indent + 2);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    out.push_str(&" ".repeat(indent));
+}
+
+/// A scalar (or empty collection) without its trailing newline. Multi-line
+/// strings such as snapshots become literal blocks indented by `indent`.
+fn write_scalar(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::String(s) if is_block(s) => {
+            out.push_str(if s.ends_with('\n') { "|" } else { "|-" });
+            for line in s.trim_end_matches('\n').split('\n') {
+                out.push('\n');
+                if !line.is_empty() {
+                    push_indent(out, indent);
+                    out.push_str(line);
+                }
+            }
+        }
+        Value::String(s) => out.push_str(&scalar_string(s)),
+        Value::Object(_) => out.push_str("{}"),
+        Value::Array(_) => out.push_str("[]"),
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+/// Whether `s` can be written as a literal block and read back unchanged
+fn is_block(s: &str) -> bool {
+    let first_line = s.split('\n').find(|line| !line.is_empty()).unwrap_or("");
+    s.contains('\n')
+        && !first_line.is_empty()
+        && !first_line.starts_with(' ')
+        && !s.ends_with("\n\n")
+        && !s.chars().any(|c| c.is_control() && c != '\n' && c != '\t')
+}
+
+/// `s` unquoted when YAML would read it back as the same string, otherwise
+/// double-quoted (JSON's string escapes are valid YAML)
+fn scalar_string(s: &str) -> String {
+    if is_plain(s) {
+        s.to_string()
+    } else {
+        Value::String(s.to_string()).to_string()
+    }
+}
+
+fn is_plain(s: &str) -> bool {
+    const RESERVED: &[&str] = &[
+        "~", "null", "true", "false", "yes", "no", "on", "off", "y", "n", ".inf", "-.inf", "+.inf",
+        ".nan",
+    ];
+    let Some(first) = s.chars().next() else {
+        return false;
+    };
+    let lower = s.to_ascii_lowercase();
+    !first.is_whitespace()
+        && !s.ends_with(char::is_whitespace)
+        && !"-?:,[]{}#&*!|>'\"%@`".contains(first)
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.ends_with(':')
+        && !s.chars().any(char::is_control)
+        && !RESERVED.contains(&lower.as_str())
+        && s.parse::<f64>().is_err()
+        && !lower.starts_with("0x")
+        && !lower.starts_with("0o")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Format::parse("jsonl"), Ok(Format::Jsonl));
+        assert_eq!(Format::parse("yml"), Ok(Format::Yaml));
+        assert_eq!(
+            Format::parse("xml").unwrap_err(),
+            "Unknown format: xml (expected plain, json, jsonl, yaml)"
+        );
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        let value = json!({
+            "success": true,
+            "data": {
+                "snapshot": "- heading \"Example\" [ref=e1]\n- link \"More\" [ref=e2]",
+                "refs": { "e1": { "role": "heading", "name": "Example" } },
+                "tabs": [{ "index": 0, "url": "https://example.com" }, { "index": 1 }],
+                "empty": [],
+            },
+            "error": null,
+        });
+        // serde_json sorts object keys
+        let expected = r#"data:
+  empty: []
+  refs:
+    e1:
+      name: Example
+      role: heading
+  snapshot: |-
+    - heading "Example" [ref=e1]
+    - link "More" [ref=e2]
+  tabs:
+    - index: 0
+      url: https://example.com
+    - index: 1
+error: null
+success: true
+"#;
+        assert_eq!(to_yaml(&value), expected);
+        assert_eq!(to_yaml(&json!([[1, 2], "x"])), "- - 1\n  - 2\n- x\n");
+        assert_eq!(to_yaml(&json!("a\nb\n")), "|\n  a\n  b\n");
+    }
+
+    #[test]
+    fn test_scalar_string() {
+        assert_eq!(scalar_string("Example Domain"), "Example Domain");
+        assert_eq!(scalar_string("true"), "\"true\"");
+        assert_eq!(scalar_string("42"), "\"42\"");
+        assert_eq!(scalar_string(""), "\"\"");
+        assert_eq!(scalar_string("- item"), "\"- item\"");
+        assert_eq!(scalar_string("key: value"), "\"key: value\"");
+        assert_eq!(scalar_string("tab\there"), "\"tab\\there\"");
+        assert_eq!(scalar_string(" padded"), "\" padded\"");
+    }
+}
@@ -1,7 +1,11 @@
 use crate::color;
+use crate::connection;
 use std::process::{exit, Command, Stdio};
 
-pub fn run_install(with_deps: bool) {
+/// Browsers Playwright can download for the daemon
+const BROWSERS: [&str; 3] = ["chromium", "firefox", "webkit"];
+
+pub fn run_install(with_deps: bool, browsers: &[&str]) {
     let is_linux = cfg!(target_os = "linux");
 
     if is_linux {
@@ -136,60 +140,69 @@ pub fn run_install(with_deps: bool) {
                 color::warning_indicator()
             );
             println!("  agent-browser install --with-deps");
-            println!("  or: bunx --bun playwright install-deps chromium");
+            println!(
+                "  or: bunx --bun playwright install-deps {}",
+                browsers.join(" ")
+            );
             println!();
         }
     }
 
-    println!("{}", color::cyan("Installing Chromium browser..."));
+    println!(
+        "{}",
+        color::cyan(&format!("Installing {}...", browsers.join(", ")))
+    );
 
-    // On Windows, we need to use cmd.exe to run npx because npx is actually npx.cmd
-    // and Command::new() doesn't resolve .cmd files the way the shell does.
-    // Pass the entire command as a single string to /c to handle paths with spaces.
-    #[cfg(windows)]
-    let status = Command::new("cmd")
-        .args(["/c", "bunx --bun playwright install chromium"])
-        .status();
-
-    #[cfg(not(windows))]
-    let status = Command::new("bun")
-        .args(["x", "--bun", "playwright", "install", "chromium"])
-        .status();
+    // The daemon's own entry point downloads the browsers, so they match the
+    // Playwright it launches them with, and then checks that each one starts
+    let status = connection::daemon_command().and_then(|mut cmd| {
+        cmd.env("AGENT_BROWSER_INSTALL", browsers.join(","))
+            .status()
+            .map_err(|e| format!("Failed to run the installer: {}", e))
+    });
 
     match status {
-        Ok(s) if s.success() => {
-            println!(
-                "{} Chromium installed successfully",
-                color::success_indicator()
-            );
-            if is_linux && !with_deps {
-                println!();
-                println!(
-                    "{} If you see \"shared library\" errors when running, use:",
-                    color::yellow("Note:")
-                );
-                println!("  agent-browser install --with-deps");
-            }
-        }
+        // The installer has already reported each browser as ready
+        Ok(s) if s.success() => {}
         Ok(_) => {
             eprintln!("{} Failed to install browser", color::error_indicator());
-            if is_linux {
+            if is_linux && !with_deps {
                 println!(
-                    "{} Try installing system dependencies first:",
+                    "{} If a browser fails to launch, install its system dependencies:",
                     color::yellow("Tip:")
                 );
-                println!("  agent-browser install --with-deps");
+                println!("  agent-browser install --with-deps {}", browsers.join(" "));
             }
             exit(1);
         }
         Err(e) => {
-            eprintln!("{} Failed to run bunx: {}", color::error_indicator(), e);
-            eprintln!("Make sure Bun is installed and in your PATH");
+            eprintln!("{} {}", color::error_indicator(), e);
             exit(1);
         }
     }
 }
 
+/// Browser names from `install [chromium|firefox|webkit]...`, Chromium when none are given
+pub fn parse_browsers(args: &[String]) -> Result<Vec<&str>, String> {
+    let mut browsers = Vec::new();
+    for arg in args.iter().filter(|a| !a.starts_with('-')) {
+        if !BROWSERS.contains(&arg.as_str()) {
+            return Err(format!(
+                "Unknown browser: {} (expected {})",
+                arg,
+                BROWSERS.join(", ")
+            ));
+        }
+        if !browsers.contains(&arg.as_str()) {
+            browsers.push(arg.as_str());
+        }
+    }
+    if browsers.is_empty() {
+        browsers.push("chromium");
+    }
+    Ok(browsers)
+}
+
 fn which_exists(cmd: &str) -> bool {
     #[cfg(unix)]
     {
@@ -223,3 +236,26 @@ fn package_exists_apt(pkg: &str) -> bool {
         .map(|s| s.success())
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_browsers() {
+        assert_eq!(parse_browsers(&args(&[])).unwrap(), vec!["chromium"]);
+        assert_eq!(
+            parse_browsers(&args(&["firefox", "--with-deps", "webkit", "firefox"])).unwrap(),
+            vec!["firefox", "webkit"]
+        );
+        let err = parse_browsers(&args(&["chrome"])).unwrap_err();
+        assert_eq!(
+            err,
+            "Unknown browser: chrome (expected chromium, firefox, webkit)"
+        );
+    }
+}
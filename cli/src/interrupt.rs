@@ -0,0 +1,50 @@
+//! Ctrl+C while waiting on the daemon: cancel the pending command before
+//! exiting, so the browser isn't left running it.
+
+use std::process::exit;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use agent_browser_client::{Command, DaemonConnection};
+
+use crate::commands::gen_id;
+
+/// Session and id of the command we are waiting on
+static IN_FLIGHT: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// How long Ctrl+C waits for the daemon to acknowledge the cancel
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Handle Ctrl+C from here on. Without a command in flight it just exits.
+pub fn install() {
+    let _ = ctrlc::set_handler(on_interrupt);
+}
+
+/// Mark `cmd` as the command Ctrl+C should cancel
+pub fn set_in_flight(session: &str, cmd: &serde_json::Value) {
+    let id = cmd.get("id").and_then(|v| v.as_str()).map(str::to_string);
+    *IN_FLIGHT.lock().unwrap() = id.map(|id| (session.to_string(), id));
+}
+
+/// Clear the command set by [`set_in_flight`] once it has been answered
+pub fn clear() {
+    *IN_FLIGHT.lock().unwrap() = None;
+}
+
+fn on_interrupt() {
+    let pending = IN_FLIGHT.lock().unwrap().take();
+    if let Some((session, id)) = pending {
+        // Off this thread, so a hung daemon can't keep us from exiting
+        let (done, wait) = mpsc::channel();
+        thread::spawn(move || {
+            let cancel = Command::Cancel { command_id: id }.into_request(gen_id());
+            let _ = DaemonConnection::open(&session).and_then(|mut c| c.send(&cancel));
+            let _ = done.send(());
+        });
+        let _ = wait.recv_timeout(CANCEL_TIMEOUT);
+    }
+    // 128 + SIGINT, as the shell reports an interrupted command
+    exit(130);
+}
@@ -0,0 +1,150 @@
+//! Advisory per-session lock (`--lock`), so concurrent CLI invocations on the
+//! same session take turns instead of interleaving their commands.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Seek, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::connection::get_socket_dir;
+
+/// How long to wait for the lock unless `--lock-timeout` says otherwise
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a waiting invocation retries
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The wait from `--lock-timeout <ms>`, or the default
+pub fn parse_timeout(value: Option<&str>) -> Result<Duration, String> {
+    match value {
+        None => Ok(DEFAULT_LOCK_TIMEOUT),
+        Some(ms) => ms
+            .parse()
+            .map(Duration::from_millis)
+            .map_err(|_| format!("Invalid --lock-timeout: '{}' is not a number of ms", ms)),
+    }
+}
+
+fn get_lock_path(session: &str) -> PathBuf {
+    get_socket_dir().join(format!("{}.lock", session))
+}
+
+/// Holds the session's lock until dropped. The OS releases it too when the
+/// process exits, so a crashed invocation never leaves a session locked.
+pub struct SessionLock {
+    _file: File,
+}
+
+/// Take the lock on `session`, waiting up to `timeout` for whoever holds it
+pub fn acquire(session: &str, timeout: Duration) -> Result<SessionLock, String> {
+    let path = get_lock_path(session);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create socket directory: {}", e))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(TryLockError::WouldBlock) => {
+                let holder = fs::read_to_string(&path).unwrap_or_default();
+                let holder = match holder.trim() {
+                    "" => String::new(),
+                    pid => format!(" (held by pid {})", pid),
+                };
+                return Err(format!(
+                    "Timed out after {}ms waiting for the lock on session '{}'{}",
+                    timeout.as_millis(),
+                    session,
+                    holder
+                ));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(format!("Failed to lock {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    // Record the holder for the error message of whoever waits next
+    let _ = file.set_len(0);
+    let _ = file.rewind();
+    let _ = write!(file, "{}", std::process::id());
+    Ok(SessionLock { _file: file })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::sync::{Mutex, MutexGuard};
+
+    // Mutex to prevent parallel tests from interfering with env vars
+    static ENV_MUTEX: Mutex<()> = Mutex::new(());
+
+    /// RAII guard that locks env mutex and restores env vars on drop
+    struct EnvGuard<'a> {
+        _lock: MutexGuard<'a, ()>,
+        vars: Vec<(String, Option<String>)>,
+    }
+
+    impl<'a> EnvGuard<'a> {
+        fn new(var_names: &[&str]) -> Self {
+            let lock = ENV_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+            let vars = var_names
+                .iter()
+                .map(|&name| (name.to_string(), env::var(name).ok()))
+                .collect();
+            Self { _lock: lock, vars }
+        }
+    }
+
+    impl Drop for EnvGuard<'_> {
+        fn drop(&mut self) {
+            for (name, value) in &self.vars {
+                match value {
+                    Some(v) => env::set_var(name, v),
+                    None => env::remove_var(name),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout(None).unwrap(), DEFAULT_LOCK_TIMEOUT);
+        assert_eq!(
+            parse_timeout(Some("250")).unwrap(),
+            Duration::from_millis(250)
+        );
+        assert!(parse_timeout(Some("soon")).is_err());
+    }
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR"]);
+        let dir = env::temp_dir().join(format!("agent-browser-lock-{}", std::process::id()));
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", &dir);
+
+        let held = acquire("work", DEFAULT_LOCK_TIMEOUT).unwrap();
+        let err = acquire("work", Duration::from_millis(100)).err().unwrap();
+        assert!(err.contains("waiting for the lock on session 'work'"));
+        assert!(err.contains(&format!("held by pid {}", std::process::id())));
+        // Other sessions are unaffected
+        drop(acquire("other", Duration::ZERO).unwrap());
+
+        drop(held);
+        drop(acquire("work", Duration::ZERO).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
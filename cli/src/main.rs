@@ -1,28 +1,61 @@
+mod alias;
+mod auth;
+mod bench;
+mod broadcast;
 mod color;
 mod commands;
+mod completions;
+mod config;
 mod connection;
+mod diff;
+mod doctor;
+mod dry_run;
+mod embedded;
+mod exit_code;
+mod filter;
 mod flags;
+mod follow;
+mod form;
+mod format;
 mod install;
+mod interrupt;
+mod lock;
+mod mcp;
 mod output;
+mod pipe;
+mod plugins;
+mod profiles;
+mod runtime;
+mod script;
+mod serve;
+mod spec;
+mod template;
+mod upgrade;
+mod waiting;
 
 use serde_json::json;
 use std::env;
 use std::fs;
 use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[cfg(unix)]
 use libc;
 
-#[cfg(windows)]
-use windows_sys::Win32::Foundation::CloseHandle;
-#[cfg(windows)]
-use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
-
-use commands::{gen_id, parse_command, ParseError};
-use connection::{ensure_daemon, get_socket_dir, send_command};
-use flags::{clean_args, parse_flags};
+use agent_browser_client::protocol::PROTOCOL_VERSION;
+use commands::{gen_id, parse_command, parse_duration, secret_value, ParseError};
+use connection::{
+    ensure_daemon, is_daemon_running, live_sessions, send_command, send_command_timed,
+    DaemonResult, ErrorCode, Handshake, Response, Timings,
+};
+use flags::{clean_args, parse_flags, validate_session, Flags};
+use format::Format;
 use install::run_install;
-use output::{print_command_help, print_help, print_response, print_version};
+use output::{
+    print_command_help, print_help, print_image_data, print_response, print_timings,
+    print_transfer_progress, print_version, write_response,
+};
 
 fn parse_proxy(proxy_str: &str) -> serde_json::Value {
     let Some(protocol_end) = proxy_str.find("://") else {
@@ -54,53 +87,168 @@ fn parse_proxy(proxy_str: &str) -> serde_json::Value {
     })
 }
 
+/// Resolve a --profile directory to an absolute path (so a daemon started from another
+/// directory sees the same one), expanding `~` and creating it if it does not exist yet.
+fn resolve_profile(path: &str) -> Result<String, String> {
+    let expanded = match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => std::path::PathBuf::from(path),
+    };
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        env::current_dir()
+            .map_err(|e| format!("Cannot resolve profile path {}: {}", path, e))?
+            .join(expanded)
+    };
+
+    if absolute.exists() && !absolute.is_dir() {
+        return Err(format!(
+            "Profile path is not a directory: {}",
+            absolute.display()
+        ));
+    }
+    fs::create_dir_all(&absolute).map_err(|e| {
+        format!(
+            "Cannot create profile directory {}: {}",
+            absolute.display(),
+            e
+        )
+    })?;
+    Ok(absolute.to_string_lossy().to_string())
+}
+
+/// Connect to the session's daemon, starting one with the launch options in
+/// `flags` if none is running
+fn start_daemon_for(flags: &Flags) -> Result<DaemonResult, String> {
+    ensure_daemon(
+        &flags.session,
+        flags.headed,
+        flags.browser.as_deref(),
+        flags.executable_path.as_deref(),
+        &flags.extensions,
+        flags.args.as_deref(),
+        flags.user_agent.as_deref(),
+        flags.proxy.as_deref(),
+        flags.proxy_bypass.as_deref(),
+        flags.ignore_https_errors,
+        flags.allow_file_access,
+        flags.profile.as_deref(),
+        flags.state.as_deref(),
+        flags.provider.as_deref(),
+        flags.device.as_deref(),
+    )
+}
+
+/// Report that no daemon can run the command, and exit
+fn daemon_unavailable(error: &str, flags: &Flags) -> ! {
+    let resp = Response {
+        success: false,
+        error: Some(error.to_string()),
+        error_code: Some(ErrorCode::DaemonUnavailable),
+        ..Default::default()
+    };
+    print_response(&resp, flags.json, None);
+    print_daemon_log(flags);
+    exit(exit_code::DAEMON_UNAVAILABLE);
+}
+
+/// How long `close` waits for the daemon to exit
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The daemon a `close` request stops, to wait for once it has answered:
+/// none for `close --keep-daemon`, or for a remote daemon
+fn daemon_to_stop(cmd: &serde_json::Value, session: &str) -> Option<u32> {
+    let action = cmd.get("action").and_then(|v| v.as_str());
+    if action != Some("close")
+        || cmd.get("keepDaemon").is_some()
+        || env::var_os("AGENT_BROWSER_REMOTE").is_some()
+    {
+        return None;
+    }
+    connection::daemon_pid(session)
+}
+
+/// Lines of the daemon's log `--show-daemon-log` prints
+const DAEMON_LOG_LINES: usize = 20;
+
+/// With --show-daemon-log, the end of the daemon's log on stderr, which often
+/// says why a command failed when the error itself can't (a browser that
+/// didn't launch, a daemon that crashed mid-command)
+fn print_daemon_log(flags: &Flags) {
+    if !flags.show_daemon_log || env::var_os("AGENT_BROWSER_REMOTE").is_some() {
+        return;
+    }
+    let lines = connection::daemon_log_tail(&flags.session, DAEMON_LOG_LINES);
+    let path = connection::get_log_path(&flags.session)
+        .display()
+        .to_string();
+    if lines.is_empty() {
+        eprintln!("{}", color::dim(&format!("Daemon log {} is empty", path)));
+        return;
+    }
+    eprintln!("{}", color::dim(&format!("Daemon log ({}):", path)));
+    for line in lines {
+        eprintln!("  {}", color::dim(&line));
+    }
+}
+
+/// `d` in milliseconds, to a tenth
+fn millis(d: Duration) -> f64 {
+    (d.as_secs_f64() * 10_000.0).round() / 10.0
+}
+
+/// The `--timings` breakdown of a command that spent `spawn` making sure a
+/// daemon runs and `round_trip` on its request, `connect` of that opening the
+/// socket. The daemon reports its own share; without it (an older daemon)
+/// the transfer can't be told apart from it.
+fn timings(
+    reported: Option<Timings>,
+    spawn: Duration,
+    connect: Duration,
+    round_trip: Duration,
+    started: Instant,
+) -> Timings {
+    let daemon = reported.and_then(|t| t.daemon);
+    let exchange = millis(round_trip.saturating_sub(connect));
+    Timings {
+        spawn: Some(millis(spawn)),
+        connect: Some(millis(connect)),
+        daemon,
+        transfer: daemon.map(|daemon| ((exchange - daemon).max(0.0) * 10.0).round() / 10.0),
+        total: Some(millis(started.elapsed())),
+    }
+}
+
+/// `is visible|enabled|checked` answer through the exit code as well as
+/// stdout, so a false result is reported as a failure. So does an `audit`
+/// that found violations at or above its `--fail-on` level.
+fn predicate_failed(action: Option<&str>, resp: &Response) -> bool {
+    let key = match action {
+        Some("isvisible") => "visible",
+        Some("isenabled") => "enabled",
+        Some("ischecked") => "checked",
+        Some("audit") => "passed",
+        _ => return false,
+    };
+    resp.data
+        .as_ref()
+        .and_then(|d| d.get(key))
+        .and_then(|v| v.as_bool())
+        == Some(false)
+}
+
 fn run_session(args: &[String], session: &str, json_mode: bool) {
     let subcommand = args.get(1).map(|s| s.as_str());
 
     match subcommand {
         Some("list") => {
-            let socket_dir = get_socket_dir();
-            let mut sessions: Vec<String> = Vec::new();
-
-            if let Ok(entries) = fs::read_dir(&socket_dir) {
-                for entry in entries.flatten() {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    // Look for pid files in socket directory
-                    if name.ends_with(".pid") {
-                        let session_name = name.strip_suffix(".pid").unwrap_or("");
-                        if !session_name.is_empty() {
-                            // Check if session is actually running
-                            let pid_path = socket_dir.join(&name);
-                            if let Ok(pid_str) = fs::read_to_string(&pid_path) {
-                                if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                                    #[cfg(unix)]
-                                    let running = unsafe { libc::kill(pid as i32, 0) == 0 };
-                                    #[cfg(windows)]
-                                    let running = unsafe {
-                                        let handle =
-                                            OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
-                                        if handle != 0 {
-                                            CloseHandle(handle);
-                                            true
-                                        } else {
-                                            false
-                                        }
-                                    };
-                                    if running {
-                                        sessions.push(session_name.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+            let sessions = live_sessions();
 
             if json_mode {
-                println!(
-                    r#"{{"success":true,"data":{{"sessions":{}}}}}"#,
-                    serde_json::to_string(&sessions).unwrap_or_default()
-                );
+                format::print(&json!({ "success": true, "data": { "sessions": sessions } }));
             } else if sessions.is_empty() {
                 println!("No active sessions");
             } else {
@@ -118,7 +266,7 @@ fn run_session(args: &[String], session: &str, json_mode: bool) {
         None | Some(_) => {
             // Just show current session
             if json_mode {
-                println!(r#"{{"success":true,"data":{{"session":"{}"}}}}"#, session);
+                format::print(&json!({ "success": true, "data": { "session": session } }));
             } else {
                 println!("{}", session);
             }
@@ -126,17 +274,132 @@ fn run_session(args: &[String], session: &str, json_mode: bool) {
     }
 }
 
+/// CLI, protocol, daemon and browser versions. Never starts a daemon: the
+/// daemon and browser are reported as not running instead.
+fn run_version(session: &str, json_mode: bool) {
+    let cli = env!("CARGO_PKG_VERSION");
+    let running = env::var_os("AGENT_BROWSER_REMOTE").is_some() || is_daemon_running(session);
+    let handshake = running.then(|| connection::handshake(session));
+
+    let (daemon, browser) = match &handshake {
+        None => (json!({ "running": false }), None),
+        Some(Ok(Handshake::Compatible(hello))) => {
+            // Only a launched browser has a version; `status` doesn't launch one
+            let browser = hello
+                .supports("status")
+                .then(|| send_command(json!({ "id": gen_id(), "action": "status" }), session))
+                .and_then(|resp| resp.ok()?.data)
+                .and_then(|data| Some(data.get("browserVersion")?.as_str()?.to_string()));
+            let daemon = json!({
+                "running": true,
+                "version": hello.version,
+                "protocol": hello.protocol_version,
+                "compatible": true,
+            });
+            (daemon, browser)
+        }
+        Some(Ok(Handshake::Mismatch(hello))) => {
+            let daemon = json!({
+                "running": true,
+                "version": hello.version,
+                "protocol": hello.protocol_version,
+                "compatible": false,
+            });
+            (daemon, None)
+        }
+        Some(Ok(Handshake::Legacy)) => (json!({ "running": true, "compatible": false }), None),
+        Some(Err(e)) => (json!({ "running": true, "error": e.to_string() }), None),
+    };
+
+    if json_mode {
+        let data = json!({
+            "cli": cli,
+            "protocol": PROTOCOL_VERSION,
+            "daemon": daemon,
+            "browser": browser,
+        });
+        format::print(&json!({ "success": true, "data": data }));
+        return;
+    }
+
+    let browser_line = browser_line(handshake.as_ref(), browser.as_deref());
+    println!("agent-browser {}", cli);
+    println!("Protocol: {}", PROTOCOL_VERSION);
+    const RESTART: &str = "run 'agent-browser close' to restart it";
+    let daemon_line = match handshake {
+        None => color::dim("not running"),
+        Some(Ok(Handshake::Compatible(hello))) => {
+            format!("{} (protocol {})", hello.version, hello.protocol_version)
+        }
+        Some(Ok(Handshake::Mismatch(hello))) => format!(
+            "{} (protocol {}) {}",
+            hello.version,
+            hello.protocol_version,
+            color::yellow(&format!("incompatible, {}", RESTART))
+        ),
+        Some(Ok(Handshake::Legacy)) => {
+            color::yellow(&format!("older than protocol versioning, {}", RESTART))
+        }
+        Some(Err(e)) => color::yellow(&format!("not answering ({})", e)),
+    };
+    println!("Daemon: {}", daemon_line);
+    if let Some(line) = browser_line {
+        println!("Browser: {}", line);
+    }
+}
+
+/// What `version` says of the browser, given the daemon's handshake and the
+/// version it reported. Only a compatible daemon is asked, so with any other
+/// the browser is unknown rather than not launched.
+fn browser_line(
+    handshake: Option<&Result<Handshake, connection::Error>>,
+    browser: Option<&str>,
+) -> Option<String> {
+    match handshake? {
+        Ok(Handshake::Compatible(_)) => Some(match browser {
+            Some(version) => version.to_string(),
+            None => color::dim("not launched"),
+        }),
+        Ok(Handshake::Mismatch(_)) | Ok(Handshake::Legacy) => {
+            Some(color::dim("unknown, the daemon is incompatible"))
+        }
+        Err(_) => Some(color::dim("unknown, the daemon is not answering")),
+    }
+}
+
 fn main() {
+    let started = Instant::now();
     // Ignore SIGPIPE to prevent panic when piping to head/tail
     #[cfg(unix)]
     unsafe {
         libc::signal(libc::SIGPIPE, libc::SIG_DFL);
     }
 
-    let args: Vec<String> = env::args().skip(1).collect();
-    let flags = parse_flags(&args);
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Config files supply defaults for anything not set by flags or env, and command aliases.
+    // Errors are reported once the flags say how.
+    let config = config::load();
+    let aliased = match config {
+        Ok(ref config) => alias::expand(&args, &config.aliases),
+        Err(_) => Ok(None),
+    };
+    if let Ok(Some(ref expanded)) = aliased {
+        args = expanded.clone();
+    }
+
+    let mut flags = parse_flags(&args);
     let clean = clean_args(&args);
+    if flags.no_color {
+        color::disable();
+    }
+    if flags.quiet {
+        output::set_quiet();
+    }
+    if flags.verbose {
+        output::set_verbose();
+    }
 
+    // Help and the version don't depend on the config, so a broken one can't hide them
     let has_help = args.iter().any(|a| a == "--help" || a == "-h");
     let has_version = args.iter().any(|a| a == "--version" || a == "-V");
 
@@ -150,78 +413,440 @@ fn main() {
         return;
     }
 
+    // `help <command>` is `<command> --help`
+    if clean.first().map(|s| s.as_str()) == Some("help") {
+        match clean.get(1) {
+            None => print_help(),
+            Some(cmd) if print_command_help(cmd) => {}
+            Some(cmd) => {
+                eprintln!("{} Unknown command: {}", color::error_indicator(), cmd);
+                eprintln!("Run 'agent-browser help' for the list of commands");
+                exit(1);
+            }
+        }
+        return;
+    }
+
     if has_version {
         print_version();
         return;
     }
 
+    match config {
+        Ok(config) => {
+            for ignored in &config.ignored {
+                eprintln!("{} {}", color::warning_indicator(), ignored);
+            }
+            config.apply(&mut flags)
+        }
+        Err(e) => {
+            eprintln!("{} {}", color::error_indicator(), e);
+            exit(1);
+        }
+    }
+
+    if let Err(e) = validate_session(&flags.session) {
+        eprintln!("{} {}", color::error_indicator(), e);
+        exit(1);
+    }
+
+    // --json is --format json; a --format given as well wins
+    let format = match flags.format.as_deref().map(Format::parse) {
+        Some(Ok(format)) => format,
+        Some(Err(e)) => {
+            eprintln!("{} {}", color::error_indicator(), e);
+            exit(1);
+        }
+        None if flags.json => Format::Json,
+        None => Format::Plain,
+    };
+    flags.json = format != Format::Plain;
+    format::set(format);
+    // A filter applies to the structured result, whatever the format
+    if let Some(ref path) = flags.filter {
+        match filter::Filter::parse(path) {
+            Ok(filter) => format::set_filter(filter),
+            Err(e) => {
+                eprintln!("{} {}", color::error_indicator(), e);
+                exit(1);
+            }
+        }
+        flags.json = true;
+    }
+    // Reported once the format is known, so --json gets its error as JSON
+    if let Err(e) = aliased {
+        if flags.json {
+            format::print(&json!({ "success": false, "error": e.replace('\n', " ") }));
+        } else {
+            eprintln!("{} {}", color::error_indicator(), e);
+        }
+        exit(1);
+    }
+
+    // The client connects wherever AGENT_BROWSER_REMOTE points, so --remote overrides it
+    if let Some(ref remote) = flags.remote {
+        env::set_var("AGENT_BROWSER_REMOTE", remote);
+    }
+    // Likewise for the socket path, which a spawned daemon inherits
+    if let Some(ref socket) = flags.socket {
+        env::set_var("AGENT_BROWSER_SOCKET", socket);
+    }
+    // And for the runtime daemons (and `install`) are started with
+    if let Some(ref runtime) = flags.runtime {
+        env::set_var("AGENT_BROWSER_RUNTIME", runtime);
+    }
+    // A daemon started now reads its limits from the environment, in milliseconds
+    let limits = [
+        ("AGENT_BROWSER_IDLE_TIMEOUT", &flags.idle_timeout),
+        ("AGENT_BROWSER_MAX_LIFETIME", &flags.max_lifetime),
+    ];
+    for (var, value) in limits {
+        let Some(value) = value else { continue };
+        match parse_duration(value) {
+            Some(ms) => env::set_var(var, ms.to_string()),
+            None => {
+                eprintln!(
+                    "{} Invalid time limit: {} (expected e.g. 90s, 10m, 2h or off)",
+                    color::error_indicator(),
+                    value
+                );
+                exit(1);
+            }
+        }
+    }
+    // The client spaces out the commands it sends by these, in every mode
+    if let Some(ref ms) = flags.min_delay {
+        if ms.parse::<u64>().is_err() {
+            eprintln!(
+                "{} Invalid --min-delay: {} (expected milliseconds)",
+                color::error_indicator(),
+                ms
+            );
+            exit(1);
+        }
+        env::set_var("AGENT_BROWSER_MIN_DELAY", ms);
+    }
+    if let Some(ref rps) = flags.max_rps {
+        if !rps.parse::<f64>().is_ok_and(|n| n > 0.0 && n.is_finite()) {
+            eprintln!(
+                "{} Invalid --max-rps: {} (expected a number above 0)",
+                color::error_indicator(),
+                rps
+            );
+            exit(1);
+        }
+        env::set_var("AGENT_BROWSER_MAX_RPS", rps);
+    }
+
+    if args.iter().any(|a| a == "--dump-schema") {
+        let schema = agent_browser_client::protocol::schema();
+        println!("{:#}", schema);
+        return;
+    }
+
     if clean.is_empty() {
         print_help();
         return;
     }
 
+    // The commands main runs itself skip parse_command, so check their flags here
+    if let Some(command) = clean.first().filter(|c| {
+        matches!(
+            c.as_str(),
+            "install" | "doctor" | "bench" | "version" | "completions"
+        )
+    }) {
+        if let Some(flag) = spec::unknown_flag(command, &clean) {
+            let e = ParseError::UnknownFlag {
+                flag: flag.to_string(),
+                command: command.clone(),
+                valid_options: spec::flags(command).unwrap_or_default(),
+            };
+            if flags.json {
+                format::print(
+                    &json!({ "success": false, "error": e.format(), "type": "unknown_flag" }),
+                );
+            } else {
+                eprintln!("{}", color::red(&e.format()));
+            }
+            exit(1);
+        }
+    }
+
+    // Only commands for the daemon have a request to print
+    let daemon_upgrade = clean.first().map(|s| s.as_str()) == Some("daemon")
+        && clean.get(1).map(|s| s.as_str()) == Some("upgrade");
+    let runs_itself = daemon_upgrade
+        || matches!(
+            clean.first().map(|s| s.as_str()),
+            Some(
+                "install"
+                    | "doctor"
+                    | "bench"
+                    | "completions"
+                    | "version"
+                    | "session"
+                    | "profile"
+                    | "auth"
+                    | "mcp"
+                    | "serve"
+                    | "pipe"
+            )
+        );
+    if flags.dry_run && (runs_itself || flags.all_sessions) {
+        let used = if flags.all_sessions {
+            "--all-sessions"
+        } else {
+            clean[0].as_str()
+        };
+        let e = format!("--dry-run can't be used with {}", used);
+        if flags.json {
+            format::print(&json!({ "success": false, "error": e }));
+        } else {
+            eprintln!("{} {}", color::error_indicator(), e);
+        }
+        exit(1);
+    }
+
     // Handle install separately
     if clean.get(0).map(|s| s.as_str()) == Some("install") {
         let with_deps = args.iter().any(|a| a == "--with-deps" || a == "-d");
-        run_install(with_deps);
+        match install::parse_browsers(&clean[1..]) {
+            Ok(browsers) => run_install(with_deps, &browsers),
+            Err(e) => {
+                eprintln!("{} {}", color::error_indicator(), e);
+                exit(1);
+            }
+        }
+        return;
+    }
+
+    // Doctor inspects the setup itself, so it must work when no daemon can start
+    if clean.first().map(|s| s.as_str()) == Some("doctor") {
+        doctor::run_doctor(&flags, clean.iter().any(|a| a == "--fix"));
+        return;
+    }
+
+    // Bench measures the daemon from outside, with its own connections
+    if clean.first().map(|s| s.as_str()) == Some("bench") {
+        bench::run_bench(&clean[1..], &flags);
+        return;
+    }
+
+    if clean.first().map(|s| s.as_str()) == Some("completions") {
+        let shell = clean.get(1).map(|s| s.as_str()).unwrap_or("");
+        match completions::script(shell) {
+            Ok(script) => print!("{}", script),
+            Err(e) => {
+                eprintln!("{} {}", color::error_indicator(), e);
+                eprintln!("Usage: agent-browser completions <bash|zsh|fish>");
+                exit(1);
+            }
+        }
+        return;
+    }
+
+    if clean.first().map(|s| s.as_str()) == Some("version") {
+        run_version(&flags.session, flags.json);
         return;
     }
 
     // Handle session separately (doesn't need daemon)
     if clean.get(0).map(|s| s.as_str()) == Some("session") {
+        if clean.get(1).map(|s| s.as_str()) == Some("each") {
+            let rest = match clean.get(2).map(|s| s.as_str()) {
+                Some("--") => &clean[3..],
+                _ => &clean[2..],
+            };
+            if !broadcast::run(rest, &flags) {
+                exit(1);
+            }
+            return;
+        }
         run_session(&clean, &flags.session, flags.json);
         return;
     }
 
-    let cmd = match parse_command(&clean, &flags) {
-        Ok(c) => c,
-        Err(e) => {
-            if flags.json {
-                let error_type = match &e {
-                    ParseError::UnknownCommand { .. } => "unknown_command",
-                    ParseError::UnknownSubcommand { .. } => "unknown_subcommand",
-                    ParseError::MissingArguments { .. } => "missing_arguments",
-                    ParseError::InvalidValue { .. } => "invalid_value",
-                };
-                println!(
-                    r#"{{"success":false,"error":"{}","type":"{}"}}"#,
-                    e.format().replace('\n', " "),
-                    error_type
-                );
-            } else {
-                eprintln!("{}", color::red(&e.format()));
-            }
+    // Switching profiles talks to the daemon only if one is running
+    if clean.first().map(|s| s.as_str()) == Some("profile") {
+        profiles::run_profile(&clean[1..], &flags);
+        return;
+    }
+
+    // Logging in happens in a headed browser of its own, beside the session's
+    if clean.first().map(|s| s.as_str()) == Some("auth") {
+        auth::run_auth(&clean[1..], &flags);
+        return;
+    }
+
+    // Upgrading replaces the daemon, so it can't go through the one that's running
+    if daemon_upgrade {
+        upgrade::run_upgrade(&flags);
+        return;
+    }
+
+    // Broadcasts go to sessions that are already running, never start one
+    if flags.all_sessions {
+        if !broadcast::run(&clean, &flags) {
             exit(1);
         }
+        return;
+    }
+
+    // Scripts, and commands chained with ++, are parsed in full before the daemon is contacted
+    let script = if clean.first().map(|s| s.as_str()) == Some("run") {
+        match script::load(&clean[1..], &flags) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                if flags.json {
+                    format::print(&json!({ "success": false, "error": e }));
+                } else {
+                    eprintln!("{} {}", color::error_indicator(), e);
+                }
+                exit(1);
+            }
+        }
+    } else if clean.iter().any(|a| a == script::CHAIN_SEPARATOR) {
+        match script::chain(&clean, &flags) {
+            Ok(s) => s,
+            Err(e) => {
+                if flags.json {
+                    format::print(&json!({ "success": false, "error": e }));
+                } else {
+                    eprintln!("{} {}", color::error_indicator(), e);
+                }
+                exit(1);
+            }
+        }
+    } else {
+        None
     };
 
-    let daemon_result = match ensure_daemon(
-        &flags.session,
-        flags.headed,
-        flags.executable_path.as_deref(),
-        &flags.extensions,
-        flags.args.as_deref(),
-        flags.user_agent.as_deref(),
-        flags.proxy.as_deref(),
-        flags.proxy_bypass.as_deref(),
-        flags.ignore_https_errors,
-        flags.allow_file_access,
-        flags.profile.as_deref(),
-        flags.state.as_deref(),
-        flags.provider.as_deref(),
-        flags.device.as_deref(),
-    ) {
-        Ok(result) => result,
-        Err(e) => {
-            if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, e);
-            } else {
-                eprintln!("{} {}", color::error_indicator(), e);
+    // MCP tool calls, HTTP requests and piped requests are parsed one at a time while serving
+    let serve_mcp = clean.first().map(|s| s.as_str()) == Some("mcp");
+    let serve_pipe = clean.first().map(|s| s.as_str()) == Some("pipe");
+    let serve_http = if clean.first().map(|s| s.as_str()) == Some("serve") {
+        match serve::parse_options(&clean[1..]) {
+            Ok(options) => Some(options),
+            Err(e) => {
+                if flags.json {
+                    format::print(&json!({ "success": false, "error": e }));
+                } else {
+                    eprintln!("{} {}", color::error_indicator(), e);
+                }
+                exit(1);
             }
+        }
+    } else {
+        None
+    };
+
+    let mut cmd = match &script {
+        Some(_) => serde_json::Value::Null,
+        None if serve_mcp || serve_pipe || serve_http.is_some() => serde_json::Value::Null,
+        None => match parse_command(&clean, &flags) {
+            Ok(c) => c,
+            Err(e) => {
+                if flags.json {
+                    let error_type = match &e {
+                        ParseError::UnknownCommand { .. } => "unknown_command",
+                        ParseError::UnknownSubcommand { .. } => "unknown_subcommand",
+                        ParseError::MissingArguments { .. } => "missing_arguments",
+                        ParseError::InvalidValue { .. } => "invalid_value",
+                        ParseError::UnknownFlag { .. } => "unknown_flag",
+                    };
+                    format::print(&json!({
+                        "success": false,
+                        "error": e.format().replace('\n', " "),
+                        "type": error_type,
+                    }));
+                } else {
+                    eprintln!("{}", color::red(&e.format()));
+                }
+                exit(1);
+            }
+        },
+    };
+
+    // `raw` prints the daemon's answer whole, as --json would
+    let raw = clean.first().map(|s| s.as_str()) == Some("raw");
+    if raw {
+        flags.json = true;
+    }
+
+    // `screenshot -o <file>` asks for the image itself, as --stdout does
+    if flags.output.is_some()
+        && !flags.json
+        && cmd.get("action").and_then(|v| v.as_str()) == Some("screenshot")
+    {
+        cmd["stdout"] = json!(true);
+    }
+
+    if flags.dry_run {
+        if !dry_run::run(&cmd, script.as_ref(), &flags) {
             exit(1);
         }
+        return;
+    }
+
+    if let Some(ref profile) = flags.profile {
+        match resolve_profile(profile) {
+            Ok(path) => flags.profile = Some(path),
+            Err(e) => {
+                if flags.json {
+                    format::print(&json!({ "success": false, "error": e }));
+                } else {
+                    eprintln!("{} {}", color::error_indicator(), e);
+                }
+                exit(1);
+            }
+        }
+    }
+
+    // Held until we exit, so concurrent invocations with --lock take turns. Servers run
+    // until stopped and would keep everyone else waiting, so they never take it.
+    let _lock = if flags.lock == Some(true) && !serve_mcp && !serve_pipe && serve_http.is_none() {
+        let acquired = lock::parse_timeout(flags.lock_timeout.as_deref())
+            .and_then(|timeout| lock::acquire(&flags.session, timeout));
+        match acquired {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                if flags.json {
+                    format::print(&json!({ "success": false, "error": e }));
+                } else {
+                    eprintln!("{} {}", color::error_indicator(), e);
+                }
+                exit(1);
+            }
+        }
+    } else {
+        None
     };
 
+    // Asking about a daemon that isn't running shouldn't start one
+    let action = cmd.get("action").and_then(|v| v.as_str());
+    if matches!(action, Some("daemon_status") | Some("status"))
+        && env::var_os("AGENT_BROWSER_REMOTE").is_none()
+        && !is_daemon_running(&flags.session)
+    {
+        if flags.json {
+            let data = json!({ "running": false });
+            format::print(&json!({ "success": true, "data": data }));
+        } else {
+            println!("Daemon not running");
+        }
+        return;
+    }
+
+    // Also called again to replace a daemon that died under us
+    let start_daemon = || start_daemon_for(&flags);
+    let spawn_started = Instant::now();
+    let daemon_result = match start_daemon() {
+        Ok(result) => result,
+        Err(e) => daemon_unavailable(&e, &flags),
+    };
+    let mut spawn = spawn_started.elapsed();
+
     // Warn if launch-time options were explicitly passed via CLI but daemon was already running
     // Only warn about flags that were passed on the command line, not those set via environment
     // variables (since the daemon already uses the env vars when it starts).
@@ -281,29 +906,72 @@ fn main() {
                 ignored_flags.join(", ")
             );
         }
+        // Unlike the others, the limits can be changed on the running daemon
+        let changing_limits = cmd.get("action").and_then(|v| v.as_str()) == Some("daemon_config");
+        if (flags.cli_idle_timeout || flags.cli_max_lifetime) && !changing_limits && !flags.json {
+            eprintln!(
+                "{} --idle-timeout/--max-lifetime ignored: daemon already running. Use 'agent-browser daemon config' to change them.",
+                color::warning_indicator()
+            );
+        }
+    }
+
+    // A daemon started by another install can speak an older or newer protocol. One we
+    // just started came from this install, so only check those that were already running.
+    // A `raw` request is for trying out what the daemon supports, so it answers for itself.
+    if daemon_result.already_running && !raw {
+        let action = cmd.get("action").and_then(|a| a.as_str());
+        // Connection errors are left for the command itself to report
+        if let Ok(handshake) = connection::handshake(&flags.session) {
+            match handshake.check(action) {
+                Ok(Some(warning)) if !flags.json => {
+                    eprintln!("{} {}", color::warning_indicator(), warning);
+                }
+                Ok(_) => {}
+                Err(e) => daemon_unavailable(&e, &flags),
+            }
+        }
     }
 
     // Validate mutually exclusive options
     if flags.cdp.is_some() && flags.provider.is_some() {
         let msg = "Cannot use --cdp and -p/--provider together";
         if flags.json {
-            println!(r#"{{"success":false,"error":"{}"}}"#, msg);
+            format::print(&json!({ "success": false, "error": msg }));
         } else {
-            eprintln!("\x1b[31m✗\x1b[0m {}", msg);
+            eprintln!("{} {}", color::error_indicator(), msg);
         }
         exit(1);
     }
 
+    if let Some(ref browser) = flags.browser {
+        if !["chromium", "firefox", "webkit"].contains(&browser.as_str()) {
+            let msg = format!(
+                "Invalid --browser: '{}' (expected chromium, firefox or webkit)",
+                browser
+            );
+            if flags.json {
+                format::print(&json!({ "success": false, "error": msg }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), msg);
+            }
+            exit(1);
+        }
+    }
+
     if flags.provider.is_some() && !flags.extensions.is_empty() {
         let msg = "Cannot use --extension with -p/--provider (extensions require local browser)";
         if flags.json {
-            println!(r#"{{"success":false,"error":"{}"}}"#, msg);
+            format::print(&json!({ "success": false, "error": msg }));
         } else {
-            eprintln!("\x1b[31m✗\x1b[0m {}", msg);
+            eprintln!("{} {}", color::error_indicator(), msg);
         }
         exit(1);
     }
 
+    // The launch sent below, if any, so a restarted daemon can be set up the same way
+    let mut launch_request = None;
+
     // Connect via CDP if --cdp flag is set
     // Accepts either a port number (e.g., "9222") or a full URL (e.g., "ws://..." or "wss://...")
     if let Some(ref cdp_value) = flags.cdp {
@@ -324,7 +992,7 @@ fn main() {
                 Ok(p) if p == 0 => {
                     let msg = "Invalid CDP port: port must be greater than 0".to_string();
                     if flags.json {
-                        println!(r#"{{"success":false,"error":"{}"}}"#, msg);
+                        format::print(&json!({ "success": false, "error": msg }));
                     } else {
                         eprintln!("{} {}", color::error_indicator(), msg);
                     }
@@ -336,7 +1004,7 @@ fn main() {
                         p
                     );
                     if flags.json {
-                        println!(r#"{{"success":false,"error":"{}"}}"#, msg);
+                        format::print(&json!({ "success": false, "error": msg }));
                     } else {
                         eprintln!("{} {}", color::error_indicator(), msg);
                     }
@@ -349,7 +1017,7 @@ fn main() {
                         cdp_value
                     );
                     if flags.json {
-                        println!(r#"{{"success":false,"error":"{}"}}"#, msg);
+                        format::print(&json!({ "success": false, "error": msg }));
                     } else {
                         eprintln!("{} {}", color::error_indicator(), msg);
                     }
@@ -367,6 +1035,7 @@ fn main() {
             launch_cmd["ignoreHTTPSErrors"] = json!(true);
         }
 
+        launch_request = Some(launch_cmd.clone());
         let err = match send_command(launch_cmd, &flags.session) {
             Ok(resp) if resp.success => None,
             Ok(resp) => Some(
@@ -378,7 +1047,7 @@ fn main() {
 
         if let Some(msg) = err {
             if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, msg);
+                format::print(&json!({ "success": false, "error": msg }));
             } else {
                 eprintln!("{} {}", color::error_indicator(), msg);
             }
@@ -394,6 +1063,7 @@ fn main() {
             "provider": provider
         });
 
+        launch_request = Some(launch_cmd.clone());
         let err = match send_command(launch_cmd, &flags.session) {
             Ok(resp) if resp.success => None,
             Ok(resp) => Some(
@@ -405,9 +1075,9 @@ fn main() {
 
         if let Some(msg) = err {
             if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, msg);
+                format::print(&json!({ "success": false, "error": msg }));
             } else {
-                eprintln!("\x1b[31m✗\x1b[0m {}", msg);
+                eprintln!("{} {}", color::error_indicator(), msg);
             }
             exit(1);
         }
@@ -415,6 +1085,8 @@ fn main() {
 
     // Launch headed browser or configure browser options (without CDP or provider)
     if (flags.headed
+        || flags.headless
+        || flags.browser.is_some()
         || flags.profile.is_some()
         || flags.state.is_some()
         || flags.proxy.is_some()
@@ -426,14 +1098,24 @@ fn main() {
     {
         let mut launch_cmd = json!({
             "id": gen_id(),
-            "action": "launch",
-            "headless": !flags.headed
+            "action": "launch"
         });
 
         let cmd_obj = launch_cmd
             .as_object_mut()
             .expect("json! macro guarantees object type");
 
+        // A running browser only switches mode (and restarts) when --headed, --headless or
+        // --browser was passed explicitly, not for defaults from config or the environment
+        if !daemon_result.already_running || flags.cli_headed || flags.headless {
+            cmd_obj.insert("headless".to_string(), json!(!flags.headed));
+        }
+        if let Some(ref browser) = flags.browser {
+            if !daemon_result.already_running || flags.cli_browser {
+                cmd_obj.insert("browser".to_string(), json!(browser));
+            }
+        }
+
         // Add profile path if specified
         if let Some(ref profile_path) = flags.profile {
             cmd_obj.insert("profile".to_string(), json!(profile_path));
@@ -477,6 +1159,7 @@ fn main() {
             launch_cmd["allowFileAccess"] = json!(true);
         }
 
+        launch_request = Some(launch_cmd.clone());
         match send_command(launch_cmd, &flags.session) {
             Ok(resp) if !resp.success => {
                 // Launch command failed (e.g., invalid state file, profile error)
@@ -484,7 +1167,7 @@ fn main() {
                     .error
                     .unwrap_or_else(|| "Browser launch failed".to_string());
                 if flags.json {
-                    println!(r#"{{"success":false,"error":"{}"}}"#, error_msg);
+                    format::print(&json!({ "success": false, "error": error_msg }));
                 } else {
                     eprintln!("{} {}", color::error_indicator(), error_msg);
                 }
@@ -492,7 +1175,7 @@ fn main() {
             }
             Err(e) => {
                 if flags.json {
-                    println!(r#"{{"success":false,"error":"{}"}}"#, e);
+                    format::print(&json!({ "success": false, "error": e.to_string() }));
                 } else {
                     eprintln!(
                         "{} Could not configure browser: {}",
@@ -508,23 +1191,207 @@ fn main() {
         }
     }
 
-    match send_command(cmd.clone(), &flags.session) {
-        Ok(resp) => {
+    // A browser started now has none of the session's profile yet
+    if !daemon_result.already_running {
+        profiles::restore(&flags.session);
+    }
+
+    if serve_mcp {
+        if !mcp::serve(&flags) {
+            exit(1);
+        }
+        return;
+    }
+
+    if serve_pipe {
+        if !pipe::run(&flags) {
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(ref options) = serve_http {
+        if !serve::run(options, &flags) {
+            exit(1);
+        }
+        return;
+    }
+
+    // Ctrl+C from here on cancels the command being waited on
+    interrupt::install();
+
+    if let Some(ref script) = script {
+        let ok = script::execute(script, &flags);
+        profiles::save(&flags.session);
+        if !ok {
+            exit(1);
+        }
+        return;
+    }
+
+    if cmd.get("follow").and_then(|v| v.as_bool()) == Some(true) {
+        let ok = match cmd.get("action").and_then(|v| v.as_str()) {
+            Some("subscribe") => follow::events(&cmd, &flags.session, flags.json),
+            Some("watch") => follow::watch(&cmd, &flags.session, flags.json),
+            _ => follow::console(&cmd, &flags.session, flags.json),
+        };
+        if !ok {
+            exit(1);
+        }
+        return;
+    }
+
+    // `close` takes the browser state with it, so the profile is saved first
+    let closing = cmd.get("action").and_then(|v| v.as_str()) == Some("close");
+    if closing {
+        profiles::save(&flags.session);
+    }
+    let mut stopping = daemon_to_stop(&cmd, &flags.session);
+
+    interrupt::set_in_flight(&flags.session, &cmd);
+    let mut progress = |received, total| {
+        if !flags.json {
+            print_transfer_progress(received, total);
+        }
+    };
+    // `wait`, `open` and `expect` show what they're waiting for until answered
+    let waiting_for = waiting::describe(&cmd);
+    let spinner = || {
+        waiting_for
+            .as_deref()
+            .filter(|_| !flags.json && !flags.quiet)
+            .and_then(waiting::Spinner::start)
+    };
+    let mut sent = Instant::now();
+    let mut result = {
+        let _spinner = spinner();
+        send_command_timed(cmd.clone(), &flags.session, &mut progress)
+    };
+    // A daemon that died (or left a stale socket) never saw the command, so start a new
+    // one and send it again. Failures after delivery are not retried.
+    let max_restarts = connection::max_restarts();
+    let mut restarts = 0;
+    while let Err(ref e) = result {
+        if restarts == max_restarts || !connection::never_delivered(e) {
+            break;
+        }
+        thread::sleep(connection::restart_backoff(restarts));
+        restarts += 1;
+        if !flags.json {
+            eprintln!(
+                "{} Daemon not reachable ({}), restarting it ({}/{})",
+                color::warning_indicator(),
+                e,
+                restarts,
+                max_restarts
+            );
+        }
+        let restart_started = Instant::now();
+        match start_daemon() {
+            Ok(started) if !started.already_running => {
+                if let Some(ref launch) = launch_request {
+                    let _ = send_command(launch.clone(), &flags.session);
+                }
+                profiles::restore(&flags.session);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                interrupt::clear();
+                daemon_unavailable(&e, &flags);
+            }
+        }
+        spawn += restart_started.elapsed();
+        stopping = daemon_to_stop(&cmd, &flags.session);
+        sent = Instant::now();
+        let _spinner = spinner();
+        result = send_command_timed(cmd.clone(), &flags.session, &mut progress);
+    }
+    let round_trip = sent.elapsed();
+    interrupt::clear();
+    if result.is_ok() && !closing {
+        profiles::save(&flags.session);
+    }
+    // Extract action for context-specific output handling
+    let action = cmd.get("action").and_then(|v| v.as_str());
+    match result {
+        Ok((mut resp, connect)) => {
+            if let Some(secret) = secret_value(&cmd) {
+                resp.redact(secret);
+            }
+            if flags.timings {
+                let reported = resp.timings.take();
+                resp.timings = Some(timings(reported, spawn, connect, round_trip, started));
+            }
             let success = resp.success;
-            // Extract action for context-specific output handling
-            let action = cmd.get("action").and_then(|v| v.as_str());
-            print_response(&resp, flags.json, action);
             if !success {
-                exit(1);
+                resp.error_code = exit_code::classify(action, &resp);
+            }
+            if let (true, Some(pid)) = (success, stopping) {
+                if !connection::wait_for_shutdown(&flags.session, pid, SHUTDOWN_TIMEOUT) {
+                    eprintln!(
+                        "{} The daemon (pid {}) is still running {:?} after close",
+                        color::warning_indicator(),
+                        pid,
+                        SHUTDOWN_TIMEOUT
+                    );
+                }
+            }
+            // The daemon answers status without touching the browser, so its round
+            // trip is the ping
+            if let (Some("status"), Some(data)) = (action, resp.data.as_mut()) {
+                data["ping"] = json!(millis(round_trip));
+            }
+            if let Some(options) = cmd.get("diff") {
+                if !diff::run(&resp, options, flags.json) {
+                    exit(1);
+                }
+                return;
+            }
+            let image_to_stdout = cmd.get("stdout").and_then(|v| v.as_bool()) == Some(true);
+            if success && image_to_stdout && !flags.json && flags.output.is_none() {
+                let as_base64 = cmd.get("base64").and_then(|v| v.as_bool()) == Some(true);
+                if let Err(e) = print_image_data(&resp, as_base64) {
+                    eprintln!("{} {}", color::error_indicator(), e);
+                    exit(1);
+                }
+                return;
+            }
+            match flags.output.as_deref() {
+                Some(path) if success => {
+                    if let Err(e) = write_response(&resp, flags.json, action, path) {
+                        eprintln!("{} {}", color::error_indicator(), e);
+                        exit(1);
+                    }
+                }
+                _ => print_response(&resp, flags.json, action),
+            }
+            if let (Some(timings), false) = (&resp.timings, flags.json) {
+                print_timings(timings);
+            }
+            if !success {
+                if let (Some(what), false) = (&waiting_for, flags.json) {
+                    waiting::print_timeout(what, &resp);
+                }
+                print_daemon_log(&flags);
+                exit(exit_code::for_code(resp.error_code));
+            }
+            if predicate_failed(action, &resp) {
+                exit(exit_code::FAILURE);
             }
         }
         Err(e) => {
-            if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, e);
-            } else {
-                eprintln!("{} {}", color::error_indicator(), e);
+            let resp = Response {
+                success: false,
+                error: Some(e.to_string()),
+                error_code: exit_code::classify_error(action, &e),
+                ..Default::default()
+            };
+            print_response(&resp, flags.json, action);
+            if let (Some(what), false) = (&waiting_for, flags.json) {
+                waiting::print_timeout(what, &resp);
             }
-            exit(1);
+            print_daemon_log(&flags);
+            exit(exit_code::for_code(resp.error_code));
         }
     }
 }
@@ -533,6 +1400,104 @@ fn main() {
 mod tests {
     use super::*;
 
+    fn response(data: serde_json::Value) -> Response {
+        Response {
+            success: true,
+            data: Some(data),
+            ..Default::default()
+        }
+    }
+
+    fn hello(protocol_version: u32) -> agent_browser_client::protocol::HelloData {
+        agent_browser_client::protocol::HelloData {
+            protocol_version,
+            version: "0.1.0".to_string(),
+            actions: vec!["status".to_string()],
+            compression: vec![],
+        }
+    }
+
+    #[test]
+    fn test_browser_line() {
+        let compatible = Ok(Handshake::Compatible(hello(PROTOCOL_VERSION)));
+        assert_eq!(
+            browser_line(Some(&compatible), Some("Chromium 131.0")).as_deref(),
+            Some("Chromium 131.0")
+        );
+        assert_eq!(
+            browser_line(Some(&compatible), None).as_deref(),
+            Some("not launched")
+        );
+        // Without a daemon there is no browser line
+        assert_eq!(browser_line(None, None), None);
+    }
+
+    #[test]
+    fn test_browser_line_incompatible_daemon() {
+        // A daemon on another protocol isn't asked, so its browser is unknown
+        let mismatch = Ok(Handshake::Mismatch(hello(PROTOCOL_VERSION + 1)));
+        assert_eq!(
+            browser_line(Some(&mismatch), None).as_deref(),
+            Some("unknown, the daemon is incompatible")
+        );
+        assert_eq!(
+            browser_line(Some(&Ok(Handshake::Legacy)), None).as_deref(),
+            Some("unknown, the daemon is incompatible")
+        );
+        let failed = Err(connection::Error::Remote("refused".to_string()));
+        assert_eq!(
+            browser_line(Some(&failed), None).as_deref(),
+            Some("unknown, the daemon is not answering")
+        );
+    }
+
+    #[test]
+    fn test_predicate_failed() {
+        let hidden = response(json!({ "visible": false }));
+        assert!(predicate_failed(Some("isvisible"), &hidden));
+        assert!(!predicate_failed(
+            Some("isvisible"),
+            &response(json!({ "visible": true }))
+        ));
+        assert!(predicate_failed(
+            Some("ischecked"),
+            &response(json!({ "checked": false }))
+        ));
+    }
+
+    #[test]
+    fn test_predicate_failed_audit() {
+        let failed = response(json!({ "violations": [], "passed": false }));
+        assert!(predicate_failed(Some("audit"), &failed));
+        assert!(!predicate_failed(
+            Some("audit"),
+            &response(json!({ "passed": true }))
+        ));
+    }
+
+    #[test]
+    fn test_predicate_failed_ignores_other_actions() {
+        let unchecked = response(json!({ "checked": false, "changed": true }));
+        assert!(!predicate_failed(Some("uncheck"), &unchecked));
+        assert!(!predicate_failed(None, &unchecked));
+    }
+
+    #[test]
+    fn test_resolve_profile() {
+        let root = env::temp_dir().join(format!("ab-profile-test-{}", std::process::id()));
+        let dir = root.join("profile");
+        let resolved = resolve_profile(dir.to_str().unwrap()).unwrap();
+        assert_eq!(resolved, dir.to_string_lossy());
+        assert!(dir.is_dir());
+
+        let file = root.join("file");
+        fs::write(&file, "").unwrap();
+        let err = resolve_profile(file.to_str().unwrap()).unwrap_err();
+        assert!(err.contains("not a directory"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
     #[test]
     fn test_parse_proxy_simple() {
         let result = parse_proxy("http://proxy.com:8080");
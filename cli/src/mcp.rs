@@ -0,0 +1,588 @@
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, Write};
+
+use crate::commands::secret_value;
+use crate::connection::{DaemonConnection, Error, Response};
+use crate::flags::Flags;
+use crate::script::{parse_tokens, tokenize};
+
+/// Protocol revisions this server speaks, newest first. The tools surface is
+/// the same in all of them.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// One input of a tool. Positional parameters are appended in declaration
+/// order; the others become `flag` (booleans) or `flag value`.
+struct Param {
+    name: &'static str,
+    ty: &'static str,
+    flag: Option<&'static str>,
+    required: bool,
+    description: &'static str,
+}
+
+const fn arg(name: &'static str, description: &'static str) -> Param {
+    Param {
+        name,
+        ty: "string",
+        flag: None,
+        required: true,
+        description,
+    }
+}
+
+const fn switch(name: &'static str, flag: &'static str, description: &'static str) -> Param {
+    Param {
+        name,
+        ty: "boolean",
+        flag: Some(flag),
+        required: false,
+        description,
+    }
+}
+
+const fn option(name: &'static str, flag: &'static str, description: &'static str) -> Param {
+    Param {
+        name,
+        ty: "string",
+        flag: Some(flag),
+        required: false,
+        description,
+    }
+}
+
+/// A tool and the CLI command it expands to
+struct Tool {
+    name: &'static str,
+    description: &'static str,
+    command: &'static [&'static str],
+    params: &'static [Param],
+}
+
+const SELECTOR: Param = arg(
+    "selector",
+    "CSS selector, or a ref from snapshot such as @e2",
+);
+
+/// Typed tools for the common actions, and `command` for everything else.
+/// That is the whole surface by design: every command `parse_command` accepts
+/// is reachable through `command`, and a tool per action would bury the
+/// common ones in a list of nearly two hundred.
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "navigate",
+        description: "Open a URL in the current tab",
+        command: &["open"],
+        params: &[arg("url", "URL to open")],
+    },
+    Tool {
+        name: "back",
+        description: "Go back in history",
+        command: &["back"],
+        params: &[],
+    },
+    Tool {
+        name: "forward",
+        description: "Go forward in history",
+        command: &["forward"],
+        params: &[],
+    },
+    Tool {
+        name: "reload",
+        description: "Reload the page",
+        command: &["reload"],
+        params: &[],
+    },
+    Tool {
+        name: "snapshot",
+        description: "Accessibility tree of the page. Elements get refs (@e1, @e2, ...) \
+                      that other tools accept as selectors",
+        command: &["snapshot"],
+        params: &[
+            switch("interactive", "-i", "Only interactive elements"),
+            switch("compact", "-c", "Remove empty structural elements"),
+            option("selector", "-s", "Only the subtree under this CSS selector"),
+        ],
+    },
+    Tool {
+        name: "click",
+        description: "Click an element",
+        command: &["click"],
+        params: &[SELECTOR],
+    },
+    Tool {
+        name: "fill",
+        description: "Clear an input and fill it with text",
+        command: &["fill"],
+        params: &[SELECTOR, arg("text", "Text to fill")],
+    },
+    Tool {
+        name: "type",
+        description: "Type text into an element, key by key",
+        command: &["type"],
+        params: &[SELECTOR, arg("text", "Text to type")],
+    },
+    Tool {
+        name: "press",
+        description: "Press a key or chord, e.g. Enter, Tab, Control+a",
+        command: &["press"],
+        params: &[arg("key", "Key to press")],
+    },
+    Tool {
+        name: "hover",
+        description: "Hover over an element",
+        command: &["hover"],
+        params: &[SELECTOR],
+    },
+    Tool {
+        name: "select",
+        description: "Select an option in a <select> by value",
+        command: &["select"],
+        params: &[SELECTOR, arg("value", "Option value")],
+    },
+    Tool {
+        name: "check",
+        description: "Check a checkbox or radio button",
+        command: &["check"],
+        params: &[SELECTOR],
+    },
+    Tool {
+        name: "uncheck",
+        description: "Uncheck a checkbox",
+        command: &["uncheck"],
+        params: &[SELECTOR],
+    },
+    Tool {
+        name: "scroll",
+        description: "Scroll the page",
+        command: &["scroll"],
+        params: &[
+            arg("direction", "up, down, left, right, top or bottom"),
+            Param {
+                name: "pixels",
+                ty: "integer",
+                flag: None,
+                required: false,
+                description: "Distance for up/down/left/right (default 300)",
+            },
+        ],
+    },
+    Tool {
+        name: "wait",
+        description: "Wait for an element to appear, or for a number of milliseconds",
+        command: &["wait"],
+        params: &[arg("target", "Selector, ref or milliseconds")],
+    },
+    Tool {
+        name: "screenshot",
+        description: "Take a screenshot of the page",
+        command: &["screenshot", "--stdout"],
+        params: &[
+            switch(
+                "full_page",
+                "--full-page",
+                "Capture the full scrollable page",
+            ),
+            option("selector", "--selector", "Capture only this element"),
+        ],
+    },
+    Tool {
+        name: "get_text",
+        description: "Text content of an element",
+        command: &["get", "text"],
+        params: &[SELECTOR],
+    },
+    Tool {
+        name: "get_url",
+        description: "URL of the current page",
+        command: &["get", "url"],
+        params: &[],
+    },
+    Tool {
+        name: "get_title",
+        description: "Title of the current page",
+        command: &["get", "title"],
+        params: &[],
+    },
+    Tool {
+        name: "eval",
+        description: "Evaluate JavaScript in the page and return the result",
+        command: &["eval"],
+        params: &[arg("script", "JavaScript expression or function body")],
+    },
+    Tool {
+        name: "command",
+        description: "Run any agent-browser command, written as on the command line without \
+                      the agent-browser prefix, e.g. `get attr @e3 href` or `cookies clear`",
+        command: &[],
+        params: &[arg("command", "Command line to run")],
+    },
+];
+
+fn input_schema(tool: &Tool) -> Value {
+    let properties: Map<String, Value> = tool
+        .params
+        .iter()
+        .map(|p| {
+            (
+                p.name.to_string(),
+                json!({ "type": p.ty, "description": p.description }),
+            )
+        })
+        .collect();
+    let required: Vec<&str> = tool
+        .params
+        .iter()
+        .filter(|p| p.required)
+        .map(|p| p.name)
+        .collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn tool_list() -> Value {
+    let tools: Vec<Value> = TOOLS
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.name,
+                "description": t.description,
+                "inputSchema": input_schema(t),
+            })
+        })
+        .collect();
+    json!({ "tools": tools })
+}
+
+/// CLI arguments for a tool call
+fn tool_args(tool: &Tool, arguments: &Map<String, Value>) -> Result<Vec<String>, String> {
+    if let Some(unknown) = arguments
+        .keys()
+        .find(|k| !tool.params.iter().any(|p| p.name == k.as_str()))
+    {
+        return Err(format!("Unknown argument for {}: {}", tool.name, unknown));
+    }
+
+    let mut args: Vec<String> = tool.command.iter().map(|s| s.to_string()).collect();
+    for param in tool.params {
+        let value = match arguments.get(param.name) {
+            None | Some(Value::Null) if param.required => {
+                return Err(format!("Missing required argument: {}", param.name));
+            }
+            None | Some(Value::Null) => continue,
+            Some(Value::String(s)) => s.clone(),
+            Some(v) => v.to_string(),
+        };
+        match (param.flag, param.ty) {
+            (Some(flag), "boolean") => {
+                if value == "true" {
+                    args.push(flag.to_string());
+                }
+            }
+            (Some(flag), _) => {
+                args.push(flag.to_string());
+                args.push(value);
+            }
+            (None, _) if tool.name == "command" => args.extend(tokenize(&value)?),
+            (None, _) => {
+                // A value like `--x` to fill is text, not a flag to reject
+                if value.starts_with("--") && !args.iter().any(|a| a == "--") {
+                    args.push("--".to_string());
+                }
+                args.push(value);
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// MCP tool result for a daemon response
+fn tool_result(cmd: &Value, resp: &Response) -> Value {
+    if !resp.success {
+        let error = resp.error.as_deref().unwrap_or("Command failed");
+        return json!({ "content": [{ "type": "text", "text": error }], "isError": true });
+    }
+
+    let data = resp.data.as_ref().unwrap_or(&Value::Null);
+    let content = if let Some(image) = data.get("base64").and_then(|v| v.as_str()) {
+        let mime = match cmd.get("format").and_then(|v| v.as_str()) {
+            Some("jpeg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            _ => "image/png",
+        };
+        json!({ "type": "image", "data": image, "mimeType": mime })
+    } else if let Some(snapshot) = data.get("snapshot").and_then(|v| v.as_str()) {
+        json!({ "type": "text", "text": snapshot })
+    } else if data.is_null() {
+        json!({ "type": "text", "text": "Done" })
+    } else {
+        let text = serde_json::to_string_pretty(data).unwrap_or_default();
+        json!({ "type": "text", "text": text })
+    };
+    json!({ "content": [content], "isError": false })
+}
+
+fn tool_error(message: &str) -> Value {
+    json!({ "content": [{ "type": "text", "text": message }], "isError": true })
+}
+
+fn reply(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn reply_error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+struct Server<'a> {
+    flags: &'a Flags,
+    conn: Option<DaemonConnection>,
+}
+
+impl Server<'_> {
+    /// Answer one JSON-RPC message. Notifications get no reply.
+    fn handle(&mut self, msg: &Value) -> Option<Value> {
+        let id = msg.get("id")?.clone();
+        let params = msg.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match msg.get("method").and_then(|v| v.as_str()) {
+            Some("initialize") => {
+                let requested = params.get("protocolVersion").and_then(|v| v.as_str());
+                let version = requested
+                    .filter(|v| PROTOCOL_VERSIONS.contains(v))
+                    .unwrap_or(PROTOCOL_VERSIONS[0]);
+                json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "agent-browser", "version": env!("CARGO_PKG_VERSION") },
+                })
+            }
+            Some("ping") => json!({}),
+            Some("tools/list") => tool_list(),
+            Some("tools/call") => {
+                let name = params.get("name").and_then(|v| v.as_str());
+                let Some(tool) = TOOLS.iter().find(|t| Some(t.name) == name) else {
+                    let message = format!("Unknown tool: {}", name.unwrap_or(""));
+                    return Some(reply_error(id, -32602, &message));
+                };
+                let empty = Map::new();
+                let arguments = params
+                    .get("arguments")
+                    .and_then(|v| v.as_object())
+                    .unwrap_or(&empty);
+                self.call(tool, arguments)
+            }
+            Some(method) => {
+                let message = format!("Method not found: {}", method);
+                return Some(reply_error(id, -32601, &message));
+            }
+            None => return Some(reply_error(id, -32600, "Invalid request")),
+        };
+        Some(reply(id, result))
+    }
+
+    fn call(&mut self, tool: &Tool, arguments: &Map<String, Value>) -> Value {
+        let cmd = match tool_args(tool, arguments).and_then(|a| parse_tokens(&a, self.flags)) {
+            Ok(cmd) => cmd,
+            Err(e) => return tool_error(&e),
+        };
+
+        // These would end the session or need a terminal rather than one reply
+        if cmd.get("action").and_then(|v| v.as_str()) == Some("close") {
+            return tool_error("close is not available over MCP; run `agent-browser close`");
+        }
+        if cmd.get("follow").and_then(|v| v.as_bool()) == Some(true) || cmd.get("diff").is_some() {
+            return tool_error("--follow and --diff are not available over MCP");
+        }
+
+        match self.send(&cmd) {
+            Ok(mut resp) => {
+                if let Some(secret) = secret_value(&cmd) {
+                    resp.redact(secret);
+                }
+                tool_result(&cmd, &resp)
+            }
+            Err(e) => tool_error(&e.to_string()),
+        }
+    }
+
+    /// Send over the open connection, reconnecting if an earlier command dropped it
+    fn send(&mut self, cmd: &Value) -> Result<Response, Error> {
+        let conn = match self.conn.as_mut() {
+            Some(c) => c,
+            None => self
+                .conn
+                .insert(DaemonConnection::open(&self.flags.session)?),
+        };
+        let resp = conn.send(cmd);
+        if resp.is_err() {
+            self.conn = None;
+        }
+        resp
+    }
+}
+
+/// Serve MCP over stdin/stdout until the client closes stdin. Every tool call
+/// goes to the session's daemon, which has already been started with the
+/// launch options from the command line. Returns false if stdout is closed
+/// while replying.
+pub fn serve(flags: &Flags) -> bool {
+    let mut server = Server { flags, conn: None };
+    let mut stdout = io::stdout().lock();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = match serde_json::from_str::<Value>(&line) {
+            Ok(msg) if msg.is_object() => server.handle(&msg),
+            Ok(_) => Some(reply_error(Value::Null, -32600, "Invalid request")),
+            Err(e) => Some(reply_error(
+                Value::Null,
+                -32700,
+                &format!("Parse error: {}", e),
+            )),
+        };
+        if let Some(answer) = answer {
+            if writeln!(stdout, "{}", answer)
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::parse_flags;
+
+    fn tool(name: &str) -> &'static Tool {
+        TOOLS.iter().find(|t| t.name == name).unwrap()
+    }
+
+    fn arguments(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_tool_args_positional_and_flags() {
+        let args = tool_args(
+            tool("snapshot"),
+            &arguments(json!({ "interactive": true, "compact": false, "selector": "#main" })),
+        )
+        .unwrap();
+        assert_eq!(args, vec!["snapshot", "-i", "-s", "#main"]);
+
+        let args = tool_args(
+            tool("scroll"),
+            &arguments(json!({ "direction": "down", "pixels": 500 })),
+        )
+        .unwrap();
+        assert_eq!(args, vec!["scroll", "down", "500"]);
+
+        let args = tool_args(
+            tool("fill"),
+            &arguments(json!({ "selector": "@e1", "text": "--x" })),
+        )
+        .unwrap();
+        assert_eq!(args, vec!["fill", "@e1", "--", "--x"]);
+        let cmd = parse_tokens(&args, &parse_flags(&[])).unwrap();
+        assert_eq!(cmd["value"], "--x");
+    }
+
+    #[test]
+    fn test_tool_args_errors() {
+        assert!(tool_args(tool("click"), &Map::new()).is_err());
+        let result = tool_args(
+            tool("click"),
+            &arguments(json!({ "selector": "a", "x": 1 })),
+        );
+        assert!(result.unwrap_err().contains("Unknown argument"));
+    }
+
+    #[test]
+    fn test_tools_are_commands() {
+        for tool in TOOLS.iter().filter(|t| t.name != "command") {
+            assert!(
+                crate::spec::flags(tool.command[0]).is_some(),
+                "{} runs unknown command {}",
+                tool.name,
+                tool.command[0]
+            );
+        }
+    }
+
+    #[test]
+    fn test_command_tool_tokenizes() {
+        let args = tool_args(
+            tool("command"),
+            &arguments(json!({ "command": "get attr @e3 'data-id'" })),
+        )
+        .unwrap();
+        let cmd = parse_tokens(&args, &parse_flags(&[])).unwrap();
+        assert_eq!(cmd["action"], "getattribute");
+        assert_eq!(cmd["attribute"], "data-id");
+    }
+
+    #[test]
+    fn test_initialize_and_list() {
+        let flags = parse_flags(&[]);
+        let mut server = Server {
+            flags: &flags,
+            conn: None,
+        };
+        let init = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {} }
+        });
+        let resp = server.handle(&init).unwrap();
+        assert_eq!(resp["result"]["protocolVersion"], "2024-11-05");
+        assert!(resp["result"]["capabilities"]["tools"].is_object());
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(server.handle(&notification).is_none());
+
+        let list = server
+            .handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+            .unwrap();
+        let tools = list["result"]["tools"].as_array().unwrap();
+        assert!(tools.iter().any(|t| t["name"] == "navigate"));
+        assert_eq!(tools[0]["inputSchema"]["required"], json!(["url"]));
+    }
+
+    #[test]
+    fn test_call_errors_without_daemon() {
+        let flags = parse_flags(&[]);
+        let mut server = Server {
+            flags: &flags,
+            conn: None,
+        };
+        let call = |name: &str, args: Value| {
+            json!({
+                "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": { "name": name, "arguments": args }
+            })
+        };
+
+        let resp = server.handle(&call("nope", json!({}))).unwrap();
+        assert_eq!(resp["error"]["code"], -32602);
+
+        let resp = server
+            .handle(&call("command", json!({ "command": "close" })))
+            .unwrap();
+        assert_eq!(resp["result"]["isError"], true);
+
+        let resp = server
+            .handle(&call("command", json!({ "command": "bogus" })))
+            .unwrap();
+        assert_eq!(resp["result"]["isError"], true);
+
+        let resp = server
+            .handle(&json!({ "jsonrpc": "2.0", "id": 4, "method": "resources/list" }))
+            .unwrap();
+        assert_eq!(resp["error"]["code"], -32601);
+    }
+}
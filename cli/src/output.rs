@@ -1,9 +1,60 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
+
 use crate::color;
-use crate::connection::Response;
+use crate::connection::{Response, Timings};
+use crate::format;
+
+static QUIET: OnceLock<bool> = OnceLock::new();
+static VERBOSE: OnceLock<bool> = OnceLock::new();
+
+/// `-q`: leave out the ✓ confirmations, printing only results and errors
+pub fn set_quiet() {
+    let _ = QUIET.set(true);
+}
+
+fn is_quiet() -> bool {
+    QUIET.get().copied().unwrap_or(false)
+}
+
+/// `-v`: log every request and response, with its round trip, to stderr
+pub fn set_verbose() {
+    let _ = VERBOSE.set(true);
+}
+
+pub fn is_verbose() -> bool {
+    VERBOSE.get().copied().unwrap_or(false)
+}
+
+/// `println!` for a ✓ confirmation, which `-q` leaves out
+macro_rules! success {
+    ($($arg:tt)*) => {
+        if !is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// The `--timings` breakdown on stderr, so it never mixes with the result
+pub fn print_timings(timings: &Timings) {
+    let phases = [
+        ("spawn", timings.spawn),
+        ("connect", timings.connect),
+        ("daemon", timings.daemon),
+        ("transfer", timings.transfer),
+        ("total", timings.total),
+    ];
+    let phases: Vec<String> = phases
+        .iter()
+        .filter_map(|(name, ms)| ms.map(|ms| format!("{} {:.1} ms", name, ms)))
+        .collect();
+    eprintln!("{} {}", color::dim("Timings:"), phases.join(", "));
+}
 
 pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
     if json_mode {
-        println!("{}", serde_json::to_string(resp).unwrap_or_default());
+        format::print(resp);
         return;
     }
 
@@ -17,17 +68,420 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
     }
 
     if let Some(data) = &resp.data {
-        // Navigation response
-        if let Some(url) = data.get("url").and_then(|v| v.as_str()) {
+        // Article: print the Markdown (before the url/title block)
+        if action == Some("article") {
+            if let Some(markdown) = data.get("markdown").and_then(|v| v.as_str()) {
+                if markdown.is_empty() {
+                    println!("{}", color::dim("(no readable content)"));
+                } else {
+                    println!("{}", markdown);
+                }
+                return;
+            }
+        }
+        // Session status (before the tab list, which shares its "tabs" field)
+        if action == Some("status") {
+            print_status(data);
+            return;
+        }
+        // Web storage entries
+        if action == Some("storage_get") {
+            if let Some(entries) = data.get("data").and_then(|v| v.as_object()) {
+                for (key, value) in entries {
+                    println!("{}={}", key, value.as_str().unwrap_or_default());
+                }
+                return;
+            }
+            if data.get("value").map(|v| v.is_null()).unwrap_or(false) {
+                println!("{}", color::dim("(not set)"));
+                return;
+            }
+        }
+        // Mocks
+        if action == Some("mock_add") {
+            if let Some(mock) = data.get("mock") {
+                success!(
+                    "{} Mock {} added for {}",
+                    color::success_indicator(),
+                    mock.get("id").and_then(|v| v.as_u64()).unwrap_or(0),
+                    mock.get("url").and_then(|v| v.as_str()).unwrap_or("")
+                );
+                return;
+            }
+        }
+        if action == Some("mock_list") {
+            if let Some(mocks) = data.get("mocks").and_then(|v| v.as_array()) {
+                if mocks.is_empty() {
+                    println!("No mocks");
+                }
+                for mock in mocks {
+                    println!("{}", format_mock(mock));
+                }
+                return;
+            }
+        }
+        if action == Some("mock_remove") {
+            if let Some(id) = data.get("removed").and_then(|v| v.as_u64()) {
+                success!("{} Mock {} removed", color::success_indicator(), id);
+                return;
+            }
+        }
+        // Extra HTTP headers
+        if action == Some("headers") {
+            if let Some(headers) = data.get("headers").and_then(|v| v.as_object()) {
+                if headers.is_empty() {
+                    success!("{} Extra headers cleared", color::success_indicator());
+                } else {
+                    let names: Vec<&str> = headers.keys().map(|k| k.as_str()).collect();
+                    success!(
+                        "{} Sending extra headers: {}",
+                        color::success_indicator(),
+                        names.join(", ")
+                    );
+                }
+                return;
+            }
+        }
+        if action == Some("permissions") {
+            let names: Vec<&str> = data
+                .get("permissions")
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+            if data.get("granted").and_then(|v| v.as_bool()) == Some(true) {
+                success!(
+                    "{} Granted {} to {}",
+                    color::success_indicator(),
+                    names.join(", "),
+                    data.get("origin")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("every origin")
+                );
+            } else {
+                success!("{} Permissions cleared", color::success_indicator());
+            }
+            return;
+        }
+        if action == Some("trace_start") {
+            let parts: Vec<&str> = ["screenshots", "snapshots"]
+                .into_iter()
+                .filter(|k| data.get(*k).and_then(|v| v.as_bool()).unwrap_or(false))
+                .collect();
+            let detail = if parts.is_empty() {
+                "actions only".to_string()
+            } else {
+                parts.join(", ")
+            };
+            success!(
+                "{} Tracing started ({})",
+                color::success_indicator(),
+                detail
+            );
+            return;
+        }
+        if action == Some("coverage_start") {
+            success!(
+                "{} Coverage started (JS and CSS)",
+                color::success_indicator()
+            );
+            return;
+        }
+        if action == Some("coverage_stop") {
+            print_coverage(data);
+            return;
+        }
+        if action == Some("useragent") {
+            if let Some(ua) = data.get("userAgent").and_then(|v| v.as_str()) {
+                success!("{} User agent set to {}", color::success_indicator(), ua);
+                return;
+            }
+        }
+        if action == Some("credentials") {
+            if let Some(user) = data.get("username").and_then(|v| v.as_str()) {
+                success!(
+                    "{} HTTP credentials set for {}",
+                    color::success_indicator(),
+                    user
+                );
+                return;
+            }
+        }
+        // Dialogs
+        if action == Some("dialog") {
+            let verb = match data.get("response").and_then(|v| v.as_str()) {
+                Some("accept") => "Accepted",
+                _ => "Dismissed",
+            };
+            if let Some(dialog) = data.get("dialog") {
+                success!(
+                    "{} {} {}",
+                    color::success_indicator(),
+                    verb,
+                    format_dialog(dialog)
+                );
+            } else {
+                success!(
+                    "{} No dialog open; the next one will be {}",
+                    color::success_indicator(),
+                    verb.to_lowercase()
+                );
+            }
+            return;
+        }
+        if action == Some("dialog_auto") {
+            let policy = match data.get("policy").and_then(|v| v.as_str()) {
+                Some("accept") => "Dialogs will be accepted automatically",
+                Some("off") => "Dialogs will stay open until accepted or dismissed",
+                _ => "Dialogs will be dismissed automatically",
+            };
+            success!("{} {}", color::success_indicator(), policy);
+            let pending = data.get("pending").and_then(|v| v.as_array());
+            for dialog in pending.into_iter().flatten() {
+                println!("  Open {}", format_dialog(dialog));
+            }
+            return;
+        }
+        // Blocked patterns
+        if action == Some("block") || action == Some("unblock") {
+            if let Some(blocked) = data.get("blocked").and_then(|v| v.as_array()) {
+                let patterns: Vec<&str> = blocked.iter().filter_map(|v| v.as_str()).collect();
+                if patterns.is_empty() {
+                    success!("{} No requests blocked", color::success_indicator());
+                } else {
+                    success!(
+                        "{} Blocking {}",
+                        color::success_indicator(),
+                        patterns.join(" ")
+                    );
+                }
+                return;
+            }
+        }
+        // Network log
+        if action == Some("network_log") {
+            if let Some(entries) = data.get("entries").and_then(|v| v.as_array()) {
+                if entries.is_empty() {
+                    println!("No requests recorded");
+                }
+                for entry in entries {
+                    println!("{}", format_network_entry(entry));
+                }
+                return;
+            }
+        }
+        // Downloads (waitfordownload also returns the source url)
+        if action == Some("waitfordownload") {
+            if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
+                success!(
+                    "{} Download saved to {}",
+                    color::success_indicator(),
+                    color::green(path)
+                );
+                return;
+            }
+        }
+        if action == Some("download_dir") {
+            if let Some(dir) = data.get("directory").and_then(|v| v.as_str()) {
+                success!(
+                    "{} Downloads will be saved to {}",
+                    color::success_indicator(),
+                    color::green(dir)
+                );
+                return;
+            }
+        }
+        match action {
+            Some("read") => {
+                let text = data.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                let from = data.get("from").and_then(|v| v.as_u64()).unwrap_or(0);
+                let to = data.get("to").and_then(|v| v.as_u64()).unwrap_or(0);
+                let total = data.get("total").and_then(|v| v.as_u64()).unwrap_or(0);
+                let position = match (text.is_empty(), to < total) {
+                    (true, true) => "Start of page".to_string(),
+                    (true, false) => "End of page".to_string(),
+                    (false, true) => {
+                        format!("Lines {}-{} of {}, read next for more", from, to, total)
+                    }
+                    (false, false) => format!("Lines {}-{} of {}, end of page", from, to, total),
+                };
+                if !text.is_empty() {
+                    println!("{}", text);
+                }
+                println!("{}", color::dim(&position));
+                return;
+            }
+            Some("url") if data.get("query").is_some() => {
+                print_url_parts(data);
+                return;
+            }
+            Some("waitforspaidle") => {
+                let url = data.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                success!("{} Settled at {}", color::success_indicator(), url);
+                return;
+            }
+            Some("waitforrequest") | Some("waitforresponse") => {
+                let url = data.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                let method = data.get("method").and_then(|v| v.as_str()).unwrap_or("");
+                match data.get("status").and_then(|v| v.as_u64()) {
+                    Some(status) => success!(
+                        "{} {} {} {}",
+                        color::success_indicator(),
+                        status,
+                        method,
+                        url
+                    ),
+                    None => success!("{} {} {}", color::success_indicator(), method, url),
+                }
+                return;
+            }
+            Some("highlight") => {
+                if let Some(count) = data.get("count").and_then(|v| v.as_u64()) {
+                    success!(
+                        "{} Highlighted {} element{}",
+                        color::success_indicator(),
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    );
+                    return;
+                }
+            }
+            Some("fillform") => {
+                let names = |key: &str| -> Vec<String> {
+                    data.get(key)
+                        .and_then(|v| v.as_array())
+                        .map(|a| {
+                            a.iter()
+                                .filter_map(|v| v.as_str().map(String::from))
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                };
+                let filled = names("filled");
+                let skipped = data.get("skipped").and_then(|v| v.as_array());
+                let not_found = names("notFound");
+                let total = filled.len() + skipped.map_or(0, |s| s.len()) + not_found.len();
+                success!(
+                    "{} Filled {} of {} field{}",
+                    color::success_indicator(),
+                    filled.len(),
+                    total,
+                    if total == 1 { "" } else { "s" }
+                );
+                for entry in skipped.into_iter().flatten() {
+                    let field = entry.get("field").and_then(|v| v.as_str()).unwrap_or("");
+                    let reason = entry.get("reason").and_then(|v| v.as_str()).unwrap_or("");
+                    println!(
+                        "{} Skipped {}: {}",
+                        color::warning_indicator(),
+                        field,
+                        reason
+                    );
+                }
+                if !not_found.is_empty() {
+                    println!(
+                        "{} Not found: {}",
+                        color::warning_indicator(),
+                        not_found.join(", ")
+                    );
+                }
+                return;
+            }
+            Some("context_new") => {
+                let name = data.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                let tab = data.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+                success!(
+                    "{} Created context {} {}",
+                    color::success_indicator(),
+                    color::bold(name),
+                    color::dim(&format!("(tab {})", tab))
+                );
+                return;
+            }
+            Some("context_close") => {
+                let name = data.get("closed").and_then(|v| v.as_str()).unwrap_or("");
+                success!("{} Closed context {}", color::success_indicator(), name);
+                return;
+            }
+            Some("context_list") => {
+                let contexts = data.get("contexts").and_then(|v| v.as_array());
+                for context in contexts.into_iter().flatten() {
+                    let name = context.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                    let tabs = context.get("tabs").and_then(|v| v.as_u64()).unwrap_or(0);
+                    let active = context.get("active").and_then(|v| v.as_bool()) == Some(true);
+                    let marker = if active {
+                        color::cyan("→")
+                    } else {
+                        " ".to_string()
+                    };
+                    let count = format!("({} tab{})", tabs, if tabs == 1 { "" } else { "s" });
+                    println!("{} {} {}", marker, name, color::dim(&count));
+                }
+                return;
+            }
+            _ => {}
+        }
+        if print_url_result(action, data) {
+            return;
+        }
+        // Navigation response (a fetch's url is the one fetched, printed with it below)
+        if let Some(url) = data
+            .get("url")
+            .and_then(|v| v.as_str())
+            .filter(|_| action != Some("fetch"))
+        {
             if let Some(title) = data.get("title").and_then(|v| v.as_str()) {
-                println!("{} {}", color::success_indicator(), color::bold(title));
-                println!("  {}", color::dim(url));
+                success!("{} {}", color::success_indicator(), color::bold(title));
+                success!("  {}", color::dim(url));
+                if let Some(tab) = data.get("tab").and_then(|v| v.as_u64()) {
+                    success!("  {}", color::dim(&format!("Opened in tab {}", tab)));
+                }
                 return;
             }
             println!("{}", url);
             return;
         }
-        // Snapshot
+        // Snapshot diff: + added, - removed, ~ changed
+        if action == Some("snapshot_diff") {
+            if let Some(changes) = data.get("changes").and_then(|v| v.as_array()) {
+                if data.get("initial").and_then(|v| v.as_bool()) == Some(true) {
+                    println!(
+                        "{}",
+                        color::dim("No previous snapshot, showing the full tree")
+                    );
+                } else if changes.is_empty() {
+                    println!("{}", color::dim("No changes"));
+                }
+                for change in changes {
+                    println!("{}", format_snapshot_change(change));
+                }
+                return;
+            }
+        }
+        // Snapshot (`--format json` returns the parsed tree instead of text). With
+        // --if-changed the hash to poll with next follows it.
+        if action == Some("snapshot") {
+            let hash = data
+                .get("hash")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let unchanged = data.get("unchanged").and_then(|v| v.as_bool());
+            if unchanged == Some(true) {
+                println!("{}", color::dim(&format!("Unchanged (hash {})", hash)));
+                return;
+            }
+            if let Some(nodes) = data.get("nodes") {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(nodes).unwrap_or_default()
+                );
+            } else if let Some(snapshot) = data.get("snapshot").and_then(|v| v.as_str()) {
+                println!("{}", snapshot);
+            }
+            if unchanged == Some(false) {
+                println!("{}", color::dim(&format!("# Hash: {}", hash)));
+            }
+            return;
+        }
         if let Some(snapshot) = data.get("snapshot").and_then(|v| v.as_str()) {
             println!("{}", snapshot);
             return;
@@ -42,21 +496,93 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
             println!("{}", text);
             return;
         }
+        // get text --all: one match per line
+        if let Some(texts) = data.get("texts").and_then(|v| v.as_array()) {
+            if texts.is_empty() {
+                println!("{}", color::dim("(no matches)"));
+            }
+            for text in texts {
+                println!("{}", text.as_str().unwrap_or(""));
+            }
+            return;
+        }
         // HTML
         if let Some(html) = data.get("html").and_then(|v| v.as_str()) {
             println!("{}", html);
             return;
         }
+        // Missing attribute
+        if action == Some("getattribute") && data.get("value").is_some_and(|v| v.is_null()) {
+            println!("{}", color::dim("(not set)"));
+            return;
+        }
+        // Computed style values
+        if action == Some("computedstyle") {
+            if let Some(styles) = data.get("styles").and_then(|v| v.as_object()) {
+                for (property, value) in styles {
+                    match value.as_str() {
+                        Some(value) => println!("{}: {}", property, value),
+                        None => println!("{}: {}", property, color::dim("(not set)")),
+                    }
+                }
+                return;
+            }
+        }
         // Value
         if let Some(value) = data.get("value").and_then(|v| v.as_str()) {
             println!("{}", value);
             return;
         }
+        // Passed assertion
+        if action == Some("expect") {
+            match data.get("actual").and_then(|v| v.as_str()) {
+                Some(actual) => success!("{} Passed: {:?}", color::success_indicator(), actual),
+                None => success!("{} Passed", color::success_indicator()),
+            }
+            return;
+        }
+        // Repeated key press
+        if action == Some("press") {
+            if let Some(count) = data.get("count").and_then(|v| v.as_u64()) {
+                success!("{} Pressed {} time(s)", color::success_indicator(), count);
+                return;
+            }
+        }
+        // click, hover and check with --all
+        if matches!(action, Some("click") | Some("hover") | Some("check")) {
+            if let Some(count) = data.get("count").and_then(|v| v.as_u64()) {
+                let verb = match action {
+                    Some("click") => "Clicked",
+                    Some("hover") => "Hovered over",
+                    _ => "Checked",
+                };
+                success!(
+                    "{} {} {} element(s)",
+                    color::success_indicator(),
+                    verb,
+                    count
+                );
+                return;
+            }
+        }
         // Count
         if let Some(count) = data.get("count").and_then(|v| v.as_i64()) {
             println!("{}", count);
             return;
         }
+        // check/uncheck report the final state and whether it changed
+        if matches!(action, Some("check") | Some("uncheck")) {
+            if let Some(changed) = data.get("changed").and_then(|v| v.as_bool()) {
+                let checked = data.get("checked").and_then(|v| v.as_bool()) == Some(true);
+                let state = if checked { "checked" } else { "unchecked" };
+                if changed {
+                    success!("{} Now {}", color::success_indicator(), state);
+                } else {
+                    success!("{} Already {}", color::success_indicator(), state);
+                }
+                return;
+            }
+        }
         // Boolean results
         if let Some(visible) = data.get("visible").and_then(|v| v.as_bool()) {
             println!("{}", visible);
@@ -139,16 +665,25 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
                 } else {
                     " ".to_string()
                 };
-                println!("{} [{}] {} - {}", marker, i, title, url);
+                // Tabs outside the default context say which one they are in
+                match tab.get("context").and_then(|v| v.as_str()) {
+                    Some(context) if context != "default" => println!(
+                        "{} [{}] {} - {} {}",
+                        marker,
+                        i,
+                        title,
+                        url,
+                        color::dim(&format!("({})", context))
+                    ),
+                    _ => println!("{} [{}] {} - {}", marker, i, title, url),
+                }
             }
             return;
         }
         // Console logs
         if let Some(logs) = data.get("messages").and_then(|v| v.as_array()) {
             for log in logs {
-                let level = log.get("type").and_then(|v| v.as_str()).unwrap_or("log");
-                let text = log.get("text").and_then(|v| v.as_str()).unwrap_or("");
-                println!("{} {}", color::console_level_prefix(level), text);
+                println!("{}", format_console_message(log));
             }
             return;
         }
@@ -160,6 +695,31 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
             }
             return;
         }
+        // Navigation history
+        if let Some(history) = data.get("history").and_then(|v| v.as_array()) {
+            if history.is_empty() {
+                println!("No navigations yet");
+            }
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+            for entry in history {
+                let index = entry.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+                let url = entry.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                let title = entry
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .filter(|t| !t.is_empty())
+                    .unwrap_or("Untitled");
+                let at = entry
+                    .get("timestamp")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(now);
+                let ago = format!("({} ago)", format_duration(now.saturating_sub(at)));
+                println!("[{}] {} - {} {}", index, title, url, color::dim(&ago));
+            }
+            return;
+        }
         // Cookies
         if let Some(cookies) = data.get("cookies").and_then(|v| v.as_array()) {
             for cookie in cookies {
@@ -186,15 +746,42 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
             }
             return;
         }
+        // Uploaded files
+        if let Some(files) = data.get("uploaded").and_then(|v| v.as_array()) {
+            success!(
+                "{} Uploaded {} file(s)",
+                color::success_indicator(),
+                files.len()
+            );
+            return;
+        }
+        // Imported cookies
+        if let Some(imported) = data.get("imported").and_then(|v| v.as_u64()) {
+            success!(
+                "{} Imported {} cookies",
+                color::success_indicator(),
+                imported
+            );
+            return;
+        }
+        // Cleared input
+        if action == Some("clear") {
+            success!("{} Cleared", color::success_indicator());
+            return;
+        }
         // Cleared requests
         if let Some(cleared) = data.get("cleared").and_then(|v| v.as_bool()) {
             if cleared {
-                println!("{} Request log cleared", color::success_indicator());
+                success!("{} Request log cleared", color::success_indicator());
                 return;
             }
         }
-        // Bounding box
+        // Bounding box (null when the element is not rendered)
         if let Some(box_data) = data.get("box") {
+            if box_data.is_null() {
+                println!("{}", color::dim("(not visible)"));
+                return;
+            }
             println!(
                 "{}",
                 serde_json::to_string_pretty(box_data).unwrap_or_default()
@@ -252,16 +839,32 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
         }
         // Closed
         if data.get("closed").is_some() {
-            println!("{} Browser closed", color::success_indicator());
+            if let Some(path) = data.get("statePath").and_then(|v| v.as_str()) {
+                success!(
+                    "{} State saved to {}",
+                    color::success_indicator(),
+                    color::green(path)
+                );
+            }
+            let kept = data.get("keepDaemon").and_then(|v| v.as_bool()) == Some(true);
+            success!(
+                "{} Browser closed{}",
+                color::success_indicator(),
+                if kept {
+                    color::dim(" (daemon still running)")
+                } else {
+                    String::new()
+                }
+            );
             return;
         }
         // Recording start (has "started" field)
         if let Some(started) = data.get("started").and_then(|v| v.as_bool()) {
             if started {
                 if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
-                    println!("{} Recording started: {}", color::success_indicator(), path);
+                    success!("{} Recording started: {}", color::success_indicator(), path);
                 } else {
-                    println!("{} Recording started", color::success_indicator());
+                    success!("{} Recording started", color::success_indicator());
                 }
                 return;
             }
@@ -273,51 +876,253 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
                 .and_then(|v| v.as_str())
                 .unwrap_or("unknown");
             if let Some(prev_path) = data.get("previousPath").and_then(|v| v.as_str()) {
-                println!(
+                success!(
                     "{} Recording restarted: {} (previous saved to {})",
                     color::success_indicator(),
                     path,
                     prev_path
                 );
             } else {
-                println!("{} Recording started: {}", color::success_indicator(), path);
+                success!("{} Recording started: {}", color::success_indicator(), path);
             }
             return;
         }
-        // Recording stop (has "frames" field - from recording_stop action)
-        if data.get("frames").is_some() {
-            if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
-                if let Some(error) = data.get("error").and_then(|v| v.as_str()) {
-                    println!(
-                        "{} Recording saved to {} - {}",
-                        color::warning_indicator(),
-                        path,
-                        error
-                    );
-                } else {
-                    println!("{} Recording saved to {}", color::success_indicator(), path);
+        // Selected dropdown options
+        if action == Some("select") {
+            if let Some(selected) = data.get("selected").and_then(|v| v.as_array()) {
+                let values: Vec<&str> = selected.iter().filter_map(|v| v.as_str()).collect();
+                success!(
+                    "{} Selected {}",
+                    color::success_indicator(),
+                    values.join(", ")
+                );
+                return;
+            }
+        }
+        // Fuzzy find candidates, best first
+        if action == Some("find") {
+            if let Some(matches) = data.get("matches").and_then(|v| v.as_array()) {
+                if matches.is_empty() {
+                    println!("{}", color::dim("No matching elements"));
                 }
+                for m in matches {
+                    println!("{}", format_ref(m));
+                }
+                return;
+            }
+        }
+        // Fetch: status line, headers, blank line, body (like curl -i)
+        if action == Some("fetch") && data.get("path").is_none() {
+            let status = data.get("status").and_then(|v| v.as_u64()).unwrap_or(0);
+            let line = format_status_line(data);
+            if status >= 400 {
+                println!("{}", color::red(&line));
             } else {
-                println!("{} Recording stopped", color::success_indicator());
+                println!("{}", color::green(&line));
             }
+            print_headers_and_body(data, "save it with --output <file>");
             return;
         }
-        // Download response (has "suggestedFilename" or "filename" field)
-        if data.get("suggestedFilename").is_some() || data.get("filename").is_some() {
-            if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
-                let filename = data
-                    .get("suggestedFilename")
-                    .or_else(|| data.get("filename"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                if filename.is_empty() {
-                    println!(
+        // Capture started or stopped
+        if action == Some("network_capture") {
+            let pattern = data.get("capturing").and_then(|v| v.as_str()).unwrap_or("");
+            let limit = data.get("limit").and_then(|v| v.as_u64()).unwrap_or(0);
+            success!(
+                "{} Capturing bodies of the next {} requests matching {}",
+                color::success_indicator(),
+                limit,
+                pattern
+            );
+            return;
+        }
+        if action == Some("network_capture_stop") {
+            match data.get("stopped").and_then(|v| v.as_str()) {
+                Some(pattern) => success!(
+                    "{} Stopped capturing {}",
+                    color::success_indicator(),
+                    pattern
+                ),
+                None => println!("{}", color::dim("No capture running")),
+            }
+            return;
+        }
+        // Network throttling now in effect
+        if action == Some("throttle") {
+            if let Some(profile) = data.get("profile").and_then(|v| v.as_str()) {
+                if profile == "off" {
+                    success!("{} Throttling off", color::success_indicator());
+                } else {
+                    let limit = |key: &str, unit: &str, none: &str| {
+                        data.get(key)
+                            .and_then(|v| v.as_f64())
+                            .map(|n| format!("{} {}", n, unit))
+                            .unwrap_or_else(|| none.to_string())
+                    };
+                    success!(
+                        "{} Throttling {}: {} down, {} up, {} latency",
+                        color::success_indicator(),
+                        profile,
+                        limit("downloadKbps", "kbps", "unlimited"),
+                        limit("uploadKbps", "kbps", "unlimited"),
+                        limit("latencyMs", "ms", "no added")
+                    );
+                }
+                return;
+            }
+        }
+        // Table already rendered as CSV or JSON by the daemon
+        if action == Some("table") {
+            if let Some(output) = data.get("output").and_then(|v| v.as_str()) {
+                println!("{}", output);
+                return;
+            }
+        }
+        // Forms, each followed by its fields
+        if action == Some("forms") {
+            if let Some(forms) = data.get("forms").and_then(|v| v.as_array()) {
+                if forms.is_empty() {
+                    println!("{}", color::dim("No forms or fields"));
+                }
+                for form in forms {
+                    println!("{}", format_form(form));
+                    let fields = form.get("fields").and_then(|v| v.as_array());
+                    for field in fields.into_iter().flatten() {
+                        println!("  {}", format_form_field(field));
+                    }
+                }
+                return;
+            }
+        }
+        // Links: href, then the link text
+        if action == Some("links") {
+            if let Some(links) = data.get("links").and_then(|v| v.as_array()) {
+                if links.is_empty() {
+                    println!("{}", color::dim("No links"));
+                }
+                for link in links {
+                    let href = link.get("href").and_then(|v| v.as_str()).unwrap_or("");
+                    let text = link.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                    if text.is_empty() {
+                        println!("{}", href);
+                    } else {
+                        println!("{}  {}", href, color::dim(text));
+                    }
+                }
+                return;
+            }
+        }
+        // Refs cached from the last snapshot
+        if action == Some("refs") {
+            if let Some(refs) = data.get("refs").and_then(|v| v.as_array()) {
+                if refs.is_empty() {
+                    println!("{}", color::dim("No refs (run snapshot first)"));
+                }
+                for r in refs {
+                    println!("{}", format_ref(r));
+                }
+                return;
+            }
+        }
+        // Elements on screen, with where they are
+        if action == Some("visible_elements") {
+            if let Some(elements) = data.get("elements").and_then(|v| v.as_array()) {
+                if elements.is_empty() {
+                    println!("{}", color::dim("No interactive elements in the viewport"));
+                }
+                for el in elements {
+                    let b = |key: &str| {
+                        el.get("box")
+                            .and_then(|b| b.get(key))
+                            .and_then(|v| v.as_i64())
+                            .unwrap_or(0)
+                    };
+                    let position = format!(
+                        " {}x{} at ({}, {})",
+                        b("width"),
+                        b("height"),
+                        b("x"),
+                        b("y")
+                    );
+                    println!("{}{}", format_ref(el), color::dim(&position));
+                }
+                return;
+            }
+        }
+        // Daemon lifetime
+        if matches!(action, Some("daemon_status") | Some("daemon_config")) {
+            if action == Some("daemon_config") {
+                success!("{} Daemon limits updated", color::success_indicator());
+            }
+            print_daemon_status(data);
+            return;
+        }
+        // Frame list (before recording stop, which also has a "frames" field)
+        if action == Some("frame_list") {
+            if let Some(frames) = data.get("frames").and_then(|v| v.as_array()) {
+                for frame in frames {
+                    let active = frame.get("active").and_then(|v| v.as_bool()) == Some(true);
+                    let index = frame.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+                    let name = frame.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                    let url = frame.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                    let marker = if active { "→" } else { " " };
+                    let label = match (index, name) {
+                        (0, _) => "(main) ".to_string(),
+                        (_, "") => String::new(),
+                        (_, name) => format!("name={} ", name),
+                    };
+                    println!("{} [{}] {}{}", marker, index, label, url);
+                }
+                return;
+            }
+        }
+        // Frame switch
+        if action == Some("frame") {
+            if let Some(frame) = data.get("frame") {
+                let url = frame.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                success!("{} Switched to frame {}", color::success_indicator(), url);
+                return;
+            }
+        }
+        // Recording stop (has "frames" field - from recording_stop action)
+        if data.get("frames").is_some() {
+            let path = data
+                .get("path")
+                .and_then(|v| v.as_str())
+                .filter(|p| !p.is_empty());
+            if let Some(path) = path {
+                if let Some(error) = data.get("error").and_then(|v| v.as_str()) {
+                    println!(
+                        "{} Recording saved to {} - {}",
+                        color::warning_indicator(),
+                        path,
+                        error
+                    );
+                } else {
+                    success!("{} Recording saved to {}", color::success_indicator(), path);
+                }
+            } else if let Some(error) = data.get("error").and_then(|v| v.as_str()) {
+                println!("{} {}", color::warning_indicator(), error);
+            } else {
+                success!("{} Recording stopped", color::success_indicator());
+            }
+            return;
+        }
+        // Download response (has "suggestedFilename" or "filename" field)
+        if data.get("suggestedFilename").is_some() || data.get("filename").is_some() {
+            if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
+                let filename = data
+                    .get("suggestedFilename")
+                    .or_else(|| data.get("filename"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                if filename.is_empty() {
+                    success!(
                         "{} Downloaded to {}",
                         color::success_indicator(),
                         color::green(path)
                     );
                 } else {
-                    println!(
+                    success!(
                         "{} Downloaded to {} ({})",
                         color::success_indicator(),
                         color::green(path),
@@ -330,49 +1135,112 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
         // Path-based operations (screenshot/pdf/trace/har/download/state/video)
         if let Some(path) = data.get("path").and_then(|v| v.as_str()) {
             match action.unwrap_or("") {
-                "screenshot" => println!(
-                    "{} Screenshot saved to {}",
-                    color::success_indicator(),
-                    color::green(path)
-                ),
-                "pdf" => println!(
+                "screenshot" => match data.get("annotated").and_then(|v| v.as_u64()) {
+                    Some(annotated) => success!(
+                        "{} Screenshot saved to {} {}",
+                        color::success_indicator(),
+                        color::green(path),
+                        color::dim(&format!("({} refs annotated)", annotated))
+                    ),
+                    None => success!(
+                        "{} Screenshot saved to {}",
+                        color::success_indicator(),
+                        color::green(path)
+                    ),
+                },
+                "pdf" => success!(
                     "{} PDF saved to {}",
                     color::success_indicator(),
                     color::green(path)
                 ),
-                "trace_stop" => println!(
-                    "{} Trace saved to {}",
+                "save" => match data.get("resources").and_then(|v| v.as_u64()) {
+                    Some(resources) => success!(
+                        "{} Page saved to {} ({} resources)",
+                        color::success_indicator(),
+                        color::green(path),
+                        resources
+                    ),
+                    None => success!(
+                        "{} Page saved to {}",
+                        color::success_indicator(),
+                        color::green(path)
+                    ),
+                },
+                "trace_stop" => {
+                    let bytes = data.get("bytes").and_then(|v| v.as_u64()).unwrap_or(0);
+                    success!(
+                        "{} Trace saved to {} ({})",
+                        color::success_indicator(),
+                        color::green(path),
+                        format_size(bytes)
+                    );
+                    println!(
+                        "{}",
+                        color::dim(&format!("  View with: npx playwright show-trace {}", path))
+                    );
+                }
+                "har_start" => success!(
+                    "{} Recording HAR to {}",
                     color::success_indicator(),
                     color::green(path)
                 ),
-                "har_stop" => println!(
-                    "{} HAR saved to {}",
+                "har_stop" => success!(
+                    "{} HAR saved to {} ({} requests)",
                     color::success_indicator(),
-                    color::green(path)
+                    color::green(path),
+                    data.get("requestCount")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0)
                 ),
-                "download" | "waitfordownload" => println!(
+                "download" => success!(
                     "{} Download saved to {}",
                     color::success_indicator(),
                     color::green(path)
                 ),
-                "video_stop" => println!(
+                "video_stop" => success!(
                     "{} Video saved to {}",
                     color::success_indicator(),
                     color::green(path)
                 ),
-                "state_save" => println!(
+                "recording_stop" => success!(
+                    "{} Script saved to {} ({} actions)",
+                    color::success_indicator(),
+                    color::green(path),
+                    data.get("actions").and_then(|v| v.as_u64()).unwrap_or(0)
+                ),
+                "cookies_export" => success!(
+                    "{} Exported {} cookies to {}",
+                    color::success_indicator(),
+                    data.get("count").and_then(|v| v.as_u64()).unwrap_or(0),
+                    color::green(path)
+                ),
+                "fetch" => success!(
+                    "{} Response saved to {} ({}, {})",
+                    color::success_indicator(),
+                    color::green(path),
+                    format_size(data.get("bytes").and_then(|v| v.as_u64()).unwrap_or(0)),
+                    format_status_line(data)
+                ),
+                "content" => success!(
+                    "{} HTML saved to {} ({})",
+                    color::success_indicator(),
+                    color::green(path),
+                    format_size(data.get("bytes").and_then(|v| v.as_u64()).unwrap_or(0))
+                ),
+                "state_save" => success!(
                     "{} State saved to {}",
                     color::success_indicator(),
                     color::green(path)
                 ),
                 "state_load" => {
-                    if let Some(note) = data.get("note").and_then(|v| v.as_str()) {
-                        println!("{}", note);
-                    }
-                    println!(
-                        "{} State path set to {}",
+                    let cookies = data.get("cookieCount").and_then(|v| v.as_u64());
+                    let origins = data.get("originCount").and_then(|v| v.as_u64());
+                    success!(
+                        "{} State loaded from {} ({} cookies, {} origins)",
                         color::success_indicator(),
-                        color::green(path)
+                        color::green(path),
+                        cookies.unwrap_or(0),
+                        origins.unwrap_or(0)
                     );
                 }
                 // video_start and other commands that provide a path with a note
@@ -382,7 +1250,7 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
                     }
                     println!("Path: {}", path);
                 }
-                _ => println!(
+                _ => success!(
                     "{} Saved to {}",
                     color::success_indicator(),
                     color::green(path)
@@ -391,1172 +1259,3280 @@ pub fn print_response(resp: &Response, json_mode: bool, action: Option<&str>) {
             return;
         }
 
-        // Informational note
-        if let Some(note) = data.get("note").and_then(|v| v.as_str()) {
-            println!("{}", note);
-            return;
+        // Informational note
+        if let Some(note) = data.get("note").and_then(|v| v.as_str()) {
+            println!("{}", note);
+            return;
+        }
+        // Default success
+        success!("{} Done", color::success_indicator());
+    }
+}
+
+/// One console line: `[error] Uncaught TypeError: ...`
+pub fn format_console_message(message: &serde_json::Value) -> String {
+    let level = message
+        .get("type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("log");
+    let text = message.get("text").and_then(|v| v.as_str()).unwrap_or("");
+    format!("{} {}", color::console_level_prefix(level), text)
+}
+
+/// One `watch` change: `+ li: New row`, `- li: Old row` or `~ 3 results`
+pub fn format_mutation(change: &serde_json::Value) -> String {
+    let text = change.get("text").and_then(|v| v.as_str()).unwrap_or("");
+    let tag = change.get("tag").and_then(|v| v.as_str()).unwrap_or("");
+    match change.get("type").and_then(|v| v.as_str()) {
+        Some("added") => format!("{} {}: {}", color::green("+"), tag, text),
+        Some("removed") => format!("{} {}: {}", color::red("-"), tag, text),
+        _ => format!("{} {}", color::yellow("~"), text),
+    }
+}
+
+/// One network log line: `#12 GET 200 https://... (xhr, 120ms, 3.4 KB)`
+fn format_network_entry(entry: &serde_json::Value) -> String {
+    let id = entry.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+    let method = entry
+        .get("method")
+        .and_then(|v| v.as_str())
+        .unwrap_or("GET");
+    let url = entry.get("url").and_then(|v| v.as_str()).unwrap_or("");
+
+    let status = match (
+        entry.get("failure").and_then(|v| v.as_str()),
+        entry.get("status").and_then(|v| v.as_u64()),
+    ) {
+        (Some(failure), _) => color::red(failure),
+        (None, Some(code)) if code >= 400 => color::red(&code.to_string()),
+        (None, Some(code)) => color::green(&code.to_string()),
+        (None, None) => color::dim("..."),
+    };
+
+    let mut details = Vec::new();
+    if let Some(kind) = entry.get("resourceType").and_then(|v| v.as_str()) {
+        details.push(kind.to_string());
+    }
+    if let Some(ms) = entry.get("duration").and_then(|v| v.as_u64()) {
+        details.push(format!("{}ms", ms));
+    }
+    if let Some(size) = entry.get("size").and_then(|v| v.as_u64()) {
+        details.push(format_size(size));
+    }
+    if entry.get("captured").and_then(|v| v.as_bool()) == Some(true) {
+        details.push("captured".to_string());
+    }
+
+    format!(
+        "{} {} {} {} {}",
+        color::dim(&format!("#{}", id)),
+        method,
+        status,
+        url,
+        color::dim(&format!("({})", details.join(", ")))
+    )
+}
+
+/// One mock line: `#1 **/api/users 200 application/json (512 B, 3 hits)`
+fn format_mock(mock: &serde_json::Value) -> String {
+    let id = mock.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+    let url = mock.get("url").and_then(|v| v.as_str()).unwrap_or("");
+    let status = mock.get("status").and_then(|v| v.as_u64()).unwrap_or(200);
+    let content_type = mock
+        .get("contentType")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let size = mock.get("size").and_then(|v| v.as_u64()).unwrap_or(0);
+    let hits = mock.get("hits").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    format!(
+        "{} {} {} {} {}",
+        color::dim(&format!("#{}", id)),
+        url,
+        status,
+        content_type,
+        color::dim(&format!(
+            "({}, {} {})",
+            format_size(size),
+            hits,
+            if hits == 1 { "hit" } else { "hits" }
+        ))
+    )
+}
+
+/// `confirm: Delete this item?`
+fn format_dialog(dialog: &serde_json::Value) -> String {
+    let kind = dialog
+        .get("type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("dialog");
+    let message = dialog.get("message").and_then(|v| v.as_str()).unwrap_or("");
+    format!("{}: {}", kind, message)
+}
+
+/// `+ button "Save" [ref=e3]`, with the old line under changed entries
+fn format_snapshot_change(change: &serde_json::Value) -> String {
+    let line = change.get("line").and_then(|v| v.as_str()).unwrap_or("");
+    match change.get("type").and_then(|v| v.as_str()) {
+        Some("added") => color::green(&format!("+ {}", line)),
+        Some("removed") => color::red(&format!("- {}", line)),
+        _ => {
+            let before = change.get("before").and_then(|v| v.as_str()).unwrap_or("");
+            format!(
+                "{}\n  {}",
+                color::yellow(&format!("~ {}", line)),
+                color::dim(&format!("was: {}", before))
+            )
+        }
+    }
+}
+
+/// `Form 0 #login POST https://example.com/session`
+fn format_form(form: &serde_json::Value) -> String {
+    let index = form.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+    let mut line = color::bold(&format!("Form {}", index));
+    let id = form.get("id").and_then(|v| v.as_str());
+    let name = form.get("name").and_then(|v| v.as_str());
+    match (id, name) {
+        (Some(id), _) => line.push_str(&format!(" #{}", id)),
+        (None, Some(name)) => line.push_str(&format!(" name={}", name)),
+        (None, None) => {}
+    }
+    match form.get("method").and_then(|v| v.as_str()) {
+        Some(method) => {
+            let target = form.get("action").and_then(|v| v.as_str()).unwrap_or("");
+            line.push_str(&format!(" {} {}", method, target));
+        }
+        None => line.push_str(&color::dim(" (fields outside any form)")),
+    }
+    line
+}
+
+/// `email (email) "Email address" = "" required #email`
+fn format_form_field(field: &serde_json::Value) -> String {
+    let get = |key: &str| field.get(key).and_then(|v| v.as_str()).unwrap_or("");
+    let mut line = format!("{} ({})", get("name"), get("type"));
+    if !get("label").is_empty() {
+        line.push_str(&format!(" {:?}", get("label")));
+    }
+    match field.get("checked").and_then(|v| v.as_bool()) {
+        Some(true) => line.push_str(" checked"),
+        Some(false) => line.push_str(" unchecked"),
+        None => line.push_str(&format!(" = {:?}", get("value"))),
+    }
+    if field.get("required").and_then(|v| v.as_bool()) == Some(true) {
+        line.push_str(&color::yellow(" required"));
+    }
+    if field.get("disabled").and_then(|v| v.as_bool()) == Some(true) {
+        line.push_str(&color::dim(" disabled"));
+    }
+    if let Some(selector) = field.get("selector").and_then(|v| v.as_str()) {
+        line.push_str(&format!(" {}", color::dim(selector)));
+    }
+    line
+}
+
+/// `@e3 button "Save" [nth=1]`
+fn format_ref(entry: &serde_json::Value) -> String {
+    let ref_id = entry.get("ref").and_then(|v| v.as_str()).unwrap_or("");
+    let role = entry.get("role").and_then(|v| v.as_str()).unwrap_or("");
+    let mut line = format!("{} {}", color::cyan(&format!("@{}", ref_id)), role);
+    if let Some(name) = entry.get("name").and_then(|v| v.as_str()) {
+        line.push_str(&format!(" {:?}", name));
+    }
+    if let Some(nth) = entry.get("nth").and_then(|v| v.as_u64()) {
+        line.push_str(&color::dim(&format!(" [nth={}]", nth)));
+    }
+    line
+}
+
+/// Headers (dimmed), then a blank line and the body, if there is one. Binary
+/// bodies are summarized, with `binary_hint` saying how to get them.
+fn print_headers_and_body(message: &serde_json::Value, binary_hint: &str) {
+    if let Some(headers) = message.get("headers").and_then(|v| v.as_object()) {
+        for (name, value) in headers {
+            let header = format!("{}: {}", name, value.as_str().unwrap_or(""));
+            println!("{}", color::dim(&header));
+        }
+    }
+    let body = message.get("body").and_then(|v| v.as_str()).unwrap_or("");
+    if message.get("base64").and_then(|v| v.as_bool()) == Some(true) {
+        let size = format_size((body.len() * 3 / 4) as u64);
+        println!();
+        println!(
+            "{}",
+            color::dim(&format!("(binary body, {}; {})", size, binary_hint))
+        );
+    } else if !body.is_empty() {
+        println!();
+        println!("{}", body);
+    }
+}
+
+/// `404 Not Found`
+fn format_status_line(data: &serde_json::Value) -> String {
+    let status = data.get("status").and_then(|v| v.as_u64()).unwrap_or(0);
+    match data.get("statusText").and_then(|v| v.as_str()) {
+        Some(text) if !text.is_empty() => format!("{} {}", status, text),
+        _ => status.to_string(),
+    }
+}
+
+/// `320 ms`, or `-` when the browser did not report the value
+fn format_ms(value: Option<&serde_json::Value>) -> String {
+    match value.and_then(|v| v.as_u64()) {
+        Some(ms) => format!("{} ms", ms),
+        None => "-".to_string(),
+    }
+}
+
+/// Used-byte totals and the files with the most unused code, for `coverage stop`
+/// `get url --parsed`: the URL, then a line per part it has
+fn print_url_parts(data: &serde_json::Value) {
+    if let Some(url) = data.get("url").and_then(|v| v.as_str()) {
+        println!("{}", url);
+    }
+    for part in ["scheme", "host", "port", "path", "query", "fragment"] {
+        let value = match data.get(part) {
+            None | Some(serde_json::Value::Null) => continue,
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        };
+        println!("  {} {}", color::dim(&format!("{:<8}", part)), value);
+    }
+}
+
+fn print_coverage(data: &serde_json::Value) {
+    const MAX_FILES: usize = 10;
+
+    let bytes = |v: &serde_json::Value, key: &str| v.get(key).and_then(|b| b.as_u64()).unwrap_or(0);
+    let percent = |used: u64, total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            used as f64 * 100.0 / total as f64
+        }
+    };
+
+    for (label, key) in [("JS", "js"), ("CSS", "css")] {
+        let Some(totals) = data.get(key) else {
+            continue;
+        };
+        let (used, total) = (bytes(totals, "usedBytes"), bytes(totals, "totalBytes"));
+        println!(
+            "{} {:<4}{:>5.1}% used ({} of {}, {} files)",
+            color::bold("Coverage"),
+            label,
+            percent(used, total),
+            format_size(used),
+            format_size(total),
+            bytes(totals, "files")
+        );
+    }
+
+    let empty = Vec::new();
+    let files = data
+        .get("files")
+        .and_then(|v| v.as_array())
+        .unwrap_or(&empty);
+    if !files.is_empty() {
+        println!();
+        println!("{}", color::bold("Most unused:"));
+    }
+    for file in files.iter().take(MAX_FILES) {
+        let (used, total) = (bytes(file, "usedBytes"), bytes(file, "totalBytes"));
+        let url = file.get("url").and_then(|v| v.as_str()).unwrap_or("");
+        println!(
+            "  {:<4}{:>10} unused  {:>5.1}% used  {}",
+            file.get("type").and_then(|v| v.as_str()).unwrap_or(""),
+            format_size(total.saturating_sub(used)),
+            percent(used, total),
+            if url.is_empty() { "(inline)" } else { url }
+        );
+    }
+    if files.len() > MAX_FILES {
+        println!(
+            "  {}",
+            color::dim(&format!("... and {} more", files.len() - MAX_FILES))
+        );
+    }
+
+    if let Some(path) = data.get("lcov").and_then(|v| v.as_str()) {
+        println!();
+        success!(
+            "{} LCOV written to {}",
+            color::success_indicator(),
+            color::green(path)
+        );
+    }
+}
+
+/// axe violations for `audit a11y`, most severe impact first
+/// Results that carry the page's `url` among their own fields, which the
+/// navigation branch would otherwise print alone. False for other actions.
+fn print_url_result(action: Option<&str>, data: &serde_json::Value) -> bool {
+    match action {
+        Some("audit") => print_audit(data),
+        Some("metrics") => print_metrics(data),
+        Some("network_show") => print_network_show(data),
+        _ => return false,
+    }
+    true
+}
+
+/// A captured exchange: the request, then the response, each like fetch
+fn print_network_show(data: &serde_json::Value) {
+    let method = data.get("method").and_then(|v| v.as_str()).unwrap_or("GET");
+    let url = data.get("url").and_then(|v| v.as_str()).unwrap_or("");
+    println!("{}", color::bold(&format!("{} {}", method, url)));
+    if let Some(request) = data.get("request") {
+        print_headers_and_body(request, "--json has it base64-encoded");
+    }
+    println!();
+    if let Some(failure) = data.get("failure").and_then(|v| v.as_str()) {
+        println!("{}", color::red(failure));
+        return;
+    }
+    let status = data.get("status").and_then(|v| v.as_u64()).unwrap_or(0);
+    let line = format_status_line(data);
+    if status >= 400 {
+        println!("{}", color::red(&line));
+    } else {
+        println!("{}", color::green(&line));
+    }
+    if let Some(response) = data.get("response") {
+        print_headers_and_body(response, "--json has it base64-encoded");
+    }
+}
+
+fn print_audit(data: &serde_json::Value) {
+    const IMPACTS: &[&str] = &["critical", "serious", "moderate", "minor"];
+    const MAX_NODES: usize = 5;
+
+    let empty = Vec::new();
+    let violations = data
+        .get("violations")
+        .and_then(|v| v.as_array())
+        .unwrap_or(&empty);
+    let impact_of = |v: &serde_json::Value| {
+        v.get("impact")
+            .and_then(|i| i.as_str())
+            .unwrap_or("minor")
+            .to_string()
+    };
+
+    if violations.is_empty() {
+        success!("{} No accessibility violations", color::success_indicator());
+    } else {
+        let counts: Vec<String> = IMPACTS
+            .iter()
+            .filter_map(|impact| {
+                let n = violations
+                    .iter()
+                    .filter(|v| impact_of(v) == *impact)
+                    .count();
+                (n > 0).then(|| format!("{} {}", n, impact))
+            })
+            .collect();
+        println!(
+            "{} {} accessibility violation{} ({})",
+            color::warning_indicator(),
+            violations.len(),
+            if violations.len() == 1 { "" } else { "s" },
+            counts.join(", ")
+        );
+    }
+
+    for impact in IMPACTS {
+        let group: Vec<&serde_json::Value> = violations
+            .iter()
+            .filter(|v| impact_of(v) == *impact)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        let heading = match *impact {
+            "critical" | "serious" => color::red(impact),
+            "moderate" => color::yellow(impact),
+            _ => color::dim(impact),
+        };
+        println!();
+        println!("{}", color::bold(&heading));
+        for v in group {
+            let id = v.get("id").and_then(|x| x.as_str()).unwrap_or("");
+            let help = v.get("help").and_then(|x| x.as_str()).unwrap_or("");
+            let nodes = v.get("nodes").and_then(|x| x.as_array()).unwrap_or(&empty);
+            println!("  {} {}", color::cyan(id), help);
+            if let Some(url) = v.get("helpUrl").and_then(|x| x.as_str()) {
+                println!("    {}", color::dim(url));
+            }
+            for node in nodes.iter().take(MAX_NODES) {
+                let target = node.get("target").and_then(|x| x.as_str()).unwrap_or("");
+                println!("    - {}", target);
+            }
+            if nodes.len() > MAX_NODES {
+                println!(
+                    "    {}",
+                    color::dim(&format!("... and {} more", nodes.len() - MAX_NODES))
+                );
+            }
+        }
+    }
+
+    if data.get("passed").and_then(|v| v.as_bool()) == Some(false) {
+        let level = data.get("failOn").and_then(|v| v.as_str()).unwrap_or("");
+        println!();
+        println!(
+            "{} Failing: violations at or above {}",
+            color::error_indicator(),
+            level
+        );
+    }
+}
+
+/// Page speed summary for `get metrics`
+fn print_metrics(data: &serde_json::Value) {
+    let label = |name: &str| color::bold(&format!("{:<11}", name));
+
+    if let Some(url) = data.get("url").and_then(|v| v.as_str()) {
+        println!("{}", color::dim(url));
+    }
+    if let Some(nav) = data.get("navigation").filter(|v| v.is_object()) {
+        let size = nav
+            .get("transferSize")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        println!(
+            "{} TTFB {}, DOMContentLoaded {}, load {} ({})",
+            label("Navigation"),
+            format_ms(nav.get("ttfb")),
+            format_ms(nav.get("domContentLoaded")),
+            format_ms(nav.get("load")),
+            format_size(size)
+        );
+    }
+    if let Some(paint) = data.get("paint") {
+        let cls = match paint.get("cls").and_then(|v| v.as_f64()) {
+            Some(cls) => cls.to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "{} FCP {}, LCP {}, CLS {}",
+            label("Paint"),
+            format_ms(paint.get("fcp")),
+            format_ms(paint.get("lcp")),
+            cls
+        );
+    }
+    if let Some(heap) = data.get("heap").filter(|v| v.is_object()) {
+        let size = |key: &str| format_size(heap.get(key).and_then(|v| v.as_u64()).unwrap_or(0));
+        println!(
+            "{} {} used of {}",
+            label("JS heap"),
+            size("used"),
+            size("total")
+        );
+    }
+    if let Some(resources) = data.get("resources") {
+        let count = resources.get("count").and_then(|v| v.as_u64()).unwrap_or(0);
+        let bytes = resources
+            .get("transferSize")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let mut line = format!(
+            "{} {} requests, {}",
+            label("Resources"),
+            count,
+            format_size(bytes)
+        );
+        if let Some(by_type) = resources.get("byType").and_then(|v| v.as_object()) {
+            let mut types: Vec<(&String, u64)> = by_type
+                .iter()
+                .map(|(k, v)| (k, v.as_u64().unwrap_or(0)))
+                .collect();
+            types.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            let parts: Vec<String> = types.iter().map(|(k, n)| format!("{} {}", k, n)).collect();
+            if !parts.is_empty() {
+                line.push_str(&color::dim(&format!(" ({})", parts.join(", "))));
+            }
+        }
+        println!("{}", line);
+    }
+}
+
+/// Uptime and what is left of each limit, for `daemon status`
+fn print_daemon_status(data: &serde_json::Value) {
+    let pid = data.get("pid").and_then(|v| v.as_u64()).unwrap_or(0);
+    let version = data.get("version").and_then(|v| v.as_str()).unwrap_or("");
+    let uptime = data.get("uptime").and_then(|v| v.as_u64()).unwrap_or(0);
+    println!(
+        "Daemon pid {} ({}), up {}",
+        pid,
+        version,
+        format_duration(uptime)
+    );
+    for (label, limit, remaining) in [
+        ("Idle timeout", "idleTimeout", "idleRemaining"),
+        ("Max lifetime", "maxLifetime", "lifetimeRemaining"),
+    ] {
+        match data.get(limit).and_then(|v| v.as_u64()) {
+            Some(ms) => {
+                let left = data.get(remaining).and_then(|v| v.as_u64()).unwrap_or(0);
+                println!(
+                    "{}: {} {}",
+                    label,
+                    format_duration(ms),
+                    color::dim(&format!("({} left)", format_duration(left)))
+                );
+            }
+            None => println!("{}: {}", label, color::dim("off")),
+        }
+    }
+}
+
+/// Daemon health and the browser's tabs, for `status`
+fn print_status(data: &serde_json::Value) {
+    let pid = data.get("pid").and_then(|v| v.as_u64()).unwrap_or(0);
+    let version = data.get("version").and_then(|v| v.as_str()).unwrap_or("");
+    let uptime = data.get("uptime").and_then(|v| v.as_u64()).unwrap_or(0);
+    let memory = data.get("memory").and_then(|v| v.as_u64()).unwrap_or(0);
+    println!(
+        "Daemon pid {} ({}), up {}, {} resident",
+        pid,
+        version,
+        format_duration(uptime),
+        format_size(memory)
+    );
+    if let Some(ping) = data.get("ping").and_then(|v| v.as_f64()) {
+        println!("Ping: {:.1}ms", ping);
+    }
+
+    let tabs = data
+        .get("tabs")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    if data.get("launched").and_then(|v| v.as_bool()) != Some(true) {
+        println!("Browser: {}", color::dim("not launched"));
+        return;
+    }
+    let browser = data
+        .get("browserVersion")
+        .and_then(|v| v.as_str())
+        .unwrap_or("launched");
+    let count = match tabs.len() {
+        1 => "(1 tab)".to_string(),
+        n => format!("({} tabs)", n),
+    };
+    println!("Browser: {} {}", browser, color::dim(&count));
+    for (i, tab) in tabs.iter().enumerate() {
+        let url = tab.get("url").and_then(|v| v.as_str()).unwrap_or("");
+        let active = tab.get("active").and_then(|v| v.as_bool()).unwrap_or(false);
+        let marker = if active {
+            color::cyan("→")
+        } else {
+            " ".to_string()
+        };
+        println!("{} [{}] {}", marker, i, url);
+    }
+}
+
+/// `1h 47m`, `9m 58s` or `45s`: the two largest units
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    match (h, m) {
+        (0, 0) => format!("{}s", s),
+        (0, _) if s == 0 => format!("{}m", m),
+        (0, _) => format!("{}m {}s", m, s),
+        (_, 0) => format!("{}h", h),
+        _ => format!("{}h {}m", h, m),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+/// Write an image returned as base64 (screenshot --stdout) to stdout, either
+/// as raw bytes for piping or as the base64 text itself.
+/// Show how much of a large response has arrived, on stderr when it is a
+/// terminal. Responses under 1 MB arrive too quickly to be worth it.
+pub fn print_transfer_progress(received: u64, total: u64) {
+    const MIN_SIZE: u64 = 1024 * 1024;
+    if total < MIN_SIZE || !io::stderr().is_terminal() {
+        return;
+    }
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let text = format!("Receiving {:.1}/{:.1} MB", mb(received), mb(total));
+    eprint!("\r{}", color::dim(&text));
+    if received >= total {
+        // Clear the line before the response is printed
+        eprint!("\r{}\r", " ".repeat(text.len()));
+    }
+}
+
+pub fn print_image_data(resp: &Response, as_base64: bool) -> Result<(), String> {
+    let data = resp
+        .data
+        .as_ref()
+        .and_then(|d| d.get("base64"))
+        .and_then(|v| v.as_str())
+        .ok_or("Response did not include image data")?;
+
+    if as_base64 {
+        println!("{}", data);
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout().lock();
+    if stdout.is_terminal() {
+        return Err(
+            "Refusing to write binary image data to a terminal. Pipe the output or use --base64"
+                .to_string(),
+        );
+    }
+    let bytes = STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid image data: {}", e))?;
+    stdout
+        .write_all(&bytes)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| format!("Failed to write image: {}", e))
+}
+
+/// What `-o` writes for a successful response: the whole document in a
+/// structured format, otherwise the result itself (image bytes, snapshot,
+/// Markdown, HTML, text, value, table, fetched body or eval result). `None`
+/// when the command has no such result.
+pub fn payload(resp: &Response, json_mode: bool) -> Option<Vec<u8>> {
+    if json_mode {
+        return Some(format::render(resp).into_bytes());
+    }
+    let data = resp.data.as_ref()?;
+    if let Some(image) = data.get("base64").and_then(|v| v.as_str()) {
+        return STANDARD.decode(image).ok();
+    }
+    // A fetch or response body as it came, binary ones sent as base64
+    if let Some(body) = data.get("body").and_then(|v| v.as_str()) {
+        if data.get("base64").and_then(|v| v.as_bool()) == Some(true) {
+            return STANDARD.decode(body).ok();
+        }
+        return Some(body.as_bytes().to_vec());
+    }
+    for key in ["snapshot", "markdown", "html", "text", "value", "output"] {
+        if let Some(text) = data.get(key).and_then(|v| v.as_str()) {
+            return Some(format!("{}\n", text).into_bytes());
+        }
+    }
+    // A `snapshot --format json` tree, an eval result or a body parsed as JSON
+    let value = data
+        .get("nodes")
+        .or_else(|| data.get("result"))
+        .or_else(|| data.get("body"))?;
+    let json = serde_json::to_string_pretty(value).unwrap_or_default();
+    Some(format!("{}\n", json).into_bytes())
+}
+
+/// Print a successful response's [`payload`] to `path` (stdout for `-`).
+/// Commands without one print as usual, with a warning.
+pub fn write_response(
+    resp: &Response,
+    json_mode: bool,
+    action: Option<&str>,
+    path: &str,
+) -> Result<(), String> {
+    let Some(bytes) = payload(resp, json_mode) else {
+        eprintln!(
+            "{} {} has no result to write to {}; use --json to write the response",
+            color::warning_indicator(),
+            action.unwrap_or("This command"),
+            path
+        );
+        print_response(resp, json_mode, action);
+        return Ok(());
+    };
+    if path == "-" {
+        let mut stdout = io::stdout().lock();
+        return stdout
+            .write_all(&bytes)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| format!("Failed to write output: {}", e));
+    }
+    std::fs::write(path, &bytes).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    if !json_mode {
+        eprintln!(
+            "{} Wrote {} to {}",
+            color::success_indicator(),
+            format_size(bytes.len() as u64),
+            color::green(path)
+        );
+    }
+    Ok(())
+}
+
+/// Print command-specific help. Returns true if help was printed, false if command unknown.
+pub fn print_command_help(command: &str) -> bool {
+    let help = match command {
+        // === Navigation ===
+        "open" | "goto" | "navigate" => {
+            r##"
+agent-browser open - Navigate to a URL
+
+Usage: agent-browser open <url> [options]
+
+Navigates the browser to the specified URL. If no protocol is provided,
+https:// is automatically prepended.
+
+Aliases: goto, navigate
+
+Options:
+  --new-tab            Open it in a new tab, leaving the current page as it is
+  --window             Open it in a new window, with its own cookies and storage
+  --wait-until <state> When navigation is done: load (default), domcontentloaded,
+                       networkidle, or commit (response received)
+  --referer <url>      Send a Referer header with the request
+  --timeout <ms>       Fail if navigation takes longer than this
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+  --headers <json>     Set HTTP headers (scoped to this origin)
+  --headed             Show browser window
+
+Examples:
+  agent-browser open example.com
+  agent-browser open https://github.com
+  agent-browser open localhost:3000
+  agent-browser open example.com --wait-until domcontentloaded --timeout 60000
+  agent-browser open docs.example.com --new-tab
+  agent-browser open api.example.com --headers '{"Authorization": "Bearer token"}'
+    # ^ Headers only sent to api.example.com, not other domains
+"##
+        }
+        "back" => {
+            r##"
+agent-browser back - Navigate back in history
+
+Usage: agent-browser back
+
+Goes back one page in the browser history, equivalent to clicking
+the browser's back button.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser back
+"##
+        }
+        "history" => {
+            r##"
+agent-browser history - List recent navigations
+
+Usage: agent-browser history [-n <count>]
+       agent-browser history go <n>
+
+Lists the pages visited in this session, in any tab, oldest first and
+numbered. Reloads count once. `history go <n>` opens entry n in the
+current tab, however far back it is.
+
+Options:
+  -n <count>           How many recent entries to show (default: 20)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser history
+  agent-browser history -n 5 --json
+  agent-browser history go 3
+"##
+        }
+        "forward" => {
+            r##"
+agent-browser forward - Navigate forward in history
+
+Usage: agent-browser forward
+
+Goes forward one page in the browser history, equivalent to clicking
+the browser's forward button.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser forward
+"##
+        }
+        "reload" => {
+            r##"
+agent-browser reload - Reload the current page
+
+Usage: agent-browser reload
+
+Reloads the current page, equivalent to pressing F5 or clicking
+the browser's reload button.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser reload
+"##
+        }
+
+        // === Core Actions ===
+        "click" => {
+            r##"
+agent-browser click - Click an element
+
+Usage: agent-browser click <selector> [--nth <n> | --all]
+       agent-browser click --text <text> [--exact] [--nth <n>]
+
+Clicks on the specified element. The selector can be a CSS selector,
+text=, role= or xpath= selector, or an element reference from snapshot
+(e.g., @e1). A selector matching several elements is an error unless --nth
+picks one or --all clicks each of them in turn.
+
+With --text, the element is found by what it says instead: a button, link,
+tab, menu item, checkbox, radio or option with that accessible name, or if
+there is none, an element containing the text. Several matches are an error
+unless --nth picks one.
+
+Options:
+  --text <text>        Click the element with this label or text
+  --exact              Match the whole text, case-sensitively, not a substring
+  --nth <n>            Which match to click (0 is the first, -1 the last)
+  --all                Click every match of the selector, in page order
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser click "#submit-button"
+  agent-browser click ".result a" --nth 2
+  agent-browser click ".accordion-header" --all
+  agent-browser click @e1
+  agent-browser click "button.primary"
+  agent-browser click "//button[@type='submit']"
+  agent-browser click "text=Sign in"
+  agent-browser click 'role=button[name="Save"]'
+  agent-browser click --text "Sign in"
+  agent-browser click --text "Add to cart" --nth 1
+"##
+        }
+        "dblclick" => {
+            r##"
+agent-browser dblclick - Double-click an element
+
+Usage: agent-browser dblclick <selector>
+
+Double-clicks on the specified element. Useful for text selection
+or triggering double-click handlers.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser dblclick "#editable-text"
+  agent-browser dblclick @e5
+"##
+        }
+        "fill" => {
+            r##"
+agent-browser fill - Clear and fill an input field
+
+Usage: agent-browser fill <selector> <text>
+       agent-browser fill <selector> --stdin
+       agent-browser fill <selector> --secret <ENV_NAME>
+       agent-browser fill --form <file.json|file.yaml>
+
+Clears the input field and fills it with the specified text.
+This replaces any existing content in the field.
+
+With --form, every field in the file is filled in one request, in the
+order listed. The file maps fields to values: a field is a selector, ref,
+label or name attribute. Checkboxes and radio buttons take true or false,
+selects an option's value or label. Fields that are disabled or read-only
+are skipped, and the output lists them along with fields not found.
+
+  {"#email": "ada@example.com", "Password": "hunter2", "Remember me": true}
+
+Options:
+  --stdin              Read the value from stdin (keeps it out of shell history)
+  --secret <ENV_NAME>  Read the value from an environment variable; it is
+                       masked as ******** in all output
+  --form <file>        Fill the fields in a JSON object or flat YAML file
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser fill "#email" "user@example.com"
+  agent-browser fill @e3 "Hello World"
+  agent-browser fill "input[name='search']" "query"
+  agent-browser fill "#password" --secret LOGIN_PASSWORD
+  pass show site | agent-browser fill "#password" --stdin
+  agent-browser fill --form signup.yaml
+"##
+        }
+        "type" => {
+            r##"
+agent-browser type - Type text into an element
+
+Usage: agent-browser type <selector> <text>
+
+Types text into the specified element character by character.
+Unlike fill, this does not clear existing content first.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser type "#search" "hello"
+  agent-browser type @e2 "additional text"
+"##
+        }
+        "hover" => {
+            r##"
+agent-browser hover - Hover over an element
+
+Usage: agent-browser hover <selector> [--nth <n> | --all]
+       agent-browser hover --text <text> [--exact] [--nth <n>]
+
+Moves the mouse to hover over the specified element. Useful for
+triggering hover states or dropdown menus. --text finds the element by
+its label or text, and --nth and --all pick matches, as for click.
+
+Options:
+  --text <text>        Hover the element with this label or text
+  --exact              Match the whole text, case-sensitively, not a substring
+  --nth <n>            Which match to hover (0 is the first, -1 the last)
+  --all                Hover over every match in turn
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser hover "#dropdown-trigger"
+  agent-browser hover @e4
+  agent-browser hover --text "Products"
+"##
+        }
+        "focus" => {
+            r##"
+agent-browser focus - Focus an element
+
+Usage: agent-browser focus <selector>
+
+Sets keyboard focus to the specified element.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser focus "#input-field"
+  agent-browser focus @e2
+"##
+        }
+        "blur" => {
+            r##"
+agent-browser blur - Remove focus from an element
+
+Usage: agent-browser blur <selector>
+
+Removes keyboard focus from the specified element, firing its blur and
+change handlers (useful for triggering validation after fill).
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser blur "#email"
+  agent-browser blur @e2
+"##
+        }
+        "clear" => {
+            r##"
+agent-browser clear - Clear an input
+
+Usage: agent-browser clear <selector>
+
+Empties an input, textarea or contenteditable element. Fires input events
+like a user deleting the text.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser clear "#search"
+  agent-browser clear @e3
+"##
+        }
+        "check" => {
+            r##"
+agent-browser check - Check a checkbox
+
+Usage: agent-browser check <selector> [--nth <n> | --all]
+
+Checks a checkbox or radio button. If already checked, no action is taken.
+Fails if the element is not a checkbox, radio button or ARIA equivalent.
+
+Options:
+  --nth <n>            Which match to check (0 is the first, -1 the last)
+  --all                Check every match, e.g. all the boxes of a list
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser check "#terms-checkbox"
+  agent-browser check @e7
+  agent-browser check "table input[type=checkbox]" --all
+"##
+        }
+        "uncheck" => {
+            r##"
+agent-browser uncheck - Uncheck a checkbox
+
+Usage: agent-browser uncheck <selector>
+
+Unchecks a checkbox. If already unchecked, no action is taken. Fails if the
+element is not a checkbox, or is a checked radio button (check another option
+in the group instead).
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser uncheck "#newsletter-opt-in"
+  agent-browser uncheck @e8
+"##
+        }
+        "select" => {
+            r##"
+agent-browser select - Select a dropdown option
+
+Usage: agent-browser select <selector> <value...|--label <text>...|--index <n>>
+
+Selects one or more options in a <select> dropdown by value, by visible
+label, or by position.
+
+Options:
+  --label <text>       Select the option with this visible text (repeatable)
+  --index <n>          Select the option at this position (0-based)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser select "#country" "US"
+  agent-browser select @e5 "option2"
+  agent-browser select "#menu" "opt1" "opt2" "opt3"
+  agent-browser select "#country" --label "United States"
+  agent-browser select "#size" --index 0
+"##
+        }
+        "drag" => {
+            r##"
+agent-browser drag - Drag and drop
+
+Usage: agent-browser drag <source> <target> [--steps <n>]
+
+Drags an element from source to target location. Works for kanban boards,
+sortable lists, and drop zones that listen for HTML5 drag events.
+
+Options:
+  --steps <n>          Move the mouse to the target in n increments instead of
+                       one native drag (for sliders and pointer-tracking widgets)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser drag "#draggable" "#drop-zone"
+  agent-browser drag @e1 @e2
+  agent-browser drag "#slider-handle" "#slider-end" --steps 20
+"##
+        }
+        "upload" => {
+            r##"
+agent-browser upload - Upload files
+
+Usage: agent-browser upload <selector> <files...>
+
+Sets the files of an <input type="file"> element. Every file must exist
+locally; paths are resolved against the current directory before they are
+sent to the daemon.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser upload "#file-input" ./document.pdf
+  agent-browser upload @e3 ./image1.png ./image2.png
+"##
+        }
+        "download" => {
+            r##"
+agent-browser download - Download files
+
+Usage:
+  agent-browser download <selector> <path>
+  agent-browser download wait [path] [--timeout <ms>]
+  agent-browser download dir <path>
+
+Clicks an element that triggers a download and saves the file to the specified path.
+
+Subcommands:
+  wait [path]          Block until the next download completes and print where
+                       it was saved (optionally save it to path)
+  dir <path>           Save every download to this directory, including ones a
+                       click starts, unless a command gives it a path
+
+Arguments:
+  selector             Element to click (CSS selector or @ref)
+  path                 Path where the downloaded file will be saved
+
+Options:
+  --timeout <ms>       Timeout for download wait (default: action timeout)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser download "#download-btn" ./file.pdf
+  agent-browser download @e5 ./report.xlsx
+  agent-browser download "a[href$='.zip']" ./archive.zip
+  agent-browser download dir ./downloads
+  agent-browser download wait --timeout 30000
+"##
+        }
+
+        // === Keyboard ===
+        "press" | "key" => {
+            r##"
+agent-browser press - Press a key or key combination
+
+Usage: agent-browser press <key|chord> [x<count>]
+
+Presses a key or key combination. Supports special keys and modifiers.
+Append x<count> to press it repeatedly (up to 1000 times).
+
+Aliases: key
+
+Special Keys:
+  Enter, Tab, Escape, Backspace, Delete, Space
+  ArrowUp, ArrowDown, ArrowLeft, ArrowRight
+  Home, End, PageUp, PageDown
+  F1-F12
+
+Modifiers (combine with +):
+  Control, Alt, Shift, Meta, ControlOrMeta
+  Aliases: ctrl, cmd, command, option, mod (Control on Linux/Windows, Meta on macOS)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser press Enter
+  agent-browser press Tab
+  agent-browser press Control+a
+  agent-browser press Control+Shift+s
+  agent-browser press Escape
+  agent-browser press Tab x3
+  agent-browser press mod+a
+"##
+        }
+        "keydown" => {
+            r##"
+agent-browser keydown - Press a key down (without release)
+
+Usage: agent-browser keydown <key>
+
+Presses a key down without releasing it. Use keyup to release.
+Useful for holding modifier keys: held modifiers also apply to later clicks
+and key presses, e.g. keydown Shift, click two items, keyup Shift.
+
+Aliases: keys down
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser keydown Shift
+  agent-browser keydown Control
+"##
+        }
+        "keyup" => {
+            r##"
+agent-browser keyup - Release a key
+
+Usage: agent-browser keyup <key>
+
+Releases a key that was pressed with keydown.
+
+Aliases: keys up
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser keyup Shift
+  agent-browser keyup Control
+"##
+        }
+        "keys" => {
+            r##"
+agent-browser keys - Hold or release a key
+
+Usage: agent-browser keys <down|up> <key>
+
+Same as keydown and keyup: keys down presses a key without releasing it,
+keys up releases it.
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser keys down Shift
+  agent-browser keys up Shift
+"##
+        }
+
+        // === Scroll ===
+        "scroll" => {
+            r##"
+agent-browser scroll - Scroll the page
+
+Usage: agent-browser scroll [direction] [amount]
+       agent-browser scroll to <selector>
+       agent-browser scroll by <dx> <dy>
+       agent-browser scroll top|bottom
+
+Scrolls the page (or the selected frame) in the specified direction, by an
+exact offset, to an element, or to the start or end of the page. Scrolling to
+the bottom is useful for triggering lazy-loaded content.
+
+Arguments:
+  direction            up, down, left, right (default: down)
+  amount               Pixels to scroll (default: 300)
+  to <selector>        Scroll until the element is in view
+  by <dx> <dy>         Scroll by pixel offsets (negative scrolls up/left)
+  top, bottom          Jump to the top or bottom of the page
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser scroll
+  agent-browser scroll down 500
+  agent-browser scroll up 200
+  agent-browser scroll left 100
+  agent-browser scroll to "#comments"
+  agent-browser scroll by 0 -250
+  agent-browser scroll bottom
+"##
+        }
+        "scrollintoview" | "scrollinto" => {
+            r##"
+agent-browser scrollintoview - Scroll element into view
+
+Usage: agent-browser scrollintoview <selector>
+
+Scrolls the page until the specified element is visible in the viewport.
+
+Aliases: scrollinto
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser scrollintoview "#footer"
+  agent-browser scrollintoview @e15
+"##
+        }
+
+        // === Wait ===
+        "wait" => {
+            r##"
+agent-browser wait - Wait for condition
+
+Usage: agent-browser wait <selector|ms|option> [--timeout <ms>]
+
+Waits for an element to appear, a timeout, or other conditions.
+
+Modes:
+  <selector>           Wait for element to appear
+  <ms>                 Wait for specified milliseconds
+  --hidden <selector>  Wait for element to be hidden or removed
+  --url <pattern>      Wait for URL to match pattern
+  --load <state>       Wait for load state (load, domcontentloaded, networkidle)
+  --idle               Wait for the network to be idle (same as --load networkidle)
+  --spa-idle           Wait for a client-side route change to settle: no route
+                       change, DOM change or request for the quiet period
+  --fn <expression>    Wait for JavaScript expression to be truthy
+  --text <text>        Wait for text to appear on page
+  --download [path]    Wait for a download to complete (optionally save to path)
+  --request <url>      Wait for a request to a matching URL to be sent
+  --response <url>     Wait for a response from a matching URL to finish loading
+
+URLs are globs (** across path segments) or, without a *, substrings. Only
+requests sent after the wait starts count, so run it right after the action
+that triggers the call.
+
+Options:
+  --timeout <ms>       Give up after this long (all modes except <ms>)
+  --status <code>      With --response, only a response with this status
+  --quiet <ms>         With --spa-idle, how long it has to stay quiet (default: 500)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser wait "#loading-spinner"
+  agent-browser wait 2000
+  agent-browser wait --url "**/dashboard"
+  agent-browser wait --load networkidle
+  agent-browser wait --idle --timeout 10000
+  agent-browser wait --spa-idle --quiet 1s
+  agent-browser wait --hidden "#loading-spinner"
+  agent-browser wait --fn "window.appReady === true"
+  agent-browser wait --text "Welcome back"
+  agent-browser wait --download ./file.pdf
+  agent-browser wait --download ./report.xlsx --timeout 30000
+  agent-browser wait --request "**/api/search*"
+  agent-browser wait --response /api/cart --status 200
+"##
+        }
+        "expect" => {
+            r##"
+agent-browser expect - Assert a condition, retrying until it holds
+
+Usage: agent-browser expect <text|visible|url|title> [args] [--timeout <ms>]
+
+Polls the page until the condition holds. On timeout, prints the expected and
+actual values and exits 1, so a script or shell pipeline stops at the first
+failed check.
+
+Assertions:
+  text <selector> <expected>  Element text contains expected
+  visible <selector>          Element is visible
+  url <pattern>               URL matches a glob (** across segments) or substring
+  title <pattern>             Page title contains pattern
+
+Patterns written as /source/flags are matched as regular expressions.
+
+Options:
+  --timeout <ms>       How long to keep retrying (default: 5000)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser expect text "#status" "Saved"
+  agent-browser expect visible @e4 --timeout 10000
+  agent-browser expect url "**/dashboard"
+  agent-browser expect title "/^Inbox \(\d+\)$/"
+"##
+        }
+
+        // === Screenshot/PDF ===
+        "screenshot" => {
+            r##"
+agent-browser screenshot - Take a screenshot
+
+Usage:
+  agent-browser screenshot [selector] [path] [options]
+  agent-browser screenshot diff <baseline.png> [--threshold <0-1>] [--out <diff.png>]
+
+Captures a screenshot of the current page, or of a single element when a
+selector is given. If no path is provided, saves to a temporary directory
+with a generated filename.
+
+Options:
+  --full, -f           Capture full page (not just viewport)
+  --full-page          Same as --full
+  --selector <sel>     Capture only this element
+  --format <fmt>       png (default), jpeg or webp; inferred from the path
+                       extension when omitted
+  --quality <0-100>    Image quality for jpeg and webp
+  --clip <x,y,w,h>     Capture only this region (CSS pixels)
+  --stdout             Write the image to stdout instead of a file
+  --base64             With --stdout, print base64 text instead of raw bytes
+  --annotate-refs      Outline each element of the last snapshot and label it
+                       with its @ref (run snapshot first)
+
+Diff:
+  Captures the page as PNG and compares it pixel by pixel against a baseline.
+  Changed pixels are drawn in red in the diff image, which defaults to
+  <baseline>.diff.png. Exits with status 1 when the fraction of changed
+  pixels is above the threshold, or when the sizes differ.
+
+  --threshold <0-1>    Allowed fraction of changed pixels (default: 0.01)
+  --out <path>         Where to write the diff image
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser screenshot
+  agent-browser screenshot ./screenshot.png
+  agent-browser screenshot --full ./full-page.png
+  agent-browser screenshot @e3 ./button.png
+  agent-browser screenshot --selector "#chart" ./chart.webp --quality 90
+  agent-browser screenshot --format jpeg --quality 70 --clip 0,0,800,600
+  agent-browser screenshot --stdout > page.png
+  agent-browser screenshot --stdout --base64 | my-image-model
+  agent-browser screenshot --annotate-refs ./labelled.png
+  agent-browser screenshot diff ./baseline.png
+  agent-browser screenshot diff ./baseline.png --full --threshold 0 --out ./diff.png
+"##
+        }
+        "pdf" => {
+            r##"
+agent-browser pdf - Save page as PDF
+
+Usage: agent-browser pdf [path] [options]
+
+Saves the current page as a PDF file. If no path is provided,
+saves to a temporary directory with a generated filename.
+
+Options:
+  --format <size>      Paper size: Letter (default), Legal, Tabloid, Ledger, A0-A6
+  --landscape          Use landscape orientation
+  --margin <margin>    Page margin for all sides (1cm, 0.5in, 20px) or
+                       top,right,bottom,left
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser pdf
+  agent-browser pdf ./page.pdf
+  agent-browser pdf ~/Documents/report.pdf --format A4
+  agent-browser pdf ./slides.pdf --landscape --margin 0.5in
+  agent-browser pdf ./report.pdf --margin 1cm,2cm,1cm,2cm
+"##
+        }
+
+        "read" => {
+            r##"
+agent-browser read - Read the page a chunk at a time
+
+Usage: agent-browser read [selector] [--max-chars <n>]
+       agent-browser read next|prev [--max-chars <n>]
+
+Reads the page the way a screen reader would: one line per heading,
+paragraph, text, link or control, in document order, with states and
+refs. Containers without a name are read through what they contain.
+
+`read` starts at the top of the page, or at the element a selector or
+ref matches, and takes a fresh snapshot so the refs it shows can be used
+at once. `read next` and `read prev` move through the same lines.
+
+Options:
+  --max-chars <n>      Characters per chunk (default: 2000)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser read
+  agent-browser read "#pricing"
+  agent-browser read next
+  agent-browser read prev --max-chars 500
+"##
+        }
+        "save" => {
+            r##"
+agent-browser save - Save the rendered page for offline viewing
+
+Usage: agent-browser save <path.mhtml>
+       agent-browser save --html-dir <dir>
+
+Saves the page as it is now, after scripts have run. A path saves one
+MHTML file that Chromium-based browsers open directly. --html-dir writes
+index.html with copies of the images, stylesheets and fonts the page
+loaded, under <dir>/<host>/<path>; scripts are left out so the page opens
+as it was saved. Chromium only.
+
+Options:
+  --html-dir <dir>     Save HTML and resources to a directory instead
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser save ./bug-report.mhtml
+  agent-browser save --html-dir ./checkout-page
+"##
+        }
+
+        // === Snapshot ===
+        "snapshot" => {
+            r##"
+agent-browser snapshot - Get accessibility tree snapshot
+
+Usage: agent-browser snapshot [options]
+       agent-browser snapshot diff [options]
+
+Returns an accessibility tree representation of the page with element
+references (like @e1, @e2) that can be used in subsequent commands.
+Designed for AI agents to understand page structure.
+
+Refs stay valid until the next snapshot and resolve in the frame and scope
+they were taken from. Use `agent-browser refs` to list them again.
+
+`snapshot diff` takes a new snapshot and prints only the lines that were added
+(+), removed (-) or changed (~) since the previous one, ignoring ref numbering.
+The new snapshot becomes the baseline for the next diff, and its refs are the
+ones to use. Use the same options for both snapshots.
+
+With --max-chars or --page, long output is split between lines into pages. A
+final line reports the page, the lines shown and how many characters were
+omitted, and which --page to request next.
+
+With --coords, each ref gets its bounding box as [box=x,y,width,height] in CSS
+pixels relative to the viewport, so it lines up with a screenshot. [offscreen]
+marks boxes outside the viewport (scroll to reach them) and [hidden] elements
+that aren't drawn. In json and yaml the box is a `box` field.
+
+Every snapshot has a content hash (in --json output). Polling with
+--if-changed <hash> prints just "Unchanged" while the tree still has that
+hash, and otherwise the snapshot followed by its new hash. Use the same
+options for every poll.
+
+Options:
+  -i, --interactive    Only include interactive elements
+  -C, --cursor         Include cursor-interactive elements (cursor:pointer, onclick, tabindex)
+  -c, --compact        Remove empty structural elements
+  -d, --depth <n>      Limit tree depth
+  -s, --selector <sel> Scope snapshot to CSS selector
+  --format <fmt>       Output format: text (default), json, yaml, md
+  --max-chars <n>      Split output into pages of at most n characters
+  --page <k>           Show page k (default: 1, page size 20000 chars)
+  --coords             Add each ref's bounding box and visibility
+  --if-changed <hash>  Only return the tree if its hash differs from <hash>
+
+Formats:
+  text                 Indented tree with [ref=e1] markers
+  json                 Nested nodes: role, name, ref, attributes, text, children
+  yaml                 The same nodes as YAML
+  md                   Nested Markdown list, refs as `@e1`
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser snapshot
+  agent-browser snapshot -i
+  agent-browser snapshot -i -C         # Interactive + cursor-interactive elements
+  agent-browser snapshot --compact --depth 5
+  agent-browser snapshot -s "#main-content"
+  agent-browser snapshot -i --format json
+  agent-browser snapshot --max-chars 8000 --page 2
+  agent-browser snapshot -i --coords   # Where each element is on screen
+  agent-browser snapshot -i --if-changed 3f2a9c0d1b7e4a56
+  agent-browser snapshot diff -i       # What changed since the last snapshot -i
+"##
+        }
+
+        "refs" => {
+            r##"
+agent-browser refs - List refs from the last snapshot
+
+Usage: agent-browser refs [--filter <text>]
+
+Lists the refs cached from the last snapshot (or find) with their roles and
+names, without taking a new snapshot.
+
+Options:
+  --filter <text>      Only list refs whose role or name contains the text
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser refs
+  agent-browser refs --filter button
+  agent-browser refs -f "sign in"
+"##
+        }
+
+        "visible-elements" => {
+            r##"
+agent-browser visible-elements - List interactive elements on screen
+
+Usage: agent-browser visible-elements [--role <role>[,<role>...]]
+
+Takes a fresh interactive snapshot and lists the elements that are at least
+partly inside the viewport, with their refs and boxes (x, y relative to the
+viewport, in CSS pixels). Elements scrolled out of view or not drawn are left
+out. The refs can be used in the next command, as after a snapshot.
+
+Options:
+  --role <role>        Only elements with this role; comma-separated or repeated
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser visible-elements
+  agent-browser visible-elements --role button,link
+  agent-browser scroll down && agent-browser visible-elements --role textbox
+"##
         }
-        // Default success
-        println!("{} Done", color::success_indicator());
-    }
-}
 
-/// Print command-specific help. Returns true if help was printed, false if command unknown.
-pub fn print_command_help(command: &str) -> bool {
-    let help = match command {
-        // === Navigation ===
-        "open" | "goto" | "navigate" => {
+        // === Eval ===
+        "eval" => {
             r##"
-agent-browser open - Navigate to a URL
+agent-browser eval - Execute JavaScript
+
+Usage: agent-browser eval [options] <script>
 
-Usage: agent-browser open <url>
+Executes JavaScript code in the browser context and returns the result.
 
-Navigates the browser to the specified URL. If no protocol is provided,
-https:// is automatically prepended.
+With --arg or --selector the script must be a function expression. It is
+called with the matched element first (if --selector is given), then each
+--arg value in order.
 
-Aliases: goto, navigate
+Options:
+  -b, --base64         Decode script from base64 (avoids shell escaping issues)
+  --stdin              Read script from stdin (useful for heredocs/multiline)
+  --file <path>        Read script from a file
+  -s, --selector <sel> Pass the matching element as the first argument
+  --arg <json>         Argument for the function (repeatable); values that
+                       are not valid JSON are passed as strings
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
-  --headers <json>     Set HTTP headers (scoped to this origin)
-  --headed             Show browser window
 
 Examples:
-  agent-browser open example.com
-  agent-browser open https://github.com
-  agent-browser open localhost:3000
-  agent-browser open api.example.com --headers '{"Authorization": "Bearer token"}'
-    # ^ Headers only sent to api.example.com, not other domains
+  agent-browser eval "document.title"
+  agent-browser eval "window.location.href"
+  agent-browser eval "document.querySelectorAll('a').length"
+  agent-browser eval -b "ZG9jdW1lbnQudGl0bGU="
+  agent-browser eval "(a, b) => a + b" --arg 2 --arg 3
+  agent-browser eval -s @e4 "(el, attr) => el.getAttribute(attr)" --arg href
+  agent-browser eval --file scrape.js --arg '{"limit": 10}'
+
+  # Read from stdin with heredoc
+  cat <<'EOF' | agent-browser eval --stdin
+  const links = document.querySelectorAll('a');
+  links.length;
+  EOF
 "##
         }
-        "back" => {
+
+        // === Close ===
+        "close" | "quit" | "exit" => {
             r##"
-agent-browser back - Navigate back in history
+agent-browser close - Close the browser
 
-Usage: agent-browser back
+Usage: agent-browser close [--keep-daemon] [--save-state <file>]
 
-Goes back one page in the browser history, equivalent to clicking
-the browser's back button.
+Closes the browser instance for the current session and stops its daemon,
+waiting for it to exit and removing any socket or PID file it leaves behind.
+
+Options:
+  --keep-daemon        Close the browser but keep the daemon running, so the
+                       next command starts a new browser without a cold start
+  --save-state <file>  Save the storage state (cookies and localStorage) to
+                       <file> first, for `--state` or `state load`. If it
+                       can't be saved, nothing is closed
+
+Aliases: quit, exit
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser back
+  agent-browser close
+  agent-browser close --session mysession
+  agent-browser close --save-state ./auth.json
+  agent-browser close --keep-daemon
 "##
         }
-        "forward" => {
+
+        // === Get ===
+        "get" => {
             r##"
-agent-browser forward - Navigate forward in history
+agent-browser get - Retrieve information from elements or page
 
-Usage: agent-browser forward
+Usage: agent-browser get <subcommand> [args]
 
-Goes forward one page in the browser history, equivalent to clicking
-the browser's forward button.
+Retrieves various types of information from elements or the page.
+
+Subcommands:
+  text <selector>            Get text content of element (--nth <n> for one of
+                             several matches, --all for a list of all of them)
+  html [sel]                 Get page HTML, or inner HTML of element
+  value <selector>           Get value of input element
+  attr <selector> <name>     Get attribute value ("(not set)" if missing)
+  title                      Get page title
+  url [--parsed]             Get current URL (--parsed: scheme, host, port, path,
+                             decoded query parameters and fragment separately)
+  count <selector>           Count matching elements
+  box <selector>             Get bounding box (x, y, width, height) (alias: bbox)
+  styles <selector>          Get computed styles of elements
+  style <selector> <prop...> Computed values of CSS properties on one element
+                             (font-size or fontSize; -- before custom properties)
+  article [--selector <sel>] Main content as Markdown (Readability-style)
+  metrics                    Page speed: navigation timing, FCP/LCP/CLS, JS heap,
+                             resource counts
+
+html options:
+  -s, --selector <sel>       Element to dump (same as the positional selector)
+  --outer                    Include the element's own tag (outerHTML)
+  -o, --output <file>        Write the HTML to a file instead of printing it
+
+The article subcommand finds the element holding the page's main text (or uses
+--selector), drops navigation, sidebars and other page chrome, and prints the
+rest as Markdown: headings, paragraphs, lists, links, images, code and tables.
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser forward
+  agent-browser get text @e1
+  agent-browser get text .price --all --json
+  agent-browser get html "#content"
+  agent-browser get html -o page.html
+  agent-browser get html --selector "#main" --outer
+  agent-browser get value "#email-input"
+  agent-browser get attr "#link" href
+  agent-browser get title
+  agent-browser get url
+  agent-browser get url --parsed --json
+  agent-browser get count "li.item"
+  agent-browser get box "#header"
+  agent-browser get styles "button"
+  agent-browser get styles @e1
+  agent-browser get style "#error" color display font-size
+  agent-browser get style @e3 -- --brand-color
+  agent-browser get article
+  agent-browser get article --selector "#post"
+  agent-browser get metrics --json
 "##
         }
-        "reload" => {
+
+        "audit" => {
             r##"
-agent-browser reload - Reload the current page
+agent-browser audit - Run an accessibility audit
 
-Usage: agent-browser reload
+Usage: agent-browser audit a11y [options]
 
-Reloads the current page, equivalent to pressing F5 or clicking
-the browser's reload button.
+Runs axe-core in the current page and prints the violations grouped by impact
+(critical, serious, moderate, minor), each with the elements that fail it.
+
+axe-core is bundled with the daemon. Point AGENT_BROWSER_AXE_PATH at an
+axe.min.js to run another version.
+
+Options:
+  -s, --selector <sel>       Only audit this element and its descendants
+  --fail-on <impact>         Exit non-zero if any violation is at or above this
+                             impact: minor, moderate, serious, critical
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser reload
+  agent-browser audit a11y
+  agent-browser audit a11y --fail-on serious
+  agent-browser audit a11y --selector "#checkout" --json
 "##
         }
 
-        // === Core Actions ===
-        "click" => {
+        "forms" => {
             r##"
-agent-browser click - Click an element
+agent-browser forms - List forms and their fields
 
-Usage: agent-browser click <selector>
+Usage: agent-browser forms
 
-Clicks on the specified element. The selector can be a CSS selector,
-XPath, or an element reference from snapshot (e.g., @e1).
+Lists each form with its method and action, followed by its fields: name,
+type, label, current value (passwords masked), whether it is required, and a
+selector to fill it with. Fields outside any <form> are listed last.
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser click "#submit-button"
-  agent-browser click @e1
-  agent-browser click "button.primary"
-  agent-browser click "//button[@type='submit']"
+  agent-browser forms
+  agent-browser forms --json
 "##
         }
-        "dblclick" => {
+
+        "fetch" => {
             r##"
-agent-browser dblclick - Double-click an element
+agent-browser fetch - Make an HTTP request from the page
 
-Usage: agent-browser dblclick <selector>
+Usage: agent-browser fetch <url> [options]
 
-Double-clicks on the specified element. Useful for text selection
-or triggering double-click handlers.
+Runs fetch() inside the current page, so the request carries the page's
+cookies and origin - handy for calling a site's own API after logging in
+through the browser. Relative URLs resolve against the page. Prints the
+status line, response headers and body; binary bodies need --output.
+
+Options:
+  -X, --method <method>     HTTP method (default: GET, or POST with a body)
+  -d, --body <text|@file>   Request body; @file sends the file's contents
+  -H, --header <name:value> Request header (repeatable)
+  -o, --output <file>       Save the response body to a file
 
 Global Options:
-  --json               Output as JSON
+  --json               Output as JSON (status, headers, body)
   --session <name>     Use specific session
 
 Examples:
-  agent-browser dblclick "#editable-text"
-  agent-browser dblclick @e5
+  agent-browser fetch /api/me
+  agent-browser fetch /api/items -X POST --body @item.json
+  agent-browser fetch https://example.com/report.pdf -o report.pdf
+  agent-browser fetch /graphql -d '{"query":"{ viewer { id } }"}' -H content-type:application/json
 "##
         }
-        "fill" => {
+
+        "table" => {
             r##"
-agent-browser fill - Clear and fill an input field
+agent-browser table - Extract an HTML table as CSV or JSON
 
-Usage: agent-browser fill <selector> <text>
+Usage: agent-browser table <selector> [--format csv|json]
 
-Clears the input field and fills it with the specified text.
-This replaces any existing content in the field.
+Reads the table the selector points at (or the first table inside it) and
+prints it as CSV, or as JSON objects keyed by column header. Cells spanning
+several columns or rows are repeated into each one. Header rows come from
+<thead>, or from leading rows made only of <th> cells; stacked header rows
+are joined as "Group / Column". ARIA grids are supported too.
+
+Options:
+  --format <fmt>       csv (default) or json
+
+Global Options:
+  --json               Output as JSON (headers and rows arrays)
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser table "#prices"
+  agent-browser table @e4 --format json
+  agent-browser table "table.results" > results.csv
+"##
+        }
+
+        "links" => {
+            r##"
+agent-browser links - List the links on the page
+
+Usage: agent-browser links [--selector <sel>] [--absolute]
+
+Lists every anchor with an href and its text, in document order. Hrefs are
+resolved against the page URL and deduplicated; javascript: and bare "#" links
+are skipped. Same-origin links are shown as paths unless --absolute is given.
+
+Options:
+  -s, --selector <sel>  Only links inside the matching element(s)
+  --absolute            Print full URLs for same-origin links too
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser fill "#email" "user@example.com"
-  agent-browser fill @e3 "Hello World"
-  agent-browser fill "input[name='search']" "query"
+  agent-browser links
+  agent-browser links --selector "nav"
+  agent-browser links --absolute --json
 "##
         }
-        "type" => {
+
+        // === Is ===
+        "is" => {
             r##"
-agent-browser type - Type text into an element
+agent-browser is - Check element state
 
-Usage: agent-browser type <selector> <text>
+Usage: agent-browser is <subcommand> <selector>
 
-Types text into the specified element character by character.
-Unlike fill, this does not clear existing content first.
+Checks the state of an element and prints true/false. Exits 0 when true and
+1 when false (or on error), so it can be used directly in shell conditionals.
+
+Subcommands:
+  visible <selector>   Check if element is visible
+  enabled <selector>   Check if element is enabled (not disabled)
+  checked <selector>   Check if checkbox/radio is checked
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser type "#search" "hello"
-  agent-browser type @e2 "additional text"
+  agent-browser is visible "#modal"
+  agent-browser is enabled "#submit-btn"
+  agent-browser is checked "#agree-checkbox"
+  agent-browser is visible "#cookie-banner" && agent-browser click "#accept"
 "##
         }
-        "hover" => {
+
+        // === Find ===
+        "find" => {
             r##"
-agent-browser hover - Hover over an element
+agent-browser find - Find and interact with elements by locator
 
-Usage: agent-browser hover <selector>
+Usage: agent-browser find <locator> <value> [action] [text]
+       agent-browser find <query> [--role <role>] [--limit <n>]
 
-Moves the mouse to hover over the specified element. Useful for
-triggering hover states or dropdown menus.
+Finds elements using semantic locators and optionally performs an action.
+
+Given a free-text query instead of a locator, takes a fresh snapshot and lists
+the elements whose accessible name best matches it, best first, with refs to use
+in the next command. Exact and prefix matches rank above partial ones. A first
+word followed by a value and an action is taken as a locator, so a mistyped one
+is an error rather than a query.
+
+Locators:
+  role <role>              Find by ARIA role (--name <n>, --exact)
+  text <text>              Find by text content (--exact)
+  label <label>            Find by associated label (--exact)
+  placeholder <text>       Find by placeholder text (--exact)
+  alt <text>               Find by alt text (--exact)
+  title <text>             Find by title attribute (--exact)
+  testid <id>              Find by data-testid attribute
+  first <selector>         First matching element
+  last <selector>          Last matching element
+  nth <index> <selector>   Nth matching element (0-based)
+
+Actions (default: click):
+  click, fill, type, hover, focus, check, uncheck
+
+Options:
+  --name <name>        Filter role by accessible name
+  --exact              Require exact text match
+  --role <role>        Only list elements with this role (query form)
+  --limit <n>          Maximum number of results (query form, default: 10)
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser hover "#dropdown-trigger"
-  agent-browser hover @e4
+  agent-browser find role button click --name Submit
+  agent-browser find text "Sign In" click
+  agent-browser find label "Email" fill "user@example.com"
+  agent-browser find placeholder "Search..." type "query"
+  agent-browser find testid "login-form" click
+  agent-browser find first "li.item" click
+  agent-browser find nth 2 ".card" hover
+  agent-browser find "sign in" --role button
+  agent-browser find checkout --limit 3
 "##
         }
-        "focus" => {
+
+        // === Mouse ===
+        "mouse" => {
             r##"
-agent-browser focus - Focus an element
+agent-browser mouse - Low-level mouse operations
 
-Usage: agent-browser focus <selector>
+Usage: agent-browser mouse <subcommand> [args]
 
-Sets keyboard focus to the specified element.
+Performs low-level mouse operations for precise control, e.g. in canvas
+apps and custom widgets that have no usable selectors. Coordinates are CSS
+pixels relative to the viewport.
+
+Subcommands:
+  move <x> <y>         Move mouse to coordinates
+  click <x> <y>        Click at coordinates
+  down [button]        Press mouse button (left, right, middle)
+  up [button]          Release mouse button
+  wheel <dy> [dx]      Scroll mouse wheel
+
+Options:
+  --button <button>    Button for click/down/up (left, right, middle)
+  --double             Double-click (with click)
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser focus "#input-field"
-  agent-browser focus @e2
+  agent-browser mouse move 100 200
+  agent-browser mouse click 100 200
+  agent-browser mouse click 100 200 --button right
+  agent-browser mouse click 100 200 --double
+  agent-browser mouse down
+  agent-browser mouse up
+  agent-browser mouse down right
+  agent-browser mouse wheel 100
+  agent-browser mouse wheel -50 0
 "##
         }
-        "check" => {
+
+        // === Set ===
+        "set" => {
             r##"
-agent-browser check - Check a checkbox
+agent-browser set - Configure browser settings
+
+Usage: agent-browser set <setting> [args]
 
-Usage: agent-browser check <selector>
+Configures various browser settings and emulation options.
 
-Checks a checkbox element. If already checked, no action is taken.
+Settings:
+  viewport <w> <h> [--scale <n>]
+                             Set viewport size (and devicePixelRatio)
+  device <name>              Emulate device (e.g., "iPhone 12")
+  geo <lat> <lng>            Set geolocation
+  offline [on|off]           Toggle offline mode
+  headers <json>             Set extra HTTP headers
+  user-agent <ua>            Override the User-Agent for all pages
+  credentials <user> <pass>  Set HTTP basic auth for the current context
+  media [dark|light]         Set color scheme preference
+        [reduced-motion]     Enable reduced motion
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser check "#terms-checkbox"
-  agent-browser check @e7
+  agent-browser set viewport 1920 1080
+  agent-browser set device "iPhone 12"
+  agent-browser set geo 37.7749 -122.4194
+  agent-browser set offline on
+  agent-browser set headers '{"X-Custom": "value"}'
+  agent-browser set user-agent "Mozilla/5.0 (compatible; MyBot/1.0)"
+  agent-browser set credentials admin secret123
+  agent-browser set media dark
+  agent-browser set media light reduced-motion
 "##
         }
-        "uncheck" => {
+
+        // === Headers ===
+        "headers" => {
             r##"
-agent-browser uncheck - Uncheck a checkbox
+agent-browser headers - Extra HTTP headers for all requests
 
-Usage: agent-browser uncheck <selector>
+Usage:
+  agent-browser headers set <name> <value>
+  agent-browser headers clear
 
-Unchecks a checkbox element. If already unchecked, no action is taken.
+Adds headers to every request the session makes, across all tabs and
+windows. `set` adds or replaces one header and keeps the others; `clear`
+removes them all. To scope headers to one origin, use `open <url> --headers`.
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser uncheck "#newsletter-opt-in"
-  agent-browser uncheck @e8
+  agent-browser headers set Authorization "Bearer abc123"
+  agent-browser headers set X-Feature-Flag beta
+  agent-browser headers clear
 "##
         }
-        "select" => {
+
+        // === Block ===
+        "block" | "unblock" => {
             r##"
-agent-browser select - Select a dropdown option
+agent-browser block - Block requests by URL pattern
 
-Usage: agent-browser select <selector> <value...>
+Usage:
+  agent-browser block <pattern>...
+  agent-browser unblock <pattern>... | --all
 
-Selects one or more options in a <select> dropdown by value.
+Aborts requests whose URL matches a pattern: a glob (* within a path segment,
+** across segments) or, without a *, a substring. Globs also match the URL
+without its query, so "**/*.png" blocks logo.png?v=2 too. Useful for skipping
+images, fonts and trackers on heavy pages.
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser select "#country" "US"
-  agent-browser select @e5 "option2"
-  agent-browser select "#menu" "opt1" "opt2" "opt3"
+  agent-browser block "**/*.png" "**/*.jpg" analytics
+  agent-browser block "*://fonts.googleapis.com/**"
+  agent-browser unblock "**/*.png"
+  agent-browser unblock --all
 "##
         }
-        "drag" => {
+
+        // === Mock ===
+        "mock" => {
             r##"
-agent-browser drag - Drag and drop
+agent-browser mock - Stub network responses
 
-Usage: agent-browser drag <source> <target>
+Usage: agent-browser mock <subcommand> [args]
 
-Drags an element from source to target location.
+Answer matching requests with a fixed response instead of hitting the
+network. Mocks apply to every tab in the browser context.
+
+Subcommands:
+  add <url-pattern> [options]  Add a mock (glob, e.g. "**/api/users", or substring)
+    --status <code>            Response status (default: 200)
+    --body-file <file>         Response body from a file (content type is
+                               inferred from the extension)
+    --body <text>              Inline response body
+    --header <name:value>      Response header (repeatable)
+  list                         List mocks with their hit counts
+  remove <id>                  Remove a mock
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser drag "#draggable" "#drop-zone"
-  agent-browser drag @e1 @e2
+  agent-browser mock add "**/api/users" --body-file ./fixtures/users.json
+  agent-browser mock add "**/api/login" --status 401 --body '{"error":"denied"}' --header content-type:application/json
+  agent-browser mock list
+  agent-browser mock remove 1
 "##
         }
-        "upload" => {
+
+        // === Network ===
+        "network" => {
             r##"
-agent-browser upload - Upload files
+agent-browser network - Network interception and monitoring
 
-Usage: agent-browser upload <selector> <files...>
+Usage: agent-browser network <subcommand> [args]
 
-Uploads one or more files to a file input element.
+Intercept, mock, or monitor network requests.
+
+Subcommands:
+  route <url> [options]      Intercept requests matching URL pattern
+    --abort                  Abort matching requests
+    --body <json>            Respond with custom body
+  unroute [url]              Remove route (all if no URL)
+  requests [options]         List captured requests
+    --clear                  Clear request log
+    --filter <pattern>       Filter by URL pattern
+  log [options]              Show recorded requests with status, timing and size
+    --filter <url-glob>      Only URLs matching the glob (* within a segment,
+                             ** across segments) or containing the text
+    --since <id>             Only requests recorded after this id
+  capture <url-glob>         Keep the headers and bodies of the next matching
+                             requests; network log marks them "captured"
+    --limit <n>              How many to capture (default: 20)
+  capture off                Stop capturing (captured bodies are kept)
+  show <id>                  Print a captured request and its response, by
+                             network log id (--json for all of it)
+  offline <on|off>           Cut the browser off from the network, or restore it
+  throttle <profile>         Limit bandwidth and add latency (every tab of the
+                             current context, Chromium only); profiles: slow3g,
+                             fast3g, off, custom
+    --down <kbps>            custom: download limit (default: unlimited)
+    --up <kbps>              custom: upload limit (default: unlimited)
+    --latency <ms>           custom: added round-trip latency (default: 0)
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser upload "#file-input" ./document.pdf
-  agent-browser upload @e3 ./image1.png ./image2.png
+  agent-browser network route "**/api/*" --abort
+  agent-browser network route "**/data.json" --body '{"mock": true}'
+  agent-browser network unroute
+  agent-browser network requests
+  agent-browser network requests --filter "api"
+  agent-browser network requests --clear
+  agent-browser network log --filter "**/api/**"
+  agent-browser network log --since 42
+  agent-browser network capture "**/api/cart*" --limit 5
+  agent-browser network show 42
+  agent-browser network show 42 --json | jq -r .data.response.body
+  agent-browser network offline on
+  agent-browser network throttle slow3g
+  agent-browser network throttle custom --down 1000 --up 250 --latency 300
+  agent-browser network throttle off
 "##
         }
-        "download" => {
+
+        // === Storage ===
+        "storage" => {
             r##"
-agent-browser download - Download a file by clicking an element
+agent-browser storage - Manage web storage
 
-Usage: agent-browser download <selector> <path>
+Usage: agent-browser storage [operation] [key] [value] [--session-storage]
+       agent-browser storage <local|session> [operation] [key] [value]
 
-Clicks an element that triggers a download and saves the file to the specified path.
+Manage localStorage (default) and sessionStorage of the current page.
 
-Arguments:
-  selector             Element to click (CSS selector or @ref)
-  path                 Path where the downloaded file will be saved
+Operations:
+  get [key]            Get all storage or specific key (default)
+  set <key> <value>    Set a key-value pair
+  remove <key>         Remove a key
+  clear                Clear all storage
+
+Options:
+  --session-storage    Use sessionStorage instead of localStorage
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser download "#download-btn" ./file.pdf
-  agent-browser download @e5 ./report.xlsx
-  agent-browser download "a[href$='.zip']" ./archive.zip
+  agent-browser storage get
+  agent-browser storage get authToken
+  agent-browser storage set theme "dark"
+  agent-browser storage remove theme
+  agent-browser storage clear --session-storage
+  agent-browser storage session get userId
 "##
         }
 
-        // === Keyboard ===
-        "press" | "key" => {
+        // === Cookies ===
+        "cookies" => {
             r##"
-agent-browser press - Press a key or key combination
+agent-browser cookies - Manage browser cookies
 
-Usage: agent-browser press <key>
+Usage: agent-browser cookies [operation] [args]
 
-Presses a key or key combination. Supports special keys and modifiers.
+Manage browser cookies for the current context.
 
-Aliases: key
+Operations:
+  list                               List all cookies (default)
+  get [name]                         Get one cookie by name (all if omitted)
+  set <name> <value> [options]       Set a cookie with optional properties
+  clear                              Clear all cookies
+  export <file>                      Save all cookies to a JSON file
+  import <file>                      Add cookies from a JSON file
 
-Special Keys:
-  Enter, Tab, Escape, Backspace, Delete, Space
-  ArrowUp, ArrowDown, ArrowLeft, ArrowRight
-  Home, End, PageUp, PageDown
-  F1-F12
+Cookie Set Options:
+  --url <url>                        URL for the cookie (allows setting before page load)
+  --domain <domain>                  Cookie domain (e.g., ".example.com")
+  --path <path>                      Cookie path (e.g., "/api")
+  --httpOnly                         Set HttpOnly flag (prevents JavaScript access)
+  --secure                           Set Secure flag (HTTPS only)
+  --sameSite <Strict|Lax|None>       SameSite policy
+  --expires <timestamp>              Expiration time (Unix timestamp in seconds)
 
-Modifiers (combine with +):
-  Control, Alt, Shift, Meta
+Note: If --url, --domain, and --path are all omitted, the cookie will be set
+for the current page URL.
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser press Enter
-  agent-browser press Tab
-  agent-browser press Control+a
-  agent-browser press Control+Shift+s
-  agent-browser press Escape
-"##
-        }
-        "keydown" => {
-            r##"
-agent-browser keydown - Press a key down (without release)
+  # Simple cookie for current page
+  agent-browser cookies set session_id "abc123"
 
-Usage: agent-browser keydown <key>
+  # Set cookie for a URL before loading it (useful for authentication)
+  agent-browser cookies set session_id "abc123" --url https://app.example.com
 
-Presses a key down without releasing it. Use keyup to release.
-Useful for holding modifier keys.
+  # Set secure, httpOnly cookie with domain and path
+  agent-browser cookies set auth_token "xyz789" --domain example.com --path /api --httpOnly --secure
 
-Global Options:
-  --json               Output as JSON
-  --session <name>     Use specific session
+  # Set cookie with SameSite policy
+  agent-browser cookies set tracking_consent "yes" --sameSite Strict
 
-Examples:
-  agent-browser keydown Shift
-  agent-browser keydown Control
-"##
-        }
-        "keyup" => {
-            r##"
-agent-browser keyup - Release a key
+  # Set cookie with expiration (Unix timestamp)
+  agent-browser cookies set temp_token "temp123" --expires 1735689600
 
-Usage: agent-browser keyup <key>
+  # Get all cookies
+  agent-browser cookies
 
-Releases a key that was pressed with keydown.
+  # Get a single cookie
+  agent-browser cookies get session_id
 
-Global Options:
-  --json               Output as JSON
-  --session <name>     Use specific session
+  # Clear all cookies
+  agent-browser cookies clear
 
-Examples:
-  agent-browser keyup Shift
-  agent-browser keyup Control
+  # Persist login cookies across daemon restarts
+  agent-browser cookies export ./cookies.json
+  agent-browser cookies import ./cookies.json
 "##
         }
 
-        // === Scroll ===
-        "scroll" => {
+        // === Tabs ===
+        "tab" => {
             r##"
-agent-browser scroll - Scroll the page
+agent-browser tab - Manage browser tabs
 
-Usage: agent-browser scroll [direction] [amount]
+Usage: agent-browser tab [operation] [args]
 
-Scrolls the page in the specified direction.
+Manage browser tabs in the current window.
 
-Arguments:
-  direction            up, down, left, right (default: down)
-  amount               Pixels to scroll (default: 300)
+Operations:
+  list                 List all tabs (default)
+  new [url]            Open new tab
+  close [index]        Close tab (current if no index)
+  <index>              Switch to tab by index
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser scroll
-  agent-browser scroll down 500
-  agent-browser scroll up 200
-  agent-browser scroll left 100
+  agent-browser tab
+  agent-browser tab list
+  agent-browser tab new
+  agent-browser tab new https://example.com
+  agent-browser tab 2
+  agent-browser tab close
+  agent-browser tab close 1
 "##
         }
-        "scrollintoview" | "scrollinto" => {
+
+        // === Window ===
+        "window" => {
             r##"
-agent-browser scrollintoview - Scroll element into view
+agent-browser window - Manage browser windows
 
-Usage: agent-browser scrollintoview <selector>
+Usage: agent-browser window <operation>
 
-Scrolls the page until the specified element is visible in the viewport.
+Manage browser windows.
 
-Aliases: scrollinto
+Operations:
+  new                  Open new browser window
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser scrollintoview "#footer"
-  agent-browser scrollintoview @e15
+  agent-browser window new
 "##
         }
 
-        // === Wait ===
-        "wait" => {
+        // === Context ===
+        "context" => {
             r##"
-agent-browser wait - Wait for condition
-
-Usage: agent-browser wait <selector|ms|option>
+agent-browser context - Manage isolated browser contexts
 
-Waits for an element to appear, a timeout, or other conditions.
+Usage: agent-browser context <operation> [args]
 
-Modes:
-  <selector>           Wait for element to appear
-  <ms>                 Wait for specified milliseconds
-  --url <pattern>      Wait for URL to match pattern
-  --load <state>       Wait for load state (load, domcontentloaded, networkidle)
-  --fn <expression>    Wait for JavaScript expression to be truthy
-  --text <text>        Wait for text to appear on page
-  --download [path]    Wait for a download to complete (optionally save to path)
+Each context has its own cookies, storage and cache, so one session can
+be logged in as two users at once. Tabs opened with `tab new` stay in the
+current context. The first context is called "default".
 
-Download Options (with --download):
-  --timeout <ms>       Timeout in milliseconds for download to start
+Operations:
+  list                 List contexts and their tabs (default)
+  new [--name <name>]  Create a context with one tab and switch to it
+  switch <name>        Switch to the context's first tab
+  close [name]         Close a context and its tabs (current if no name)
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser wait "#loading-spinner"
-  agent-browser wait 2000
-  agent-browser wait --url "**/dashboard"
-  agent-browser wait --load networkidle
-  agent-browser wait --fn "window.appReady === true"
-  agent-browser wait --text "Welcome back"
-  agent-browser wait --download ./file.pdf
-  agent-browser wait --download ./report.xlsx --timeout 30000
+  agent-browser context new --name admin
+  agent-browser open example.com/login
+  agent-browser context switch default
+  agent-browser context list
+  agent-browser context close admin
 "##
         }
 
-        // === Screenshot/PDF ===
-        "screenshot" => {
+        // === Frame ===
+        "frame" => {
             r##"
-agent-browser screenshot - Take a screenshot
+agent-browser frame - Switch frame context
 
-Usage: agent-browser screenshot [path]
+Usage: agent-browser frame <list|use <selector|index>|main>
 
-Captures a screenshot of the current page. If no path is provided,
-saves to a temporary directory with a generated filename.
+Switch to an iframe or back to the main frame. While a frame is selected,
+click, fill, snapshot and other element commands run inside it.
 
-Options:
-  --full, -f           Capture full page (not just viewport)
+Operations:
+  list                 List frames of the current page (index 0 is main)
+  use <selector>       Switch to the iframe matching a CSS selector
+  use <index>          Switch to a frame by its index from `frame list`
+  main                 Switch back to main frame
+  <selector>           Shorthand for `frame use <selector>`
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser screenshot
-  agent-browser screenshot ./screenshot.png
-  agent-browser screenshot --full ./full-page.png
+  agent-browser frame list
+  agent-browser frame use 1
+  agent-browser frame use "#embed-iframe"
+  agent-browser frame use "iframe[name='content']"
+  agent-browser frame main
 "##
         }
-        "pdf" => {
+
+        // === Dialog ===
+        "dialog" => {
             r##"
-agent-browser pdf - Save page as PDF
+agent-browser dialog - Handle browser dialogs
+
+Usage: agent-browser dialog <accept|dismiss|auto> [args]
 
-Usage: agent-browser pdf <path>
+Respond to browser dialogs (alert, confirm, prompt, beforeunload).
 
-Saves the current page as a PDF file.
+By default dialogs are dismissed as soon as they open. `dialog accept` and
+`dialog dismiss` resolve a dialog that is currently open, or if none is, the
+next one that appears. `dialog auto` sets the policy for later dialogs; with
+`off`, dialogs stay open until you accept or dismiss them.
+
+Operations:
+  accept [text]        Accept dialog, optionally with prompt text
+  dismiss              Dismiss/cancel dialog
+  auto accept [text]   Accept all future dialogs
+  auto dismiss         Dismiss all future dialogs (default)
+  auto off             Leave future dialogs open
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser pdf ./page.pdf
-  agent-browser pdf ~/Documents/report.pdf
+  agent-browser dialog accept
+  agent-browser dialog accept "my input"
+  agent-browser dialog auto accept
+  agent-browser dialog auto off
+  agent-browser dialog dismiss
 "##
         }
 
-        // === Snapshot ===
-        "snapshot" => {
+        // === Trace ===
+        "trace" => {
             r##"
-agent-browser snapshot - Get accessibility tree snapshot
+agent-browser trace - Record execution trace
 
-Usage: agent-browser snapshot [options]
+Usage: agent-browser trace <operation> [args]
 
-Returns an accessibility tree representation of the page with element
-references (like @e1, @e2) that can be used in subsequent commands.
-Designed for AI agents to understand page structure.
+Record a Playwright trace of the session, for replaying a failed run in the
+Trace Viewer (npx playwright show-trace <file.zip>).
 
-Options:
-  -i, --interactive    Only include interactive elements
-  -C, --cursor         Include cursor-interactive elements (cursor:pointer, onclick, tabindex)
-  -c, --compact        Remove empty structural elements
-  -d, --depth <n>      Limit tree depth
-  -s, --selector <sel> Scope snapshot to CSS selector
+Operations:
+  start [--screenshots] [--snapshots]
+                       Start recording. Both screenshots and DOM snapshots are
+                       recorded unless only one of them is named
+  stop <file.zip>      Stop recording and save the trace archive
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser snapshot
-  agent-browser snapshot -i
-  agent-browser snapshot -i -C         # Interactive + cursor-interactive elements
-  agent-browser snapshot --compact --depth 5
-  agent-browser snapshot -s "#main-content"
+  agent-browser trace start
+  agent-browser trace start --snapshots
+  agent-browser trace stop ./debug-trace.zip
 "##
         }
 
-        // === Eval ===
-        "eval" => {
+        // === Coverage ===
+        "coverage" => {
             r##"
-agent-browser eval - Execute JavaScript
+agent-browser coverage - Measure JS and CSS code coverage
 
-Usage: agent-browser eval [options] <script>
+Usage: agent-browser coverage <operation> [args]
 
-Executes JavaScript code in the browser context and returns the result.
+Record which JavaScript and CSS actually runs on the current page, across
+navigations, for the flow between start and stop. Chromium only.
 
-Options:
-  -b, --base64         Decode script from base64 (avoids shell escaping issues)
-  --stdin              Read script from stdin (useful for heredocs/multiline)
+Operations:
+  start                      Start collecting JS and CSS coverage
+  stop [--lcov <file.info>]  Stop and print used/unused bytes per file, most
+                             unused first. --lcov also writes JS line coverage
+                             as an LCOV tracefile (for genhtml or CI tools)
 
 Global Options:
-  --json               Output as JSON
+  --json               Output as JSON (full per-file list)
   --session <name>     Use specific session
 
 Examples:
-  agent-browser eval "document.title"
-  agent-browser eval "window.location.href"
-  agent-browser eval "document.querySelectorAll('a').length"
-  agent-browser eval -b "ZG9jdW1lbnQudGl0bGU="
-
-  # Read from stdin with heredoc
-  cat <<'EOF' | agent-browser eval --stdin
-  const links = document.querySelectorAll('a');
-  links.length;
-  EOF
+  agent-browser coverage start
+  agent-browser open https://example.com && agent-browser click "#buy"
+  agent-browser coverage stop
+  agent-browser coverage stop --lcov ./coverage/lcov.info --json
 "##
         }
 
-        // === Close ===
-        "close" | "quit" | "exit" => {
+        // === HAR ===
+        "har" => {
             r##"
-agent-browser close - Close the browser
+agent-browser har - Record network traffic as HAR
 
-Usage: agent-browser close
+Usage: agent-browser har <operation> [path]
 
-Closes the browser instance for the current session.
+Records every request the page makes from `har start` until `har stop`,
+then writes a HAR 1.2 archive for performance analysis or replay.
 
-Aliases: quit, exit
+Operations:
+  start <path>         Start recording to path
+  stop [path]          Stop recording and save (optionally to a different path)
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser close
-  agent-browser close --session mysession
+  agent-browser har start ./session.har
+  agent-browser open example.com
+  agent-browser har stop
 "##
         }
 
-        // === Get ===
-        "get" => {
+        // === Record (video or script) ===
+        "record" => {
             r##"
-agent-browser get - Retrieve information from elements or page
+agent-browser record - Record browser session to video, or your actions to a script
 
-Usage: agent-browser get <subcommand> [args]
+Usage: agent-browser record start <path.webm|dir> [url]
+       agent-browser record start <script.ab|actions.json> [url]
+       agent-browser record stop
+       agent-browser record restart <path.webm|dir> [url]
 
-Retrieves various types of information from elements or the page.
+Record the browser to a WebM video file using Playwright's native recording.
+Creates a fresh browser context but preserves cookies and localStorage.
+If no URL is provided, automatically navigates to your current page.
+Given a directory, the video is saved there as recording-<timestamp>.webm.
 
-Subcommands:
-  text <selector>            Get text content of element
-  html <selector>            Get inner HTML of element
-  value <selector>           Get value of input element
-  attr <selector> <name>     Get attribute value
-  title                      Get page title
-  url                        Get current URL
-  count <selector>           Count matching elements
-  box <selector>             Get bounding box (x, y, width, height)
-  styles <selector>          Get computed styles of elements
+Given a .ab file, what you do in the (headed) browser is recorded instead:
+clicks, fills, checkboxes, selects, Enter and the pages you open. 'record
+stop' writes it as a script for 'agent-browser run'; a .json file gets a
+list of daemon commands. Password fields are written as
+'fill <selector> --secret PASSWORD', so replaying reads $PASSWORD.
+
+Operations:
+  start <path> [url]     Start recording (defaults to current URL if omitted)
+  stop                   Stop recording, save the video or script, print its path
+  restart <path> [url]   Stop current recording (if any) and start a new one
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser get text @e1
-  agent-browser get html "#content"
-  agent-browser get value "#email-input"
-  agent-browser get attr "#link" href
-  agent-browser get title
-  agent-browser get url
-  agent-browser get count "li.item"
-  agent-browser get box "#header"
-  agent-browser get styles "button"
-  agent-browser get styles @e1
+  # Record from current page (preserves login state)
+  agent-browser open https://app.example.com/dashboard
+  agent-browser snapshot -i            # Explore and plan
+  agent-browser record start ./demo.webm
+  agent-browser click @e3              # Execute planned actions
+  agent-browser record stop
+
+  # Or specify a different URL
+  agent-browser record start ./demo.webm https://example.com
+
+  # Timestamped file in a directory
+  agent-browser record start ./recordings
+
+  # Restart recording with a new file (stops previous, starts new)
+  agent-browser record restart ./take2.webm
+
+  # Record a login by hand, then replay it
+  agent-browser --headed record start login.ab https://example.com/login
+  agent-browser record stop
+  PASSWORD=hunter2 agent-browser run login.ab
 "##
         }
 
-        // === Is ===
-        "is" => {
+        // === Console/Errors ===
+        "console" => {
             r##"
-agent-browser is - Check element state
+agent-browser console - View console logs
 
-Usage: agent-browser is <subcommand> <selector>
+Usage: agent-browser console [--follow] [--level error|warn|all] [--clear]
 
-Checks the state of an element and returns true/false.
+View browser console output (log, warn, error, info) collected since the
+page loaded.
 
-Subcommands:
-  visible <selector>   Check if element is visible
-  enabled <selector>   Check if element is enabled (not disabled)
-  checked <selector>   Check if checkbox/radio is checked
+Options:
+  --follow             Keep printing new messages until interrupted (Ctrl+C)
+  --level <level>      Only show errors (error), warnings and errors (warn),
+                       or everything (all, default)
+  --clear              Clear console log buffer
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser is visible "#modal"
-  agent-browser is enabled "#submit-btn"
-  agent-browser is checked "#agree-checkbox"
+  agent-browser console
+  agent-browser console --level error
+  agent-browser console --follow --level warn
+  agent-browser console --clear
 "##
         }
-
-        // === Find ===
-        "find" => {
+        "events" => {
             r##"
-agent-browser find - Find and interact with elements by locator
+agent-browser events - Stream browser events
 
-Usage: agent-browser find <locator> <value> [action] [text]
-
-Finds elements using semantic locators and optionally performs an action.
+Usage: agent-browser events --follow [--types console,network,dialog]
 
-Locators:
-  role <role>              Find by ARIA role (--name <n>, --exact)
-  text <text>              Find by text content (--exact)
-  label <label>            Find by associated label (--exact)
-  placeholder <text>       Find by placeholder text (--exact)
-  alt <text>               Find by alt text (--exact)
-  title <text>             Find by title attribute (--exact)
-  testid <id>              Find by data-testid attribute
-  first <selector>         First matching element
-  last <selector>          Last matching element
-  nth <index> <selector>   Nth matching element (0-based)
+Keeps the connection to the daemon open and prints each event as a line of
+JSON as it happens, until interrupted (Ctrl+C). Each line has the form
+{"event":"console","timestamp":...,"data":{...}}.
 
-Actions (default: click):
-  click, fill, type, hover, focus, check, uncheck
+Event types:
+  console              Console messages (same fields as `console --json`)
+  network              Finished or failed requests (same fields as `network log`)
+  dialog               alert/confirm/prompt/beforeunload dialogs
 
 Options:
-  --name <name>        Filter role by accessible name
-  --exact              Require exact text match
+  --types <list>       Comma-separated event types (default: all)
 
 Global Options:
-  --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser find role button click --name Submit
-  agent-browser find text "Sign In" click
-  agent-browser find label "Email" fill "user@example.com"
-  agent-browser find placeholder "Search..." type "query"
-  agent-browser find testid "login-form" click
-  agent-browser find first "li.item" click
-  agent-browser find nth 2 ".card" hover
+  agent-browser events --follow
+  agent-browser events --follow --types console,dialog
+  agent-browser events --follow --types network | jq .data.url
 "##
         }
-
-        // === Mouse ===
-        "mouse" => {
+        "watch" => {
             r##"
-agent-browser mouse - Low-level mouse operations
+agent-browser watch - Print DOM changes inside an element
 
-Usage: agent-browser mouse <subcommand> [args]
+Usage: agent-browser watch <selector> [--event added|removed|text] [--once] [--timeout <ms>]
 
-Performs low-level mouse operations for precise control.
+Observes the element with a MutationObserver and prints a line per change
+until interrupted (Ctrl+C), the timeout passes or the page navigates away:
 
-Subcommands:
-  move <x> <y>         Move mouse to coordinates
-  down [button]        Press mouse button (left, right, middle)
-  up [button]          Release mouse button
-  wheel <dy> [dx]      Scroll mouse wheel
+  + li: New row          An element was added inside it
+  - li: Old row          An element was removed from it
+  ~ 3 results            Its text changed (the new text)
+
+With --once it exits after the first change, and fails if the timeout comes
+or the page navigates first, for "wait until this list updates" steps.
+
+Options:
+  --event <list>       Comma-separated changes to report (default: all)
+  --once               Exit after the first change
+  --timeout <ms>       Stop watching after this long
 
 Global Options:
-  --json               Output as JSON
+  --json               One JSON line per change ({"event":"mutation","data":{...}})
   --session <name>     Use specific session
 
 Examples:
-  agent-browser mouse move 100 200
-  agent-browser mouse down
-  agent-browser mouse up
-  agent-browser mouse down right
-  agent-browser mouse wheel 100
-  agent-browser mouse wheel -50 0
+  agent-browser watch "#results"
+  agent-browser watch "#results" --event added --once --timeout 10000
+  agent-browser watch @e4 --event text
 "##
         }
-
-        // === Set ===
-        "set" => {
+        "errors" => {
             r##"
-agent-browser set - Configure browser settings
+agent-browser errors - View page errors
 
-Usage: agent-browser set <setting> [args]
+Usage: agent-browser errors [--clear]
 
-Configures various browser settings and emulation options.
+View JavaScript errors and uncaught exceptions.
 
-Settings:
-  viewport <w> <h>           Set viewport size
-  device <name>              Emulate device (e.g., "iPhone 12")
-  geo <lat> <lng>            Set geolocation
-  offline [on|off]           Toggle offline mode
-  headers <json>             Set extra HTTP headers
-  credentials <user> <pass>  Set HTTP authentication
-  media [dark|light]         Set color scheme preference
-        [reduced-motion]     Enable reduced motion
+Options:
+  --clear              Clear error buffer
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser set viewport 1920 1080
-  agent-browser set device "iPhone 12"
-  agent-browser set geo 37.7749 -122.4194
-  agent-browser set offline on
-  agent-browser set headers '{"X-Custom": "value"}'
-  agent-browser set credentials admin secret123
-  agent-browser set media dark
-  agent-browser set media light reduced-motion
+  agent-browser errors
+  agent-browser errors --clear
 "##
         }
 
-        // === Network ===
-        "network" => {
+        // === Highlight ===
+        "highlight" => {
             r##"
-agent-browser network - Network interception and monitoring
+agent-browser highlight - Outline the elements a selector matches
 
-Usage: agent-browser network <subcommand> [args]
+Usage: agent-browser highlight <selector> [--duration <ms>] [--label <text>]
 
-Intercept, mock, or monitor network requests.
+Draws an outline around every element the selector matches, and scrolls the
+first into view, so you can check in a headed browser that a selector targets
+what you think it does. A new highlight replaces the last one. Fails when
+nothing visible matches.
 
-Subcommands:
-  route <url> [options]      Intercept requests matching URL pattern
-    --abort                  Abort matching requests
-    --body <json>            Respond with custom body
-  unroute [url]              Remove route (all if no URL)
-  requests [options]         List captured requests
-    --clear                  Clear request log
-    --filter <pattern>       Filter by URL pattern
+Options:
+  --duration <ms>      How long the outline stays (default: 3000; also 5s).
+                       0 keeps it until the page navigates
+  --label <text>       Show this text above each element
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser network route "**/api/*" --abort
-  agent-browser network route "**/data.json" --body '{"mock": true}'
-  agent-browser network unroute
-  agent-browser network requests
-  agent-browser network requests --filter "api"
-  agent-browser network requests --clear
+  agent-browser highlight "#target-element"
+  agent-browser highlight @e5
+  agent-browser highlight ".card" --label card --duration 0
 "##
         }
 
-        // === Storage ===
-        "storage" => {
+        "clipboard" => {
             r##"
-agent-browser storage - Manage web storage
+agent-browser clipboard - Read and write the clipboard
 
-Usage: agent-browser storage <type> [operation] [key] [value]
+Usage: agent-browser clipboard <read|write|copy|paste> [text]
 
-Manage localStorage and sessionStorage.
+Works with the browser's clipboard so copy-to-clipboard buttons can be
+checked. The daemon grants the clipboard permissions and focuses the page
+first (permissions apply in Chromium; other engines may prompt or refuse).
 
-Types:
-  local                localStorage
-  session              sessionStorage
-
-Operations:
-  get [key]            Get all storage or specific key
-  set <key> <value>    Set a key-value pair
-  clear                Clear all storage
+Subcommands:
+  read                 Print the clipboard text
+  write <text>         Put text on the clipboard
+  copy                 Press Ctrl+C in the page
+  paste                Press Ctrl+V in the page
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser storage local
-  agent-browser storage local get authToken
-  agent-browser storage local set theme "dark"
-  agent-browser storage local clear
-  agent-browser storage session get userId
+  agent-browser click "button.copy-link"
+  agent-browser clipboard read
+  agent-browser clipboard write "pasted text"
+  agent-browser clipboard paste
 "##
         }
 
-        // === Cookies ===
-        "cookies" => {
+        // === State ===
+        "state" => {
             r##"
-agent-browser cookies - Manage browser cookies
+agent-browser state - Save/load browser state
 
-Usage: agent-browser cookies [operation] [args]
+Usage: agent-browser state <operation> <path>
 
-Manage browser cookies for the current context.
+Save or restore browser state (cookies and localStorage) in Playwright's
+storage state format.
 
 Operations:
-  get                                Get all cookies (default)
-  set <name> <value> [options]       Set a cookie with optional properties
-  clear                              Clear all cookies
+  save <path>          Save current state to file
+  load <path>          Restore cookies and localStorage into the current context
 
-Cookie Set Options:
-  --url <url>                        URL for the cookie (allows setting before page load)
-  --domain <domain>                  Cookie domain (e.g., ".example.com")
-  --path <path>                      Cookie path (e.g., "/api")
-  --httpOnly                         Set HttpOnly flag (prevents JavaScript access)
-  --secure                           Set Secure flag (HTTPS only)
-  --sameSite <Strict|Lax|None>       SameSite policy
-  --expires <timestamp>              Expiration time (Unix timestamp in seconds)
+Applying State at Launch:
+  Use --state flag when launching browser to load saved state:
+  agent-browser --state ./auth-state.json open https://example.com
 
-Note: If --url, --domain, and --path are all omitted, the cookie will be set
-for the current page URL.
+  Or set AGENT_BROWSER_STATE environment variable.
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  # Simple cookie for current page
-  agent-browser cookies set session_id "abc123"
+  agent-browser state save ./auth-state.json
+  agent-browser state load ./auth-state.json
+  agent-browser --state ./auth-state.json open https://example.com
+"##
+        }
 
-  # Set cookie for a URL before loading it (useful for authentication)
-  agent-browser cookies set session_id "abc123" --url https://app.example.com
+        // === Session ===
+        "session" => {
+            r##"
+agent-browser session - Manage sessions
 
-  # Set secure, httpOnly cookie with domain and path
-  agent-browser cookies set auth_token "xyz789" --domain example.com --path /api --httpOnly --secure
+Usage: agent-browser session [operation]
 
-  # Set cookie with SameSite policy
-  agent-browser cookies set tracking_consent "yes" --sameSite Strict
+Manage isolated browser sessions. Each session has its own browser
+instance with separate cookies, storage, and state.
 
-  # Set cookie with expiration (Unix timestamp)
-  agent-browser cookies set temp_token "temp123" --expires 1735689600
+Operations:
+  (none)               Show current session name
+  list                 List all active sessions
+  each [--] <command>  Run a command in every active session (same as
+                       --all-sessions); --json prints an array of
+                       {session, success, data, error}
+
+Environment:
+  AGENT_BROWSER_SESSION    Default session name
 
-  # Get all cookies
-  agent-browser cookies
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
 
-  # Clear all cookies
-  agent-browser cookies clear
+Examples:
+  agent-browser session
+  agent-browser session list
+  agent-browser session each -- get url
+  agent-browser --all-sessions --json snapshot -i
+  agent-browser --session test open example.com
 "##
         }
 
-        // === Tabs ===
-        "tab" => {
+        "profile" => {
             r##"
-agent-browser tab - Manage browser tabs
+agent-browser profile - Switch between named cookie jars
 
-Usage: agent-browser tab [operation] [args]
+Usage: agent-browser profile [use <name>|off|list|current]
 
-Manage browser tabs in the current window.
+A profile is the cookies and localStorage of one account, saved in
+~/.agent-browser/profiles/<name>.json. While a session uses a profile, every
+browser it starts loads the profile, and the browser's state is saved back to
+it after each command (before close), so a login made in one run is there in
+the next.
 
 Operations:
-  list                 List all tabs (default)
-  new [url]            Open new tab
-  close [index]        Close tab (current if no index)
-  <index>              Switch to tab by index
+  (none), current      Show the session's profile
+  use <name>           Save the current profile, clear the browser's cookies
+                       and load <name>'s; a new name starts empty
+  off                  Save the current profile and stop using it
+  list                 List saved profiles
+
+Switching clears cookies but not localStorage, which the new profile's own
+values overwrite. For separate caches and IndexedDB as well, use --profile
+<dir> with a session per account.
+
+Environment:
+  AGENT_BROWSER_PROFILES_DIR   Where profiles are kept
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser tab
-  agent-browser tab list
-  agent-browser tab new
-  agent-browser tab new https://example.com
-  agent-browser tab 2
-  agent-browser tab close
-  agent-browser tab close 1
+  agent-browser profile use alice
+  agent-browser open app.example.com/login
+  agent-browser profile use bob
+  agent-browser profile list
+  agent-browser --session admin profile use admin
 "##
         }
 
-        // === Window ===
-        "window" => {
+        "auth" => {
             r##"
-agent-browser window - Manage browser windows
+agent-browser auth - Log in by hand, then reuse the login headless
 
-Usage: agent-browser window <operation>
+Usage: agent-browser auth login <url> [--save <profile>] [--success <selector>]
 
-Manage browser windows.
+Opens <url> in a headed browser window and waits for you to log in there.
+Logging in is done when the page leaves the login page (its URL, apart from
+the query and fragment, changes), or when --success <selector> appears, for
+logins that stay on one page. The browser's cookies and localStorage are then
+saved as a named profile and the window is closed; load the profile into any
+session, headless or not, with profile use <name>.
 
-Operations:
-  new                  Open new browser window
+The window belongs to a session of its own, <session>-login, so the session's
+browser is left as it is.
+
+Options:
+  --save <profile>     Profile to save the login as (default: the URL's host,
+                       e.g. app-example-com)
+  --success <selector> Logged in once this element is visible
+  --timeout <ms>       How long to wait for the login (default: 300000)
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
+  --browser <name>     Browser for the login window
 
 Examples:
-  agent-browser window new
+  agent-browser auth login app.example.com/login --save work
+  agent-browser auth login https://example.com --success "[data-test=avatar]"
+  agent-browser profile use work && agent-browser open app.example.com
 "##
         }
 
-        // === Frame ===
-        "frame" => {
+        // === Daemon ===
+        "daemon" => {
             r##"
-agent-browser frame - Switch frame context
+agent-browser daemon - Inspect, limit or upgrade the session's daemon
 
-Usage: agent-browser frame <selector|main>
+Usage: agent-browser daemon status
+       agent-browser daemon config [--idle-timeout <duration>] [--max-lifetime <duration>]
+       agent-browser daemon upgrade
 
-Switch to an iframe or back to the main frame.
+A daemon with limits shuts itself down, closing its browser, once it has
+gone --idle-timeout without commands or has run for --max-lifetime.
+Durations are like 90s, 10m or 2h (a bare number is milliseconds); 0 or
+off removes a limit. The same flags on any other command apply to a
+daemon that command starts.
 
-Arguments:
-  <selector>           CSS selector for iframe
-  main                 Switch back to main frame
+Subcommands:
+  status    Pid, uptime, and the time left before each limit (does not
+            start a daemon)
+  config    Change the limits of the running daemon
+  upgrade   Replace the daemon with this install's, keeping the session:
+            its storage state is saved and its tabs reopened (history,
+            form input and named contexts are lost)
+
+Environment:
+  AGENT_BROWSER_IDLE_TIMEOUT    Default for --idle-timeout
+  AGENT_BROWSER_MAX_LIFETIME    Default for --max-lifetime
 
 Global Options:
   --json               Output as JSON
   --session <name>     Use specific session
 
 Examples:
-  agent-browser frame "#embed-iframe"
-  agent-browser frame "iframe[name='content']"
-  agent-browser frame main
+  agent-browser --idle-timeout 10m --max-lifetime 2h open example.com
+  agent-browser daemon status
+  agent-browser daemon config --idle-timeout 30m
+  agent-browser daemon config --max-lifetime off
+  agent-browser daemon upgrade
 "##
         }
 
-        // === Dialog ===
-        "dialog" => {
+        // === Scripts ===
+        "run" => {
             r##"
-agent-browser dialog - Handle browser dialogs
+agent-browser run - Run commands from a script
 
-Usage: agent-browser dialog <response> [text]
+Usage: agent-browser run <file|-> [--continue-on-error]
 
-Respond to browser dialogs (alert, confirm, prompt).
+Executes one command per line over a single daemon connection. Lines
+use the same syntax as the command line (without the agent-browser
+prefix). Blank lines and lines starting with # are ignored. All lines
+are parsed before anything runs.
 
-Operations:
-  accept [text]        Accept dialog, optionally with prompt text
-  dismiss              Dismiss/cancel dialog
+Execution stops at the first failing command unless
+--continue-on-error is given. Exits non-zero if any command failed.
+
+Options:
+  -k, --continue-on-error  Keep going after a failed command
+
+Lines run by the CLI:
+  retry <n> <command>              Run a command until it succeeds, up to n times
+  if-visible <sel> then <command>  Run a command only if sel is visible now
+  sleep <duration>                 Pause, e.g. 500 (ms), 2s or 1m
+  set <name> <value>               Set a variable
+  capture <name> [<filter>] <cmd>  Keep a command's result in a variable
+
+${NAME} in a line is replaced by a variable, or else by the environment
+variable. capture keeps the value its filter (as in --filter) picks, or
+the result itself when it has a single field. Write $${ for a literal ${.
 
 Global Options:
-  --json               Output as JSON
+  --json               Output all results as a single JSON object
   --session <name>     Use specific session
 
 Examples:
-  agent-browser dialog accept
-  agent-browser dialog accept "my input"
-  agent-browser dialog dismiss
+  agent-browser run login.ab
+  cat steps.ab | agent-browser run -
+  agent-browser run smoke.ab --continue-on-error --json
+
+Script example:
+  # login.ab
+  open example.com/login
+  fill #email "user@example.com"
+  fill #password "hunter2"
+  click "button[type=submit]"
+  wait --url "**/dashboard"
+  if-visible "#promo" then click "#promo .close"
+  retry 3 click "text=Continue"
+  capture ORDER .data.text get text "#order-id"
+  open "https://example.com/orders/${ORDER}"
+
+A few commands can also be chained on the command line with ++. They
+run the same way, over one connection, stopping at the first failure:
+  agent-browser open example.com ++ wait --idle ++ snapshot -i
 "##
         }
 
-        // === Trace ===
-        "trace" => {
+        // === MCP ===
+        "mcp" => {
             r##"
-agent-browser trace - Record execution trace
+agent-browser mcp - Serve browser tools over the Model Context Protocol
 
-Usage: agent-browser trace <operation> [path]
+Usage: agent-browser mcp
 
-Record a trace for debugging with Playwright Trace Viewer.
+Runs an MCP server on stdin/stdout for agent frameworks that speak MCP.
+Tool calls are parsed like command-line arguments and sent to the session's
+daemon, which is started with the launch options given to mcp.
 
-Operations:
-  start [path]         Start recording trace
-  stop [path]          Stop recording and save trace
+Tools:
+  navigate, back, forward, reload, snapshot, click, fill, type, press,
+  hover, select, check, uncheck, scroll, wait, screenshot, get_text,
+  get_url, get_title, eval
+  command                    Any other command, written as on the command
+                             line, e.g. "cookies clear" or "tab new"
+
+The typed tools cover the common actions; every other command is reached
+through the command tool rather than a tool of its own.
+
+close, --follow and --diff are not available over MCP. The browser keeps
+running after the client disconnects; use agent-browser close to stop it.
 
 Global Options:
-  --json               Output as JSON
   --session <name>     Use specific session
+  --headed             Show the browser window
 
 Examples:
-  agent-browser trace start
-  agent-browser trace start ./my-trace
-  agent-browser trace stop
-  agent-browser trace stop ./debug-trace.zip
+  agent-browser mcp
+  agent-browser --session research --headed mcp
+
+Client configuration:
+  { "mcpServers": { "browser": { "command": "agent-browser", "args": ["mcp"] } } }
 "##
         }
 
-        // === Record (video) ===
-        "record" => {
+        // === JSONL pipe ===
+        "pipe" => {
             r##"
-agent-browser record - Record browser session to video
+agent-browser pipe - Forward JSON requests from stdin over one connection
 
-Usage: agent-browser record start <path.webm> [url]
-       agent-browser record stop
-       agent-browser record restart <path.webm> [url]
+Usage: agent-browser pipe
 
-Record the browser to a WebM video file using Playwright's native recording.
-Creates a fresh browser context but preserves cookies and localStorage.
-If no URL is provided, automatically navigates to your current page.
+Reads one JSON request per line from stdin and writes the daemon's response
+to each as one line of JSON on stdout, until stdin is closed. For agent
+frameworks that keep one child process instead of running the CLI for every
+action. The daemon is started with the launch options given to pipe.
 
-Operations:
-  start <path> [url]     Start recording (defaults to current URL if omitted)
-  stop                   Stop recording and save video
-  restart <path> [url]   Stop current recording (if any) and start a new one
+Requests:
+  {"id": "1", "action": "navigate", "url": "..."}   A protocol command
+  {"id": "2", "command": "get text @e1"}            A command line
+
+Requests are sent as soon as they are read, so several can run at once (a
+wait while console is polled), and responses are written as they finish.
+Each carries its request's id, to match them up by; one is generated for
+requests without one. --follow and --diff are not available in pipe mode.
 
 Global Options:
-  --json               Output as JSON
   --session <name>     Use specific session
+  --headed             Show the browser window
 
 Examples:
-  # Record from current page (preserves login state)
-  agent-browser open https://app.example.com/dashboard
-  agent-browser snapshot -i            # Explore and plan
-  agent-browser record start ./demo.webm
-  agent-browser click @e3              # Execute planned actions
-  agent-browser record stop
-
-  # Or specify a different URL
-  agent-browser record start ./demo.webm https://example.com
-
-  # Restart recording with a new file (stops previous, starts new)
-  agent-browser record restart ./take2.webm
+  agent-browser pipe < requests.jsonl
+  echo '{"command": "open example.com"}' | agent-browser pipe
 "##
         }
 
-        // === Console/Errors ===
-        "console" => {
+        // === HTTP bridge ===
+        "serve" => {
             r##"
-agent-browser console - View console logs
+agent-browser serve - Drive the session over HTTP
+
+Usage: agent-browser serve [--port <n>] [--host <addr>] [--token <secret>]
+
+Runs an HTTP server that forwards requests to the session's daemon, for CI
+systems and remote agents that cannot run the CLI. The daemon is started with
+the launch options given to serve.
 
-Usage: agent-browser console [--clear]
+Endpoints:
+  POST /command    Run one command. The body is either a protocol command
+                   ({"action": "navigate", "url": "..."}) or a command line
+                   ({"command": "get text @e1"}). Answers with the daemon's
+                   {"success", "data", "error"} response
+  GET  /health     Session name, for readiness checks
 
-View browser console output (log, warn, error, info).
+An events --follow command ({"command": "events --follow"}) streams events
+as chunked newline-delimited JSON until the client disconnects.
+
+So that web pages the user visits can't drive the browser, POST bodies must
+be sent as Content-Type: application/json, requests with an Origin header
+are refused, and without --token the Host header must be 127.0.0.1 or
+localhost with the port.
 
 Options:
-  --clear              Clear console log buffer
+  --port <n>         Port to listen on (default: 8377)
+  --host <addr>      Address to bind (default: 127.0.0.1)
+  --token <secret>   Require "Authorization: Bearer <secret>"
+                     (or set AGENT_BROWSER_SERVE_TOKEN)
 
 Global Options:
-  --json               Output as JSON
   --session <name>     Use specific session
+  --headed             Show the browser window
 
 Examples:
-  agent-browser console
-  agent-browser console --clear
+  agent-browser serve
+  agent-browser serve --port 9000 --host 0.0.0.0 --token "$TOKEN"
+  curl -H 'Content-Type: application/json' \
+    -d '{"command": "open example.com"}' localhost:8377/command
 "##
         }
-        "errors" => {
+
+        // === Install ===
+        "install" => {
             r##"
-agent-browser errors - View page errors
+agent-browser install - Install browser binaries
 
-Usage: agent-browser errors [--clear]
+Usage: agent-browser install [chromium|firefox|webkit]... [--with-deps]
 
-View JavaScript errors and uncaught exceptions.
+Downloads browser binaries with the daemon's Playwright, showing its progress,
+then launches each one headless to check it works. Installs Chromium when no
+browser is named.
 
 Options:
-  --clear              Clear error buffer
+  -d, --with-deps      Also install system dependencies (Linux only)
 
-Global Options:
-  --json               Output as JSON
-  --session <name>     Use specific session
+Examples:
+  agent-browser install
+  agent-browser install firefox webkit
+  agent-browser install --with-deps
+"##
+        }
+
+        // === Status ===
+        "status" => {
+            r##"
+agent-browser status - Show the session's daemon and browser at a glance
+
+Usage: agent-browser status
+
+Shows the round-trip time to the daemon, its pid, version, uptime and
+memory, and the browser's version with the URL of every open tab. Answered
+without launching a browser; prints "Daemon not running" (or
+{"running": false} with --json) when the session has no daemon.
 
 Examples:
-  agent-browser errors
-  agent-browser errors --clear
+  agent-browser status
+  agent-browser status --json
+  agent-browser --session work status
 "##
         }
 
-        // === Highlight ===
-        "highlight" => {
+        // === Raw ===
+        "raw" => {
             r##"
-agent-browser highlight - Highlight an element
+agent-browser raw - Send a protocol request as it is
 
-Usage: agent-browser highlight <selector>
+Usage: agent-browser raw <json>
+       agent-browser raw --file <path>
 
-Visually highlights an element on the page for debugging.
+Sends a request written out in the daemon's protocol and prints its answer
+as JSON, whatever the action. Useful for actions the CLI has no command for
+yet; the daemon is not asked up front whether it supports them. An "id" is
+generated when the payload has none. --dump-schema shows the protocol's
+framing and its core actions.
 
-Global Options:
-  --json               Output as JSON
-  --session <name>     Use specific session
+Options:
+  --file <path>        Read the payload from a file
 
 Examples:
-  agent-browser highlight "#target-element"
-  agent-browser highlight @e5
+  agent-browser raw '{"action": "title"}'
+  agent-browser raw '{"action": "scroll", "direction": "down", "amount": 500}'
+  agent-browser raw --file request.json
 "##
         }
 
-        // === State ===
-        "state" => {
+        // === Completions ===
+        "help" => {
             r##"
-agent-browser state - Save/load browser state
+agent-browser help - Show help
 
-Usage: agent-browser state <operation> <path>
-
-Save or restore browser state (cookies, localStorage, sessionStorage).
+Usage: agent-browser help [command]
 
-Operations:
-  save <path>          Save current state to file
-  load <path>          Note: State must be loaded at browser launch via --state flag
+Without a command, lists every command and the global options. With one,
+shows its arguments, flags, selector forms and examples, the same as
+agent-browser <command> --help.
 
-Applying State:
-  Use --state flag when launching browser to load saved state:
-  agent-browser --state ./auth-state.json open https://example.com
+Examples:
+  agent-browser help
+  agent-browser help click
+"##
+        }
+        "completions" => {
+            r##"
+agent-browser completions - Print a shell completion script
 
-  Or set AGENT_BROWSER_STATE environment variable.
+Usage: agent-browser completions <bash|zsh|fish>
 
-Global Options:
-  --json               Output as JSON
-  --session <name>     Use specific session
+Completes commands, their subcommands and flags, and the global options.
 
 Examples:
-  agent-browser state save ./auth-state.json
-  agent-browser --state ./auth-state.json open https://example.com
+  # bash, in ~/.bashrc
+  source <(agent-browser completions bash)
+
+  # zsh, in ~/.zshrc (after compinit)
+  source <(agent-browser completions zsh)
+
+  # fish
+  agent-browser completions fish > ~/.config/fish/completions/agent-browser.fish
 "##
         }
 
-        // === Session ===
-        "session" => {
+        // === Version ===
+        "version" => {
             r##"
-agent-browser session - Manage sessions
+agent-browser version - Show CLI, protocol, daemon and browser versions
 
-Usage: agent-browser session [operation]
+Usage: agent-browser version
 
-Manage isolated browser sessions. Each session has its own browser
-instance with separate cookies, storage, and state.
+Prints this CLI's version and protocol version, then asks the session's
+daemon for its version and protocol and the browser's build. Never starts a
+daemon or a browser: they are reported as not running or not launched. A
+daemon on another protocol version is flagged with how to restart it.
 
-Operations:
-  (none)               Show current session name
-  list                 List all active sessions
+--version (-V) prints only the CLI version.
 
-Environment:
-  AGENT_BROWSER_SESSION    Default session name
+Examples:
+  agent-browser version
+  agent-browser version --json
+"##
+        }
 
-Global Options:
-  --json               Output as JSON
-  --session <name>     Use specific session
+        // === Doctor ===
+        "doctor" => {
+            r##"
+agent-browser doctor - Check the setup and suggest fixes
+
+Usage: agent-browser doctor [--fix]
+
+Checks that Bun is installed and recent enough, the daemon script is found,
+the browser is downloaded, the socket directory is usable, no stale pid or
+socket files are left behind, and running daemons speak this CLI's protocol.
+Each failed check prints a fix. Exits non-zero when a check fails.
+
+Options:
+  --fix                Remove stale pid and socket files
 
 Examples:
-  agent-browser session
-  agent-browser session list
-  agent-browser --session test open example.com
+  agent-browser doctor
+  agent-browser doctor --browser firefox
+  agent-browser doctor --json
 "##
         }
 
-        // === Install ===
-        "install" => {
+        "bench" => {
             r##"
-agent-browser install - Install browser binaries
+agent-browser bench - Measure the tool's own overhead
+
+Usage: agent-browser bench [--n <count>]
+
+Times what agent-browser adds to each step of an agent loop and prints the
+p50, p90 and p99 and the slowest run of each:
 
-Usage: agent-browser install [--with-deps]
+  Round trip           Connecting to the session's daemon and getting an
+                       answer, which every command pays
+  Snapshot             A snapshot of the current page
+  Cold start           Starting a new daemon until it answers, which the
+                       first command of a session pays (3 runs, in a
+                       throwaway session; skipped with a remote daemon)
 
-Downloads and installs browser binaries required for automation.
+The session's daemon must be running, on the page to measure.
 
 Options:
-  -d, --with-deps      Also install system dependencies (Linux only)
+  --n <count>          Round trips and snapshots to time (default: 100)
 
 Examples:
-  agent-browser install
-  agent-browser install --with-deps
+  agent-browser open example.com && agent-browser bench
+  agent-browser bench --n 20 --json
 "##
         }
 
@@ -1645,9 +4621,14 @@ Examples:
         }
         "device" => {
             r##"
-agent-browser device - Manage iOS simulators
+agent-browser device - Emulate a device, or list iOS simulators
+
+Usage: agent-browser device <name>
+       agent-browser device list
 
-Usage: agent-browser device <subcommand>
+With a name, applies a Playwright device descriptor to the current page:
+viewport, devicePixelRatio, and (in Chromium) user agent and touch support.
+Names are matched ignoring case; unknown names list similar devices.
 
 Subcommands:
   list    List available iOS simulators
@@ -1657,98 +4638,306 @@ Options:
   --session <name>     Use specific session
 
 Examples:
+  agent-browser device "iPhone 14"
+  agent-browser device pixel 7
   agent-browser device list
   agent-browser -p ios device list
 "##
         }
 
+        "emulate" => {
+            r##"
+agent-browser emulate - Emulate location, locale and timezone
+
+Usage: agent-browser emulate <geo|locale|timezone|reset> [args]
+
+Overrides what location-aware and localized pages see, without relaunching
+the browser. Locale and timezone apply to the current tab (Chromium only).
+
+Subcommands:
+  geo <lat> <lon> [--accuracy <m>]
+                       Set navigator.geolocation (permission is granted)
+  locale <tag>         Set Intl, navigator.language and Accept-Language
+  timezone <tz>        Set the IANA timezone, e.g. Europe/Berlin
+  reset                Undo all three
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser emulate geo 48.8566 2.3522
+  agent-browser emulate locale fr-FR
+  agent-browser emulate timezone America/New_York
+  agent-browser emulate reset
+"##
+        }
+
+        "permissions" => {
+            r##"
+agent-browser permissions - Grant site permissions
+
+Usage: agent-browser permissions grant <origin|*> <permission...>
+       agent-browser permissions clear
+
+Grants browser permissions up front, so a page asking for the camera, the
+clipboard or notifications gets them instead of stalling on a prompt no one
+answers. An origin like example.com means https; * grants them to every
+origin. Permissions may also be given comma-separated. clear revokes every
+permission granted, including the one emulate geo grants.
+
+Permissions:
+  camera, microphone, geolocation, notifications, clipboard-read,
+  clipboard-write, midi, midi-sysex, background-sync, payment-handler,
+  storage-access, local-fonts, accelerometer, gyroscope, magnetometer,
+  ambient-light-sensor
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser permissions grant https://meet.example.com camera microphone
+  agent-browser permissions grant example.com clipboard-read,clipboard-write
+  agent-browser permissions grant '*' notifications
+  agent-browser permissions clear
+"##
+        }
+
+        "viewport" => {
+            r##"
+agent-browser viewport - Set the viewport size
+
+Usage: agent-browser viewport <width> <height> [--scale <n>]
+
+Resizes the page viewport. --scale sets the devicePixelRatio as well
+(Chromium only), for testing HiDPI layouts and srcset images.
+
+Options:
+  --scale <n>          Device scale factor (e.g. 2 or 3)
+
+Global Options:
+  --json               Output as JSON
+  --session <name>     Use specific session
+
+Examples:
+  agent-browser viewport 1280 720
+  agent-browser viewport 390 844 --scale 3
+"##
+        }
+
         _ => return false,
     };
-    println!("{}", help.trim());
+    let help = help.trim();
+    // Commands that take an element list the selector forms before the global options
+    if help.contains("<sel") || help.contains("<source>") {
+        let selectors = format!("{}\nGlobal Options:", SELECTORS);
+        println!("{}", help.replacen("Global Options:", &selectors, 1));
+    } else {
+        println!("{}", help);
+    }
     true
 }
 
+/// The selector forms element commands accept
+const SELECTORS: &str = r#"Selectors (<sel>):
+  @e1                        Ref from the last snapshot
+  #id, .class, div > a       CSS
+  "text=Submit"              Element containing text (quote for an exact match)
+  'role=button[name="Save"]' ARIA role and accessible name
+  "xpath=//div[@id='main']"  XPath (also any selector starting with //)
+"#;
+
 pub fn print_help() {
     println!(
         r#"
 agent-browser - fast browser automation CLI for AI agents
 
 Usage: agent-browser <command> [args] [options]
+       agent-browser help <command>    Arguments, flags and examples of one command
 
 Core Commands:
-  open <url>                 Navigate to URL
-  click <sel>                Click element (or @ref)
+  open <url>                 Navigate to URL (--new-tab, --window, --wait-until, ...)
+  click <sel>                Click element (or @ref; --nth <n>, --all)
+  click --text <text>        Click by label or visible text (--exact, --nth <n>)
   dblclick <sel>             Double-click element
   type <sel> <text>          Type into element
-  fill <sel> <text>          Clear and fill
-  press <key>                Press key (Enter, Tab, Control+a)
-  hover <sel>                Hover element
+  fill <sel> <text>          Clear and fill (--stdin, --secret <ENV_NAME>)
+  fill --form <file>         Fill many fields from a JSON or YAML file
+  press <key> [x<n>]         Press key (Enter, Tab, Control+a), optionally n times
+  keys down|up <key>         Hold or release a key (e.g. Shift across clicks)
+  hover <sel>                Hover element (--nth <n>, --all)
   focus <sel>                Focus element
-  check <sel>                Check checkbox
+  blur <sel>                 Remove focus from element
+  clear <sel>                Clear input
+  check <sel>                Check checkbox (--nth <n>, --all)
   uncheck <sel>              Uncheck checkbox
-  select <sel> <val...>      Select dropdown option
+  select <sel> <val...>      Select dropdown option (or --label <text>, --index <n>)
   drag <src> <dst>           Drag and drop
   upload <sel> <files...>    Upload files
   download <sel> <path>      Download file by clicking element
+  download wait [path]       Wait for the next download to finish
+  download dir <path>        Set the download directory
   scroll <dir> [px]          Scroll (up/down/left/right)
+  scroll to <sel> | by <dx> <dy> | top | bottom
+                             Scroll to an element, by an offset, or to an end
   scrollintoview <sel>       Scroll element into view
-  wait <sel|ms>              Wait for element or time
-  screenshot [path]          Take screenshot
-  pdf <path>                 Save as PDF
-  snapshot                   Accessibility tree with refs (for AI)
-  eval <js>                  Run JavaScript
+  wait <sel|ms>              Wait for element or time (--hidden, --url, --text, --idle,
+                             --spa-idle, --request, --response)
+  watch <sel>                Print DOM changes inside an element (--event, --once)
+  expect <kind> <args>       Assert text, visible, url or title (retries, --timeout)
+  screenshot [sel] [path]    Take screenshot (--full, --format, --clip, --annotate-refs)
+  screenshot diff <png>      Compare page against a baseline image
+  pdf [path]                 Save as PDF (--format, --landscape, --margin)
+  save <path.mhtml>          Save the rendered page (--html-dir <dir> for HTML + assets)
+  snapshot                   Accessibility tree with refs (--format json|yaml|md)
+  snapshot diff              Changes since the previous snapshot
+  read [selector|next|prev]  Read the page a chunk at a time, like a screen reader
+  refs [--filter <text>]     List refs from the last snapshot
+  visible-elements           Interactive elements in the viewport (--role)
+  eval <js>                  Run JavaScript (--file, --selector, --arg <json>)
   connect <port|url>         Connect to browser via CDP
-  close                      Close browser
+  close                      Close browser (--keep-daemon, --save-state <file>)
 
 Navigation:
   back                       Go back
   forward                    Go forward
   reload                     Reload page
+  history [-n <count>]       Recent navigations; history go <n> to revisit one
 
 Get Info:  agent-browser get <what> [selector]
-  text, html, value, attr <name>, title, url, count, box, styles
+  text, html, value, attr <name>, title, url, count, box, styles,
+  text <sel> --all            Text of every match (--nth <n> for one)
+  style <sel> <prop...>       Computed CSS values (color, font-size, ...)
+  article [--selector <sel>]  Main content as Markdown
+  metrics                     Navigation timing, FCP/LCP/CLS, JS heap, resources
+
+Links:  agent-browser links [--selector <sel>] [--absolute]
+Forms:  agent-browser forms   Forms with fields, labels, values, required
+Tables: agent-browser table <sel> [--format csv|json]   Table as CSV or JSON
+Audit:  agent-browser audit a11y [--fail-on <impact>]   Accessibility violations (axe-core)
 
 Check State:  agent-browser is <what> <selector>
   visible, enabled, checked
 
 Find Elements:  agent-browser find <locator> <value> <action> [text]
   role, text, label, placeholder, alt, title, testid, first, last, nth
+  find <query> [--role <role>] [--limit <n>]   List best-matching elements with refs
 
 Mouse:  agent-browser mouse <action> [args]
-  move <x> <y>, down [btn], up [btn], wheel <dy> [dx]
+  move <x> <y>, click <x> <y> [--button btn] [--double], down [btn], up [btn], wheel <dy> [dx]
+
+Emulation:
+  viewport <w> <h> [--scale <n>]  Set viewport size (alias: set viewport)
+  device <name>              Emulate a device, e.g. "iPhone 14" (alias: set device)
+  emulate geo <lat> <lon>    Geolocation (--accuracy <m>)
+  emulate locale <tag>       Locale, e.g. de-DE
+  emulate timezone <tz>      Timezone, e.g. Asia/Tokyo
+  emulate reset              Undo geo, locale and timezone
+  permissions grant <origin> <permission...>
+                             Grant camera, notifications, ... (* for any origin)
+  permissions clear          Revoke granted permissions
 
 Browser Settings:  agent-browser set <setting> [value]
   viewport <w> <h>, device <name>, geo <lat> <lng>
-  offline [on|off], headers <json>, credentials <user> <pass>
+  offline [on|off], headers <json>, user-agent <ua>, credentials <user> <pass>
   media [dark|light] [reduced-motion]
 
 Network:  agent-browser network <action>
   route <url> [--abort|--body <json>]
   unroute [url]
   requests [--clear] [--filter <pattern>]
+  log [--filter <url-glob>] [--since <id>]
+  capture <url-glob> [--limit <n>] | off
+  show <id>                  Captured request and response bodies
+  offline <on|off>
+  throttle <slow3g|fast3g|off|custom> [--down <kbps>] [--up <kbps>] [--latency <ms>]
+
+Headers:
+  headers set <name> <value> Add a header to all requests
+  headers clear              Remove extra headers
+
+Blocking:
+  block <pattern>...         Abort matching requests (e.g. "**/*.png" analytics)
+  unblock <pattern>... | --all
+
+Mocks:  agent-browser mock <action>
+  add <url> [--status <code>] [--body-file <file>] [--header <k:v>]
+  list
+  remove <id>
+
+Fetch:  agent-browser fetch <url> [-X <method>] [-d <text|@file>] [-H <k:v>] [-o <file>]
+                             HTTP request from the page, with its cookies
 
 Storage:
-  cookies [get|set|clear]    Manage cookies (set supports --url, --domain, --path, --httpOnly, --secure, --sameSite, --expires)
-  storage <local|session>    Manage web storage
+  cookies [list|get|set|clear|export|import]
+                             Manage cookies (set supports --url, --domain, --path, --httpOnly, --secure, --sameSite, --expires)
+  storage [get|set|remove|clear]
+                             Manage web storage (--session-storage for sessionStorage)
+  clipboard <read|write <text>|copy|paste>
+                             Browser clipboard (permissions granted automatically)
 
 Tabs:
   tab [new|list|close|<n>]   Manage tabs
+  context [new|list|switch|close]
+                             Isolated contexts, each with its own cookies
+
+Frames:
+  frame list                 List frames of the current page
+  frame use <selector|index> Run element commands inside an iframe
+  frame main                 Back to the main frame
+
+Dialogs:
+  dialog accept [text]       Accept the open (or next) dialog
+  dialog dismiss             Dismiss the open (or next) dialog
+  dialog auto <accept|dismiss|off>
+                             Set how future dialogs are handled
 
 Debug:
-  trace start|stop [path]    Record trace
-  record start <path> [url]  Start video recording (WebM)
-  record stop                Stop and save video
-  console [--clear]          View console logs
+  trace start|stop <file>    Record Playwright trace
+  coverage start | stop [--lcov <file>]
+                             Measure JS/CSS coverage (Chromium)
+  har start <path> | stop    Record network traffic as HAR
+  record start <path|dir> [url]
+                             Start video recording (WebM)
+  record start <file.ab|.json> [url]
+                             Record your actions in the browser as a script
+  record stop                Stop and save the video or script, printing its path
+  console [--follow] [--level error|warn|all] [--clear]
+                             View console logs
+  events --follow [--types console,network,dialog]
+                             Stream events as newline-delimited JSON
   errors [--clear]           View page errors
-  highlight <sel>            Highlight element
+  highlight <sel>            Outline matching elements (--duration, --label)
 
 Sessions:
   session                    Show current session name
   session list               List active sessions
+  session each <command>     Run a command in every active session
+  profile use <name>         Switch the session to a saved cookie jar (off, list)
+  auth login <url>           Log in in a headed window, save it as a profile (--save)
+
+Daemon:
+  status                     Ping, daemon pid/uptime/memory, browser version and tabs
+  raw <json>                 Send a protocol request and print the response (--file)
+  daemon status              Uptime and time left before the daemon shuts down
+  daemon config              Change --idle-timeout/--max-lifetime of a running daemon
+  daemon upgrade             Restart the daemon on this install, keeping tabs and cookies
+
+Scripts:
+  run <file|->               Run commands from a file or stdin, one per line
+  <cmd> ++ <cmd> ...         Run several commands over one connection
+  mcp                        Serve browser tools over MCP (stdio)
+  pipe                       Forward JSON requests from stdin, one per line
+  serve [--port <n>]         Accept commands over HTTP (POST /command)
 
 Setup:
-  install                    Install browser binaries
+  install [browser]...       Install chromium (default), firefox or webkit
   install --with-deps        Also install system dependencies (Linux)
+  doctor [--fix]             Check the setup and suggest fixes
+  bench [--n <count>]        Time round trips, snapshots and daemon cold starts
+  version                    CLI, protocol, daemon and browser versions
+  completions <shell>        Completion script for bash, zsh or fish
 
 Snapshot Options:
   -i, --interactive          Only interactive elements
@@ -1756,8 +4945,24 @@ Snapshot Options:
   -d, --depth <n>            Limit tree depth
   -s, --selector <sel>       Scope to CSS selector
 
+{selectors}
+
 Options:
-  --session <name>           Isolated session (or AGENT_BROWSER_SESSION env)
+  -S, --session <name>       Isolated session (or AGENT_BROWSER_SESSION env)
+  --all-sessions             Run the command in every active session
+  --lock                     Wait for other --lock invocations on the session to
+                             finish first (--no-lock to opt out of config/env)
+  --lock-timeout <ms>        How long --lock waits (default: 30000)
+  --retry <n>                Retry interactions up to n times when the element
+                             detaches or won't settle (or AGENT_BROWSER_RETRY)
+  --idle-timeout <duration>  Stop a daemon started now after this long without
+                             commands, e.g. 10m (or AGENT_BROWSER_IDLE_TIMEOUT)
+  --max-lifetime <duration>  Stop a daemon started now this long after it started,
+                             e.g. 2h (or AGENT_BROWSER_MAX_LIFETIME)
+  --min-delay <ms>           Wait at least this long between commands to the
+                             session, across processes (or AGENT_BROWSER_MIN_DELAY)
+  --max-rps <n>              Send at most n commands a second to the session
+                             (or AGENT_BROWSER_MAX_RPS)
   --profile <path>           Persistent browser profile (or AGENT_BROWSER_PROFILE env)
   --state <path>             Load storage state from JSON file (or AGENT_BROWSER_STATE env)
   --headers <json>           HTTP headers scoped to URL's origin (for auth)
@@ -1774,12 +4979,53 @@ Options:
   --allow-file-access        Allow file:// URLs to access local files (Chromium only)
   -p, --provider <name>      Browser provider: ios, browserbase, kernel, browseruse
   --device <name>            iOS device name (e.g., "iPhone 15 Pro")
-  --json                     JSON output
+  --json                     JSON output (same as --format json)
+  --no-color                 No colors (also NO_COLOR, or when stdout isn't a terminal)
+  -q, --quiet                Print results and errors only, no ✓ confirmations or
+                             waiting line
+  -v, --verbose              Log each request, response and round trip to stderr
+  --timings                  Report where the time went on stderr: spawn, connect,
+                             daemon, transfer and total (in the JSON with --json)
+  --show-daemon-log          When a command fails, print the last lines of the
+                             daemon's log to stderr (or AGENT_BROWSER_SHOW_DAEMON_LOG)
+  --dry-run                  Print the request and the session it's for, without
+                             sending it or starting a daemon
+  --format <fmt>             plain (default), json, jsonl (one line per result as it
+                             arrives) or yaml; before snapshot, screenshot, pdf and
+                             table, whose own --format comes after them
+  --filter <path>            Print only this part of the JSON result, e.g. .data.url
+                             or .data.tabs[0].title (strings raw unless --json);
+                             before refs and network, whose own --filter comes after
+  -o, --output <path>        Write the result (snapshot, HTML, text, screenshot image,
+                             or the document with --json) to a file; - for stdout.
+                             Before get and fetch, whose own --output comes after
   --full, -f                 Full page screenshot
   --headed                   Show browser window (not headless)
+  --headless                 Run headless (overrides headless = false in config)
+  --browser <name>           chromium (default), firefox or webkit (or AGENT_BROWSER_BROWSER)
+                             --headed/--headless/--browser restart a running browser
+                             that is in a different mode
   --cdp <port>               Connect via CDP (Chrome DevTools Protocol)
   --debug                    Debug output
+  --timeout <ms>             Fail the command if it takes longer (default: 30s,
+                             or the command's own timeout). After open, wait,
+                             expect or download it is that command's --timeout
+  --remote <url>             Use a daemon at tcp://host:port instead of a local one
+                             (or AGENT_BROWSER_REMOTE)
+  --socket <path>            Daemon socket path, in place of <session>.sock (Unix,
+                             or AGENT_BROWSER_SOCKET)
+  --runtime <bun|path>       Bun executable new daemons run on (default: bun from
+                             PATH or ~/.bun/bin; or AGENT_BROWSER_RUNTIME)
   --version, -V              Show version
+  --dump-schema              Print the typed core of the daemon protocol as JSON Schema
+
+Other flags must be ones the command takes (see agent-browser <command> --help).
+End them with -- to pass text that starts with -, e.g. fill @e1 -- --text.
+
+Exit Codes:
+  0  Success                    3  Timed out
+  1  Other failure              4  Daemon unavailable
+  2  Element not found          5  Navigation failed
 
 Environment:
   AGENT_BROWSER_SESSION          Session name (default: "default")
@@ -1788,6 +5034,37 @@ Environment:
   AGENT_BROWSER_STREAM_PORT      Enable WebSocket streaming on port (e.g., 9223)
   AGENT_BROWSER_IOS_DEVICE       Default iOS device name
   AGENT_BROWSER_IOS_UDID         Default iOS device UDID
+  AGENT_BROWSER_DEFAULT_TIMEOUT  Default action timeout in ms (default: 60000)
+  AGENT_BROWSER_VIEWPORT         Default viewport, e.g. 1280x720
+  AGENT_BROWSER_COMPRESSION      gzip: compress large responses from the daemon
+  AGENT_BROWSER_RUNTIME_DIR      Directory for sockets, pid files, temp files and the
+                                 extracted daemon
+  AGENT_BROWSER_SOCKET_DIR       Directory for sockets and pid files only
+  AGENT_BROWSER_HOME             Run the daemon from this checkout, not the embedded one
+  AGENT_BROWSER_RUNTIME          Default for --runtime
+  AGENT_BROWSER_MAX_RESTARTS     Times to restart a dead daemon and resend (default: 2)
+  AGENT_BROWSER_LOCK             1: same as --lock
+  AGENT_BROWSER_LOCK_TIMEOUT     Default for --lock-timeout
+  AGENT_BROWSER_RETRY            Default for --retry
+  AGENT_BROWSER_SHOW_DAEMON_LOG  1: same as --show-daemon-log
+  AGENT_BROWSER_PROFILES_DIR     Where `profile use` keeps profiles
+  AGENT_BROWSER_IDLE_TIMEOUT     Default for --idle-timeout
+  AGENT_BROWSER_MAX_LIFETIME     Default for --max-lifetime
+  AGENT_BROWSER_MIN_DELAY        Default for --min-delay
+  AGENT_BROWSER_MAX_RPS          Default for --max-rps
+  AGENT_BROWSER_AUDIT_LOG        1 or a path: log every command the daemon runs as JSONL
+  AGENT_BROWSER_REMOTE           Daemon address, e.g. tcp://10.0.0.5:9400
+  AGENT_BROWSER_TOKEN            Token the daemon requires on every request
+  AGENT_BROWSER_LISTEN           Daemon: listen on host:port instead of a socket (needs a token)
+
+Config Files:
+  ~/.config/agent-browser/config.toml   User defaults
+  .agent-browser.toml                   Project defaults (searched upwards from cwd)
+  [aliases] in either defines commands of your own ($1, $2, ... and $@ for arguments):
+    login = "open $1 ++ fill #user $2 ++ fill #pass $3 ++ click #submit"
+  ~/.config/agent-browser/commands/<name>.js
+                                        Command <name>: a function run in the page with
+                                        the arguments, printed like eval
 
 Examples:
   agent-browser open example.com
@@ -1806,10 +5083,90 @@ iOS Simulator (requires Xcode and Appium):
   agent-browser -p ios device list                         # List simulators
   agent-browser -p ios swipe up                            # Swipe gesture
   agent-browser -p ios tap @e1                             # Touch element
-"#
+"#,
+        selectors = SELECTORS.trim_end()
     );
 }
 
 pub fn print_version() {
     println!("agent-browser {}", env!("CARGO_PKG_VERSION"));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_audit_result_is_not_a_navigation() {
+        // As handleAudit sends it: runAudit's result, with the page's url
+        let data = json!({
+            "url": "https://example.com/",
+            "violations": [{
+                "id": "image-alt",
+                "impact": "critical",
+                "help": "Images must have alternate text",
+                "helpUrl": "https://dequeuniversity.com/rules/axe/4.10/image-alt",
+                "nodes": [{ "target": "img", "html": "<img>", "failureSummary": "" }],
+            }],
+            "passes": 12,
+            "incomplete": 0,
+            "failOn": "serious",
+            "passed": false,
+        });
+        assert!(print_url_result(Some("audit"), &data));
+        assert!(!print_url_result(Some("navigate"), &data));
+        let resp = Response {
+            success: true,
+            data: Some(data),
+            ..Default::default()
+        };
+        print_response(&resp, false, Some("audit"));
+    }
+
+    #[test]
+    fn test_metrics_result_is_not_a_navigation() {
+        let data = json!({
+            "url": "https://example.com/",
+            "navigation": { "ttfb": 80, "domContentLoaded": 300, "load": 450 },
+            "paint": { "fcp": 320, "lcp": 400, "cls": 0 },
+        });
+        assert!(print_url_result(Some("metrics"), &data));
+    }
+
+    #[test]
+    fn test_network_show_result_is_not_a_navigation() {
+        // A CapturedExchange, as handleNetworkShow sends it
+        let data = json!({
+            "id": 3,
+            "method": "POST",
+            "url": "https://example.com/api/cart",
+            "request": { "headers": { "content-type": "application/json" }, "body": "{}" },
+            "status": 201,
+            "statusText": "Created",
+            "response": { "headers": {}, "body": "{\"ok\":true}" },
+        });
+        assert!(print_url_result(Some("network_show"), &data));
+    }
+
+    fn payload_of(data: serde_json::Value) -> Option<Vec<u8>> {
+        let resp = Response {
+            success: true,
+            data: Some(data),
+            ..Default::default()
+        };
+        payload(&resp, false)
+    }
+
+    #[test]
+    fn test_payload_of_fetch_and_table() {
+        let fetched = json!({ "status": 200, "body": "{\"a\":1}" });
+        assert_eq!(payload_of(fetched), Some(b"{\"a\":1}".to_vec()));
+        let binary = json!({ "status": 200, "body": "iVBORw==", "base64": true });
+        assert_eq!(payload_of(binary), Some(vec![0x89, b'P', b'N', b'G']));
+        let parsed = json!({ "status": 200, "body": { "a": 1 } });
+        assert_eq!(payload_of(parsed), Some(b"{\n  \"a\": 1\n}\n".to_vec()));
+        let table = json!({ "output": "name,age\nAda,36" });
+        assert_eq!(payload_of(table), Some(b"name,age\nAda,36\n".to_vec()));
+    }
+}
@@ -0,0 +1,186 @@
+//! `agent-browser pipe`: one JSON request per line on stdin, one JSON response
+//! per line on stdout, all over a single daemon connection, so an agent
+//! framework can keep one child process instead of running the CLI for every
+//! action.
+//!
+//! Requests are what `serve` takes in `POST /command`: a protocol command
+//! (`{"action": "navigate", "url": "..."}`) or a command line
+//! (`{"command": "get text @e1"}`). Each is sent as soon as it is read, so
+//! several can run at once, e.g. a `wait` for a selector while `console` is
+//! polled. Responses are written as they arrive, each with its request's `id`
+//! (generated when the request has none) to match them up by.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::commands::secret_value;
+use crate::connection::DaemonConnection;
+use crate::flags::Flags;
+use crate::serve::command_from_body;
+
+/// The daemon request for one line of input, or the failure to answer it with
+fn request(line: &str, flags: &Flags) -> Result<Value, Value> {
+    // The caller's id, even for a request that turns out to be invalid
+    let id = serde_json::from_str::<Value>(line)
+        .ok()
+        .and_then(|v| v.get("id").cloned())
+        .unwrap_or(Value::Null);
+    if !id.is_null() && !id.is_string() {
+        return Err(failure(id, "id must be a string"));
+    }
+
+    let mut cmd = command_from_body(line.as_bytes(), flags).map_err(|e| failure(id.clone(), &e))?;
+    if !id.is_null() {
+        cmd["id"] = id;
+    }
+
+    // These answer with a stream rather than one response
+    let follow = cmd.get("follow").and_then(|v| v.as_bool()) == Some(true);
+    if follow || cmd.get("diff").is_some() {
+        let message = "--follow and --diff are not available in pipe mode";
+        return Err(failure(cmd["id"].clone(), message));
+    }
+    Ok(cmd)
+}
+
+fn failure(id: Value, error: &str) -> Value {
+    json!({ "id": id, "success": false, "data": null, "error": error })
+}
+
+/// Write one line of output; false once stdout is closed
+fn write_line(value: &Value) -> bool {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", value)
+        .and_then(|_| stdout.flush())
+        .is_ok()
+}
+
+/// Forward requests from stdin until it is closed, then wait for the ones
+/// still running. The session's daemon has already been started with the
+/// launch options from the command line. Returns false if the daemon can't
+/// be reached or stdout is closed while replying.
+pub fn run(flags: &Flags) -> bool {
+    let mut conn = match DaemonConnection::open(&flags.session) {
+        Ok(conn) => conn,
+        Err(e) => {
+            write_line(&failure(Value::Null, &e.to_string()));
+            return false;
+        }
+    };
+    let mut reader = match conn.try_clone() {
+        Ok(reader) => reader,
+        Err(e) => {
+            write_line(&failure(Value::Null, &e.to_string()));
+            return false;
+        }
+    };
+
+    // `fill --secret` values to mask in their responses, by request id
+    let secrets: Arc<Mutex<HashMap<String, String>>> = Arc::default();
+    let (answered, answers) = mpsc::channel();
+    let responses = {
+        let secrets = Arc::clone(&secrets);
+        thread::spawn(move || {
+            // The connection closing (after `close`, say) ends the pipe
+            while let Ok(Some(mut resp)) = reader.next_response() {
+                let secret = resp
+                    .id
+                    .as_ref()
+                    .and_then(|id| secrets.lock().unwrap().remove(id));
+                if let Some(secret) = secret {
+                    resp.redact(&secret);
+                }
+                if !write_line(&serde_json::to_value(resp).unwrap_or_default()) {
+                    return false;
+                }
+                if answered.send(()).is_err() {
+                    break;
+                }
+            }
+            true
+        })
+    };
+
+    let mut submitted = 0;
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cmd = match request(&line, flags) {
+            Ok(cmd) => cmd,
+            Err(answer) => {
+                if !write_line(&answer) {
+                    return false;
+                }
+                continue;
+            }
+        };
+        let id = cmd["id"].as_str().unwrap_or_default().to_string();
+        if let Some(secret) = secret_value(&cmd) {
+            secrets
+                .lock()
+                .unwrap()
+                .insert(id.clone(), secret.to_string());
+        }
+        match conn.submit(&cmd) {
+            Ok(()) => submitted += 1,
+            Err(e) => {
+                if !write_line(&failure(json!(id), &e.to_string())) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    // Until every request is answered, or the daemon goes away first
+    for _ in 0..submitted {
+        if answers.recv().is_err() {
+            break;
+        }
+    }
+    !responses.is_finished() || responses.join().unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::parse_flags;
+
+    #[test]
+    fn test_request() {
+        let flags = parse_flags(&[]);
+        let cmd = request(r#"{"id": "1", "command": "get url"}"#, &flags).unwrap();
+        assert_eq!(cmd["id"], "1");
+        assert_eq!(cmd["action"], "url");
+        let cmd = request(r#"{"action": "title"}"#, &flags).unwrap();
+        assert!(cmd["id"].is_string());
+    }
+
+    #[test]
+    fn test_invalid_requests_keep_their_id() {
+        let flags = parse_flags(&[]);
+
+        let answer = request(r#"{"id": "7", "url": "https://example.com"}"#, &flags).unwrap_err();
+        assert_eq!(answer["id"], "7");
+        assert_eq!(answer["success"], false);
+        assert!(answer["error"].as_str().unwrap().contains("\"action\""));
+
+        let answer = request(r#"{"id": "8", "command": "console --follow"}"#, &flags).unwrap_err();
+        assert_eq!(answer["id"], "8");
+        assert!(answer["error"].as_str().unwrap().contains("--follow"));
+
+        let answer = request(r#"{"id": 9, "action": "title"}"#, &flags).unwrap_err();
+        assert_eq!(answer["error"], "id must be a string");
+
+        let answer = request("not json", &flags).unwrap_err();
+        assert_eq!(answer["id"], Value::Null);
+        assert!(answer["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid JSON"));
+    }
+}
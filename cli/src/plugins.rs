@@ -0,0 +1,79 @@
+//! Commands of your own in JavaScript. `<name>.js` in the `commands`
+//! directory next to the user config file becomes `agent-browser <name>`:
+//! the file holds a function, which is evaluated in the page with the
+//! command's arguments as strings. Its return value prints as `eval`'s does.
+//!
+//! ```js
+//! // ~/.config/agent-browser/commands/count.js: agent-browser count <selector>
+//! (selector) => document.querySelectorAll(selector).length
+//! ```
+
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::ParseError;
+use crate::config::user_config_path;
+
+/// `~/.config/agent-browser/commands`, or under `$XDG_CONFIG_HOME`
+pub fn commands_dir() -> Option<PathBuf> {
+    Some(user_config_path()?.parent()?.join("commands"))
+}
+
+/// The file behind the plugin command `name`, if there is one. Only plain
+/// names are looked up, so a command can't reach outside the directory.
+pub fn find(name: &str) -> Option<PathBuf> {
+    let plain = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !plain {
+        return None;
+    }
+    Some(commands_dir()?.join(format!("{}.js", name))).filter(|path| path.is_file())
+}
+
+/// The `evaluate` request that runs the plugin at `path` with `args`
+pub fn command(path: &PathBuf, args: &[&str], id: &str) -> Result<Value, ParseError> {
+    let source = fs::read_to_string(path).map_err(|e| ParseError::InvalidValue {
+        message: format!("Failed to read {}: {}", path.display(), e),
+        usage: "<command> [args...]",
+    })?;
+    // Called as `(<source>)(...args)`, which a closing semicolon would break
+    let script = source.trim_end().trim_end_matches(';');
+    // `args` is sent even when empty: without it the function isn't called
+    Ok(json!({ "id": id, "action": "evaluate", "script": script, "args": args }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_rejects_paths() {
+        assert_eq!(find("../config"), None);
+        assert_eq!(find("a/b"), None);
+        assert_eq!(find(""), None);
+    }
+
+    #[test]
+    fn test_command() {
+        let path = std::env::temp_dir().join(format!("ab-plugin-test-{}.js", std::process::id()));
+        fs::write(
+            &path,
+            "// count <selector>\n(selector) => document.querySelectorAll(selector).length;\n",
+        )
+        .unwrap();
+
+        let cmd = command(&path, &["li"], "1").unwrap();
+        assert_eq!(cmd["action"], "evaluate");
+        assert_eq!(
+            cmd["script"],
+            "// count <selector>\n(selector) => document.querySelectorAll(selector).length"
+        );
+        assert_eq!(cmd["args"], json!(["li"]));
+        assert_eq!(command(&path, &[], "2").unwrap()["args"], json!([]));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+//! `profile use <name>`: named cookie jars, one per account. A profile is the
+//! storage state (cookies and localStorage) kept in
+//! `~/.agent-browser/profiles/<name>.json`. While a session uses one, the CLI
+//! loads it into every browser the session starts and saves the session's
+//! state back to it after every command, so switching accounts is a single
+//! `profile use`. Unlike `--profile <dir>`, a whole browser profile fixed at
+//! launch, these switch on the running browser.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::gen_id;
+use crate::connection::{create_private_dir, is_daemon_running, send_command};
+use crate::flags::Flags;
+use crate::format;
+
+/// Which profile each session uses, a file per session holding its name
+const ACTIVE_DIR: &str = ".active";
+
+/// Where profiles are kept: AGENT_BROWSER_PROFILES_DIR, or
+/// `~/.agent-browser/profiles`. Not the runtime directory, which can be
+/// cleared at logout.
+fn profiles_dir() -> Option<PathBuf> {
+    match env::var("AGENT_BROWSER_PROFILES_DIR") {
+        Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => dirs::home_dir().map(|home| home.join(".agent-browser").join("profiles")),
+    }
+}
+
+/// Profile names become file names
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid profile name '{}': use letters, digits, '-' or '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+fn state_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.json", name))
+}
+
+/// The profile `session` uses, if any. A remote daemon can't reach the files
+/// here, so it never uses one.
+pub fn active(session: &str) -> Option<String> {
+    if env::var_os("AGENT_BROWSER_REMOTE").is_some() {
+        return None;
+    }
+    let text = fs::read_to_string(profiles_dir()?.join(ACTIVE_DIR).join(session)).ok()?;
+    let name = text.trim();
+    validate_name(name).ok().map(|_| name.to_string())
+}
+
+fn set_active(dir: &Path, session: &str, name: Option<&str>) -> Result<(), String> {
+    let path = dir.join(ACTIVE_DIR).join(session);
+    let result = match name {
+        Some(name) => {
+            create_private_dir(&dir.join(ACTIVE_DIR)).and_then(|_| fs::write(&path, name))
+        }
+        None => match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    };
+    result.map_err(|e| format!("Failed to update {}: {}", path.display(), e))
+}
+
+fn request(session: &str, action: &str, path: Option<&PathBuf>) -> Result<Value, String> {
+    let mut cmd = json!({ "id": gen_id(), "action": action });
+    if let Some(path) = path {
+        cmd["path"] = json!(path.to_string_lossy());
+    }
+    let resp = send_command(cmd, session).map_err(|e| e.to_string())?;
+    if !resp.success {
+        return Err(resp.error.unwrap_or_else(|| format!("{} failed", action)));
+    }
+    Ok(resp.data.unwrap_or(Value::Null))
+}
+
+/// Load the session's profile into its browser, which was just started
+pub fn restore(session: &str) {
+    let (Some(name), Some(dir)) = (active(session), profiles_dir()) else {
+        return;
+    };
+    let path = state_path(&dir, &name);
+    if !path.is_file() {
+        return;
+    }
+    if let Err(e) = request(session, "state_load", Some(&path)) {
+        eprintln!(
+            "{} Couldn't load profile '{}': {}",
+            color::warning_indicator(),
+            name,
+            e
+        );
+    }
+}
+
+/// Save the session's browser state to its profile, if it uses one
+pub fn save(session: &str) {
+    let (Some(name), Some(dir)) = (active(session), profiles_dir()) else {
+        return;
+    };
+    if let Err(e) = save_to(session, &dir, &name) {
+        eprintln!(
+            "{} Couldn't save profile '{}': {}",
+            color::warning_indicator(),
+            name,
+            e
+        );
+    }
+}
+
+fn save_to(session: &str, dir: &Path, name: &str) -> Result<(), String> {
+    create_private_dir(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    request(session, "state_save", Some(&state_path(dir, name))).map(|_| ())
+}
+
+/// Save the session's browser state as profile `name`, whichever profile the
+/// session uses, and return where it was saved
+pub fn save_as(session: &str, name: &str) -> Result<PathBuf, String> {
+    validate_name(name)?;
+    let dir = profiles_dir().ok_or("Couldn't find the home directory for profiles")?;
+    save_to(session, &dir, name)?;
+    Ok(state_path(&dir, name))
+}
+
+/// Saved profiles, by name
+fn list(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    name.strip_suffix(".json").map(String::from)
+                })
+                .filter(|name| validate_name(name).is_ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Save the current profile, then clear the browser's cookies and load
+/// `name`'s. Without a running daemon only the choice is recorded; the next
+/// browser the session starts loads it.
+fn switch(session: &str, dir: &Path, name: &str) -> Result<Value, String> {
+    validate_name(name)?;
+    let running = is_daemon_running(session);
+    let mut loaded = Value::Null;
+    if running {
+        if let Some(current) = active(session) {
+            save_to(session, dir, &current)?;
+        }
+        request(session, "cookies_clear", None)?;
+        let path = state_path(dir, name);
+        if path.is_file() {
+            loaded = request(session, "state_load", Some(&path))?;
+        }
+    }
+    set_active(dir, session, Some(name))?;
+    Ok(json!({
+        "profile": name,
+        "cookieCount": loaded.get("cookieCount").cloned().unwrap_or(Value::Null),
+        // Loaded by the next browser the session starts
+        "pending": !running,
+    }))
+}
+
+fn run_subcommand(args: &[String], session: &str) -> Result<Value, String> {
+    const USAGE: &str = "Usage: agent-browser profile <use <name>|off|list|current>";
+    if env::var_os("AGENT_BROWSER_REMOTE").is_some() {
+        return Err("Profiles are files the CLI keeps, so they need a local daemon".to_string());
+    }
+    let dir = profiles_dir().ok_or("Couldn't find the home directory for profiles")?;
+    match args.first().map(String::as_str) {
+        Some("use") => match args.get(1) {
+            Some(name) => switch(session, &dir, name),
+            None => Err(format!("profile use requires a profile name\n{}", USAGE)),
+        },
+        Some("off") => {
+            if let Some(current) = active(session) {
+                if is_daemon_running(session) {
+                    save_to(session, &dir, &current)?;
+                }
+            }
+            set_active(&dir, session, None)?;
+            Ok(json!({ "profile": null }))
+        }
+        Some("list") => Ok(json!({ "profiles": list(&dir), "active": active(session) })),
+        Some("current") | None => Ok(json!({ "profile": active(session) })),
+        Some(other) => Err(format!("Unknown subcommand: {}\n{}", other, USAGE)),
+    }
+}
+
+pub fn run_profile(args: &[String], flags: &Flags) {
+    let data = match run_subcommand(args, &flags.session) {
+        Ok(data) => data,
+        Err(e) => {
+            if flags.json {
+                format::print(&json!({ "success": false, "error": e }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), e);
+            }
+            exit(1);
+        }
+    };
+    if flags.json {
+        format::print(&json!({ "success": true, "data": data }));
+        return;
+    }
+    let profile = data.get("profile").and_then(|v| v.as_str());
+    match args.first().map(String::as_str) {
+        Some("use") => {
+            let loaded = match data.get("cookieCount").and_then(|v| v.as_u64()) {
+                Some(n) => format!("{} cookie{} loaded", n, if n == 1 { "" } else { "s" }),
+                None if data["pending"] == true => "loaded when the browser starts".to_string(),
+                None => "new profile".to_string(),
+            };
+            println!(
+                "{} Using profile {} {}",
+                color::success_indicator(),
+                color::bold(profile.unwrap_or("")),
+                color::dim(&format!("({})", loaded))
+            );
+        }
+        Some("off") => println!("{} No profile in use", color::success_indicator()),
+        Some("list") => {
+            let names = data["profiles"].as_array().cloned().unwrap_or_default();
+            if names.is_empty() {
+                println!("No saved profiles");
+            }
+            let active = data.get("active").and_then(|v| v.as_str());
+            for name in names.iter().filter_map(|v| v.as_str()) {
+                let marker = if Some(name) == active {
+                    color::cyan("→")
+                } else {
+                    " ".to_string()
+                };
+                println!("{} {}", marker, name);
+            }
+        }
+        _ => match profile {
+            Some(name) => println!("{}", name),
+            None => println!("No profile in use"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("alice_2-admin").is_ok());
+        for bad in ["", "../x", "a/b", "a.b", "team lead"] {
+            assert!(validate_name(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_list_and_active_files() {
+        let dir = env::temp_dir().join(format!("ab-profiles-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for file in ["work.json", "alice.json", "notes.txt", "bad name.json"] {
+            fs::write(dir.join(file), "{}").unwrap();
+        }
+        assert_eq!(list(&dir), vec!["alice", "work"]);
+
+        set_active(&dir, "s1", Some("work")).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(ACTIVE_DIR).join("s1")).unwrap(),
+            "work"
+        );
+        set_active(&dir, "s1", None).unwrap();
+        assert!(!dir.join(ACTIVE_DIR).join("s1").exists());
+        // Turning off a profile that isn't on is fine
+        set_active(&dir, "s1", None).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
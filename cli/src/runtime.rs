@@ -0,0 +1,159 @@
+//! The runtime daemons are spawned with. The daemon is written against Bun's
+//! APIs (`Bun.listen`, `Bun.file`), so Bun is the only runtime that can run it.
+
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Oldest Bun the daemon is tested with
+const MIN_BUN_VERSION: (u32, u32, u32) = (1, 2, 0);
+
+#[cfg(windows)]
+const BUN_EXECUTABLE: &str = "bun.exe";
+#[cfg(not(windows))]
+const BUN_EXECUTABLE: &str = "bun";
+
+const INSTALL_HINT: &str =
+    "Install Bun from https://bun.sh, or point --runtime (AGENT_BROWSER_RUNTIME) at its executable.";
+
+/// The Bun executable to spawn the daemon with: `--runtime` / AGENT_BROWSER_RUNTIME
+/// when it names one, otherwise `bun` from PATH or the installer's default
+/// location, checked against the minimum version.
+pub fn resolve() -> Result<PathBuf, String> {
+    resolve_version().map(|(bun, _)| bun)
+}
+
+/// [`resolve`], also returning the version Bun reported
+pub fn resolve_version() -> Result<(PathBuf, String), String> {
+    let choice = env::var("AGENT_BROWSER_RUNTIME")
+        .ok()
+        .filter(|v| !v.is_empty());
+    let bun = find(choice.as_deref())?;
+    let version = check_version(&bun)?;
+    Ok((bun, version))
+}
+
+fn find(choice: Option<&str>) -> Result<PathBuf, String> {
+    match choice {
+        None | Some("bun") => {
+            let path = env::var_os("PATH").unwrap_or_default();
+            find_in(&path, BUN_EXECUTABLE)
+                .or_else(installed_bun)
+                .ok_or_else(|| {
+                    format!("Bun not found, and the daemon runs on it. {}", INSTALL_HINT)
+                })
+        }
+        Some(name) if is_node(name) => Err(format!(
+            "The daemon can't run on Node.js: it uses Bun's APIs. {}",
+            INSTALL_HINT
+        )),
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if path.is_file() {
+                Ok(path)
+            } else {
+                Err(format!("Runtime not found: {}", path.display()))
+            }
+        }
+    }
+}
+
+fn is_node(name: &str) -> bool {
+    Path::new(name)
+        .file_stem()
+        .is_some_and(|stem| stem == "node" || stem == "nodejs")
+}
+
+/// The first `name` in a PATH-style list of directories
+fn find_in(paths: &OsStr, name: &str) -> Option<PathBuf> {
+    env::split_paths(paths)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Where Bun's install script puts it (`$BUN_INSTALL/bin`, by default
+/// `~/.bun/bin`), which shells only add to PATH once their profile is reloaded
+fn installed_bun() -> Option<PathBuf> {
+    let root = env::var_os("BUN_INSTALL")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".bun")))?;
+    let bun = root.join("bin").join(BUN_EXECUTABLE);
+    bun.is_file().then_some(bun)
+}
+
+fn check_version(bun: &Path) -> Result<String, String> {
+    let output = Command::new(bun)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", bun.display(), e))?;
+    let reported = String::from_utf8_lossy(&output.stdout);
+    let reported = reported.trim();
+    match parse_version(reported) {
+        Some(version) if version >= MIN_BUN_VERSION => Ok(reported.to_string()),
+        Some(_) => {
+            let (major, minor, patch) = MIN_BUN_VERSION;
+            Err(format!(
+                "Bun {} is too old for the daemon (needs {}.{}.{} or later). Run 'bun upgrade'.",
+                reported, major, minor, patch
+            ))
+        }
+        None => Err(format!(
+            "{} doesn't look like Bun (--version printed '{}'). {}",
+            bun.display(),
+            reported,
+            INSTALL_HINT
+        )),
+    }
+}
+
+/// `1.2.3` (or `1.2.3-canary.1+abc`) as numbers
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("1.3.0-canary.12+a1b2c3"), Some((1, 3, 0)));
+        assert_eq!(parse_version("v22.11.0"), None);
+        assert_eq!(parse_version("1.2"), None);
+        assert!(parse_version("1.1.45").unwrap() < MIN_BUN_VERSION);
+    }
+
+    #[test]
+    fn test_find_rejects_node() {
+        let err = find(Some("node")).unwrap_err();
+        assert!(err.contains("can't run on Node.js"));
+        assert!(find(Some("/usr/local/bin/node")).is_err());
+        assert!(find(Some("/no/such/bun"))
+            .unwrap_err()
+            .starts_with("Runtime not found"));
+    }
+
+    #[test]
+    fn test_find_in_path() {
+        let dir = env::temp_dir().join(format!("agent-browser-runtime-{}", std::process::id()));
+        let (empty, bin) = (dir.join("empty"), dir.join("bin"));
+        fs::create_dir_all(&empty).unwrap();
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join(BUN_EXECUTABLE), b"").unwrap();
+
+        let paths = env::join_paths([&empty, &bin]).unwrap();
+        assert_eq!(
+            find_in(&paths, BUN_EXECUTABLE),
+            Some(bin.join(BUN_EXECUTABLE))
+        );
+        let paths = env::join_paths([&empty]).unwrap();
+        assert_eq!(find_in(&paths, BUN_EXECUTABLE), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
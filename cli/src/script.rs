@@ -0,0 +1,1081 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+
+use crate::color;
+use crate::commands::{
+    gen_id, parse_command, parse_duration, secret_value, ParseError, SET_OPTIONS,
+};
+use crate::connection::{DaemonConnection, Error, Response};
+use crate::exit_code;
+use crate::filter::Filter;
+use crate::flags::{clean_args, parse_flags, Flags};
+use crate::format;
+use crate::interrupt;
+use crate::output::print_response;
+use crate::template;
+
+/// A single line from a script, parsed before anything runs
+pub struct ScriptLine {
+    /// 1-based line number in the source file
+    pub line: usize,
+    /// The original command text, for reporting
+    pub text: String,
+    pub step: Step,
+}
+
+/// What a line does: send a daemon command, or one of the constructs the CLI
+/// runs itself around commands
+pub enum Step {
+    Command(Value),
+    /// A command using a variable that is only known once the script runs,
+    /// parsed from these tokens when it gets there
+    Deferred(Vec<String>),
+    /// `retry <n> <command>`: run `step` until it succeeds, at most `attempts` times
+    Retry {
+        attempts: u32,
+        step: Box<Step>,
+    },
+    /// `if-visible <selector> then <command>`: run `step` only when `selector`
+    /// is visible right now, otherwise skip it
+    IfVisible {
+        selector: String,
+        step: Box<Step>,
+    },
+    /// `sleep <duration>`
+    Sleep(Duration),
+    /// `set <name> <value>`; `value` may use other variables
+    Set {
+        name: String,
+        value: String,
+    },
+    /// `capture <name> [<filter>] <command>`: keep a value from the answer to `step`
+    Capture {
+        name: String,
+        filter: Option<Filter>,
+        step: Box<Step>,
+    },
+}
+
+impl Step {
+    /// The command this step sends, if it is known before the script runs
+    pub fn request(&self) -> Option<&Value> {
+        match self {
+            Step::Command(cmd) => Some(cmd),
+            Step::Retry { step, .. }
+            | Step::IfVisible { step, .. }
+            | Step::Capture { step, .. } => step.request(),
+            _ => None,
+        }
+    }
+
+    /// The variables running this step sets
+    fn assigns(&self) -> Vec<String> {
+        match self {
+            Step::Set { name, .. } => vec![name.clone()],
+            Step::Capture { name, step, .. } => {
+                let mut names = step.assigns();
+                names.push(name.clone());
+                names
+            }
+            Step::Retry { step, .. } | Step::IfVisible { step, .. } => step.assigns(),
+            Step::Command(_) | Step::Deferred(_) | Step::Sleep(_) => Vec::new(),
+        }
+    }
+}
+
+/// The variables as parsing sees them, line by line. `None` marks one whose
+/// value is only known once the script runs: a `capture`, a `set` that uses
+/// one, or anything set inside `if-visible`.
+#[derive(Default)]
+pub struct Variables(HashMap<String, Option<String>>);
+
+impl Variables {
+    /// `text` with its variables replaced, or `None` when it uses one that
+    /// isn't known yet
+    fn expand(&self, text: &str) -> Result<Option<String>, String> {
+        let mut pending = false;
+        let expanded = template::expand(text, |name| match self.0.get(name) {
+            Some(Some(value)) => Ok(value.clone()),
+            Some(None) => {
+                pending = true;
+                Ok(String::new())
+            }
+            None => env_var(name),
+        })?;
+        Ok((!pending).then_some(expanded))
+    }
+
+    /// `text` with its variables replaced, for values needed before the
+    /// script runs
+    fn expand_now(&self, text: &str) -> Result<String, String> {
+        self.expand(text)?.ok_or_else(|| {
+            format!(
+                "{} uses a variable that is only set while the script runs",
+                text
+            )
+        })
+    }
+}
+
+fn env_var(name: &str) -> Result<String, String> {
+    env::var(name).map_err(|_| format!("${{{}}} is not set", name))
+}
+
+/// A parsed script ready to execute against a session
+pub struct Script {
+    pub lines: Vec<ScriptLine>,
+    pub continue_on_error: bool,
+    /// Given on the command line with `++` rather than read from a file
+    pub chained: bool,
+}
+
+/// Separates chained commands: `open example.com ++ snapshot -i`
+pub const CHAIN_SEPARATOR: &str = "++";
+
+/// Load and parse a script from `run` arguments (`<file>` or `-` for stdin).
+/// Every line is parsed before anything is sent, so a typo near the end of a
+/// script fails without running the first half.
+pub fn load(args: &[String], flags: &Flags) -> Result<Script, String> {
+    let mut path: Option<&str> = None;
+    let mut continue_on_error = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--continue-on-error" | "-k" => continue_on_error = true,
+            a if path.is_none() => path = Some(a),
+            a => return Err(format!("Unexpected argument: {}", a)),
+        }
+    }
+
+    let path = path.ok_or(
+        "Missing arguments for: run\nUsage: agent-browser run <file|-> [--continue-on-error]",
+    )?;
+
+    let source = if path == "-" {
+        let mut buf = String::new();
+        io::stdin()
+            .read_to_string(&mut buf)
+            .map_err(|e| format!("Failed to read script from stdin: {}", e))?;
+        buf
+    } else {
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?
+    };
+
+    Ok(Script {
+        lines: parse_script(&source, flags)?,
+        continue_on_error,
+        chained: false,
+    })
+}
+
+/// Parse arguments holding several commands separated by `++` into a script
+/// that stops at the first failure, or `None` when there is only one command.
+/// Each command's `line` is its position in the chain.
+pub fn chain(args: &[String], flags: &Flags) -> Result<Option<Script>, String> {
+    if !args.iter().any(|a| a == CHAIN_SEPARATOR) {
+        return Ok(None);
+    }
+
+    let mut lines = Vec::new();
+    let mut vars = Variables::default();
+    for (idx, tokens) in args.split(|a| a == CHAIN_SEPARATOR).enumerate() {
+        let line = idx + 1;
+        match tokens.first().map(|s| s.as_str()) {
+            None => {
+                return Err(format!(
+                    "Command {}: expected a command between {} separators",
+                    line, CHAIN_SEPARATOR
+                ))
+            }
+            Some("run") => return Err(format!("Command {}: run cannot be chained", line)),
+            Some(_) => {}
+        }
+
+        let step =
+            parse_step(tokens, flags, &mut vars).map_err(|e| format!("Command {}: {}", line, e))?;
+
+        lines.push(ScriptLine {
+            line,
+            text: tokens.join(" "),
+            step,
+        });
+    }
+
+    Ok(Some(Script {
+        lines,
+        continue_on_error: false,
+        chained: true,
+    }))
+}
+
+/// Parse script source into commands. Blank lines and lines starting with `#`
+/// are skipped. Each line is tokenized like a shell command line.
+pub fn parse_script(source: &str, flags: &Flags) -> Result<Vec<ScriptLine>, String> {
+    let mut lines = Vec::new();
+    let mut vars = Variables::default();
+
+    for (idx, raw) in source.lines().enumerate() {
+        let line = idx + 1;
+        let text = raw.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+
+        let tokens = tokenize(text).map_err(|e| format!("Line {}: {}", line, e))?;
+        if tokens.first().map(|s| s.as_str()) == Some("run") {
+            return Err(format!("Line {}: run cannot be nested in a script", line));
+        }
+
+        let step =
+            parse_step(&tokens, flags, &mut vars).map_err(|e| format!("Line {}: {}", line, e))?;
+
+        lines.push(ScriptLine {
+            line,
+            text: text.to_string(),
+            step,
+        });
+    }
+
+    Ok(lines)
+}
+
+/// Parse one line: `retry`, `if-visible`, `sleep`, `set` and `capture`, or a
+/// daemon command. The command a construct wraps may be another construct.
+/// `${NAME}` is replaced using `vars`, which learns what the line sets.
+pub fn parse_step(tokens: &[String], flags: &Flags, vars: &mut Variables) -> Result<Step, String> {
+    let missing = |context: &str, usage| {
+        ParseError::MissingArguments {
+            context: context.to_string(),
+            usage,
+        }
+        .format()
+    };
+    let invalid = |message: String, usage| ParseError::InvalidValue { message, usage }.format();
+
+    match tokens.first().map(|s| s.as_str()) {
+        Some("retry") => {
+            const USAGE: &str = "retry <n> <command>";
+            let (Some(n), Some(rest)) = (tokens.get(1), tokens.get(2..)) else {
+                return Err(missing("retry", USAGE));
+            };
+            if rest.is_empty() {
+                return Err(missing("retry", USAGE));
+            }
+            let n = vars.expand_now(n)?;
+            let attempts = n
+                .parse::<u32>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| invalid(format!("Invalid number of attempts: {}", n), USAGE))?;
+            Ok(Step::Retry {
+                attempts,
+                step: Box::new(parse_step(rest, flags, vars)?),
+            })
+        }
+        Some("if-visible") => {
+            const USAGE: &str = "if-visible <selector> then <command>";
+            match (
+                tokens.get(1),
+                tokens.get(2).map(|s| s.as_str()),
+                tokens.get(3..),
+            ) {
+                (Some(selector), Some("then"), Some(rest)) if !rest.is_empty() => {
+                    vars.expand(selector)?;
+                    let step = parse_step(rest, flags, vars)?;
+                    // Whether it sets anything is only known once it runs
+                    for name in step.assigns() {
+                        vars.0.insert(name, None);
+                    }
+                    Ok(Step::IfVisible {
+                        selector: selector.clone(),
+                        step: Box::new(step),
+                    })
+                }
+                (Some(_), Some(word), _) if word != "then" => Err(invalid(
+                    format!("Expected then after the selector, got: {}", word),
+                    USAGE,
+                )),
+                _ => Err(missing("if-visible", USAGE)),
+            }
+        }
+        Some("sleep") => {
+            const USAGE: &str = "sleep <duration>";
+            match tokens.get(1..) {
+                Some([duration]) => {
+                    let duration = vars.expand_now(duration)?;
+                    parse_duration(&duration)
+                        .map(|ms| Step::Sleep(Duration::from_millis(ms)))
+                        .ok_or_else(|| invalid(format!("Invalid duration: {}", duration), USAGE))
+                }
+                Some([]) | None => Err(missing("sleep", USAGE)),
+                Some(_) => Err(invalid("sleep takes one duration".to_string(), USAGE)),
+            }
+        }
+        // `set viewport` and the other settings are still browser commands
+        Some("set") if tokens.get(1).is_some_and(|name| is_variable(name)) => {
+            let name = tokens[1].clone();
+            let value = match tokens.get(2..) {
+                Some(rest) if !rest.is_empty() => rest.join(" "),
+                _ => return Err(missing("set", "set <name> <value>")),
+            };
+            let known = vars.expand(&value)?;
+            vars.0.insert(name.clone(), known);
+            Ok(Step::Set { name, value })
+        }
+        Some("capture") => {
+            const USAGE: &str = "capture <name> [<filter>] <command>";
+            let Some(name) = tokens.get(1) else {
+                return Err(missing("capture", USAGE));
+            };
+            if !template::is_name(name) {
+                return Err(invalid(format!("Invalid variable name: {}", name), USAGE));
+            }
+            let (filter, rest) = match tokens.get(2) {
+                Some(path) if path.starts_with('.') => (Some(Filter::parse(path)?), &tokens[3..]),
+                _ => (None, &tokens[2..]),
+            };
+            if rest.is_empty() {
+                return Err(missing("capture", USAGE));
+            }
+            let step = parse_step(rest, flags, vars)?;
+            vars.0.insert(name.clone(), None);
+            Ok(Step::Capture {
+                name: name.clone(),
+                filter,
+                step: Box::new(step),
+            })
+        }
+        _ => {
+            let mut expanded = Vec::new();
+            let mut pending = false;
+            for token in tokens {
+                match vars.expand(token)? {
+                    Some(token) => expanded.push(token),
+                    None => pending = true,
+                }
+            }
+            if pending {
+                return Ok(Step::Deferred(tokens.to_vec()));
+            }
+            parse_tokens(&expanded, flags).map(Step::Command)
+        }
+    }
+}
+
+/// Whether `set <name>` defines a variable rather than changing a setting
+fn is_variable(name: &str) -> bool {
+    template::is_name(name) && !SET_OPTIONS.contains(&name)
+}
+
+/// Parse one command's arguments into a daemon request. Flags like --full may
+/// appear among the arguments; session and launch options always come from
+/// the outer invocation.
+pub fn parse_tokens(tokens: &[String], flags: &Flags) -> Result<Value, String> {
+    let mut line_flags = parse_flags(tokens);
+    line_flags.full |= flags.full;
+    line_flags.provider = flags.provider.clone();
+    line_flags.device = flags.device.clone();
+    if line_flags.headers.is_none() {
+        line_flags.headers = flags.headers.clone();
+    }
+
+    parse_command(&clean_args(tokens), &line_flags).map_err(|e| e.format())
+}
+
+/// Split a line into arguments. Supports single quotes (literal), double
+/// quotes (with `\"` and `\\` escapes) and backslash escapes outside quotes.
+pub fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => current.push(ch),
+                        None => return Err("Unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(ch @ ('"' | '\\')) => current.push(ch),
+                            Some(ch) => {
+                                current.push('\\');
+                                current.push(ch);
+                            }
+                            None => return Err("Unterminated double quote".to_string()),
+                        },
+                        Some(ch) => current.push(ch),
+                        None => return Err("Unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_token = true;
+                if let Some(ch) = chars.next() {
+                    current.push(ch);
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            c => {
+                in_token = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_token {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
+
+/// Execute a script over a single daemon connection. Returns true if every
+/// executed command succeeded.
+pub fn execute(script: &Script, flags: &Flags) -> bool {
+    let json_mode = flags.json;
+    let total = script.lines.len();
+    let mut results: Vec<Value> = Vec::new();
+    let mut executed = 0;
+    let mut failed = 0;
+    let mut stopped_at: Option<usize> = None;
+
+    let mut runner = match DaemonConnection::open(&flags.session) {
+        Ok(c) => Runner {
+            conn: Some(c),
+            flags,
+            vars: HashMap::new(),
+        },
+        Err(e) => {
+            report_fatal(&e.to_string(), json_mode);
+            return false;
+        }
+    };
+
+    for line in &script.lines {
+        if !json_mode {
+            println!("{}", color::dim(&format!("[{}] {}", line.line, line.text)));
+        }
+
+        let outcome = runner.run(&line.step);
+        let mut entry = json!({
+            "line": line.line,
+            "command": line.text,
+            "success": outcome.success(),
+            "data": null,
+            "error": null,
+        });
+        match &outcome.result {
+            Some(Ok(resp)) => {
+                if !json_mode {
+                    print_response(resp, false, outcome.action());
+                }
+                entry["data"] = json!(resp.data);
+                entry["error"] = json!(resp.error);
+                entry["errorCode"] = json!(resp.error_code);
+                entry["errorContext"] = json!(resp.error_context);
+            }
+            Some(Err(e)) => {
+                if !json_mode {
+                    eprintln!("{} {}", color::error_indicator(), e);
+                }
+                entry["error"] = json!(e.to_string());
+                entry["errorCode"] = json!(exit_code::classify_error(outcome.action(), e));
+            }
+            None => {}
+        }
+        if let Some(attempts) = outcome.attempts {
+            entry["attempts"] = json!(attempts);
+        }
+        if outcome.skipped {
+            entry["skipped"] = json!(true);
+        }
+        if let Some((ref name, ref value)) = outcome.captured {
+            entry["captured"] = json!({ name: value });
+        }
+
+        // jsonl prints each result as it comes in and leaves it out of the summary
+        if format::is_streaming() {
+            format::print(&entry);
+        } else {
+            results.push(entry);
+        }
+        executed += 1;
+
+        if !outcome.success() {
+            failed += 1;
+            if !script.continue_on_error {
+                stopped_at = Some(line.line);
+                break;
+            }
+        }
+    }
+
+    let error = match stopped_at {
+        Some(line) if script.chained => Some(format!("Chain stopped at command {}", line)),
+        Some(line) => Some(format!("Script stopped at line {}", line)),
+        None if failed > 0 => Some(format!("{} of {} commands failed", failed, total)),
+        None => None,
+    };
+
+    if json_mode {
+        let mut out = json!({
+            "success": error.is_none(),
+            "data": {
+                "total": total,
+                "executed": executed,
+                "failed": failed,
+            },
+        });
+        if !format::is_streaming() {
+            out["data"]["results"] = json!(results);
+        }
+        if let Some(ref e) = error {
+            out["error"] = json!(e);
+        }
+        format::print(&out);
+    } else if let Some(ref e) = error {
+        eprintln!("{} {}", color::error_indicator(), e);
+    }
+
+    error.is_none()
+}
+
+/// What running a step came to
+struct Outcome {
+    /// The command that answered last
+    command: Option<Value>,
+    /// Its answer; `None` when nothing was sent (`sleep`, `set`, a skipped `if-visible`)
+    result: Option<Result<Response, Error>>,
+    /// How many times a `retry` sent its command
+    attempts: Option<u32>,
+    skipped: bool,
+    /// The variable a `capture` set, and its value
+    captured: Option<(String, String)>,
+}
+
+impl Outcome {
+    fn sent(command: Value, result: Result<Response, Error>) -> Outcome {
+        Outcome {
+            command: Some(command),
+            result: Some(result),
+            attempts: None,
+            skipped: false,
+            captured: None,
+        }
+    }
+
+    fn nothing_sent(skipped: bool) -> Outcome {
+        Outcome {
+            command: None,
+            result: None,
+            attempts: None,
+            skipped,
+            captured: None,
+        }
+    }
+
+    /// A step that failed before sending anything, e.g. on a variable that was never set
+    fn failed(message: String) -> Outcome {
+        Outcome {
+            result: Some(Err(Error::Command(message))),
+            ..Outcome::nothing_sent(false)
+        }
+    }
+
+    fn success(&self) -> bool {
+        match &self.result {
+            Some(Ok(resp)) => resp.success,
+            Some(Err(_)) => false,
+            None => true,
+        }
+    }
+
+    fn action(&self) -> Option<&str> {
+        self.command
+            .as_ref()
+            .and_then(|c| c.get("action"))
+            .and_then(|v| v.as_str())
+    }
+
+    fn error(&self) -> String {
+        match &self.result {
+            Some(Ok(resp)) => resp.error.clone().unwrap_or_default(),
+            Some(Err(e)) => e.to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+/// Runs steps over one connection, keeping the variables they set
+struct Runner<'a> {
+    conn: Option<DaemonConnection>,
+    flags: &'a Flags,
+    vars: HashMap<String, String>,
+}
+
+impl Runner<'_> {
+    fn run(&mut self, step: &Step) -> Outcome {
+        let json_mode = self.flags.json;
+        match step {
+            Step::Command(command) => Outcome::sent(command.clone(), self.send(command)),
+            Step::Deferred(tokens) => {
+                let expanded: Result<Vec<String>, String> =
+                    tokens.iter().map(|token| self.expand(token)).collect();
+                match expanded.and_then(|tokens| parse_tokens(&tokens, self.flags)) {
+                    Ok(command) => {
+                        let result = self.send(&command);
+                        Outcome::sent(command, result)
+                    }
+                    Err(e) => Outcome::failed(e),
+                }
+            }
+            Step::Retry { attempts, step } => {
+                let mut attempt = 1;
+                loop {
+                    let mut outcome = self.run(step);
+                    if outcome.success() || attempt == *attempts {
+                        outcome.attempts = Some(attempt);
+                        return outcome;
+                    }
+                    if !json_mode {
+                        eprintln!(
+                            "{}",
+                            color::dim(&format!(
+                                "Attempt {} of {} failed: {}",
+                                attempt,
+                                attempts,
+                                outcome.error()
+                            ))
+                        );
+                    }
+                    attempt += 1;
+                }
+            }
+            Step::IfVisible { selector, step } => {
+                let selector = match self.expand(selector) {
+                    Ok(selector) => selector,
+                    Err(e) => return Outcome::failed(e),
+                };
+                let check = json!({ "id": gen_id(), "action": "isvisible", "selector": selector });
+                match self.send(&check) {
+                    Ok(resp) if resp.success => {
+                        let visible = resp
+                            .data
+                            .as_ref()
+                            .and_then(|d| d.get("visible"))
+                            .and_then(|v| v.as_bool())
+                            == Some(true);
+                        if visible {
+                            return self.run(step);
+                        }
+                        if !json_mode {
+                            println!(
+                                "{}",
+                                color::dim(&format!("Skipped: {} is not visible", selector))
+                            );
+                        }
+                        Outcome::nothing_sent(true)
+                    }
+                    // The check itself failing, e.g. on an invalid selector, fails the line
+                    result => Outcome::sent(check, result),
+                }
+            }
+            Step::Sleep(duration) => {
+                thread::sleep(*duration);
+                Outcome::nothing_sent(false)
+            }
+            Step::Set { name, value } => match self.expand(value) {
+                Ok(value) => {
+                    self.vars.insert(name.clone(), value);
+                    Outcome::nothing_sent(false)
+                }
+                Err(e) => Outcome::failed(e),
+            },
+            Step::Capture { name, filter, step } => {
+                let mut outcome = self.run(step);
+                let Some(Ok(ref resp)) = outcome.result else {
+                    return outcome;
+                };
+                if !resp.success {
+                    return outcome;
+                }
+                match captured_value(resp, filter.as_ref()) {
+                    Some(value) => {
+                        self.vars.insert(name.clone(), value.clone());
+                        outcome.captured = Some((name.clone(), value));
+                        outcome
+                    }
+                    None => Outcome {
+                        result: Some(Err(Error::Command(format!(
+                            "Nothing to capture into {}",
+                            name
+                        )))),
+                        ..outcome
+                    },
+                }
+            }
+        }
+    }
+
+    /// `text` with `${NAME}` replaced by the variable, or the environment variable
+    fn expand(&self, text: &str) -> Result<String, String> {
+        template::expand(text, |name| match self.vars.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => env_var(name),
+        })
+    }
+
+    /// Send one command, reconnecting if the previous one dropped the connection
+    fn send(&mut self, command: &Value) -> Result<Response, Error> {
+        let session = &self.flags.session;
+        interrupt::set_in_flight(session, command);
+        let resp = match self.conn.as_mut() {
+            Some(c) => c.send(command),
+            None => DaemonConnection::open(session).and_then(|mut c| {
+                let r = c.send(command);
+                self.conn = Some(c);
+                r
+            }),
+        };
+        interrupt::clear();
+
+        match resp {
+            Ok(mut resp) => {
+                if let Some(secret) = secret_value(command) {
+                    resp.redact(secret);
+                }
+                if !resp.success {
+                    let action = command.get("action").and_then(|v| v.as_str());
+                    resp.error_code = exit_code::classify(action, &resp);
+                }
+                Ok(resp)
+            }
+            Err(e) => {
+                self.conn = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// What `capture` keeps from an answer: the value `filter` selects from it,
+/// or else its data, unwrapped when it has a single field (the URL from
+/// `get url`). Strings are kept as they are, anything else as JSON.
+fn captured_value(resp: &Response, filter: Option<&Filter>) -> Option<String> {
+    let value = match filter {
+        Some(filter) => filter.select(&serde_json::to_value(resp).ok()?)?.clone(),
+        None => match resp.data.as_ref()? {
+            Value::Object(map) if map.len() == 1 => map.values().next()?.clone(),
+            data => data.clone(),
+        },
+    };
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        other => Some(other.to_string()),
+    }
+}
+
+fn report_fatal(msg: &str, json_mode: bool) {
+    if json_mode {
+        format::print(&json!({ "success": false, "error": msg }));
+    } else {
+        eprintln!("{} {}", color::error_indicator(), msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags() -> Flags {
+        parse_flags(&[])
+    }
+
+    fn command(step: &Step) -> &Value {
+        match step {
+            Step::Command(command) => command,
+            _ => panic!("not a daemon command"),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_simple() {
+        assert_eq!(tokenize("click @e1").unwrap(), vec!["click", "@e1"]);
+    }
+
+    #[test]
+    fn test_tokenize_quotes() {
+        assert_eq!(
+            tokenize(r##"fill "#email" "a b""##).unwrap(),
+            vec!["fill", "#email", "a b"]
+        );
+        assert_eq!(
+            tokenize("type 'it''s' x").unwrap(),
+            vec!["type", "its", "x"]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_escapes() {
+        assert_eq!(
+            tokenize(r#"fill #q "say \"hi\"""#).unwrap(),
+            vec!["fill", "#q", r#"say "hi""#]
+        );
+        assert_eq!(tokenize(r"type a\ b").unwrap(), vec!["type", "a b"]);
+    }
+
+    #[test]
+    fn test_tokenize_empty_quoted_arg() {
+        assert_eq!(tokenize(r#"fill #q """#).unwrap(), vec!["fill", "#q", ""]);
+    }
+
+    #[test]
+    fn test_tokenize_unterminated() {
+        assert!(tokenize(r#"fill #q "abc"#).is_err());
+        assert!(tokenize("fill #q 'abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_script_skips_comments_and_blanks() {
+        let source = "# login flow\n\nopen example.com\n  # indented comment\nclick @e1\n";
+        let lines = parse_script(source, &flags()).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line, 3);
+        assert_eq!(command(&lines[0].step)["action"], "navigate");
+        assert_eq!(lines[1].line, 5);
+        assert_eq!(command(&lines[1].step)["action"], "click");
+    }
+
+    #[test]
+    fn test_parse_script_reports_line_number() {
+        let err = parse_script("open example.com\nbogus\n", &flags())
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Line 2:"));
+    }
+
+    #[test]
+    fn test_parse_script_line_flags() {
+        let lines = parse_script("screenshot --full\n", &flags()).unwrap();
+        assert_eq!(command(&lines[0].step)["fullPage"], true);
+    }
+
+    #[test]
+    fn test_parse_script_rejects_nested_run() {
+        assert!(parse_script("run other.ab\n", &flags()).is_err());
+    }
+
+    #[test]
+    fn test_parse_script_constructs() {
+        let source = "retry 3 click #submit\nif-visible #cookies then click #accept\nsleep 2s\n";
+        let lines = parse_script(source, &flags()).unwrap();
+        match &lines[0].step {
+            Step::Retry { attempts, step } => {
+                assert_eq!(*attempts, 3);
+                assert_eq!(command(step)["selector"], "#submit");
+            }
+            _ => panic!("expected retry"),
+        }
+        match &lines[1].step {
+            Step::IfVisible { selector, step } => {
+                assert_eq!(selector, "#cookies");
+                assert_eq!(command(step)["selector"], "#accept");
+            }
+            _ => panic!("expected if-visible"),
+        }
+        assert!(matches!(lines[2].step, Step::Sleep(d) if d == Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_parse_script_nested_constructs() {
+        let lines =
+            parse_script("if-visible .banner then retry 2 click .close\n", &flags()).unwrap();
+        let Step::IfVisible { step, .. } = &lines[0].step else {
+            panic!("expected if-visible");
+        };
+        assert!(matches!(**step, Step::Retry { attempts: 2, .. }));
+    }
+
+    #[test]
+    fn test_parse_script_construct_errors() {
+        let err = |source: &str| parse_script(source, &flags()).err().unwrap();
+        assert!(err("retry click #a\n").contains("Invalid number of attempts: click"));
+        assert!(err("retry 0 click #a\n").contains("Invalid number of attempts: 0"));
+        assert!(err("retry 3\n").contains("Missing arguments for: retry"));
+        assert!(err("if-visible #a click #b\n").contains("Expected then after the selector"));
+        assert!(err("if-visible #a then\n").contains("Missing arguments for: if-visible"));
+        assert!(err("sleep\n").contains("Missing arguments for: sleep"));
+        assert!(err("sleep soon\n").contains("Invalid duration: soon"));
+        assert!(err("retry 2 bogus\n").starts_with("Line 1: Unknown command: bogus"));
+    }
+
+    #[test]
+    fn test_parse_script_variables() {
+        env::set_var("AB_SCRIPT_TEST_USER", "someone");
+        let source = "set BASE https://example.com\n\
+                      open ${BASE}/login\n\
+                      fill #user ${AB_SCRIPT_TEST_USER}\n\
+                      set viewport 1280 720\n\
+                      type #note 'costs $${PRICE}'\n";
+        let lines = parse_script(source, &flags()).unwrap();
+        assert!(
+            matches!(&lines[0].step, Step::Set { name, value } if name == "BASE" && value == "https://example.com")
+        );
+        assert_eq!(command(&lines[1].step)["url"], "https://example.com/login");
+        assert_eq!(command(&lines[2].step)["value"], "someone");
+        assert_eq!(command(&lines[3].step)["action"], "viewport");
+        assert_eq!(command(&lines[4].step)["text"], "costs ${PRICE}");
+    }
+
+    #[test]
+    fn test_parse_script_capture() {
+        let source = "capture URL get url\n\
+                      capture TITLE .data.title get title\n\
+                      set NEXT ${URL}/next\n\
+                      open ${NEXT}\n";
+        let lines = parse_script(source, &flags()).unwrap();
+        match &lines[0].step {
+            Step::Capture { name, filter, step } => {
+                assert_eq!(name, "URL");
+                assert!(filter.is_none());
+                assert_eq!(command(step)["action"], "url");
+            }
+            _ => panic!("expected capture"),
+        }
+        assert!(matches!(
+            &lines[1].step,
+            Step::Capture {
+                filter: Some(_),
+                ..
+            }
+        ));
+        // Only known once the capture runs
+        assert!(matches!(&lines[3].step, Step::Deferred(tokens) if tokens[1] == "${NEXT}"));
+    }
+
+    #[test]
+    fn test_step_request() {
+        let source = "retry 3 click #go\n\
+                      capture URL get url\n\
+                      sleep 1s\n\
+                      open ${URL}\n";
+        let lines = parse_script(source, &flags()).unwrap();
+        let actions: Vec<_> = lines
+            .iter()
+            .map(|l| l.step.request().map(|cmd| cmd["action"].clone()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![Some(json!("click")), Some(json!("url")), None, None]
+        );
+    }
+
+    #[test]
+    fn test_parse_script_variable_errors() {
+        let err = |source: &str| parse_script(source, &flags()).err().unwrap();
+        assert_eq!(
+            err("open ${AB_SCRIPT_TEST_UNSET}\n"),
+            "Line 1: ${AB_SCRIPT_TEST_UNSET} is not set"
+        );
+        assert!(err("capture URL get url\nsleep ${URL}\n")
+            .starts_with("Line 2: ${URL} uses a variable that is only set while the script runs"));
+        assert!(err("capture 1X get url\n").contains("Invalid variable name: 1X"));
+        assert!(err("capture URL\n").contains("Missing arguments for: capture"));
+        assert!(err("set NAME\n").contains("Missing arguments for: set"));
+        assert!(err("eval `${x}`\n").contains("is not set"));
+    }
+
+    #[test]
+    fn test_captured_value() {
+        let resp = |data: Value| Response {
+            success: true,
+            data: Some(data),
+            ..Default::default()
+        };
+        let url = resp(json!({ "url": "https://example.com/" }));
+        assert_eq!(
+            captured_value(&url, None).as_deref(),
+            Some("https://example.com/")
+        );
+        let count = resp(json!({ "count": 3, "selector": "li" }));
+        assert_eq!(
+            captured_value(&count, Filter::parse(".data.count").ok().as_ref()).as_deref(),
+            Some("3")
+        );
+        assert_eq!(
+            captured_value(&count, None).as_deref(),
+            Some(r#"{"count":3,"selector":"li"}"#)
+        );
+        assert_eq!(
+            captured_value(&count, Filter::parse(".data.x").ok().as_ref()),
+            None
+        );
+    }
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_chain() {
+        let script = chain(
+            &args("open example.com ++ wait --idle ++ snapshot -i"),
+            &flags(),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(script.chained);
+        assert!(!script.continue_on_error);
+        let actions: Vec<_> = script
+            .lines
+            .iter()
+            .map(|l| command(&l.step)["action"].clone())
+            .collect();
+        assert_eq!(actions, vec!["navigate", "waitforloadstate", "snapshot"]);
+        assert_eq!(script.lines[2].line, 3);
+        assert_eq!(script.lines[2].text, "snapshot -i");
+        assert_eq!(command(&script.lines[2].step)["interactive"], true);
+    }
+
+    #[test]
+    fn test_chain_single_command() {
+        assert!(chain(&args("open example.com"), &flags())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_chain_errors() {
+        let err = |s: &str| chain(&args(s), &flags()).err().unwrap();
+        assert!(err("open example.com ++").starts_with("Command 2:"));
+        assert!(err("++ snapshot").starts_with("Command 1:"));
+        assert!(err("open example.com ++ bogus").starts_with("Command 2:"));
+        assert_eq!(
+            err("open a.test ++ run b.ab"),
+            "Command 2: run cannot be chained"
+        );
+    }
+}
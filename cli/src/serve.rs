@@ -0,0 +1,575 @@
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use crate::color;
+use crate::commands::{gen_id, secret_value};
+use crate::connection::DaemonConnection;
+use crate::flags::Flags;
+use crate::format;
+use crate::script::{parse_tokens, tokenize};
+
+pub const DEFAULT_PORT: u16 = 8377;
+
+/// Largest request body accepted (uploads and mocks carry base64 payloads)
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// Longest request line or header line, and most header lines, accepted
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+const USAGE: &str = "serve [--port <n>] [--host <addr>] [--token <secret>]";
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub host: String,
+    pub port: u16,
+    pub token: Option<String>,
+}
+
+/// Parse the arguments after `serve`. The token can also come from
+/// AGENT_BROWSER_SERVE_TOKEN so it stays out of the process list.
+pub fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        host: "127.0.0.1".to_string(),
+        port: DEFAULT_PORT,
+        token: std::env::var("AGENT_BROWSER_SERVE_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty()),
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {}\nUsage: agent-browser {}", arg, USAGE))
+        };
+        match arg.as_str() {
+            "--port" => {
+                let port = value()?;
+                options.port = port
+                    .parse()
+                    .ok()
+                    .filter(|p| *p > 0)
+                    .ok_or_else(|| format!("Invalid port: {}", port))?;
+            }
+            "--host" => options.host = value()?,
+            "--token" => options.token = Some(value()?),
+            other => {
+                return Err(format!(
+                    "Unknown serve option: {}\nUsage: agent-browser {}",
+                    other, USAGE
+                ))
+            }
+        }
+    }
+    Ok(options)
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Read a line of at most MAX_LINE bytes, so a client can't make the
+/// server buffer without end
+fn read_line(reader: &mut impl BufRead, what: &str) -> Result<String, (u16, String)> {
+    let mut line = String::new();
+    Read::take(&mut *reader, MAX_LINE as u64 + 1)
+        .read_line(&mut line)
+        .map_err(|_| (400, format!("Could not read {}", what)))?;
+    if line.len() > MAX_LINE {
+        return Err((431, format!("{} longer than {} bytes", what, MAX_LINE)));
+    }
+    Ok(line)
+}
+
+/// Read one HTTP/1.1 request. Errors carry the status to answer with.
+fn read_request(reader: &mut impl BufRead) -> Result<Request, (u16, String)> {
+    let bad = |msg: &str| (400, msg.to_string());
+
+    let line = read_line(reader, "Request line")?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(m), Some(t)) => (m.to_string(), t),
+        _ => return Err(bad("Malformed request line")),
+    };
+    let path = target.split('?').next().unwrap_or("").to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader, "Header")?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err((431, format!("More than {} headers", MAX_HEADERS)));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| bad("Malformed header"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    if let Some(length) = request.header("Content-Length") {
+        let length: usize = length.parse().map_err(|_| bad("Invalid Content-Length"))?;
+        if length > MAX_BODY {
+            return Err((413, format!("Body larger than {} bytes", MAX_BODY)));
+        }
+        request.body.resize(length, 0);
+        reader
+            .read_exact(&mut request.body)
+            .map_err(|_| bad("Body shorter than Content-Length"))?;
+    } else if request.header("Transfer-Encoding").is_some() {
+        return Err((411, "Send the body with a Content-Length".to_string()));
+    }
+    Ok(request)
+}
+
+/// What to send back for a request
+enum Reply {
+    Json(u16, Value),
+    /// Forward a `subscribe` command and stream its events
+    Stream(Value),
+}
+
+fn error(status: u16, message: &str) -> Reply {
+    Reply::Json(status, json!({ "success": false, "error": message }))
+}
+
+/// Turn a POST /command body (or a `pipe` line) into a daemon request:
+/// either a protocol command with an `action`, or `{"command": "..."}` in
+/// CLI syntax.
+pub fn command_from_body(body: &[u8], flags: &Flags) -> Result<Value, String> {
+    let mut body: Value =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    if body.get("action").and_then(|v| v.as_str()).is_some() {
+        if body.get("id").is_none() {
+            body["id"] = json!(gen_id());
+        }
+        return Ok(body);
+    }
+    match body.get("command").and_then(|v| v.as_str()) {
+        Some(line) => parse_tokens(&tokenize(line)?, flags),
+        None => Err(
+            "Request needs an \"action\" (protocol command) or a \"command\" string".to_string(),
+        ),
+    }
+}
+
+/// Whether a request may have come from a web page rather than a local
+/// client. Browsers send `Origin` on cross-site requests and keep the
+/// attacker's host name in `Host` after DNS rebinding, so either would let
+/// any page the user visits drive the browser. Returns why it is refused.
+fn cross_site(request: &Request, options: &Options) -> Option<&'static str> {
+    if request.header("Origin").is_some() {
+        return Some("Requests from web pages are refused");
+    }
+    let host = request.header("Host")?;
+    let allowed = ["127.0.0.1", "localhost", "[::1]", options.host.as_str()]
+        .iter()
+        .any(|name| host.eq_ignore_ascii_case(&format!("{}:{}", name, options.port)));
+    // A token can't be learned by a rebound page, so with one any name will do
+    if !allowed && options.token.is_none() {
+        return Some("Host must be 127.0.0.1 or localhost with the port (or set --token)");
+    }
+    None
+}
+
+fn route(request: &Request, options: &Options, flags: &Flags) -> Reply {
+    if let Some(reason) = cross_site(request, options) {
+        return error(403, reason);
+    }
+    if let Some(ref token) = options.token {
+        let expected = format!("Bearer {}", token);
+        if request.header("Authorization") != Some(expected.as_str()) {
+            return error(401, "Missing or wrong bearer token");
+        }
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/health") => Reply::Json(
+            200,
+            json!({ "success": true, "data": { "session": flags.session } }),
+        ),
+        ("POST", "/command") => {
+            // Pages can only send JSON after a CORS preflight, which is never answered
+            let json = request
+                .header("Content-Type")
+                .and_then(|t| t.split(';').next())
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case("application/json"));
+            if !json {
+                return error(415, "Send the body as Content-Type: application/json");
+            }
+            let mut cmd = match command_from_body(&request.body, flags) {
+                Ok(cmd) => cmd,
+                Err(e) => return error(400, &e),
+            };
+            let follow = cmd
+                .as_object_mut()
+                .and_then(|o| o.remove("follow"))
+                .and_then(|v| v.as_bool())
+                == Some(true);
+            if cmd.get("action").and_then(|v| v.as_str()) == Some("subscribe") {
+                return Reply::Stream(cmd);
+            }
+            if follow || cmd.get("diff").is_some() {
+                return error(400, "--follow and --diff are only available for events");
+            }
+            match DaemonConnection::open(&flags.session).and_then(|mut c| c.send(&cmd)) {
+                Ok(mut resp) => {
+                    if let Some(secret) = secret_value(&cmd) {
+                        resp.redact(secret);
+                    }
+                    Reply::Json(200, serde_json::to_value(&resp).unwrap_or_default())
+                }
+                Err(e) => error(502, &e.to_string()),
+            }
+        }
+        (_, "/health") | (_, "/command") => error(405, "Method not allowed"),
+        _ => error(404, "Not found"),
+    }
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        _ => "Bad Gateway",
+    }
+}
+
+fn write_json(stream: &mut TcpStream, status: u16, body: &Value) -> std::io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        status_text(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Send `subscribe` and relay the response and every event as chunked
+/// newline-delimited JSON until the client or the daemon hangs up.
+fn stream_events(stream: &mut TcpStream, cmd: &Value, session: &str) -> std::io::Result<()> {
+    let mut conn = match DaemonConnection::open(session) {
+        Ok(c) => c,
+        Err(e) => {
+            return write_json(
+                stream,
+                502,
+                &json!({ "success": false, "error": e.to_string() }),
+            )
+        }
+    };
+    let first = match conn.send(cmd) {
+        Ok(resp) if resp.success => serde_json::to_value(&resp).unwrap_or_default(),
+        Ok(resp) => return write_json(stream, 200, &serde_json::to_value(&resp)?),
+        Err(e) => {
+            return write_json(
+                stream,
+                502,
+                &json!({ "success": false, "error": e.to_string() }),
+            )
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+    )?;
+    let mut send_line = |value: &Value| {
+        let line = format!("{}\n", value);
+        write!(stream, "{:x}\r\n{}\r\n", line.len(), line).and_then(|_| stream.flush())
+    };
+    send_line(&first)?;
+    while let Ok(Some(event)) = conn.next_event() {
+        send_line(&event)?;
+    }
+    write!(stream, "0\r\n\r\n")
+}
+
+fn handle(mut stream: TcpStream, options: &Options, flags: &Flags) {
+    stream.set_read_timeout(Some(Duration::from_secs(30))).ok();
+    let request = match stream.try_clone() {
+        Ok(s) => read_request(&mut BufReader::new(s)),
+        Err(_) => return,
+    };
+
+    let (label, status) = match request {
+        Err((status, message)) => {
+            let _ = write_json(
+                &mut stream,
+                status,
+                &json!({ "success": false, "error": message }),
+            );
+            ("-".to_string(), status)
+        }
+        Ok(request) => {
+            let label = format!("{} {}", request.method, request.path);
+            match route(&request, options, flags) {
+                Reply::Json(status, body) => {
+                    let _ = write_json(&mut stream, status, &body);
+                    (label, status)
+                }
+                Reply::Stream(cmd) => {
+                    let _ = stream_events(&mut stream, &cmd, &flags.session);
+                    (label, 200)
+                }
+            }
+        }
+    };
+    if !flags.json {
+        eprintln!("{}", color::dim(&format!("{} {}", label, status)));
+    }
+}
+
+/// Serve the HTTP bridge until the process is killed. Each connection gets
+/// its own thread and daemon connection. Returns false if the address could
+/// not be bound.
+pub fn run(options: &Options, flags: &Flags) -> bool {
+    let listener = match TcpListener::bind((options.host.as_str(), options.port)) {
+        Ok(l) => l,
+        Err(e) => {
+            let msg = format!(
+                "Could not listen on {}:{}: {}",
+                options.host, options.port, e
+            );
+            if flags.json {
+                format::print(&json!({ "success": false, "error": msg }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), msg);
+            }
+            return false;
+        }
+    };
+
+    let url = format!("http://{}:{}", options.host, options.port);
+    if flags.json {
+        format::print(&json!({ "success": true, "data": { "url": url } }));
+    } else {
+        println!(
+            "{} Serving session {} on {}",
+            color::success_indicator(),
+            color::cyan(&flags.session),
+            color::green(&url)
+        );
+        println!("{}", color::dim(&format!("  POST {}/command", url)));
+    }
+
+    thread::scope(|scope| {
+        for stream in listener.incoming().flatten() {
+            scope.spawn(|| handle(stream, options, flags));
+        }
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::parse_flags;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: vec![
+                ("Host".to_string(), format!("127.0.0.1:{}", DEFAULT_PORT)),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn with_header(mut request: Request, name: &str, value: &str) -> Request {
+        request
+            .headers
+            .retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        request.headers.push((name.to_string(), value.to_string()));
+        request
+    }
+
+    fn status(reply: Reply) -> u16 {
+        match reply {
+            Reply::Json(status, _) => status,
+            Reply::Stream(_) => 200,
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse_options(&strings(&["--port", "9000", "--host", "0.0.0.0"])).unwrap();
+        assert_eq!(options.port, 9000);
+        assert_eq!(options.host, "0.0.0.0");
+
+        assert_eq!(parse_options(&[]).unwrap().port, DEFAULT_PORT);
+        assert!(parse_options(&strings(&["--port", "0"])).is_err());
+        assert!(parse_options(&strings(&["--port"])).is_err());
+        assert!(parse_options(&strings(&["--verbose"])).is_err());
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /command?x=1 HTTP/1.1\r\nHost: localhost\r\ncontent-length: 17\r\n\r\n{\"action\":\"url\"}\n";
+        let request = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/command");
+        assert_eq!(request.header("Content-Length"), Some("17"));
+        assert_eq!(request.body, b"{\"action\":\"url\"}\n");
+
+        let raw = "POST /command HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n";
+        assert_eq!(read_request(&mut raw.as_bytes()).err().unwrap().0, 411);
+    }
+
+    #[test]
+    fn test_read_request_limits() {
+        let raw = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read_request(&mut raw.as_bytes()).err().unwrap().0, 431);
+
+        let raw = format!(
+            "GET /health HTTP/1.1\r\nX-Big: {}\r\n\r\n",
+            "a".repeat(MAX_LINE)
+        );
+        assert_eq!(read_request(&mut raw.as_bytes()).err().unwrap().0, 431);
+
+        let raw = format!(
+            "GET /health HTTP/1.1\r\n{}\r\n",
+            "X-A: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(read_request(&mut raw.as_bytes()).err().unwrap().0, 431);
+
+        let raw = format!(
+            "POST /command HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(read_request(&mut raw.as_bytes()).err().unwrap().0, 413);
+    }
+
+    #[test]
+    fn test_route_refuses_web_pages() {
+        let flags = parse_flags(&[]);
+        let mut options = parse_options(&[]).unwrap();
+        options.token = None;
+        let health = || request("GET", "/health", "");
+
+        let origin = with_header(health(), "Origin", "https://evil.example");
+        assert_eq!(status(route(&origin, &options, &flags)), 403);
+
+        let rebound = with_header(health(), "Host", "evil.example:8377");
+        assert_eq!(status(route(&rebound, &options, &flags)), 403);
+        let other_port = with_header(health(), "Host", "localhost:80");
+        assert_eq!(status(route(&other_port, &options, &flags)), 403);
+        let local = with_header(health(), "Host", "localhost:8377");
+        assert_eq!(status(route(&local, &options, &flags)), 200);
+
+        // Simple requests a page can send without a preflight
+        let form = with_header(
+            request("POST", "/command", r#"{"action":"url"}"#),
+            "Content-Type",
+            "text/plain",
+        );
+        assert_eq!(status(route(&form, &options, &flags)), 415);
+        let mut untyped = request("POST", "/command", r#"{"action":"url"}"#);
+        untyped
+            .headers
+            .retain(|(k, _)| !k.eq_ignore_ascii_case("Content-Type"));
+        assert_eq!(status(route(&untyped, &options, &flags)), 415);
+
+        // A token stands in for the host name, but never for Origin
+        options.token = Some("s3cret".to_string());
+        let remote = with_header(
+            with_header(health(), "Host", "10.0.0.5:8377"),
+            "Authorization",
+            "Bearer s3cret",
+        );
+        assert_eq!(status(route(&remote, &options, &flags)), 200);
+        let origin = with_header(remote, "Origin", "https://evil.example");
+        assert_eq!(status(route(&origin, &options, &flags)), 403);
+    }
+
+    #[test]
+    fn test_command_from_body() {
+        let flags = parse_flags(&[]);
+        let cmd =
+            command_from_body(br#"{"action":"navigate","url":"https://a.com"}"#, &flags).unwrap();
+        assert_eq!(cmd["action"], "navigate");
+        assert!(cmd["id"].is_string());
+
+        let cmd = command_from_body(br#"{"command":"get attr @e1 href"}"#, &flags).unwrap();
+        assert_eq!(cmd["action"], "getattribute");
+
+        assert!(command_from_body(b"{}", &flags).is_err());
+        assert!(command_from_body(b"not json", &flags).is_err());
+    }
+
+    #[test]
+    fn test_route_errors() {
+        let flags = parse_flags(&[]);
+        let mut options = parse_options(&[]).unwrap();
+        options.token = None;
+
+        assert_eq!(
+            status(route(&request("GET", "/health", ""), &options, &flags)),
+            200
+        );
+        assert_eq!(
+            status(route(&request("GET", "/command", ""), &options, &flags)),
+            405
+        );
+        assert_eq!(
+            status(route(&request("GET", "/nope", ""), &options, &flags)),
+            404
+        );
+        let follow = request("POST", "/command", r#"{"command":"console --follow"}"#);
+        assert_eq!(status(route(&follow, &options, &flags)), 400);
+
+        options.token = Some("s3cret".to_string());
+        assert_eq!(
+            status(route(&request("GET", "/health", ""), &options, &flags)),
+            401
+        );
+        let mut authed = request("GET", "/health", "");
+        authed
+            .headers
+            .push(("authorization".to_string(), "Bearer s3cret".to_string()));
+        assert_eq!(status(route(&authed, &options, &flags)), 200);
+    }
+}
@@ -0,0 +1,440 @@
+//! What each command accepts: its subcommands and its own flags. Unknown
+//! flags are rejected against this table before a command is parsed, and
+//! the shell completions are generated from it.
+
+use crate::flags::{GLOBAL_FLAGS, GLOBAL_FLAGS_WITH_VALUE, SHARED_FLAGS_WITH_VALUE};
+
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// Every command with its subcommands (or fixed first arguments) and the
+/// flags it accepts besides the global ones. Aliases are in [`ALIASES`].
+pub const COMMANDS: &[(&str, &[&str], &[&str])] = &[
+    (
+        "open",
+        &[],
+        &[
+            "--new-tab",
+            "--window",
+            "--wait-until",
+            "--referer",
+            "--timeout",
+        ],
+    ),
+    ("back", &[], &[]),
+    ("forward", &[], &[]),
+    ("reload", &[], &[]),
+    ("click", &[], &["--text", "--exact", "--nth", "--all"]),
+    ("dblclick", &[], &[]),
+    ("type", &[], &[]),
+    ("fill", &[], &["--stdin", "--secret", "--form"]),
+    ("press", &[], &[]),
+    ("keydown", &[], &[]),
+    ("keyup", &[], &[]),
+    ("keys", &["down", "up"], &[]),
+    ("hover", &[], &["--text", "--exact", "--nth", "--all"]),
+    ("focus", &[], &[]),
+    ("blur", &[], &[]),
+    ("clear", &[], &[]),
+    ("check", &[], &["--nth", "--all"]),
+    ("uncheck", &[], &[]),
+    ("select", &[], &["--label", "--index"]),
+    ("drag", &[], &["--steps"]),
+    ("upload", &[], &[]),
+    ("download", &["wait", "dir"], &["--timeout"]),
+    (
+        "scroll",
+        &["up", "down", "left", "right", "to", "by", "top", "bottom"],
+        &[],
+    ),
+    ("scrollintoview", &[], &[]),
+    (
+        "wait",
+        &[],
+        &[
+            "--hidden",
+            "--url",
+            "-u",
+            "--text",
+            "-t",
+            "--idle",
+            "--load",
+            "-l",
+            "--fn",
+            "-f",
+            "--download",
+            "-d",
+            "--request",
+            "--response",
+            "--status",
+            "--spa-idle",
+            "--quiet",
+            "--timeout",
+        ],
+    ),
+    (
+        "expect",
+        &["text", "visible", "url", "title"],
+        &["--timeout"],
+    ),
+    (
+        "screenshot",
+        &["diff"],
+        &[
+            "--full-page",
+            "--format",
+            "--quality",
+            "--clip",
+            "--selector",
+            "--stdout",
+            "--base64",
+            "--annotate-refs",
+            "--out",
+            "--threshold",
+        ],
+    ),
+    ("pdf", &[], &["--format", "--landscape", "--margin"]),
+    ("save", &[], &["--html-dir"]),
+    (
+        "snapshot",
+        &["diff"],
+        &[
+            "--interactive",
+            "-i",
+            "--compact",
+            "-c",
+            "--depth",
+            "-d",
+            "--selector",
+            "-s",
+            "--cursor",
+            "-C",
+            "--format",
+            "--max-chars",
+            "--page",
+            "--coords",
+            "--if-changed",
+        ],
+    ),
+    ("refs", &[], &["--filter"]),
+    ("visible-elements", &[], &["--role"]),
+    ("read", &["next", "prev"], &["--max-chars"]),
+    (
+        "eval",
+        &[],
+        &[
+            "--file",
+            "--selector",
+            "-s",
+            "--arg",
+            "--stdin",
+            "--base64",
+            "-b",
+        ],
+    ),
+    ("close", &[], &["--keep-daemon", "--save-state"]),
+    ("connect", &[], &[]),
+    (
+        "get",
+        &[
+            "text", "html", "value", "attr", "title", "url", "count", "box", "styles", "style",
+            "article", "metrics",
+        ],
+        &[
+            "--selector",
+            "-s",
+            "--outer",
+            "--output",
+            "-o",
+            "--parsed",
+            "--nth",
+            "--all",
+        ],
+    ),
+    ("links", &[], &["--selector", "-s", "--absolute"]),
+    ("forms", &[], &[]),
+    ("table", &[], &["--format"]),
+    ("audit", &["a11y"], &["--fail-on", "--selector", "-s"]),
+    ("is", &["visible", "enabled", "checked"], &[]),
+    (
+        "find",
+        &[
+            "role",
+            "text",
+            "label",
+            "placeholder",
+            "alt",
+            "title",
+            "testid",
+            "first",
+            "last",
+            "nth",
+        ],
+        &["--name", "--exact", "--role", "--limit"],
+    ),
+    (
+        "mouse",
+        &["move", "click", "down", "up", "wheel"],
+        &["--button", "--double"],
+    ),
+    (
+        "set",
+        &[
+            "viewport",
+            "device",
+            "geo",
+            "offline",
+            "headers",
+            "user-agent",
+            "credentials",
+            "media",
+        ],
+        &["--scale"],
+    ),
+    (
+        "network",
+        &[
+            "route", "unroute", "requests", "log", "capture", "show", "offline", "throttle",
+        ],
+        &[
+            "--abort",
+            "--body",
+            "--clear",
+            "--filter",
+            "--since",
+            "--limit",
+            "--down",
+            "--up",
+            "--latency",
+        ],
+    ),
+    (
+        "mock",
+        &["add", "list", "remove"],
+        &["--status", "--body", "--body-file", "--header"],
+    ),
+    (
+        "fetch",
+        &[],
+        &[
+            "--method", "-X", "--body", "-d", "--header", "-H", "--output", "-o",
+        ],
+    ),
+    ("headers", &["set", "clear"], &[]),
+    ("block", &[], &[]),
+    ("unblock", &[], &["--all"]),
+    (
+        "storage",
+        &["local", "session", "get", "set", "remove", "clear"],
+        &["--session-storage"],
+    ),
+    (
+        "cookies",
+        &["list", "get", "set", "clear", "export", "import"],
+        &[
+            "--url",
+            "--domain",
+            "--path",
+            "--httpOnly",
+            "--secure",
+            "--sameSite",
+            "--expires",
+        ],
+    ),
+    ("tab", &["new", "list", "close"], &[]),
+    ("window", &["new"], &[]),
+    ("context", &["new", "list", "switch", "close"], &["--name"]),
+    ("frame", &["list", "use", "main"], &[]),
+    ("dialog", &["accept", "dismiss", "auto"], &[]),
+    (
+        "trace",
+        &["start", "stop"],
+        &["--screenshots", "--snapshots"],
+    ),
+    ("coverage", &["start", "stop"], &["--lcov"]),
+    ("har", &["start", "stop"], &[]),
+    ("record", &["start", "stop", "restart"], &[]),
+    ("console", &[], &["--follow", "--level", "--clear"]),
+    ("events", &[], &["--follow", "--types"]),
+    ("watch", &[], &["--event", "--once"]),
+    ("errors", &[], &["--clear"]),
+    ("history", &["go"], &["-n"]),
+    ("highlight", &[], &["--duration", "--label"]),
+    ("clipboard", &["read", "write", "copy", "paste"], &[]),
+    ("state", &["save", "load"], &[]),
+    ("tap", &[], &[]),
+    ("swipe", &["up", "down", "left", "right"], &[]),
+    ("device", &["list"], &[]),
+    (
+        "emulate",
+        &["geo", "locale", "timezone", "reset"],
+        &["--accuracy"],
+    ),
+    ("permissions", &["grant", "clear"], &[]),
+    ("viewport", &[], &["--scale"]),
+    ("status", &[], &[]),
+    ("raw", &[], &["--file"]),
+    ("daemon", &["status", "config", "upgrade"], &[]),
+    // Handled by main without a daemon
+    ("session", &["list", "each"], &[]),
+    ("profile", &["use", "off", "list", "current"], &[]),
+    ("auth", &["login"], &["--save", "--success"]),
+    ("run", &[], &["--continue-on-error", "-k"]),
+    ("mcp", &[], &[]),
+    ("pipe", &[], &[]),
+    ("serve", &[], &["--port", "--host", "--token"]),
+    (
+        "install",
+        &["chromium", "firefox", "webkit"],
+        &["--with-deps", "-d"],
+    ),
+    ("doctor", &[], &["--fix"]),
+    ("bench", &[], &["--n"]),
+    ("version", &[], &[]),
+    ("completions", SHELLS, &[]),
+    ("help", &[], &[]),
+];
+
+/// Other names for commands, with the command they stand for
+pub const ALIASES: &[(&str, &str)] = &[
+    ("goto", "open"),
+    ("navigate", "open"),
+    ("key", "press"),
+    ("scrollinto", "scrollintoview"),
+    ("quit", "close"),
+    ("exit", "close"),
+];
+
+/// The flags `command` (or an alias of it) accepts besides the global ones,
+/// `None` for an unknown command
+pub fn flags(command: &str) -> Option<&'static [&'static str]> {
+    let command = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == command)
+        .map_or(command, |(_, name)| name);
+    COMMANDS
+        .iter()
+        .find(|(name, _, _)| *name == command)
+        .map(|(_, _, flags)| *flags)
+}
+
+/// Whether `arg` is written as a long flag: `--name`, not `--` or `---`
+fn is_long_flag(arg: &str) -> bool {
+    arg.strip_prefix("--")
+        .and_then(|name| name.chars().next())
+        .is_some_and(|c| c.is_ascii_alphabetic())
+}
+
+/// Whether `arg` is written as a short flag: `-x`, not `-1` or `-`
+fn is_short_flag(arg: &str) -> bool {
+    arg.strip_prefix('-')
+        .and_then(|name| name.chars().next().filter(|_| name.len() == 1))
+        .is_some_and(|c| c.is_ascii_alphabetic())
+}
+
+/// The first flag in `args` that neither `command` nor the global flags
+/// accept. Arguments after a `--` separator are never flags, so text like
+/// `-x` is typed as `type @e1 -- -x`.
+pub fn unknown_flag<'a>(command: &str, args: &'a [String]) -> Option<&'a str> {
+    let accepted = flags(command)?;
+    args.iter()
+        .take_while(|arg| *arg != "--")
+        .map(String::as_str)
+        .find(|arg| {
+            (is_long_flag(arg) || is_short_flag(arg))
+                && !accepted.contains(arg)
+                && !GLOBAL_FLAGS.contains(arg)
+                && !GLOBAL_FLAGS_WITH_VALUE.contains(arg)
+                && !SHARED_FLAGS_WITH_VALUE.contains(arg)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{parse_command, ParseError};
+    use crate::flags::parse_flags;
+
+    /// Commands main handles before parse_command
+    const MAIN_COMMANDS: &[&str] = &[
+        "session",
+        "profile",
+        "auth",
+        "run",
+        "mcp",
+        "pipe",
+        "serve",
+        "install",
+        "doctor",
+        "bench",
+        "version",
+        "completions",
+        "help",
+    ];
+
+    #[test]
+    fn test_commands_are_known() {
+        for (name, _, _) in COMMANDS {
+            if MAIN_COMMANDS.contains(name) {
+                continue;
+            }
+            let result = parse_command(&[name.to_string()], &parse_flags(&[]));
+            assert!(
+                !matches!(result, Err(ParseError::UnknownCommand { .. })),
+                "{} is not a command",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_command_has_a_spec() {
+        // Each arm of parse_action's top-level match, e.g. `        "open" | "goto" =>`
+        let source = include_str!("commands.rs");
+        let start = source.find("fn parse_action(").unwrap();
+        let end = source[start..].find("\n}\n").unwrap() + start;
+        for line in source[start..end].lines() {
+            let Some(arm) = line.strip_prefix("        \"") else {
+                continue;
+            };
+            let Some((names, _)) = arm.split_once(" =>") else {
+                continue;
+            };
+            for name in names.split(" | ").map(|n| n.trim_matches('"')) {
+                let name = name.split('"').next().unwrap();
+                assert!(flags(name).is_some(), "{} is missing from COMMANDS", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_flag() {
+        let args = |s: &str| -> Vec<String> { s.split_whitespace().map(String::from).collect() };
+        assert_eq!(
+            unknown_flag("snapshot", &args("snapshot -i --depth 3")),
+            None
+        );
+        assert_eq!(
+            unknown_flag("snapshot", &args("snapshot --interactiv")),
+            Some("--interactiv")
+        );
+        assert_eq!(
+            unknown_flag("snapshot", &args("snapshot -i -x")),
+            Some("-x")
+        );
+        assert_eq!(unknown_flag("snapshot", &args("snapshot -i -q -f")), None);
+        // Commands without short flags of their own reject them too
+        assert_eq!(unknown_flag("click", &args("click #a -z")), Some("-z"));
+        assert_eq!(unknown_flag("type", &args("type @e1 -x")), Some("-x"));
+        assert_eq!(unknown_flag("type", &args("type @e1 -- -x")), None);
+        assert_eq!(unknown_flag("screenshot", &args("screenshot -f")), None);
+        // Aliases share their command's flags
+        assert_eq!(
+            unknown_flag("goto", &args("goto example.com --referer x")),
+            None
+        );
+        // Values like negative numbers and text after `--` are not flags
+        assert_eq!(unknown_flag("scroll", &args("scroll by -100 0")), None);
+        assert_eq!(unknown_flag("fill", &args("fill @e1 -- --literal")), None);
+        assert_eq!(unknown_flag("frobnicate", &args("frobnicate --x")), None);
+    }
+}
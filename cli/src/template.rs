@@ -0,0 +1,92 @@
+//! `${NAME}` in script lines: replaced by a variable from `set` or `capture`,
+//! or else by the environment variable of that name. `$${` is a literal `${`.
+
+/// Whether `name` can name a variable: letters, digits and `_`, not starting
+/// with a digit
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `text` with each `${NAME}` replaced by what `lookup` gives for `NAME`.
+/// The first error from `lookup` is returned as is.
+pub fn expand(
+    text: &str,
+    mut lookup: impl FnMut(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let from = &rest[start..];
+        if let Some(after) = from.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+        } else if let Some(after) = from.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("Unclosed ${{ in {}", text))?;
+            let name = &after[..end];
+            if !is_name(name) {
+                return Err(format!("Invalid variable name: ${{{}}}", name));
+            }
+            out.push_str(&lookup(name)?);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &from[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_with(text: &str) -> Result<String, String> {
+        expand(text, |name| match name {
+            "HOST" => Ok("example.com".to_string()),
+            "EMPTY" => Ok(String::new()),
+            _ => Err(format!("${{{}}} is not set", name)),
+        })
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand_with("https://${HOST}/login").unwrap(),
+            "https://example.com/login"
+        );
+        assert_eq!(
+            expand_with("${HOST}${EMPTY}${HOST}").unwrap(),
+            "example.comexample.com"
+        );
+        assert_eq!(expand_with("$5 and $HOST").unwrap(), "$5 and $HOST");
+        assert_eq!(expand_with("$${HOST}").unwrap(), "${HOST}");
+        assert_eq!(expand_with("no variables").unwrap(), "no variables");
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert_eq!(expand_with("${USER}").unwrap_err(), "${USER} is not set");
+        assert_eq!(expand_with("${HOST").unwrap_err(), "Unclosed ${ in ${HOST");
+        assert_eq!(
+            expand_with("${1X}").unwrap_err(),
+            "Invalid variable name: ${1X}"
+        );
+    }
+
+    #[test]
+    fn test_is_name() {
+        assert!(is_name("BASE_URL"));
+        assert!(is_name("_x1"));
+        assert!(!is_name(""));
+        assert!(!is_name("1X"));
+        assert!(!is_name("A-B"));
+    }
+}
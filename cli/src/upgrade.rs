@@ -0,0 +1,228 @@
+//! `daemon upgrade`: replace the session's daemon with one running this
+//! install's daemon.js, without losing the session's place. The old daemon's
+//! tab URLs and storage state (cookies and localStorage) are saved first. Two
+//! daemons can't share a session's socket, so the old one is then retired, and
+//! the new one loads the state and reopens the tabs. Anything else the old
+//! browser held, such as history, form input or named contexts, is lost.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+
+use serde_json::{json, Value};
+
+use crate::color;
+use crate::commands::gen_id;
+use crate::connection::{self, get_socket_dir, is_daemon_running, send_command};
+use crate::flags::Flags;
+use crate::format;
+
+/// What the old daemon had open
+struct Saved {
+    pid: u32,
+    version: String,
+    urls: Vec<String>,
+    active: usize,
+    /// Its storage state, if it had a browser to save it from
+    state: Option<PathBuf>,
+}
+
+fn request(session: &str, mut cmd: Value) -> Result<Value, String> {
+    cmd["id"] = json!(gen_id());
+    let action = cmd["action"].as_str().unwrap_or("").to_string();
+    let resp = send_command(cmd, session).map_err(|e| e.to_string())?;
+    if !resp.success {
+        return Err(resp.error.unwrap_or_else(|| format!("{} failed", action)));
+    }
+    Ok(resp.data.unwrap_or(Value::Null))
+}
+
+/// The tab URLs in `status` data, and the index of the active one
+fn tabs(status: &Value) -> (Vec<String>, usize) {
+    let tabs = status["tabs"].as_array().cloned().unwrap_or_default();
+    let urls = tabs
+        .iter()
+        .map(|tab| tab["url"].as_str().unwrap_or("about:blank").to_string())
+        .collect();
+    let active = tabs.iter().position(|tab| tab["active"] == true);
+    (urls, active.unwrap_or(0))
+}
+
+fn save(session: &str) -> Result<Saved, String> {
+    // `status` answers without launching a browser the session doesn't have
+    let status = request(session, json!({ "action": "status" }))
+        .map_err(|e| format!("Couldn't read the session from the old daemon: {}", e))?;
+    let pid = status["pid"]
+        .as_u64()
+        .ok_or("The old daemon didn't report its pid")? as u32;
+    let (urls, active) = tabs(&status);
+    let state = if status["launched"] == true {
+        let path = get_socket_dir().join(format!("{}.upgrade.json", session));
+        request(
+            session,
+            json!({ "action": "state_save", "path": path.to_string_lossy() }),
+        )
+        .map_err(|e| format!("Couldn't save the old daemon's storage state: {}", e))?;
+        Some(path)
+    } else {
+        None
+    };
+    Ok(Saved {
+        pid,
+        version: status["version"].as_str().unwrap_or("unknown").to_string(),
+        urls,
+        active,
+        state,
+    })
+}
+
+/// Close the old daemon and wait for it to exit
+fn retire(session: &str, pid: u32) -> Result<(), String> {
+    request(session, json!({ "action": "close" }))?;
+    if !connection::wait_for_shutdown(session, pid, crate::SHUTDOWN_TIMEOUT) {
+        return Err(format!(
+            "The old daemon (pid {}) didn't exit within {:?}",
+            pid,
+            crate::SHUTDOWN_TIMEOUT
+        ));
+    }
+    Ok(())
+}
+
+/// Load the saved state into the new daemon and reopen the tabs. Tabs that
+/// fail to load are left open and reported, rather than failing the upgrade.
+fn restore(session: &str, saved: &Saved) -> Result<Vec<String>, String> {
+    if let Some(path) = &saved.state {
+        request(
+            session,
+            json!({ "action": "state_load", "path": path.to_string_lossy() }),
+        )
+        .map_err(|e| format!("Couldn't load the saved storage state: {}", e))?;
+    }
+    let mut warnings = Vec::new();
+    for (i, url) in saved.urls.iter().enumerate() {
+        let result = match (i, url.as_str()) {
+            (0, "about:blank") => continue,
+            (0, _) => request(session, json!({ "action": "navigate", "url": url })),
+            (_, "about:blank") => request(session, json!({ "action": "tab_new" })),
+            _ => request(session, json!({ "action": "tab_new", "url": url })),
+        };
+        if let Err(e) = result {
+            warnings.push(format!("Couldn't reopen {}: {}", url, e));
+        }
+    }
+    if saved.urls.len() > 1 {
+        request(
+            session,
+            json!({ "action": "tab_switch", "index": saved.active }),
+        )?;
+    }
+    Ok(warnings)
+}
+
+fn upgrade(flags: &Flags) -> Result<Value, String> {
+    let session = flags.session.as_str();
+    let saved = save(session)?;
+    // Past this point the old daemon is gone, so the state file is kept if
+    // anything goes wrong, for `state load`
+    let kept = |e: String| match &saved.state {
+        Some(path) => format!("{} (the saved state is in {})", e, path.display()),
+        None => e,
+    };
+    retire(session, saved.pid).map_err(kept)?;
+    crate::start_daemon_for(flags).map_err(kept)?;
+    let warnings = restore(session, &saved).map_err(kept)?;
+    if let Some(path) = &saved.state {
+        let _ = fs::remove_file(path);
+    }
+
+    let status = request(session, json!({ "action": "status" }))?;
+    Ok(json!({
+        "from": { "pid": saved.pid, "version": saved.version },
+        "to": { "pid": status["pid"], "version": status["version"] },
+        "tabs": saved.urls.len(),
+        "warnings": warnings,
+    }))
+}
+
+pub fn run_upgrade(flags: &Flags) {
+    let result = if env::var_os("AGENT_BROWSER_REMOTE").is_some() {
+        Err("A remote daemon is upgraded on its own machine".to_string())
+    } else if !is_daemon_running(&flags.session) {
+        Err(format!(
+            "No daemon is running for session '{}'",
+            flags.session
+        ))
+    } else {
+        upgrade(flags)
+    };
+    let data = match result {
+        Ok(data) => data,
+        Err(e) => {
+            if flags.json {
+                format::print(&json!({ "success": false, "error": e }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), e);
+            }
+            exit(1);
+        }
+    };
+    if flags.json {
+        format::print(&json!({ "success": true, "data": data }));
+        return;
+    }
+    for warning in data["warnings"].as_array().into_iter().flatten() {
+        eprintln!(
+            "{} {}",
+            color::warning_indicator(),
+            warning.as_str().unwrap_or("")
+        );
+    }
+    let side = |side: &Value| {
+        format!(
+            "{} (pid {})",
+            side["version"].as_str().unwrap_or("?"),
+            side["pid"]
+        )
+    };
+    let tabs = data["tabs"].as_u64().unwrap_or(0);
+    println!(
+        "{} Daemon upgraded from {} to {}{}",
+        color::success_indicator(),
+        side(&data["from"]),
+        side(&data["to"]),
+        color::dim(&format!(
+            ", {} tab{} reopened",
+            tabs,
+            if tabs == 1 { "" } else { "s" }
+        ))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabs() {
+        let status = json!({
+            "tabs": [
+                { "index": 0, "url": "https://example.com/", "active": false },
+                { "index": 1, "url": "https://example.com/cart", "active": true },
+            ]
+        });
+        assert_eq!(
+            tabs(&status),
+            (
+                vec![
+                    "https://example.com/".to_string(),
+                    "https://example.com/cart".to_string()
+                ],
+                1
+            )
+        );
+        // A daemon without a browser has no tabs
+        assert_eq!(tabs(&json!({ "tabs": [] })), (vec![], 0));
+    }
+}
@@ -0,0 +1,178 @@
+//! What `wait`, `open` and `expect` are waiting for: shown on stderr with the
+//! time so far while the daemon works on them, and again if they time out,
+//! with where the page was by then.
+
+use serde_json::Value;
+use std::io::{self, IsTerminal, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::color;
+use crate::connection::{ErrorCode, Response};
+
+const FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// How often the line is redrawn
+const TICK: Duration = Duration::from_millis(100);
+
+/// Commands answered sooner than this never show the line
+const DELAY: Duration = Duration::from_millis(500);
+
+/// Longer descriptions are cut, so the line fits and can be cleared
+const MAX_WIDTH: usize = 60;
+
+/// What `cmd` waits for, as in "Waiting for ...", or `None` for a command
+/// that doesn't wait
+pub fn describe(cmd: &Value) -> Option<String> {
+    let field = |name: &str| cmd.get(name).and_then(|v| v.as_str());
+    let what = match field("action")? {
+        "navigate" => format!("{} to load", field("url")?),
+        "wait" => match (field("selector"), field("state")) {
+            (Some(sel), Some("hidden")) => format!("{} to be hidden", sel),
+            (Some(sel), _) => match sel.strip_prefix("text=") {
+                Some(text) => format!("text {:?}", text),
+                None => sel.to_string(),
+            },
+            (None, _) => format!("{} ms", cmd.get("timeout")?.as_u64()?),
+        },
+        "waitforurl" => format!("the URL to match {}", field("url")?),
+        "waitforloadstate" => format!("the {} load state", field("state")?),
+        "waitforfunction" => format!("{} to be truthy", field("expression")?),
+        "waitfordownload" => "a download".to_string(),
+        "waitforrequest" => format!("a request to {}", field("url")?),
+        "waitforresponse" => match cmd.get("status").and_then(|v| v.as_u64()) {
+            Some(status) => format!("a {} response from {}", status, field("url")?),
+            None => format!("a response from {}", field("url")?),
+        },
+        "waitforspaidle" => "the app to settle".to_string(),
+        "expect" => match field("kind")? {
+            "visible" => format!("{} to be visible", field("selector")?),
+            "text" => format!(
+                "the text of {} to match {:?}",
+                field("selector")?,
+                field("expected")?
+            ),
+            kind => format!("the page {} to match {:?}", kind, field("expected")?),
+        },
+        _ => return None,
+    };
+    Some(what)
+}
+
+/// `text`, cut to `MAX_WIDTH` characters
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_WIDTH {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_WIDTH - 1).collect();
+    format!("{}…", cut)
+}
+
+/// The "Waiting for ..." line, redrawn with the elapsed time until dropped,
+/// which clears it
+pub struct Spinner {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Show that the command is waiting for `what`, if stderr is a terminal
+    pub fn start(what: &str) -> Option<Spinner> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+        let what = truncate(what);
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let started = Instant::now();
+            let mut width = 0;
+            let mut frame = 0;
+            // Dropping the sender ends the wait at once, not at the next tick
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(TICK) {
+                if started.elapsed() < DELAY {
+                    continue;
+                }
+                let text = format!(
+                    "{} Waiting for {} ({:.1}s)",
+                    FRAMES[frame % FRAMES.len()],
+                    what,
+                    started.elapsed().as_secs_f64()
+                );
+                let mut stderr = io::stderr().lock();
+                let _ = write!(stderr, "\r{}", color::dim(&text));
+                let _ = stderr.flush();
+                width = width.max(text.chars().count());
+                frame += 1;
+            }
+            if width > 0 {
+                eprint!("\r{}\r", " ".repeat(width));
+            }
+        });
+        Some(Spinner {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Below a timed-out command's error, what it was waiting for and the page's
+/// URL and title at the time, when the daemon reported them
+pub fn print_timeout(what: &str, resp: &Response) {
+    if resp.error_code != Some(ErrorCode::Timeout) {
+        return;
+    }
+    eprintln!("  {} {}", color::dim("Waiting for:"), what);
+    let context = resp.error_context.as_ref();
+    let Some(url) = context.and_then(|c| c.page_url.as_deref()) else {
+        return;
+    };
+    match context
+        .and_then(|c| c.page_title.as_deref())
+        .filter(|title| !title.is_empty())
+    {
+        Some(title) => eprintln!("  {} {} ({})", color::dim("Page:"), url, title),
+        None => eprintln!("  {} {}", color::dim("Page:"), url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_describe() {
+        let wait = json!({ "action": "wait", "selector": "#done" });
+        assert_eq!(describe(&wait).unwrap(), "#done");
+        let hidden = json!({ "action": "wait", "selector": "#spinner", "state": "hidden" });
+        assert_eq!(describe(&hidden).unwrap(), "#spinner to be hidden");
+        let text = json!({ "action": "wait", "selector": "text=Welcome" });
+        assert_eq!(describe(&text).unwrap(), "text \"Welcome\"");
+        let open = json!({ "action": "navigate", "url": "https://example.com" });
+        assert_eq!(describe(&open).unwrap(), "https://example.com to load");
+        let expect = json!({ "action": "expect", "kind": "url", "expected": "**/done" });
+        assert_eq!(
+            describe(&expect).unwrap(),
+            "the page url to match \"**/done\""
+        );
+        assert!(describe(&json!({ "action": "click", "selector": "#go" })).is_none());
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("#done"), "#done");
+        let long = "x".repeat(100);
+        assert_eq!(truncate(&long).chars().count(), MAX_WIDTH);
+        assert!(truncate(&long).ends_with('…'));
+    }
+}
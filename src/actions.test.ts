@@ -1,5 +1,14 @@
 import { describe, it, expect } from 'bun:test';
-import { toAIFriendlyError } from './actions.js';
+import {
+  failureResponse,
+  toAIFriendlyError,
+  CommandError,
+  cssPropertyName,
+  isDetachedError,
+  urlParts,
+  withPageContext,
+} from './actions.js';
+import type { BrowserManager } from './browser.js';
 
 describe('toAIFriendlyError', () => {
   describe('element blocked by overlay', () => {
@@ -37,3 +46,107 @@ describe('toAIFriendlyError', () => {
     });
   });
 });
+
+describe('failureResponse', () => {
+  it('carries the code, selector and stack of an explained error', () => {
+    const error = toAIFriendlyError(
+      new Error("locator.click: waiting for locator('#go') to be visible"),
+      '#go'
+    );
+    expect(error).toBeInstanceOf(CommandError);
+
+    const response = failureResponse({ id: 'r1', action: 'click', selector: '#go' }, error);
+    expect(response.success).toBe(false);
+    if (response.success) return;
+    expect(response.errorCode).toBe('not_found');
+    expect(response.errorContext?.selector).toBe('#go');
+    expect(response.errorContext?.stack?.split('\n')[0]).toStartWith('at ');
+  });
+
+  it('classifies navigation failures and keeps the URL', () => {
+    const response = failureResponse(
+      { id: 'r2', action: 'navigate', url: 'https://nope.invalid' },
+      new Error('page.goto: net::ERR_NAME_NOT_RESOLVED at https://nope.invalid')
+    );
+    if (response.success) throw new Error('expected a failure');
+    expect(response.errorCode).toBe('navigation');
+    expect(response.errorContext?.url).toBe('https://nope.invalid');
+  });
+
+  it('adds the page URL and title to a timeout', async () => {
+    const browser = {
+      isLaunched: () => true,
+      getPage: () => ({ url: () => 'https://example.com/login', title: async () => 'Sign in' }),
+    } as unknown as BrowserManager;
+    const timedOut = failureResponse(
+      { id: 'r4', action: 'waitforurl', url: '**/dashboard' },
+      new Error('page.waitForURL: Timeout 5000ms exceeded.')
+    );
+    const response = await withPageContext(timedOut, browser);
+    if (response.success) throw new Error('expected a failure');
+    expect(response.errorContext?.pageUrl).toBe('https://example.com/login');
+    expect(response.errorContext?.pageTitle).toBe('Sign in');
+    expect(response.errorContext?.url).toBe('**/dashboard');
+
+    const other = failureResponse({ id: 'r5', action: 'tab_switch', index: 4 }, 'No tab 4');
+    expect(await withPageContext(other, browser)).toBe(other);
+  });
+
+  it('leaves out the code of an unclassified failure', () => {
+    const response = failureResponse({ id: 'r3', action: 'tab_switch', index: 4 }, 'No tab 4');
+    if (response.success) throw new Error('expected a failure');
+    expect(response.errorCode).toBeUndefined();
+    expect(response.errorContext).toBeUndefined();
+  });
+});
+
+describe('isDetachedError', () => {
+  it('should match elements lost mid-action', () => {
+    const detached = new Error('elementHandle.click: Element is not attached to the DOM');
+    expect(isDetachedError(detached)).toBe(true);
+    expect(isDetachedError(new Error('Execution context was destroyed'))).toBe(true);
+    const wrapped = toAIFriendlyError(new Error('Element is not stable'), '@e2');
+    expect(isDetachedError(wrapped)).toBe(true);
+  });
+
+  it('should not match timeouts or other failures', () => {
+    const timeout = new Error(
+      'locator.click: Timeout 5000ms exceeded.\n  - element was detached from the DOM, retrying'
+    );
+    expect(isDetachedError(timeout)).toBe(false);
+    expect(isDetachedError(new Error('Element not found'))).toBe(false);
+    expect(isDetachedError('Element is not attached to the DOM')).toBe(false);
+  });
+});
+
+describe('urlParts', () => {
+  it('should split a URL and decode its query', () => {
+    expect(urlParts('https://shop.test:8443/search?q=red%20shoes&size=9&size=10#results')).toEqual({
+      scheme: 'https',
+      host: 'shop.test',
+      port: 8443,
+      path: '/search',
+      query: { q: 'red shoes', size: ['9', '10'] },
+      fragment: 'results',
+    });
+  });
+
+  it('should leave out what the URL lacks', () => {
+    expect(urlParts('https://example.com/')).toEqual({
+      scheme: 'https',
+      host: 'example.com',
+      port: null,
+      path: '/',
+      query: {},
+      fragment: null,
+    });
+  });
+});
+
+describe('cssPropertyName', () => {
+  it('should accept CSS, camelCase and custom property names', () => {
+    expect(cssPropertyName('font-size')).toBe('font-size');
+    expect(cssPropertyName('backgroundColor')).toBe('background-color');
+    expect(cssPropertyName('--accentColor')).toBe('--accentColor');
+  });
+});
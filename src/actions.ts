@@ -1,52 +1,130 @@
-import type { Page, Frame } from 'playwright-core';
-import { mkdirSync } from 'node:fs';
+import type { Page, Frame, Locator } from 'playwright-core';
+import { mkdirSync, readFileSync, statSync, writeFileSync } from 'node:fs';
 import path from 'node:path';
-import type { BrowserManager, ScreencastFrame } from './browser.js';
+import {
+  DEFAULT_CAPTURE_LIMIT,
+  urlMatcher,
+  type BrowserManager,
+  type ScreencastFrame,
+} from './browser.js';
 import { getAppDir } from './daemon.js';
+import { extractArticle } from './article.js';
+import { extractTable, tableToCsv, tableToJson, type TableData } from './table.js';
+import { exceedsImpact, runAudit } from './audit.js';
+import { summarizeCoverage, toLcov } from './coverage.js';
+import {
+  addRefAnnotations,
+  DEFAULT_HIGHLIGHT_MS,
+  highlightElements,
+  removeRefAnnotations,
+} from './annotate.js';
+import {
+  DEFAULT_SPA_QUIET_MS,
+  SPA_ACTIVITY_SCRIPT,
+  isSpaIdle,
+  trackRequests,
+  type SpaActivity,
+} from './spa.js';
+import { saveHtmlDir } from './archive.js';
+import {
+  type RefGeometry,
+  type RefMap,
+  type SnapshotFormat,
+  type SnapshotNode,
+  type SnapshotPage,
+  DEFAULT_SNAPSHOT_PAGE_CHARS,
+  addCoords,
+  diffSnapshots,
+  findRefs,
+  formatSnapshot,
+  listRefs,
+  paginateSnapshot,
+  parseSnapshotTree,
+  snapshotHash,
+  visibleElements,
+} from './snapshot.js';
+import { chunkAfter, chunkBefore, DEFAULT_READ_CHARS, findItem, readItems } from './reader.js';
 import type {
   Command,
   Response,
   NavigateCommand,
   ClickCommand,
+  MatchPick,
   TypeCommand,
   FillCommand,
+  FillFormCommand,
   CheckCommand,
   UncheckCommand,
   UploadCommand,
   DoubleClickCommand,
   FocusCommand,
+  BlurCommand,
   DragCommand,
   FrameCommand,
+  FrameListCommand,
+  FindCommand,
+  RefsCommand,
+  VisibleElementsCommand,
+  ArticleCommand,
+  LinksCommand,
+  FormsCommand,
+  TableCommand,
+  MetricsCommand,
+  AuditCommand,
+  SnapshotDiffCommand,
+  ReadCommand,
   GetByRoleCommand,
   GetByTextCommand,
+  GetByNameCommand,
   GetByLabelCommand,
   GetByPlaceholderCommand,
   PressCommand,
   ScreenshotCommand,
   EvaluateCommand,
   WaitCommand,
+  ExpectCommand,
   ScrollCommand,
   SelectCommand,
   HoverCommand,
   ContentCommand,
   TabNewCommand,
   TabSwitchCommand,
+  CloseCommand,
   TabCloseCommand,
   WindowNewCommand,
+  ContextNewCommand,
+  ContextSwitchCommand,
+  ContextCloseCommand,
   CookiesSetCommand,
+  CookiesExportCommand,
+  CookiesImportCommand,
   StorageGetCommand,
   StorageSetCommand,
+  StorageRemoveCommand,
   StorageClearCommand,
   DialogCommand,
+  DialogAutoCommand,
   PdfCommand,
+  SaveCommand,
   RouteCommand,
   RequestsCommand,
+  NetworkLogCommand,
+  NetworkCaptureCommand,
+  NetworkCaptureStopCommand,
+  NetworkShowCommand,
+  MockAddCommand,
+  MockListCommand,
+  MockRemoveCommand,
+  FetchCommand,
+  BlockCommand,
+  UnblockCommand,
   DownloadCommand,
   GeolocationCommand,
   PermissionsCommand,
   ViewportCommand,
   DeviceCommand,
   GetAttributeCommand,
+  ComputedStyleCommand,
   GetTextCommand,
   IsVisibleCommand,
   IsEnabledCommand,
@@ -56,10 +134,15 @@ import type {
   StylesCommand,
   TraceStartCommand,
   TraceStopCommand,
+  HarStartCommand,
   HarStopCommand,
+  CoverageStartCommand,
+  CoverageStopCommand,
   StorageStateSaveCommand,
   ConsoleCommand,
   ErrorsCommand,
+  HistoryCommand,
+  HistoryGoCommand,
   KeyboardCommand,
   WheelCommand,
   TapCommand,
@@ -76,6 +159,7 @@ import type {
   AddStyleCommand,
   EmulateMediaCommand,
   OfflineCommand,
+  ThrottleCommand,
   HeadersCommand,
   GetByAltTextCommand,
   GetByTitleCommand,
@@ -86,8 +170,10 @@ import type {
   SetContentCommand,
   TimezoneCommand,
   LocaleCommand,
+  EmulateResetCommand,
   HttpCredentialsCommand,
   MouseMoveCommand,
+  MouseClickCommand,
   MouseDownCommand,
   MouseUpCommand,
   WaitForFunctionCommand,
@@ -98,6 +184,10 @@ import type {
   InsertTextCommand,
   MultiSelectCommand,
   WaitForDownloadCommand,
+  WaitForRequestCommand,
+  WaitForResponseCommand,
+  WaitForSpaIdleCommand,
+  DownloadDirCommand,
   ResponseBodyCommand,
   ScreencastStartCommand,
   ScreencastStopCommand,
@@ -107,14 +197,18 @@ import type {
   RecordingStartCommand,
   RecordingStopCommand,
   RecordingRestartCommand,
+  CodegenStartCommand,
+  CodegenStopData,
   NavigateData,
   ScreenshotData,
   EvaluateData,
   ContentData,
+  FillFormData,
   TabListData,
   TabNewData,
   TabSwitchData,
   TabCloseData,
+  ContextListData,
   ScreencastStartData,
   ScreencastStopData,
   RecordingStartData,
@@ -122,6 +216,8 @@ import type {
   RecordingRestartData,
   InputEventData,
   StylesData,
+  ErrorCode,
+  ErrorContext,
 } from './types.js';
 import { successResponse, errorResponse } from './protocol.js';
 
@@ -140,8 +236,27 @@ export function setScreencastFrameCallback(
 
 // Snapshot response type
 interface SnapshotData {
-  snapshot: string;
+  snapshot?: string;
+  nodes?: SnapshotNode[];
   refs?: Record<string, { role: string; name?: string }>;
+  pagination?: Omit<SnapshotPage, 'text'>;
+}
+
+/**
+ * A failure with a known class, reported as the response's errorCode
+ */
+export class CommandError extends Error {
+  readonly code: ErrorCode;
+  readonly selector: string | undefined;
+
+  constructor(message: string, code: ErrorCode, selector?: string, cause?: unknown) {
+    super(message, { cause });
+    this.name = 'CommandError';
+    this.code = code;
+    this.selector = selector;
+    // Keep the frames of the error being explained, not of this rewrite
+    if (cause instanceof Error && cause.stack) this.stack = cause.stack;
+  }
 }
 
 /**
@@ -157,34 +272,47 @@ export function toAIFriendlyError(error: unknown, selector: string): Error {
     const countMatch = message.match(/resolved to (\d+) elements/);
     const count = countMatch ? countMatch[1] : 'multiple';
 
-    return new Error(
+    return new CommandError(
       `Selector "${selector}" matched ${count} elements. ` +
-        `Run 'snapshot' to get updated refs, or use a more specific CSS selector.`
+        `Run 'snapshot' to get updated refs, use a more specific CSS selector, ` +
+        `or pick matches with --nth <n> or --all.`,
+      'ambiguous',
+      selector,
+      error
     );
   }
 
   // Handle element not interactable (must be checked BEFORE timeout case)
   // This includes cases where an overlay/modal blocks the element
   if (message.includes('intercepts pointer events')) {
-    return new Error(
+    return new CommandError(
       `Element "${selector}" is blocked by another element (likely a modal or overlay). ` +
-        `Try dismissing any modals/cookie banners first.`
+        `Try dismissing any modals/cookie banners first.`,
+      'blocked',
+      selector,
+      error
     );
   }
 
   // Handle element not visible
   if (message.includes('not visible') && !message.includes('Timeout')) {
-    return new Error(
+    return new CommandError(
       `Element "${selector}" is not visible. ` +
-        `Try scrolling it into view or check if it's hidden.`
+        `Try scrolling it into view or check if it's hidden.`,
+      'not_visible',
+      selector,
+      error
     );
   }
 
   // Handle general timeout (element exists but action couldn't complete)
   if (message.includes('Timeout') && message.includes('exceeded')) {
-    return new Error(
+    return new CommandError(
       `Action on "${selector}" timed out. The element may be blocked, still loading, or not interactable. ` +
-        `Run 'snapshot' to check the current page state.`
+        `Run 'snapshot' to check the current page state.`,
+      'timeout',
+      selector,
+      error
     );
   }
 
@@ -193,9 +321,12 @@ export function toAIFriendlyError(error: unknown, selector: string): Error {
     message.includes('waiting for') &&
     (message.includes('to be visible') || message.includes('Timeout'))
   ) {
-    return new Error(
+    return new CommandError(
       `Element "${selector}" not found or not visible. ` +
-        `Run 'snapshot' to see current page elements.`
+        `Run 'snapshot' to see current page elements.`,
+      'not_found',
+      selector,
+      error
     );
   }
 
@@ -203,10 +334,56 @@ export function toAIFriendlyError(error: unknown, selector: string): Error {
   return error instanceof Error ? error : new Error(message);
 }
 
+const NAVIGATION_ACTIONS = new Set(['navigate', 'back', 'forward', 'reload']);
+
+/**
+ * Class of a failure that wasn't raised as a CommandError
+ */
+function errorCode(action: string, message: string): ErrorCode | undefined {
+  if (/Timeout \d+ms exceeded|timed out/.test(message)) return 'timeout';
+  if (NAVIGATION_ACTIONS.has(action) || /net::ERR_|NS_ERROR_/.test(message)) return 'navigation';
+  return undefined;
+}
+
+/**
+ * Error response for `command` failing with `error`: the message, its class,
+ * the selector or URL the command was working on and the daemon's stack frames
+ * @internal Exported for testing
+ */
+export function failureResponse(command: Command, error: unknown): Response {
+  const message = error instanceof Error ? error.message : String(error);
+  const code = error instanceof CommandError ? error.code : errorCode(command.action, message);
+
+  const context: ErrorContext = {};
+  if (error instanceof CommandError && error.selector !== undefined) {
+    context.selector = error.selector;
+  } else if ('selector' in command && typeof command.selector === 'string') {
+    context.selector = command.selector;
+  }
+  if ('url' in command && typeof command.url === 'string') context.url = command.url;
+  const frames = error instanceof Error ? (error.stack ?? '').split('\n') : [];
+  const stack = frames
+    .map((line) => line.trim())
+    .filter((line) => line.startsWith('at '))
+    .join('\n');
+  if (stack) context.stack = stack;
+
+  return errorResponse(
+    command.id,
+    message,
+    code,
+    Object.keys(context).length > 0 ? context : undefined
+  );
+}
+
 /**
  * Execute a command and return a response
  */
-export async function executeCommand(command: Command, browser: BrowserManager): Promise<Response> {
+export async function executeCommand(
+  command: Command,
+  browser: BrowserManager,
+  signal?: AbortSignal
+): Promise<Response> {
   try {
     switch (command.action) {
       case 'launch':
@@ -219,6 +396,8 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleType(command, browser);
       case 'fill':
         return await handleFill(command, browser);
+      case 'fillform':
+        return await handleFillForm(command, browser);
       case 'check':
         return await handleCheck(command, browser);
       case 'uncheck':
@@ -229,16 +408,28 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleDoubleClick(command, browser);
       case 'focus':
         return await handleFocus(command, browser);
+      case 'blur':
+        return await handleBlur(command, browser);
       case 'drag':
         return await handleDrag(command, browser);
       case 'frame':
         return await handleFrame(command, browser);
       case 'mainframe':
         return await handleMainFrame(command, browser);
+      case 'frame_list':
+        return await handleFrameList(command, browser);
+      case 'find':
+        return await handleFind(command, browser);
+      case 'refs':
+        return await handleRefs(command, browser);
+      case 'visible_elements':
+        return await handleVisibleElements(command, browser);
       case 'getbyrole':
         return await handleGetByRole(command, browser);
       case 'getbytext':
         return await handleGetByText(command, browser);
+      case 'getbyname':
+        return await handleGetByName(command, browser);
       case 'getbylabel':
         return await handleGetByLabel(command, browser);
       case 'getbyplaceholder':
@@ -249,10 +440,16 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleScreenshot(command, browser);
       case 'snapshot':
         return await handleSnapshot(command, browser);
+      case 'snapshot_diff':
+        return await handleSnapshotDiff(command, browser);
+      case 'read':
+        return await handleRead(command, browser);
       case 'evaluate':
         return await handleEvaluate(command, browser);
       case 'wait':
-        return await handleWait(command, browser);
+        return await handleWait(command, browser, signal);
+      case 'expect':
+        return await handleExpect(command, browser, signal);
       case 'scroll':
         return await handleScroll(command, browser);
       case 'select':
@@ -273,28 +470,66 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleTabClose(command, browser);
       case 'window_new':
         return await handleWindowNew(command, browser);
+      case 'context_new':
+        return await handleContextNew(command, browser);
+      case 'context_list':
+        return await handleContextList(command, browser);
+      case 'context_switch':
+        return await handleContextSwitch(command, browser);
+      case 'context_close':
+        return await handleContextClose(command, browser);
       case 'cookies_get':
         return await handleCookiesGet(command, browser);
       case 'cookies_set':
         return await handleCookiesSet(command, browser);
       case 'cookies_clear':
         return await handleCookiesClear(command, browser);
+      case 'cookies_export':
+        return await handleCookiesExport(command, browser);
+      case 'cookies_import':
+        return await handleCookiesImport(command, browser);
       case 'storage_get':
         return await handleStorageGet(command, browser);
       case 'storage_set':
         return await handleStorageSet(command, browser);
+      case 'storage_remove':
+        return await handleStorageRemove(command, browser);
       case 'storage_clear':
         return await handleStorageClear(command, browser);
       case 'dialog':
         return await handleDialog(command, browser);
+      case 'dialog_auto':
+        return await handleDialogAuto(command, browser);
       case 'pdf':
         return await handlePdf(command, browser);
+      case 'save':
+        return await handleSave(command, browser);
       case 'route':
         return await handleRoute(command, browser);
       case 'unroute':
         return await handleUnroute(command, browser);
       case 'requests':
         return await handleRequests(command, browser);
+      case 'mock_add':
+        return await handleMockAdd(command, browser);
+      case 'mock_list':
+        return await handleMockList(command, browser);
+      case 'mock_remove':
+        return await handleMockRemove(command, browser);
+      case 'fetch':
+        return await handleFetch(command, browser);
+      case 'block':
+        return await handleBlock(command, browser);
+      case 'unblock':
+        return await handleUnblock(command, browser);
+      case 'network_log':
+        return await handleNetworkLog(command, browser);
+      case 'network_capture':
+        return await handleNetworkCapture(command, browser);
+      case 'network_capture_stop':
+        return await handleNetworkCaptureStop(command, browser);
+      case 'network_show':
+        return await handleNetworkShow(command, browser);
       case 'download':
         return await handleDownload(command, browser);
       case 'geolocation':
@@ -319,8 +554,12 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleTitle(command, browser);
       case 'getattribute':
         return await handleGetAttribute(command, browser);
+      case 'computedstyle':
+        return await handleComputedStyle(command, browser);
       case 'gettext':
         return await handleGetText(command, browser);
+      case 'article':
+        return await handleArticle(command, browser);
       case 'isvisible':
         return await handleIsVisible(command, browser);
       case 'isenabled':
@@ -333,6 +572,16 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleBoundingBox(command, browser);
       case 'styles':
         return await handleStyles(command, browser);
+      case 'links':
+        return await handleLinks(command, browser);
+      case 'forms':
+        return await handleForms(command, browser);
+      case 'table':
+        return await handleTable(command, browser);
+      case 'metrics':
+        return await handleMetrics(command, browser);
+      case 'audit':
+        return await handleAudit(command, browser);
       case 'video_start':
         return await handleVideoStart(command, browser);
       case 'video_stop':
@@ -345,6 +594,10 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleHarStart(command, browser);
       case 'har_stop':
         return await handleHarStop(command, browser);
+      case 'coverage_start':
+        return await handleCoverageStart(command, browser);
+      case 'coverage_stop':
+        return await handleCoverageStop(command, browser);
       case 'state_save':
         return await handleStateSave(command, browser);
       case 'state_load':
@@ -353,6 +606,10 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleConsole(command, browser);
       case 'errors':
         return await handleErrors(command, browser);
+      case 'history':
+        return await handleHistory(command, browser);
+      case 'history_go':
+        return await handleHistoryGo(command, browser);
       case 'keyboard':
         return await handleKeyboard(command, browser);
       case 'wheel':
@@ -389,6 +646,8 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleEmulateMedia(command, browser);
       case 'offline':
         return await handleOffline(command, browser);
+      case 'throttle':
+        return await handleThrottle(command, browser);
       case 'headers':
         return await handleHeaders(command, browser);
       case 'pause':
@@ -402,7 +661,7 @@ export async function executeCommand(command: Command, browser: BrowserManager):
       case 'nth':
         return await handleNth(command, browser);
       case 'waitforurl':
-        return await handleWaitForUrl(command, browser);
+        return await handleWaitForUrl(command, browser, signal);
       case 'waitforloadstate':
         return await handleWaitForLoadState(command, browser);
       case 'setcontent':
@@ -411,10 +670,14 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleTimezone(command, browser);
       case 'locale':
         return await handleLocale(command, browser);
+      case 'emulate_reset':
+        return await handleEmulateReset(command, browser);
       case 'credentials':
         return await handleCredentials(command, browser);
       case 'mousemove':
         return await handleMouseMove(command, browser);
+      case 'mouseclick':
+        return await handleMouseClick(command, browser);
       case 'mousedown':
         return await handleMouseDown(command, browser);
       case 'mouseup':
@@ -437,6 +700,14 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleMultiSelect(command, browser);
       case 'waitfordownload':
         return await handleWaitForDownload(command, browser);
+      case 'waitforrequest':
+        return await handleWaitForRequest(command, browser);
+      case 'waitforresponse':
+        return await handleWaitForResponse(command, browser);
+      case 'waitforspaidle':
+        return await handleWaitForSpaIdle(command, browser, signal);
+      case 'download_dir':
+        return await handleDownloadDir(command, browser);
       case 'responsebody':
         return await handleResponseBody(command, browser);
       case 'screencast_start':
@@ -455,6 +726,8 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleRecordingStop(command, browser);
       case 'recording_restart':
         return await handleRecordingRestart(command, browser);
+      case 'codegen_start':
+        return await handleCodegenStart(command, browser);
       default: {
         // TypeScript narrows to never here, but we handle it for safety
         const unknownCommand = command as { id: string; action: string };
@@ -462,9 +735,61 @@ export async function executeCommand(command: Command, browser: BrowserManager):
       }
     }
   } catch (error) {
-    const message = error instanceof Error ? error.message : String(error);
-    return errorResponse(command.id, message);
+    if (command.retry && isDetachedError(error) && !signal?.aborted) {
+      // The element was re-rendered under us; the handler queries it afresh
+      await sleep(RETRY_DELAY_MS, signal);
+      if (!signal?.aborted) {
+        return executeCommand({ ...command, retry: command.retry - 1 }, browser, signal);
+      }
+    }
+    return withPageContext(failureResponse(command, error), browser);
+  }
+}
+
+/** Pause before a --retry, so a re-render in progress can finish */
+const RETRY_DELAY_MS = 100;
+
+/** How long a timed-out command's failure waits for the page's title */
+const PAGE_CONTEXT_TIMEOUT_MS = 1000;
+
+/**
+ * Add the page's URL and title to a timeout, which often explains it: a
+ * redirect to a login page, an error page, a navigation that never finished
+ * @internal Exported for testing
+ */
+export async function withPageContext(response: Response, browser: BrowserManager): Promise<Response> {
+  if (response.success || response.errorCode !== 'timeout' || !browser.isLaunched()) {
+    return response;
+  }
+  try {
+    const page = browser.getPage();
+    // A page still busy loading can keep the title from answering
+    const title = await Promise.race([
+      page.title(),
+      sleep(PAGE_CONTEXT_TIMEOUT_MS).then(() => undefined),
+    ]);
+    response.errorContext = { ...response.errorContext, pageUrl: page.url(), pageTitle: title };
+  } catch {
+    // A closed page leaves the failure as it was
+  }
+  return response;
+}
+
+const DETACHED_ERROR =
+  /not attached to the DOM|detached from the DOM|is not stable|Execution context was destroyed/i;
+
+/**
+ * Whether `error`, or an error it wraps, is Playwright losing the element it
+ * was acting on, which a fresh query of the same selector usually fixes.
+ * Timeouts aren't: their call logs mention detaching too, and retrying one
+ * would only wait out the timeout again.
+ */
+export function isDetachedError(error: unknown): boolean {
+  for (let e = error; e instanceof Error; e = e.cause) {
+    if (e.name === 'TimeoutError' || /Timeout \d+ms exceeded/.test(e.message)) return false;
+    if (DETACHED_ERROR.test(e.message)) return true;
   }
+  return false;
 }
 
 async function handleLaunch(
@@ -479,6 +804,12 @@ async function handleNavigate(
   command: NavigateCommand,
   browser: BrowserManager
 ): Promise<Response<NavigateData>> {
+  let tab: number | undefined;
+  if (command.target === 'tab') {
+    tab = (await browser.newTab()).index;
+  } else if (command.target === 'window') {
+    tab = (await browser.newWindow()).index;
+  }
   const page = browser.getPage();
 
   // If headers are provided, set up scoped headers for this origin
@@ -488,29 +819,63 @@ async function handleNavigate(
 
   await page.goto(command.url, {
     waitUntil: command.waitUntil ?? 'load',
+    referer: command.referer,
+    timeout: command.timeout,
   });
 
   return successResponse(command.id, {
     url: page.url(),
     title: await page.title(),
+    ...(tab === undefined ? {} : { tab }),
   });
 }
 
-async function handleClick(command: ClickCommand, browser: BrowserManager): Promise<Response> {
+/**
+ * The elements a command with `--nth` or `--all` acts on: the index-th match
+ * (-1 for the last), every match in document order, or the selector itself,
+ * which must then match one element. `--all` fails when nothing matches, so
+ * a typo isn't mistaken for a page with nothing to do.
+ */
+async function pickMatches(
+  browser: BrowserManager,
+  selector: string,
+  pick: MatchPick
+): Promise<Locator[]> {
   // Support both refs (@e1) and regular selectors
-  const locator = browser.getLocator(command.selector);
+  const locator = browser.getLocator(selector);
+  if (pick.index !== undefined) {
+    return [pick.index === -1 ? locator.last() : locator.nth(pick.index)];
+  }
+  if (!pick.all) {
+    return [locator];
+  }
+  const matches = await locator.all();
+  if (matches.length === 0) {
+    throw new CommandError(`No element matches "${selector}"`, 'not_found', selector);
+  }
+  return matches;
+}
+
+/** What a command did to `count` elements, with the count when --all was given */
+function withCount(count: number, pick: MatchPick, data: Record<string, unknown>) {
+  return pick.all ? { ...data, count } : data;
+}
 
+async function handleClick(command: ClickCommand, browser: BrowserManager): Promise<Response> {
+  const matches = await pickMatches(browser, command.selector, command);
   try {
-    await locator.click({
-      button: command.button,
-      clickCount: command.clickCount,
-      delay: command.delay,
-    });
+    for (const locator of matches) {
+      await locator.click({
+        button: command.button,
+        clickCount: command.clickCount,
+        delay: command.delay,
+      });
+    }
   } catch (error) {
     throw toAIFriendlyError(error, command.selector);
   }
 
-  return successResponse(command.id, { clicked: true });
+  return successResponse(command.id, withCount(matches.length, command, { clicked: true }));
 }
 
 async function handleType(command: TypeCommand, browser: BrowserManager): Promise<Response> {
@@ -533,14 +898,17 @@ async function handleType(command: TypeCommand, browser: BrowserManager): Promis
 
 async function handlePress(command: PressCommand, browser: BrowserManager): Promise<Response> {
   const page = browser.getPage();
+  const count = command.count ?? 1;
 
-  if (command.selector) {
-    await page.press(command.selector, command.key);
-  } else {
-    await page.keyboard.press(command.key);
+  for (let i = 0; i < count; i++) {
+    if (command.selector) {
+      await page.press(command.selector, command.key);
+    } else {
+      await page.keyboard.press(command.key);
+    }
   }
 
-  return successResponse(command.id, { pressed: true });
+  return successResponse(command.id, { pressed: true, count });
 }
 
 async function handleScreenshot(
@@ -551,7 +919,8 @@ async function handleScreenshot(
 
   const options: Parameters<Page['screenshot']>[0] = {
     fullPage: command.fullPage,
-    type: command.format ?? 'png',
+    type: command.format === 'jpeg' ? 'jpeg' : 'png',
+    clip: command.clip,
   };
 
   if (command.format === 'jpeg' && command.quality !== undefined) {
@@ -563,10 +932,47 @@ async function handleScreenshot(
     target = browser.getLocator(command.selector);
   }
 
+  let annotated: number | undefined;
+  if (command.annotateRefs) {
+    if (Object.keys(browser.getRefMap()).length === 0) {
+      throw new Error('No refs to annotate. Run snapshot first.');
+    }
+    annotated = await addRefAnnotations(browser);
+  }
+
   try {
+    let buffer: Buffer;
+    if (command.format === 'webp') {
+      // Playwright only encodes PNG/JPEG; WebP goes through CDP
+      let clip = command.clip;
+      if (command.selector) {
+        const locator = browser.getLocator(command.selector);
+        await locator.scrollIntoViewIfNeeded();
+        clip = (await locator.boundingBox()) ?? undefined;
+        if (!clip) {
+          throw new Error(`Element ${command.selector} is not visible`);
+        }
+      }
+      buffer = await captureWebp(browser, {
+        clip,
+        fullPage: command.fullPage && !command.selector,
+        quality: command.quality,
+      });
+    } else {
+      buffer = await target.screenshot(options);
+    }
+    if (annotated !== undefined) {
+      await removeRefAnnotations(browser);
+    }
+
+    // --stdout: hand the image back to the CLI instead of writing a file
+    if (command.stdout) {
+      return successResponse(command.id, { base64: buffer.toString('base64'), annotated });
+    }
+
     let savePath = command.path;
     if (!savePath) {
-      const ext = command.format === 'jpeg' ? 'jpg' : 'png';
+      const ext = command.format === 'jpeg' ? 'jpg' : (command.format ?? 'png');
       const timestamp = new Date().toISOString().replace(/[:.]/g, '-');
       const random = Math.random().toString(36).substring(2, 8);
       const filename = `screenshot-${timestamp}-${random}.${ext}`;
@@ -575,9 +981,12 @@ async function handleScreenshot(
       savePath = path.join(screenshotDir, filename);
     }
 
-    await target.screenshot({ ...options, path: savePath });
-    return successResponse(command.id, { path: savePath });
+    writeFileSync(savePath, buffer);
+    return successResponse(command.id, { path: savePath, annotated });
   } catch (error) {
+    if (annotated !== undefined) {
+      await removeRefAnnotations(browser);
+    }
     if (command.selector) {
       throw toAIFriendlyError(error, command.selector);
     }
@@ -585,6 +994,42 @@ async function handleScreenshot(
   }
 }
 
+/**
+ * Capture a WebP screenshot via CDP (Chromium only). Like Playwright, `clip` is relative to the
+ * viewport unless `fullPage` is set, in which case it is relative to the page.
+ */
+async function captureWebp(
+  browser: BrowserManager,
+  options: {
+    clip?: { x: number; y: number; width: number; height: number };
+    fullPage?: boolean;
+    quality?: number;
+  }
+): Promise<Buffer> {
+  let cdp;
+  try {
+    cdp = await browser.getCDPSession();
+  } catch {
+    throw new Error('WebP screenshots are only supported in Chromium');
+  }
+
+  const { cssContentSize, cssVisualViewport } = await cdp.send('Page.getLayoutMetrics');
+  let clip = options.clip;
+  if (clip && !options.fullPage) {
+    clip = { ...clip, x: clip.x + cssVisualViewport.pageX, y: clip.y + cssVisualViewport.pageY };
+  } else if (!clip && options.fullPage) {
+    clip = { x: 0, y: 0, width: cssContentSize.width, height: cssContentSize.height };
+  }
+
+  const { data } = await cdp.send('Page.captureScreenshot', {
+    format: 'webp',
+    quality: options.quality,
+    clip: clip ? { ...clip, scale: 1 } : undefined,
+    captureBeyondViewport: options.fullPage || clip !== undefined,
+  });
+  return Buffer.from(data, 'base64');
+}
+
 async function handleSnapshot(
   command: Command & {
     action: 'snapshot';
@@ -593,17 +1038,32 @@ async function handleSnapshot(
     maxDepth?: number;
     compact?: boolean;
     selector?: string;
+    format?: SnapshotFormat;
+    maxChars?: number;
+    page?: number;
+    coords?: boolean;
+    ifChanged?: string;
   },
   browser: BrowserManager
 ): Promise<Response<SnapshotData>> {
   // Use enhanced snapshot with refs and optional filtering
-  const { tree, refs } = await browser.getSnapshot({
+  const snapshotResult = await browser.getSnapshot({
     interactive: command.interactive,
     cursor: command.cursor,
     maxDepth: command.maxDepth,
     compact: command.compact,
     selector: command.selector,
   });
+  const { refs } = snapshotResult;
+  const tree = command.coords
+    ? addCoords(snapshotResult.tree, await refGeometry(browser, refs))
+    : snapshotResult.tree;
+  const hash = snapshotHash(tree);
+  if (command.ifChanged === hash) {
+    return successResponse(command.id, { unchanged: true, hash });
+  }
+  // Only reported to callers that asked, so plain snapshots stay as they were
+  const unchanged = command.ifChanged === undefined ? undefined : false;
 
   // Simplify refs for output (just role and name)
   const simpleRefs: Record<string, { role: string; name?: string }> = {};
@@ -611,9 +1071,129 @@ async function handleSnapshot(
     simpleRefs[ref] = { role: data.role, name: data.name };
   }
 
+  const refsData = Object.keys(simpleRefs).length > 0 ? simpleRefs : undefined;
+  if (command.format === 'json') {
+    return successResponse(command.id, {
+      nodes: parseSnapshotTree(tree),
+      refs: refsData,
+      hash,
+      unchanged,
+    });
+  }
+  const snapshot = tree ? formatSnapshot(tree, command.format ?? 'text') : 'Empty page';
+  if (command.maxChars === undefined && command.page === undefined) {
+    return successResponse(command.id, { snapshot, refs: refsData, hash, unchanged });
+  }
+  const { text, ...pagination } = paginateSnapshot(
+    snapshot,
+    command.maxChars ?? DEFAULT_SNAPSHOT_PAGE_CHARS,
+    command.page
+  );
   return successResponse(command.id, {
-    snapshot: tree || 'Empty page',
-    refs: Object.keys(simpleRefs).length > 0 ? simpleRefs : undefined,
+    snapshot: text,
+    refs: refsData,
+    pagination,
+    hash,
+    unchanged,
+  });
+}
+
+/**
+ * Where each ref's element is drawn, or null for those that aren't. Boxes are
+ * relative to the viewport, like a screenshot of it.
+ */
+async function refGeometry(
+  browser: BrowserManager,
+  refs: RefMap
+): Promise<Record<string, RefGeometry | null>> {
+  const page = browser.getPage();
+  const viewport =
+    page.viewportSize() ??
+    ((await page.evaluate('({ width: window.innerWidth, height: window.innerHeight })')) as {
+      width: number;
+      height: number;
+    });
+  const entries = await Promise.all(
+    Object.keys(refs).map(async (ref): Promise<[string, RefGeometry | null]> => {
+      const locator = browser.getLocator(`@${ref}`).first();
+      const box = await locator.boundingBox({ timeout: 1000 }).catch(() => null);
+      if (!box || box.width === 0 || box.height === 0) return [ref, null];
+      const inViewport =
+        box.x < viewport.width &&
+        box.y < viewport.height &&
+        box.x + box.width > 0 &&
+        box.y + box.height > 0;
+      const rounded = {
+        x: Math.round(box.x),
+        y: Math.round(box.y),
+        width: Math.round(box.width),
+        height: Math.round(box.height),
+      };
+      return [ref, { box: rounded, inViewport }];
+    })
+  );
+  return Object.fromEntries(entries);
+}
+
+async function handleSnapshotDiff(
+  command: SnapshotDiffCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const previous = browser.getLastSnapshot();
+  const { tree } = await browser.getSnapshot({
+    interactive: command.interactive,
+    cursor: command.cursor,
+    maxDepth: command.maxDepth,
+    compact: command.compact,
+    selector: command.selector,
+  });
+  // The new snapshot becomes the baseline, so repeated diffs only show what is new
+  return successResponse(command.id, {
+    changes: diffSnapshots(previous, tree),
+    initial: previous === '',
+  });
+}
+
+async function handleRead(command: ReadCommand, browser: BrowserManager): Promise<Response> {
+  const maxChars = command.maxChars ?? DEFAULT_READ_CHARS;
+  let reading = browser.getReading();
+  let range;
+  if (command.direction) {
+    if (!reading) {
+      throw new Error('Nothing is being read. Start with: read [selector]');
+    }
+    range =
+      command.direction === 'next'
+        ? chunkAfter(reading.items, reading.to, maxChars)
+        : chunkBefore(reading.items, reading.from, maxChars);
+  } else {
+    // A full snapshot, so the refs in what is read can be used right away
+    const { tree } = await browser.getSnapshot();
+    const items = readItems(tree);
+    let start = 0;
+    if (command.selector) {
+      let own: string;
+      try {
+        own = await browser.getLocator(command.selector).first().ariaSnapshot();
+      } catch (error) {
+        throw toAIFriendlyError(error, command.selector);
+      }
+      const [first] = readItems(own);
+      start = first ? findItem(items, first) : -1;
+      if (start === -1) {
+        throw new Error(`Nothing to read in ${command.selector}`);
+      }
+    }
+    reading = { items, from: start, to: start };
+    range = chunkAfter(items, start, maxChars);
+  }
+
+  browser.setReading({ items: reading.items, ...range });
+  return successResponse(command.id, {
+    text: reading.items.slice(range.from, range.to).map((item) => item.line).join('\n'),
+    from: range.from + 1,
+    to: range.to,
+    total: reading.items.length,
   });
 }
 
@@ -622,6 +1202,27 @@ async function handleEvaluate(
   browser: BrowserManager
 ): Promise<Response<EvaluateData>> {
   const page = browser.getPage();
+  const args = command.args ?? [];
+
+  if (command.selector) {
+    // The script is a function; it receives the element, then the args
+    // eslint-disable-next-line @typescript-eslint/no-implied-eval
+    const fn = new Function(`return (el, args) => (${command.script})(el, ...args)`)();
+    const locator = browser.getLocator(command.selector);
+    let result: unknown;
+    try {
+      result = await locator.evaluate(fn, args);
+    } catch (error) {
+      throw toAIFriendlyError(error, command.selector);
+    }
+    return successResponse(command.id, { result });
+  }
+
+  if (command.args) {
+    // Args are JSON values, so they can be inlined into the call
+    const result = await page.evaluate(`(${command.script})(...${JSON.stringify(args)})`);
+    return successResponse(command.id, { result });
+  }
 
   // Evaluate the script directly as a string expression
   const result = await page.evaluate(command.script);
@@ -629,29 +1230,153 @@ async function handleEvaluate(
   return successResponse(command.id, { result });
 }
 
-async function handleWait(command: WaitCommand, browser: BrowserManager): Promise<Response> {
+/**
+ * Wait `ms` like page.waitForTimeout, but stop early when the command is cancelled.
+ */
+function sleep(ms: number, signal?: AbortSignal): Promise<void> {
+  return new Promise((resolve) => {
+    const done = () => {
+      clearTimeout(timer);
+      signal?.removeEventListener('abort', done);
+      resolve();
+    };
+    const timer = setTimeout(done, ms);
+    signal?.addEventListener('abort', done, { once: true });
+  });
+}
+
+/**
+ * Settle like `promise`, or reject with the abort reason as soon as the command is
+ * cancelled. The Playwright wait behind `promise` runs out its own timeout unobserved.
+ */
+function untilAborted<T>(promise: Promise<T>, signal?: AbortSignal): Promise<T> {
+  if (!signal) return promise;
+  if (signal.aborted) return Promise.reject(signal.reason);
+  return new Promise((resolve, reject) => {
+    const abort = () => reject(signal.reason);
+    signal.addEventListener('abort', abort, { once: true });
+    promise.then(resolve, reject).finally(() => signal.removeEventListener('abort', abort));
+  });
+}
+
+async function handleWait(
+  command: WaitCommand,
+  browser: BrowserManager,
+  signal?: AbortSignal
+): Promise<Response> {
   const page = browser.getPage();
 
   if (command.selector) {
-    await page.waitForSelector(command.selector, {
-      state: command.state ?? 'visible',
-      timeout: command.timeout,
-    });
+    // Like waitForSelector, wait on the first match; refs and the selected frame apply
+    const locator = browser.getLocator(command.selector).first();
+    await untilAborted(
+      locator.waitFor({ state: command.state ?? 'visible', timeout: command.timeout }),
+      signal
+    );
   } else if (command.timeout) {
-    await page.waitForTimeout(command.timeout);
+    await sleep(command.timeout, signal);
   } else {
     // Default: wait for load state
-    await page.waitForLoadState('load');
+    await untilAborted(page.waitForLoadState('load'), signal);
   }
 
   return successResponse(command.id, { waited: true });
 }
 
+const EXPECT_DEFAULT_TIMEOUT = 5000;
+const EXPECT_POLL_INTERVAL = 100;
+
+/**
+ * Build a matcher for an `expect` pattern. `/source/flags` is a regular expression;
+ * otherwise URLs use the network log's globs and text/title match as a substring.
+ */
+function expectMatcher(kind: ExpectCommand['kind'], pattern: string): (actual: string) => boolean {
+  const regex = pattern.match(/^\/(.+)\/([dgimsuy]*)$/);
+  if (regex) {
+    const re = new RegExp(regex[1], regex[2]);
+    return (actual) => re.test(actual);
+  }
+  return kind === 'url' ? urlMatcher(pattern) : (actual) => actual.includes(pattern);
+}
+
+/**
+ * Read the current value an `expect` checks. Text is the whitespace-normalized
+ * inner text of all matching elements, or null when nothing matches yet.
+ */
+async function readExpectActual(
+  command: ExpectCommand,
+  browser: BrowserManager
+): Promise<string | boolean | null> {
+  switch (command.kind) {
+    case 'url':
+      return browser.getPage().url();
+    case 'title':
+      return await browser.getPage().title();
+    case 'visible':
+      return await browser.getLocator(command.selector!).isVisible();
+    case 'text': {
+      const texts = await browser.getLocator(command.selector!).allInnerTexts();
+      return texts.length > 0 ? texts.join(' ').replace(/\s+/g, ' ').trim() : null;
+    }
+  }
+}
+
+function expectFailure(command: ExpectCommand, actual: string | boolean | null, timeout: number) {
+  if (command.kind === 'visible') {
+    return `Expected ${command.selector} to be visible (timed out after ${timeout}ms)`;
+  }
+  const subject = command.kind === 'text' ? `text of ${command.selector}` : `page ${command.kind}`;
+  return [
+    `Expected ${subject} to match (timed out after ${timeout}ms)`,
+    `  expected: ${JSON.stringify(command.expected)}`,
+    `  actual:   ${actual === null ? '(no matching element)' : JSON.stringify(actual)}`,
+  ].join('\n');
+}
+
+async function handleExpect(
+  command: ExpectCommand,
+  browser: BrowserManager,
+  signal?: AbortSignal
+): Promise<Response> {
+  if ((command.kind === 'text' || command.kind === 'visible') && !command.selector) {
+    throw new Error(`expect ${command.kind} requires a selector`);
+  }
+  if (command.kind !== 'visible' && command.expected === undefined) {
+    throw new Error(`expect ${command.kind} requires an expected value`);
+  }
+
+  const matcher =
+    command.kind === 'visible' ? null : expectMatcher(command.kind, command.expected!);
+  const passes = (actual: string | boolean | null) =>
+    matcher ? typeof actual === 'string' && matcher(actual) : actual === true;
+
+  const timeout = command.timeout ?? EXPECT_DEFAULT_TIMEOUT;
+  const deadline = Date.now() + timeout;
+  let actual = await readExpectActual(command, browser);
+  while (!passes(actual)) {
+    signal?.throwIfAborted();
+    if (Date.now() >= deadline) {
+      throw new Error(expectFailure(command, actual, timeout));
+    }
+    await browser.getPage().waitForTimeout(EXPECT_POLL_INTERVAL);
+    actual = await readExpectActual(command, browser);
+  }
+
+  return successResponse(command.id, { passed: true, kind: command.kind, actual });
+}
+
 async function handleScroll(command: ScrollCommand, browser: BrowserManager): Promise<Response> {
-  const page = browser.getPage();
+  const frame = browser.getFrame();
 
-  if (command.selector) {
-    const element = page.locator(command.selector);
+  if (command.position) {
+    // scrollingElement covers both standards and quirks mode documents
+    const top =
+      command.position === 'top'
+        ? '0'
+        : '(document.scrollingElement || document.documentElement).scrollHeight';
+    await frame.evaluate(`window.scrollTo(window.scrollX, ${top})`);
+  } else if (command.selector) {
+    const element = browser.getLocator(command.selector);
     await element.scrollIntoViewIfNeeded();
 
     if (command.x !== undefined || command.y !== undefined) {
@@ -685,58 +1410,89 @@ async function handleScroll(command: ScrollCommand, browser: BrowserManager): Pr
       }
     }
 
-    await page.evaluate(`window.scrollBy(${deltaX}, ${deltaY})`);
+    await frame.evaluate(`window.scrollBy(${deltaX}, ${deltaY})`);
   }
 
   return successResponse(command.id, { scrolled: true });
 }
 
 async function handleSelect(command: SelectCommand, browser: BrowserManager): Promise<Response> {
-  const locator = browser.getLocator(command.selector);
-  const values = Array.isArray(command.values) ? command.values : [command.values];
+  const given = [command.values, command.label, command.index].filter((v) => v !== undefined);
+  if (given.length !== 1) {
+    throw new Error('select needs exactly one of: values, label, index');
+  }
+
+  const toArray = (v: string | string[]) => (Array.isArray(v) ? v : [v]);
+  const options =
+    command.values !== undefined
+      ? toArray(command.values)
+      : command.label !== undefined
+        ? toArray(command.label).map((label) => ({ label }))
+        : { index: command.index };
 
+  const locator = browser.getLocator(command.selector);
+  let selected: string[];
   try {
-    await locator.selectOption(values);
+    selected = await locator.selectOption(options);
   } catch (error) {
     throw toAIFriendlyError(error, command.selector);
   }
 
-  return successResponse(command.id, { selected: values });
+  return successResponse(command.id, { selected });
 }
 
 async function handleHover(command: HoverCommand, browser: BrowserManager): Promise<Response> {
-  const locator = browser.getLocator(command.selector);
+  const matches = await pickMatches(browser, command.selector, command);
   try {
-    await locator.hover();
+    for (const locator of matches) {
+      await locator.hover();
+    }
   } catch (error) {
     throw toAIFriendlyError(error, command.selector);
   }
 
-  return successResponse(command.id, { hovered: true });
+  return successResponse(command.id, withCount(matches.length, command, { hovered: true }));
 }
 
 async function handleContent(
   command: ContentCommand,
   browser: BrowserManager
-): Promise<Response<ContentData>> {
-  const page = browser.getPage();
-
+): Promise<Response<ContentData | { path: string; bytes: number }>> {
   let html: string;
   if (command.selector) {
-    html = await page.locator(command.selector).innerHTML();
+    const locator = browser.getLocator(command.selector);
+    try {
+      html = command.outer
+        ? await locator.evaluate((el) => el.outerHTML)
+        : await locator.innerHTML();
+    } catch (error) {
+      throw toAIFriendlyError(error, command.selector);
+    }
   } else {
-    html = await page.content();
+    html = await browser.getFrame().content();
   }
 
+  if (command.path) {
+    mkdirSync(path.dirname(command.path), { recursive: true });
+    writeFileSync(command.path, html);
+    return successResponse(command.id, { path: command.path, bytes: Buffer.byteLength(html) });
+  }
   return successResponse(command.id, { html });
 }
 
-async function handleClose(
-  command: Command & { action: 'close' },
-  browser: BrowserManager
-): Promise<Response> {
+async function handleClose(command: CloseCommand, browser: BrowserManager): Promise<Response> {
+  // Saved before anything is torn down, so a failed save leaves the browser as it was
+  const saveState = browser.isLaunched() ? command.saveState : undefined;
+  if (saveState) {
+    mkdirSync(path.dirname(saveState), { recursive: true });
+    await browser.saveStorageState(saveState);
+  }
   await browser.close();
-  return successResponse(command.id, { closed: true });
+  return successResponse(command.id, {
+    closed: true,
+    keepDaemon: command.keepDaemon === true,
+    ...(saveState && { statePath: saveState }),
+  });
 }
 
 async function handleTabNew(
@@ -793,6 +1549,35 @@ async function handleWindowNew(
   return successResponse(command.id, result);
 }
 
+async function handleContextNew(
+  command: ContextNewCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  return successResponse(command.id, await browser.newContext(command.name));
+}
+
+async function handleContextList(
+  command: Command & { action: 'context_list' },
+  browser: BrowserManager
+): Promise<Response<ContextListData>> {
+  return successResponse(command.id, { contexts: browser.listContexts() });
+}
+
+async function handleContextSwitch(
+  command: ContextSwitchCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const result = await browser.switchContext(command.name);
+  return successResponse(command.id, { ...result, title: await browser.getPage().title() });
+}
+
+async function handleContextClose(
+  command: ContextCloseCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  return successResponse(command.id, await browser.closeContext(command.name));
+}
+
 // New handlers for enhanced Playwright parity
 
 async function handleFill(command: FillCommand, browser: BrowserManager): Promise<Response> {
@@ -800,29 +1585,164 @@ async function handleFill(command: FillCommand, browser: BrowserManager): Promis
   try {
     await locator.fill(command.value);
   } catch (error) {
-    throw toAIFriendlyError(error, command.selector);
+    const friendly = toAIFriendlyError(error, command.selector);
+    // Playwright call logs can quote the value
+    if (command.secret && command.value) {
+      friendly.message = friendly.message.split(command.value).join('********');
+    }
+    throw friendly;
   }
   return successResponse(command.id, { filled: true });
 }
 
-async function handleCheck(command: CheckCommand, browser: BrowserManager): Promise<Response> {
-  const locator = browser.getLocator(command.selector);
+// How long one `fill --form` field may take, so one stuck field doesn't hold up the rest
+const FORM_FIELD_TIMEOUT = 5000;
+
+/**
+ * The element a `fill --form` key names: a selector or ref, else the field
+ * with that label, else the one with that name attribute. Null if none match.
+ */
+async function formField(browser: BrowserManager, key: string): Promise<Locator | null> {
+  const frame = browser.getFrame();
+  const candidates = [
+    () => browser.getLocator(key),
+    () => frame.getByLabel(key),
+    () => frame.locator(`[name=${JSON.stringify(key)}]`),
+  ];
+  for (const candidate of candidates) {
+    try {
+      const locator = candidate();
+      if ((await locator.count()) > 0) return locator;
+    } catch {
+      // Not a valid selector; try it as a label
+    }
+  }
+  return null;
+}
+
+/**
+ * Fill one field found by formField. Returns why it was skipped, or null
+ * once it's filled.
+ */
+async function fillFormField(
+  locator: Locator,
+  key: string,
+  value: string | number | boolean
+): Promise<string | null> {
+  const count = await locator.count();
+  if (count > 1) return `matched ${count} elements`;
+
+  const options = { timeout: FORM_FIELD_TIMEOUT };
   try {
-    await locator.check();
+    const kind = await locator.evaluate((el) => {
+      const type = el.tagName === 'INPUT' ? el.getAttribute('type')?.toLowerCase() : null;
+      if (type === 'checkbox' || type === 'radio') return 'checkable';
+      const role = el.getAttribute('role');
+      if (role && ['checkbox', 'radio', 'switch'].includes(role)) return 'checkable';
+      return el.tagName === 'SELECT' ? 'select' : 'text';
+    });
+    if (!(await locator.isEnabled())) return 'disabled';
+
+    if (kind === 'checkable') {
+      if (typeof value !== 'boolean') return 'checkboxes and radio buttons take true or false';
+      if (value !== (await locator.isChecked())) await locator.setChecked(value, options);
+    } else if (kind === 'select') {
+      await locator.selectOption(String(value), options);
+    } else {
+      if (!(await locator.isEditable())) return 'read-only';
+      await locator.fill(String(value), options);
+    }
   } catch (error) {
-    throw toAIFriendlyError(error, command.selector);
+    return toAIFriendlyError(error, key).message;
+  }
+  return null;
+}
+
+async function handleFillForm(
+  command: FillFormCommand,
+  browser: BrowserManager
+): Promise<Response<FillFormData>> {
+  const data: FillFormData = { filled: [], skipped: [], notFound: [] };
+  for (const { field, value } of command.fields) {
+    const locator = await formField(browser, field);
+    if (!locator) {
+      data.notFound.push(field);
+      continue;
+    }
+    const reason = await fillFormField(locator, field, value);
+    if (reason === null) {
+      data.filled.push(field);
+    } else {
+      data.skipped.push({ field, reason });
+    }
+  }
+  return successResponse(command.id, data);
+}
+
+async function handleCheck(command: CheckCommand, browser: BrowserManager): Promise<Response> {
+  const matches = await pickMatches(browser, command.selector, command);
+  let changed = 0;
+  for (const locator of matches) {
+    if ((await setChecked(locator, command.selector, true)).changed) changed++;
   }
-  return successResponse(command.id, { checked: true });
+  return successResponse(
+    command.id,
+    withCount(matches.length, command, { checked: true, changed: changed > 0 })
+  );
 }
 
 async function handleUncheck(command: UncheckCommand, browser: BrowserManager): Promise<Response> {
   const locator = browser.getLocator(command.selector);
+  return successResponse(command.id, await setChecked(locator, command.selector, false));
+}
+
+/**
+ * Bring a checkbox or radio button to the requested state instead of toggling it.
+ * Native inputs and ARIA checkbox/radio/switch roles are accepted.
+ */
+async function setChecked(
+  locator: Locator,
+  selector: string,
+  checked: boolean
+): Promise<{ checked: boolean; changed: boolean }> {
+  let kind: string | null;
+  let wasChecked = false;
   try {
-    await locator.uncheck();
+    kind = await locator.evaluate((el) => {
+      const type = el.tagName === 'INPUT' ? el.getAttribute('type')?.toLowerCase() : null;
+      if (type === 'checkbox' || type === 'radio') {
+        return type;
+      }
+      const role = el.getAttribute('role');
+      return role && ['checkbox', 'radio', 'switch', 'menuitemcheckbox'].includes(role)
+        ? role
+        : null;
+    });
+    if (kind !== null) {
+      wasChecked = await locator.isChecked();
+    }
   } catch (error) {
-    throw toAIFriendlyError(error, command.selector);
+    throw toAIFriendlyError(error, selector);
+  }
+
+  if (kind === null) {
+    throw new Error(`Element "${selector}" is not a checkbox or radio button`);
   }
-  return successResponse(command.id, { unchecked: true });
+  if (wasChecked === checked) {
+    return { checked, changed: false };
+  }
+  if (!checked && kind === 'radio') {
+    throw new Error(
+      `Cannot uncheck radio button "${selector}". Check another option in the group instead.`
+    );
+  }
+
+  try {
+    await locator.setChecked(checked);
+  } catch (error) {
+    throw toAIFriendlyError(error, selector);
+  }
+  return { checked, changed: true };
 }
 
 async function handleUpload(command: UploadCommand, browser: BrowserManager): Promise<Response> {
@@ -859,9 +1779,50 @@ async function handleFocus(command: FocusCommand, browser: BrowserManager): Prom
   return successResponse(command.id, { focused: true });
 }
 
+async function handleBlur(command: BlurCommand, browser: BrowserManager): Promise<Response> {
+  const locator = browser.getLocator(command.selector);
+  try {
+    await locator.blur();
+  } catch (error) {
+    throw toAIFriendlyError(error, command.selector);
+  }
+  return successResponse(command.id, { blurred: true });
+}
+
 async function handleDrag(command: DragCommand, browser: BrowserManager): Promise<Response> {
-  const frame = browser.getFrame();
-  await frame.dragAndDrop(command.source, command.target);
+  const source = browser.getLocator(command.source);
+  const target = browser.getLocator(command.target);
+
+  if (command.steps === undefined) {
+    try {
+      await source.dragTo(target);
+    } catch (error) {
+      throw toAIFriendlyError(error, command.source);
+    }
+    return successResponse(command.id, { dragged: true });
+  }
+
+  // Move the mouse in small increments so pages that track pointer movement
+  // (sliders, sortable lists) see intermediate positions
+  const page = browser.getPage();
+  try {
+    await source.hover();
+  } catch (error) {
+    throw toAIFriendlyError(error, command.source);
+  }
+  let box: { x: number; y: number; width: number; height: number } | null;
+  try {
+    await target.scrollIntoViewIfNeeded();
+    box = await target.boundingBox();
+  } catch (error) {
+    throw toAIFriendlyError(error, command.target);
+  }
+  if (!box) {
+    throw new Error(`Element "${command.target}" is not visible`);
+  }
+  await page.mouse.down();
+  await page.mouse.move(box.x + box.width / 2, box.y + box.height / 2, { steps: command.steps });
+  await page.mouse.up();
   return successResponse(command.id, { dragged: true });
 }
 
@@ -870,8 +1831,20 @@ async function handleFrame(command: FrameCommand, browser: BrowserManager): Prom
     selector: command.selector,
     name: command.name,
     url: command.url,
+    index: command.index,
+  });
+  const frame = browser.getFrame();
+  return successResponse(command.id, {
+    switched: true,
+    frame: { name: frame.name(), url: frame.url() },
   });
-  return successResponse(command.id, { switched: true });
+}
+
+async function handleFrameList(
+  command: FrameListCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  return successResponse(command.id, { frames: browser.listFrames() });
 }
 
 async function handleMainFrame(
@@ -882,6 +1855,30 @@ async function handleMainFrame(
   return successResponse(command.id, { switched: true });
 }
 
+async function handleFind(command: FindCommand, browser: BrowserManager): Promise<Response> {
+  // Search a fresh snapshot so the returned refs are usable in the next command
+  const { refs } = await browser.getSnapshot();
+  const matches = findRefs(refs, command.query, { role: command.role, limit: command.limit });
+  return successResponse(command.id, { matches });
+}
+
+async function handleRefs(command: RefsCommand, browser: BrowserManager): Promise<Response> {
+  const refs = listRefs(browser.getRefMap(), command.filter);
+  return successResponse(command.id, { refs });
+}
+
+async function handleVisibleElements(
+  command: VisibleElementsCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  // Like find, a fresh snapshot so the refs are usable in the next command
+  const { refs } = await browser.getSnapshot({ interactive: true });
+  const geometry = await refGeometry(browser, refs);
+  return successResponse(command.id, {
+    elements: visibleElements(refs, geometry, command.roles),
+  });
+}
+
 async function handleGetByRole(
   command: GetByRoleCommand,
   browser: BrowserManager
@@ -922,6 +1919,62 @@ async function handleGetByText(
   }
 }
 
+// Roles of controls named by their label, tried before plain text
+const NAMED_ROLES = [
+  'button',
+  'link',
+  'menuitem',
+  'tab',
+  'checkbox',
+  'radio',
+  'switch',
+  'option',
+] as const;
+
+async function handleGetByName(
+  command: GetByNameCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const frame = browser.getFrame();
+  const options = { name: command.text, exact: command.exact };
+  let named = frame.getByRole(NAMED_ROLES[0], options);
+  for (const role of NAMED_ROLES.slice(1)) named = named.or(frame.getByRole(role, options));
+  // Text inside a button matches too, so controls come first to click the button itself
+  const matches =
+    (await named.count()) > 0 ? named : frame.getByText(command.text, { exact: command.exact });
+
+  if (command.index === undefined) {
+    const count = await matches.count();
+    if (count > 1) {
+      throw new CommandError(
+        `Text "${command.text}" matched ${count} elements. ` +
+          `Use --exact, or --nth <n> to pick one (0 is the first).`,
+        'ambiguous',
+        command.text
+      );
+    }
+  }
+  const locator =
+    command.index === undefined
+      ? matches
+      : command.index === -1
+        ? matches.last()
+        : matches.nth(command.index);
+
+  try {
+    switch (command.subaction) {
+      case 'click':
+        await locator.click();
+        return successResponse(command.id, { clicked: true });
+      case 'hover':
+        await locator.hover();
+        return successResponse(command.id, { hovered: true });
+    }
+  } catch (error) {
+    throw toAIFriendlyError(error, command.text);
+  }
+}
+
 async function handleGetByLabel(
   command: GetByLabelCommand,
   browser: BrowserManager
@@ -966,6 +2019,13 @@ async function handleCookiesGet(
   const page = browser.getPage();
   const context = page.context();
   const cookies = await context.cookies(command.urls);
+  if (command.name !== undefined) {
+    const matching = cookies.filter((cookie) => cookie.name === command.name);
+    if (matching.length === 0) {
+      throw new Error(`Cookie not found: ${command.name}`);
+    }
+    return successResponse(command.id, { cookies: matching });
+  }
   return successResponse(command.id, { cookies });
 }
 
@@ -997,6 +2057,38 @@ async function handleCookiesClear(
   return successResponse(command.id, { cleared: true });
 }
 
+async function handleCookiesExport(
+  command: CookiesExportCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const cookies = await browser.getPage().context().cookies();
+  mkdirSync(path.dirname(command.path), { recursive: true });
+  writeFileSync(command.path, JSON.stringify({ cookies }, null, 2));
+  return successResponse(command.id, { path: command.path, count: cookies.length });
+}
+
+async function handleCookiesImport(
+  command: CookiesImportCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  let parsed: unknown;
+  try {
+    parsed = JSON.parse(readFileSync(command.path, 'utf-8'));
+  } catch (error) {
+    const message = error instanceof Error ? error.message : String(error);
+    throw new Error(`Failed to read cookies from ${command.path}: ${message}`);
+  }
+  // Accept a bare array, a `cookies export` file, or a Playwright storage state file
+  const cookies = Array.isArray(parsed)
+    ? parsed
+    : (parsed as { cookies?: unknown } | null)?.cookies;
+  if (!Array.isArray(cookies)) {
+    throw new Error(`No cookies array found in ${command.path}`);
+  }
+  await browser.getPage().context().addCookies(cookies);
+  return successResponse(command.id, { imported: cookies.length });
+}
+
 async function handleStorageGet(
   command: StorageGetCommand,
   browser: BrowserManager
@@ -1036,6 +2128,17 @@ async function handleStorageSet(
   return successResponse(command.id, { set: true });
 }
 
+async function handleStorageRemove(
+  command: StorageRemoveCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  const storageType = command.type === 'local' ? 'localStorage' : 'sessionStorage';
+
+  await page.evaluate(`${storageType}.removeItem(${JSON.stringify(command.key)})`);
+  return successResponse(command.id, { removed: command.key });
+}
+
 async function handleStorageClear(
   command: StorageClearCommand,
   browser: BrowserManager
@@ -1047,52 +2150,238 @@ async function handleStorageClear(
   return successResponse(command.id, { cleared: true });
 }
 
-async function handleDialog(command: DialogCommand, browser: BrowserManager): Promise<Response> {
-  browser.setDialogHandler(command.response, command.promptText);
-  return successResponse(command.id, { handler: 'set', response: command.response });
+async function handleDialog(command: DialogCommand, browser: BrowserManager): Promise<Response> {
+  const dialog = await browser.respondToDialog(command.response, command.promptText);
+  return successResponse(command.id, {
+    response: command.response,
+    ...(dialog ? { dialog } : { handler: 'next' }),
+  });
+}
+
+async function handleDialogAuto(
+  command: DialogAutoCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  browser.setDialogPolicy(command.response, command.promptText);
+  return successResponse(command.id, {
+    policy: command.response,
+    pending: browser.getPendingDialogs(),
+  });
+}
+
+async function handlePdf(command: PdfCommand, browser: BrowserManager): Promise<Response> {
+  const page = browser.getPage();
+
+  let savePath = command.path;
+  if (!savePath) {
+    const timestamp = new Date().toISOString().replace(/[:.]/g, '-');
+    const random = Math.random().toString(36).substring(2, 8);
+    const pdfDir = path.join(getAppDir(), 'tmp', 'pdfs');
+    mkdirSync(pdfDir, { recursive: true });
+    savePath = path.join(pdfDir, `page-${timestamp}-${random}.pdf`);
+  }
+
+  await page.pdf({
+    path: savePath,
+    format: command.format ?? 'Letter',
+    landscape: command.landscape,
+    margin: command.margin,
+  });
+  return successResponse(command.id, { path: savePath });
+}
+
+async function handleSave(command: SaveCommand, browser: BrowserManager): Promise<Response> {
+  if (!command.path === !command.htmlDir) {
+    throw new Error('save needs exactly one of: path, htmlDir');
+  }
+  let cdp;
+  try {
+    cdp = await browser.getCDPSession();
+  } catch {
+    throw new Error('Saving pages is only supported in Chromium');
+  }
+
+  if (command.htmlDir) {
+    const { file, resources } = await saveHtmlDir(browser.getPage(), cdp, command.htmlDir);
+    return successResponse(command.id, { path: file, resources });
+  }
+  const savePath = command.path as string;
+  const { data } = await cdp.send('Page.captureSnapshot', { format: 'mhtml' });
+  mkdirSync(path.dirname(savePath), { recursive: true });
+  writeFileSync(savePath, data);
+  return successResponse(command.id, { path: savePath });
+}
+
+// Network & Request handlers
+
+async function handleRoute(command: RouteCommand, browser: BrowserManager): Promise<Response> {
+  await browser.addRoute(command.url, {
+    response: command.response,
+    abort: command.abort,
+  });
+  return successResponse(command.id, { routed: command.url });
+}
+
+async function handleUnroute(
+  command: Command & { action: 'unroute'; url?: string },
+  browser: BrowserManager
+): Promise<Response> {
+  await browser.removeRoute(command.url);
+  return successResponse(command.id, { unrouted: command.url ?? 'all' });
+}
+
+async function handleRequests(
+  command: RequestsCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  if (command.clear) {
+    browser.clearRequests();
+    return successResponse(command.id, { cleared: true });
+  }
+
+  // Start tracking if not already
+  browser.startRequestTracking();
+
+  const requests = browser.getRequests(command.filter);
+  return successResponse(command.id, { requests });
+}
+
+async function handleMockAdd(command: MockAddCommand, browser: BrowserManager): Promise<Response> {
+  const mock = await browser.addMock({
+    url: command.url,
+    status: command.status ?? 200,
+    body: Buffer.from(command.body ?? '', 'base64'),
+    contentType: command.contentType,
+    headers: command.headers,
+  });
+  return successResponse(command.id, { mock });
+}
+
+async function handleMockList(
+  command: MockListCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  return successResponse(command.id, { mocks: browser.listMocks() });
+}
+
+async function handleMockRemove(
+  command: MockRemoveCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  await browser.removeMock(command.mockId);
+  return successResponse(command.id, { removed: command.mockId });
+}
+
+async function handleFetch(command: FetchCommand, browser: BrowserManager): Promise<Response> {
+  // Use a string function body to avoid TypeScript transpilation issues
+  const scriptBody = `async ({ url, method, headers, body }) => {
+    const TEXT = /^text\\/|json|xml|javascript|ecmascript|x-www-form-urlencoded/i;
+    const init = { method, headers, credentials: 'include' };
+    if (body !== undefined) {
+      init.body = Uint8Array.from(atob(body), (c) => c.charCodeAt(0));
+    }
+    const response = await fetch(url, init);
+    const bytes = new Uint8Array(await response.arrayBuffer());
+    const contentType = response.headers.get('content-type') || '';
+    const result = {
+      status: response.status,
+      statusText: response.statusText,
+      url: response.url,
+      headers: Object.fromEntries(response.headers.entries()),
+    };
+    if (!contentType || TEXT.test(contentType)) {
+      result.body = new TextDecoder().decode(bytes);
+    } else {
+      // Binary bodies travel base64-encoded
+      let binary = '';
+      for (let i = 0; i < bytes.length; i += 0x8000) {
+        binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+      }
+      result.body = btoa(binary);
+      result.base64 = true;
+    }
+    return result;
+  }`;
+  // eslint-disable-next-line @typescript-eslint/no-implied-eval
+  const fn = new Function('return ' + scriptBody)();
+  const result: {
+    status: number;
+    statusText: string;
+    url: string;
+    headers: Record<string, string>;
+    body: string;
+    base64?: boolean;
+  } = await browser.getFrame().evaluate(fn, {
+    url: command.url,
+    method: command.method?.toUpperCase() ?? (command.body !== undefined ? 'POST' : 'GET'),
+    headers: command.headers ?? {},
+    body: command.body,
+  });
+
+  if (command.path) {
+    const data = Buffer.from(result.body, result.base64 ? 'base64' : 'utf8');
+    mkdirSync(path.dirname(command.path), { recursive: true });
+    writeFileSync(command.path, data);
+    return successResponse(command.id, {
+      status: result.status,
+      statusText: result.statusText,
+      url: result.url,
+      headers: result.headers,
+      path: command.path,
+      bytes: data.length,
+    });
+  }
+  return successResponse(command.id, result);
 }
 
-async function handlePdf(command: PdfCommand, browser: BrowserManager): Promise<Response> {
-  const page = browser.getPage();
-  await page.pdf({
-    path: command.path,
-    format: command.format ?? 'Letter',
-  });
-  return successResponse(command.id, { path: command.path });
+async function handleBlock(command: BlockCommand, browser: BrowserManager): Promise<Response> {
+  const blocked = await browser.block(command.patterns);
+  return successResponse(command.id, { blocked });
 }
 
-// Network & Request handlers
+async function handleUnblock(command: UnblockCommand, browser: BrowserManager): Promise<Response> {
+  const blocked = await browser.unblock(command.patterns);
+  return successResponse(command.id, { blocked });
+}
 
-async function handleRoute(command: RouteCommand, browser: BrowserManager): Promise<Response> {
-  await browser.addRoute(command.url, {
-    response: command.response,
-    abort: command.abort,
-  });
-  return successResponse(command.id, { routed: command.url });
+async function handleNetworkLog(
+  command: NetworkLogCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const entries = browser.getNetworkLog(command.filter, command.since);
+  return successResponse(command.id, { entries });
 }
 
-async function handleUnroute(
-  command: Command & { action: 'unroute'; url?: string },
+async function handleNetworkCapture(
+  command: NetworkCaptureCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  await browser.removeRoute(command.url);
-  return successResponse(command.id, { unrouted: command.url ?? 'all' });
+  const limit = command.limit ?? DEFAULT_CAPTURE_LIMIT;
+  browser.startCapture(command.url, limit);
+  return successResponse(command.id, { capturing: command.url, limit });
 }
 
-async function handleRequests(
-  command: RequestsCommand,
+async function handleNetworkCaptureStop(
+  command: NetworkCaptureStopCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  if (command.clear) {
-    browser.clearRequests();
-    return successResponse(command.id, { cleared: true });
-  }
+  return successResponse(command.id, { stopped: browser.stopCapture() });
+}
 
-  // Start tracking if not already
-  browser.startRequestTracking();
+async function handleNetworkShow(
+  command: NetworkShowCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const exchange = browser.getCapture(command.requestId);
+  if (exchange) return successResponse(command.id, exchange);
 
-  const requests = browser.getRequests(command.filter);
-  return successResponse(command.id, { requests });
+  const entry = browser.getNetworkLog().find((e) => e.id === command.requestId);
+  const reason = !entry
+    ? 'it is not in the network log'
+    : entry.captured && entry.duration === null
+      ? 'it is still in flight'
+      : 'it was not captured. Start a capture first: network capture <url-glob>';
+  throw new Error(`No body for request ${command.requestId}: ${reason}`);
 }
 
 async function handleDownload(
@@ -1103,6 +2392,7 @@ async function handleDownload(
   const locator = browser.getLocator(command.selector);
 
   const [download] = await Promise.all([page.waitForEvent('download'), locator.click()]);
+  browser.claimDownload(download);
 
   await download.saveAs(command.path);
   return successResponse(command.id, {
@@ -1126,10 +2416,11 @@ async function handlePermissions(
   command: PermissionsCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  await browser.setPermissions(command.permissions, command.grant);
+  await browser.setPermissions(command.permissions, command.grant, command.origin);
   return successResponse(command.id, {
     permissions: command.permissions,
     granted: command.grant,
+    origin: command.origin ?? null,
   });
 }
 
@@ -1138,9 +2429,13 @@ async function handleViewport(
   browser: BrowserManager
 ): Promise<Response> {
   await browser.setViewport(command.width, command.height);
+  if (command.deviceScaleFactor) {
+    await browser.setDeviceScaleFactor(command.deviceScaleFactor, command.width, command.height);
+  }
   return successResponse(command.id, {
     width: command.width,
     height: command.height,
+    deviceScaleFactor: command.deviceScaleFactor,
   });
 }
 
@@ -1148,17 +2443,20 @@ async function handleUserAgent(
   command: Command & { action: 'useragent'; userAgent: string },
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  const context = page.context();
-  // Note: Can't change user agent after context is created, but we can for new pages
-  return successResponse(command.id, {
-    note: 'User agent can only be set at launch time. Use device command instead.',
-  });
+  await browser.setUserAgent(command.userAgent);
+  return successResponse(command.id, { userAgent: command.userAgent });
 }
 
 async function handleDevice(command: DeviceCommand, browser: BrowserManager): Promise<Response> {
-  const device = browser.getDevice(command.device);
-  if (!device) {
+  const name = browser.resolveDeviceName(command.device);
+  const device = name ? browser.getDevice(name) : undefined;
+  if (!name || !device) {
+    const query = command.device.toLowerCase();
+    const similar = browser.listDevices().filter((n) => n.toLowerCase().includes(query));
+    if (similar.length > 0) {
+      const suggestions = similar.slice(0, 10).join(', ');
+      throw new Error(`Unknown device: ${command.device}. Did you mean: ${suggestions}?`);
+    }
     const available = browser.listDevices().slice(0, 10).join(', ');
     throw new Error(`Unknown device: ${command.device}. Available: ${available}...`);
   }
@@ -1184,8 +2482,14 @@ async function handleDevice(command: DeviceCommand, browser: BrowserManager): Pr
     }
   }
 
+  try {
+    await browser.emulateUserAgent(device.userAgent, device.hasTouch);
+  } catch {
+    // CDP is Chromium-only; elsewhere the launch-time user agent stays
+  }
+
   return successResponse(command.id, {
-    device: command.device,
+    device: name,
     viewport: device.viewport,
     userAgent: device.userAgent,
     deviceScaleFactor: device.deviceScaleFactor,
@@ -1224,7 +2528,38 @@ async function handleUrl(
   browser: BrowserManager
 ): Promise<Response> {
   const page = browser.getPage();
-  return successResponse(command.id, { url: page.url() });
+  const url = page.url();
+  return successResponse(command.id, command.parsed ? { url, ...urlParts(url) } : { url });
+}
+
+/**
+ * The parts of `href` for `get url --parsed`. Query parameters are decoded,
+ * and one that appears more than once is an array of its values in order.
+ * `port` is null unless the URL gives one.
+ */
+export function urlParts(href: string): {
+  scheme: string;
+  host: string;
+  port: number | null;
+  path: string;
+  query: Record<string, string | string[]>;
+  fragment: string | null;
+} {
+  const url = new URL(href);
+  const query: Record<string, string | string[]> = {};
+  for (const [key, value] of url.searchParams) {
+    const seen = query[key];
+    if (seen === undefined) query[key] = value;
+    else query[key] = Array.isArray(seen) ? [...seen, value] : [seen, value];
+  }
+  return {
+    scheme: url.protocol.replace(/:$/, ''),
+    host: url.hostname,
+    port: url.port ? Number(url.port) : null,
+    path: url.pathname,
+    query,
+    fragment: url.hash ? url.hash.slice(1) : null,
+  };
 }
 
 async function handleTitle(
@@ -1245,12 +2580,60 @@ async function handleGetAttribute(
   return successResponse(command.id, { attribute: command.attribute, value });
 }
 
-async function handleGetText(command: GetTextCommand, browser: BrowserManager): Promise<Response> {
+/**
+ * The name `getPropertyValue` takes for a property written as in CSS
+ * (`font-size`), in camelCase (`fontSize`) or as a custom property (`--accent`)
+ * @internal Exported for testing
+ */
+export function cssPropertyName(property: string): string {
+  if (property.startsWith('--')) return property;
+  return property.replace(/[A-Z]/g, (c) => `-${c.toLowerCase()}`);
+}
+
+async function handleComputedStyle(
+  command: ComputedStyleCommand,
+  browser: BrowserManager
+): Promise<Response> {
   const locator = browser.getLocator(command.selector);
+  let values: string[];
+  try {
+    values = await locator.evaluate((el, names) => {
+      const style = getComputedStyle(el);
+      return names.map((name) => style.getPropertyValue(name));
+    }, command.properties.map(cssPropertyName));
+  } catch (error) {
+    throw toAIFriendlyError(error, command.selector);
+  }
+  // Keyed as asked for; an unknown property has no value
+  const styles = Object.fromEntries(
+    command.properties.map((property, i) => [property, values[i] || null])
+  );
+  return successResponse(command.id, { styles });
+}
+
+async function handleGetText(command: GetTextCommand, browser: BrowserManager): Promise<Response> {
+  if (command.all) {
+    // Every match's text, in document order; none is an empty list, not an error
+    const texts = await browser.getLocator(command.selector).allTextContents();
+    return successResponse(command.id, { texts });
+  }
+  const [locator] = await pickMatches(browser, command.selector, command);
   const text = await locator.textContent();
   return successResponse(command.id, { text });
 }
 
+async function handleArticle(command: ArticleCommand, browser: BrowserManager): Promise<Response> {
+  if (!command.selector) {
+    return successResponse(command.id, await extractArticle(browser.getFrame()));
+  }
+  try {
+    const article = await extractArticle(browser.getLocator(command.selector));
+    return successResponse(command.id, article);
+  } catch (error) {
+    throw toAIFriendlyError(error, command.selector);
+  }
+}
+
 async function handleIsVisible(
   command: IsVisibleCommand,
   browser: BrowserManager
@@ -1279,8 +2662,7 @@ async function handleIsChecked(
 }
 
 async function handleCount(command: CountCommand, browser: BrowserManager): Promise<Response> {
-  const page = browser.getPage();
-  const count = await page.locator(command.selector).count();
+  const count = await browser.getLocator(command.selector).count();
   return successResponse(command.id, { count });
 }
 
@@ -1288,11 +2670,260 @@ async function handleBoundingBox(
   command: BoundingBoxCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  const box = await page.locator(command.selector).boundingBox();
+  const box = await browser.getLocator(command.selector).boundingBox();
   return successResponse(command.id, { box });
 }
 
+async function handleLinks(command: LinksCommand, browser: BrowserManager): Promise<Response> {
+  // Use a string function body to avoid TypeScript transpilation issues
+  const scriptBody = `(roots) => {
+    const seen = new Set();
+    const links = [];
+    for (const root of roots) {
+      const anchors = root.matches('a[href]') ? [root] : [];
+      anchors.push(...root.querySelectorAll('a[href]'));
+      for (const a of anchors) {
+        const raw = a.getAttribute('href').trim();
+        if (!raw || raw === '#' || /^javascript:/i.test(raw)) continue;
+        let href;
+        try {
+          href = new URL(raw, document.baseURI).href;
+        } catch {
+          continue;
+        }
+        if (seen.has(href)) continue;
+        seen.add(href);
+        const img = a.querySelector('img[alt]');
+        const text =
+          (a.innerText || a.textContent || '').replace(/\\s+/g, ' ').trim() ||
+          a.getAttribute('aria-label') ||
+          a.getAttribute('title') ||
+          (img ? img.getAttribute('alt') : '');
+        links.push({ href, text });
+      }
+    }
+    return links;
+  }`;
+  // eslint-disable-next-line @typescript-eslint/no-implied-eval
+  const fn = new Function('return ' + scriptBody)();
+
+  const scope = command.selector
+    ? browser.getLocator(command.selector)
+    : browser.getFrame().locator(':root');
+  let links: { href: string; text: string }[];
+  try {
+    links = await scope.evaluateAll(fn);
+  } catch (error) {
+    throw command.selector ? toAIFriendlyError(error, command.selector) : error;
+  }
+
+  if (!command.absolute) {
+    // Same-origin links read better as paths; other origins stay absolute
+    const base = new URL(browser.getFrame().url());
+    links = links.map((link) => {
+      const url = new URL(link.href);
+      return url.origin === base.origin && url.origin !== 'null'
+        ? { ...link, href: url.pathname + url.search + url.hash }
+        : link;
+    });
+  }
+  return successResponse(command.id, { links });
+}
+
+async function handleForms(command: FormsCommand, browser: BrowserManager): Promise<Response> {
+  // Use a string function body to avoid TypeScript transpilation issues
+  const scriptBody = `() => {
+    const SKIP_TYPES = new Set(['hidden', 'submit', 'button', 'reset', 'image']);
+    const clean = (text) => (text || '').replace(/\\s+/g, ' ').trim();
+
+    const labelFor = (el) => {
+      const labelledBy = el.getAttribute('aria-labelledby');
+      if (labelledBy) {
+        const text = labelledBy
+          .split(/\\s+/)
+          .map((id) => clean(document.getElementById(id)?.textContent))
+          .join(' ');
+        if (text.trim()) return text.trim();
+      }
+      const ariaLabel = el.getAttribute('aria-label');
+      if (ariaLabel) return clean(ariaLabel);
+      const label = el.labels && el.labels[0];
+      if (label) return clean(label.innerText || label.textContent);
+      return clean(el.getAttribute('placeholder') || el.getAttribute('title'));
+    };
+
+    // Prefer #id, then a document-unique [name=...]
+    const selectorFor = (el) => {
+      if (el.id) return '#' + CSS.escape(el.id);
+      const name = el.getAttribute('name');
+      if (name) {
+        const sel = el.tagName.toLowerCase() + '[name="' + CSS.escape(name) + '"]';
+        if (document.querySelectorAll(sel).length === 1) return sel;
+      }
+      return null;
+    };
+
+    const describe = (el) => {
+      const tag = el.tagName.toLowerCase();
+      const type = tag === 'input' ? (el.getAttribute('type') || 'text').toLowerCase() : tag;
+      const field = {
+        name: el.getAttribute('name') || el.id || '',
+        type,
+        label: labelFor(el),
+        value: type === 'password' && el.value ? '********' : el.value,
+        required: el.required,
+        selector: selectorFor(el),
+      };
+      if (type === 'checkbox' || type === 'radio') field.checked = el.checked;
+      if (el.disabled) field.disabled = true;
+      if (tag === 'select') {
+        field.options = Array.from(el.options).map((o) => clean(o.text));
+        field.value = Array.from(el.selectedOptions).map((o) => clean(o.text)).join(', ');
+      }
+      return field;
+    };
+
+    const isField = (el) => {
+      const tag = el.tagName.toLowerCase();
+      if (tag === 'select' || tag === 'textarea') return true;
+      return tag === 'input' && !SKIP_TYPES.has((el.getAttribute('type') || '').toLowerCase());
+    };
+
+    const forms = Array.from(document.forms).map((form, index) => ({
+      index,
+      id: form.id || null,
+      name: form.getAttribute('name'),
+      method: (form.getAttribute('method') || 'get').toUpperCase(),
+      action: form.action,
+      fields: Array.from(form.elements).filter(isField).map(describe),
+    }));
+
+    // Fields outside any <form>, common in single-page apps
+    const loose = Array.from(document.querySelectorAll('input, select, textarea')).filter(
+      (el) => !el.form && isField(el)
+    );
+    if (loose.length > 0) {
+      forms.push({
+        index: forms.length,
+        id: null,
+        name: null,
+        method: null,
+        action: null,
+        fields: loose.map(describe),
+      });
+    }
+    return forms;
+  }`;
+  // eslint-disable-next-line @typescript-eslint/no-implied-eval
+  const fn = new Function('return ' + scriptBody)();
+  const forms = await browser.getFrame().evaluate(fn);
+  return successResponse(command.id, { forms });
+}
+
+async function handleTable(command: TableCommand, browser: BrowserManager): Promise<Response> {
+  const format = command.format ?? 'csv';
+  let table: TableData;
+  try {
+    table = await extractTable(browser.getLocator(command.selector));
+  } catch (error) {
+    throw toAIFriendlyError(error, command.selector);
+  }
+  const output = format === 'json' ? tableToJson(table) : tableToCsv(table);
+  return successResponse(command.id, { ...table, format, output });
+}
+
+async function handleAudit(command: AuditCommand, browser: BrowserManager): Promise<Response> {
+  let result;
+  try {
+    const scope = command.selector ? browser.getLocator(command.selector) : undefined;
+    result = await runAudit(browser.getFrame(), scope);
+  } catch (error) {
+    throw command.selector ? toAIFriendlyError(error, command.selector) : error;
+  }
+  const passed = command.failOn ? !exceedsImpact(result.violations, command.failOn) : true;
+  return successResponse(command.id, { ...result, failOn: command.failOn ?? null, passed });
+}
+
+async function handleMetrics(command: MetricsCommand, browser: BrowserManager): Promise<Response> {
+  // Use a string function body to avoid TypeScript transpilation issues
+  const scriptBody = `async () => {
+    const round = (ms) => (typeof ms === 'number' && ms > 0 ? Math.round(ms) : null);
+
+    const nav = performance.getEntriesByType('navigation')[0];
+    const navigation = nav
+      ? {
+          type: nav.type,
+          ttfb: round(nav.responseStart),
+          domContentLoaded: round(nav.domContentLoadedEventEnd),
+          load: round(nav.loadEventEnd),
+          transferSize: nav.transferSize || 0,
+        }
+      : null;
+
+    // LCP and layout shifts are only reported to observers; buffered entries
+    // arrive asynchronously, so give them a moment to be delivered
+    const observe = (type) =>
+      new Promise((resolve) => {
+        const entries = [];
+        try {
+          const observer = new PerformanceObserver((list) => entries.push(...list.getEntries()));
+          observer.observe({ type, buffered: true });
+          setTimeout(() => {
+            observer.disconnect();
+            resolve(entries);
+          }, 50);
+        } catch {
+          resolve(null);
+        }
+      });
+    const [lcpEntries, shifts] = await Promise.all([
+      observe('largest-contentful-paint'),
+      observe('layout-shift'),
+    ]);
+    const fcp = performance.getEntriesByName('first-contentful-paint')[0];
+    const lcp = lcpEntries && lcpEntries.length ? lcpEntries[lcpEntries.length - 1] : null;
+    const cls = shifts
+      ? shifts.filter((e) => !e.hadRecentInput).reduce((sum, e) => sum + e.value, 0)
+      : null;
+
+    const byType = {};
+    let bytes = 0;
+    const resources = performance.getEntriesByType('resource');
+    for (const r of resources) {
+      byType[r.initiatorType] = (byType[r.initiatorType] || 0) + 1;
+      bytes += r.transferSize || 0;
+    }
+
+    return {
+      url: location.href,
+      navigation,
+      paint: {
+        fcp: fcp ? round(fcp.startTime) : null,
+        lcp: lcp ? round(lcp.startTime) : null,
+        cls: cls === null ? null : Math.round(cls * 1000) / 1000,
+      },
+      resources: { count: resources.length, transferSize: bytes, byType },
+    };
+  }`;
+  // eslint-disable-next-line @typescript-eslint/no-implied-eval
+  const fn = new Function('return ' + scriptBody)();
+  const metrics = await browser.getPage().evaluate(fn);
+
+  // Exact heap sizes come from CDP (Chromium only); performance.memory is rounded
+  let heap: { used: number; total: number } | null = null;
+  try {
+    const cdp = await browser.getCDPSession();
+    await cdp.send('Performance.enable');
+    const { metrics: values } = await cdp.send('Performance.getMetrics');
+    const value = (name: string) => values.find((m) => m.name === name)?.value ?? 0;
+    heap = { used: value('JSHeapUsedSize'), total: value('JSHeapTotalSize') };
+  } catch {
+    // Not available outside Chromium
+  }
+
+  return successResponse(command.id, { ...metrics, heap });
+}
+
 async function handleStyles(
   command: StylesCommand,
   browser: BrowserManager
@@ -1380,11 +3011,11 @@ async function handleTraceStart(
   command: TraceStartCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  await browser.startTracing({
+  const tracing = await browser.startTracing({
     screenshots: command.screenshots,
     snapshots: command.snapshots,
   });
-  return successResponse(command.id, { started: true });
+  return successResponse(command.id, { started: true, ...tracing });
 }
 
 async function handleTraceStop(
@@ -1392,26 +3023,40 @@ async function handleTraceStop(
   browser: BrowserManager
 ): Promise<Response> {
   await browser.stopTracing(command.path);
-  return successResponse(command.id, { path: command.path });
+  return successResponse(command.id, { path: command.path, bytes: statSync(command.path).size });
 }
 
 async function handleHarStart(
-  command: Command & { action: 'har_start' },
+  command: HarStartCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  await browser.startHarRecording();
-  browser.startRequestTracking();
-  return successResponse(command.id, { started: true });
+  browser.startHarRecording(command.path);
+  return successResponse(command.id, { started: true, path: command.path });
 }
 
 async function handleHarStop(command: HarStopCommand, browser: BrowserManager): Promise<Response> {
-  // HAR recording is handled at context level
-  // For now, we save tracked requests as a simplified HAR-like format
-  const requests = browser.getRequests();
-  return successResponse(command.id, {
-    path: command.path,
-    requestCount: requests.length,
-  });
+  const result = await browser.stopHarRecording(command.path);
+  return successResponse(command.id, result);
+}
+
+async function handleCoverageStart(
+  command: CoverageStartCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  await browser.startCoverage();
+  return successResponse(command.id, { started: true });
+}
+
+async function handleCoverageStop(
+  command: CoverageStopCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const { js, css } = await browser.stopCoverage();
+  if (command.lcov) {
+    mkdirSync(path.dirname(command.lcov), { recursive: true });
+    writeFileSync(command.lcov, toLcov(js));
+  }
+  return successResponse(command.id, { ...summarizeCoverage(js, css), lcov: command.lcov ?? null });
 }
 
 async function handleStateSave(
@@ -1426,10 +3071,11 @@ async function handleStateLoad(
   command: Command & { action: 'state_load'; path: string },
   browser: BrowserManager
 ): Promise<Response> {
-  // Storage state is loaded at context creation
+  const { cookies, origins } = await browser.loadStorageState(command.path);
   return successResponse(command.id, {
-    note: 'Storage state must be loaded at browser launch. Use --state flag.',
     path: command.path,
+    cookieCount: cookies,
+    originCount: origins,
   });
 }
 
@@ -1439,7 +3085,7 @@ async function handleConsole(command: ConsoleCommand, browser: BrowserManager):
     return successResponse(command.id, { cleared: true });
   }
 
-  const messages = browser.getConsoleMessages();
+  const messages = browser.getConsoleMessages(command.level, command.since);
   return successResponse(command.id, { messages });
 }
 
@@ -1453,6 +3099,23 @@ async function handleErrors(command: ErrorsCommand, browser: BrowserManager): Pr
   return successResponse(command.id, { errors });
 }
 
+async function handleHistory(command: HistoryCommand, browser: BrowserManager): Promise<Response> {
+  return successResponse(command.id, { history: browser.getHistory(command.limit) });
+}
+
+async function handleHistoryGo(
+  command: HistoryGoCommand,
+  browser: BrowserManager
+): Promise<Response<NavigateData>> {
+  const entry = browser.getHistoryEntry(command.index);
+  if (!entry) {
+    throw new Error(`No history entry ${command.index}; run history to list them`);
+  }
+  const page = browser.getPage();
+  await page.goto(entry.url);
+  return successResponse(command.id, { url: page.url(), title: await page.title() });
+}
+
 async function handleKeyboard(
   command: KeyboardCommand,
   browser: BrowserManager
@@ -1466,8 +3129,7 @@ async function handleWheel(command: WheelCommand, browser: BrowserManager): Prom
   const page = browser.getPage();
 
   if (command.selector) {
-    const element = page.locator(command.selector);
-    await element.hover();
+    await browser.getLocator(command.selector).hover();
   }
 
   await page.mouse.wheel(command.deltaX ?? 0, command.deltaY ?? 0);
@@ -1493,26 +3155,61 @@ async function handleClipboard(
     case 'paste':
       await page.keyboard.press('Control+v');
       return successResponse(command.id, { pasted: true });
-    case 'read':
+    case 'read': {
+      await allowClipboard(page);
       const text = await page.evaluate('navigator.clipboard.readText()');
       return successResponse(command.id, { text });
+    }
+    case 'write': {
+      if (command.text === undefined) {
+        return errorResponse(command.id, 'clipboard write requires text');
+      }
+      await allowClipboard(page);
+      await page.evaluate(`navigator.clipboard.writeText(${JSON.stringify(command.text)})`);
+      return successResponse(command.id, { written: true });
+    }
     default:
       return errorResponse(command.id, 'Unknown clipboard operation');
   }
 }
 
+/**
+ * The async clipboard API needs permission and a focused page.
+ */
+async function allowClipboard(page: Page): Promise<void> {
+  try {
+    await page.context().grantPermissions(['clipboard-read', 'clipboard-write']);
+  } catch {
+    // Only Chromium knows these permissions; other engines prompt or allow on their own
+  }
+  await page.bringToFront();
+}
+
 async function handleHighlight(
   command: HighlightCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  await page.locator(command.selector).highlight();
-  return successResponse(command.id, { highlighted: true });
+  const count = await highlightElements(browser, browser.getLocator(command.selector), {
+    label: command.label,
+    duration: command.duration ?? DEFAULT_HIGHLIGHT_MS,
+  });
+  if (count === 0) {
+    throw new CommandError(
+      `No visible element matches "${command.selector}"`,
+      'not_found',
+      command.selector
+    );
+  }
+  return successResponse(command.id, { highlighted: true, count });
 }
 
 async function handleClear(command: ClearCommand, browser: BrowserManager): Promise<Response> {
-  const page = browser.getPage();
-  await page.locator(command.selector).clear();
+  const locator = browser.getLocator(command.selector);
+  try {
+    await locator.clear();
+  } catch (error) {
+    throw toAIFriendlyError(error, command.selector);
+  }
   return successResponse(command.id, { cleared: true });
 }
 
@@ -1520,8 +3217,7 @@ async function handleSelectAll(
   command: SelectAllCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  await page.locator(command.selector).selectText();
+  await browser.getLocator(command.selector).selectText();
   return successResponse(command.id, { selected: true });
 }
 
@@ -1529,8 +3225,7 @@ async function handleInnerText(
   command: InnerTextCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  const text = await page.locator(command.selector).innerText();
+  const text = await browser.getLocator(command.selector).innerText();
   return successResponse(command.id, { text });
 }
 
@@ -1538,8 +3233,7 @@ async function handleInnerHtml(
   command: InnerHtmlCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  const html = await page.locator(command.selector).innerHTML();
+  const html = await browser.getLocator(command.selector).innerHTML();
   return successResponse(command.id, { html });
 }
 
@@ -1556,8 +3250,7 @@ async function handleSetValue(
   command: SetValueCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  await page.locator(command.selector).fill(command.value);
+  await browser.getLocator(command.selector).fill(command.value);
   return successResponse(command.id, { set: true });
 }
 
@@ -1565,8 +3258,7 @@ async function handleDispatch(
   command: DispatchEventCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  await page.locator(command.selector).dispatchEvent(command.event, command.eventInit);
+  await browser.getLocator(command.selector).dispatchEvent(command.event, command.eventInit);
   return successResponse(command.id, { dispatched: command.event });
 }
 
@@ -1641,9 +3333,35 @@ async function handleOffline(command: OfflineCommand, browser: BrowserManager):
   return successResponse(command.id, { offline: command.offline });
 }
 
+// Chrome DevTools presets
+const THROTTLE_PRESETS = {
+  slow3g: { downloadKbps: 400, uploadKbps: 400, latencyMs: 2000 },
+  fast3g: { downloadKbps: 1440, uploadKbps: 675, latencyMs: 563 },
+};
+
+async function handleThrottle(
+  command: ThrottleCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  if (command.profile === 'off') {
+    await browser.setNetworkThrottle(null);
+    return successResponse(command.id, { profile: 'off' });
+  }
+  const conditions =
+    command.profile === 'custom'
+      ? {
+          downloadKbps: command.downloadKbps,
+          uploadKbps: command.uploadKbps,
+          latencyMs: command.latencyMs,
+        }
+      : THROTTLE_PRESETS[command.profile];
+  await browser.setNetworkThrottle(conditions);
+  return successResponse(command.id, { profile: command.profile, ...conditions });
+}
+
 async function handleHeaders(command: HeadersCommand, browser: BrowserManager): Promise<Response> {
-  await browser.setExtraHeaders(command.headers);
-  return successResponse(command.id, { set: true });
+  const headers = await browser.setExtraHeaders(command.headers, command.merge);
+  return successResponse(command.id, { set: true, headers });
 }
 
 async function handlePause(
@@ -1713,8 +3431,7 @@ async function handleGetByTestId(
 }
 
 async function handleNth(command: NthCommand, browser: BrowserManager): Promise<Response> {
-  const page = browser.getPage();
-  const base = page.locator(command.selector);
+  const base = browser.getLocator(command.selector);
   const locator = command.index === -1 ? base.last() : base.nth(command.index);
 
   switch (command.subaction) {
@@ -1738,10 +3455,11 @@ async function handleNth(command: NthCommand, browser: BrowserManager): Promise<
 
 async function handleWaitForUrl(
   command: WaitForUrlCommand,
-  browser: BrowserManager
+  browser: BrowserManager,
+  signal?: AbortSignal
 ): Promise<Response> {
   const page = browser.getPage();
-  await page.waitForURL(command.url, { timeout: command.timeout });
+  await untilAborted(page.waitForURL(command.url, { timeout: command.timeout }), signal);
   return successResponse(command.id, { url: page.url() });
 }
 
@@ -1767,22 +3485,23 @@ async function handleTimezone(
   command: TimezoneCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  // Timezone must be set at context level before navigation
-  // This is a limitation - it sets for the current context
-  const page = browser.getPage();
-  await page.context().setGeolocation({ latitude: 0, longitude: 0 }); // Trigger context awareness
-  return successResponse(command.id, {
-    note: 'Timezone must be set at browser launch. Use --timezone flag.',
-    timezone: command.timezone,
-  });
+  await browser.setTimezone(command.timezone);
+  return successResponse(command.id, { timezone: command.timezone });
 }
 
 async function handleLocale(command: LocaleCommand, browser: BrowserManager): Promise<Response> {
-  // Locale must be set at context creation
-  return successResponse(command.id, {
-    note: 'Locale must be set at browser launch. Use --locale flag.',
-    locale: command.locale,
-  });
+  await browser.setLocale(command.locale);
+  return successResponse(command.id, { locale: command.locale });
+}
+
+async function handleEmulateReset(
+  command: EmulateResetCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  await browser.clearGeolocation();
+  await browser.setLocale(null);
+  await browser.setTimezone(null);
+  return successResponse(command.id, { reset: true });
 }
 
 async function handleCredentials(
@@ -1794,7 +3513,7 @@ async function handleCredentials(
     username: command.username,
     password: command.password,
   });
-  return successResponse(command.id, { set: true });
+  return successResponse(command.id, { set: true, username: command.username });
 }
 
 async function handleMouseMove(
@@ -1806,6 +3525,18 @@ async function handleMouseMove(
   return successResponse(command.id, { moved: true, x: command.x, y: command.y });
 }
 
+async function handleMouseClick(
+  command: MouseClickCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  await page.mouse.click(command.x, command.y, {
+    button: command.button ?? 'left',
+    clickCount: command.clickCount ?? 1,
+  });
+  return successResponse(command.id, { clicked: true, x: command.x, y: command.y });
+}
+
 async function handleMouseDown(
   command: MouseDownCommand,
   browser: BrowserManager
@@ -1843,8 +3574,12 @@ async function handleScrollIntoView(
   command: ScrollIntoViewCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  await page.locator(command.selector).scrollIntoViewIfNeeded();
+  const locator = browser.getLocator(command.selector);
+  try {
+    await locator.scrollIntoViewIfNeeded();
+  } catch (error) {
+    throw toAIFriendlyError(error, command.selector);
+  }
   return successResponse(command.id, { scrolled: true });
 }
 
@@ -1882,8 +3617,7 @@ async function handleMultiSelect(
   command: MultiSelectCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  const selected = await page.locator(command.selector).selectOption(command.values);
+  const selected = await browser.getLocator(command.selector).selectOption(command.values);
   return successResponse(command.id, { selected });
 }
 
@@ -1893,11 +3627,15 @@ async function handleWaitForDownload(
 ): Promise<Response> {
   const page = browser.getPage();
   const download = await page.waitForEvent('download', { timeout: command.timeout });
+  browser.claimDownload(download);
 
   let filePath: string;
   if (command.path) {
     filePath = command.path;
     await download.saveAs(filePath);
+  } else if (browser.getDownloadDir()) {
+    filePath = path.join(browser.getDownloadDir()!, download.suggestedFilename());
+    await download.saveAs(filePath);
   } else {
     filePath = (await download.path()) || download.suggestedFilename();
   }
@@ -1909,6 +3647,83 @@ async function handleWaitForDownload(
   });
 }
 
+async function handleWaitForRequest(
+  command: WaitForRequestCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  const matches = urlMatcher(command.url);
+  const request = await page.waitForRequest((req) => matches(req.url()), {
+    timeout: command.timeout,
+  });
+  return successResponse(command.id, { url: request.url(), method: request.method() });
+}
+
+async function handleWaitForResponse(
+  command: WaitForResponseCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  const matches = urlMatcher(command.url);
+  const response = await page.waitForResponse(
+    (resp) =>
+      matches(resp.url()) && (command.status === undefined || resp.status() === command.status),
+    { timeout: command.timeout }
+  );
+  // Headers are in; the call is over once the body is too
+  await response.finished();
+  return successResponse(command.id, {
+    url: response.url(),
+    method: response.request().method(),
+    status: response.status(),
+  });
+}
+
+const SPA_IDLE_DEFAULT_TIMEOUT = 30000;
+const SPA_IDLE_POLL_INTERVAL = 100;
+
+async function handleWaitForSpaIdle(
+  command: WaitForSpaIdleCommand,
+  browser: BrowserManager,
+  signal?: AbortSignal
+): Promise<Response> {
+  const page = browser.getPage();
+  const quiet = command.quiet ?? DEFAULT_SPA_QUIET_MS;
+  const timeout = command.timeout ?? SPA_IDLE_DEFAULT_TIMEOUT;
+  const deadline = Date.now() + timeout;
+  const network = trackRequests(page);
+  try {
+    for (;;) {
+      signal?.throwIfAborted();
+      // Null while a full navigation swaps the document; the new one starts over
+      const activity = (await page
+        .evaluate(SPA_ACTIVITY_SCRIPT)
+        .catch(() => null)) as SpaActivity | null;
+      const requests = { inflight: network.inflight(), sinceChange: network.sinceChange() };
+      if (activity && isSpaIdle(activity, requests, quiet)) {
+        return successResponse(command.id, { url: page.url() });
+      }
+      if (Date.now() >= deadline) {
+        throw new Error(
+          `The page didn't settle: timed out after ${timeout}ms ` +
+            `with ${requests.inflight} request(s) in flight`
+        );
+      }
+      await page.waitForTimeout(SPA_IDLE_POLL_INTERVAL);
+    }
+  } finally {
+    network.stop();
+  }
+}
+
+async function handleDownloadDir(
+  command: DownloadDirCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  browser.setDownloadDir(command.path);
+  return successResponse(command.id, { directory: command.path });
+}
+
 async function handleResponseBody(
   command: ResponseBodyCommand,
   browser: BrowserManager
@@ -2016,21 +3831,30 @@ async function handleRecordingStart(
   command: RecordingStartCommand,
   browser: BrowserManager
 ): Promise<Response<RecordingStartData>> {
-  await browser.startRecording(command.path, command.url);
-  return successResponse(command.id, {
-    started: true,
-    path: command.path,
-  });
+  const path = await browser.startRecording(command.path, command.url);
+  return successResponse(command.id, { started: true, path });
 }
 
+// `record stop` ends a script recording if one is running, otherwise the video
 async function handleRecordingStop(
   command: RecordingStopCommand,
   browser: BrowserManager
-): Promise<Response<RecordingStopData>> {
+): Promise<Response<RecordingStopData | CodegenStopData>> {
+  if (browser.isCodegenRecording()) {
+    return successResponse(command.id, browser.stopCodegen());
+  }
   const result = await browser.stopRecording();
   return successResponse(command.id, result);
 }
 
+async function handleCodegenStart(
+  command: CodegenStartCommand,
+  browser: BrowserManager
+): Promise<Response<RecordingStartData>> {
+  await browser.startCodegen(command.path, command.url);
+  return successResponse(command.id, { started: true, path: command.path });
+}
+
 async function handleRecordingRestart(
   command: RecordingRestartCommand,
   browser: BrowserManager
@@ -2038,7 +3862,7 @@ async function handleRecordingRestart(
   const result = await browser.restartRecording(command.path, command.url);
   return successResponse(command.id, {
     started: true,
-    path: command.path,
+    path: result.path,
     previousPath: result.previousPath,
     stopped: result.stopped,
   });
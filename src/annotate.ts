@@ -0,0 +1,127 @@
+/**
+ * Outlines and labels drawn over the page.
+ *
+ * `screenshot --annotate-refs` draws the refs of the last snapshot onto the
+ * page before it's captured, so an agent looking at the image can tell which
+ * `@ref` is which element. Refs whose element has gone since the snapshot are
+ * left out, and the overlay is removed again once the screenshot is taken.
+ *
+ * `highlight` outlines every element a selector matches, so someone watching
+ * a headed browser can check it targets what they think. Its overlay removes
+ * itself after the duration, or stays until the page navigates.
+ *
+ * Usage:
+ *   agent-browser snapshot -i
+ *   agent-browser screenshot --annotate-refs page.png
+ *   agent-browser highlight ".card" --label cards --duration 5s
+ */
+
+import type { Locator } from 'playwright-core';
+import type { BrowserManager } from './browser.js';
+
+const OVERLAY_ID = '__agent-browser-annotations';
+const HIGHLIGHT_ID = '__agent-browser-highlight';
+
+/** How long a highlight stays when no duration is given */
+export const DEFAULT_HIGHLIGHT_MS = 3000;
+
+// Adds the outline, and the label if there is one, for one element to the
+// overlay, creating it on first use. An overlay with a duration removes itself
+// after it. A string so it reaches the page untranspiled.
+const ANNOTATE_SCRIPT = `(el, { label, color, overlayId, duration }) => {
+  const rect = el.getBoundingClientRect();
+  if (rect.width === 0 && rect.height === 0) return false;
+  let overlay = document.getElementById(overlayId);
+  if (!overlay) {
+    overlay = document.createElement('div');
+    overlay.id = overlayId;
+    overlay.style.cssText =
+      'position:absolute;top:0;left:0;width:0;height:0;pointer-events:none;z-index:2147483647';
+    document.documentElement.appendChild(overlay);
+    if (duration > 0) setTimeout(() => overlay.remove(), duration);
+  }
+  const top = rect.top + window.scrollY;
+  const left = rect.left + window.scrollX;
+  const box = document.createElement('div');
+  box.style.cssText =
+    'position:absolute;box-sizing:border-box;border:2px solid ' + color + ';' +
+    'top:' + top + 'px;left:' + left + 'px;width:' + rect.width + 'px;height:' + rect.height + 'px';
+  overlay.append(box);
+  if (label) {
+    const tag = document.createElement('div');
+    tag.textContent = label;
+    // Above the element, or inside it when there's no room at the top of the page
+    tag.style.cssText =
+      'position:absolute;padding:0 3px;background:' + color + ';color:#fff;' +
+      'font:bold 11px/14px monospace;white-space:nowrap;' +
+      'top:' + (top >= 14 ? top - 14 : top) + 'px;left:' + left + 'px';
+    overlay.append(tag);
+  }
+  return true;
+}`;
+
+// eslint-disable-next-line @typescript-eslint/no-implied-eval
+const annotate = new Function('return ' + ANNOTATE_SCRIPT)() as (
+  el: unknown,
+  args: { label?: string; color: string; overlayId: string; duration: number }
+) => boolean;
+
+/**
+ * Label every element of the last snapshot that's still on the page.
+ * Returns how many were labelled.
+ */
+export async function addRefAnnotations(browser: BrowserManager): Promise<number> {
+  const refs = Object.keys(browser.getRefMap());
+  const added = await Promise.all(
+    refs.map(async (ref) => {
+      const locator = browser.getLocator(`@${ref}`).first();
+      try {
+        if ((await locator.count()) === 0) return false;
+        const args = { label: `@${ref}`, color: '#e5484d', overlayId: OVERLAY_ID, duration: 0 };
+        return await locator.evaluate(annotate, args, { timeout: 1000 });
+      } catch {
+        // Detached between the count and the evaluate
+        return false;
+      }
+    })
+  );
+  return added.filter(Boolean).length;
+}
+
+/**
+ * Remove what addRefAnnotations drew, from whichever frame the refs were in
+ */
+export async function removeRefAnnotations(browser: BrowserManager): Promise<void> {
+  await removeOverlay(browser, OVERLAY_ID);
+}
+
+/**
+ * Outline every element `locator` matches, replacing the last highlight, and
+ * scroll the first into view. `duration` 0 keeps it until the page navigates.
+ * Returns how many elements were outlined.
+ */
+export async function highlightElements(
+  browser: BrowserManager,
+  locator: Locator,
+  options: { label?: string; duration: number }
+): Promise<number> {
+  await removeOverlay(browser, HIGHLIGHT_ID);
+  const elements = await locator.all();
+  if (elements.length === 0) return 0;
+  await elements[0].scrollIntoViewIfNeeded({ timeout: 1000 }).catch(() => {});
+  const args = { ...options, color: '#0a84ff', overlayId: HIGHLIGHT_ID };
+  const drawn = await Promise.all(
+    elements.map((element) => element.evaluate(annotate, args).catch(() => false))
+  );
+  return drawn.filter(Boolean).length;
+}
+
+async function removeOverlay(browser: BrowserManager, id: string): Promise<void> {
+  const script = `document.getElementById(${JSON.stringify(id)})?.remove()`;
+  await Promise.all(
+    browser
+      .getPage()
+      .frames()
+      .map((frame) => frame.evaluate(script).catch(() => {}))
+  );
+}
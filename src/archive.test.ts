@@ -0,0 +1,26 @@
+import { describe, it, expect } from 'bun:test';
+import { assetPath } from './archive.js';
+
+describe('assetPath', () => {
+  it('should mirror the host and path', () => {
+    expect(assetPath('https://example.com/static/app.css')).toBe('example.com/static/app.css');
+    expect(assetPath('http://localhost:3000/')).toBe('localhost_3000/index');
+  });
+
+  it('should keep resources that differ by query apart', () => {
+    const a = assetPath('https://example.com/img/logo.png?v=1');
+    const b = assetPath('https://example.com/img/logo.png?v=2');
+    expect(a).toMatch(/^example\.com\/img\/logo-[0-9a-f]{8}\.png$/);
+    expect(a).not.toBe(b);
+  });
+
+  it('should skip URLs that are not fetched over HTTP', () => {
+    expect(assetPath('data:image/png;base64,AAAA')).toBeNull();
+    expect(assetPath('blob:https://example.com/1234')).toBeNull();
+    expect(assetPath('not a url')).toBeNull();
+  });
+
+  it('should not leave the directory', () => {
+    expect(assetPath('https://example.com/a/../../etc/passwd')).toBe('example.com/etc/passwd');
+  });
+});
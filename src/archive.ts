@@ -0,0 +1,112 @@
+/**
+ * Save the rendered page for offline inspection: one MHTML file, or an HTML
+ * file with copies of the page's resources next to it.
+ *
+ * MHTML comes from Chromium's Page.captureSnapshot. The directory form takes
+ * the resources the page loaded from Page.getResourceTree and writes each
+ * under `<host>/<path>`, so the relative url()s in stylesheets still resolve,
+ * then points the HTML's src and href attributes at those copies. Scripts are
+ * left out of the HTML so it opens as the DOM was, instead of being rebuilt.
+ *
+ * Usage:
+ *   agent-browser save bug.mhtml
+ *   agent-browser save --html-dir ./page
+ */
+
+import { createHash } from 'node:crypto';
+import { mkdirSync, writeFileSync } from 'node:fs';
+import path from 'node:path';
+import type { CDPSession, Page } from 'playwright-core';
+
+/**
+ * Where a resource at `url` is saved, relative to the directory, or null for
+ * URLs that aren't fetched over HTTP (data:, blob: and the like)
+ */
+export function assetPath(url: string): string | null {
+  let parsed: URL;
+  try {
+    parsed = new URL(url);
+  } catch {
+    return null;
+  }
+  if (parsed.protocol !== 'http:' && parsed.protocol !== 'https:') return null;
+
+  let pathname = parsed.pathname.endsWith('/') ? `${parsed.pathname}index` : parsed.pathname;
+  // The same path with another query is another file
+  if (parsed.search) {
+    const hash = createHash('sha1').update(parsed.search).digest('hex').slice(0, 8);
+    const dot = pathname.lastIndexOf('.');
+    pathname =
+      dot > pathname.lastIndexOf('/')
+        ? `${pathname.slice(0, dot)}-${hash}${pathname.slice(dot)}`
+        : `${pathname}-${hash}`;
+  }
+  const safe = `${parsed.host}${pathname}`.replace(/[^\w.\-/]/g, '_');
+  // URL parsing already resolved `..`; a segment of only dots is still unsafe
+  if (safe.split('/').some((segment) => /^\.+$/.test(segment))) return null;
+  return safe;
+}
+
+// The page's DOM as HTML, with resource URLs replaced by the paths in `saved`
+// (absolute URL to relative path). A string so it reaches the page untranspiled.
+export const RENDERED_HTML_SCRIPT = `(saved) => {
+  const root = document.documentElement.cloneNode(true);
+  for (const el of root.querySelectorAll('script, base')) el.remove();
+  for (const el of root.querySelectorAll('[src], [href]')) {
+    for (const attr of ['src', 'href']) {
+      const value = el.getAttribute(attr);
+      if (value === null) continue;
+      try {
+        const url = new URL(value, document.baseURI).href;
+        el.setAttribute(attr, saved[url] ?? url);
+      } catch {
+        // Left as it was
+      }
+    }
+    // Candidates would be fetched from the site instead of the copy in src
+    el.removeAttribute('srcset');
+  }
+  const doctype = document.doctype ? '<!DOCTYPE ' + document.doctype.name + '>\\n' : '';
+  return doctype + root.outerHTML;
+}`;
+
+/**
+ * Write the page to `dir` as index.html, with copies of the resources it
+ * loaded. Resources the browser no longer has cached are linked to the site.
+ */
+export async function saveHtmlDir(
+  page: Page,
+  cdp: CDPSession,
+  dir: string
+): Promise<{ file: string; resources: number }> {
+  const { frameTree } = await cdp.send('Page.getResourceTree');
+  const saved: Record<string, string> = {};
+  const trees = [frameTree];
+  for (let tree = trees.pop(); tree; tree = trees.pop()) {
+    trees.push(...(tree.childFrames ?? []));
+    for (const { url } of tree.resources) {
+      const relative = assetPath(url);
+      if (!relative || url in saved) continue;
+      try {
+        const { content, base64Encoded } = await cdp.send('Page.getResourceContent', {
+          frameId: tree.frame.id,
+          url,
+        });
+        const file = path.join(dir, relative);
+        mkdirSync(path.dirname(file), { recursive: true });
+        writeFileSync(file, base64Encoded ? Buffer.from(content, 'base64') : content);
+        saved[url] = relative;
+      } catch {
+        // Evicted from the cache, or its path is already a directory
+      }
+    }
+  }
+
+  const html = (await page.evaluate(
+    `(${RENDERED_HTML_SCRIPT})(${JSON.stringify(saved)})`
+  )) as string;
+  const file = path.join(dir, 'index.html');
+  mkdirSync(dir, { recursive: true });
+  writeFileSync(file, html);
+  return { file, resources: Object.keys(saved).length };
+}
@@ -0,0 +1,222 @@
+/**
+ * Readability-style main content extraction rendered as Markdown.
+ *
+ * Picks the element that holds the page's main text (or the one given by a
+ * selector), drops navigation, sidebars and other chrome, and converts what is
+ * left to Markdown in the page.
+ *
+ * Usage:
+ *   agent-browser open example.com/blog/post
+ *   agent-browser get article
+ *   agent-browser get article --selector "#post"
+ */
+
+import type { Frame, Locator } from 'playwright-core';
+
+export interface Article {
+  title: string;
+  url: string;
+  markdown: string;
+}
+
+/**
+ * Extract the main content of a frame, or of the element a locator points at.
+ */
+export async function extractArticle(target: Frame | Locator): Promise<Article> {
+  // Use a string function body to avoid TypeScript transpilation issues
+  const scriptBody = `(element) => {
+    const SKIP = new Set([
+      'script', 'style', 'noscript', 'template', 'svg', 'canvas', 'iframe', 'object',
+      'nav', 'aside', 'footer', 'form', 'button', 'select', 'input', 'textarea', 'dialog'
+    ]);
+    const SKIP_ROLES = new Set([
+      'navigation', 'banner', 'contentinfo', 'complementary', 'search', 'dialog'
+    ]);
+    const CHROME = /comment|share|social|related|sidebar|footer|header|menu|promo|sponsor|cookie|newsletter|breadcrumb|pagination|\\bads?\\b/i;
+
+    const isHidden = (el) =>
+      el.hidden || el.getAttribute('aria-hidden') === 'true' ||
+      getComputedStyle(el).display === 'none';
+
+    const skip = (el) => {
+      const tag = el.tagName.toLowerCase();
+      if (SKIP.has(tag)) return true;
+      if (SKIP_ROLES.has((el.getAttribute('role') || '').toLowerCase())) return true;
+      if (tag === 'header' && !el.closest('article, main')) return true;
+      return isHidden(el);
+    };
+
+    // Score blocks by the paragraph text they hold, penalising link-heavy ones
+    const pickRoot = () => {
+      const scores = new Map();
+      for (const p of document.querySelectorAll('p, pre, blockquote, li, td')) {
+        const text = (p.innerText || '').trim();
+        if (text.length < 25) continue;
+        const points = 1 + text.split(',').length + Math.min(Math.floor(text.length / 100), 3);
+        let parent = p.parentElement;
+        for (let level = 0; parent && level < 3; level++, parent = parent.parentElement) {
+          scores.set(parent, (scores.get(parent) || 0) + points / (level + 1));
+        }
+      }
+      let best = null;
+      let bestScore = 0;
+      for (const [el, score] of scores) {
+        const text = (el.innerText || '').length || 1;
+        let links = 0;
+        for (const a of el.querySelectorAll('a')) links += (a.innerText || '').length;
+        const hint = CHROME.test(el.className + ' ' + el.id) ? 0.5 : 1;
+        const tag = el.tagName.toLowerCase();
+        const bonus = tag === 'article' || tag === 'main' ? 1.5 : 1;
+        const final = score * (1 - links / text) * hint * bonus;
+        if (final > bestScore) {
+          best = el;
+          bestScore = final;
+        }
+      }
+      return best || document.querySelector('article, main, [role="main"]') || document.body;
+    };
+
+    const root = element || pickRoot();
+
+    const absolute = (url) => {
+      try {
+        return new URL(url, document.baseURI).href;
+      } catch {
+        return url;
+      }
+    };
+
+    const inline = (node) => {
+      if (node.nodeType === Node.TEXT_NODE) return node.textContent.replace(/\\s+/g, ' ');
+      if (node.nodeType !== Node.ELEMENT_NODE || skip(node)) return '';
+      const tag = node.tagName.toLowerCase();
+      const inner = () => Array.from(node.childNodes).map(inline).join('');
+      switch (tag) {
+        case 'br':
+          return '\\n';
+        case 'img': {
+          const src = node.getAttribute('src');
+          return src ? '![' + (node.getAttribute('alt') || '') + '](' + absolute(src) + ')' : '';
+        }
+        case 'a': {
+          const text = inner().trim();
+          const href = node.getAttribute('href');
+          if (!text || !href || href.startsWith('#') || href.startsWith('javascript:')) return text;
+          return '[' + text + '](' + absolute(href) + ')';
+        }
+        case 'strong':
+        case 'b': {
+          const text = inner().trim();
+          return text ? '**' + text + '**' : '';
+        }
+        case 'em':
+        case 'i': {
+          const text = inner().trim();
+          return text ? '_' + text + '_' : '';
+        }
+        case 'code':
+          return '\`' + node.textContent + '\`';
+        default:
+          return inner();
+      }
+    };
+
+    const BLOCKS = new Set([
+      'p', 'div', 'section', 'article', 'main', 'header', 'h1', 'h2', 'h3', 'h4', 'h5',
+      'h6', 'ul', 'ol', 'li', 'pre', 'blockquote', 'table', 'hr', 'figure', 'figcaption', 'dl',
+      'dt', 'dd'
+    ]);
+
+    const indent = (text, prefix) =>
+      text.split('\\n').map((line, i) => (i === 0 || !line ? line : prefix + line)).join('\\n');
+
+    // Inline runs become paragraphs; nested blocks are rendered on their own
+    const children = (node) => {
+      const parts = [];
+      let run = '';
+      const flush = () => {
+        const text = run
+          .split('\\n')
+          .map((line) => line.trim())
+          .join('\\n')
+          .replace(/\\n{3,}/g, '\\n\\n')
+          .trim();
+        if (text) parts.push(text);
+        run = '';
+      };
+      for (const child of node.childNodes) {
+        if (child.nodeType === Node.ELEMENT_NODE && BLOCKS.has(child.tagName.toLowerCase())) {
+          if (skip(child)) continue;
+          flush();
+          const text = block(child);
+          if (text) parts.push(text);
+        } else {
+          run += inline(child);
+        }
+      }
+      flush();
+      return parts.join('\\n\\n');
+    };
+
+    const list = (node, ordered) => {
+      let n = 0;
+      return Array.from(node.children)
+        .filter((li) => li.tagName.toLowerCase() === 'li' && !skip(li))
+        .map((li) => {
+          const marker = ordered ? ++n + '. ' : '- ';
+          return marker + indent(children(li), ' '.repeat(marker.length));
+        })
+        .join('\\n');
+    };
+
+    const table = (node) => {
+      const rows = Array.from(node.querySelectorAll('tr')).map((tr) =>
+        Array.from(tr.children).map((cell) =>
+          children(cell).replace(/\\n+/g, ' ').replace(/\\|/g, '\\\\|')
+        )
+      );
+      if (rows.length === 0) return '';
+      const width = Math.max(...rows.map((r) => r.length));
+      const line = (cells) =>
+        '| ' + Array.from({ length: width }, (_, i) => cells[i] || '').join(' | ') + ' |';
+      const header = [line(rows[0]), line(Array(width).fill('---'))];
+      return [...header, ...rows.slice(1).map(line)].join('\\n');
+    };
+
+    const block = (node) => {
+      const tag = node.tagName.toLowerCase();
+      if (/^h[1-6]$/.test(tag)) {
+        const text = children(node).replace(/\\n+/g, ' ');
+        return text ? '#'.repeat(Number(tag[1])) + ' ' + text : '';
+      }
+      switch (tag) {
+        case 'ul':
+          return list(node, false);
+        case 'ol':
+          return list(node, true);
+        case 'pre':
+          return '\`\`\`\\n' + node.textContent.replace(/\\n$/, '') + '\\n\`\`\`';
+        case 'blockquote':
+          return children(node).split('\\n').map((line) => '> ' + line).join('\\n');
+        case 'table':
+          return table(node);
+        case 'hr':
+          return '---';
+        default:
+          return children(node);
+      }
+    };
+
+    const markdown = block(root).trim();
+    const heading = root.querySelector('h1');
+    return {
+      title: document.title || (heading ? heading.innerText.trim() : ''),
+      url: location.href,
+      markdown,
+    };
+  }`;
+
+  // eslint-disable-next-line @typescript-eslint/no-implied-eval
+  const fn = new Function('return ' + scriptBody)();
+  return 'parentFrame' in target ? target.evaluate(fn, null) : target.evaluate(fn);
+}
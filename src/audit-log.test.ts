@@ -0,0 +1,76 @@
+import { describe, it, expect } from 'bun:test';
+import { auditEntry, auditLogPath, redactParams } from './audit-log.js';
+import type { Command } from './types.js';
+
+describe('auditLogPath', () => {
+  it('should be off unless set', () => {
+    expect(auditLogPath(undefined, 'default', '/home/u')).toBeNull();
+    expect(auditLogPath('0', 'default', '/home/u')).toBeNull();
+    expect(auditLogPath('false', 'default', '/home/u')).toBeNull();
+  });
+
+  it('should default to one file per session', () => {
+    expect(auditLogPath('1', 'work', '/home/u')).toBe('/home/u/.agent-browser/audit/work.jsonl');
+    expect(auditLogPath('/var/log/ab.jsonl', 'work', '/home/u')).toBe('/var/log/ab.jsonl');
+  });
+});
+
+describe('redactParams', () => {
+  it('should leave out transport fields', () => {
+    const command = {
+      id: '1',
+      action: 'click',
+      selector: '#go',
+      token: 't0k3n',
+      framing: 'length',
+    } as Command;
+    expect(redactParams(command)).toEqual({ selector: '#go' });
+  });
+
+  it('should mask secret values and passwords', () => {
+    const fill = { id: '1', action: 'fill', selector: '#pw', value: 'hunter2', secret: true };
+    expect(redactParams(fill as Command)).toEqual({
+      selector: '#pw',
+      value: '********',
+      secret: true,
+    });
+    const credentials = { id: '2', action: 'credentials', username: 'me', password: 'hunter2' };
+    expect(redactParams(credentials as Command)).toEqual({
+      username: 'me',
+      password: '********',
+    });
+    const headers = { id: '3', action: 'headers', headers: { Authorization: 'Bearer x', A: 'b' } };
+    expect(redactParams(headers as Command)).toEqual({
+      headers: { Authorization: '********', A: 'b' },
+    });
+  });
+
+  it('should keep the value of a plain fill', () => {
+    const fill = { id: '1', action: 'fill', selector: '#q', value: 'shoes' } as Command;
+    expect(redactParams(fill)).toEqual({ selector: '#q', value: 'shoes' });
+  });
+});
+
+describe('auditEntry', () => {
+  it('should record the outcome and duration', () => {
+    const command = { id: '7', action: 'navigate', url: 'https://example.com' } as Command;
+    const entry = auditEntry(
+      command,
+      { id: '7', success: false, error: 'net::ERR_NAME_NOT_RESOLVED', errorCode: 'navigation' },
+      'default',
+      Date.UTC(2026, 0, 1),
+      Date.UTC(2026, 0, 1) + 250
+    );
+    expect(entry).toEqual({
+      time: '2026-01-01T00:00:00.000Z',
+      session: 'default',
+      id: '7',
+      action: 'navigate',
+      params: { url: 'https://example.com' },
+      success: false,
+      error: 'net::ERR_NAME_NOT_RESOLVED',
+      errorCode: 'navigation',
+      durationMs: 250,
+    });
+  });
+});
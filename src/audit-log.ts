@@ -0,0 +1,124 @@
+/**
+ * Opt-in log of every command a daemon runs, one JSON line each, so a long
+ * agent session can be reconstructed afterwards.
+ *
+ * AGENT_BROWSER_AUDIT_LOG turns it on: `1` appends to
+ * `~/.agent-browser/audit/<session>.jsonl`, anything else is the path of the
+ * file to append to. Passwords, tokens and `fill --secret` values are masked.
+ *
+ * Usage:
+ *   AGENT_BROWSER_AUDIT_LOG=1 agent-browser open example.com
+ *   tail -f ~/.agent-browser/audit/default.jsonl
+ */
+
+import { appendFileSync, mkdirSync } from 'node:fs';
+import { dirname, join } from 'node:path';
+import type { Command, Response } from './types.js';
+
+export interface AuditEntry {
+  time: string;
+  session: string;
+  id: string;
+  action: string;
+  // The command's own fields, secrets masked
+  params: Record<string, unknown>;
+  success: boolean;
+  error?: string;
+  errorCode?: string;
+  durationMs: number;
+}
+
+const MASK = '********';
+
+// Fields of how the request travels, not what it asks for
+const TRANSPORT_FIELDS = ['id', 'action', 'token', 'framing', 'compression', 'timings'];
+
+// Masked wherever they appear, including header names
+const SECRET_KEYS = ['password', 'token', 'authorization', 'proxy-authorization'];
+
+/**
+ * The file AGENT_BROWSER_AUDIT_LOG names for `session`, or null when it's off.
+ * The default lives under `home` rather than the socket directory, which may be
+ * cleared on logout.
+ */
+export function auditLogPath(
+  value: string | undefined,
+  session: string,
+  home: string
+): string | null {
+  if (!value || value === '0' || value === 'false') return null;
+  if (value === '1' || value === 'true') {
+    return join(home, '.agent-browser', 'audit', `${session}.jsonl`);
+  }
+  return value;
+}
+
+function maskSecrets(value: unknown): unknown {
+  if (Array.isArray(value)) return value.map(maskSecrets);
+  if (value === null || typeof value !== 'object') return value;
+  return Object.fromEntries(
+    Object.entries(value).map(([key, v]) => [
+      key,
+      SECRET_KEYS.includes(key.toLowerCase()) ? MASK : maskSecrets(v),
+    ])
+  );
+}
+
+/**
+ * What a command asked for, without its transport fields and with secrets masked.
+ */
+export function redactParams(command: Command): Record<string, unknown> {
+  const params: Record<string, unknown> = {};
+  for (const [key, value] of Object.entries(command)) {
+    if (!TRANSPORT_FIELDS.includes(key)) params[key] = value;
+  }
+  if (params.secret === true && typeof params.value === 'string') params.value = MASK;
+  return maskSecrets(params) as Record<string, unknown>;
+}
+
+export function auditEntry(
+  command: Command,
+  response: Response,
+  session: string,
+  startedAt: number,
+  now: number = Date.now()
+): AuditEntry {
+  const entry: AuditEntry = {
+    time: new Date(startedAt).toISOString(),
+    session,
+    id: command.id,
+    action: command.action,
+    params: redactParams(command),
+    success: response.success,
+    durationMs: now - startedAt,
+  };
+  if (!response.success) {
+    entry.error = response.error;
+    if (response.errorCode) entry.errorCode = response.errorCode;
+  }
+  return entry;
+}
+
+export class AuditLog {
+  private path: string;
+  private session: string;
+
+  constructor(path: string, session: string) {
+    this.path = path;
+    this.session = session;
+  }
+
+  /**
+   * Append one command's entry. Best effort: a log that can't be written never
+   * fails the command.
+   */
+  record(command: Command, response: Response, startedAt: number): void {
+    const line = JSON.stringify(auditEntry(command, response, this.session, startedAt)) + '\n';
+    try {
+      mkdirSync(dirname(this.path), { recursive: true, mode: 0o700 });
+      appendFileSync(this.path, line, { mode: 0o600 });
+    } catch {
+      // Nothing to report it to; the command's own answer matters more
+    }
+  }
+}
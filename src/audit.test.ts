@@ -0,0 +1,22 @@
+import { describe, it, expect } from 'bun:test';
+import { exceedsImpact, type AuditViolation } from './audit.js';
+
+const violation = (impact: AuditViolation['impact']): AuditViolation => ({
+  id: 'color-contrast',
+  impact,
+  help: 'Elements must meet minimum color contrast ratio thresholds',
+  helpUrl: 'https://dequeuniversity.com/rules/axe/4.10/color-contrast',
+  nodes: [],
+});
+
+describe('exceedsImpact', () => {
+  it('should fail at or above the threshold', () => {
+    expect(exceedsImpact([violation('serious')], 'serious')).toBe(true);
+    expect(exceedsImpact([violation('critical')], 'serious')).toBe(true);
+  });
+
+  it('should pass below the threshold', () => {
+    expect(exceedsImpact([violation('moderate'), violation(null)], 'serious')).toBe(false);
+    expect(exceedsImpact([], 'minor')).toBe(false);
+  });
+});
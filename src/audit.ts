@@ -0,0 +1,94 @@
+/**
+ * Accessibility audit with axe-core.
+ *
+ * axe-core is bundled into the daemon; AGENT_BROWSER_AXE_PATH points at another
+ * axe.min.js to use instead. The source is evaluated in the page rather than
+ * added as a script tag, so a strict Content-Security-Policy does not block it.
+ *
+ * Usage:
+ *   agent-browser audit a11y
+ *   agent-browser audit a11y --fail-on serious
+ */
+
+import axe from 'axe-core';
+import { readFileSync } from 'node:fs';
+import type { Frame, Locator } from 'playwright-core';
+
+export const IMPACTS = ['minor', 'moderate', 'serious', 'critical'] as const;
+export type Impact = (typeof IMPACTS)[number];
+
+export interface AuditViolation {
+  id: string;
+  impact: Impact | null;
+  help: string;
+  helpUrl: string;
+  nodes: { target: string; html: string; failureSummary: string }[];
+}
+
+export interface AuditResult {
+  url: string;
+  violations: AuditViolation[];
+  passes: number;
+  incomplete: number;
+}
+
+let axeSource: string | null = null;
+
+function loadAxeSource(): string {
+  if (axeSource) return axeSource;
+
+  const path = process.env.AGENT_BROWSER_AXE_PATH;
+  if (!path) {
+    axeSource = axe.source;
+    return axeSource;
+  }
+  try {
+    axeSource = readFileSync(path, 'utf8');
+  } catch (error) {
+    const reason = error instanceof Error ? error.message : String(error);
+    throw new Error(`Cannot read AGENT_BROWSER_AXE_PATH (${path}): ${reason}`);
+  }
+  return axeSource;
+}
+
+/**
+ * Run axe in a frame, over the whole document or only the element a locator points at.
+ */
+export async function runAudit(frame: Frame, scope?: Locator): Promise<AuditResult> {
+  const loaded = await frame.evaluate('typeof window.axe !== "undefined"');
+  if (!loaded) {
+    await frame.evaluate(loadAxeSource());
+  }
+
+  // Use a string function body to avoid TypeScript transpilation issues
+  const scriptBody = `async (element) => {
+    const result = await window.axe.run(element || document, { resultTypes: ['violations'] });
+    return {
+      url: location.href,
+      violations: result.violations.map((v) => ({
+        id: v.id,
+        impact: v.impact || null,
+        help: v.help,
+        helpUrl: v.helpUrl,
+        nodes: v.nodes.map((n) => ({
+          target: n.target.map((t) => (Array.isArray(t) ? t.join(' >>> ') : t)).join(' '),
+          html: n.html,
+          failureSummary: n.failureSummary || '',
+        })),
+      })),
+      passes: result.passes.length,
+      incomplete: result.incomplete.length,
+    };
+  }`;
+  // eslint-disable-next-line @typescript-eslint/no-implied-eval
+  const fn = new Function('return ' + scriptBody)();
+  return scope ? scope.evaluate(fn) : frame.evaluate(fn, null);
+}
+
+/**
+ * Whether any violation is at or above the given impact.
+ */
+export function exceedsImpact(violations: AuditViolation[], failOn: Impact): boolean {
+  const threshold = IMPACTS.indexOf(failOn);
+  return violations.some((v) => v.impact !== null && IMPACTS.indexOf(v.impact) >= threshold);
+}
@@ -1,6 +1,31 @@
 import { describe, it, expect, beforeAll, afterAll, beforeEach, mock, spyOn } from "bun:test";
-import { BrowserManager } from "./browser.js";
+import { BrowserManager, urlMatcher } from "./browser.js";
+import { CommandError, executeCommand } from "./actions.js";
+import type { Command } from "./types.js";
 import { chromium } from "playwright-core";
+import { unlinkSync, writeFileSync } from "fs";
+import { tmpdir } from "os";
+import { join } from "path";
+
+describe("urlMatcher", () => {
+  it("should match globs against the whole URL", () => {
+    const matches = urlMatcher("**/api/*");
+    expect(matches("https://shop.test/api/cart")).toBe(true);
+    expect(matches("https://shop.test/api/cart/items")).toBe(false);
+    expect(urlMatcher("https://*.test/**")("https://shop.test/a/b")).toBe(true);
+  });
+
+  it("should match globs without the query string", () => {
+    const matches = urlMatcher("**/*.png");
+    expect(matches("https://cdn.test/img/logo.png?v=2")).toBe(true);
+    expect(matches("https://cdn.test/logo.png.html")).toBe(false);
+  });
+
+  it("should match patterns without a star as a substring", () => {
+    expect(urlMatcher("analytics")("https://www.test/js/analytics.js")).toBe(true);
+    expect(urlMatcher("analytics")("https://www.test/app.js")).toBe(false);
+  });
+});
 
 describe("BrowserManager", () => {
   let browser: BrowserManager;
@@ -183,6 +208,87 @@ describe("BrowserManager", () => {
     });
   });
 
+  describe("named contexts", () => {
+    it("should apply settings to the current context only", async () => {
+      await browser.newContext("isolated");
+      await browser.setOffline(true);
+      expect(await browser.getPage().evaluate(() => navigator.onLine)).toBe(false);
+
+      await browser.switchContext("default");
+      expect(await browser.getPage().evaluate(() => navigator.onLine)).toBe(true);
+
+      await browser.closeContext("isolated");
+    });
+
+    it("should keep a context offline when throttling it", async () => {
+      await browser.newContext("throttled");
+      await browser.setOffline(true);
+      await browser.setNetworkThrottle({ latencyMs: 50 });
+      expect(await browser.getPage().evaluate(() => navigator.onLine)).toBe(false);
+
+      await browser.setNetworkThrottle(null);
+      expect(await browser.getPage().evaluate(() => navigator.onLine)).toBe(false);
+
+      await browser.closeContext("throttled");
+    });
+
+    it("should grant permissions in the context switched to", async () => {
+      const state = () =>
+        browser
+          .getPage()
+          .evaluate(async () => (await navigator.permissions.query({ name: "geolocation" })).state);
+      await browser.newContext("granted");
+      await browser.switchContext("default");
+      await browser.switchContext("granted");
+      await browser.getPage().goto("https://example.com");
+      await browser.setPermissions(["geolocation"], true, "https://example.com");
+      expect(await state()).toBe("granted");
+
+      await browser.switchContext("default");
+      await browser.getPage().goto("https://example.com");
+      expect(await state()).not.toBe("granted");
+
+      await browser.closeContext("granted");
+    });
+  });
+
+  describe("cancelled waits", () => {
+    // Run `command`, cancelling it after 100ms; its own timeout is far off
+    const cancel = async (command: Record<string, unknown>) => {
+      const controller = new AbortController();
+      setTimeout(() => controller.abort(new CommandError("Cancelled", "cancelled")), 100);
+      const started = Date.now();
+      const response = await executeCommand(
+        { id: "w1", timeout: 20000, ...command } as Command,
+        browser,
+        controller.signal,
+      );
+      return { response, elapsed: Date.now() - started };
+    };
+
+    beforeEach(async () => {
+      await browser.getPage().setContent("<p>Nothing to see</p>");
+    });
+
+    it("should stop waiting for a selector", async () => {
+      const { response, elapsed } = await cancel({ action: "wait", selector: "#never" });
+      expect(response).toMatchObject({ success: false, errorCode: "cancelled" });
+      expect(elapsed).toBeLessThan(5000);
+    });
+
+    it("should stop waiting for text", async () => {
+      const { response, elapsed } = await cancel({ action: "wait", selector: "text=Never shown" });
+      expect(response).toMatchObject({ success: false, errorCode: "cancelled" });
+      expect(elapsed).toBeLessThan(5000);
+    });
+
+    it("should stop waiting for a URL", async () => {
+      const { response, elapsed } = await cancel({ action: "waitforurl", url: "**/never" });
+      expect(response).toMatchObject({ success: false, errorCode: "cancelled" });
+      expect(elapsed).toBeLessThan(5000);
+    });
+  });
+
   describe("localStorage operations", () => {
     it("should set and get localStorage item", async () => {
       const page = browser.getPage();
@@ -226,6 +332,37 @@ describe("BrowserManager", () => {
     });
   });
 
+  describe("state load", () => {
+    it("should keep the scratch page out of the tabs", async () => {
+      const statePath = join(tmpdir(), `agent-browser-state-${process.pid}.json`);
+      writeFileSync(
+        statePath,
+        JSON.stringify({
+          cookies: [],
+          origins: [
+            { origin: "https://example.com", localStorage: [{ name: "k", value: "v" }] },
+          ],
+        })
+      );
+      const pages = browser.getPages().length;
+      const active = browser.getActiveIndex();
+      const seen: number[] = [];
+      const context = browser.getPage().context();
+      const onPage = () => seen.push(browser.getPages().length);
+      context.on("page", onPage);
+      try {
+        const result = await browser.loadStorageState(statePath);
+        expect(result.origins).toBe(1);
+      } finally {
+        context.off("page", onPage);
+        unlinkSync(statePath);
+      }
+      expect(seen).toEqual([pages]);
+      expect(browser.getPages().length).toBe(pages);
+      expect(browser.getActiveIndex()).toBe(active);
+    });
+  });
+
   describe("sessionStorage operations", () => {
     it("should set and get sessionStorage item", async () => {
       const page = browser.getPage();
@@ -12,13 +12,35 @@ import {
   type Route,
   type Locator,
   type CDPSession,
+  type Download,
   type Video,
 } from 'playwright-core';
-import { join } from 'node:path';
+import { dirname, extname, join } from 'node:path';
 import { tmpdir, homedir } from 'node:os';
-import { mkdirSync, rmSync } from 'node:fs';
+import { existsSync, mkdirSync, readFileSync, rmSync, statSync, writeFileSync } from 'node:fs';
 import type { LaunchCommand } from './types.js';
 import { type RefMap, type EnhancedSnapshot, getEnhancedSnapshot, parseRef } from './snapshot.js';
+import type { ReadItem } from './reader.js';
+import {
+  type WatchChange,
+  type WatchEvent,
+  WATCH_BINDING,
+  startWatchScript,
+  stopWatchScript,
+  toWatchChange,
+} from './watch.js';
+import { type HarEntry, buildHar, buildHarEntry } from './har.js';
+import { type CapturedExchange, captureExchange } from './capture.js';
+import {
+  type RecordedAction,
+  RECORDER_SCRIPT,
+  addAction,
+  codegenFormat,
+  isRecordedAction,
+  toCommandList,
+  toScript,
+} from './codegen.js';
+import type { CSSCoverageEntry, JSCoverageEntry } from './coverage.js';
 
 // Screencast frame data from CDP
 export interface ScreencastFrame {
@@ -52,17 +74,124 @@ interface TrackedRequest {
   resourceType: string;
 }
 
+export interface NetworkLogEntry {
+  id: number;
+  method: string;
+  url: string;
+  resourceType: string;
+  status: number | null;
+  failure?: string;
+  startTime: number;
+  duration: number | null; // ms, null while the request is in flight
+  size: number | null; // response body bytes
+  captured?: boolean; // bodies kept by `network capture`
+}
+
+export interface MockInfo {
+  id: number;
+  url: string;
+  status: number;
+  contentType?: string;
+  headers?: Record<string, string>;
+  size: number;
+  hits: number;
+}
+
+// Oldest entries are dropped once the log grows past this
+const MAX_NETWORK_LOG = 1000;
+
+// Requests captured by `network capture` when no --limit is given
+export const DEFAULT_CAPTURE_LIMIT = 20;
+
 interface ConsoleMessage {
+  seq: number;
   type: string;
   text: string;
   timestamp: number;
 }
 
+export type ConsoleLevel = 'error' | 'warn' | 'all';
+
+const CONSOLE_LEVEL_TYPES: Record<Exclude<ConsoleLevel, 'all'>, string[]> = {
+  error: ['error'],
+  warn: ['error', 'warning'],
+};
+
 interface PageError {
   message: string;
   timestamp: number;
 }
 
+// `network throttle` limits: throughputs in kbit/s, unlimited when omitted
+interface NetworkThrottle {
+  downloadKbps?: number;
+  uploadKbps?: number;
+  latencyMs?: number;
+}
+
+export interface HistoryEntry {
+  // Counts up from 1 for the session, so an entry keeps its number as older ones drop off
+  index: number;
+  url: string;
+  title: string;
+  timestamp: number;
+}
+
+const MAX_HISTORY = 500;
+
+export const BROWSER_EVENT_TYPES = ['console', 'network', 'dialog'] as const;
+export type BrowserEventType = (typeof BROWSER_EVENT_TYPES)[number];
+
+/**
+ * An event pushed to `subscribe` clients as it happens
+ */
+export interface BrowserEvent {
+  type: BrowserEventType;
+  timestamp: number;
+  data: object;
+}
+
+type BrowserEventListener = (event: BrowserEvent) => void;
+
+export type DialogResponse = 'accept' | 'dismiss';
+
+/**
+ * Default action timeout for local browsers. AGENT_BROWSER_DEFAULT_TIMEOUT (ms) is set by the
+ * CLI from the config file, or can be set directly.
+ */
+function getDefaultTimeout(): number {
+  const value = parseInt(process.env.AGENT_BROWSER_DEFAULT_TIMEOUT ?? '', 10);
+  return Number.isFinite(value) && value > 0 ? value : 60000;
+}
+
+/**
+ * Default viewport. AGENT_BROWSER_VIEWPORT uses the form "1280x720".
+ */
+function getDefaultViewport(): { width: number; height: number } {
+  const match = /^(\d+)x(\d+)$/.exec(process.env.AGENT_BROWSER_VIEWPORT ?? '');
+  return match
+    ? { width: parseInt(match[1], 10), height: parseInt(match[2], 10) }
+    : { width: 1280, height: 720 };
+}
+
+/**
+ * Build the matcher for every URL pattern commands take: wait, expect, network log and
+ * capture, mock and block. A pattern with `*` is a glob over the whole URL, `*` within a
+ * path segment and `**` across segments; it also matches the URL without its query and
+ * fragment, so `**/logo.png` covers `logo.png?v=2`. Patterns without `*` match as a substring.
+ */
+export function urlMatcher(pattern: string): (url: string) => boolean {
+  if (!pattern.includes('*')) {
+    return (url) => url.includes(pattern);
+  }
+  const source = pattern
+    .split('**')
+    .map((part) => part.replace(/[.+?^${}()|[\]\\]/g, '\\$&').replace(/\*/g, '[^/]*'))
+    .join('.*');
+  const regex = new RegExp(`^${source}$`);
+  return (url) => regex.test(url) || regex.test(url.replace(/[?#].*$/, ''));
+}
+
 /**
  * Manages the Playwright browser lifecycle with multiple tabs/windows
  */
@@ -70,6 +199,10 @@ export class BrowserManager {
   private browser: Browser | null = null;
   private cdpEndpoint: string | null = null; // stores port number or full URL
   private isPersistentContext: boolean = false;
+  // Mode of a locally launched browser; null for CDP and cloud providers
+  private localLaunchMode: { headless: boolean; browser: string } | null = null;
+  private tracingContext: BrowserContext | null = null;
+  private coveragePage: Page | null = null;
   private browserbaseSessionId: string | null = null;
   private browserbaseApiKey: string | null = null;
   private browserUseSessionId: string | null = null;
@@ -79,15 +212,77 @@ export class BrowserManager {
   private contexts: BrowserContext[] = [];
   private pages: Page[] = [];
   private activePageIndex: number = 0;
+  // Names given with `context new`; the first context is "default"
+  private contextNames: Map<BrowserContext, string> = new Map();
   private activeFrame: Frame | null = null;
-  private dialogHandler: ((dialog: Dialog) => Promise<void>) | null = null;
+  // Dialogs are dismissed by default, matching Playwright's behaviour without a listener
+  private dialogPolicy: { response: DialogResponse | 'off'; promptText?: string } = {
+    response: 'dismiss',
+  };
+  private nextDialogResponse: { response: DialogResponse; promptText?: string } | null = null;
+  private pendingDialogs: Dialog[] = [];
   private trackedRequests: TrackedRequest[] = [];
+  private networkLog: NetworkLogEntry[] = [];
+  private nextNetworkLogId: number = 1;
+  private pendingRequests: WeakMap<Request, NetworkLogEntry> = new WeakMap();
+  private capture: { pattern: string; matches: (url: string) => boolean; left: number } | null =
+    null;
+  // Keyed by network log id, and dropped with their entries
+  private captures: Map<number, CapturedExchange> = new Map();
   private routes: Map<string, (route: Route) => Promise<void>> = new Map();
+  private mocks: Map<
+    number,
+    { info: MockInfo; matcher: (url: URL) => boolean; handler: (route: Route) => Promise<void> }
+  > = new Map();
+  private nextMockId: number = 1;
+  private blocked: Map<
+    string,
+    { matcher: (url: URL) => boolean; handler: (route: Route) => Promise<void> }
+  > = new Map();
   private consoleMessages: ConsoleMessage[] = [];
+  private consoleSeq: number = 0;
+  private eventListeners: Map<BrowserEventListener, Set<BrowserEventType>> = new Map();
+  private watchListeners: Map<string, (change: WatchChange) => void> = new Map();
+  // Pages the mutation binding was exposed on; a binding can't be removed
+  private watchPages: WeakSet<Page> = new WeakSet();
+  private nextWatchId: number = 1;
   private pageErrors: PageError[] = [];
-  private isRecordingHar: boolean = false;
+  private history: HistoryEntry[] = [];
+  private nextHistoryIndex: number = 1;
+  private harRecording: {
+    path: string;
+    startTime: number;
+    entries: Promise<HarEntry | null>[];
+  } | null = null;
+  private codegen: {
+    path: string;
+    actions: RecordedAction[];
+    lastActionAt: number;
+  } | null = null;
+  // Contexts the recorder binding and script were added to; neither can be removed
+  private codegenContexts: WeakSet<BrowserContext> = new WeakSet();
   private refMap: RefMap = {};
+  // Frame and scope selector the cached refs were taken from
+  private refFrame: Frame | null = null;
+  private refScope: string | undefined;
   private lastSnapshot: string = '';
+  // Items of the page being read with `read`, and the chunk read last
+  private reading: { items: ReadItem[]; from: number; to: number } | null = null;
+  private downloadDir: string | null = null;
+  // Downloads a command saves itself, which the download dir leaves alone
+  private claimedDownloads: WeakSet<Download> = new WeakSet();
+  private extraHeaders: Record<string, string> = {};
+  private userAgentOverride: string | null = null;
+  // CDP sessions of our own per page, for overrides that outlive tab switches
+  private pageSessions: WeakMap<Page, CDPSession> = new WeakMap();
+  // `network throttle` limits and `offline` state, per context
+  private networkThrottles: WeakMap<BrowserContext, NetworkThrottle> = new WeakMap();
+  private offlineContexts: WeakSet<BrowserContext> = new WeakSet();
+  // `set locale` and `set timezone` overrides, per context
+  private localeOverrides: WeakMap<BrowserContext, string> = new WeakMap();
+  private timezoneOverrides: WeakMap<BrowserContext, string> = new WeakMap();
+  // Contexts opening a scratch page, whose 'page' event isn't a new tab
+  private scratchPageContexts: WeakSet<BrowserContext> = new WeakSet();
   private scopedHeaderRoutes: Map<string, (route: Route) => Promise<void>> = new Map();
 
   // CDP session for screencast and input injection
@@ -120,13 +315,30 @@ export class BrowserManager {
     compact?: boolean;
     selector?: string;
   }): Promise<EnhancedSnapshot> {
-    const page = this.getPage();
-    const snapshot = await getEnhancedSnapshot(page, options);
+    const frame = this.getFrame();
+    const snapshot = await getEnhancedSnapshot(frame, options);
     this.refMap = snapshot.refs;
+    this.refFrame = frame;
+    this.refScope = options?.selector;
     this.lastSnapshot = snapshot.tree;
     return snapshot;
   }
 
+  /**
+   * Get the tree from the last snapshot ('' if none was taken)
+   */
+  getLastSnapshot(): string {
+    return this.lastSnapshot;
+  }
+
+  getReading(): { items: ReadItem[]; from: number; to: number } | null {
+    return this.reading;
+  }
+
+  setReading(reading: { items: ReadItem[]; from: number; to: number }): void {
+    this.reading = reading;
+  }
+
   /**
    * Get the cached ref map from last snapshot
    */
@@ -145,21 +357,31 @@ export class BrowserManager {
     const refData = this.refMap[ref];
     if (!refData) return null;
 
-    const page = this.getPage();
+    // Resolve against the frame the snapshot was taken in, even if another
+    // frame has been selected since. Fall back to the current frame once the
+    // snapshot's frame is gone or belongs to another tab.
+    const refFrame = this.refFrame;
+    const frame =
+      refFrame && !refFrame.isDetached() && refFrame.page() === this.getPage()
+        ? refFrame
+        : this.getFrame();
 
     // Check if this is a cursor-interactive element (uses CSS selector, not ARIA role)
     // These have pseudo-roles 'clickable' or 'focusable' and a CSS selector
     if (refData.role === 'clickable' || refData.role === 'focusable') {
       // The selector is a CSS selector, use it directly
-      return page.locator(refData.selector);
+      return frame.locator(refData.selector);
     }
 
+    // nth indexes are counted within the snapshot's scope, so resolve there too
+    const root = this.refScope ? frame.locator(this.refScope) : frame;
+
     // Build locator with exact: true to avoid substring matches
     let locator: Locator;
     if (refData.name) {
-      locator = page.getByRole(refData.role as any, { name: refData.name, exact: true });
+      locator = root.getByRole(refData.role as any, { name: refData.name, exact: true });
     } else {
-      locator = page.getByRole(refData.role as any);
+      locator = root.getByRole(refData.role as any);
     }
 
     // If an nth index is stored (for disambiguation), use it
@@ -186,8 +408,41 @@ export class BrowserManager {
     if (locator) return locator;
 
     // Otherwise treat as regular selector
-    const page = this.getPage();
-    return page.locator(selectorOrRef);
+    return this.getFrame().locator(selectorOrRef);
+  }
+
+  /**
+   * Set the directory downloads are saved to when no explicit path is given
+   */
+  setDownloadDir(dir: string): void {
+    mkdirSync(dir, { recursive: true });
+    this.downloadDir = dir;
+  }
+
+  /**
+   * Get the configured download directory, or null if downloads stay in Playwright's temp dir
+   */
+  getDownloadDir(): string | null {
+    return this.downloadDir;
+  }
+
+  /**
+   * Keep `download` out of the download dir; the caller saves it where it was asked to
+   */
+  claimDownload(download: Download): void {
+    this.claimedDownloads.add(download);
+  }
+
+  /**
+   * Save a download started by any page, in any context, to the download dir once it
+   * has finished, unless a command claimed it meanwhile
+   */
+  private async saveToDownloadDir(download: Download): Promise<void> {
+    const dir = this.downloadDir;
+    if (!dir) return;
+    await download.path().catch(() => null);
+    if (this.claimedDownloads.has(download)) return;
+    await download.saveAs(join(dir, download.suggestedFilename())).catch(() => {});
   }
 
   /**
@@ -201,22 +456,55 @@ export class BrowserManager {
   }
 
   /**
-   * Get the current frame (or page's main frame if no frame is selected)
+   * Get the current frame (or page's main frame if no frame is selected).
+   * Falls back to the main frame once the selected frame is detached or
+   * another tab is active.
    */
   getFrame(): Frame {
-    if (this.activeFrame) {
+    const page = this.getPage();
+    if (this.activeFrame && !this.activeFrame.isDetached() && this.activeFrame.page() === page) {
       return this.activeFrame;
     }
-    return this.getPage().mainFrame();
+    this.activeFrame = null;
+    return page.mainFrame();
+  }
+
+  /**
+   * List frames of the current page in document order. Index 0 is the main frame.
+   */
+  listFrames(): { index: number; name: string; url: string; active: boolean }[] {
+    const current = this.getFrame();
+    return this.getPage()
+      .frames()
+      .map((frame, index) => ({
+        index,
+        name: frame.name(),
+        url: frame.url(),
+        active: frame === current,
+      }));
   }
 
   /**
-   * Switch to a frame by selector, name, or URL
+   * Switch to a frame by selector, name, URL, or index from `listFrames`
    */
-  async switchToFrame(options: { selector?: string; name?: string; url?: string }): Promise<void> {
+  async switchToFrame(options: {
+    selector?: string;
+    name?: string;
+    url?: string;
+    index?: number;
+  }): Promise<void> {
     const page = this.getPage();
 
-    if (options.selector) {
+    if (options.index !== undefined) {
+      const frames = page.frames();
+      const frame = frames[options.index];
+      if (!frame) {
+        throw new Error(
+          `Frame index ${options.index} out of range (page has ${frames.length} frames)`
+        );
+      }
+      this.activeFrame = options.index === 0 ? null : frame;
+    } else if (options.selector) {
       const frameElement = await page.$(options.selector);
       if (!frameElement) {
         throw new Error(`Frame not found: ${options.selector}`);
@@ -249,36 +537,66 @@ export class BrowserManager {
   }
 
   /**
-   * Set up dialog handler
+   * Respond to the oldest pending dialog, or if none is open, to the next one
+   * that appears. Returns the dialog that was handled, if any.
    */
-  setDialogHandler(response: 'accept' | 'dismiss', promptText?: string): void {
-    const page = this.getPage();
-
-    // Remove existing handler if any
-    if (this.dialogHandler) {
-      page.removeListener('dialog', this.dialogHandler);
+  async respondToDialog(
+    response: DialogResponse,
+    promptText?: string
+  ): Promise<{ type: string; message: string } | null> {
+    const dialog = this.pendingDialogs.shift();
+    if (!dialog) {
+      this.nextDialogResponse = { response, promptText };
+      return null;
     }
+    await this.applyDialogResponse(dialog, { response, promptText });
+    return { type: dialog.type(), message: dialog.message() };
+  }
+
+  /**
+   * Set how dialogs are handled when no one-shot response is armed. `off`
+   * leaves them open until `respondToDialog` is called.
+   */
+  setDialogPolicy(response: DialogResponse | 'off', promptText?: string): void {
+    this.dialogPolicy = { response, promptText };
+  }
+
+  getPendingDialogs(): { type: string; message: string; defaultValue: string }[] {
+    return this.pendingDialogs.map((d) => ({
+      type: d.type(),
+      message: d.message(),
+      defaultValue: d.defaultValue(),
+    }));
+  }
 
-    this.dialogHandler = async (dialog: Dialog) => {
+  private async applyDialogResponse(
+    dialog: Dialog,
+    { response, promptText }: { response: DialogResponse; promptText?: string }
+  ): Promise<void> {
+    try {
       if (response === 'accept') {
         await dialog.accept(promptText);
       } else {
         await dialog.dismiss();
       }
-    };
-
-    page.on('dialog', this.dialogHandler);
+    } catch {
+      // Dialog was already closed (e.g. the page navigated away)
+    }
   }
 
-  /**
-   * Clear dialog handler
-   */
-  clearDialogHandler(): void {
-    if (this.dialogHandler) {
-      const page = this.getPage();
-      page.removeListener('dialog', this.dialogHandler);
-      this.dialogHandler = null;
+  private handleDialog(dialog: Dialog): void {
+    const once = this.nextDialogResponse;
+    if (once) {
+      this.nextDialogResponse = null;
+      void this.applyDialogResponse(dialog, once);
+      return;
     }
+    const { response, promptText } = this.dialogPolicy;
+    if (response === 'off') {
+      this.pendingDialogs.push(dialog);
+      return;
+    }
+    void this.applyDialogResponse(dialog, { response, promptText });
   }
 
   /**
@@ -307,6 +625,102 @@ export class BrowserManager {
     return this.trackedRequests;
   }
 
+  /**
+   * Get network log entries, optionally only those after `since` and matching a URL glob
+   */
+  getNetworkLog(filter?: string, since?: number): NetworkLogEntry[] {
+    const matches = filter ? urlMatcher(filter) : () => true;
+    return this.networkLog.filter((e) => (since === undefined || e.id > since) && matches(e.url));
+  }
+
+  /**
+   * Keep the bodies of the next `limit` requests whose URL matches the glob,
+   * replacing any capture still running
+   */
+  startCapture(pattern: string, limit: number = DEFAULT_CAPTURE_LIMIT): void {
+    this.capture = { pattern, matches: urlMatcher(pattern), left: limit };
+  }
+
+  /**
+   * Stop capturing; bodies already captured are kept. Returns the glob that was
+   * being captured, if any.
+   */
+  stopCapture(): string | null {
+    const pattern = this.capture?.pattern ?? null;
+    this.capture = null;
+    return pattern;
+  }
+
+  /**
+   * A captured request and response, by network log id
+   */
+  getCapture(id: number): CapturedExchange | undefined {
+    return this.captures.get(id);
+  }
+
+  private logRequestStart(request: Request): void {
+    const entry: NetworkLogEntry = {
+      id: this.nextNetworkLogId++,
+      method: request.method(),
+      url: request.url(),
+      resourceType: request.resourceType(),
+      status: null,
+      startTime: Date.now(),
+      duration: null,
+      size: null,
+    };
+    // Decided when the request starts, so the limit counts requests in order
+    if (this.capture?.matches(entry.url)) {
+      entry.captured = true;
+      if (--this.capture.left === 0) this.capture = null;
+    }
+    this.pendingRequests.set(request, entry);
+    this.networkLog.push(entry);
+    if (this.networkLog.length > MAX_NETWORK_LOG) {
+      const dropped = this.networkLog.shift();
+      if (dropped) this.captures.delete(dropped.id);
+    }
+  }
+
+  private async logRequestEnd(request: Request, failure?: string): Promise<void> {
+    const entry = this.pendingRequests.get(request);
+    if (!entry) return;
+    this.pendingRequests.delete(request);
+    entry.duration = Date.now() - entry.startTime;
+
+    const har = this.harRecording;
+    if (har && entry.startTime >= har.startTime) {
+      har.entries.push(
+        (failure !== undefined ? Promise.resolve(null) : request.response())
+          .then((response) => buildHarEntry(request, response, entry.startTime, failure))
+          .catch(() => null)
+      );
+    }
+
+    if (entry.captured) {
+      try {
+        this.captures.set(entry.id, await captureExchange(entry.id, request, failure));
+      } catch {
+        // Page or context closed before the bodies could be read
+        entry.captured = false;
+      }
+    }
+
+    if (failure !== undefined) {
+      entry.failure = failure;
+      this.emitEvent('network', { ...entry });
+      return;
+    }
+    try {
+      const response = await request.response();
+      entry.status = response?.status() ?? null;
+      entry.size = (await request.sizes()).responseBodySize;
+    } catch {
+      // Page or context closed before sizes were available
+    }
+    this.emitEvent('network', { ...entry });
+  }
+
   /**
    * Clear tracked requests
    */
@@ -350,6 +764,90 @@ export class BrowserManager {
     await page.route(url, handler);
   }
 
+  /**
+   * Register a mocked response for requests matching `url`. Mocks apply to every page in the
+   * current context and are matched newest first.
+   */
+  async addMock(options: {
+    url: string;
+    status: number;
+    body: Buffer;
+    contentType?: string;
+    headers?: Record<string, string>;
+  }): Promise<MockInfo> {
+    const info: MockInfo = {
+      id: this.nextMockId++,
+      url: options.url,
+      status: options.status,
+      contentType: options.contentType,
+      headers: options.headers,
+      size: options.body.length,
+      hits: 0,
+    };
+    const handler = async (route: Route) => {
+      info.hits++;
+      await route.fulfill({
+        status: options.status,
+        body: options.body,
+        contentType: options.contentType,
+        headers: options.headers,
+      });
+    };
+
+    const matches = urlMatcher(options.url);
+    const matcher = (url: URL) => matches(url.href);
+    await this.currentContext().route(matcher, handler);
+    this.mocks.set(info.id, { info, matcher, handler });
+    return info;
+  }
+
+  listMocks(): MockInfo[] {
+    return [...this.mocks.values()].map((m) => m.info);
+  }
+
+  async removeMock(id: number): Promise<void> {
+    const mock = this.mocks.get(id);
+    if (!mock) {
+      throw new Error(`Mock ${id} not found. Run: agent-browser mock list`);
+    }
+    await this.currentContext().unroute(mock.matcher, mock.handler);
+    this.mocks.delete(id);
+  }
+
+  /**
+   * Abort requests whose URL matches any of the patterns (see `urlMatcher`).
+   * Returns all blocked patterns.
+   */
+  async block(patterns: string[]): Promise<string[]> {
+    const context = this.currentContext();
+    for (const pattern of patterns) {
+      if (this.blocked.has(pattern)) continue;
+      const matches = urlMatcher(pattern);
+      const matcher = (url: URL) => matches(url.href);
+      const handler = (route: Route) => route.abort('blockedbyclient');
+      await context.route(matcher, handler);
+      this.blocked.set(pattern, { matcher, handler });
+    }
+    return [...this.blocked.keys()];
+  }
+
+  /**
+   * Stop blocking the given patterns, or all of them when none are given.
+   * Returns the patterns that remain blocked.
+   */
+  async unblock(patterns?: string[]): Promise<string[]> {
+    const context = this.currentContext();
+    for (const pattern of patterns ?? [...this.blocked.keys()]) {
+      const entry = this.blocked.get(pattern);
+      if (!entry) {
+        throw new Error(`Pattern not blocked: ${pattern}`);
+      }
+      await context.unroute(entry.matcher, entry.handler);
+      this.blocked.delete(pattern);
+    }
+    return [...this.blocked.keys()];
+  }
+
   /**
    * Remove a route
    */
@@ -375,20 +873,93 @@ export class BrowserManager {
    * Set geolocation
    */
   async setGeolocation(latitude: number, longitude: number, accuracy?: number): Promise<void> {
-    const context = this.contexts[0];
+    const context = this.currentContext();
     if (context) {
       await context.setGeolocation({ latitude, longitude, accuracy });
+      // Without the permission navigator.geolocation reports PERMISSION_DENIED
+      await context.grantPermissions(['geolocation']);
     }
   }
 
   /**
-   * Set permissions
+   * Clear the emulated geolocation
    */
-  async setPermissions(permissions: string[], grant: boolean): Promise<void> {
-    const context = this.contexts[0];
+  async clearGeolocation(): Promise<void> {
+    const context = this.currentContext();
+    if (context) {
+      await context.setGeolocation(null);
+    }
+  }
+
+  /**
+   * Override the locale (Intl, navigator.language and Accept-Language) of every tab
+   * in the current context, and of tabs it opens later, via CDP; or restore the
+   * default with null. Locale is otherwise fixed when the context is created.
+   */
+  async setLocale(locale: string | null): Promise<void> {
+    const context = this.currentContext();
+    const wasSet = this.localeOverrides.has(context);
+    if (locale) {
+      this.localeOverrides.set(context, locale);
+    } else {
+      this.localeOverrides.delete(context);
+    }
+    if (locale || wasSet) {
+      for (const page of this.pages.filter((p) => p.context() === context)) {
+        await this.applyLocale(page, true);
+      }
+    }
+  }
+
+  /**
+   * Send a page its context's locale. Without `force`, pages of contexts without
+   * an override are left alone.
+   */
+  private async applyLocale(page: Page, force = false): Promise<void> {
+    const locale = this.localeOverrides.get(page.context());
+    if ((!locale && !force) || page.isClosed()) return;
+    const cdp = await this.pageSession(page);
+    await cdp.send('Emulation.setLocaleOverride', locale ? { locale } : {});
+    const userAgent =
+      this.userAgentOverride ?? ((await page.evaluate('navigator.userAgent')) as string);
+    await cdp.send('Emulation.setUserAgentOverride', { userAgent, acceptLanguage: locale ?? '' });
+  }
+
+  /**
+   * Override the timezone of every tab in the current context, and of tabs it opens
+   * later, via CDP; or restore the default with null
+   */
+  async setTimezone(timezoneId: string | null): Promise<void> {
+    const context = this.currentContext();
+    const wasSet = this.timezoneOverrides.has(context);
+    if (timezoneId) {
+      this.timezoneOverrides.set(context, timezoneId);
+    } else {
+      this.timezoneOverrides.delete(context);
+    }
+    if (timezoneId || wasSet) {
+      for (const page of this.pages.filter((p) => p.context() === context)) {
+        await this.applyTimezone(page, true);
+      }
+    }
+  }
+
+  private async applyTimezone(page: Page, force = false): Promise<void> {
+    const timezoneId = this.timezoneOverrides.get(page.context());
+    if ((!timezoneId && !force) || page.isClosed()) return;
+    const cdp = await this.pageSession(page);
+    await cdp.send('Emulation.setTimezoneOverride', { timezoneId: timezoneId ?? '' });
+  }
+
+  /**
+   * Grant permissions, to one origin or to all of them, so pages asking for
+   * them don't wait on a prompt; or clear every permission granted so far
+   */
+  async setPermissions(permissions: string[], grant: boolean, origin?: string): Promise<void> {
+    const context = this.currentContext();
     if (context) {
       if (grant) {
-        await context.grantPermissions(permissions);
+        await context.grantPermissions(permissions, origin ? { origin } : undefined);
       } else {
         await context.clearPermissions();
       }
@@ -429,6 +1000,19 @@ export class BrowserManager {
     });
   }
 
+  /**
+   * Override the user agent and touch support via CDP. Both are otherwise fixed
+   * when the context is created.
+   */
+  async emulateUserAgent(userAgent: string, hasTouch: boolean): Promise<void> {
+    const cdp = await this.getCDPSession();
+    await cdp.send('Emulation.setUserAgentOverride', { userAgent });
+    await cdp.send('Emulation.setTouchEmulationEnabled', {
+      enabled: hasTouch,
+      maxTouchPoints: hasTouch ? 5 : 0,
+    });
+  }
+
   /**
    * Clear device metrics override to restore default devicePixelRatio
    */
@@ -444,6 +1028,15 @@ export class BrowserManager {
     return devices[deviceName as keyof typeof devices];
   }
 
+  /**
+   * Resolve a device name ignoring case ("iphone 14" -> "iPhone 14")
+   */
+  resolveDeviceName(deviceName: string): string | undefined {
+    if (deviceName in devices) return deviceName;
+    const lower = deviceName.toLowerCase();
+    return Object.keys(devices).find((name) => name.toLowerCase() === lower);
+  }
+
   /**
    * List available devices
    */
@@ -457,19 +1050,89 @@ export class BrowserManager {
   startConsoleTracking(): void {
     const page = this.getPage();
     page.on('console', (msg) => {
-      this.consoleMessages.push({
-        type: msg.type(),
-        text: msg.text(),
-        timestamp: Date.now(),
-      });
+      this.recordConsoleMessage(msg.type(), msg.text());
     });
   }
 
+  private recordConsoleMessage(type: string, text: string): void {
+    const message = { seq: ++this.consoleSeq, type, text, timestamp: Date.now() };
+    this.consoleMessages.push(message);
+    this.emitEvent('console', message);
+  }
+
+  /**
+   * Subscribe to browser events. Returns a function that removes the listener.
+   */
+  subscribe(types: readonly BrowserEventType[], listener: BrowserEventListener): () => void {
+    this.eventListeners.set(listener, new Set(types));
+    return () => {
+      this.eventListeners.delete(listener);
+    };
+  }
+
   /**
-   * Get console messages
+   * Report `events` changes inside the element at `selector` to `listener`
+   * until the returned function is called. `onEnd` is called if the page
+   * navigates away first, which ends the watch.
    */
-  getConsoleMessages(): ConsoleMessage[] {
-    return this.consoleMessages;
+  async watchElement(
+    selector: string,
+    events: readonly WatchEvent[],
+    listener: (change: WatchChange) => void,
+    onEnd: () => void
+  ): Promise<() => void> {
+    const page = this.getPage();
+    if (!this.watchPages.has(page)) {
+      this.watchPages.add(page);
+      await page.exposeBinding(WATCH_BINDING, (_source, id: unknown, change: unknown) => {
+        const parsed = toWatchChange(change);
+        if (typeof id === 'string' && parsed) this.watchListeners.get(id)?.(parsed);
+      });
+    }
+
+    const id = String(this.nextWatchId++);
+    const onNavigate = (frame: Frame) => {
+      if (frame !== page.mainFrame()) return;
+      stop();
+      onEnd();
+    };
+    const stop = () => {
+      this.watchListeners.delete(id);
+      page.off('framenavigated', onNavigate);
+      page.evaluate(stopWatchScript(id)).catch(() => {});
+    };
+    this.watchListeners.set(id, listener);
+    try {
+      await this.getLocator(selector)
+        .first()
+        .evaluate(startWatchScript, { id, events, binding: WATCH_BINDING });
+    } catch (error) {
+      this.watchListeners.delete(id);
+      throw error;
+    }
+    page.on('framenavigated', onNavigate);
+    return stop;
+  }
+
+  private emitEvent(type: BrowserEventType, data: object): void {
+    if (this.eventListeners.size === 0) return;
+    const event: BrowserEvent = { type, timestamp: Date.now(), data };
+    for (const [listener, types] of this.eventListeners) {
+      if (types.has(type)) {
+        listener(event);
+      }
+    }
+  }
+
+  /**
+   * Get console messages, optionally only those at or above `level` and
+   * recorded after sequence number `since`
+   */
+  getConsoleMessages(level: ConsoleLevel = 'all', since?: number): ConsoleMessage[] {
+    const types = level === 'all' ? null : CONSOLE_LEVEL_TYPES[level];
+    return this.consoleMessages.filter(
+      (m) => (since === undefined || m.seq > since) && (types === null || types.includes(m.type))
+    );
   }
 
   /**
@@ -484,6 +1147,10 @@ export class BrowserManager {
    */
   startErrorTracking(): void {
     const page = this.getPage();
+    page.on('download', (download) => {
+      this.saveToDownloadDir(download);
+    });
+
     page.on('pageerror', (error) => {
       this.pageErrors.push({
         message: error.message,
@@ -507,40 +1174,239 @@ export class BrowserManager {
   }
 
   /**
-   * Start HAR recording
+   * Add a main-frame navigation to the history; a reload stays one entry
+   */
+  private recordHistory(url: string): void {
+    if (url === 'about:blank' || this.history[this.history.length - 1]?.url === url) return;
+    this.history.push({ index: this.nextHistoryIndex++, url, title: '', timestamp: Date.now() });
+    if (this.history.length > MAX_HISTORY) this.history.shift();
+  }
+
+  /**
+   * The most recent `limit` navigations in any tab, oldest first
    */
-  async startHarRecording(): Promise<void> {
-    // HAR is started at context level, flag for tracking
-    this.isRecordingHar = true;
+  getHistory(limit?: number): HistoryEntry[] {
+    return limit === undefined ? [...this.history] : this.history.slice(-limit);
+  }
+
+  /**
+   * The history entry numbered `index`, if it hasn't dropped off
+   */
+  getHistoryEntry(index: number): HistoryEntry | undefined {
+    return this.history.find((entry) => entry.index === index);
+  }
+
+  /**
+   * Start HAR recording. Requests that finish from now on are captured until stopHarRecording.
+   */
+  startHarRecording(path: string): void {
+    if (this.harRecording) {
+      throw new Error(`HAR recording already in progress (${this.harRecording.path})`);
+    }
+    this.harRecording = { path, startTime: Date.now(), entries: [] };
   }
 
   /**
    * Check if HAR recording
    */
   isHarRecording(): boolean {
-    return this.isRecordingHar;
+    return this.harRecording !== null;
+  }
+
+  /**
+   * Stop HAR recording and write the archive. `path` overrides the path given at start.
+   */
+  async stopHarRecording(path?: string): Promise<{ path: string; requestCount: number }> {
+    if (!this.harRecording) {
+      throw new Error('No HAR recording in progress. Run: agent-browser har start <file>');
+    }
+    const recording = this.harRecording;
+    this.harRecording = null;
+
+    const entries = (await Promise.all(recording.entries)).filter((e): e is HarEntry => e !== null);
+    entries.sort((a, b) => a.startedDateTime.localeCompare(b.startedDateTime));
+    const savePath = path ?? recording.path;
+    mkdirSync(dirname(savePath), { recursive: true });
+    writeFileSync(savePath, JSON.stringify(buildHar(entries), null, 2));
+    return { path: savePath, requestCount: entries.length };
+  }
+
+  /**
+   * Start recording the user's interactions as a script, written to `path` by
+   * stopCodegen. With `url` the page navigates there first.
+   */
+  async startCodegen(path: string, url?: string): Promise<void> {
+    if (this.codegen) {
+      throw new Error(
+        `Script recording already in progress (${this.codegen.path}). Run 'record stop' first.`
+      );
+    }
+    const page = this.getPage();
+    this.codegen = { path, actions: [], lastActionAt: 0 };
+    for (const context of this.contexts) {
+      if (this.codegenContexts.has(context)) continue;
+      this.codegenContexts.add(context);
+      await context.exposeBinding('__agentBrowserRecord', (_source, action: unknown) =>
+        this.recordAction(action)
+      );
+      await context.addInitScript(RECORDER_SCRIPT);
+    }
+    // The init script only runs on the next navigation
+    for (const open of this.pages) {
+      await open.evaluate(RECORDER_SCRIPT).catch(() => {});
+    }
+    if (url) {
+      await page.goto(url);
+    }
+    this.recordNavigation(page.url(), true);
+  }
+
+  isCodegenRecording(): boolean {
+    return this.codegen !== null;
+  }
+
+  /**
+   * Stop recording and write the script (a command list for `.json` files)
+   */
+  stopCodegen(): { path: string; actions: number } {
+    if (!this.codegen) {
+      throw new Error('No script recording in progress. Run: agent-browser record start <file.ab>');
+    }
+    const { path, actions } = this.codegen;
+    this.codegen = null;
+    const contents =
+      codegenFormat(path) === 'json'
+        ? JSON.stringify(toCommandList(actions), null, 2) + '\n'
+        : toScript(actions);
+    mkdirSync(dirname(path), { recursive: true });
+    writeFileSync(path, contents);
+    return { path, actions: actions.length };
+  }
+
+  private recordAction(action: unknown): void {
+    if (!this.codegen || !isRecordedAction(action)) return;
+    addAction(this.codegen.actions, action);
+    this.codegen.lastActionAt = Date.now();
+  }
+
+  /**
+   * Record a main-frame navigation as `open`, unless it followed a recorded
+   * action (a link or a submitted form) and replaying that action repeats it
+   */
+  private recordNavigation(url: string, force: boolean = false): void {
+    if (!this.codegen || url === 'about:blank') return;
+    if (!force && Date.now() - this.codegen.lastActionAt < 2000) return;
+    addAction(this.codegen.actions, { kind: 'open', url });
   }
 
   /**
    * Set offline mode
    */
   async setOffline(offline: boolean): Promise<void> {
-    const context = this.contexts[0];
+    const context = this.currentContext();
     if (context) {
       await context.setOffline(offline);
+      if (offline) {
+        this.offlineContexts.add(context);
+      } else {
+        this.offlineContexts.delete(context);
+      }
+      await this.applyNetworkThrottles(context);
     }
   }
 
   /**
-   * Set extra HTTP headers (global - all requests)
+   * Throttle the network of every tab in the current context, and of tabs it opens
+   * later, via CDP; or lift the limits with null. Throughputs are in kbit/s; an
+   * omitted one is unlimited.
    */
-  async setExtraHeaders(headers: Record<string, string>): Promise<void> {
-    const context = this.contexts[0];
-    if (context) {
-      await context.setExtraHTTPHeaders(headers);
+  async setNetworkThrottle(conditions: NetworkThrottle | null): Promise<void> {
+    const context = this.currentContext();
+    // Lifting limits that were never set leaves the pages alone
+    const wasThrottled = this.networkThrottles.has(context);
+    if (conditions) {
+      this.networkThrottles.set(context, conditions);
+    } else {
+      this.networkThrottles.delete(context);
+    }
+    if (conditions || wasThrottled) {
+      for (const page of this.pages.filter((p) => p.context() === context)) {
+        await this.applyNetworkThrottle(page, true);
+      }
+    }
+  }
+
+  private async applyNetworkThrottles(context: BrowserContext): Promise<void> {
+    for (const page of this.pages.filter((p) => p.context() === context)) {
+      await this.applyNetworkThrottle(page);
     }
   }
 
+  /**
+   * Send a page its context's throttle, keeping the context's offline state. Without
+   * `force`, pages of unthrottled contexts are left to Playwright's own emulation.
+   */
+  private async applyNetworkThrottle(page: Page, force = false): Promise<void> {
+    const context = page.context();
+    const conditions = this.networkThrottles.get(context);
+    if ((!conditions && !force) || page.isClosed()) return;
+    // CDP takes bytes per second, -1 meaning no limit
+    const throughput = (kbps?: number) => (kbps ? (kbps * 1000) / 8 : -1);
+    const cdp = await this.pageSession(page);
+    await cdp.send('Network.emulateNetworkConditions', {
+      offline: this.offlineContexts.has(context),
+      latency: conditions?.latencyMs ?? 0,
+      downloadThroughput: throughput(conditions?.downloadKbps),
+      uploadThroughput: throughput(conditions?.uploadKbps),
+    });
+  }
+
+  private async pageSession(page: Page): Promise<CDPSession> {
+    let cdp = this.pageSessions.get(page);
+    if (!cdp) {
+      cdp = await page.context().newCDPSession(page);
+      this.pageSessions.set(page, cdp);
+    }
+    return cdp;
+  }
+
+  /**
+   * Override the user agent for every open page and for pages opened later.
+   * Playwright fixes the user agent when a context is created, so this goes
+   * through a CDP session per page.
+   */
+  async setUserAgent(userAgent: string): Promise<void> {
+    this.userAgentOverride = userAgent;
+    for (const page of this.pages) {
+      await this.applyUserAgent(page);
+    }
+  }
+
+  private async applyUserAgent(page: Page): Promise<void> {
+    if (this.userAgentOverride === null || page.isClosed()) return;
+    const cdp = await this.pageSession(page);
+    // Keep the Accept-Language of a `set locale`, which goes with the user agent
+    await cdp.send('Emulation.setUserAgentOverride', {
+      userAgent: this.userAgentOverride,
+      acceptLanguage: this.localeOverrides.get(page.context()),
+    });
+  }
+
+  /**
+   * Set extra HTTP headers (global - all requests). With `merge`, the headers are added to the
+   * ones already set instead of replacing them. Returns the headers now in effect.
+   */
+  async setExtraHeaders(
+    headers: Record<string, string>,
+    merge: boolean = false
+  ): Promise<Record<string, string>> {
+    this.extraHeaders = merge ? { ...this.extraHeaders, ...headers } : { ...headers };
+    for (const context of this.contexts) {
+      await context.setExtraHTTPHeaders(this.extraHeaders);
+    }
+    return this.extraHeaders;
+  }
+
   /**
    * Set scoped HTTP headers (only for requests matching the origin)
    * Uses route interception to add headers only to matching requests
@@ -612,35 +1478,140 @@ export class BrowserManager {
   }
 
   /**
-   * Start tracing
+   * Start a Playwright trace of the current context. Screenshots and DOM snapshots
+   * are both recorded unless turned off.
    */
-  async startTracing(options: { screenshots?: boolean; snapshots?: boolean }): Promise<void> {
-    const context = this.contexts[0];
-    if (context) {
-      await context.tracing.start({
-        screenshots: options.screenshots ?? true,
-        snapshots: options.snapshots ?? true,
-      });
+  async startTracing(options: { screenshots?: boolean; snapshots?: boolean }): Promise<{
+    screenshots: boolean;
+    snapshots: boolean;
+  }> {
+    const context = this.currentContext();
+    if (!context) {
+      throw new Error('Browser not launched');
+    }
+    if (this.tracingContext) {
+      throw new Error("Tracing is already running. Use 'trace stop <file.zip>' first");
     }
+    const tracing = {
+      screenshots: options.screenshots ?? true,
+      snapshots: options.snapshots ?? true,
+    };
+    await context.tracing.start(tracing);
+    this.tracingContext = context;
+    return tracing;
   }
 
   /**
-   * Stop tracing and save
+   * Stop tracing and save the trace archive
    */
   async stopTracing(path: string): Promise<void> {
-    const context = this.contexts[0];
-    if (context) {
-      await context.tracing.stop({ path });
+    const context = this.tracingContext;
+    if (!context) {
+      throw new Error("No trace in progress. Use 'trace start' first");
+    }
+    this.tracingContext = null;
+    mkdirSync(dirname(path), { recursive: true });
+    await context.tracing.stop({ path });
+  }
+
+  /**
+   * Start JS and CSS coverage on the active page. Coverage is kept across
+   * navigations so a whole flow can be measured.
+   */
+  async startCoverage(): Promise<void> {
+    const page = this.getPage();
+    if (this.coveragePage) {
+      throw new Error("Coverage is already running. Use 'coverage stop' first");
+    }
+    // Remote (CDP and provider) sessions are always Chromium
+    if (this.localLaunchMode && this.localLaunchMode.browser !== 'chromium') {
+      throw new Error('Coverage is only supported in Chromium');
+    }
+    await Promise.all([
+      page.coverage.startJSCoverage({ resetOnNavigation: false }),
+      page.coverage.startCSSCoverage({ resetOnNavigation: false }),
+    ]);
+    this.coveragePage = page;
+  }
+
+  /**
+   * Stop coverage and return the raw entries for the page it was started on
+   */
+  async stopCoverage(): Promise<{ js: JSCoverageEntry[]; css: CSSCoverageEntry[] }> {
+    const page = this.coveragePage;
+    if (!page) {
+      throw new Error("No coverage in progress. Use 'coverage start' first");
     }
+    this.coveragePage = null;
+    if (page.isClosed()) {
+      throw new Error('The page coverage was started on has been closed');
+    }
+    const [js, css] = await Promise.all([
+      page.coverage.stopJSCoverage(),
+      page.coverage.stopCSSCoverage(),
+    ]);
+    return { js, css };
   }
 
   /**
    * Save storage state (cookies, localStorage, etc.)
    */
   async saveStorageState(path: string): Promise<void> {
-    const context = this.contexts[0];
-    if (context) {
-      await context.storageState({ path });
+    if (this.contexts.length > 0) {
+      await this.currentContext().storageState({ path });
+    }
+  }
+
+  /**
+   * Restore cookies and localStorage from a storage state file into the current context.
+   * localStorage is written from a scratch page whose requests are fulfilled locally,
+   * so the saved origins are never actually loaded.
+   */
+  async loadStorageState(path: string): Promise<{ cookies: number; origins: number }> {
+    const state = JSON.parse(readFileSync(path, 'utf-8')) as {
+      cookies?: Parameters<BrowserContext['addCookies']>[0];
+      origins?: Array<{ origin: string; localStorage?: Array<{ name: string; value: string }> }>;
+    };
+    const context = this.getPage().context();
+
+    const cookies = state.cookies ?? [];
+    if (cookies.length > 0) {
+      await context.addCookies(cookies);
+    }
+
+    const origins = (state.origins ?? []).filter((o) => (o.localStorage?.length ?? 0) > 0);
+    if (origins.length > 0) {
+      const page = await this.newScratchPage(context);
+      try {
+        await page.route('**/*', (route) =>
+          route.fulfill({ status: 200, contentType: 'text/html', body: '<html></html>' })
+        );
+        for (const { origin, localStorage } of origins) {
+          await page.goto(origin);
+          await page.evaluate((items) => {
+            for (const { name, value } of items) {
+              window.localStorage.setItem(name, value);
+            }
+          }, localStorage ?? []);
+        }
+      } finally {
+        await page.close();
+      }
+    }
+
+    return { cookies: cookies.length, origins: origins.length };
+  }
+
+  /**
+   * Open a page for the daemon's own use. It is not tracked as a tab, so tab
+   * indices and the active tab stay as they are while it is open.
+   */
+  private async newScratchPage(context: BrowserContext): Promise<Page> {
+    this.scratchPageContexts.add(context);
+    try {
+      return await context.newPage();
+    } finally {
+      this.scratchPageContexts.delete(context);
     }
   }
 
@@ -1046,7 +2017,8 @@ export class BrowserManager {
     if (this.isLaunched()) {
       const needsRelaunch =
         (!cdpEndpoint && this.cdpEndpoint !== null) ||
-        (!!cdpEndpoint && this.needsCdpReconnect(cdpEndpoint));
+        (!!cdpEndpoint && this.needsCdpReconnect(cdpEndpoint)) ||
+        (!cdpEndpoint && this.launchModeDiffers(options));
       if (needsRelaunch) {
         await this.close();
       } else {
@@ -1089,7 +2061,7 @@ export class BrowserManager {
 
     const launcher =
       browserType === 'firefox' ? firefox : browserType === 'webkit' ? webkit : chromium;
-    const viewport = options.viewport ?? { width: 1280, height: 720 };
+    const viewport = options.viewport ?? getDefaultViewport();
 
     // Build base args array with file access flags if enabled
     // --allow-file-access-from-files: allows file:// URLs to read other file:// URLs via XHR/fetch
@@ -1158,7 +2130,7 @@ export class BrowserManager {
       });
     }
 
-    context.setDefaultTimeout(60000);
+    context.setDefaultTimeout(getDefaultTimeout());
     this.contexts.push(context);
     this.setupContextTracking(context);
 
@@ -1169,6 +2141,24 @@ export class BrowserManager {
       this.setupPageTracking(page);
     }
     this.activePageIndex = this.pages.length > 0 ? this.pages.length - 1 : 0;
+    this.localLaunchMode = {
+      headless: !hasExtensions && (options.headless ?? true),
+      browser: browserType,
+    };
+  }
+
+  /**
+   * Whether a launch request asks for a different headless mode or browser than the
+   * locally launched one. Options left out of the request match anything.
+   */
+  private launchModeDiffers(options: LaunchCommand): boolean {
+    const mode = this.localLaunchMode;
+    if (!mode) return false;
+    const headless = options.extensions?.length ? false : options.headless;
+    return (
+      (headless !== undefined && headless !== mode.headless) ||
+      (options.browser !== undefined && options.browser !== mode.browser)
+    );
   }
 
   /**
@@ -1250,12 +2240,48 @@ export class BrowserManager {
    * Set up console, error, and close tracking for a page
    */
   private setupPageTracking(page: Page): void {
+    // Pages opened after `set user-agent`, `set locale`, `set timezone` or `network
+    // throttle` pick up the overrides too
+    this.applyUserAgent(page).catch(() => {});
+    this.applyLocale(page).catch(() => {});
+    this.applyTimezone(page).catch(() => {});
+    this.applyNetworkThrottle(page).catch(() => {});
+
+    page.on('request', (request) => this.logRequestStart(request));
+    page.on('requestfinished', (request) => this.logRequestEnd(request));
+    page.on('requestfailed', (request) =>
+      this.logRequestEnd(request, request.failure()?.errorText ?? 'failed')
+    );
+
     page.on('console', (msg) => {
-      this.consoleMessages.push({
-        type: msg.type(),
-        text: msg.text(),
-        timestamp: Date.now(),
+      this.recordConsoleMessage(msg.type(), msg.text());
+    });
+
+    page.on('dialog', (dialog) => {
+      this.emitEvent('dialog', {
+        type: dialog.type(),
+        message: dialog.message(),
+        defaultValue: dialog.defaultValue(),
+        url: page.url(),
       });
+      this.handleDialog(dialog);
+    });
+
+    page.on('framenavigated', (frame) => {
+      if (frame !== page.mainFrame()) return;
+      this.recordNavigation(frame.url());
+      this.recordHistory(frame.url());
+    });
+
+    page.on('load', () => {
+      const entry = this.history[this.history.length - 1];
+      if (!entry || entry.url !== page.url()) return;
+      page.title().then(
+        (title) => {
+          entry.title = title;
+        },
+        () => {}
+      );
     });
 
     page.on('pageerror', (error) => {
@@ -1266,6 +2292,7 @@ export class BrowserManager {
     });
 
     page.on('close', () => {
+      this.pendingDialogs = this.pendingDialogs.filter((d) => d.page() !== page);
       const index = this.pages.indexOf(page);
       if (index !== -1) {
         this.pages.splice(index, 1);
@@ -1282,6 +2309,7 @@ export class BrowserManager {
    */
   private setupContextTracking(context: BrowserContext): void {
     context.on('page', (page) => {
+      if (this.scratchPageContexts.has(context)) return;
       // Only add if not already tracked (avoids duplicates when newTab() creates pages)
       if (!this.pages.includes(page)) {
         this.pages.push(page);
@@ -1301,7 +2329,7 @@ export class BrowserManager {
     // Invalidate CDP session since we're switching to a new page
     await this.invalidateCDPSession();
 
-    const context = this.contexts[0]; // Use first context for tabs
+    const context = this.currentContext();
     const page = await context.newPage();
     // Only add if not already tracked (setupContextTracking may have already added it via 'page' event)
     if (!this.pages.includes(page)) {
@@ -1325,10 +2353,12 @@ export class BrowserManager {
     }
 
     const context = await this.browser.newContext({
-      viewport: viewport ?? { width: 1280, height: 720 },
+      viewport: viewport ?? getDefaultViewport(),
+      extraHTTPHeaders: this.extraHeaders,
     });
-    context.setDefaultTimeout(60000);
+    context.setDefaultTimeout(getDefaultTimeout());
     this.contexts.push(context);
+    this.contextNames.set(context, this.unusedContextName('window'));
     this.setupContextTracking(context);
 
     const page = await context.newPage();
@@ -1342,6 +2372,112 @@ export class BrowserManager {
     return { index: this.activePageIndex, total: this.pages.length };
   }
 
+  /**
+   * The context of the active tab
+   */
+  private currentContext(): BrowserContext {
+    return this.pages[this.activePageIndex]?.context() ?? this.contexts[0];
+  }
+
+  private contextName(context: BrowserContext): string {
+    const name = this.contextNames.get(context);
+    if (name) return name;
+    const index = this.contexts.indexOf(context);
+    return index === 0 ? 'default' : `context-${index}`;
+  }
+
+  private findContext(name: string): BrowserContext | undefined {
+    return this.contexts.find((context) => this.contextName(context) === name);
+  }
+
+  private unusedContextName(prefix: string): string {
+    let n = 1;
+    while (this.findContext(`${prefix}-${n}`)) n++;
+    return `${prefix}-${n}`;
+  }
+
+  /**
+   * Create an isolated context (its own cookies and storage) with one tab, and switch to it
+   */
+  async newContext(name?: string): Promise<{ name: string; index: number; total: number }> {
+    if (name !== undefined && this.findContext(name)) {
+      throw new Error(`Context "${name}" already exists`);
+    }
+    const contextName = name ?? this.unusedContextName('context');
+    await this.invalidateCDPSession();
+    const result = await this.newWindow();
+    this.contextNames.set(this.pages[result.index].context(), contextName);
+    return { name: contextName, ...result };
+  }
+
+  /**
+   * Every context with how many tabs it has open
+   */
+  listContexts(): Array<{ name: string; tabs: number; active: boolean }> {
+    const current = this.contexts.length > 0 ? this.currentContext() : undefined;
+    return this.contexts.map((context) => ({
+      name: this.contextName(context),
+      tabs: this.pages.filter((page) => page.context() === context).length,
+      active: context === current,
+    }));
+  }
+
+  /**
+   * Make the first tab of the context `name` active, opening one if it has none
+   */
+  async switchContext(name: string): Promise<{ name: string; index: number; url: string }> {
+    const context = this.findContext(name);
+    if (!context) {
+      throw new Error(`No context named "${name}". Run "context list" to see them.`);
+    }
+    let page = this.pages.find((p) => p.context() === context);
+    if (!page) {
+      page = await context.newPage();
+      if (!this.pages.includes(page)) {
+        this.pages.push(page);
+        this.setupPageTracking(page);
+      }
+    }
+    const { index, url } = await this.switchTo(this.pages.indexOf(page));
+    return { name, index, url };
+  }
+
+  /**
+   * Close a context (the current one by default) and all of its tabs
+   */
+  async closeContext(name?: string): Promise<{ closed: string; remaining: number }> {
+    const context = name === undefined ? this.currentContext() : this.findContext(name);
+    if (!context) {
+      throw new Error(`No context named "${name}". Run "context list" to see them.`);
+    }
+    if (this.contexts.length === 1) {
+      throw new Error('Cannot close the last context. Use "close" to close the browser.');
+    }
+    if (context === this.recordingContext) {
+      throw new Error('This context is recording a video. Run "record stop" first.');
+    }
+
+    const active = this.pages[this.activePageIndex];
+    if (active?.context() === context) {
+      await this.invalidateCDPSession();
+    }
+    const closed = this.contextName(context);
+    this.pages = this.pages.filter((page) => page.context() !== context);
+    this.contexts.splice(this.contexts.indexOf(context), 1);
+    this.contextNames.delete(context);
+    await context.close().catch(() => {});
+
+    if (this.pages.length === 0) {
+      const page = await this.contexts[0].newPage();
+      if (!this.pages.includes(page)) {
+        this.pages.push(page);
+        this.setupPageTracking(page);
+      }
+    }
+    this.activePageIndex = active ? Math.max(0, this.pages.indexOf(active)) : 0;
+    return { closed, remaining: this.contexts.length };
+  }
+
   /**
    * Invalidate the current CDP session (must be called before switching pages)
    * This ensures screencast and input injection work correctly after tab switch
@@ -1418,13 +2554,16 @@ export class BrowserManager {
   /**
    * List all tabs with their info
    */
-  async listTabs(): Promise<Array<{ index: number; url: string; title: string; active: boolean }>> {
+  async listTabs(): Promise<
+    Array<{ index: number; url: string; title: string; active: boolean; context: string }>
+  > {
     const tabs = await Promise.all(
       this.pages.map(async (page, index) => ({
         index,
         url: page.url(),
         title: await page.title().catch(() => ''),
         active: index === this.activePageIndex,
+        context: this.contextName(page.context()),
       }))
     );
     return tabs;
@@ -1616,10 +2755,12 @@ export class BrowserManager {
    * Creates a fresh browser context with video recording enabled.
    * Automatically captures current URL and transfers cookies/storage if no URL provided.
    *
-   * @param outputPath - Path to the output video file (will be .webm)
+   * @param outputPath - Path to the output video file (will be .webm), or a directory to
+   *   create a timestamped file in
    * @param url - Optional URL to navigate to (defaults to current page URL)
+   * @returns Path of the video file being recorded
    */
-  async startRecording(outputPath: string, url?: string): Promise<void> {
+  async startRecording(outputPath: string, url?: string): Promise<string> {
     if (this.recordingContext) {
       throw new Error(
         "Recording already in progress. Run 'record stop' first, or use 'record restart' to stop and start a new recording."
@@ -1630,6 +2771,14 @@ export class BrowserManager {
       throw new Error('Browser not launched. Call launch first.');
     }
 
+    // A directory (existing, or a path without an extension) gets a timestamped file name
+    const isDirectory = existsSync(outputPath) && statSync(outputPath).isDirectory();
+    if (isDirectory || extname(outputPath) === '') {
+      mkdirSync(outputPath, { recursive: true });
+      const stamp = new Date().toISOString().replace(/[:.]/g, '-');
+      outputPath = join(outputPath, `recording-${stamp}.webm`);
+    }
+
     // Check if output file already exists
     if (await Bun.file(outputPath).exists()) {
       throw new Error(`Output file already exists: ${outputPath}`);
@@ -1644,7 +2793,7 @@ export class BrowserManager {
 
     // Auto-capture current URL if none provided
     const currentPage = this.pages.length > 0 ? this.pages[this.activePageIndex] : null;
-    const currentContext = this.contexts.length > 0 ? this.contexts[0] : null;
+    const currentContext = this.contexts.length > 0 ? this.currentContext() : null;
     if (!url && currentPage) {
       const currentUrl = currentPage.url();
       if (currentUrl && currentUrl !== 'about:blank') {
@@ -1717,6 +2866,8 @@ export class BrowserManager {
     if (url) {
       await this.recordingPage.goto(url, { waitUntil: 'load' });
     }
+
+    return outputPath;
   }
 
   /**
@@ -1805,7 +2956,7 @@ export class BrowserManager {
   async restartRecording(
     outputPath: string,
     url?: string
-  ): Promise<{ previousPath?: string; stopped: boolean }> {
+  ): Promise<{ path: string; previousPath?: string; stopped: boolean }> {
     let previousPath: string | undefined;
     let stopped = false;
 
@@ -1817,9 +2968,9 @@ export class BrowserManager {
     }
 
     // Start new recording
-    await this.startRecording(outputPath, url);
+    const path = await this.startRecording(outputPath, url);
 
-    return { previousPath, stopped };
+    return { path, previousPath, stopped };
   }
 
   /**
@@ -1883,7 +3034,11 @@ export class BrowserManager {
 
     this.pages = [];
     this.contexts = [];
+    this.contextNames.clear();
     this.cdpEndpoint = null;
+    this.localLaunchMode = null;
+    this.tracingContext = null;
+    this.coveragePage = null;
     this.browserbaseSessionId = null;
     this.browserbaseApiKey = null;
     this.browserUseSessionId = null;
@@ -1893,6 +3048,8 @@ export class BrowserManager {
     this.isPersistentContext = false;
     this.activePageIndex = 0;
     this.refMap = {};
+    this.refFrame = null;
+    this.refScope = undefined;
     this.lastSnapshot = '';
     this.frameCallback = null;
   }
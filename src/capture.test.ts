@@ -0,0 +1,23 @@
+import { describe, it, expect } from 'bun:test';
+import { encodeBody } from './capture.js';
+
+describe('encodeBody', () => {
+  it('should keep text and JSON bodies as text', () => {
+    const json = Buffer.from('{"total":3}');
+    expect(encodeBody(json, 'application/json; charset=utf-8')).toEqual({ body: '{"total":3}' });
+    expect(encodeBody(Buffer.from('a=1&b=2'), 'application/x-www-form-urlencoded')).toEqual({
+      body: 'a=1&b=2',
+    });
+    expect(encodeBody(Buffer.from('plain'), undefined)).toEqual({ body: 'plain' });
+  });
+
+  it('should base64-encode binary bodies', () => {
+    const png = Buffer.from([0x89, 0x50, 0x4e, 0x47]);
+    expect(encodeBody(png, 'image/png')).toEqual({ body: 'iVBORw==', base64: true });
+  });
+
+  it('should leave out empty bodies', () => {
+    expect(encodeBody(null, 'application/json')).toEqual({});
+    expect(encodeBody(Buffer.alloc(0), 'text/html')).toEqual({});
+  });
+});
@@ -0,0 +1,80 @@
+/**
+ * Request and response bodies kept for `network capture`.
+ *
+ * The network log only has sizes and timings. A capture keeps the headers and
+ * bodies of the requests matching a URL glob, under the id of their network
+ * log entry, for `network show` to print later.
+ *
+ * Usage:
+ *   agent-browser network capture /api/cart --limit 5
+ *   agent-browser click @e4
+ *   agent-browser network log --filter /api/cart
+ *   agent-browser network show 42
+ */
+
+import type { Request } from 'playwright-core';
+
+const TEXT_TYPES = /^text\/|json|xml|javascript|ecmascript|x-www-form-urlencoded/i;
+
+export interface CapturedBody {
+  headers: Record<string, string>;
+  body?: string;
+  /** Set when `body` is base64, for binary content */
+  base64?: boolean;
+}
+
+export interface CapturedExchange {
+  id: number;
+  method: string;
+  url: string;
+  status: number | null;
+  failure?: string;
+  request: CapturedBody;
+  response: CapturedBody | null;
+}
+
+/** A body as text, or base64 when the content type isn't a text one */
+export function encodeBody(
+  data: Buffer | null,
+  contentType: string | undefined
+): { body?: string; base64?: boolean } {
+  if (!data || data.length === 0) return {};
+  if (!contentType || TEXT_TYPES.test(contentType)) {
+    return { body: data.toString('utf8') };
+  }
+  return { body: data.toString('base64'), base64: true };
+}
+
+/**
+ * Read the headers and bodies of a finished (or failed) request. Bodies that
+ * can't be read any more, such as those of redirects, are left out.
+ */
+export async function captureExchange(
+  id: number,
+  request: Request,
+  failure?: string
+): Promise<CapturedExchange> {
+  const requestHeaders = await request.allHeaders().catch(() => request.headers());
+  const exchange: CapturedExchange = {
+    id,
+    method: request.method(),
+    url: request.url(),
+    status: null,
+    request: {
+      headers: requestHeaders,
+      ...encodeBody(request.postDataBuffer(), requestHeaders['content-type']),
+    },
+    response: null,
+  };
+  if (failure !== undefined) {
+    exchange.failure = failure;
+    return exchange;
+  }
+  const response = await request.response().catch(() => null);
+  if (!response) return exchange;
+  exchange.status = response.status();
+  const headers = await response.allHeaders().catch(() => response.headers());
+  const body = await response.body().catch(() => null);
+  exchange.response = { headers, ...encodeBody(body, headers['content-type']) };
+  return exchange;
+}
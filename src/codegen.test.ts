@@ -0,0 +1,100 @@
+import { describe, it, expect } from 'bun:test';
+import {
+  type RecordedAction,
+  addAction,
+  codegenFormat,
+  isRecordedAction,
+  isScriptPath,
+  quoteArg,
+  toCommandList,
+  toScript,
+} from './codegen.js';
+
+const login: RecordedAction[] = [
+  { kind: 'open', url: 'https://example.com/login' },
+  { kind: 'fill', selector: '#email', value: 'user@example.com' },
+  { kind: 'fill', selector: 'input[name="password"]', value: '', secret: true },
+  { kind: 'check', selector: '#remember' },
+  { kind: 'select', selector: '#lang', value: 'en' },
+  { kind: 'click', selector: 'text="Sign in"' },
+  { kind: 'press', key: 'Enter' },
+];
+
+describe('toScript', () => {
+  it('should write one command per action', () => {
+    const script = toScript(login, new Date(Date.UTC(2026, 0, 1)));
+    expect(script).toBe(
+      [
+        '# Recorded with agent-browser record on 2026-01-01T00:00:00.000Z',
+        '# Password fields are filled from $PASSWORD',
+        'open https://example.com/login',
+        'fill #email user@example.com',
+        `fill 'input[name="password"]' --secret PASSWORD`,
+        'check #remember',
+        'select #lang en',
+        `click 'text="Sign in"'`,
+        'press Enter',
+        '',
+      ].join('\n')
+    );
+  });
+
+  it('should leave out values a script line cannot hold', () => {
+    const script = toScript([{ kind: 'fill', selector: '#bio', value: 'a\nb' }]);
+    expect(script).toContain('# fill #bio: multi-line value left out\n');
+  });
+});
+
+describe('toCommandList', () => {
+  it('should write daemon commands without password values', () => {
+    expect(toCommandList(login.slice(0, 3))).toEqual([
+      { action: 'navigate', url: 'https://example.com/login' },
+      { action: 'fill', selector: '#email', value: 'user@example.com' },
+      { action: 'fill', selector: 'input[name="password"]', secret: true },
+    ]);
+  });
+});
+
+describe('quoteArg', () => {
+  it('should quote only when needed', () => {
+    expect(quoteArg('#email')).toBe('#email');
+    expect(quoteArg('hello world')).toBe("'hello world'");
+    expect(quoteArg('')).toBe("''");
+    expect(quoteArg(`it's "here"`)).toBe(`"it's \\"here\\""`);
+    expect(quoteArg('total: ${price}')).toBe("'total: $${price}'");
+  });
+});
+
+describe('addAction', () => {
+  it('should keep the last value of a field', () => {
+    const actions: RecordedAction[] = [];
+    addAction(actions, { kind: 'fill', selector: '#q', value: 'sho' });
+    addAction(actions, { kind: 'fill', selector: '#q', value: 'shoes' });
+    addAction(actions, { kind: 'click', selector: '#go' });
+    addAction(actions, { kind: 'click', selector: '#go' });
+    expect(actions).toEqual([
+      { kind: 'fill', selector: '#q', value: 'shoes' },
+      { kind: 'click', selector: '#go' },
+    ]);
+  });
+});
+
+describe('isRecordedAction', () => {
+  it('should reject malformed actions from the page', () => {
+    expect(isRecordedAction({ kind: 'click', selector: '#a' })).toBe(true);
+    expect(isRecordedAction({ kind: 'fill', selector: '#a' })).toBe(false);
+    expect(isRecordedAction({ kind: 'evaluate', script: 'alert(1)' })).toBe(false);
+    expect(isRecordedAction('click')).toBe(false);
+  });
+});
+
+describe('isScriptPath', () => {
+  it('should tell scripts from videos', () => {
+    expect(isScriptPath('login.ab')).toBe(true);
+    expect(isScriptPath('steps.JSON')).toBe(true);
+    expect(isScriptPath('demo.webm')).toBe(false);
+    expect(isScriptPath('./recordings')).toBe(false);
+    expect(codegenFormat('steps.json')).toBe('json');
+    expect(codegenFormat('login.ab')).toBe('script');
+  });
+});
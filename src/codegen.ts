@@ -0,0 +1,265 @@
+/**
+ * Record what a user does in the browser as an agent-browser script.
+ *
+ * `record start <file.ab>` injects a listener into every page that reports
+ * clicks, fills, checkboxes, selects and Enter presses with a selector for
+ * the element; navigations the user starts become `open` lines. `record stop`
+ * writes them out, as script lines for `run`, or as a JSON list of commands
+ * when the file ends in `.json`.
+ *
+ * Usage:
+ *   agent-browser --headed record start login.ab https://example.com/login
+ *   # ... log in by hand ...
+ *   agent-browser record stop
+ *   agent-browser run login.ab
+ */
+
+import { extname } from 'node:path';
+
+export type RecordedAction =
+  | { kind: 'open'; url: string }
+  | { kind: 'click'; selector: string }
+  | { kind: 'fill'; selector: string; value: string; secret?: boolean }
+  | { kind: 'check'; selector: string }
+  | { kind: 'uncheck'; selector: string }
+  | { kind: 'select'; selector: string; value: string }
+  | { kind: 'press'; key: string };
+
+export type CodegenFormat = 'script' | 'json';
+
+/**
+ * Files ending in `.json` get a command list, anything else a script.
+ */
+export function codegenFormat(path: string): CodegenFormat {
+  return extname(path).toLowerCase() === '.json' ? 'json' : 'script';
+}
+
+/**
+ * Whether `path` asks for a recorded script rather than a video: `.ab`
+ * scripts and `.json` command lists.
+ */
+export function isScriptPath(path: string): boolean {
+  return ['.ab', '.json'].includes(extname(path).toLowerCase());
+}
+
+/**
+ * The environment variable recorded password fields read their value from,
+ * so the password itself never lands in the file
+ */
+export const SECRET_ENV = 'PASSWORD';
+
+// Runs in every page; the binding is exposed by BrowserManager.startCodegen.
+// A string so it reaches the page untranspiled.
+export const RECORDER_SCRIPT = `(() => {
+  if (window.__agentBrowserRecorder) return;
+  window.__agentBrowserRecorder = true;
+
+  const send = (action) => {
+    if (typeof window.__agentBrowserRecord === 'function') window.__agentBrowserRecord(action);
+  };
+  const isUnique = (selector) => {
+    try {
+      return document.querySelectorAll(selector).length === 1;
+    } catch {
+      return false;
+    }
+  };
+  const quote = (value) => '"' + value.replace(/\\\\/g, '\\\\\\\\').replace(/"/g, '\\\\"') + '"';
+  const clean = (text) => (text || '').replace(/\\s+/g, ' ').trim();
+
+  const cssPath = (el) => {
+    const steps = [];
+    const isInside = (node) => node && node.nodeType === 1 && node !== document.documentElement;
+    for (let node = el; isInside(node); node = node.parentElement) {
+      if (node.id && isUnique('#' + CSS.escape(node.id))) {
+        steps.unshift('#' + CSS.escape(node.id));
+        break;
+      }
+      const tag = node.tagName.toLowerCase();
+      const siblings = node.parentElement
+        ? Array.from(node.parentElement.children).filter((s) => s.tagName === node.tagName)
+        : [];
+      const nth = ':nth-of-type(' + (siblings.indexOf(node) + 1) + ')';
+      steps.unshift(siblings.length > 1 ? tag + nth : tag);
+    }
+    return steps.join(' > ');
+  };
+
+  const selectorFor = (el) => {
+    for (const attr of ['data-testid', 'data-test', 'data-cy']) {
+      const value = el.getAttribute(attr);
+      const selector = value && '[' + attr + '=' + quote(value) + ']';
+      if (selector && isUnique(selector)) return selector;
+    }
+    if (el.id && isUnique('#' + CSS.escape(el.id))) return '#' + CSS.escape(el.id);
+    const tag = el.tagName.toLowerCase();
+    const name = el.getAttribute('name');
+    const byName = name && tag + '[name=' + quote(name) + ']';
+    if (byName && isUnique(byName)) return byName;
+    const text = clean(el.innerText);
+    if ((tag === 'a' || tag === 'button') && text && text.length <= 40) {
+      const same = Array.from(document.querySelectorAll(tag)).filter(
+        (e) => clean(e.innerText) === text
+      );
+      if (same.length === 1) return 'text=' + quote(text);
+    }
+    return cssPath(el);
+  };
+
+  const TEXT_TYPES = ['text', 'email', 'password', 'search', 'tel', 'url', 'number', 'date',
+    'datetime-local', 'month', 'week', 'time', 'color', 'range'];
+  const isTextField = (el) =>
+    el.tagName === 'TEXTAREA' ||
+    (el.tagName === 'INPUT' &&
+      TEXT_TYPES.includes((el.getAttribute('type') || 'text').toLowerCase()));
+  const isToggle = (el) =>
+    el.tagName === 'INPUT' && (el.type === 'checkbox' || el.type === 'radio');
+  // Enter and the change that follows it report the same value once
+  const filled = new WeakMap();
+  const fill = (el) => {
+    if (filled.get(el) === el.value) return;
+    filled.set(el, el.value);
+    // Passwords never leave the page; the script reads them from the environment
+    if (el.type === 'password') {
+      send({ kind: 'fill', selector: selectorFor(el), value: '', secret: true });
+    } else {
+      send({ kind: 'fill', selector: selectorFor(el), value: el.value });
+    }
+  };
+
+  document.addEventListener('click', (e) => {
+    if (!e.isTrusted || !(e.target instanceof Element)) return;
+    const target =
+      e.target.closest('a, button, input, select, textarea, label, [role=button], [role=link]') ||
+      e.target;
+    // Fields and toggles are recorded from their change event
+    if (isTextField(target) || isToggle(target) || target.tagName === 'SELECT') return;
+    if (target.tagName === 'LABEL' && target.control) return;
+    send({ kind: 'click', selector: selectorFor(target) });
+  }, true);
+
+  document.addEventListener('change', (e) => {
+    const el = e.target;
+    if (!(el instanceof Element)) return;
+    if (isToggle(el)) {
+      const kind = el.checked || el.type === 'radio' ? 'check' : 'uncheck';
+      send({ kind, selector: selectorFor(el) });
+    } else if (el.tagName === 'SELECT') {
+      send({ kind: 'select', selector: selectorFor(el), value: el.value });
+    } else if (isTextField(el)) {
+      fill(el);
+    }
+  }, true);
+
+  document.addEventListener('keydown', (e) => {
+    if (!e.isTrusted || e.key !== 'Enter' || !(e.target instanceof Element)) return;
+    // The value is committed before the key that submits it
+    if (isTextField(e.target) && e.target.tagName !== 'TEXTAREA') fill(e.target);
+    if (e.target.tagName !== 'TEXTAREA') send({ kind: 'press', key: 'Enter' });
+  }, true);
+})();`;
+
+/**
+ * Whether a value reported by a page is a well-formed action. Pages can call
+ * the binding themselves, so nothing they send is trusted.
+ */
+export function isRecordedAction(value: unknown): value is RecordedAction {
+  if (value === null || typeof value !== 'object') return false;
+  const action = value as Record<string, unknown>;
+  const isText = (key: string) => typeof action[key] === 'string';
+  switch (action.kind) {
+    case 'open':
+      return isText('url');
+    case 'click':
+    case 'check':
+    case 'uncheck':
+      return isText('selector');
+    case 'fill':
+    case 'select':
+      return isText('selector') && isText('value');
+    case 'press':
+      return isText('key');
+    default:
+      return false;
+  }
+}
+
+/**
+ * Append `action`, folding it into the last one where that says the same:
+ * a field filled again replaces its earlier fill, and an action reported
+ * twice in a row is kept once.
+ */
+export function addAction(actions: RecordedAction[], action: RecordedAction): void {
+  const last = actions[actions.length - 1];
+  if (last && JSON.stringify(last) === JSON.stringify(action)) return;
+  if (last?.kind === 'fill' && action.kind === 'fill' && last.selector === action.selector) {
+    actions[actions.length - 1] = action;
+    return;
+  }
+  actions.push(action);
+}
+
+/**
+ * `value` as one argument of a script line, quoted when it needs to be. A
+ * `${` is written `$${` so `run` doesn't read it as a variable.
+ */
+export function quoteArg(value: string): string {
+  if (/^[\w@#.:/%+=,-]+$/.test(value)) return value;
+  // A function, since $$ in a replacement string means one $
+  const text = value.replaceAll('${', () => '$${');
+  if (!text.includes("'")) return `'${text}'`;
+  return `"${text.replace(/[\\"]/g, '\\$&')}"`;
+}
+
+function scriptLine(action: RecordedAction): string {
+  switch (action.kind) {
+    case 'open':
+      return `open ${quoteArg(action.url)}`;
+    case 'fill':
+      if (action.secret) return `fill ${quoteArg(action.selector)} --secret ${SECRET_ENV}`;
+      // Script lines can't hold a line break
+      if (action.value.includes('\n')) {
+        return `# fill ${quoteArg(action.selector)}: multi-line value left out`;
+      }
+      return `fill ${quoteArg(action.selector)} ${quoteArg(action.value)}`;
+    case 'select':
+      return `select ${quoteArg(action.selector)} ${quoteArg(action.value)}`;
+    case 'press':
+      return `press ${quoteArg(action.key)}`;
+    default:
+      return `${action.kind} ${quoteArg(action.selector)}`;
+  }
+}
+
+/**
+ * The actions as a script for `agent-browser run`
+ */
+export function toScript(actions: RecordedAction[], recordedAt: Date = new Date()): string {
+  const header = [`# Recorded with agent-browser record on ${recordedAt.toISOString()}`];
+  if (actions.some((a) => a.kind === 'fill' && a.secret)) {
+    header.push(`# Password fields are filled from $${SECRET_ENV}`);
+  }
+  return [...header, ...actions.map(scriptLine)].join('\n') + '\n';
+}
+
+/**
+ * The actions as daemon commands, without ids. Password fields have no value.
+ */
+export function toCommandList(actions: RecordedAction[]): Record<string, unknown>[] {
+  return actions.map((action) => {
+    switch (action.kind) {
+      case 'open':
+        return { action: 'navigate', url: action.url };
+      case 'fill':
+        return action.secret
+          ? { action: 'fill', selector: action.selector, secret: true }
+          : { action: 'fill', selector: action.selector, value: action.value };
+      case 'select':
+        return { action: 'select', selector: action.selector, values: action.value };
+      case 'press':
+        return { action: 'press', key: action.key };
+      default:
+        return { action: action.kind, selector: action.selector };
+    }
+  });
+}
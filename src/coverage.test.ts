@@ -0,0 +1,62 @@
+import { describe, it, expect } from 'bun:test';
+import { summarizeCoverage, toLcov, type JSCoverageEntry } from './coverage.js';
+
+// Top-level script ran once; the body of `unused` never ran
+const source = 'function unused() {\n  return 1;\n}\n\nconsole.log(2);';
+const script: JSCoverageEntry = {
+  url: 'https://example.com/app.js',
+  source,
+  functions: [
+    { functionName: '', ranges: [{ startOffset: 0, endOffset: source.length, count: 1 }] },
+    { functionName: 'unused', ranges: [{ startOffset: 0, endOffset: 33, count: 0 }] },
+  ],
+};
+
+describe('summarizeCoverage', () => {
+  it('should let nested ranges override their parent', () => {
+    const summary = summarizeCoverage([script], []);
+    expect(summary.js).toEqual({
+      files: 1,
+      totalBytes: source.length,
+      usedBytes: source.length - 33,
+    });
+  });
+
+  it('should merge overlapping CSS ranges and sort by unused bytes', () => {
+    const summary = summarizeCoverage(
+      [],
+      [
+        { url: 'a.css', text: 'x'.repeat(10), ranges: [{ start: 0, end: 10 }] },
+        {
+          url: 'b.css',
+          text: 'x'.repeat(10),
+          ranges: [
+            { start: 0, end: 4 },
+            { start: 2, end: 6 },
+          ],
+        },
+      ]
+    );
+    expect(summary.css).toEqual({ files: 2, totalBytes: 20, usedBytes: 16 });
+    expect(summary.files.map((f) => f.url)).toEqual(['b.css', 'a.css']);
+  });
+});
+
+describe('toLcov', () => {
+  it('should write per-line hit counts and skip blank lines', () => {
+    expect(toLcov([script])).toBe(
+      [
+        'TN:',
+        'SF:https://example.com/app.js',
+        'DA:1,0',
+        'DA:2,0',
+        'DA:3,0',
+        'DA:5,1',
+        'LF:4',
+        'LH:1',
+        'end_of_record',
+        '',
+      ].join('\n')
+    );
+  });
+});
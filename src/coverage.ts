@@ -0,0 +1,133 @@
+/**
+ * JS and CSS coverage summaries and LCOV export.
+ *
+ * Chromium reports JS coverage as V8 block ranges: every function has an
+ * outer range, with nested ranges for blocks that ran a different number of
+ * times. Ranges are painted largest first so the innermost count wins, which
+ * gives a per-character count for the script source.
+ *
+ * Usage:
+ *   agent-browser coverage start
+ *   agent-browser coverage stop --lcov coverage.info
+ */
+
+export interface JSCoverageEntry {
+  url: string;
+  source?: string;
+  functions: {
+    functionName: string;
+    ranges: { startOffset: number; endOffset: number; count: number }[];
+  }[];
+}
+
+export interface CSSCoverageEntry {
+  url: string;
+  text?: string;
+  ranges: { start: number; end: number }[];
+}
+
+export interface CoverageFile {
+  url: string;
+  type: 'js' | 'css';
+  totalBytes: number;
+  usedBytes: number;
+}
+
+export interface CoverageTotals {
+  files: number;
+  totalBytes: number;
+  usedBytes: number;
+}
+
+export interface CoverageSummary {
+  js: CoverageTotals;
+  css: CoverageTotals;
+  /** Sorted by unused bytes, largest first */
+  files: CoverageFile[];
+}
+
+/**
+ * Execution count for every character of a script's source.
+ */
+export function characterCounts(entry: JSCoverageEntry): Uint32Array {
+  const counts = new Uint32Array(entry.source?.length ?? 0);
+  const ranges = entry.functions
+    .flatMap((fn) => fn.ranges)
+    .sort((a, b) => b.endOffset - b.startOffset - (a.endOffset - a.startOffset));
+  for (const range of ranges) {
+    counts.fill(range.count, range.startOffset, Math.min(range.endOffset, counts.length));
+  }
+  return counts;
+}
+
+function totals(files: CoverageFile[]): CoverageTotals {
+  return {
+    files: files.length,
+    totalBytes: files.reduce((sum, f) => sum + f.totalBytes, 0),
+    usedBytes: files.reduce((sum, f) => sum + f.usedBytes, 0),
+  };
+}
+
+/**
+ * Used and total bytes per file, plus JS and CSS totals.
+ */
+export function summarizeCoverage(
+  js: JSCoverageEntry[],
+  css: CSSCoverageEntry[]
+): CoverageSummary {
+  const jsFiles: CoverageFile[] = js.map((entry) => ({
+    url: entry.url,
+    type: 'js',
+    totalBytes: entry.source?.length ?? 0,
+    usedBytes: characterCounts(entry).filter((count) => count > 0).length,
+  }));
+
+  const cssFiles: CoverageFile[] = css.map((entry) => {
+    // Ranges can overlap when a rule is matched more than once
+    const used = new Uint8Array(entry.text?.length ?? 0);
+    for (const range of entry.ranges) {
+      used.fill(1, range.start, Math.min(range.end, used.length));
+    }
+    return {
+      url: entry.url,
+      type: 'css',
+      totalBytes: used.length,
+      usedBytes: used.filter((u) => u === 1).length,
+    };
+  });
+
+  const files = [...jsFiles, ...cssFiles].sort(
+    (a, b) => b.totalBytes - b.usedBytes - (a.totalBytes - a.usedBytes)
+  );
+  return { js: totals(jsFiles), css: totals(cssFiles), files };
+}
+
+/**
+ * LCOV tracefile for the JS entries. A line's hit count is the highest count
+ * of any non-whitespace character on it; blank lines are left out.
+ */
+export function toLcov(js: JSCoverageEntry[]): string {
+  const records: string[] = [];
+  for (const entry of js) {
+    if (!entry.url || entry.source === undefined) continue;
+    const counts = characterCounts(entry);
+    const source = entry.source;
+    const lines: string[] = [];
+    let hit = 0;
+    let offset = 0;
+    source.split('\n').forEach((line, index) => {
+      let max = -1;
+      for (let i = 0; i < line.length; i++) {
+        if (line[i].trim() !== '') max = Math.max(max, counts[offset + i]);
+      }
+      if (max >= 0) {
+        lines.push(`DA:${index + 1},${max}`);
+        if (max > 0) hit++;
+      }
+      offset += line.length + 1;
+    });
+    const record = ['TN:', `SF:${entry.url}`, ...lines, `LF:${lines.length}`, `LH:${hit}`];
+    records.push([...record, 'end_of_record'].join('\n'));
+  }
+  return records.length ? records.join('\n') + '\n' : '';
+}
@@ -1,7 +1,16 @@
 import { describe, it, expect, beforeEach, afterEach } from "bun:test";
 import * as os from "os";
 import * as path from "path";
-import { getSocketDir } from "./daemon.js";
+import {
+  getConnectionInfo,
+  getPidFile,
+  getSocketDir,
+  getTokenFile,
+  isAuthorized,
+  parseListenAddress,
+  parseTimeLimit,
+  remainingLifetime,
+} from "./daemon.js";
 
 /**
  * HTTP request detection pattern used in daemon.ts to prevent cross-origin attacks.
@@ -46,6 +55,7 @@ describe("getSocketDir", () => {
     // Clear relevant env vars before each test
     delete process.env.AGENT_BROWSER_SOCKET_DIR;
     delete process.env.XDG_RUNTIME_DIR;
+    delete process.env.AGENT_BROWSER_RUNTIME_DIR;
   });
 
   afterEach(() => {
@@ -72,6 +82,20 @@ describe("getSocketDir", () => {
     });
   });
 
+  describe("AGENT_BROWSER_RUNTIME_DIR", () => {
+    it("should take priority over XDG_RUNTIME_DIR", () => {
+      process.env.AGENT_BROWSER_RUNTIME_DIR = "/srv/agent-browser";
+      process.env.XDG_RUNTIME_DIR = "/run/user/1000";
+      expect(getSocketDir()).toBe("/srv/agent-browser");
+    });
+
+    it("should be overridden by AGENT_BROWSER_SOCKET_DIR", () => {
+      process.env.AGENT_BROWSER_RUNTIME_DIR = "/srv/agent-browser";
+      process.env.AGENT_BROWSER_SOCKET_DIR = "/custom/path";
+      expect(getSocketDir()).toBe("/custom/path");
+    });
+  });
+
   describe("XDG_RUNTIME_DIR", () => {
     it("should use when AGENT_BROWSER_SOCKET_DIR is not set", () => {
       process.env.XDG_RUNTIME_DIR = "run/user/1000";
@@ -96,3 +120,94 @@ describe("getSocketDir", () => {
     });
   });
 });
+
+describe("AGENT_BROWSER_SOCKET", () => {
+  afterEach(() => {
+    delete process.env.AGENT_BROWSER_SOCKET;
+  });
+
+  it.skipIf(process.platform === "win32")("should replace the session's socket path", () => {
+    process.env.AGENT_BROWSER_SOCKET = "/tmp/ci/browser.sock";
+    expect(getConnectionInfo("work")).toEqual({ type: "unix", path: "/tmp/ci/browser.sock" });
+  });
+});
+
+describe("getTokenFile", () => {
+  it("should sit next to the pid file", () => {
+    expect(getTokenFile("work")).toBe(path.join(path.dirname(getPidFile("work")), "work.token"));
+  });
+});
+
+describe("parseListenAddress", () => {
+  it("should parse host and port", () => {
+    expect(parseListenAddress("0.0.0.0:9400", "s3cret")).toEqual({
+      hostname: "0.0.0.0",
+      port: 9400,
+    });
+    expect(parseListenAddress("[::1]:9400", "s3cret")).toEqual({ hostname: "::1", port: 9400 });
+  });
+
+  it("should reject addresses without a valid port", () => {
+    expect(() => parseListenAddress("9400", "s3cret")).toThrow();
+    expect(() => parseListenAddress("localhost:http", "s3cret")).toThrow();
+    expect(() => parseListenAddress("localhost:70000", "s3cret")).toThrow();
+  });
+
+  it("should require a token, even on loopback", () => {
+    expect(() => parseListenAddress("0.0.0.0:9400", undefined)).toThrow("AGENT_BROWSER_TOKEN");
+    expect(() => parseListenAddress("127.0.0.1:9400", undefined)).toThrow("AGENT_BROWSER_TOKEN");
+    expect(() => parseListenAddress("localhost:9400", "")).toThrow("AGENT_BROWSER_TOKEN");
+  });
+});
+
+describe("isAuthorized", () => {
+  it("should allow any request without a configured token", () => {
+    expect(isAuthorized(undefined, undefined)).toBe(true);
+  });
+
+  it("should require the configured token", () => {
+    expect(isAuthorized("secret", "secret")).toBe(true);
+    expect(isAuthorized(undefined, "secret")).toBe(false);
+    expect(isAuthorized("wrong!", "secret")).toBe(false);
+    expect(isAuthorized("secret-but-longer", "secret")).toBe(false);
+  });
+});
+
+describe("parseTimeLimit", () => {
+  it("should parse milliseconds", () => {
+    expect(parseTimeLimit("600000")).toBe(600000);
+  });
+
+  it("should treat unset, 0 and garbage as no limit", () => {
+    expect(parseTimeLimit(undefined)).toBeNull();
+    expect(parseTimeLimit("0")).toBeNull();
+    expect(parseTimeLimit("10m")).toBeNull();
+  });
+});
+
+describe("remainingLifetime", () => {
+  const lifetime = { startedAt: 0, lastActivity: 1000, idleTimeout: 5000, maxLifetime: 10000 };
+
+  it("should count down from the last activity and the start", () => {
+    expect(remainingLifetime(lifetime, 3000, false)).toEqual({
+      idleRemaining: 3000,
+      lifetimeRemaining: 7000,
+    });
+    expect(remainingLifetime(lifetime, 20000, false)).toEqual({
+      idleRemaining: 0,
+      lifetimeRemaining: 0,
+    });
+  });
+
+  it("should not count idle time while busy", () => {
+    expect(remainingLifetime(lifetime, 9000, true).idleRemaining).toBe(5000);
+  });
+
+  it("should report disabled limits as null", () => {
+    const unlimited = { ...lifetime, idleTimeout: null, maxLifetime: null };
+    expect(remainingLifetime(unlimited, 3000, false)).toEqual({
+      idleRemaining: null,
+      lifetimeRemaining: null,
+    });
+  });
+});
@@ -1,12 +1,27 @@
-import { join } from 'node:path';
-import { mkdirSync } from 'node:fs';
+import type { Socket } from 'bun';
+import { dirname, join } from 'node:path';
+import { mkdirSync, readFileSync, writeFileSync } from 'node:fs';
 import { tmpdir, homedir } from 'node:os';
-import { BrowserManager } from './browser.js';
+import { randomBytes, timingSafeEqual } from 'node:crypto';
+import { BrowserManager, BROWSER_EVENT_TYPES } from './browser.js';
 import { IOSManager } from './ios-manager.js';
-import { parseCommand, serializeResponse, errorResponse } from './protocol.js';
-import { executeCommand } from './actions.js';
+import { WATCH_EVENTS, type WatchEndReason } from './watch.js';
+import {
+  parseCommand,
+  serializeResponse,
+  encodeResponse,
+  errorResponse,
+  successResponse,
+  PROTOCOL_VERSION,
+  SUPPORTED_ACTIONS,
+  SUPPORTED_COMPRESSION,
+} from './protocol.js';
+import type { DaemonStatusData, HelloData, Response, StatusData } from './types.js';
+import { CommandError, executeCommand, toAIFriendlyError } from './actions.js';
 import { executeIOSCommand } from './ios-actions.js';
 import { StreamServer } from './stream-server.js';
+import { AuditLog, auditLogPath } from './audit-log.js';
+import { parseBrowsers, runInstall } from './install.js';
 
 // Manager type - either desktop browser or iOS
 type Manager = BrowserManager | IOSManager;
@@ -26,8 +41,14 @@ const cleaningUp = new Set<string>();
 // Default stream port (can be overridden with AGENT_BROWSER_STREAM_PORT)
 const DEFAULT_STREAM_PORT = 9223;
 
+// How often the daemon checks its idle timeout and max lifetime
+const LIFETIME_CHECK_INTERVAL = 1000;
+
 const MAX_BUFFER_SIZE = 1024 * 1024 * 4; // 4MB
 
+// Commands still running, by id, so a `cancel` from another connection can abort them
+const inFlight = new Map<string, AbortController>();
+
 /**
  * Set the current session
  */
@@ -58,21 +79,27 @@ function getPortForSession(session: string): number {
 
 /**
  * Get the base directory for socket/pid files.
- * Priority: AGENT_BROWSER_SOCKET_DIR > XDG_RUNTIME_DIR > ~/.agent-browser > tmpdir
+ * Priority: AGENT_BROWSER_SOCKET_DIR > AGENT_BROWSER_RUNTIME_DIR > XDG_RUNTIME_DIR >
+ * ~/.agent-browser > tmpdir
  */
 export function getAppDir(): string {
-  // 1. XDG_RUNTIME_DIR (Linux standard)
+  // 1. Explicit runtime directory (also holds temporary screenshots and PDFs)
+  if (process.env.AGENT_BROWSER_RUNTIME_DIR) {
+    return process.env.AGENT_BROWSER_RUNTIME_DIR;
+  }
+
+  // 2. XDG_RUNTIME_DIR (Linux standard)
   if (process.env.XDG_RUNTIME_DIR) {
     return join(process.env.XDG_RUNTIME_DIR, 'agent-browser');
   }
 
-  // 2. Home directory fallback (like Docker Desktop's ~/.docker/run/)
+  // 3. Home directory fallback (like Docker Desktop's ~/.docker/run/)
   const homeDir = homedir();
   if (homeDir) {
     return join(homeDir, '.agent-browser');
   }
 
-  // 3. Last resort: temp dir
+  // 4. Last resort: temp dir
   return join(tmpdir(), 'agent-browser');
 }
 
@@ -92,7 +119,85 @@ export function getSocketAddress(session?: string): string {
   if (isWindows) {
     return String(getPortForSession(sess));
   }
-  return join(getSocketDir(), `${sess}.sock`);
+  return getSocketPath(sess);
+}
+
+/**
+ * Unix socket path for a session: AGENT_BROWSER_SOCKET when set, otherwise
+ * `<session>.sock` in the socket directory
+ */
+function getSocketPath(session: string): string {
+  return process.env.AGENT_BROWSER_SOCKET || join(getSocketDir(), `${session}.sock`);
+}
+
+/**
+ * Parse AGENT_BROWSER_LISTEN (`host:port`) into TCP listen options. Clients on
+ * other machines reach the daemon there with `--remote tcp://host:port`. Any
+ * user who can reach the port could drive the browser, so a token is required,
+ * even on loopback.
+ */
+export function parseListenAddress(
+  address: string,
+  token: string | undefined
+): { hostname: string; port: number } {
+  if (!token) {
+    throw new Error(`Refusing to listen on ${address} without AGENT_BROWSER_TOKEN`);
+  }
+  const idx = address.lastIndexOf(':');
+  const hostname = address.slice(0, idx).replace(/^\[(.*)\]$/, '$1');
+  const port = Number(address.slice(idx + 1));
+  if (!hostname || !Number.isInteger(port) || port < 1 || port > 65535) {
+    throw new Error(`Invalid AGENT_BROWSER_LISTEN '${address}': expected host:port`);
+  }
+  return { hostname, port };
+}
+
+/**
+ * Check a request's token against AGENT_BROWSER_TOKEN. Without a configured
+ * token every request is allowed.
+ */
+export function isAuthorized(token: string | undefined, expected: string | undefined): boolean {
+  if (!expected) return true;
+  if (token === undefined) return false;
+  const given = Buffer.from(token);
+  const wanted = Buffer.from(expected);
+  return given.length === wanted.length && timingSafeEqual(given, wanted);
+}
+
+/**
+ * When an unattended daemon shuts itself down. Timeouts are in ms; null disables them.
+ */
+export interface Lifetime {
+  startedAt: number;
+  lastActivity: number;
+  idleTimeout: number | null;
+  maxLifetime: number | null;
+}
+
+/**
+ * Parse AGENT_BROWSER_IDLE_TIMEOUT / AGENT_BROWSER_MAX_LIFETIME (ms). Unset, 0 or
+ * anything unparseable means no limit.
+ */
+export function parseTimeLimit(value: string | undefined): number | null {
+  const ms = value ? Number(value) : NaN;
+  return Number.isInteger(ms) && ms > 0 ? ms : null;
+}
+
+/**
+ * Time left before each limit shuts the daemon down, or null when that limit is off.
+ * The idle clock doesn't run while commands are in flight.
+ */
+export function remainingLifetime(
+  lifetime: Lifetime,
+  now: number,
+  busy: boolean
+): { idleRemaining: number | null; lifetimeRemaining: number | null } {
+  const { startedAt, lastActivity, idleTimeout, maxLifetime } = lifetime;
+  const idleSince = busy ? now : lastActivity;
+  return {
+    idleRemaining: idleTimeout === null ? null : Math.max(0, idleSince + idleTimeout - now),
+    lifetimeRemaining: maxLifetime === null ? null : Math.max(0, startedAt + maxLifetime - now),
+  };
 }
 
 /**
@@ -111,6 +216,15 @@ export function getPidFile(session?: string): string {
   return join(getSocketDir(), `${sess}.pid`);
 }
 
+/**
+ * Get the token file path for the current session. Holds the secret local
+ * clients must send with every request.
+ */
+export function getTokenFile(session?: string): string {
+  const sess = session ?? currentSession;
+  return join(getSocketDir(), `${sess}.token`);
+}
+
 /**
  * Check if daemon is running for the current session
  */
@@ -141,7 +255,7 @@ export function getConnectionInfo(
   if (isWindows) {
     return { type: 'tcp', port: getPortForSession(sess) };
   }
-  return { type: 'unix', path: join(getSocketDir(), `${sess}.sock`) };
+  return { type: 'unix', path: getSocketPath(sess) };
 }
 
 /**
@@ -157,8 +271,10 @@ export async function cleanupSocket(session?: string): Promise<void> {
   try {
     const pidFile = Bun.file(getPidFile(session));
     const streamPortFile = Bun.file(getStreamPortFile(session));
+    const tokenFile = Bun.file(getTokenFile(session));
     if (await pidFile.exists()) await pidFile.delete();
     if (await streamPortFile.exists()) await streamPortFile.delete();
+    if (await tokenFile.exists()) await tokenFile.delete();
     if (isWindows) {
       const portFile = Bun.file(getPortFile(session));
       if (await portFile.exists()) await portFile.delete();
@@ -181,11 +297,85 @@ export function getStreamPortFile(session?: string): string {
   return join(getSocketDir(), `${sess}.stream`);
 }
 
+let cachedVersion: string | null = null;
+
+/**
+ * The agent-browser version this daemon was installed as, for `hello`. A copy
+ * the CLI extracted from its binary has no package.json beside it and is told
+ * its version instead.
+ */
+function daemonVersion(): string {
+  if (cachedVersion === null && process.env.AGENT_BROWSER_DAEMON_VERSION) {
+    cachedVersion = process.env.AGENT_BROWSER_DAEMON_VERSION;
+  }
+  if (cachedVersion === null) {
+    try {
+      const pkg = JSON.parse(readFileSync(join(import.meta.dir, '..', 'package.json'), 'utf8'));
+      cachedVersion = typeof pkg.version === 'string' ? pkg.version : 'unknown';
+    } catch {
+      cachedVersion = 'unknown';
+    }
+  }
+  return cachedVersion;
+}
+
+/**
+ * Resolves with an error response carrying the abort reason once `signal` is aborted
+ */
+function whenAborted(id: string, signal: AbortSignal): Promise<Response> {
+  return new Promise((resolve) => {
+    signal.addEventListener(
+      'abort',
+      () => {
+        const reason: unknown = signal.reason;
+        const message = reason instanceof Error ? reason.message : 'Cancelled';
+        const code = reason instanceof CommandError ? reason.code : 'cancelled';
+        resolve(errorResponse(id, message, code));
+      },
+      { once: true }
+    );
+  });
+}
+
 type SocketData = {
   chunks: string[];
   httpChecked: boolean;
+  // Output the socket couldn't take yet, written on drain
+  backlog: Uint8Array[];
+  unsubscribe?: () => void;
 };
 
+/**
+ * Write to a client, queueing whatever the socket doesn't accept right away so
+ * multi-megabyte responses arrive whole
+ */
+function send(socket: Socket<SocketData>, data: string | Uint8Array): void {
+  const bytes = typeof data === 'string' ? Buffer.from(data) : data;
+  if (socket.data.backlog.length > 0) {
+    socket.data.backlog.push(bytes);
+    return;
+  }
+  const written = socket.write(bytes);
+  if (written < bytes.length) {
+    socket.data.backlog.push(bytes.subarray(Math.max(written, 0)));
+  }
+}
+
+/**
+ * Write queued output until the socket is full again
+ */
+function flush(socket: Socket<SocketData>): void {
+  const { backlog } = socket.data;
+  while (backlog.length > 0) {
+    const written = socket.write(backlog[0]);
+    if (written < backlog[0].length) {
+      backlog[0] = backlog[0].subarray(Math.max(written, 0));
+      return;
+    }
+    backlog.shift();
+  }
+}
+
 /**
  * Start the daemon server
  * @param options.streamPort Port for WebSocket stream server (0 to disable)
@@ -195,9 +385,12 @@ export async function startDaemon(options?: {
   streamPort?: number;
   provider?: string;
 }): Promise<void> {
-  // Ensure socket directory exists
+  // Ensure socket directory exists, private to this user when we create it
   const socketDir = getSocketDir();
-  mkdirSync(socketDir, { recursive: true });
+  mkdirSync(socketDir, { recursive: true, mode: 0o700 });
+  if (!isWindows) {
+    mkdirSync(dirname(getSocketAddress()), { recursive: true, mode: 0o700 });
+  }
 
   // Clean up any stale socket
   await cleanupSocket();
@@ -209,6 +402,48 @@ export async function startDaemon(options?: {
   // Create appropriate manager
   const manager: Manager = isIOS ? new IOSManager() : new BrowserManager();
   let shuttingDown = false;
+  // The browser launch the first commands wait for, so requests that arrive together
+  // on one connection don't each start a browser
+  let autoLaunch: Promise<void> | null = null;
+
+  // Unattended daemons don't keep a browser around forever; `daemon_config` changes the limits
+  const startedAt = Date.now();
+  const lifetime: Lifetime = {
+    startedAt,
+    lastActivity: startedAt,
+    idleTimeout: parseTimeLimit(process.env.AGENT_BROWSER_IDLE_TIMEOUT),
+    maxLifetime: parseTimeLimit(process.env.AGENT_BROWSER_MAX_LIFETIME),
+  };
+  const auditPath = auditLogPath(process.env.AGENT_BROWSER_AUDIT_LOG, currentSession, homedir());
+  const auditLog = auditPath ? new AuditLog(auditPath, currentSession) : null;
+  const daemonStatus = (): DaemonStatusData => {
+    const now = Date.now();
+    return {
+      pid: process.pid,
+      version: daemonVersion(),
+      startedAt,
+      uptime: now - startedAt,
+      idleTimeout: lifetime.idleTimeout,
+      maxLifetime: lifetime.maxLifetime,
+      ...remainingLifetime(lifetime, now, inFlight.size > 0),
+    };
+  };
+  // Tab titles are left out: a page stuck in a script would hold up the answer
+  const sessionStatus = (): StatusData => {
+    const desktop = manager instanceof BrowserManager && manager.isLaunched() ? manager : null;
+    const activeIndex = desktop?.getActiveIndex();
+    return {
+      ...daemonStatus(),
+      memory: process.memoryUsage().rss,
+      launched: manager.isLaunched(),
+      browserVersion: desktop?.getBrowser()?.version() ?? null,
+      tabs: (desktop?.getPages() ?? []).map((page, index) => ({
+        index,
+        url: page.url(),
+        active: index === activeIndex,
+      })),
+    };
+  };
 
   // Start stream server if port is specified (or use default if env var is set)
   // Note: Stream server only works with BrowserManager (desktop), not iOS
@@ -227,14 +462,30 @@ export async function startDaemon(options?: {
     await Bun.write(streamPortFile, streamPort.toString());
   }
 
+  // A TCP listen address (for remote clients) replaces the session's socket, and
+  // needs a token
+  const listenAddress = process.env.AGENT_BROWSER_LISTEN;
+  const envToken = process.env.AGENT_BROWSER_TOKEN || undefined;
+  const remoteListen = listenAddress ? parseListenAddress(listenAddress, envToken) : null;
+
+  // Local clients read the session's token from a file only this user can open, so
+  // other users who can reach the socket (or, on Windows, the port) can't drive it
+  let authToken = envToken;
+  if (!remoteListen) {
+    authToken ??= randomBytes(32).toString('hex');
+    writeFileSync(getTokenFile(), authToken, { mode: 0o600 });
+  }
+
   const pidFile = getPidFile();
 
   // Write PID file before listening
   await Bun.write(pidFile, process.pid.toString());
 
-  const listenOptions: any = isWindows
-    ? { hostname: '127.0.0.1', port: getPortForSession(currentSession) }
-    : { unix: getSocketAddress() };
+  const listenOptions: any =
+    remoteListen ??
+    (isWindows
+      ? { hostname: '127.0.0.1', port: getPortForSession(currentSession) }
+      : { unix: getSocketAddress() });
 
   if (isWindows) {
     await Bun.write(getPortFile(), listenOptions.port.toString());
@@ -248,7 +499,7 @@ export async function startDaemon(options?: {
       ...listenOptions,
       socket: {
         open(socket) {
-          socket.data = { chunks: [], httpChecked: false };
+          socket.data = { chunks: [], httpChecked: false, backlog: [] };
         },
         async data(socket, data) {
           let socketData = socket.data;
@@ -279,20 +530,100 @@ export async function startDaemon(options?: {
             }
           }
 
-          while (buffer.includes('\n')) {
-            const newlineIdx = buffer.indexOf('\n');
-            const line = buffer.substring(0, newlineIdx);
-            buffer = buffer.substring(newlineIdx + 1);
-
-            if (!line.trim()) continue;
-
+          // Take the complete lines before awaiting anything. A client that
+          // pipelines requests can trigger the next data event while these run.
+          const lastNewline = buffer.lastIndexOf('\n');
+          const rest = buffer.slice(lastNewline + 1);
+          socketData.chunks = rest ? [rest] : [];
+          buffer = buffer.slice(0, lastNewline + 1);
+
+          // Each request runs on its own and is answered with its id, so a client can have
+          // several in flight on one connection and a slow command doesn't hold up the rest
+          const handle = async (line: string): Promise<void> => {
+            // Answer failures with the request's id so clients can match them up
+            let commandId = 'error';
             try {
               const parseResult = parseCommand(line);
 
               if (!parseResult.success) {
-                const resp = errorResponse(parseResult.id ?? 'unknown', parseResult.error);
-                socket.write(serializeResponse(resp) + '\n');
-                continue;
+                const resp = errorResponse(
+                  parseResult.id ?? 'unknown',
+                  parseResult.error,
+                  'invalid_command'
+                );
+                send(socket, serializeResponse(resp) + '\n');
+                return;
+              }
+              commandId = parseResult.command.id;
+
+              if (!isAuthorized(parseResult.command.token, authToken)) {
+                send(
+                  socket,
+                  serializeResponse(errorResponse(commandId, 'Unauthorized', 'unauthorized')) + '\n'
+                );
+                return;
+              }
+
+              // Checking on the daemon doesn't keep it alive
+              const action = parseResult.command.action;
+              if (action !== 'daemon_status' && action !== 'status') {
+                lifetime.lastActivity = Date.now();
+              }
+
+              // Handshake - answered without launching a browser
+              if (parseResult.command.action === 'hello') {
+                const data: HelloData = {
+                  protocolVersion: PROTOCOL_VERSION,
+                  version: daemonVersion(),
+                  actions: SUPPORTED_ACTIONS,
+                  compression: SUPPORTED_COMPRESSION,
+                };
+                send(
+                  socket,
+                  serializeResponse(successResponse(parseResult.command.id, data)) + '\n'
+                );
+                return;
+              }
+
+              // Abort a command another connection is waiting on (sent by the CLI on Ctrl+C)
+              if (parseResult.command.action === 'cancel') {
+                const controller = inFlight.get(parseResult.command.commandId);
+                controller?.abort(new CommandError('Cancelled', 'cancelled'));
+                const data = { cancelled: controller !== undefined };
+                send(
+                  socket,
+                  serializeResponse(successResponse(parseResult.command.id, data)) + '\n'
+                );
+                return;
+              }
+
+              // Lifetime status and limits - answered without launching a browser
+              if (parseResult.command.action === 'daemon_status') {
+                send(
+                  socket,
+                  serializeResponse(successResponse(parseResult.command.id, daemonStatus())) + '\n'
+                );
+                return;
+              }
+              if (parseResult.command.action === 'daemon_config') {
+                const { idleTimeout, maxLifetime } = parseResult.command;
+                if (idleTimeout !== undefined) lifetime.idleTimeout = idleTimeout || null;
+                if (maxLifetime !== undefined) lifetime.maxLifetime = maxLifetime || null;
+                send(
+                  socket,
+                  serializeResponse(successResponse(parseResult.command.id, daemonStatus())) + '\n'
+                );
+                return;
+              }
+
+              // Session status - answered without launching a browser
+              if (parseResult.command.action === 'status') {
+                send(
+                  socket,
+                  serializeResponse(successResponse(parseResult.command.id, sessionStatus())) +
+                    '\n'
+                );
+                return;
               }
 
               // Handle device_list specially - works without a session, always uses IOSManager
@@ -305,14 +636,15 @@ export async function startDaemon(options?: {
                     success: true as const,
                     data: { devices },
                   };
-                  socket.write(serializeResponse(response) + '\n');
+                  send(socket, serializeResponse(response) + '\n');
                 } catch (err) {
                   const message = err instanceof Error ? err.message : String(err);
-                  socket.write(
+                  send(
+                    socket,
                     serializeResponse(errorResponse(parseResult.command.id, message)) + '\n'
                   );
                 }
-                continue;
+                return;
               }
 
               // Auto-launch if not already launched and this isn't a launch/close command
@@ -321,89 +653,236 @@ export async function startDaemon(options?: {
                 parseResult.command.action !== 'launch' &&
                 parseResult.command.action !== 'close'
               ) {
-                if (isIOS && manager instanceof IOSManager) {
-                  const cmd = parseResult.command as { iosDevice?: string };
-                  const iosDevice = cmd.iosDevice || process.env.AGENT_BROWSER_IOS_DEVICE;
-                  await manager.launch({
-                    device: iosDevice,
-                    udid: process.env.AGENT_BROWSER_IOS_UDID,
-                  });
-                } else if (manager instanceof BrowserManager) {
-                  const extensions = process.env.AGENT_BROWSER_EXTENSIONS
-                    ? process.env.AGENT_BROWSER_EXTENSIONS.split(',')
-                        .map((p) => p.trim())
-                        .filter(Boolean)
-                    : undefined;
-
-                  const argsEnv = process.env.AGENT_BROWSER_ARGS;
-                  const args = argsEnv
-                    ? argsEnv
-                        .split(/[,\n]/)
-                        .map((a) => a.trim())
-                        .filter((a) => a.length > 0)
-                    : undefined;
-
-                  const proxyServer = process.env.AGENT_BROWSER_PROXY;
-                  const proxyBypass = process.env.AGENT_BROWSER_PROXY_BYPASS;
-                  const proxy = proxyServer
-                    ? {
-                        server: proxyServer,
-                        ...(proxyBypass && { bypass: proxyBypass }),
-                      }
-                    : undefined;
-
-                  const ignoreHTTPSErrors = process.env.AGENT_BROWSER_IGNORE_HTTPS_ERRORS === '1';
-                  const allowFileAccess = process.env.AGENT_BROWSER_ALLOW_FILE_ACCESS === '1';
-                  await manager.launch({
-                    id: 'auto',
-                    action: 'launch' as const,
-                    headless: process.env.AGENT_BROWSER_HEADED !== '1',
-                    executablePath: process.env.AGENT_BROWSER_EXECUTABLE_PATH,
-                    extensions: extensions,
-                    profile: process.env.AGENT_BROWSER_PROFILE,
-                    storageState: process.env.AGENT_BROWSER_STATE,
-                    args,
-                    userAgent: process.env.AGENT_BROWSER_USER_AGENT,
-                    proxy,
-                    ignoreHTTPSErrors: ignoreHTTPSErrors,
-                    allowFileAccess: allowFileAccess,
-                  });
+                autoLaunch ??= (async () => {
+                  if (isIOS && manager instanceof IOSManager) {
+                    const cmd = parseResult.command as { iosDevice?: string };
+                    const iosDevice = cmd.iosDevice || process.env.AGENT_BROWSER_IOS_DEVICE;
+                    await manager.launch({
+                      device: iosDevice,
+                      udid: process.env.AGENT_BROWSER_IOS_UDID,
+                    });
+                  } else if (manager instanceof BrowserManager) {
+                    const extensions = process.env.AGENT_BROWSER_EXTENSIONS
+                      ? process.env.AGENT_BROWSER_EXTENSIONS.split(',')
+                          .map((p) => p.trim())
+                          .filter(Boolean)
+                      : undefined;
+
+                    const argsEnv = process.env.AGENT_BROWSER_ARGS;
+                    const args = argsEnv
+                      ? argsEnv
+                          .split(/[,\n]/)
+                          .map((a) => a.trim())
+                          .filter((a) => a.length > 0)
+                      : undefined;
+
+                    const proxyServer = process.env.AGENT_BROWSER_PROXY;
+                    const proxyBypass = process.env.AGENT_BROWSER_PROXY_BYPASS;
+                    const proxy = proxyServer
+                      ? {
+                          server: proxyServer,
+                          ...(proxyBypass && { bypass: proxyBypass }),
+                        }
+                      : undefined;
+
+                    const ignoreHTTPSErrors = process.env.AGENT_BROWSER_IGNORE_HTTPS_ERRORS === '1';
+                    const allowFileAccess = process.env.AGENT_BROWSER_ALLOW_FILE_ACCESS === '1';
+                    const browserEnv = process.env.AGENT_BROWSER_BROWSER;
+                    const browserType =
+                      browserEnv === 'firefox' || browserEnv === 'webkit' ? browserEnv : undefined;
+                    await manager.launch({
+                      id: 'auto',
+                      action: 'launch' as const,
+                      headless: process.env.AGENT_BROWSER_HEADED !== '1',
+                      browser: browserType,
+                      executablePath: process.env.AGENT_BROWSER_EXECUTABLE_PATH,
+                      extensions: extensions,
+                      profile: process.env.AGENT_BROWSER_PROFILE,
+                      storageState: process.env.AGENT_BROWSER_STATE,
+                      args,
+                      userAgent: process.env.AGENT_BROWSER_USER_AGENT,
+                      proxy,
+                      ignoreHTTPSErrors: ignoreHTTPSErrors,
+                      allowFileAccess: allowFileAccess,
+                    });
+                  }
+                })().finally(() => {
+                  autoLaunch = null;
+                });
+                await autoLaunch;
+              }
+
+              // Handle subscribe specially - acknowledges, then keeps writing event lines
+              // to this socket until the client disconnects
+              if (parseResult.command.action === 'subscribe') {
+                if (!(manager instanceof BrowserManager)) {
+                  send(
+                    socket,
+                    serializeResponse(
+                      errorResponse(parseResult.command.id, 'Events are not supported on iOS')
+                    ) + '\n'
+                  );
+                  return;
                 }
+                const types = parseResult.command.types ?? [...BROWSER_EVENT_TYPES];
+                socketData.unsubscribe?.();
+                socketData.unsubscribe = manager.subscribe(types, (event) => {
+                  send(
+                    socket,
+                    JSON.stringify({
+                      event: event.type,
+                      timestamp: event.timestamp,
+                      data: event.data,
+                    }) + '\n'
+                  );
+                });
+                const ack = {
+                  id: parseResult.command.id,
+                  success: true as const,
+                  data: { subscribed: types },
+                };
+                send(socket, serializeResponse(ack) + '\n');
+                auditLog?.record(parseResult.command, ack, Date.now());
+                return;
               }
 
-              // Handle close command specially - triggers full shutdown
+              // Handle watch like subscribe: acknowledges, then writes a line per DOM change
+              // until the client disconnects, or a last watch_end line if it stops first
+              if (parseResult.command.action === 'watch') {
+                const command = parseResult.command;
+                if (!(manager instanceof BrowserManager)) {
+                  send(
+                    socket,
+                    serializeResponse(
+                      errorResponse(command.id, 'Watching is not supported on iOS')
+                    ) + '\n'
+                  );
+                  return;
+                }
+                const events = command.events ?? [...WATCH_EVENTS];
+                let timer: ReturnType<typeof setTimeout> | undefined;
+                let stop: (() => void) | undefined;
+                const end = (reason: WatchEndReason) => {
+                  clearTimeout(timer);
+                  stop?.();
+                  send(
+                    socket,
+                    JSON.stringify({
+                      event: 'watch_end',
+                      timestamp: Date.now(),
+                      data: { reason },
+                    }) + '\n'
+                  );
+                };
+                socketData.unsubscribe?.();
+                try {
+                  stop = await manager.watchElement(
+                    command.selector,
+                    events,
+                    (change) => {
+                      send(
+                        socket,
+                        JSON.stringify({ event: 'mutation', timestamp: Date.now(), data: change }) +
+                          '\n'
+                      );
+                      if (command.once) end('change');
+                    },
+                    () => end('navigated')
+                  );
+                } catch (error) {
+                  const message = toAIFriendlyError(error, command.selector).message;
+                  send(socket, serializeResponse(errorResponse(command.id, message)) + '\n');
+                  return;
+                }
+                socketData.unsubscribe = () => {
+                  clearTimeout(timer);
+                  stop?.();
+                };
+                if (command.timeout !== undefined) {
+                  timer = setTimeout(() => end('timeout'), command.timeout);
+                }
+                const ack = {
+                  id: command.id,
+                  success: true as const,
+                  data: { watching: command.selector, events },
+                };
+                send(socket, serializeResponse(ack) + '\n');
+                auditLog?.record(command, ack, Date.now());
+                return;
+              }
+
+              // Handle close command specially - triggers full shutdown unless it keeps the daemon
               if (parseResult.command.action === 'close') {
+                const closeStartedAt = Date.now();
                 const response =
                   isIOS && manager instanceof IOSManager
                     ? await executeIOSCommand(parseResult.command, manager)
                     : await executeCommand(parseResult.command, manager as BrowserManager);
-                socket.write(serializeResponse(response) + '\n');
+                auditLog?.record(parseResult.command, response, closeStartedAt);
+                send(socket, encodeResponse(response, parseResult.command.framing));
 
-                if (!shuttingDown) {
+                // A close that failed (its state couldn't be saved) leaves everything running
+                if (response.success && !parseResult.command.keepDaemon && !shuttingDown) {
                   setTimeout(() => shutdown(), 100);
                 }
                 return;
               }
 
-              // Execute command with appropriate handler
-              const response =
-                isIOS && manager instanceof IOSManager
-                  ? await executeIOSCommand(parseResult.command, manager)
-                  : await executeCommand(parseResult.command, manager as BrowserManager);
-              socket.write(serializeResponse(response) + '\n');
+              // Execute command with appropriate handler. A cancel or the deadline answers it
+              // right away; handlers that poll stop at the signal, Playwright waits run out
+              // their own timeout.
+              const controller = new AbortController();
+              inFlight.set(commandId, controller);
+              const { action, deadline } = parseResult.command;
+              const timer =
+                deadline === undefined
+                  ? undefined
+                  : setTimeout(
+                      () =>
+                        controller.abort(
+                          new CommandError(`${action} timed out (deadline exceeded)`, 'timeout')
+                        ),
+                      Math.max(0, deadline - Date.now())
+                    );
+              try {
+                const startedAt = Date.now();
+                const started = performance.now();
+                const response = await Promise.race([
+                  isIOS && manager instanceof IOSManager
+                    ? executeIOSCommand(parseResult.command, manager)
+                    : executeCommand(
+                        parseResult.command,
+                        manager as BrowserManager,
+                        controller.signal
+                      ),
+                  whenAborted(commandId, controller.signal),
+                ]);
+                if (parseResult.command.timings) {
+                  const daemon = Math.round((performance.now() - started) * 10) / 10;
+                  response.timings = { daemon };
+                }
+                const { framing, compression } = parseResult.command;
+                send(socket, encodeResponse(response, framing, compression));
+                auditLog?.record(parseResult.command, response, startedAt);
+              } finally {
+                clearTimeout(timer);
+                if (inFlight.get(commandId) === controller) inFlight.delete(commandId);
+                lifetime.lastActivity = Date.now();
+              }
             } catch (err) {
               const message = err instanceof Error ? err.message : String(err);
-              socket.write(serializeResponse(errorResponse('error', message)) + '\n');
+              send(socket, serializeResponse(errorResponse(commandId, message)) + '\n');
             }
+          };
+          for (const line of buffer.split('\n')) {
+            if (line.trim()) void handle(line);
           }
-          // Save remaining partial line back to chunks
-          socketData.chunks = buffer ? [buffer] : [];
         },
-        drain() {
-          // ignore
+        drain(socket) {
+          flush(socket);
         },
-        close() {
-          // Ignore
+        close(socket) {
+          socket.data.unsubscribe?.();
         },
         error() {
           // Client disconnected, ignore
@@ -438,6 +917,16 @@ export async function startDaemon(options?: {
     process.on('SIGTERM', shutdown);
     process.on('SIGHUP', shutdown);
 
+    // Idle timeout and max lifetime
+    setInterval(() => {
+      const { idleRemaining, lifetimeRemaining } = remainingLifetime(
+        lifetime,
+        Date.now(),
+        inFlight.size > 0
+      );
+      if (idleRemaining === 0 || lifetimeRemaining === 0) shutdown();
+    }, LIFETIME_CHECK_INTERVAL);
+
     // Handle unexpected errors - always cleanup
     process.on('uncaughtException', async (err) => {
       console.error('Uncaught exception:', err);
@@ -466,8 +955,20 @@ export async function startDaemon(options?: {
   }
 }
 
-// Run daemon if this is the entry point
-if (import.meta.main || process.env.AGENT_BROWSER_DAEMON === '1') {
+// `agent-browser install` runs this entry point too, so the embedded daemon can
+// download browsers with the Playwright it bundles
+if (process.env.AGENT_BROWSER_INSTALL !== undefined) {
+  Promise.resolve()
+    .then(() => runInstall(parseBrowsers(process.env.AGENT_BROWSER_INSTALL)))
+    .then(
+      (code) => process.exit(code),
+      (err) => {
+        console.error(`✗ ${err instanceof Error ? err.message : String(err)}`);
+        process.exit(1);
+      }
+    );
+} else if (import.meta.main || process.env.AGENT_BROWSER_DAEMON === '1') {
+  // Run daemon if this is the entry point
   startDaemon().catch(async (err) => {
     console.error('Daemon error:', err);
     await cleanupSocket().finally(() => process.exit(1));
@@ -0,0 +1,133 @@
+/**
+ * Minimal HAR 1.2 builder fed from page request events.
+ *
+ * Playwright can only record HAR when a context is created, so `har start`
+ * records entries from the daemon's own request tracking instead and writes
+ * the archive on `har stop`.
+ *
+ * Usage:
+ *   agent-browser har start ./session.har
+ *   agent-browser open example.com
+ *   agent-browser har stop
+ */
+
+import type { Request, Response } from 'playwright-core';
+
+type HarHeader = { name: string; value: string };
+
+export interface HarEntry {
+  startedDateTime: string;
+  time: number;
+  request: {
+    method: string;
+    url: string;
+    httpVersion: string;
+    headers: HarHeader[];
+    queryString: HarHeader[];
+    cookies: never[];
+    headersSize: number;
+    bodySize: number;
+    postData?: { mimeType: string; text: string };
+  };
+  response: {
+    status: number;
+    statusText: string;
+    httpVersion: string;
+    headers: HarHeader[];
+    cookies: never[];
+    content: { size: number; mimeType: string };
+    redirectURL: string;
+    headersSize: number;
+    bodySize: number;
+    _failureText?: string;
+  };
+  cache: Record<string, never>;
+  timings: { send: number; wait: number; receive: number };
+}
+
+function toHeaders(headers: Record<string, string>): HarHeader[] {
+  return Object.entries(headers).map(([name, value]) => ({ name, value }));
+}
+
+function queryString(url: string): HarHeader[] {
+  try {
+    return [...new URL(url).searchParams].map(([name, value]) => ({ name, value }));
+  } catch {
+    return [];
+  }
+}
+
+/**
+ * Build a HAR entry for a finished (or failed) request. `startTime` is when the
+ * request was first seen, in ms since the epoch.
+ */
+export async function buildHarEntry(
+  request: Request,
+  response: Response | null,
+  startTime: number,
+  failure?: string
+): Promise<HarEntry> {
+  const timing = request.timing();
+  const wait =
+    timing.responseStart >= 0 && timing.requestStart >= 0
+      ? timing.responseStart - timing.requestStart
+      : 0;
+  const receive =
+    timing.responseEnd >= 0 && timing.responseStart >= 0
+      ? timing.responseEnd - timing.responseStart
+      : 0;
+
+  let bodySize = -1;
+  try {
+    bodySize = (await request.sizes()).responseBodySize;
+  } catch {
+    // Sizes are unavailable for failed requests or closed pages
+  }
+
+  const postData = request.postData();
+  const requestHeaders = request.headers();
+  const responseHeaders = response ? response.headers() : {};
+
+  return {
+    startedDateTime: new Date(startTime).toISOString(),
+    time: Math.max(0, wait + receive),
+    request: {
+      method: request.method(),
+      url: request.url(),
+      httpVersion: 'HTTP/1.1',
+      headers: toHeaders(requestHeaders),
+      queryString: queryString(request.url()),
+      cookies: [],
+      headersSize: -1,
+      bodySize: postData ? Buffer.byteLength(postData) : 0,
+      ...(postData !== null && {
+        postData: { mimeType: requestHeaders['content-type'] ?? '', text: postData },
+      }),
+    },
+    response: {
+      status: response?.status() ?? 0,
+      statusText: response?.statusText() ?? '',
+      httpVersion: 'HTTP/1.1',
+      headers: toHeaders(responseHeaders),
+      cookies: [],
+      content: { size: Math.max(bodySize, 0), mimeType: responseHeaders['content-type'] ?? '' },
+      redirectURL: responseHeaders['location'] ?? '',
+      headersSize: -1,
+      bodySize,
+      ...(failure !== undefined && { _failureText: failure }),
+    },
+    cache: {},
+    timings: { send: 0, wait: Math.max(0, wait), receive: Math.max(0, receive) },
+  };
+}
+
+export function buildHar(entries: HarEntry[]): object {
+  return {
+    log: {
+      version: '1.2',
+      creator: { name: 'agent-browser', version: 'unknown' },
+      pages: [],
+      entries,
+    },
+  };
+}
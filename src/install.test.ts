@@ -0,0 +1,17 @@
+import { describe, it, expect } from 'bun:test';
+import { parseBrowsers } from './install.js';
+
+describe('parseBrowsers', () => {
+  it('should default to chromium', () => {
+    expect(parseBrowsers(undefined)).toEqual(['chromium']);
+    expect(parseBrowsers('')).toEqual(['chromium']);
+  });
+
+  it('should accept a comma-separated list without duplicates', () => {
+    expect(parseBrowsers('firefox, webkit,firefox')).toEqual(['firefox', 'webkit']);
+  });
+
+  it('should reject unknown browsers', () => {
+    expect(() => parseBrowsers('chrome')).toThrow('Unknown browser: chrome');
+  });
+});
@@ -0,0 +1,110 @@
+/**
+ * Browser downloads for `agent-browser install`.
+ *
+ * The CLI runs the daemon's entry point with AGENT_BROWSER_INSTALL set, so the
+ * browsers come from the same Playwright the daemon launches them with.
+ * Playwright's installer draws its own progress bars on the inherited
+ * terminal; afterwards each browser is launched once to check it works.
+ */
+
+import { spawn } from 'node:child_process';
+import { existsSync } from 'node:fs';
+import { createRequire } from 'node:module';
+import { dirname, join } from 'node:path';
+import { chromium, firefox, webkit } from 'playwright-core';
+
+export const INSTALLABLE_BROWSERS = ['chromium', 'firefox', 'webkit'] as const;
+export type InstallableBrowser = (typeof INSTALLABLE_BROWSERS)[number];
+
+const BROWSER_TYPES = { chromium, firefox, webkit };
+
+/**
+ * Parse AGENT_BROWSER_INSTALL: comma-separated browser names, chromium when empty
+ */
+export function parseBrowsers(value: string | undefined): InstallableBrowser[] {
+  const names = (value ?? '')
+    .split(',')
+    .map((name) => name.trim())
+    .filter(Boolean);
+  if (names.length === 0) return ['chromium'];
+  for (const name of names) {
+    if (!(INSTALLABLE_BROWSERS as readonly string[]).includes(name)) {
+      throw new Error(
+        `Unknown browser: ${name} (expected one of ${INSTALLABLE_BROWSERS.join(', ')})`
+      );
+    }
+  }
+  return [...new Set(names)] as InstallableBrowser[];
+}
+
+/**
+ * Playwright's CLI, from the playwright-core next to agent-browser or in the current project
+ */
+function playwrightCli(): string {
+  for (const base of [import.meta.url, join(process.cwd(), 'package.json')]) {
+    try {
+      return join(dirname(createRequire(base).resolve('playwright-core/package.json')), 'cli.js');
+    } catch {
+      // Try the next location
+    }
+  }
+  throw new Error(
+    "Playwright's installer was not found. Run this from an npm install of agent-browser, " +
+      'or run `bunx playwright-core install <browser>` yourself'
+  );
+}
+
+function download(browsers: InstallableBrowser[]): Promise<void> {
+  return new Promise((resolve, reject) => {
+    const child = spawn(process.execPath, [playwrightCli(), 'install', ...browsers], {
+      stdio: 'inherit',
+    });
+    child.on('error', reject);
+    child.on('exit', (code) =>
+      code === 0 ? resolve() : reject(new Error(`Playwright's installer exited with ${code}`))
+    );
+  });
+}
+
+/**
+ * Launch a browser headless and close it again, so a missing system library
+ * shows up now instead of on the first command. Returns its version.
+ */
+export async function verifyBrowser(browser: InstallableBrowser): Promise<string> {
+  const type = BROWSER_TYPES[browser];
+  const executable = type.executablePath();
+  if (!existsSync(executable)) {
+    throw new Error(`${browser} is not installed (expected ${executable})`);
+  }
+  const instance = await type.launch({ headless: true });
+  try {
+    return instance.version();
+  } finally {
+    await instance.close();
+  }
+}
+
+/**
+ * Download and verify `browsers`. Returns the process exit code.
+ */
+export async function runInstall(browsers: InstallableBrowser[]): Promise<number> {
+  try {
+    await download(browsers);
+  } catch (err) {
+    console.error(`✗ ${err instanceof Error ? err.message : String(err)}`);
+    return 1;
+  }
+
+  let failed = false;
+  for (const browser of browsers) {
+    try {
+      const version = await verifyBrowser(browser);
+      console.log(`✓ ${browser} ${version} installed and launches`);
+    } catch (err) {
+      const message = err instanceof Error ? err.message.split('\n')[0] : String(err);
+      console.error(`✗ ${browser} does not launch: ${message}`);
+      failed = true;
+    }
+  }
+  return failed ? 1 : 0;
+}
@@ -5,6 +5,13 @@
 
 import type { IOSManager } from './ios-manager.js';
 import type { Command, Response } from './types.js';
+import {
+  DEFAULT_SNAPSHOT_PAGE_CHARS,
+  formatSnapshot,
+  paginateSnapshot,
+  parseSnapshotTree,
+  snapshotHash,
+} from './snapshot.js';
 
 function successResponse<T>(id: string, data: T): Response<T> {
   return { id, success: true, data };
@@ -80,10 +87,37 @@ export async function executeIOSCommand(command: Command, manager: IOSManager):
 
       case 'snapshot': {
         const cmd = command as any;
+        if (cmd.coords) {
+          return errorResponse(id, 'snapshot --coords is not supported on iOS Safari.');
+        }
         const result = await manager.getSnapshot({
           interactive: cmd.interactive,
         });
-        return successResponse(id, { snapshot: result.tree, refs: result.refs });
+        const hash = snapshotHash(result.tree);
+        if (cmd.ifChanged === hash) {
+          return successResponse(id, { unchanged: true, hash });
+        }
+        const unchanged = cmd.ifChanged === undefined ? undefined : false;
+        if (cmd.format === 'json') {
+          const nodes = parseSnapshotTree(result.tree);
+          return successResponse(id, { nodes, refs: result.refs, hash, unchanged });
+        }
+        const snapshot = formatSnapshot(result.tree, cmd.format ?? 'text');
+        if (cmd.maxChars === undefined && cmd.page === undefined) {
+          return successResponse(id, { snapshot, refs: result.refs, hash, unchanged });
+        }
+        const { text, ...pagination } = paginateSnapshot(
+          snapshot,
+          cmd.maxChars ?? DEFAULT_SNAPSHOT_PAGE_CHARS,
+          cmd.page
+        );
+        return successResponse(id, {
+          snapshot: text,
+          refs: result.refs,
+          pagination,
+          hash,
+          unchanged,
+        });
       }
 
       case 'scroll': {
@@ -106,7 +140,12 @@ export async function executeIOSCommand(command: Command, manager: IOSManager):
 
       case 'evaluate': {
         const cmd = command as any;
-        const result = await manager.evaluate(cmd.script, ...(cmd.args ?? []));
+        if (cmd.selector) {
+          return errorResponse(id, 'eval --selector is not supported on iOS Safari.');
+        }
+        // With args the script is a function, called with them in order
+        const script = cmd.args ? `return (${cmd.script})(...arguments)` : cmd.script;
+        const result = await manager.evaluate(script, ...(cmd.args ?? []));
         return successResponse(id, { result });
       }
 
@@ -263,6 +302,9 @@ export async function executeIOSCommand(command: Command, manager: IOSManager):
       case 'recording_restart':
         return errorResponse(id, 'Video recording is not yet supported on iOS.');
 
+      case 'codegen_start':
+        return errorResponse(id, 'Recording scripts is not supported on iOS.');
+
       default:
         return errorResponse(id, `Unknown or unsupported iOS command: ${action}`);
     }
@@ -1,5 +1,12 @@
 import { describe, it, expect } from 'bun:test';
-import { parseCommand } from './protocol.js';
+import { gunzipSync } from 'node:zlib';
+import {
+  parseCommand,
+  encodeResponse,
+  successResponse,
+  PROTOCOL_VERSION,
+  SUPPORTED_ACTIONS,
+} from './protocol.js';
 
 // Helper to create command JSON string
 const cmd = (obj: object) => JSON.stringify(obj);
@@ -31,6 +38,40 @@ describe('parseCommand', () => {
       }
     });
 
+    it('should parse navigate with wait-until, referer and timeout', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'navigate',
+          url: 'https://example.com',
+          waitUntil: 'commit',
+          referer: 'https://google.com',
+          timeout: 60000,
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown waitUntil', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'navigate', url: 'https://example.com', waitUntil: 'idle' })
+      );
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse navigate into a new tab or window', () => {
+      for (const target of ['tab', 'window']) {
+        const result = parseCommand(
+          cmd({ id: '1', action: 'navigate', url: 'https://example.com', target })
+        );
+        expect(result.success).toBe(true);
+      }
+      const result = parseCommand(
+        cmd({ id: '1', action: 'navigate', url: 'https://example.com', target: 'frame' })
+      );
+      expect(result.success).toBe(false);
+    });
+
     it('should reject navigate without url', () => {
       const result = parseCommand(cmd({ id: '1', action: 'navigate' }));
       expect(result.success).toBe(false);
@@ -66,6 +107,18 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'click' }));
       expect(result.success).toBe(false);
     });
+
+    it('should parse click on the nth or every match', () => {
+      expect(
+        parseCommand(cmd({ id: '1', action: 'click', selector: '.item', index: -1 })).success
+      ).toBe(true);
+      expect(
+        parseCommand(cmd({ id: '1', action: 'gettext', selector: '.price', all: true })).success
+      ).toBe(true);
+      expect(
+        parseCommand(cmd({ id: '1', action: 'click', selector: '.item', index: -2 })).success
+      ).toBe(false);
+    });
   });
 
   describe('type', () => {
@@ -82,6 +135,23 @@ describe('parseCommand', () => {
     });
   });
 
+  describe('press', () => {
+    it('should parse press with a chord', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'press', key: 'Control+Shift+P' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse press with count', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'press', key: 'Tab', count: 3 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject zero press count', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'press', key: 'Tab', count: 0 }));
+      expect(result.success).toBe(false);
+    });
+  });
+
   describe('fill', () => {
     it('should parse fill command', () => {
       const result = parseCommand(
@@ -93,6 +163,73 @@ describe('parseCommand', () => {
         expect(result.command.value).toBe('hello');
       }
     });
+
+    it('should keep the secret flag', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'fill', selector: '#pw', value: 'x', secret: true })
+      );
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'fill') {
+        expect(result.command.secret).toBe(true);
+      }
+    });
+  });
+
+  describe('expect', () => {
+    it('should parse expect text', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'expect', kind: 'text', selector: '#s', expected: 'Saved' })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse expect url with timeout', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'expect', kind: 'url', expected: '**/done', timeout: 2000 })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown expect kind', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'expect', kind: 'hidden' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('blur', () => {
+    it('should parse blur command', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'blur', selector: '#input' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject blur without selector', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'blur' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('select', () => {
+    it('should parse select by value', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'select', selector: '#c', values: 'US' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse select by label', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'select', selector: '#c', label: 'United States' })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse select by index', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'select', selector: '#c', index: 2 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject negative select index', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'select', selector: '#c', index: -1 }));
+      expect(result.success).toBe(false);
+    });
   });
 
   describe('wait', () => {
@@ -129,6 +266,76 @@ describe('parseCommand', () => {
       );
       expect(result.success).toBe(true);
     });
+
+    it('should parse screenshot with webp format and clip', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'screenshot',
+          format: 'webp',
+          quality: 80,
+          clip: { x: 0, y: 0, width: 800, height: 600 },
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse screenshot with stdout', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'screenshot', stdout: true }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse screenshot with annotateRefs', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'screenshot', annotateRefs: true }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject screenshot with empty clip', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'screenshot', clip: { x: 0, y: 0, width: 0, height: 600 } })
+      );
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('pdf', () => {
+    it('should parse pdf without path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'pdf' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse pdf with options', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'pdf',
+          path: '/tmp/page.pdf',
+          format: 'A4',
+          landscape: true,
+          margin: { top: '1cm', right: '1cm', bottom: '1cm', left: '1cm' },
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject pdf with unknown format', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'pdf', format: 'B5' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('save', () => {
+    it('should parse save to an MHTML file or a directory', () => {
+      const mhtml = parseCommand(cmd({ id: '1', action: 'save', path: '/tmp/page.mhtml' }));
+      expect(mhtml.success).toBe(true);
+      const dir = parseCommand(cmd({ id: '1', action: 'save', htmlDir: '/tmp/page' }));
+      expect(dir.success).toBe(true);
+    });
+
+    it('should reject an empty path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'save', path: '' }));
+      expect(result.success).toBe(false);
+    });
   });
 
   describe('cookies', () => {
@@ -150,6 +357,28 @@ describe('parseCommand', () => {
       }
     });
 
+    it('should parse cookies_get with name filter', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'cookies_get', name: 'session' }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.name).toBe('session');
+      }
+    });
+
+    it('should parse cookies_export and cookies_import', () => {
+      expect(
+        parseCommand(cmd({ id: '1', action: 'cookies_export', path: '/tmp/c.json' })).success
+      ).toBe(true);
+      expect(
+        parseCommand(cmd({ id: '1', action: 'cookies_import', path: '/tmp/c.json' })).success
+      ).toBe(true);
+    });
+
+    it('should reject cookies_export without path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'cookies_export' }));
+      expect(result.success).toBe(false);
+    });
+
     it('should parse cookies_set with minimal cookie', () => {
       const result = parseCommand(
         cmd({
@@ -340,6 +569,21 @@ describe('parseCommand', () => {
       expect(result.success).toBe(true);
     });
 
+    it('should parse storage_remove', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'storage_remove', type: 'session', key: 'token' })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.action).toBe('storage_remove');
+      }
+    });
+
+    it('should reject storage_remove without key', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'storage_remove', type: 'local' }));
+      expect(result.success).toBe(false);
+    });
+
     it('should reject storage_get without type', () => {
       const result = parseCommand(cmd({ id: '1', action: 'storage_get' }));
       expect(result.success).toBe(false);
@@ -376,6 +620,40 @@ describe('parseCommand', () => {
       expect(result.success).toBe(true);
     });
 
+    it('should parse fillform', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'fillform',
+          fields: [
+            { field: '#email', value: 'a@example.com' },
+            { field: 'Age', value: 42 },
+            { field: 'Remember me', value: true },
+          ],
+        })
+      );
+      expect(result.success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'fillform', fields: [] })).success).toBe(false);
+    });
+
+    it('should parse getbyname', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'getbyname',
+          text: 'Sign in',
+          exact: true,
+          index: -1,
+          subaction: 'hover',
+        })
+      );
+      expect(result.success).toBe(true);
+      const fill = parseCommand(
+        cmd({ id: '1', action: 'getbyname', text: 'Email', subaction: 'fill' })
+      );
+      expect(fill.success).toBe(false);
+    });
+
     it('should parse getbylabel', () => {
       const result = parseCommand(
         cmd({
@@ -420,6 +698,40 @@ describe('parseCommand', () => {
     });
   });
 
+  describe('close', () => {
+    it('should parse close with keepDaemon and saveState', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'close' })).success).toBe(true);
+      const result = parseCommand(
+        cmd({ id: '1', action: 'close', keepDaemon: true, saveState: '/tmp/auth.json' })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject an empty saveState path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'close', saveState: '' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('contexts', () => {
+    it('should parse context_new with and without a name', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'context_new' })).success).toBe(true);
+      const result = parseCommand(cmd({ id: '1', action: 'context_new', name: 'work' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should require a name for context_switch', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'context_switch', name: 'work' }));
+      expect(result.success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'context_switch' })).success).toBe(false);
+    });
+
+    it('should parse context_list and context_close', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'context_list' })).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'context_close' })).success).toBe(true);
+    });
+  });
+
   describe('snapshot', () => {
     it('should parse basic snapshot command', () => {
       const result = parseCommand(cmd({ id: '1', action: 'snapshot' }));
@@ -458,6 +770,20 @@ describe('parseCommand', () => {
       }
     });
 
+    it('should parse snapshot with coords', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'snapshot', coords: true }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse snapshot with ifChanged', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'snapshot', ifChanged: '3f2a9c0d1b7e4a56' })
+      );
+      expect(result.success).toBe(true);
+      const invalid = parseCommand(cmd({ id: '1', action: 'snapshot', ifChanged: 42 }));
+      expect(invalid.success).toBe(false);
+    });
+
     it('should parse snapshot with all options', () => {
       const result = parseCommand(
         cmd({
@@ -477,6 +803,64 @@ describe('parseCommand', () => {
         expect(result.command.selector).toBe('.content');
       }
     });
+
+    it('should keep cursor and format options', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'snapshot', cursor: true, format: 'json' })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.cursor).toBe(true);
+        expect(result.command.format).toBe('json');
+      }
+    });
+
+    it('should reject unknown formats', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'snapshot', format: 'xml' }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse snapshot_diff with filters', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'snapshot_diff', interactive: true, selector: '#main' })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.action).toBe('snapshot_diff');
+        expect(result.command.selector).toBe('#main');
+      }
+    });
+
+    it('should parse visible_elements with roles', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'visible_elements', roles: ['button', 'link'] })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.roles).toEqual(['button', 'link']);
+      }
+    });
+
+    it('should reject visible_elements with an empty role list', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'visible_elements', roles: [] }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('read', () => {
+    it('should parse read from an element', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'read', selector: '#main', maxChars: 500 })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse read next and prev', () => {
+      for (const direction of ['next', 'prev']) {
+        expect(parseCommand(cmd({ id: '1', action: 'read', direction })).success).toBe(true);
+      }
+      expect(parseCommand(cmd({ id: '1', action: 'read', direction: 'up' })).success).toBe(false);
+    });
   });
 
   describe('launch', () => {
@@ -543,6 +927,18 @@ describe('parseCommand', () => {
       }
     });
 
+    it('should parse mouseclick', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'mouseclick', x: 10, y: 20, button: 'right', clickCount: 2 })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject mouseclick without coordinates', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'mouseclick', x: 10 }));
+      expect(result.success).toBe(false);
+    });
+
     it('should parse mousedown', () => {
       const result = parseCommand(cmd({ id: '1', action: 'mousedown', button: 'left' }));
       expect(result.success).toBe(true);
@@ -559,6 +955,27 @@ describe('parseCommand', () => {
     });
   });
 
+  describe('drag', () => {
+    it('should parse drag', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'drag', source: '#a', target: '#b' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse drag with steps', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'drag', source: '#a', target: '#b', steps: 10 })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject zero drag steps', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'drag', source: '#a', target: '#b', steps: 0 })
+      );
+      expect(result.success).toBe(false);
+    });
+  });
+
   describe('scroll', () => {
     it('should parse scroll command', () => {
       const result = parseCommand(
@@ -567,6 +984,21 @@ describe('parseCommand', () => {
       expect(result.success).toBe(true);
     });
 
+    it('should parse scroll by offsets', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'scroll', x: 0, y: -250 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse scroll to bottom', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'scroll', position: 'bottom' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown scroll position', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'scroll', position: 'middle' }));
+      expect(result.success).toBe(false);
+    });
+
     it('should parse scrollintoview', () => {
       const result = parseCommand(cmd({ id: '1', action: 'scrollintoview', selector: '#element' }));
       expect(result.success).toBe(true);
@@ -590,23 +1022,239 @@ describe('parseCommand', () => {
     });
   });
 
-  describe('viewport and settings', () => {
-    it('should parse viewport', () => {
-      const result = parseCommand(cmd({ id: '1', action: 'viewport', width: 1920, height: 1080 }));
+  describe('highlight', () => {
+    it('should parse highlight with label and duration', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'highlight', selector: '.card', label: 'cards', duration: 0 })
+      );
       expect(result.success).toBe(true);
     });
 
-    it('should parse geolocation', () => {
+    it('should reject negative duration', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'highlight', selector: '.card', duration: -1 })
+      );
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('viewport and settings', () => {
+    it('should parse viewport', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'viewport', width: 1920, height: 1080 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse emulate reset', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'emulate_reset' })).success).toBe(true);
+    });
+
+    it('should parse viewport with scale', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'viewport', width: 390, height: 844, deviceScaleFactor: 3 })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse geolocation', () => {
       const result = parseCommand(
         cmd({ id: '1', action: 'geolocation', latitude: 37.7749, longitude: -122.4194 })
       );
       expect(result.success).toBe(true);
     });
 
+    it('should parse permissions for an origin', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'permissions',
+          permissions: ['camera', 'geolocation'],
+          grant: true,
+          origin: 'https://example.com',
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject permissions for an origin that is not a URL', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'permissions',
+          permissions: ['camera'],
+          grant: true,
+          origin: 'example.com',
+        })
+      );
+      expect(result.success).toBe(false);
+    });
+
     it('should parse offline', () => {
       const result = parseCommand(cmd({ id: '1', action: 'offline', offline: true }));
       expect(result.success).toBe(true);
     });
+
+    it('should parse headers with merge', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'headers', headers: { 'X-Test': '1' }, merge: true })
+      );
+      expect(result.success).toBe(true);
+    });
+  });
+
+  describe('mock', () => {
+    it('should parse mock_add', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'mock_add',
+          url: '**/api/users',
+          status: 201,
+          body: 'eyJvayI6dHJ1ZX0=',
+          contentType: 'application/json',
+          headers: { 'x-test': '1' },
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject mock_add with invalid status', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'mock_add', url: '**/x', status: 42 }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse mock_list and mock_remove', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'mock_list' })).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'mock_remove', mockId: 2 })).success).toBe(true);
+    });
+
+    it('should reject mock_remove without id', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'mock_remove' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('block', () => {
+    it('should parse block with patterns', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'block', patterns: ['*.png', '*analytics*'] })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject block without patterns', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'block', patterns: [] }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse unblock with and without patterns', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'unblock' })).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'unblock', patterns: ['*.png'] })).success).toBe(
+        true
+      );
+    });
+  });
+
+  describe('network', () => {
+    it('should parse network_log', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'network_log' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse network_log with filter and since', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'network_log', filter: '**/api/**', since: 12 })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject network_log with negative since', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'network_log', since: -1 }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse waitforrequest and waitforresponse', () => {
+      expect(
+        parseCommand(cmd({ id: '1', action: 'waitforrequest', url: '**/api/cart' })).success
+      ).toBe(true);
+      expect(
+        parseCommand(cmd({ id: '1', action: 'waitforresponse', url: '/api/cart', status: 201 }))
+          .success
+      ).toBe(true);
+    });
+
+    it('should reject waitforresponse with an invalid status', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'waitforresponse', url: '/api/cart', status: 42 })
+      );
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse waitforspaidle with a quiet period', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'waitforspaidle' })).success).toBe(true);
+      expect(
+        parseCommand(cmd({ id: '1', action: 'waitforspaidle', quiet: 1000, timeout: 8000 }))
+          .success
+      ).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'waitforspaidle', quiet: 0 })).success).toBe(
+        false
+      );
+    });
+
+    it('should parse network capture and show', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'network_capture', url: '**/api/cart*', limit: 5 })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.limit).toBe(5);
+      }
+      expect(parseCommand(cmd({ id: '1', action: 'network_capture_stop' })).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'network_show', requestId: 42 })).success).toBe(
+        true
+      );
+    });
+
+    it('should reject network capture without a URL or with a zero limit', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'network_capture' })).success).toBe(false);
+      expect(
+        parseCommand(cmd({ id: '1', action: 'network_capture', url: '/api', limit: 0 })).success
+      ).toBe(false);
+      expect(parseCommand(cmd({ id: '1', action: 'network_show' })).success).toBe(false);
+    });
+  });
+
+  describe('downloads', () => {
+    it('should parse waitfordownload with timeout', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'waitfordownload', timeout: 5000 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse download_dir', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'download_dir', path: '/tmp/dl' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject download_dir without path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'download_dir' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('har', () => {
+    it('should parse har_start with path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'har_start', path: '/tmp/s.har' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject har_start without path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'har_start' }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse har_stop without path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'har_stop' }));
+      expect(result.success).toBe(true);
+    });
   });
 
   describe('trace', () => {
@@ -632,12 +1280,187 @@ describe('parseCommand', () => {
       expect(result.success).toBe(true);
     });
 
+    it('should parse console with level and since', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'console', level: 'warn', since: 12 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown console level', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'console', level: 'debug' }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse subscribe', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'subscribe' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse subscribe with types', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'subscribe', types: ['console', 'dialog'] })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown subscribe type', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'subscribe', types: ['cookies'] }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse watch', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'watch', selector: '#list', events: ['added'], once: true })
+      );
+      expect(result.success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'watch', selector: '#list' })).success).toBe(true);
+    });
+
+    it('should reject watch without a selector or with an unknown event', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'watch' })).success).toBe(false);
+      const result = parseCommand(
+        cmd({ id: '1', action: 'watch', selector: '#list', events: ['moved'] })
+      );
+      expect(result.success).toBe(false);
+    });
+
     it('should parse errors', () => {
       const result = parseCommand(cmd({ id: '1', action: 'errors' }));
       expect(result.success).toBe(true);
     });
   });
 
+  describe('history', () => {
+    it('should parse history with a limit', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'history', limit: 20 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject a zero limit', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'history', limit: 0 }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse history_go', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'history_go', index: 3 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject history_go without an index', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'history_go' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('hello', () => {
+    it('should parse hello', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'hello', protocolVersion: PROTOCOL_VERSION })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject hello without protocolVersion', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'hello' }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should list every action', () => {
+      expect(SUPPORTED_ACTIONS).toContain('navigate');
+      expect(SUPPORTED_ACTIONS).toContain('hello');
+      expect(new Set(SUPPORTED_ACTIONS).size).toBe(SUPPORTED_ACTIONS.length);
+    });
+  });
+
+  describe('deadline', () => {
+    it('should accept a deadline on any command', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'click', selector: '#a', deadline: 1 }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.deadline).toBe(1);
+      }
+    });
+
+    it('should reject a non-integer deadline', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'back', deadline: 'soon' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('timings', () => {
+    it('should accept timings on any command', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'url', timings: true }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.timings).toBe(true);
+      }
+    });
+  });
+
+  describe('retry', () => {
+    it('should accept a retry count on any command', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'click', selector: '#a', retry: 2 }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.retry).toBe(2);
+      }
+    });
+
+    it('should reject negative or excessive retry counts', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'url', retry: -1 })).success).toBe(false);
+      expect(parseCommand(cmd({ id: '1', action: 'url', retry: 11 })).success).toBe(false);
+    });
+  });
+
+  describe('token', () => {
+    it('should accept a token on any command', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'url', token: 'secret' }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.token).toBe('secret');
+      }
+    });
+  });
+
+  describe('cancel', () => {
+    it('should parse cancel', () => {
+      const result = parseCommand(cmd({ id: '2', action: 'cancel', commandId: '1' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject cancel without commandId', () => {
+      const result = parseCommand(cmd({ id: '2', action: 'cancel' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('daemon', () => {
+    it('should parse daemon_status', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'daemon_status' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse daemon_config limits', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'daemon_config', idleTimeout: 600000, maxLifetime: 0 })
+      );
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'daemon_config') {
+        expect(result.command.idleTimeout).toBe(600000);
+        expect(result.command.maxLifetime).toBe(0);
+      }
+    });
+
+    it('should reject negative limits', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'daemon_config', idleTimeout: -1 }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse status', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'status' }));
+      expect(result.success).toBe(true);
+    });
+  });
+
   describe('dialog', () => {
     it('should parse dialog accept', () => {
       const result = parseCommand(cmd({ id: '1', action: 'dialog', response: 'accept' }));
@@ -658,6 +1481,16 @@ describe('parseCommand', () => {
         expect(result.command.promptText).toBe('hello');
       }
     });
+
+    it('should parse dialog_auto', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'dialog_auto', response: 'off' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject invalid dialog_auto response', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'dialog_auto', response: 'maybe' }));
+      expect(result.success).toBe(false);
+    });
   });
 
   describe('frame', () => {
@@ -670,6 +1503,21 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'mainframe' }));
       expect(result.success).toBe(true);
     });
+
+    it('should parse frame by index', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'frame', index: 2 }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject negative frame index', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'frame', index: -1 }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse frame_list', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'frame_list' }));
+      expect(result.success).toBe(true);
+    });
   });
 
   describe('screencast', () => {
@@ -1057,6 +1905,184 @@ describe('parseCommand', () => {
     });
   });
 
+  describe('content', () => {
+    it('should parse content with outer and path', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'content', selector: '#main', outer: true, path: '/tmp/a.html' })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.outer).toBe(true);
+        expect(result.command.path).toBe('/tmp/a.html');
+      }
+    });
+  });
+
+  describe('clipboard', () => {
+    it('should parse clipboard write', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'clipboard', operation: 'write', text: 'hello' })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown operation', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'clipboard', operation: 'clear' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('evaluate', () => {
+    it('should parse evaluate with selector and args', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'evaluate',
+          script: '(el, n) => el.children.length > n',
+          selector: '#list',
+          args: [3, { a: 1 }],
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+  });
+
+  describe('throttle', () => {
+    it('should parse custom throttle', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'throttle', profile: 'custom', downloadKbps: 1000, latencyMs: 0 })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown profile', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'throttle', profile: '2g' })).success).toBe(false);
+    });
+  });
+
+  describe('fetch', () => {
+    it('should parse fetch with method, headers and body', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'fetch',
+          url: '/api/items',
+          method: 'POST',
+          headers: { 'content-type': 'application/json' },
+          body: 'e30=',
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should require url', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'fetch' })).success).toBe(false);
+    });
+  });
+
+  describe('computedstyle', () => {
+    it('should parse computedstyle with properties', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'computedstyle', selector: '#msg', properties: ['color'] })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject computedstyle without properties', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'computedstyle', selector: '#msg', properties: [] })
+      );
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('metrics', () => {
+    it('should parse metrics', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'metrics' })).success).toBe(true);
+    });
+  });
+
+  describe('coverage', () => {
+    it('should parse coverage stop with lcov', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'coverage_stop', lcov: '/tmp/coverage.info' })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject empty lcov path', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'coverage_stop', lcov: '' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('audit', () => {
+    it('should parse audit with failOn', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'audit', failOn: 'serious' }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown impact', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'audit', failOn: 'bad' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
+  describe('table', () => {
+    it('should parse table with format', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'table', selector: '#prices', format: 'json' })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject unknown format', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'table', selector: '#prices', format: 'xml' })
+      );
+      expect(result.success).toBe(false);
+    });
+
+    it('should require selector', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'table' })).success).toBe(false);
+    });
+  });
+
+  describe('links', () => {
+    it('should parse links with selector and absolute', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'links', selector: 'nav', absolute: true })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.selector).toBe('nav');
+        expect(result.command.absolute).toBe(true);
+      }
+    });
+  });
+
+  describe('forms', () => {
+    it('should parse forms command', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'forms' })).success).toBe(true);
+    });
+  });
+
+  describe('article', () => {
+    it('should parse article with and without a selector', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'article' })).success).toBe(true);
+      const result = parseCommand(cmd({ id: '1', action: 'article', selector: '#post' }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.selector).toBe('#post');
+      }
+    });
+
+    it('should reject an empty selector', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'article', selector: '' }));
+      expect(result.success).toBe(false);
+    });
+  });
+
   describe('invalid commands', () => {
     it('should reject unknown action', () => {
       const result = parseCommand(cmd({ id: '1', action: 'unknown' }));
@@ -1074,3 +2100,35 @@ describe('parseCommand', () => {
     });
   });
 });
+
+describe('encodeResponse', () => {
+  const response = successResponse('1', { text: 'héllo' });
+
+  it('should write a JSON line by default', () => {
+    expect(encodeResponse(response)).toBe(JSON.stringify(response) + '\n');
+  });
+
+  it('should prefix length frames with the byte length', () => {
+    const json = JSON.stringify(response);
+    const frame = encodeResponse(response, 'length');
+    expect(frame).toBe(`#${Buffer.byteLength(json)}\n${json}`);
+    // Bytes, not characters: é takes two
+    expect(Buffer.byteLength(json)).toBe(json.length + 1);
+  });
+
+  it('should gzip large responses when asked', () => {
+    const large = successResponse('1', { html: '<p>hello</p>'.repeat(10000) });
+    const frame = Buffer.from(encodeResponse(large, 'length', 'gzip'));
+    const headerEnd = frame.indexOf('\n');
+    const [length, encoding] = frame.subarray(1, headerEnd).toString().split(' ');
+    const body = frame.subarray(headerEnd + 1);
+    expect(encoding).toBe('gzip');
+    expect(Number(length)).toBe(body.length);
+    expect(gunzipSync(body).toString()).toBe(JSON.stringify(large));
+  });
+
+  it('should not compress small responses', () => {
+    const json = JSON.stringify(response);
+    expect(encodeResponse(response, 'length', 'gzip')).toBe(`#${Buffer.byteLength(json)}\n${json}`);
+  });
+});
@@ -1,10 +1,32 @@
+import { gzipSync } from 'node:zlib';
 import { z } from 'zod';
-import type { Command, Response } from './types.js';
+import type {
+  Command,
+  Compression,
+  ErrorCode,
+  ErrorContext,
+  ErrorResponse,
+  Framing,
+  Response,
+} from './types.js';
+
+/**
+ * Version of the wire protocol, exchanged with `hello`. Bump it for changes
+ * that break existing clients (a removed action, a renamed field); new
+ * actions don't need a bump, since `hello` also lists the supported actions.
+ */
+export const PROTOCOL_VERSION = 1;
 
 // Base schema for all commands
 const baseCommandSchema = z.object({
   id: z.string(),
   action: z.string(),
+  deadline: z.number().int().positive().optional(),
+  framing: z.enum(['line', 'length']).optional(),
+  compression: z.enum(['gzip']).optional(),
+  token: z.string().optional(),
+  timings: z.boolean().optional(),
+  retry: z.number().int().min(0).max(10).optional(),
 });
 
 // Individual action schemas
@@ -53,13 +75,23 @@ const launchSchema = baseCommandSchema.extend({
 const navigateSchema = baseCommandSchema.extend({
   action: z.literal('navigate'),
   url: z.string().min(1),
-  waitUntil: z.enum(['load', 'domcontentloaded', 'networkidle']).optional(),
+  waitUntil: z.enum(['load', 'domcontentloaded', 'networkidle', 'commit']).optional(),
+  referer: z.string().min(1).optional(),
+  timeout: z.number().int().positive().optional(),
   headers: z.record(z.string(), z.string().optional()).optional(),
+  target: z.enum(['tab', 'window']).optional(),
 });
 
+// `--nth <n>` or `--all`: which matches of the selector to act on
+const matchPickSchema = {
+  index: z.number().int().min(-1).optional(),
+  all: z.boolean().optional(),
+};
+
 const clickSchema = baseCommandSchema.extend({
   action: z.literal('click'),
   selector: z.string().min(1),
+  ...matchPickSchema,
   button: z.enum(['left', 'right', 'middle']).optional(),
   clickCount: z.number().positive().optional(),
   delay: z.number().nonnegative().optional(),
@@ -77,11 +109,26 @@ const fillSchema = baseCommandSchema.extend({
   action: z.literal('fill'),
   selector: z.string().min(1),
   value: z.string(),
+  secret: z.boolean().optional(),
+});
+
+const fillFormSchema = baseCommandSchema.extend({
+  action: z.literal('fillform'),
+  // In the order to fill them; `field` is a selector, ref, label or name attribute
+  fields: z
+    .array(
+      z.object({
+        field: z.string().min(1),
+        value: z.union([z.string(), z.number(), z.boolean()]),
+      })
+    )
+    .min(1),
 });
 
 const checkSchema = baseCommandSchema.extend({
   action: z.literal('check'),
   selector: z.string().min(1),
+  ...matchPickSchema,
 });
 
 const uncheckSchema = baseCommandSchema.extend({
@@ -105,10 +152,16 @@ const focusSchema = baseCommandSchema.extend({
   selector: z.string().min(1),
 });
 
+const blurSchema = baseCommandSchema.extend({
+  action: z.literal('blur'),
+  selector: z.string().min(1),
+});
+
 const dragSchema = baseCommandSchema.extend({
   action: z.literal('drag'),
   source: z.string().min(1),
   target: z.string().min(1),
+  steps: z.number().int().positive().optional(),
 });
 
 const frameSchema = baseCommandSchema.extend({
@@ -116,12 +169,34 @@ const frameSchema = baseCommandSchema.extend({
   selector: z.string().min(1).optional(),
   name: z.string().optional(),
   url: z.string().optional(),
+  index: z.number().int().nonnegative().optional(),
 });
 
 const mainframeSchema = baseCommandSchema.extend({
   action: z.literal('mainframe'),
 });
 
+const frameListSchema = baseCommandSchema.extend({
+  action: z.literal('frame_list'),
+});
+
+const findSchema = baseCommandSchema.extend({
+  action: z.literal('find'),
+  query: z.string().min(1),
+  role: z.string().min(1).optional(),
+  limit: z.number().int().positive().optional(),
+});
+
+const refsSchema = baseCommandSchema.extend({
+  action: z.literal('refs'),
+  filter: z.string().min(1).optional(),
+});
+
+const visibleElementsSchema = baseCommandSchema.extend({
+  action: z.literal('visible_elements'),
+  roles: z.array(z.string().min(1)).min(1).optional(),
+});
+
 const getByRoleSchema = baseCommandSchema.extend({
   action: z.literal('getbyrole'),
   role: z.string().min(1),
@@ -137,6 +212,15 @@ const getByTextSchema = baseCommandSchema.extend({
   subaction: z.enum(['click', 'hover']),
 });
 
+const getByNameSchema = baseCommandSchema.extend({
+  action: z.literal('getbyname'),
+  text: z.string().min(1),
+  exact: z.boolean().optional(),
+  // Which match when several do; -1 for the last
+  index: z.number().int().optional(),
+  subaction: z.enum(['click', 'hover']),
+});
+
 const getByLabelSchema = baseCommandSchema.extend({
   action: z.literal('getbylabel'),
   label: z.string().min(1),
@@ -154,6 +238,7 @@ const getByPlaceholderSchema = baseCommandSchema.extend({
 const cookiesGetSchema = baseCommandSchema.extend({
   action: z.literal('cookies_get'),
   urls: z.array(z.string()).optional(),
+  name: z.string().optional(),
 });
 
 const cookiesSetSchema = baseCommandSchema.extend({
@@ -177,6 +262,16 @@ const cookiesClearSchema = baseCommandSchema.extend({
   action: z.literal('cookies_clear'),
 });
 
+const cookiesExportSchema = baseCommandSchema.extend({
+  action: z.literal('cookies_export'),
+  path: z.string().min(1),
+});
+
+const cookiesImportSchema = baseCommandSchema.extend({
+  action: z.literal('cookies_import'),
+  path: z.string().min(1),
+});
+
 const storageGetSchema = baseCommandSchema.extend({
   action: z.literal('storage_get'),
   key: z.string().optional(),
@@ -190,6 +285,12 @@ const storageSetSchema = baseCommandSchema.extend({
   type: z.enum(['local', 'session']),
 });
 
+const storageRemoveSchema = baseCommandSchema.extend({
+  action: z.literal('storage_remove'),
+  key: z.string().min(1),
+  type: z.enum(['local', 'session']),
+});
+
 const storageClearSchema = baseCommandSchema.extend({
   action: z.literal('storage_clear'),
   type: z.enum(['local', 'session']),
@@ -201,12 +302,35 @@ const dialogSchema = baseCommandSchema.extend({
   promptText: z.string().optional(),
 });
 
+const dialogAutoSchema = baseCommandSchema.extend({
+  action: z.literal('dialog_auto'),
+  response: z.enum(['accept', 'dismiss', 'off']),
+  promptText: z.string().optional(),
+});
+
 const pdfSchema = baseCommandSchema.extend({
   action: z.literal('pdf'),
-  path: z.string().min(1),
+  path: z.string().min(1).optional(),
   format: z
     .enum(['Letter', 'Legal', 'Tabloid', 'Ledger', 'A0', 'A1', 'A2', 'A3', 'A4', 'A5', 'A6'])
     .optional(),
+  landscape: z.boolean().optional(),
+  margin: z
+    .object({
+      top: z.string().optional(),
+      right: z.string().optional(),
+      bottom: z.string().optional(),
+      left: z.string().optional(),
+    })
+    .optional(),
+});
+
+const saveSchema = baseCommandSchema.extend({
+  action: z.literal('save'),
+  // An MHTML file, or
+  path: z.string().min(1).optional(),
+  // a directory for index.html and the page's resources
+  htmlDir: z.string().min(1).optional(),
 });
 
 const routeSchema = baseCommandSchema.extend({
@@ -223,6 +347,43 @@ const routeSchema = baseCommandSchema.extend({
   abort: z.boolean().optional(),
 });
 
+const mockAddSchema = baseCommandSchema.extend({
+  action: z.literal('mock_add'),
+  url: z.string().min(1),
+  status: z.number().int().min(100).max(599).optional(),
+  body: z.string().optional(),
+  contentType: z.string().optional(),
+  headers: z.record(z.string(), z.string()).optional(),
+});
+
+const mockListSchema = baseCommandSchema.extend({
+  action: z.literal('mock_list'),
+});
+
+const mockRemoveSchema = baseCommandSchema.extend({
+  action: z.literal('mock_remove'),
+  mockId: z.number().int().positive(),
+});
+
+const fetchSchema = baseCommandSchema.extend({
+  action: z.literal('fetch'),
+  url: z.string().min(1),
+  method: z.string().min(1).optional(),
+  headers: z.record(z.string(), z.string()).optional(),
+  body: z.string().optional(),
+  path: z.string().min(1).optional(),
+});
+
+const blockSchema = baseCommandSchema.extend({
+  action: z.literal('block'),
+  patterns: z.array(z.string().min(1)).min(1),
+});
+
+const unblockSchema = baseCommandSchema.extend({
+  action: z.literal('unblock'),
+  patterns: z.array(z.string().min(1)).optional(),
+});
+
 const unrouteSchema = baseCommandSchema.extend({
   action: z.literal('unroute'),
   url: z.string().optional(),
@@ -234,6 +395,65 @@ const requestsSchema = baseCommandSchema.extend({
   clear: z.boolean().optional(),
 });
 
+const networkLogSchema = baseCommandSchema.extend({
+  action: z.literal('network_log'),
+  filter: z.string().optional(),
+  since: z.number().int().nonnegative().optional(),
+});
+
+const networkCaptureSchema = baseCommandSchema.extend({
+  action: z.literal('network_capture'),
+  url: z.string().min(1),
+  limit: z.number().int().positive().optional(),
+});
+
+const networkCaptureStopSchema = baseCommandSchema.extend({
+  action: z.literal('network_capture_stop'),
+});
+
+const networkShowSchema = baseCommandSchema.extend({
+  action: z.literal('network_show'),
+  requestId: z.number().int().positive(),
+});
+
+const subscribeSchema = baseCommandSchema.extend({
+  action: z.literal('subscribe'),
+  types: z.array(z.enum(['console', 'network', 'dialog'])).min(1).optional(),
+});
+
+const watchSchema = baseCommandSchema.extend({
+  action: z.literal('watch'),
+  selector: z.string().min(1),
+  events: z.array(z.enum(['added', 'removed', 'text'])).min(1).optional(),
+  // Stop after the first change
+  once: z.boolean().optional(),
+  timeout: z.number().int().positive().optional(),
+});
+
+const helloSchema = baseCommandSchema.extend({
+  action: z.literal('hello'),
+  protocolVersion: z.number().int().nonnegative(),
+});
+
+const cancelSchema = baseCommandSchema.extend({
+  action: z.literal('cancel'),
+  commandId: z.string().min(1),
+});
+
+const daemonStatusSchema = baseCommandSchema.extend({
+  action: z.literal('daemon_status'),
+});
+
+const daemonConfigSchema = baseCommandSchema.extend({
+  action: z.literal('daemon_config'),
+  idleTimeout: z.number().int().nonnegative().optional(),
+  maxLifetime: z.number().int().nonnegative().optional(),
+});
+
+const statusSchema = baseCommandSchema.extend({
+  action: z.literal('status'),
+});
+
 const downloadSchema = baseCommandSchema.extend({
   action: z.literal('download'),
   selector: z.string().min(1),
@@ -251,12 +471,14 @@ const permissionsSchema = baseCommandSchema.extend({
   action: z.literal('permissions'),
   permissions: z.array(z.string()),
   grant: z.boolean(),
+  origin: z.string().url().optional(),
 });
 
 const viewportSchema = baseCommandSchema.extend({
   action: z.literal('viewport'),
   width: z.number().positive(),
   height: z.number().positive(),
+  deviceScaleFactor: z.number().positive().optional(),
 });
 
 const userAgentSchema = baseCommandSchema.extend({
@@ -283,6 +505,7 @@ const reloadSchema = baseCommandSchema.extend({
 
 const urlSchema = baseCommandSchema.extend({
   action: z.literal('url'),
+  parsed: z.boolean().optional(),
 });
 
 const titleSchema = baseCommandSchema.extend({
@@ -295,9 +518,21 @@ const getAttributeSchema = baseCommandSchema.extend({
   attribute: z.string().min(1),
 });
 
+const computedStyleSchema = baseCommandSchema.extend({
+  action: z.literal('computedstyle'),
+  selector: z.string().min(1),
+  properties: z.array(z.string().min(1)).min(1),
+});
+
 const getTextSchema = baseCommandSchema.extend({
   action: z.literal('gettext'),
   selector: z.string().min(1),
+  ...matchPickSchema,
+});
+
+const articleSchema = baseCommandSchema.extend({
+  action: z.literal('article'),
+  selector: z.string().min(1).optional(),
 });
 
 const isVisibleSchema = baseCommandSchema.extend({
@@ -330,6 +565,32 @@ const stylesSchema = baseCommandSchema.extend({
   selector: z.string().min(1),
 });
 
+const formsSchema = baseCommandSchema.extend({
+  action: z.literal('forms'),
+});
+
+const tableSchema = baseCommandSchema.extend({
+  action: z.literal('table'),
+  selector: z.string().min(1),
+  format: z.enum(['csv', 'json']).optional(),
+});
+
+const auditSchema = baseCommandSchema.extend({
+  action: z.literal('audit'),
+  selector: z.string().min(1).optional(),
+  failOn: z.enum(['minor', 'moderate', 'serious', 'critical']).optional(),
+});
+
+const metricsSchema = baseCommandSchema.extend({
+  action: z.literal('metrics'),
+});
+
+const linksSchema = baseCommandSchema.extend({
+  action: z.literal('links'),
+  selector: z.string().min(1).optional(),
+  absolute: z.boolean().optional(),
+});
+
 const videoStartSchema = baseCommandSchema.extend({
   action: z.literal('video_start'),
   path: z.string().min(1),
@@ -356,6 +617,12 @@ const recordingRestartSchema = baseCommandSchema.extend({
   url: z.string().min(1).optional(),
 });
 
+const codegenStartSchema = baseCommandSchema.extend({
+  action: z.literal('codegen_start'),
+  path: z.string().min(1),
+  url: z.string().min(1).optional(),
+});
+
 const traceStartSchema = baseCommandSchema.extend({
   action: z.literal('trace_start'),
   screenshots: z.boolean().optional(),
@@ -369,11 +636,21 @@ const traceStopSchema = baseCommandSchema.extend({
 
 const harStartSchema = baseCommandSchema.extend({
   action: z.literal('har_start'),
+  path: z.string().min(1),
 });
 
 const harStopSchema = baseCommandSchema.extend({
   action: z.literal('har_stop'),
-  path: z.string().min(1),
+  path: z.string().min(1).optional(),
+});
+
+const coverageStartSchema = baseCommandSchema.extend({
+  action: z.literal('coverage_start'),
+});
+
+const coverageStopSchema = baseCommandSchema.extend({
+  action: z.literal('coverage_stop'),
+  lcov: z.string().min(1).optional(),
 });
 
 const stateSaveSchema = baseCommandSchema.extend({
@@ -389,6 +666,8 @@ const stateLoadSchema = baseCommandSchema.extend({
 const consoleSchema = baseCommandSchema.extend({
   action: z.literal('console'),
   clear: z.boolean().optional(),
+  level: z.enum(['error', 'warn', 'all']).optional(),
+  since: z.number().int().nonnegative().optional(),
 });
 
 const errorsSchema = baseCommandSchema.extend({
@@ -396,6 +675,16 @@ const errorsSchema = baseCommandSchema.extend({
   clear: z.boolean().optional(),
 });
 
+const historySchema = baseCommandSchema.extend({
+  action: z.literal('history'),
+  limit: z.number().int().positive().optional(),
+});
+
+const historyGoSchema = baseCommandSchema.extend({
+  action: z.literal('history_go'),
+  index: z.number().int().positive(),
+});
+
 const keyboardSchema = baseCommandSchema.extend({
   action: z.literal('keyboard'),
   keys: z.string().min(1),
@@ -415,13 +704,15 @@ const tapSchema = baseCommandSchema.extend({
 
 const clipboardSchema = baseCommandSchema.extend({
   action: z.literal('clipboard'),
-  operation: z.enum(['copy', 'paste', 'read']),
+  operation: z.enum(['copy', 'paste', 'read', 'write']),
   text: z.string().optional(),
 });
 
 const highlightSchema = baseCommandSchema.extend({
   action: z.literal('highlight'),
   selector: z.string().min(1),
+  label: z.string().min(1).optional(),
+  duration: z.number().int().nonnegative().optional(),
 });
 
 const clearSchema = baseCommandSchema.extend({
@@ -497,9 +788,18 @@ const offlineSchema = baseCommandSchema.extend({
   offline: z.boolean(),
 });
 
+const throttleSchema = baseCommandSchema.extend({
+  action: z.literal('throttle'),
+  profile: z.enum(['slow3g', 'fast3g', 'custom', 'off']),
+  downloadKbps: z.number().positive().optional(),
+  uploadKbps: z.number().positive().optional(),
+  latencyMs: z.number().nonnegative().optional(),
+});
+
 const headersSchema = baseCommandSchema.extend({
   action: z.literal('headers'),
   headers: z.record(z.string(), z.string().optional()),
+  merge: z.boolean().optional(),
 });
 
 const pauseSchema = baseCommandSchema.extend({
@@ -562,6 +862,10 @@ const localeSchema = baseCommandSchema.extend({
   locale: z.string().min(1),
 });
 
+const emulateResetSchema = baseCommandSchema.extend({
+  action: z.literal('emulate_reset'),
+});
+
 const credentialsSchema = baseCommandSchema.extend({
   action: z.literal('credentials'),
   username: z.string(),
@@ -574,6 +878,14 @@ const mouseMoveSchema = baseCommandSchema.extend({
   y: z.number(),
 });
 
+const mouseClickSchema = baseCommandSchema.extend({
+  action: z.literal('mouseclick'),
+  x: z.number(),
+  y: z.number(),
+  button: z.enum(['left', 'right', 'middle']).optional(),
+  clickCount: z.number().int().positive().optional(),
+});
+
 const mouseDownSchema = baseCommandSchema.extend({
   action: z.literal('mousedown'),
   button: z.enum(['left', 'right', 'middle']).optional(),
@@ -631,6 +943,30 @@ const waitForDownloadSchema = baseCommandSchema.extend({
   timeout: z.number().positive().optional(),
 });
 
+const waitForRequestSchema = baseCommandSchema.extend({
+  action: z.literal('waitforrequest'),
+  url: z.string().min(1),
+  timeout: z.number().positive().optional(),
+});
+
+const waitForResponseSchema = baseCommandSchema.extend({
+  action: z.literal('waitforresponse'),
+  url: z.string().min(1),
+  status: z.number().int().min(100).max(599).optional(),
+  timeout: z.number().positive().optional(),
+});
+
+const waitForSpaIdleSchema = baseCommandSchema.extend({
+  action: z.literal('waitforspaidle'),
+  quiet: z.number().int().positive().optional(),
+  timeout: z.number().positive().optional(),
+});
+
+const downloadDirSchema = baseCommandSchema.extend({
+  action: z.literal('download_dir'),
+  path: z.string().min(1),
+});
+
 const responseBodySchema = baseCommandSchema.extend({
   action: z.literal('responsebody'),
   url: z.string().min(1),
@@ -701,6 +1037,7 @@ const pressSchema = baseCommandSchema.extend({
   action: z.literal('press'),
   key: z.string().min(1),
   selector: z.string().min(1).optional(),
+  count: z.number().int().positive().max(1000).optional(),
 });
 
 const screenshotSchema = baseCommandSchema.extend({
@@ -708,22 +1045,58 @@ const screenshotSchema = baseCommandSchema.extend({
   path: z.string().nullable().optional(),
   fullPage: z.boolean().optional(),
   selector: z.string().min(1).nullish(),
-  format: z.enum(['png', 'jpeg']).optional(),
+  format: z.enum(['png', 'jpeg', 'webp']).optional(),
   quality: z.number().min(0).max(100).optional(),
+  clip: z
+    .object({
+      x: z.number(),
+      y: z.number(),
+      width: z.number().positive(),
+      height: z.number().positive(),
+    })
+    .optional(),
+  stdout: z.boolean().optional(),
+  // Draw the last snapshot's refs onto the image
+  annotateRefs: z.boolean().optional(),
 });
 
 const snapshotSchema = baseCommandSchema.extend({
   action: z.literal('snapshot'),
   interactive: z.boolean().optional(),
+  cursor: z.boolean().optional(),
+  maxDepth: z.number().nonnegative().optional(),
+  compact: z.boolean().optional(),
+  selector: z.string().optional(),
+  format: z.enum(['text', 'json', 'yaml', 'md']).optional(),
+  maxChars: z.number().int().positive().optional(),
+  page: z.number().int().positive().optional(),
+  coords: z.boolean().optional(),
+  ifChanged: z.string().optional(),
+});
+
+const snapshotDiffSchema = baseCommandSchema.extend({
+  action: z.literal('snapshot_diff'),
+  interactive: z.boolean().optional(),
+  cursor: z.boolean().optional(),
   maxDepth: z.number().nonnegative().optional(),
   compact: z.boolean().optional(),
   selector: z.string().optional(),
 });
 
+const readSchema = baseCommandSchema.extend({
+  action: z.literal('read'),
+  // Where to start; the top of the page without one
+  selector: z.string().min(1).optional(),
+  // Continue the last reading instead
+  direction: z.enum(['next', 'prev']).optional(),
+  maxChars: z.number().int().positive().optional(),
+});
+
 const evaluateSchema = baseCommandSchema.extend({
   action: z.literal('evaluate'),
   script: z.string().min(1),
   args: z.array(z.unknown()).optional(),
+  selector: z.string().min(1).optional(),
 });
 
 const waitSchema = baseCommandSchema.extend({
@@ -733,6 +1106,14 @@ const waitSchema = baseCommandSchema.extend({
   state: z.enum(['attached', 'detached', 'visible', 'hidden']).optional(),
 });
 
+const expectSchema = baseCommandSchema.extend({
+  action: z.literal('expect'),
+  kind: z.enum(['text', 'visible', 'url', 'title']),
+  selector: z.string().min(1).optional(),
+  expected: z.string().min(1).optional(),
+  timeout: z.number().int().positive().optional(),
+});
+
 const scrollSchema = baseCommandSchema.extend({
   action: z.literal('scroll'),
   selector: z.string().min(1).optional(),
@@ -740,26 +1121,34 @@ const scrollSchema = baseCommandSchema.extend({
   y: z.number().optional(),
   direction: z.enum(['up', 'down', 'left', 'right']).optional(),
   amount: z.number().positive().optional(),
+  position: z.enum(['top', 'bottom']).optional(),
 });
 
 const selectSchema = baseCommandSchema.extend({
   action: z.literal('select'),
   selector: z.string().min(1),
-  values: z.union([z.string(), z.array(z.string())]),
+  values: z.union([z.string(), z.array(z.string())]).optional(),
+  label: z.union([z.string(), z.array(z.string())]).optional(),
+  index: z.number().int().nonnegative().optional(),
 });
 
 const hoverSchema = baseCommandSchema.extend({
   action: z.literal('hover'),
   selector: z.string().min(1),
+  ...matchPickSchema,
 });
 
 const contentSchema = baseCommandSchema.extend({
   action: z.literal('content'),
   selector: z.string().min(1).optional(),
+  outer: z.boolean().optional(),
+  path: z.string().min(1).optional(),
 });
 
 const closeSchema = baseCommandSchema.extend({
   action: z.literal('close'),
+  keepDaemon: z.boolean().optional(),
+  saveState: z.string().min(1).optional(),
 });
 
 // Tab/Window schemas
@@ -792,6 +1181,25 @@ const windowNewSchema = baseCommandSchema.extend({
     .optional(),
 });
 
+const contextNewSchema = baseCommandSchema.extend({
+  action: z.literal('context_new'),
+  name: z.string().min(1).optional(),
+});
+
+const contextListSchema = baseCommandSchema.extend({
+  action: z.literal('context_list'),
+});
+
+const contextSwitchSchema = baseCommandSchema.extend({
+  action: z.literal('context_switch'),
+  name: z.string().min(1),
+});
+
+const contextCloseSchema = baseCommandSchema.extend({
+  action: z.literal('context_close'),
+  name: z.string().min(1).optional(),
+});
+
 // Union schema for all commands
 const commandSchema = z.discriminatedUnion('action', [
   launchSchema,
@@ -799,23 +1207,33 @@ const commandSchema = z.discriminatedUnion('action', [
   clickSchema,
   typeSchema,
   fillSchema,
+  fillFormSchema,
   checkSchema,
   uncheckSchema,
   uploadSchema,
   dblclickSchema,
   focusSchema,
+  blurSchema,
   dragSchema,
   frameSchema,
   mainframeSchema,
+  frameListSchema,
+  findSchema,
+  refsSchema,
+  visibleElementsSchema,
   getByRoleSchema,
   getByTextSchema,
+  getByNameSchema,
   getByLabelSchema,
   getByPlaceholderSchema,
   pressSchema,
   screenshotSchema,
   snapshotSchema,
+  snapshotDiffSchema,
+  readSchema,
   evaluateSchema,
   waitSchema,
+  expectSchema,
   scrollSchema,
   selectSchema,
   hoverSchema,
@@ -826,17 +1244,36 @@ const commandSchema = z.discriminatedUnion('action', [
   tabSwitchSchema,
   tabCloseSchema,
   windowNewSchema,
+  contextNewSchema,
+  contextListSchema,
+  contextSwitchSchema,
+  contextCloseSchema,
   cookiesGetSchema,
   cookiesSetSchema,
   cookiesClearSchema,
+  cookiesExportSchema,
+  cookiesImportSchema,
   storageGetSchema,
   storageSetSchema,
+  storageRemoveSchema,
   storageClearSchema,
   dialogSchema,
+  dialogAutoSchema,
   pdfSchema,
+  saveSchema,
   routeSchema,
   unrouteSchema,
   requestsSchema,
+  mockAddSchema,
+  mockListSchema,
+  mockRemoveSchema,
+  fetchSchema,
+  blockSchema,
+  unblockSchema,
+  networkLogSchema,
+  networkCaptureSchema,
+  networkCaptureStopSchema,
+  networkShowSchema,
   downloadSchema,
   geolocationSchema,
   permissionsSchema,
@@ -849,26 +1286,45 @@ const commandSchema = z.discriminatedUnion('action', [
   urlSchema,
   titleSchema,
   getAttributeSchema,
+  computedStyleSchema,
   getTextSchema,
+  articleSchema,
   isVisibleSchema,
   isEnabledSchema,
   isCheckedSchema,
   countSchema,
   boundingBoxSchema,
   stylesSchema,
+  linksSchema,
+  formsSchema,
+  tableSchema,
+  metricsSchema,
+  auditSchema,
   videoStartSchema,
   videoStopSchema,
   recordingStartSchema,
   recordingStopSchema,
   recordingRestartSchema,
+  codegenStartSchema,
   traceStartSchema,
   traceStopSchema,
   harStartSchema,
   harStopSchema,
+  coverageStartSchema,
+  coverageStopSchema,
   stateSaveSchema,
   stateLoadSchema,
   consoleSchema,
+  subscribeSchema,
+  watchSchema,
+  helloSchema,
+  cancelSchema,
+  daemonStatusSchema,
+  daemonConfigSchema,
+  statusSchema,
   errorsSchema,
+  historySchema,
+  historyGoSchema,
   keyboardSchema,
   wheelSchema,
   tapSchema,
@@ -887,6 +1343,7 @@ const commandSchema = z.discriminatedUnion('action', [
   addStyleSchema,
   emulateMediaSchema,
   offlineSchema,
+  throttleSchema,
   headersSchema,
   pauseSchema,
   getByAltTextSchema,
@@ -898,8 +1355,10 @@ const commandSchema = z.discriminatedUnion('action', [
   setContentSchema,
   timezoneSchema,
   localeSchema,
+  emulateResetSchema,
   credentialsSchema,
   mouseMoveSchema,
+  mouseClickSchema,
   mouseDownSchema,
   mouseUpSchema,
   bringToFrontSchema,
@@ -911,6 +1370,10 @@ const commandSchema = z.discriminatedUnion('action', [
   insertTextSchema,
   multiSelectSchema,
   waitForDownloadSchema,
+  waitForRequestSchema,
+  waitForResponseSchema,
+  waitForSpaIdleSchema,
+  downloadDirSchema,
   responseBodySchema,
   screencastStartSchema,
   screencastStopSchema,
@@ -921,6 +1384,13 @@ const commandSchema = z.discriminatedUnion('action', [
   deviceListSchema,
 ]);
 
+/**
+ * Every action the daemon accepts, as reported by `hello`
+ */
+export const SUPPORTED_ACTIONS: string[] = commandSchema.options.map(
+  (schema) => schema.shape.action.value
+);
+
 // Parse result type
 export type ParseResult =
   | { success: true; command: Command }
@@ -965,8 +1435,16 @@ export function successResponse<T>(id: string, data: T): Response<T> {
 /**
  * Create an error response
  */
-export function errorResponse(id: string, error: string): Response {
-  return { id, success: false, error };
+export function errorResponse(
+  id: string,
+  error: string,
+  errorCode?: ErrorCode,
+  errorContext?: ErrorContext
+): Response {
+  const response: ErrorResponse = { id, success: false, error };
+  if (errorCode) response.errorCode = errorCode;
+  if (errorContext) response.errorContext = errorContext;
+  return response;
 }
 
 /**
@@ -975,3 +1453,35 @@ export function errorResponse(id: string, error: string): Response {
 export function serializeResponse(response: Response): string {
   return JSON.stringify(response);
 }
+
+/**
+ * Response compression this daemon supports, reported by `hello`
+ */
+export const SUPPORTED_COMPRESSION: Compression[] = ['gzip'];
+
+// Smaller responses are sent as is; compressing them costs more than it saves
+const COMPRESS_MIN_BYTES = 64 * 1024;
+
+/**
+ * Encode a response for the socket. `line` (the default) is the JSON followed by a
+ * newline; `length` is a `#<byte length>` header line followed by exactly that many
+ * bytes of JSON, which clients can read in chunks and show progress for. Clients
+ * that ask for `length` still accept lines, so older daemons keep working.
+ *
+ * With `compression`, large length-framed responses are compressed and the header
+ * names the encoding: `#<compressed length> gzip`.
+ */
+export function encodeResponse(
+  response: Response,
+  framing: Framing = 'line',
+  compression?: Compression
+): string | Uint8Array {
+  const json = serializeResponse(response);
+  if (framing === 'line') return json + '\n';
+  const bytes = Buffer.from(json);
+  if (compression === 'gzip' && bytes.length >= COMPRESS_MIN_BYTES) {
+    const body = gzipSync(bytes);
+    return Buffer.concat([Buffer.from(`#${body.length} gzip\n`), body]);
+  }
+  return `#${bytes.length}\n${json}`;
+}
@@ -0,0 +1,64 @@
+import { describe, it, expect } from 'bun:test';
+import { chunkAfter, chunkBefore, findItem, readItems } from './reader.js';
+
+const TREE = [
+  '- banner:',
+  '  - link "Home" [ref=e1]:',
+  '    - /url: /',
+  '- main:',
+  '  - heading "Pricing" [ref=e2] [level=2]',
+  '  - paragraph: Plans for every team',
+  '  - list:',
+  '    - listitem: Free',
+  '    - listitem: Pro',
+  '  - checkbox "Yearly" [ref=e3] [checked]',
+].join('\n');
+
+describe('readItems', () => {
+  it('should read named and text nodes in document order', () => {
+    expect(readItems(TREE).map((item) => item.line)).toEqual([
+      'link: Home @e1 </>',
+      'heading (level 2): Pricing @e2',
+      'paragraph: Plans for every team',
+      'listitem: Free',
+      'listitem: Pro',
+      'checkbox (checked): Yearly @e3',
+    ]);
+  });
+
+  it('should read text nodes without a role', () => {
+    expect(readItems('- text: Hello there').map((item) => item.line)).toEqual(['Hello there']);
+  });
+});
+
+describe('findItem', () => {
+  it('should find an element read from its own snapshot', () => {
+    const items = readItems(TREE);
+    const [pricing] = readItems('- heading "Pricing" [level=2]');
+    expect(findItem(items, pricing)).toBe(1);
+    const [missing] = readItems('- heading "About" [level=2]');
+    expect(findItem(items, missing)).toBe(-1);
+  });
+});
+
+describe('chunks', () => {
+  const items = readItems(TREE);
+
+  it('should fill a chunk up to the limit', () => {
+    // 'heading (level 2): Pricing @e2' and the paragraph, with the newline between
+    expect(chunkAfter(items, 1, 62)).toEqual({ from: 1, to: 3 });
+    expect(chunkAfter(items, 1, 61)).toEqual({ from: 1, to: 2 });
+  });
+
+  it('should always take one item', () => {
+    expect(chunkAfter(items, 0, 1)).toEqual({ from: 0, to: 1 });
+    expect(chunkBefore(items, 3, 1)).toEqual({ from: 2, to: 3 });
+  });
+
+  it('should stop at either end', () => {
+    expect(chunkAfter(items, 4, 10_000)).toEqual({ from: 4, to: 6 });
+    expect(chunkAfter(items, 6, 10_000)).toEqual({ from: 6, to: 6 });
+    expect(chunkBefore(items, 2, 10_000)).toEqual({ from: 0, to: 2 });
+    expect(chunkBefore(items, 0, 10_000)).toEqual({ from: 0, to: 0 });
+  });
+});
@@ -0,0 +1,104 @@
+/**
+ * Screen-reader-style reading of a page, a chunk at a time.
+ *
+ * The snapshot tree is flattened into one line per thing a screen reader
+ * would announce, in document order: headings, paragraphs, text, links and
+ * controls with their state and ref. Containers with no name of their own are
+ * read through their contents. `read` starts at the top of the page or at an
+ * element; `read next` and `read prev` move through the same lines, so a long
+ * page can be consumed as it's needed instead of as one large snapshot.
+ *
+ * Usage:
+ *   agent-browser read                 # From the top of the page
+ *   agent-browser read "#pricing"      # From an element
+ *   agent-browser read next            # The chunk after the last one
+ */
+
+import { parseSnapshotTree, type SnapshotNode } from './snapshot.js';
+
+/** Characters per chunk when --max-chars isn't given */
+export const DEFAULT_READ_CHARS = 2000;
+
+export interface ReadItem {
+  role: string;
+  name?: string;
+  text?: string;
+  /** How the item is read out */
+  line: string;
+}
+
+/** Lines `from` (inclusive) to `to` (exclusive) of a reading */
+export interface ReadRange {
+  from: number;
+  to: number;
+}
+
+function readLine(node: SnapshotNode): string {
+  if (node.role === 'text') return node.text ?? '';
+  const states = Object.entries(node.attributes ?? {}).map(([key, value]) =>
+    value === true ? key : `${key} ${value}`
+  );
+  let line = states.length > 0 ? `${node.role} (${states.join(', ')})` : node.role;
+  const content = [node.name, node.text].filter(Boolean).join(': ');
+  if (content) line += `: ${content}`;
+  if (node.ref) line += ` @${node.ref}`;
+  if (node.props?.url) line += ` <${node.props.url}>`;
+  return line;
+}
+
+/**
+ * The readable items of a rendered snapshot tree, in document order
+ */
+export function readItems(tree: string): ReadItem[] {
+  const items: ReadItem[] = [];
+  const walk = (nodes: SnapshotNode[]) => {
+    for (const node of nodes) {
+      if (node.name || node.text) {
+        items.push({ role: node.role, name: node.name, text: node.text, line: readLine(node) });
+      }
+      if (node.children) walk(node.children);
+    }
+  };
+  walk(parseSnapshotTree(tree));
+  return items;
+}
+
+/**
+ * Index of the first item announcing the same thing as `target`, or -1
+ */
+export function findItem(items: ReadItem[], target: ReadItem): number {
+  return items.findIndex(
+    (item) => item.role === target.role && item.name === target.name && item.text === target.text
+  );
+}
+
+/**
+ * The chunk starting at `from`: as many items as fit in `maxChars`, and at
+ * least one
+ */
+export function chunkAfter(items: ReadItem[], from: number, maxChars: number): ReadRange {
+  let to = from;
+  let chars = 0;
+  while (to < items.length) {
+    const extra = items[to].line.length + (to > from ? 1 : 0);
+    if (to > from && chars + extra > maxChars) break;
+    chars += extra;
+    to++;
+  }
+  return { from, to };
+}
+
+/**
+ * The chunk ending just before `to`, sized like chunkAfter
+ */
+export function chunkBefore(items: ReadItem[], to: number, maxChars: number): ReadRange {
+  let from = to;
+  let chars = 0;
+  while (from > 0) {
+    const extra = items[from - 1].line.length + (from < to ? 1 : 0);
+    if (from < to && chars + extra > maxChars) break;
+    chars += extra;
+    from--;
+  }
+  return { from, to };
+}
@@ -0,0 +1,249 @@
+import { describe, it, expect } from 'bun:test';
+import {
+  type RefMap,
+  addCoords,
+  diffSnapshots,
+  findRefs,
+  formatSnapshot,
+  fuzzyScore,
+  listRefs,
+  paginateSnapshot,
+  parseSnapshotTree,
+  snapshotHash,
+  visibleElements,
+} from './snapshot.js';
+
+describe('fuzzyScore', () => {
+  it('should rank exact, prefix and substring matches', () => {
+    expect(fuzzyScore('sign in', 'Sign in')).toBe(100);
+    expect(fuzzyScore('sign', 'Sign in')).toBe(90);
+    expect(fuzzyScore('in', 'Sign in')).toBe(80);
+  });
+
+  it('should match words in any order and letters in order', () => {
+    expect(fuzzyScore('in sign', 'Sign in')).toBe(60);
+    expect(fuzzyScore('sgnin', 'Sign in')).toBe(30);
+  });
+
+  it('should return 0 for unrelated names', () => {
+    expect(fuzzyScore('checkout', 'Sign in')).toBe(0);
+    expect(fuzzyScore('', 'Sign in')).toBe(0);
+  });
+});
+
+describe('findRefs', () => {
+  const refs: RefMap = {
+    e1: { selector: '', role: 'heading', name: 'Sign in to continue' },
+    e2: { selector: '', role: 'link', name: 'Sign in with Google' },
+    e3: { selector: '', role: 'button', name: 'Sign in' },
+    e4: { selector: '', role: 'textbox' },
+  };
+
+  it('should return best matches first', () => {
+    const matches = findRefs(refs, 'sign in');
+    expect(matches.map((m) => m.ref)).toEqual(['e3', 'e1', 'e2']);
+    expect(matches[0]).toEqual({ ref: 'e3', role: 'button', name: 'Sign in', score: 100 });
+  });
+
+  it('should filter by role and apply the limit', () => {
+    expect(findRefs(refs, 'sign', { role: 'link' }).map((m) => m.ref)).toEqual(['e2']);
+    expect(findRefs(refs, 'sign', { limit: 1 })).toHaveLength(1);
+  });
+});
+
+describe('listRefs', () => {
+  const refs: RefMap = {
+    e10: { selector: '', role: 'button', name: 'Save', nth: 1 },
+    e2: { selector: '', role: 'link', name: 'Docs' },
+    e3: { selector: '', role: 'textbox' },
+  };
+
+  it('should list refs in snapshot order', () => {
+    expect(listRefs(refs)).toEqual([
+      { ref: 'e2', role: 'link', name: 'Docs' },
+      { ref: 'e3', role: 'textbox' },
+      { ref: 'e10', role: 'button', name: 'Save', nth: 1 },
+    ]);
+  });
+
+  it('should filter by role or name', () => {
+    expect(listRefs(refs, 'TEXT').map((r) => r.ref)).toEqual(['e3']);
+    expect(listRefs(refs, 'sav').map((r) => r.ref)).toEqual(['e10']);
+    expect(listRefs(refs, 'nothing')).toEqual([]);
+  });
+});
+
+describe('visibleElements', () => {
+  const refs: RefMap = {
+    e1: { selector: '', role: 'link', name: 'Home' },
+    e2: { selector: '', role: 'button', name: 'Buy' },
+    e3: { selector: '', role: 'button', name: 'Footer' },
+    e4: { selector: '', role: 'checkbox' },
+  };
+  const box = { x: 10, y: 20, width: 80, height: 24 };
+  const geometry = {
+    e1: { box, inViewport: true },
+    e2: { box, inViewport: true },
+    e3: { box: { ...box, y: 1400 }, inViewport: false },
+    e4: null,
+  };
+
+  it('should keep only elements inside the viewport, with their boxes', () => {
+    expect(visibleElements(refs, geometry)).toEqual([
+      { ref: 'e1', role: 'link', name: 'Home', box },
+      { ref: 'e2', role: 'button', name: 'Buy', box },
+    ]);
+  });
+
+  it('should filter by role', () => {
+    expect(visibleElements(refs, geometry, ['Button']).map((e) => e.ref)).toEqual(['e2']);
+  });
+});
+
+describe('parseSnapshotTree', () => {
+  const tree = [
+    '- heading "Example Domain" [ref=e1] [level=1]',
+    '- paragraph: Some text',
+    '- link "More" [ref=e2]:',
+    '  - /url: https://example.com',
+    '  - img "Logo"',
+    '- checkbox "Agree" [ref=e3] [checked] [nth=1]',
+  ].join('\n');
+
+  it('should parse roles, names, refs and attributes', () => {
+    const [heading, paragraph, link, checkbox] = parseSnapshotTree(tree);
+    expect(heading).toEqual({
+      role: 'heading',
+      name: 'Example Domain',
+      ref: 'e1',
+      attributes: { level: 1 },
+    });
+    expect(paragraph).toEqual({ role: 'paragraph', text: 'Some text' });
+    expect(link.props).toEqual({ url: 'https://example.com' });
+    expect(link.children).toEqual([{ role: 'img', name: 'Logo' }]);
+    expect(checkbox).toMatchObject({ ref: 'e3', nth: 1, attributes: { checked: true } });
+  });
+
+  it('should skip lines that are not list items', () => {
+    expect(parseSnapshotTree('(no interactive elements)')).toEqual([]);
+  });
+
+  it('should render yaml and markdown', () => {
+    expect(formatSnapshot(tree, 'yaml')).toContain(
+      '- role: heading\n  name: "Example Domain"\n  ref: e1\n  attributes:\n    level: 1'
+    );
+    expect(formatSnapshot(tree, 'md').split('\n')).toEqual([
+      '- **heading** Example Domain `@e1` _(level=1)_',
+      '- **paragraph**: Some text',
+      '- **link** More `@e2` <https://example.com>',
+      '  - **img** Logo',
+      '- **checkbox** Agree `@e3` _(checked)_',
+    ]);
+    expect(formatSnapshot(tree, 'text')).toBe(tree);
+  });
+});
+
+describe('addCoords', () => {
+  const tree = [
+    '- button "Save" [ref=e1]',
+    '- link "Footer" [ref=e2]',
+    '- textbox "Secret" [ref=e3]',
+    '- button "New" [ref=e4]',
+  ].join('\n');
+  const withCoords = addCoords(tree, {
+    e1: { box: { x: 10, y: 20, width: 80, height: 32 }, inViewport: true },
+    e2: { box: { x: 0, y: 1400, width: 120, height: 18 }, inViewport: false },
+    e3: null,
+  });
+
+  it('should add boxes after the refs', () => {
+    expect(withCoords.split('\n')).toEqual([
+      '- button "Save" [ref=e1] [box=10,20,80,32]',
+      '- link "Footer" [ref=e2] [box=0,1400,120,18] [offscreen]',
+      '- textbox "Secret" [ref=e3] [hidden]',
+      '- button "New" [ref=e4]',
+    ]);
+  });
+
+  it('should parse boxes back out', () => {
+    const [save, footer, secret] = parseSnapshotTree(withCoords);
+    expect(save.box).toEqual({ x: 10, y: 20, width: 80, height: 32 });
+    expect(save.attributes).toBeUndefined();
+    expect(footer.attributes).toEqual({ offscreen: true });
+    expect(secret.box).toBeUndefined();
+    expect(secret.attributes).toEqual({ hidden: true });
+    expect(formatSnapshot(withCoords, 'md')).toContain('`@e2` _(box=0,1400,120,18, offscreen)_');
+  });
+});
+
+describe('diffSnapshots', () => {
+  const before = [
+    '- heading "Shop" [ref=e1] [level=1]',
+    '- button "Add" [ref=e2]',
+    '- checkbox "Gift" [ref=e3]',
+    '- link "Help" [ref=e4]',
+  ].join('\n');
+
+  it('should ignore ref renumbering', () => {
+    const after = before.replace('e2', 'e7').replace('e4', 'e9');
+    expect(diffSnapshots(before, after)).toEqual([]);
+  });
+
+  it('should report added, removed and changed lines', () => {
+    const after = [
+      '- heading "Shop" [ref=e1] [level=1]',
+      '- alert "Saved" [ref=e2]',
+      '- checkbox "Gift" [ref=e3] [checked]',
+      '- link "Help" [ref=e4]',
+    ].join('\n');
+    expect(diffSnapshots(before, after)).toEqual([
+      { type: 'added', line: 'alert "Saved" [ref=e2]' },
+      { type: 'changed', line: 'checkbox "Gift" [ref=e3] [checked]', before: 'checkbox "Gift"' },
+      { type: 'removed', line: 'button "Add"' },
+    ]);
+  });
+
+  it('should treat a missing baseline as all added', () => {
+    expect(diffSnapshots('', '- button "Go" [ref=e1]')).toEqual([
+      { type: 'added', line: 'button "Go" [ref=e1]' },
+    ]);
+  });
+});
+
+describe('paginateSnapshot', () => {
+  const text = Array.from({ length: 10 }, (_, i) => `- listitem "Row ${i}"`).join('\n');
+
+  it('should split between lines and add a continuation marker', () => {
+    const first = paginateSnapshot(text, 40);
+    expect(first.pages).toBe(5);
+    const lines = first.text.split('\n');
+    expect(lines.slice(0, 2)).toEqual(['- listitem "Row 0"', '- listitem "Row 1"']);
+    expect(lines[2]).toBe(
+      `# Page 1 of 5: lines 1-2 of 10, ${first.omittedChars} of ${text.length} chars omitted. ` +
+        'Continue with --page 2'
+    );
+    expect(first.omittedChars).toBe(text.length - 37);
+    const last = paginateSnapshot(text, 40, 5);
+    expect(last.fromLine).toBe(9);
+    expect(last.text).not.toContain('Continue with');
+  });
+
+  it('should leave output that fits unchanged', () => {
+    const page = paginateSnapshot(text, 10_000);
+    expect(page.text).toBe(text);
+    expect(page.omittedChars).toBe(0);
+  });
+
+  it('should reject pages past the end', () => {
+    expect(() => paginateSnapshot(text, 40, 6)).toThrow('out of range');
+  });
+});
+
+describe('snapshotHash', () => {
+  it('should be stable for the same tree and differ for another', () => {
+    const tree = '- button "Save" [ref=e1]';
+    expect(snapshotHash(tree)).toBe(snapshotHash(tree));
+    expect(snapshotHash(tree)).toMatch(/^[0-9a-f]{16}$/);
+    expect(snapshotHash('- button "Saved" [ref=e1]')).not.toBe(snapshotHash(tree));
+  });
+});
@@ -17,7 +17,8 @@
  *   agent-browser click @e2             # Click element by ref
  */
 
-import type { Page, Locator } from 'playwright-core';
+import { createHash } from 'node:crypto';
+import type { Frame, Locator } from 'playwright-core';
 
 export interface RefMap {
   [ref: string]: {
@@ -139,11 +140,11 @@ function buildSelector(role: string, name?: string): string {
 }
 
 /**
- * Query the page for clickable elements that might not have proper ARIA roles.
+ * Query the frame for clickable elements that might not have proper ARIA roles.
  * This finds elements with cursor: pointer or onclick handlers.
  */
 async function findCursorInteractiveElements(
-  page: Page,
+  frame: Frame,
   selector?: string
 ): Promise<
   Array<{
@@ -244,21 +245,21 @@ async function findCursorInteractiveElements(
 
   // eslint-disable-next-line @typescript-eslint/no-implied-eval
   const fn = new Function('return ' + scriptBody)();
-  return page.evaluate(fn, rootSelector);
+  return frame.evaluate(fn, rootSelector);
 }
 
 /**
  * Get enhanced snapshot with refs and optional filtering
  */
 export async function getEnhancedSnapshot(
-  page: Page,
+  frame: Frame,
   options: SnapshotOptions = {}
 ): Promise<EnhancedSnapshot> {
   resetRefs();
   const refs: RefMap = {};
 
   // Get ARIA snapshot from Playwright
-  const locator = options.selector ? page.locator(options.selector) : page.locator(':root');
+  const locator = options.selector ? frame.locator(options.selector) : frame.locator(':root');
   const ariaTree = await locator.ariaSnapshot();
 
   if (!ariaTree) {
@@ -274,7 +275,7 @@ export async function getEnhancedSnapshot(
   // When cursor flag is set, also find cursor-interactive elements
   // that may not have proper ARIA roles
   if (options.cursor) {
-    const cursorElements = await findCursorInteractiveElements(page, options.selector);
+    const cursorElements = await findCursorInteractiveElements(frame, options.selector);
 
     // Filter out elements whose text is already captured in the snapshot
     const existingTexts = new Set(Object.values(refs).map((r) => r.name?.toLowerCase()));
@@ -577,6 +578,510 @@ function compactTree(tree: string): string {
   return result.join('\n');
 }
 
+export type SnapshotFormat = 'text' | 'json' | 'yaml' | 'md';
+
+/** One element of a snapshot tree, parsed from its text rendering */
+export interface SnapshotNode {
+  role: string;
+  name?: string;
+  ref?: string;
+  nth?: number;
+  /** State and hints from brackets, e.g. [level=1] [checked] */
+  attributes?: Record<string, string | number | boolean>;
+  /** Inline text after the colon, e.g. `- paragraph: Some text` */
+  text?: string;
+  /** Properties from `/key: value` lines, e.g. a link's url */
+  props?: Record<string, string>;
+  /** With `--coords`, from [box=x,y,w,h] */
+  box?: ElementBox;
+  children?: SnapshotNode[];
+}
+
+/** An element's bounding box in CSS pixels, relative to the viewport */
+export interface ElementBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/** Where a ref's element is drawn, for `snapshot --coords` */
+export interface RefGeometry {
+  box: ElementBox;
+  /** Whether any of the box is inside the viewport */
+  inViewport: boolean;
+}
+
+/**
+ * Add the geometry of each ref to the tree: `[box=x,y,w,h]`, followed by
+ * `[offscreen]` when the box is outside the viewport, or `[hidden]` in its place
+ * when the element isn't drawn (null). Refs missing from `geometry` are left as
+ * they are.
+ */
+export function addCoords(tree: string, geometry: Record<string, RefGeometry | null>): string {
+  return tree.replace(/ \[ref=(e\d+)\]/g, (marker, ref: string) => {
+    if (!(ref in geometry)) return marker;
+    const element = geometry[ref];
+    if (!element) return `${marker} [hidden]`;
+    const { x, y, width, height } = element.box;
+    const offscreen = element.inViewport ? '' : ' [offscreen]';
+    return `${marker} [box=${x},${y},${width},${height}]${offscreen}`;
+  });
+}
+
+function parseBox(raw: string): ElementBox | undefined {
+  const values = raw.split(',').map(Number);
+  if (values.length !== 4 || !values.every(Number.isFinite)) return undefined;
+  const [x, y, width, height] = values;
+  return { x, y, width, height };
+}
+
+function unquote(quoted: string): string {
+  try {
+    return JSON.parse(quoted);
+  } catch {
+    return quoted.slice(1, -1);
+  }
+}
+
+function parseAttributes(node: SnapshotNode, brackets: string): void {
+  for (const [, content] of brackets.matchAll(/\[([^\]]*)\]/g)) {
+    const eq = content.indexOf('=');
+    if (eq > 0 && !content.includes(', ')) {
+      const key = content.slice(0, eq);
+      const raw = content.slice(eq + 1);
+      const value = /^\d+$/.test(raw) ? Number(raw) : raw;
+      const box = key === 'box' ? parseBox(raw) : undefined;
+      if (key === 'ref') node.ref = raw;
+      else if (key === 'nth') node.nth = Number(raw);
+      else if (box) node.box = box;
+      else (node.attributes ??= {})[key] = value;
+      continue;
+    }
+    // Bare states ([checked]) and cursor hints ([cursor:pointer, onclick])
+    for (const flag of content.split(', ')) {
+      if (flag) (node.attributes ??= {})[flag] = true;
+    }
+  }
+}
+
+/**
+ * Parse the text rendering of a snapshot back into a tree of nodes. Lines that
+ * are not list items (comments, placeholders like "(empty)") are skipped.
+ */
+export function parseSnapshotTree(tree: string): SnapshotNode[] {
+  const roots: SnapshotNode[] = [];
+  const stack: { depth: number; node: SnapshotNode }[] = [];
+
+  for (const line of tree.split('\n')) {
+    const item = line.match(/^(\s*)-\s+(.*)$/);
+    if (!item) continue;
+    const depth = Math.floor(item[1].length / 2);
+    const body = item[2];
+
+    while (stack.length > 0 && stack[stack.length - 1].depth >= depth) {
+      stack.pop();
+    }
+    const parent = stack[stack.length - 1]?.node;
+
+    // `/url: https://...` belongs to the element above it
+    const prop = body.match(/^\/([\w-]+):\s*(.*)$/);
+    if (prop) {
+      if (parent) (parent.props ??= {})[prop[1]] = prop[2];
+      continue;
+    }
+
+    const node: SnapshotNode = { role: 'text' };
+    const match = body.match(
+      /^(\w+)(?:\s+("(?:[^"\\]|\\.)*"))?((?:\s*\[[^\]]*\])*)\s*(?::\s*(.*))?$/
+    );
+    if (match) {
+      const [, role, quoted, brackets, text] = match;
+      node.role = role;
+      if (quoted && quoted !== '""') node.name = unquote(quoted);
+      if (brackets) parseAttributes(node, brackets);
+      if (text) node.text = text;
+    } else {
+      node.text = body;
+    }
+
+    if (parent) (parent.children ??= []).push(node);
+    else roots.push(node);
+    stack.push({ depth, node });
+  }
+
+  return roots;
+}
+
+function yamlScalar(value: string | number | boolean): string {
+  return typeof value === 'string' ? JSON.stringify(value) : String(value);
+}
+
+function yamlKey(key: string): string {
+  return /^[\w-]+$/.test(key) ? key : JSON.stringify(key);
+}
+
+function toYaml(nodes: SnapshotNode[], indent: string): string[] {
+  const lines: string[] = [];
+  for (const node of nodes) {
+    const fields: string[] = [`role: ${node.role}`];
+    if (node.name !== undefined) fields.push(`name: ${yamlScalar(node.name)}`);
+    if (node.ref) fields.push(`ref: ${node.ref}`);
+    if (node.nth !== undefined) fields.push(`nth: ${node.nth}`);
+    if (node.text !== undefined) fields.push(`text: ${yamlScalar(node.text)}`);
+    if (node.box) {
+      const { x, y, width, height } = node.box;
+      fields.push(`box: { x: ${x}, y: ${y}, width: ${width}, height: ${height} }`);
+    }
+    for (const [group, values] of [
+      ['attributes', node.attributes],
+      ['props', node.props],
+    ] as const) {
+      if (!values) continue;
+      fields.push(`${group}:`);
+      for (const [key, value] of Object.entries(values)) {
+        fields.push(`  ${yamlKey(key)}: ${yamlScalar(value)}`);
+      }
+    }
+    fields.forEach((field, i) => lines.push(`${indent}${i === 0 ? '- ' : '  '}${field}`));
+    if (node.children) {
+      lines.push(`${indent}  children:`);
+      lines.push(...toYaml(node.children, indent + '    '));
+    }
+  }
+  return lines;
+}
+
+function toMarkdown(nodes: SnapshotNode[], indent: string): string[] {
+  const lines: string[] = [];
+  for (const node of nodes) {
+    let line = `${indent}- **${node.role}**`;
+    if (node.name) line += ` ${node.name}`;
+    if (node.ref) line += ` \`@${node.ref}\``;
+    const attributes = Object.entries(node.attributes ?? {}).map(([key, value]) =>
+      value === true ? key : `${key}=${value}`
+    );
+    if (node.box) {
+      const { x, y, width, height } = node.box;
+      attributes.unshift(`box=${x},${y},${width},${height}`);
+    }
+    if (attributes.length > 0) line += ` _(${attributes.join(', ')})_`;
+    if (node.props?.url) line += ` <${node.props.url}>`;
+    if (node.text) line += `: ${node.text}`;
+    lines.push(line);
+    if (node.children) lines.push(...toMarkdown(node.children, indent + '  '));
+  }
+  return lines;
+}
+
+/**
+ * Render a snapshot tree as YAML or a Markdown list. `text` returns the tree as-is;
+ * use parseSnapshotTree for JSON.
+ */
+export function formatSnapshot(tree: string, format: Exclude<SnapshotFormat, 'json'>): string {
+  switch (format) {
+    case 'yaml':
+      return toYaml(parseSnapshotTree(tree), '').join('\n') || '[]';
+    case 'md':
+      return toMarkdown(parseSnapshotTree(tree), '').join('\n');
+    default:
+      return tree;
+  }
+}
+
+/** Page size used when --page is given without --max-chars */
+export const DEFAULT_SNAPSHOT_PAGE_CHARS = 20_000;
+
+export interface SnapshotPage {
+  text: string;
+  page: number;
+  pages: number;
+  /** 1-based line range of the full snapshot shown on this page */
+  fromLine: number;
+  toLine: number;
+  totalLines: number;
+  totalChars: number;
+  omittedChars: number;
+}
+
+/**
+ * Split a rendered snapshot into pages of at most `maxChars` characters, breaking
+ * only between lines (a single longer line is cut). When anything is left out,
+ * the returned text ends with a line saying what was omitted and how to continue.
+ */
+export function paginateSnapshot(text: string, maxChars: number, page = 1): SnapshotPage {
+  const lines = text.split('\n').map((line) => line.slice(0, maxChars));
+  const pages: { from: number; to: number; chars: number }[] = [];
+  let from = 0;
+  let chars = 0;
+  lines.forEach((line, i) => {
+    // Count the newline before every line but the first on a page
+    const extra = line.length + (i > from ? 1 : 0);
+    if (i > from && chars + extra > maxChars) {
+      pages.push({ from, to: i, chars });
+      from = i;
+      chars = line.length;
+    } else {
+      chars += extra;
+    }
+  });
+  pages.push({ from, to: lines.length, chars });
+
+  if (page > pages.length) {
+    throw new Error(`Page ${page} is out of range: the snapshot has ${pages.length} page(s)`);
+  }
+
+  const current = pages[page - 1];
+  const body = lines.slice(current.from, current.to).join('\n');
+  const result: SnapshotPage = {
+    text: body,
+    page,
+    pages: pages.length,
+    fromLine: current.from + 1,
+    toLine: current.to,
+    totalLines: lines.length,
+    totalChars: text.length,
+    omittedChars: text.length - body.length,
+  };
+  if (result.omittedChars > 0) {
+    let marker =
+      `# Page ${page} of ${pages.length}: lines ${result.fromLine}-${result.toLine} ` +
+      `of ${result.totalLines}, ${result.omittedChars} of ${result.totalChars} chars omitted`;
+    if (page < pages.length) marker += `. Continue with --page ${page + 1}`;
+    result.text = `${body}\n${marker}`;
+  }
+  return result;
+}
+
+export interface SnapshotChange {
+  type: 'added' | 'removed' | 'changed';
+  /** Line from the new snapshot (the old one for removals), without the list marker */
+  line: string;
+  /** Previous version of a changed line */
+  before?: string;
+}
+
+// Refs and nth indexes are renumbered on every snapshot, so ignore them when comparing
+function normalizeLine(line: string): string {
+  return line.replace(/ \[(?:ref=e\d+|nth=\d+)\]/g, '');
+}
+
+// Changes are reported without the tree's indentation and list marker
+function bareLine(line: string): string {
+  return line.trim().replace(/^-\s*/, '');
+}
+
+// Lines for the same element (depth, role and name) pair up as changes
+function lineKey(line: string): string {
+  const match = line.match(/^(\s*-\s*)(\w+)(?:\s+"((?:[^"\\]|\\.)*)")?/);
+  return match ? `${match[1]}${match[2]} ${match[3] ?? ''}` : line;
+}
+
+// Longest common subsequence tables are quadratic; beyond this, compare as sets
+const MAX_DIFF_CELLS = 4_000_000;
+
+function matchLines(before: string[], after: string[]): [number, number][] {
+  const n = before.length;
+  const m = after.length;
+  if (n * m > MAX_DIFF_CELLS) {
+    const remaining = new Map<string, number[]>();
+    after.forEach((line, j) => {
+      const indexes = remaining.get(line);
+      if (indexes) indexes.push(j);
+      else remaining.set(line, [j]);
+    });
+    const pairs: [number, number][] = [];
+    let last = -1;
+    before.forEach((line, i) => {
+      const j = remaining.get(line)?.find((k) => k > last);
+      if (j !== undefined) {
+        pairs.push([i, j]);
+        last = j;
+      }
+    });
+    return pairs;
+  }
+
+  const lengths = new Uint32Array((n + 1) * (m + 1));
+  for (let i = n - 1; i >= 0; i--) {
+    for (let j = m - 1; j >= 0; j--) {
+      lengths[i * (m + 1) + j] =
+        before[i] === after[j]
+          ? lengths[(i + 1) * (m + 1) + j + 1] + 1
+          : Math.max(lengths[(i + 1) * (m + 1) + j], lengths[i * (m + 1) + j + 1]);
+    }
+  }
+  const pairs: [number, number][] = [];
+  let i = 0;
+  let j = 0;
+  while (i < n && j < m) {
+    if (before[i] === after[j]) {
+      pairs.push([i++, j++]);
+    } else if (lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1]) {
+      i++;
+    } else {
+      j++;
+    }
+  }
+  return pairs;
+}
+
+/**
+ * Compare two snapshot trees line by line, ignoring ref numbering. Returns the
+ * added, removed and changed lines in document order. Added and changed lines
+ * carry the refs of the new snapshot; lines from the old one have theirs removed.
+ */
+export function diffSnapshots(previous: string, current: string): SnapshotChange[] {
+  const beforeLines = previous ? previous.split('\n') : [];
+  const afterLines = current ? current.split('\n') : [];
+  const before = beforeLines.map(normalizeLine);
+  const after = afterLines.map(normalizeLine);
+
+  const changes: SnapshotChange[] = [];
+  let i = 0;
+  let j = 0;
+  for (const [nextI, nextJ] of [...matchLines(before, after), [before.length, after.length]]) {
+    // Everything between two matched lines is one hunk
+    const removed = beforeLines.slice(i, nextI);
+    const added = afterLines.slice(j, nextJ);
+    const usedRemovals = new Set<number>();
+    for (const line of added) {
+      const index = removed.findIndex(
+        (old, k) => !usedRemovals.has(k) && lineKey(old) === lineKey(line)
+      );
+      if (index >= 0) {
+        usedRemovals.add(index);
+        if (normalizeLine(removed[index]) !== normalizeLine(line)) {
+          const previousLine = bareLine(normalizeLine(removed[index]));
+          changes.push({ type: 'changed', line: bareLine(line), before: previousLine });
+        }
+      } else {
+        changes.push({ type: 'added', line: bareLine(line) });
+      }
+    }
+    removed.forEach((line, k) => {
+      if (!usedRemovals.has(k)) {
+        changes.push({ type: 'removed', line: bareLine(normalizeLine(line)) });
+      }
+    });
+    i = nextI + 1;
+    j = nextJ + 1;
+  }
+  return changes;
+}
+
+/**
+ * A short content hash of a snapshot tree, for `snapshot --if-changed`. Refs are
+ * part of the tree, so a page that renumbers its elements hashes differently.
+ */
+export function snapshotHash(tree: string): string {
+  return createHash('sha256').update(tree).digest('hex').slice(0, 16);
+}
+
+export interface RefMatch {
+  ref: string;
+  role: string;
+  name: string;
+  score: number;
+}
+
+/**
+ * Score how well an accessible name matches a free-text query, from 0 (no match)
+ * to 100 (exact). Case and whitespace are ignored; a name that contains every
+ * query word, or the query's letters in order, still matches with a lower score.
+ */
+export function fuzzyScore(query: string, name: string): number {
+  const normalize = (s: string) => s.toLowerCase().replace(/\s+/g, ' ').trim();
+  const q = normalize(query);
+  const n = normalize(name);
+  if (!q || !n) return 0;
+
+  if (n === q) return 100;
+  if (n.startsWith(q)) return 90;
+  if (n.includes(q)) return 80;
+  if (q.split(' ').every((word) => n.includes(word))) return 60;
+
+  // Letters in order, e.g. "sgn in" matches "Sign in"
+  const letters = q.replace(/ /g, '');
+  let i = 0;
+  for (const c of n) {
+    if (c === letters[i]) i++;
+  }
+  return i === letters.length ? 30 : 0;
+}
+
+/**
+ * Rank refs by how well their name matches `query`. Better scores come first, then
+ * shorter names; otherwise refs keep their snapshot order.
+ */
+export function findRefs(
+  refs: RefMap,
+  query: string,
+  options: { role?: string; limit?: number } = {}
+): RefMatch[] {
+  const role = options.role?.toLowerCase();
+  const matches: RefMatch[] = [];
+  for (const [ref, data] of Object.entries(refs)) {
+    if (!data.name || (role && data.role !== role)) continue;
+    const score = fuzzyScore(query, data.name);
+    if (score > 0) {
+      matches.push({ ref, role: data.role, name: data.name, score });
+    }
+  }
+  matches.sort((a, b) => b.score - a.score || a.name.length - b.name.length);
+  return matches.slice(0, options.limit ?? 10);
+}
+
+export interface RefEntry {
+  ref: string;
+  role: string;
+  name?: string;
+  nth?: number;
+}
+
+/**
+ * List cached refs in snapshot order, optionally keeping only those whose role or
+ * name contains `filter` (case-insensitive).
+ */
+export function listRefs(refs: RefMap, filter?: string): RefEntry[] {
+  const needle = filter?.toLowerCase();
+  return Object.entries(refs)
+    .filter(
+      ([, data]) =>
+        !needle ||
+        data.role.toLowerCase().includes(needle) ||
+        (data.name ?? '').toLowerCase().includes(needle)
+    )
+    .sort(([a], [b]) => Number(a.slice(1)) - Number(b.slice(1)))
+    .map(([ref, data]) => ({
+      ref,
+      role: data.role,
+      ...(data.name && { name: data.name }),
+      ...(data.nth !== undefined && { nth: data.nth }),
+    }));
+}
+
+export interface VisibleElement extends RefEntry {
+  box: ElementBox;
+}
+
+/**
+ * The refs whose elements are at least partly inside the viewport, in snapshot
+ * order, optionally only those with one of `roles` (case-insensitive).
+ */
+export function visibleElements(
+  refs: RefMap,
+  geometry: Record<string, RefGeometry | null>,
+  roles?: string[]
+): VisibleElement[] {
+  const wanted = roles && new Set(roles.map((role) => role.toLowerCase()));
+  return listRefs(refs).flatMap((entry) => {
+    const element = geometry[entry.ref];
+    if (!element?.inViewport || (wanted && !wanted.has(entry.role))) return [];
+    return [{ ...entry, box: element.box }];
+  });
+}
+
 /**
  * Parse a ref from command argument (e.g., "@e1" -> "e1")
  */
@@ -0,0 +1,20 @@
+import { describe, it, expect } from 'bun:test';
+import { isSpaIdle } from './spa.js';
+
+describe('isSpaIdle', () => {
+  const quietPage = { sinceChange: 800, routeChanges: 1 };
+  const quietNetwork = { inflight: 0, sinceChange: 600 };
+
+  it('should settle once the page and network are quiet for long enough', () => {
+    expect(isSpaIdle(quietPage, quietNetwork, 500)).toBe(true);
+    expect(isSpaIdle(quietPage, quietNetwork, 700)).toBe(false);
+  });
+
+  it('should wait for requests in flight', () => {
+    expect(isSpaIdle(quietPage, { inflight: 1, sinceChange: 5000 }, 500)).toBe(false);
+  });
+
+  it('should wait for the DOM to stop changing', () => {
+    expect(isSpaIdle({ sinceChange: 40, routeChanges: 2 }, quietNetwork, 500)).toBe(false);
+  });
+});
@@ -0,0 +1,111 @@
+/**
+ * Waiting for a client-side route change to settle.
+ *
+ * A single-page app changes route with history.pushState and renders the new
+ * view itself, so there's no load event to wait for, and `networkidle` only
+ * covers the first load. `wait --spa-idle` instead waits until the page has
+ * been quiet for a while: no route change and no DOM change in the page, and
+ * no request in flight. WebSockets and event streams stay open by design and
+ * don't count.
+ *
+ * The page side patches the history methods and observes the document, and
+ * reports how long it has been since either last changed. It installs itself
+ * on first use, again after a full navigation, and is otherwise left in place.
+ *
+ * Usage:
+ *   agent-browser click @e3 && agent-browser wait --spa-idle
+ *   agent-browser wait --spa-idle --quiet 1000 --timeout 10000
+ */
+
+import type { Page, Request } from 'playwright-core';
+
+/** How long everything has to stay quiet when no --quiet is given */
+export const DEFAULT_SPA_QUIET_MS = 500;
+
+/** Requests that are open for as long as the page is */
+const LONG_LIVED = new Set(['websocket', 'eventsource']);
+
+/** What the page reports on each poll */
+export interface SpaActivity {
+  /** Milliseconds since the last route or DOM change, or since installing */
+  sinceChange: number;
+  /** pushState, replaceState, popstate and hashchange events seen */
+  routeChanges: number;
+}
+
+// Installs the history patch and observer unless they're already there, then
+// reports. A string so it reaches the page untranspiled.
+export const SPA_ACTIVITY_SCRIPT = `(() => {
+  let spa = window.__agentBrowserSpa;
+  if (!spa) {
+    spa = window.__agentBrowserSpa = { changedAt: performance.now(), routeChanges: 0 };
+    const route = () => {
+      spa.changedAt = performance.now();
+      spa.routeChanges++;
+    };
+    for (const method of ['pushState', 'replaceState']) {
+      const original = history[method];
+      history[method] = function (...args) {
+        const result = original.apply(this, args);
+        route();
+        return result;
+      };
+    }
+    window.addEventListener('popstate', route);
+    window.addEventListener('hashchange', route);
+    new MutationObserver(() => {
+      spa.changedAt = performance.now();
+    }).observe(document.documentElement, {
+      childList: true,
+      subtree: true,
+      attributes: true,
+      characterData: true,
+    });
+  }
+  return {
+    sinceChange: performance.now() - spa.changedAt,
+    routeChanges: spa.routeChanges,
+  };
+})()`;
+
+/**
+ * Counts the page's requests in flight from now on, and when that count last
+ * changed. `stop` removes the listeners.
+ */
+export function trackRequests(page: Page): {
+  inflight: () => number;
+  sinceChange: () => number;
+  stop: () => void;
+} {
+  const open = new Set<Request>();
+  let changedAt = Date.now();
+  const started = (request: Request) => {
+    if (LONG_LIVED.has(request.resourceType())) return;
+    open.add(request);
+    changedAt = Date.now();
+  };
+  const ended = (request: Request) => {
+    if (open.delete(request)) changedAt = Date.now();
+  };
+  page.on('request', started);
+  page.on('requestfinished', ended);
+  page.on('requestfailed', ended);
+  return {
+    inflight: () => open.size,
+    sinceChange: () => Date.now() - changedAt,
+    stop: () => {
+      page.off('request', started);
+      page.off('requestfinished', ended);
+      page.off('requestfailed', ended);
+    },
+  };
+}
+
+/** Whether the page and its network have both been quiet for `quiet` ms */
+export function isSpaIdle(
+  activity: SpaActivity,
+  network: { inflight: number; sinceChange: number },
+  quiet: number
+): boolean {
+  return network.inflight === 0 && network.sinceChange >= quiet && activity.sinceChange >= quiet;
+}
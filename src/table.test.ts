@@ -0,0 +1,37 @@
+import { describe, it, expect } from 'bun:test';
+import { tableToCsv, tableToJson } from './table.js';
+
+describe('tableToCsv', () => {
+  it('should put the header row first', () => {
+    const csv = tableToCsv({
+      headers: ['Name', 'Price'],
+      rows: [
+        ['Apple', '1.20'],
+        ['Pear', '0.90'],
+      ],
+    });
+    expect(csv).toBe('Name,Price\nApple,1.20\nPear,0.90');
+  });
+
+  it('should quote fields with commas, quotes and newlines', () => {
+    const csv = tableToCsv({ headers: null, rows: [['a,b', 'say "hi"', 'two\nlines', 'plain']] });
+    expect(csv).toBe('"a,b","say ""hi""","two\nlines",plain');
+  });
+});
+
+describe('tableToJson', () => {
+  it('should key rows by header', () => {
+    const json = tableToJson({ headers: ['Name', 'Price'], rows: [['Apple', '1.20']] });
+    expect(JSON.parse(json)).toEqual([{ Name: 'Apple', Price: '1.20' }]);
+  });
+
+  it('should make empty and repeated headers unique', () => {
+    const json = tableToJson({ headers: ['Price', '', 'Price'], rows: [['1', '2', '3']] });
+    expect(JSON.parse(json)).toEqual([{ Price: '1', 'Column 2': '2', 'Price (2)': '3' }]);
+  });
+
+  it('should return row arrays without headers', () => {
+    const json = tableToJson({ headers: null, rows: [['a', 'b']] });
+    expect(JSON.parse(json)).toEqual([['a', 'b']]);
+  });
+});
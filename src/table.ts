@@ -0,0 +1,134 @@
+/**
+ * HTML table extraction to CSV or JSON.
+ *
+ * Cells are laid out on a grid so colspan/rowspan values repeat into every
+ * column and row they cover. Header rows come from <thead>, or from leading
+ * rows made only of header cells. ARIA grids (role=row / cell / columnheader)
+ * are read the same way.
+ *
+ * Usage:
+ *   agent-browser table "#prices"
+ *   agent-browser table @e4 --format json
+ */
+
+import type { Locator } from 'playwright-core';
+
+export type TableFormat = 'csv' | 'json';
+
+export interface TableData {
+  /** One label per column, or null when the table has no header rows */
+  headers: string[] | null;
+  rows: string[][];
+}
+
+/**
+ * Read the table a locator points at, or the first table inside it.
+ */
+export async function extractTable(locator: Locator): Promise<TableData> {
+  // Use a string function body to avoid TypeScript transpilation issues
+  const scriptBody = `(el) => {
+    const CELL_ROLES = '[role="cell"], [role="gridcell"], [role="columnheader"], [role="rowheader"]';
+    const clean = (text) => (text || '').replace(/\\s+/g, ' ').trim();
+
+    const table =
+      el.tagName === 'TABLE' || el.getAttribute('role') === 'table' || el.getAttribute('role') === 'grid'
+        ? el
+        : el.querySelector('table, [role="table"], [role="grid"]');
+    if (!table) throw new Error('No table found in element');
+
+    const native = table.tagName === 'TABLE';
+    const rows = native ? Array.from(table.rows) : Array.from(table.querySelectorAll('[role="row"]'));
+    const cellsOf = (row) =>
+      native ? Array.from(row.cells) : Array.from(row.querySelectorAll(CELL_ROLES));
+    const isHeaderCell = (cell) =>
+      cell.tagName === 'TH' || cell.getAttribute('role') === 'columnheader';
+
+    const grid = rows.map(() => []);
+    rows.forEach((row, r) => {
+      let c = 0;
+      for (const cell of cellsOf(row)) {
+        while (grid[r][c] !== undefined) c++;
+        const colspan = Math.min(Math.max(Number(cell.getAttribute('colspan')) || 1, 1), 1000);
+        const span = Number(cell.getAttribute('rowspan'));
+        // rowspan=0 spans the rest of the table
+        const rowspan = span === 0 && cell.hasAttribute('rowspan') ? rows.length - r : Math.max(span || 1, 1);
+        const text = clean(cell.innerText || cell.textContent);
+        for (let dr = 0; dr < rowspan && r + dr < rows.length; dr++) {
+          for (let dc = 0; dc < colspan; dc++) grid[r + dr][c + dc] = text;
+        }
+        c += colspan;
+      }
+    });
+
+    let headerCount = 0;
+    if (native && table.tHead) {
+      headerCount = table.tHead.rows.length;
+    } else {
+      while (
+        headerCount < rows.length &&
+        cellsOf(rows[headerCount]).length > 0 &&
+        cellsOf(rows[headerCount]).every(isHeaderCell)
+      ) {
+        headerCount++;
+      }
+    }
+
+    const width = Math.max(0, ...grid.map((cells) => cells.length));
+    const full = grid.map((cells) => Array.from({ length: width }, (_, i) => cells[i] ?? ''));
+
+    // Stacked header rows combine into one label per column
+    let headers = null;
+    if (headerCount > 0) {
+      headers = Array.from({ length: width }, (_, i) => {
+        const parts = [];
+        for (const cells of full.slice(0, headerCount)) {
+          if (cells[i] && !parts.includes(cells[i])) parts.push(cells[i]);
+        }
+        return parts.join(' / ');
+      });
+    }
+
+    return {
+      headers,
+      rows: full.slice(headerCount).filter((cells) => cells.some((cell) => cell !== '')),
+    };
+  }`;
+
+  // eslint-disable-next-line @typescript-eslint/no-implied-eval
+  const fn = new Function('return ' + scriptBody)();
+  return locator.evaluate(fn);
+}
+
+function csvField(value: string): string {
+  return /[",\n\r]|^\s|\s$/.test(value) ? `"${value.replace(/"/g, '""')}"` : value;
+}
+
+/**
+ * Render a table as CSV (RFC 4180 quoting), with the header row first if there is one.
+ */
+export function tableToCsv(table: TableData): string {
+  const lines = table.headers ? [table.headers, ...table.rows] : table.rows;
+  return lines.map((cells) => cells.map(csvField).join(',')).join('\n');
+}
+
+/**
+ * Render a table as JSON: an array of objects keyed by header when the table has
+ * headers, otherwise an array of row arrays. Empty and repeated headers get
+ * unique keys ("Column 3", "Price (2)").
+ */
+export function tableToJson(table: TableData): string {
+  if (!table.headers) {
+    return JSON.stringify(table.rows, null, 2);
+  }
+  const seen = new Map<string, number>();
+  const keys = table.headers.map((header, i) => {
+    const base = header || `Column ${i + 1}`;
+    const count = (seen.get(base) ?? 0) + 1;
+    seen.set(base, count);
+    return count > 1 ? `${base} (${count})` : base;
+  });
+  const records = table.rows.map((cells) =>
+    Object.fromEntries(keys.map((key, i) => [key, cells[i] ?? '']))
+  );
+  return JSON.stringify(records, null, 2);
+}
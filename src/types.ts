@@ -1,11 +1,30 @@
 import type { Page, Browser, BrowserContext } from 'playwright-core';
+import type { SnapshotNode } from './snapshot.js';
+import type { TableFormat } from './table.js';
+import type { Impact } from './audit.js';
 
 // Base command structure
 export interface BaseCommand {
   id: string;
   action: string;
+  // Unix time in ms by which the command must finish (set by --timeout)
+  deadline?: number;
+  // How to frame the response, see encodeResponse
+  framing?: Framing;
+  // Compress large length-framed responses
+  compression?: Compression;
+  // Must match AGENT_BROWSER_TOKEN when the daemon was started with one
+  token?: string;
+  // Report the time spent on the command in the response's timings (--timings)
+  timings?: boolean;
+  // Times to retry when the element is detached or not stable (--retry)
+  retry?: number;
 }
 
+export type Framing = 'line' | 'length';
+
+export type Compression = 'gzip';
+
 // Action-specific command types
 export interface LaunchCommand extends BaseCommand {
   action: 'launch';
@@ -35,11 +54,21 @@ export interface LaunchCommand extends BaseCommand {
 export interface NavigateCommand extends BaseCommand {
   action: 'navigate';
   url: string;
-  waitUntil?: 'load' | 'domcontentloaded' | 'networkidle';
+  waitUntil?: 'load' | 'domcontentloaded' | 'networkidle' | 'commit';
+  referer?: string;
+  timeout?: number;
   headers?: Record<string, string>;
+  // Open in a new tab or window (a new context) instead of the current page
+  target?: 'tab' | 'window';
+}
+
+// `--nth <n>` (0 is the first match, -1 the last) or `--all` matches of a selector
+export interface MatchPick {
+  index?: number;
+  all?: boolean;
 }
 
-export interface ClickCommand extends BaseCommand {
+export interface ClickCommand extends BaseCommand, MatchPick {
   action: 'click';
   selector: string;
   button?: 'left' | 'right' | 'middle';
@@ -59,9 +88,16 @@ export interface FillCommand extends BaseCommand {
   action: 'fill';
   selector: string;
   value: string;
+  secret?: boolean; // never echo the value back
 }
 
-export interface CheckCommand extends BaseCommand {
+// `fill --form`: fill several fields in one request
+export interface FillFormCommand extends BaseCommand {
+  action: 'fillform';
+  fields: Array<{ field: string; value: string | number | boolean }>;
+}
+
+export interface CheckCommand extends BaseCommand, MatchPick {
   action: 'check';
   selector: string;
 }
@@ -87,10 +123,16 @@ export interface FocusCommand extends BaseCommand {
   selector: string;
 }
 
+export interface BlurCommand extends BaseCommand {
+  action: 'blur';
+  selector: string;
+}
+
 export interface DragCommand extends BaseCommand {
   action: 'drag';
   source: string;
   target: string;
+  steps?: number; // Intermediate mouse moves; uses a native drag when omitted
 }
 
 export interface FrameCommand extends BaseCommand {
@@ -98,12 +140,37 @@ export interface FrameCommand extends BaseCommand {
   selector?: string;
   name?: string;
   url?: string;
+  index?: number; // Position in frame_list output; 0 is the main frame
 }
 
 export interface MainFrameCommand extends BaseCommand {
   action: 'mainframe';
 }
 
+export interface FrameListCommand extends BaseCommand {
+  action: 'frame_list';
+}
+
+// Fuzzy-match elements in a fresh snapshot by accessible name
+export interface FindCommand extends BaseCommand {
+  action: 'find';
+  query: string;
+  role?: string;
+  limit?: number;
+}
+
+// List refs cached from the last snapshot without re-snapshotting
+export interface RefsCommand extends BaseCommand {
+  action: 'refs';
+  filter?: string;
+}
+
+// Interactive elements currently inside the viewport, with their boxes
+export interface VisibleElementsCommand extends BaseCommand {
+  action: 'visible_elements';
+  roles?: string[];
+}
+
 export interface GetByRoleCommand extends BaseCommand {
   action: 'getbyrole';
   role: string;
@@ -119,6 +186,15 @@ export interface GetByTextCommand extends BaseCommand {
   subaction: 'click' | 'hover';
 }
 
+// `click --text`: a control by accessible name, or else an element by visible text
+export interface GetByNameCommand extends BaseCommand {
+  action: 'getbyname';
+  text: string;
+  exact?: boolean;
+  index?: number;
+  subaction: 'click' | 'hover';
+}
+
 export interface GetByLabelCommand extends BaseCommand {
   action: 'getbylabel';
   label: string;
@@ -136,6 +212,7 @@ export interface GetByPlaceholderCommand extends BaseCommand {
 export interface CookiesGetCommand extends BaseCommand {
   action: 'cookies_get';
   urls?: string[];
+  name?: string;
 }
 
 export interface CookiesSetCommand extends BaseCommand {
@@ -157,6 +234,16 @@ export interface CookiesClearCommand extends BaseCommand {
   action: 'cookies_clear';
 }
 
+export interface CookiesExportCommand extends BaseCommand {
+  action: 'cookies_export';
+  path: string;
+}
+
+export interface CookiesImportCommand extends BaseCommand {
+  action: 'cookies_import';
+  path: string;
+}
+
 export interface StorageGetCommand extends BaseCommand {
   action: 'storage_get';
   key?: string;
@@ -170,6 +257,12 @@ export interface StorageSetCommand extends BaseCommand {
   type: 'local' | 'session';
 }
 
+export interface StorageRemoveCommand extends BaseCommand {
+  action: 'storage_remove';
+  key: string;
+  type: 'local' | 'session';
+}
+
 export interface StorageClearCommand extends BaseCommand {
   action: 'storage_clear';
   type: 'local' | 'session';
@@ -181,9 +274,16 @@ export interface DialogCommand extends BaseCommand {
   promptText?: string;
 }
 
+// Policy for dialogs that open while no one-shot response is armed
+export interface DialogAutoCommand extends BaseCommand {
+  action: 'dialog_auto';
+  response: 'accept' | 'dismiss' | 'off';
+  promptText?: string;
+}
+
 export interface PdfCommand extends BaseCommand {
   action: 'pdf';
-  path: string;
+  path?: string;
   format?:
     | 'Letter'
     | 'Legal'
@@ -196,6 +296,15 @@ export interface PdfCommand extends BaseCommand {
     | 'A4'
     | 'A5'
     | 'A6';
+  landscape?: boolean;
+  margin?: { top?: string; right?: string; bottom?: string; left?: string };
+}
+
+// The rendered page as an MHTML file (path) or as HTML plus resources (htmlDir); Chromium only
+export interface SaveCommand extends BaseCommand {
+  action: 'save';
+  path?: string;
+  htmlDir?: string;
 }
 
 // Network interception
@@ -211,6 +320,46 @@ export interface RouteCommand extends BaseCommand {
   abort?: boolean;
 }
 
+// Mocked responses (context-wide, addressable by id)
+export interface MockAddCommand extends BaseCommand {
+  action: 'mock_add';
+  url: string;
+  status?: number;
+  body?: string; // base64
+  contentType?: string;
+  headers?: Record<string, string>;
+}
+
+export interface MockListCommand extends BaseCommand {
+  action: 'mock_list';
+}
+
+export interface MockRemoveCommand extends BaseCommand {
+  action: 'mock_remove';
+  mockId: number;
+}
+
+// HTTP request made from the page, with its cookies and origin
+export interface FetchCommand extends BaseCommand {
+  action: 'fetch';
+  url: string;
+  method?: string;
+  headers?: Record<string, string>;
+  body?: string; // base64
+  path?: string; // save the response body here
+}
+
+// Resource blocking
+export interface BlockCommand extends BaseCommand {
+  action: 'block';
+  patterns: string[];
+}
+
+export interface UnblockCommand extends BaseCommand {
+  action: 'unblock';
+  patterns?: string[]; // If not provided, unblock everything
+}
+
 export interface UnrouteCommand extends BaseCommand {
   action: 'unroute';
   url?: string; // If not provided, remove all routes
@@ -223,6 +372,30 @@ export interface RequestsCommand extends BaseCommand {
   clear?: boolean;
 }
 
+// Network log (recorded for every page)
+export interface NetworkLogCommand extends BaseCommand {
+  action: 'network_log';
+  filter?: string; // URL glob or substring
+  since?: number; // Only entries with an id greater than this
+}
+
+// Keep request and response bodies of requests matching a URL glob
+export interface NetworkCaptureCommand extends BaseCommand {
+  action: 'network_capture';
+  url: string; // URL glob or substring
+  limit?: number; // Requests to capture, then the capture stops (default 20)
+}
+
+export interface NetworkCaptureStopCommand extends BaseCommand {
+  action: 'network_capture_stop';
+}
+
+// A captured request and response, by network log id
+export interface NetworkShowCommand extends BaseCommand {
+  action: 'network_show';
+  requestId: number;
+}
+
 // Download handling
 export interface DownloadCommand extends BaseCommand {
   action: 'download';
@@ -243,6 +416,8 @@ export interface PermissionsCommand extends BaseCommand {
   action: 'permissions';
   permissions: string[];
   grant: boolean;
+  /** Origin to grant them to, e.g. `https://example.com`; every origin when unset */
+  origin?: string;
 }
 
 // Viewport
@@ -250,6 +425,7 @@ export interface ViewportCommand extends BaseCommand {
   action: 'viewport';
   width: number;
   height: number;
+  deviceScaleFactor?: number;
 }
 
 // User agent
@@ -280,6 +456,8 @@ export interface ReloadCommand extends BaseCommand {
 // Get URL/Title
 export interface UrlCommand extends BaseCommand {
   action: 'url';
+  // Also return the URL's parts (get url --parsed)
+  parsed?: boolean;
 }
 
 export interface TitleCommand extends BaseCommand {
@@ -293,11 +471,24 @@ export interface GetAttributeCommand extends BaseCommand {
   attribute: string;
 }
 
-export interface GetTextCommand extends BaseCommand {
+// Computed values of CSS properties on one element
+export interface ComputedStyleCommand extends BaseCommand {
+  action: 'computedstyle';
+  selector: string;
+  properties: string[];
+}
+
+export interface GetTextCommand extends BaseCommand, MatchPick {
   action: 'gettext';
   selector: string;
 }
 
+// Main content as Markdown, from the whole page or one element
+export interface ArticleCommand extends BaseCommand {
+  action: 'article';
+  selector?: string;
+}
+
 export interface IsVisibleCommand extends BaseCommand {
   action: 'isvisible';
   selector: string;
@@ -330,6 +521,36 @@ export interface StylesCommand extends BaseCommand {
   selector: string;
 }
 
+// Anchors with resolved hrefs, deduplicated
+export interface LinksCommand extends BaseCommand {
+  action: 'links';
+  selector?: string;
+  /** Keep full URLs for same-origin links instead of shortening them to paths */
+  absolute?: boolean;
+}
+
+// Forms and their fields
+export interface FormsCommand extends BaseCommand {
+  action: 'forms';
+}
+
+// HTML table as CSV or JSON
+export interface TableCommand extends BaseCommand {
+  action: 'table';
+  selector: string;
+  format?: TableFormat;
+}
+
+export interface AuditCommand extends BaseCommand {
+  action: 'audit';
+  selector?: string;
+  failOn?: Impact;
+}
+
+export interface MetricsCommand extends BaseCommand {
+  action: 'metrics';
+}
+
 // More semantic locators
 export interface GetByAltTextCommand extends BaseCommand {
   action: 'getbyalttext';
@@ -393,6 +614,11 @@ export interface LocaleCommand extends BaseCommand {
   locale: string;
 }
 
+// Undo geolocation, locale and timezone emulation
+export interface EmulateResetCommand extends BaseCommand {
+  action: 'emulate_reset';
+}
+
 // HTTP basic auth
 export interface HttpCredentialsCommand extends BaseCommand {
   action: 'credentials';
@@ -407,6 +633,14 @@ export interface MouseMoveCommand extends BaseCommand {
   y: number;
 }
 
+export interface MouseClickCommand extends BaseCommand {
+  action: 'mouseclick';
+  x: number;
+  y: number;
+  button?: 'left' | 'right' | 'middle';
+  clickCount?: number;
+}
+
 export interface MouseDownCommand extends BaseCommand {
   action: 'mousedown';
   button?: 'left' | 'right' | 'middle';
@@ -472,6 +706,34 @@ export interface WaitForDownloadCommand extends BaseCommand {
   timeout?: number;
 }
 
+// Wait for a request to a URL (substring or glob) to be sent
+export interface WaitForRequestCommand extends BaseCommand {
+  action: 'waitforrequest';
+  url: string;
+  timeout?: number;
+}
+
+// Wait for a response from a URL to finish, optionally with a given status
+export interface WaitForResponseCommand extends BaseCommand {
+  action: 'waitforresponse';
+  url: string;
+  status?: number;
+  timeout?: number;
+}
+
+// Wait for a client-side route change to settle (wait --spa-idle)
+export interface WaitForSpaIdleCommand extends BaseCommand {
+  action: 'waitforspaidle';
+  quiet?: number;
+  timeout?: number;
+}
+
+// Set the directory downloads are saved to
+export interface DownloadDirCommand extends BaseCommand {
+  action: 'download_dir';
+  path: string;
+}
+
 // Get response body from intercepted request
 export interface ResponseBodyCommand extends BaseCommand {
   action: 'responsebody';
@@ -560,6 +822,13 @@ export interface RecordingRestartCommand extends BaseCommand {
   url?: string;
 }
 
+// Record the user's interactions as a script (`record start <file.ab>`)
+export interface CodegenStartCommand extends BaseCommand {
+  action: 'codegen_start';
+  path: string;
+  url?: string;
+}
+
 // Tracing
 export interface TraceStartCommand extends BaseCommand {
   action: 'trace_start';
@@ -575,11 +844,22 @@ export interface TraceStopCommand extends BaseCommand {
 // HAR recording
 export interface HarStartCommand extends BaseCommand {
   action: 'har_start';
+  path: string;
 }
 
 export interface HarStopCommand extends BaseCommand {
   action: 'har_stop';
-  path: string;
+  path?: string; // Overrides the path given to har_start
+}
+
+// JS/CSS coverage (Chromium only)
+export interface CoverageStartCommand extends BaseCommand {
+  action: 'coverage_start';
+}
+
+export interface CoverageStopCommand extends BaseCommand {
+  action: 'coverage_stop';
+  lcov?: string; // Write JS line coverage to this LCOV file
 }
 
 // Storage state (auth persistence)
@@ -597,6 +877,52 @@ export interface StorageStateLoadCommand extends BaseCommand {
 export interface ConsoleCommand extends BaseCommand {
   action: 'console';
   clear?: boolean;
+  level?: 'error' | 'warn' | 'all';
+  since?: number;
+}
+
+// Keeps the connection open and streams events as newline-delimited JSON
+export interface SubscribeCommand extends BaseCommand {
+  action: 'subscribe';
+  types?: ('console' | 'network' | 'dialog')[]; // If not provided, all event types
+}
+
+// Streams DOM changes inside an element, like subscribe streams events
+export interface WatchCommand extends BaseCommand {
+  action: 'watch';
+  selector: string;
+  events?: ('added' | 'removed' | 'text')[]; // If not provided, all changes
+  once?: boolean;
+  timeout?: number;
+}
+
+// Sent by clients when they connect; answered with HelloData
+export interface HelloCommand extends BaseCommand {
+  action: 'hello';
+  protocolVersion: number;
+}
+
+// Abort a command still running on another connection
+export interface CancelCommand extends BaseCommand {
+  action: 'cancel';
+  commandId: string;
+}
+
+// The daemon's uptime and when it will shut itself down; answered with DaemonStatusData
+export interface DaemonStatusCommand extends BaseCommand {
+  action: 'daemon_status';
+}
+
+// Change the daemon's limits, in ms (0 turns a limit off); answered with DaemonStatusData
+export interface DaemonConfigCommand extends BaseCommand {
+  action: 'daemon_config';
+  idleTimeout?: number;
+  maxLifetime?: number;
+}
+
+// Daemon health and the browser's tabs in one round trip; answered with StatusData
+export interface StatusCommand extends BaseCommand {
+  action: 'status';
 }
 
 // Page errors
@@ -605,6 +931,18 @@ export interface ErrorsCommand extends BaseCommand {
   clear?: boolean;
 }
 
+// Recent navigations, answered with { history: HistoryEntry[] }
+export interface HistoryCommand extends BaseCommand {
+  action: 'history';
+  limit?: number;
+}
+
+// Navigate to a history entry by its index
+export interface HistoryGoCommand extends BaseCommand {
+  action: 'history_go';
+  index: number;
+}
+
 // Keyboard shortcuts
 export interface KeyboardCommand extends BaseCommand {
   action: 'keyboard';
@@ -628,14 +966,16 @@ export interface TapCommand extends BaseCommand {
 // Clipboard
 export interface ClipboardCommand extends BaseCommand {
   action: 'clipboard';
-  operation: 'copy' | 'paste' | 'read';
-  text?: string;
+  operation: 'copy' | 'paste' | 'read' | 'write';
+  text?: string; // for write
 }
 
 // Highlight element (for debugging)
 export interface HighlightCommand extends BaseCommand {
   action: 'highlight';
   selector: string;
+  label?: string; // Shown above each element
+  duration?: number; // How long it stays in ms; 0 keeps it until navigation
 }
 
 // Clear input
@@ -722,10 +1062,20 @@ export interface OfflineCommand extends BaseCommand {
   offline: boolean;
 }
 
+// Network throttling: a preset, custom limits, or off
+export interface ThrottleCommand extends BaseCommand {
+  action: 'throttle';
+  profile: 'slow3g' | 'fast3g' | 'custom' | 'off';
+  downloadKbps?: number;
+  uploadKbps?: number;
+  latencyMs?: number;
+}
+
 // Set extra HTTP headers
 export interface HeadersCommand extends BaseCommand {
   action: 'headers';
   headers: Record<string, string>;
+  merge?: boolean; // Add to the current headers instead of replacing them
 }
 
 // Pause execution (for debugging)
@@ -735,8 +1085,9 @@ export interface PauseCommand extends BaseCommand {
 
 export interface PressCommand extends BaseCommand {
   action: 'press';
-  key: string;
+  key: string; // Single key or chord, e.g. Control+Shift+P
   selector?: string;
+  count?: number; // Press the key this many times
 }
 
 export interface ScreenshotCommand extends BaseCommand {
@@ -744,18 +1095,45 @@ export interface ScreenshotCommand extends BaseCommand {
   path?: string;
   fullPage?: boolean;
   selector?: string;
-  format?: 'png' | 'jpeg';
+  format?: 'png' | 'jpeg' | 'webp';
   quality?: number;
+  clip?: { x: number; y: number; width: number; height: number };
+  stdout?: boolean;
+  annotateRefs?: boolean;
 }
 
 export interface SnapshotCommand extends BaseCommand {
   action: 'snapshot';
+  format?: 'text' | 'json' | 'yaml' | 'md';
+  maxChars?: number;
+  page?: number;
+  coords?: boolean; // Add each ref's bounding box and whether it's in the viewport
+  ifChanged?: string; // Answer with just `unchanged` when the tree still has this hash
+}
+
+// Compare a fresh snapshot against the previous one
+export interface SnapshotDiffCommand extends BaseCommand {
+  action: 'snapshot_diff';
+  interactive?: boolean;
+  cursor?: boolean;
+  maxDepth?: number;
+  compact?: boolean;
+  selector?: string;
+}
+
+// Read the page a chunk at a time, from the top, from `selector`, or on from the last chunk
+export interface ReadCommand extends BaseCommand {
+  action: 'read';
+  selector?: string;
+  direction?: 'next' | 'prev';
+  maxChars?: number;
 }
 
 export interface EvaluateCommand extends BaseCommand {
   action: 'evaluate';
   script: string;
   args?: unknown[];
+  selector?: string; // matched element is passed as the first argument
 }
 
 export interface WaitCommand extends BaseCommand {
@@ -765,6 +1143,15 @@ export interface WaitCommand extends BaseCommand {
   state?: 'attached' | 'detached' | 'visible' | 'hidden';
 }
 
+// Poll a condition until it holds or the timeout expires
+export interface ExpectCommand extends BaseCommand {
+  action: 'expect';
+  kind: 'text' | 'visible' | 'url' | 'title';
+  selector?: string; // text, visible
+  expected?: string; // text, url, title: substring, URL glob, or /regex/
+  timeout?: number;
+}
+
 export interface ScrollCommand extends BaseCommand {
   action: 'scroll';
   selector?: string;
@@ -772,15 +1159,19 @@ export interface ScrollCommand extends BaseCommand {
   y?: number;
   direction?: 'up' | 'down' | 'left' | 'right';
   amount?: number;
+  position?: 'top' | 'bottom'; // Jump to the start or end of the page
 }
 
+// Exactly one of values, label or index selects the option(s)
 export interface SelectCommand extends BaseCommand {
   action: 'select';
   selector: string;
-  values: string | string[];
+  values?: string | string[];
+  label?: string | string[];
+  index?: number;
 }
 
-export interface HoverCommand extends BaseCommand {
+export interface HoverCommand extends BaseCommand, MatchPick {
   action: 'hover';
   selector: string;
 }
@@ -788,10 +1179,18 @@ export interface HoverCommand extends BaseCommand {
 export interface ContentCommand extends BaseCommand {
   action: 'content';
   selector?: string;
+  /** Include the element's own tag */
+  outer?: boolean;
+  /** Write the HTML to this file instead of returning it */
+  path?: string;
 }
 
 export interface CloseCommand extends BaseCommand {
   action: 'close';
+  /** Close the browser but leave the daemon running for the next command */
+  keepDaemon?: boolean;
+  /** Save the storage state (cookies and localStorage) to this file first */
+  saveState?: string;
 }
 
 // Tab/Window commands
@@ -819,6 +1218,26 @@ export interface WindowNewCommand extends BaseCommand {
   viewport?: { width: number; height: number };
 }
 
+// Isolated browser contexts, each with its own cookies and storage
+export interface ContextNewCommand extends BaseCommand {
+  action: 'context_new';
+  name?: string;
+}
+
+export interface ContextListCommand extends BaseCommand {
+  action: 'context_list';
+}
+
+export interface ContextSwitchCommand extends BaseCommand {
+  action: 'context_switch';
+  name: string;
+}
+
+export interface ContextCloseCommand extends BaseCommand {
+  action: 'context_close';
+  name?: string;
+}
+
 // Union of all command types
 export type Command =
   | LaunchCommand
@@ -826,23 +1245,33 @@ export type Command =
   | ClickCommand
   | TypeCommand
   | FillCommand
+  | FillFormCommand
   | CheckCommand
   | UncheckCommand
   | UploadCommand
   | DoubleClickCommand
   | FocusCommand
+  | BlurCommand
   | DragCommand
   | FrameCommand
   | MainFrameCommand
+  | FrameListCommand
+  | FindCommand
+  | RefsCommand
+  | VisibleElementsCommand
   | GetByRoleCommand
   | GetByTextCommand
+  | GetByNameCommand
   | GetByLabelCommand
   | GetByPlaceholderCommand
   | PressCommand
   | ScreenshotCommand
   | SnapshotCommand
+  | SnapshotDiffCommand
+  | ReadCommand
   | EvaluateCommand
   | WaitCommand
+  | ExpectCommand
   | ScrollCommand
   | SelectCommand
   | HoverCommand
@@ -853,17 +1282,36 @@ export type Command =
   | TabSwitchCommand
   | TabCloseCommand
   | WindowNewCommand
+  | ContextNewCommand
+  | ContextListCommand
+  | ContextSwitchCommand
+  | ContextCloseCommand
   | CookiesGetCommand
   | CookiesSetCommand
   | CookiesClearCommand
+  | CookiesExportCommand
+  | CookiesImportCommand
   | StorageGetCommand
   | StorageSetCommand
+  | StorageRemoveCommand
   | StorageClearCommand
   | DialogCommand
+  | DialogAutoCommand
   | PdfCommand
+  | SaveCommand
   | RouteCommand
   | UnrouteCommand
   | RequestsCommand
+  | MockAddCommand
+  | MockListCommand
+  | MockRemoveCommand
+  | FetchCommand
+  | BlockCommand
+  | UnblockCommand
+  | NetworkLogCommand
+  | NetworkCaptureCommand
+  | NetworkCaptureStopCommand
+  | NetworkShowCommand
   | DownloadCommand
   | GeolocationCommand
   | PermissionsCommand
@@ -876,26 +1324,45 @@ export type Command =
   | UrlCommand
   | TitleCommand
   | GetAttributeCommand
+  | ComputedStyleCommand
   | GetTextCommand
+  | ArticleCommand
   | IsVisibleCommand
   | IsEnabledCommand
   | IsCheckedCommand
   | CountCommand
   | BoundingBoxCommand
   | StylesCommand
+  | LinksCommand
+  | FormsCommand
+  | TableCommand
+  | MetricsCommand
+  | AuditCommand
   | VideoStartCommand
   | VideoStopCommand
   | RecordingStartCommand
   | RecordingStopCommand
   | RecordingRestartCommand
+  | CodegenStartCommand
   | TraceStartCommand
   | TraceStopCommand
   | HarStartCommand
   | HarStopCommand
+  | CoverageStartCommand
+  | CoverageStopCommand
   | StorageStateSaveCommand
   | StorageStateLoadCommand
   | ConsoleCommand
+  | SubscribeCommand
+  | WatchCommand
+  | HelloCommand
+  | CancelCommand
+  | DaemonStatusCommand
+  | DaemonConfigCommand
+  | StatusCommand
   | ErrorsCommand
+  | HistoryCommand
+  | HistoryGoCommand
   | KeyboardCommand
   | WheelCommand
   | TapCommand
@@ -914,6 +1381,7 @@ export type Command =
   | AddStyleCommand
   | EmulateMediaCommand
   | OfflineCommand
+  | ThrottleCommand
   | HeadersCommand
   | PauseCommand
   | GetByAltTextCommand
@@ -925,8 +1393,10 @@ export type Command =
   | SetContentCommand
   | TimezoneCommand
   | LocaleCommand
+  | EmulateResetCommand
   | HttpCredentialsCommand
   | MouseMoveCommand
+  | MouseClickCommand
   | MouseDownCommand
   | MouseUpCommand
   | BringToFrontCommand
@@ -938,6 +1408,10 @@ export type Command =
   | InsertTextCommand
   | MultiSelectCommand
   | WaitForDownloadCommand
+  | WaitForRequestCommand
+  | WaitForResponseCommand
+  | WaitForSpaIdleCommand
+  | DownloadDirCommand
   | ResponseBodyCommand
   | ScreencastStartCommand
   | ScreencastStopCommand
@@ -952,12 +1426,51 @@ export interface SuccessResponse<T = unknown> {
   id: string;
   success: true;
   data: T;
+  timings?: Timings;
+}
+
+/**
+ * Time spent on a command in ms, when the request asked for timings. The daemon
+ * reports its own share; clients add the phases they measure.
+ */
+export interface Timings {
+  daemon: number;
+}
+
+/**
+ * Failure classes clients can branch on without matching the message
+ */
+export type ErrorCode =
+  | 'invalid_command'
+  | 'unauthorized'
+  | 'not_found'
+  | 'ambiguous'
+  | 'not_visible'
+  | 'blocked'
+  | 'timeout'
+  | 'navigation'
+  | 'cancelled';
+
+/**
+ * What a failed command was working on, and the daemon's stack at the failure
+ */
+export interface ErrorContext {
+  selector?: string;
+  url?: string;
+  stack?: string;
+  /** Where the page was when a command timed out */
+  pageUrl?: string;
+  pageTitle?: string;
 }
 
 export interface ErrorResponse {
   id: string;
   success: false;
   error: string;
+  // Left out when the failure has no known class
+  errorCode?: ErrorCode;
+  errorContext?: ErrorContext;
+  timings?: Timings;
 }
 
 export type Response<T = unknown> = SuccessResponse<T> | ErrorResponse;
@@ -966,30 +1479,82 @@ export type Response<T = unknown> = SuccessResponse<T> | ErrorResponse;
 export interface NavigateData {
   url: string;
   title: string;
+  // Index of the tab a `target` navigation opened
+  tab?: number;
 }
 
 export interface ScreenshotData {
   path?: string;
   base64?: string;
+  // Refs drawn onto the image, with annotateRefs
+  annotated?: number;
 }
 
 export interface SnapshotData {
-  snapshot: string;
+  snapshot?: string;
+  /** Parsed tree for `--format json` */
+  nodes?: SnapshotNode[];
+  /** Content hash of the tree, for `ifChanged` */
+  hash?: string;
+  /** With `ifChanged`: whether the tree still has that hash (then it is left out) */
+  unchanged?: boolean;
 }
 
 export interface EvaluateData {
   result: unknown;
 }
 
+export interface HelloData {
+  protocolVersion: number;
+  /** agent-browser version of the daemon */
+  version: string;
+  /** Every action the daemon accepts */
+  actions: string[];
+  /** Response compression the daemon can apply, see BaseCommand.compression */
+  compression: Compression[];
+}
+
+export interface DaemonStatusData {
+  pid: number;
+  version: string;
+  /** Unix time in ms */
+  startedAt: number;
+  uptime: number;
+  /** Limits in ms, null when off */
+  idleTimeout: number | null;
+  maxLifetime: number | null;
+  /** Time in ms until each limit shuts the daemon down, null when off */
+  idleRemaining: number | null;
+  lifetimeRemaining: number | null;
+}
+
+export interface StatusData extends DaemonStatusData {
+  /** Resident memory of the daemon process in bytes */
+  memory: number;
+  launched: boolean;
+  /** null until a browser is launched, and for persistent profiles */
+  browserVersion: string | null;
+  tabs: Array<{ index: number; url: string; active: boolean }>;
+}
+
 export interface ContentData {
   html: string;
 }
 
+export interface FillFormData {
+  filled: string[];
+  // Found, but couldn't be filled
+  skipped: Array<{ field: string; reason: string }>;
+  notFound: string[];
+}
+
 export interface TabInfo {
   index: number;
   url: string;
   title: string;
   active: boolean;
+  // Name of the context the tab belongs to
+  context: string;
 }
 
 export interface TabListData {
@@ -1013,6 +1578,16 @@ export interface TabCloseData {
   remaining: number;
 }
 
+export interface ContextInfo {
+  name: string;
+  tabs: number;
+  active: boolean;
+}
+
+export interface ContextListData {
+  contexts: ContextInfo[];
+}
+
 export interface ScreencastStartData {
   started: boolean;
   format: string;
@@ -1034,6 +1609,12 @@ export interface RecordingStopData {
   error?: string;
 }
 
+// `record stop` of a script recording
+export interface CodegenStopData {
+  path: string;
+  actions: number;
+}
+
 export interface RecordingRestartData {
   started: boolean;
   path: string;
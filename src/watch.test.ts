@@ -0,0 +1,28 @@
+import { describe, it, expect } from 'bun:test';
+import { MAX_WATCH_TEXT, toWatchChange } from './watch.js';
+
+describe('toWatchChange', () => {
+  it('should accept changes reported by the page', () => {
+    expect(toWatchChange({ type: 'added', tag: 'LI', text: '  New\n  row ' })).toEqual({
+      type: 'added',
+      tag: 'li',
+      text: 'New row',
+    });
+    expect(toWatchChange({ type: 'text', text: '3 results' })).toEqual({
+      type: 'text',
+      text: '3 results',
+    });
+  });
+
+  it('should shorten long text', () => {
+    const change = toWatchChange({ type: 'text', text: 'x'.repeat(1000) });
+    expect(change?.text.length).toBe(MAX_WATCH_TEXT);
+  });
+
+  it('should reject anything else', () => {
+    expect(toWatchChange(null)).toBeNull();
+    expect(toWatchChange('added')).toBeNull();
+    expect(toWatchChange({ type: 'moved', text: '' })).toBeNull();
+    expect(toWatchChange({ type: 'added' })).toBeNull();
+  });
+});
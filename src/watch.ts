@@ -0,0 +1,89 @@
+/**
+ * Watch an element for DOM changes.
+ *
+ * A MutationObserver on the element reports elements added to or removed
+ * from it, and changes to its text, through a binding exposed on the page.
+ * The daemon streams each change to the client as an event line, the way
+ * `events --follow` does, until the client disconnects. A last `watch_end`
+ * line says why it stopped first: the first change with `once`, the timeout,
+ * or the page navigating away (which takes the observer with it).
+ *
+ * Usage:
+ *   agent-browser watch "#results"                       # Every change
+ *   agent-browser watch "#results" --event added --once  # Until a row appears
+ *   agent-browser watch @e4 --event text --timeout 10000
+ */
+
+export const WATCH_EVENTS = ['added', 'removed', 'text'] as const;
+export type WatchEvent = (typeof WATCH_EVENTS)[number];
+
+/** Longest text sent with a change */
+export const MAX_WATCH_TEXT = 200;
+
+export interface WatchChange {
+  type: WatchEvent;
+  /** Tag name of the added or removed element */
+  tag?: string;
+  /** Text of the added or removed element, or the element's new text */
+  text: string;
+}
+
+/** Why a watch stopped before the client disconnected: `change` is the first one with `once` */
+export type WatchEndReason = 'change' | 'timeout' | 'navigated';
+
+/** Name of the page binding changes are reported through */
+export const WATCH_BINDING = '__agentBrowserMutation';
+
+/**
+ * A change reported by the page, or null if it isn't one. The page can be
+ * running anything, so nothing it sends is trusted.
+ */
+export function toWatchChange(value: unknown): WatchChange | null {
+  if (typeof value !== 'object' || value === null) return null;
+  const { type, tag, text } = value as Record<string, unknown>;
+  if (!WATCH_EVENTS.includes(type as WatchEvent) || typeof text !== 'string') return null;
+  const change: WatchChange = {
+    type: type as WatchEvent,
+    text: text.replace(/\s+/g, ' ').trim().slice(0, MAX_WATCH_TEXT),
+  };
+  if (typeof tag === 'string') change.tag = tag.toLowerCase();
+  return change;
+}
+
+// Starts observing the element it's given; `window.__agentBrowserWatches[id]`
+// stops it. A string so it reaches the page untranspiled.
+const WATCH_SCRIPT = `(el, { id, events, binding }) => {
+  const report = (change) => {
+    if (typeof window[binding] === 'function') window[binding](id, change);
+  };
+  let text = el.textContent;
+  const observer = new MutationObserver((records) => {
+    const elements = (type, nodes) => {
+      if (!events.includes(type)) return;
+      for (const node of nodes) {
+        if (node.nodeType === 1) report({ type, tag: node.tagName, text: node.textContent || '' });
+      }
+    };
+    for (const record of records) {
+      elements('added', record.addedNodes);
+      elements('removed', record.removedNodes);
+    }
+    if (events.includes('text') && el.textContent !== text) {
+      text = el.textContent;
+      report({ type: 'text', text: text || '' });
+    }
+  });
+  observer.observe(el, { childList: true, subtree: true, characterData: true });
+  window.__agentBrowserWatches = window.__agentBrowserWatches || {};
+  window.__agentBrowserWatches[id] = observer;
+}`;
+
+// eslint-disable-next-line @typescript-eslint/no-implied-eval
+export const startWatchScript = new Function('return ' + WATCH_SCRIPT)() as (
+  el: unknown,
+  args: { id: string; events: readonly WatchEvent[]; binding: string }
+) => void;
+
+export function stopWatchScript(id: string): string {
+  return `window.__agentBrowserWatches?.[${JSON.stringify(id)}]?.disconnect()`;
+}